@@ -0,0 +1,319 @@
+//!
+//! @package subtle-rs
+//!
+//! @file Subtler functions
+//! @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+//! @version $Id$
+//!
+//! This program can be distributed under the terms of the GNU GPLv3.
+//! See the file LICENSE for details.
+//!
+
+/// IPC module shared with subtle, encoding is just used there
+#[path = "../ipc.rs"]
+#[allow(dead_code)]
+mod ipc;
+
+use std::process::ExitCode;
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use x11rb::connection::Connection;
+use x11rb::{CURRENT_TIME, NONE};
+use x11rb::protocol::xproto::{Atom, AtomEnum, ClientMessageEvent, ConnectionExt, EventMask, Window};
+use x11rb::rust_connection::RustConnection;
+use crate::ipc::{split_names, Atoms, ClientGeometry, STACK_FOCUS, STACK_FORCE};
+
+#[derive(Parser)]
+#[command(name = "subtler", about = "Query and control a running subtle-rs")]
+struct Cli {
+    /// Connect to DISPLAY instead of $DISPLAY
+    #[arg(short = 'd', long)]
+    display: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List clients with geometry, gravity and screen
+    Clients,
+    /// List views and their tags
+    Views,
+    /// List tags
+    Tags,
+    /// List gravities
+    Gravities,
+    /// Close client window
+    Close {
+        /// Window id of the client
+        win: Window,
+    },
+    /// Raise client window
+    Raise {
+        /// Window id of the client
+        win: Window,
+        /// Focus the window afterwards
+        #[arg(long)]
+        focus: bool,
+        /// Raise docks above fullscreen windows
+        #[arg(long)]
+        force: bool,
+    },
+    /// Lower client window
+    Lower {
+        /// Window id of the client
+        win: Window,
+        /// Lower desktop windows as well
+        #[arg(long)]
+        force: bool,
+    },
+    /// Dump state of client window to the log of subtle
+    Dump {
+        /// Window id of the client, defaults to the focus window
+        win: Option<Window>,
+    },
+    /// Jump to view
+    Jump {
+        /// Index of the view
+        view_idx: u32,
+    },
+}
+
+/// Connection and interned atoms
+struct Display {
+    conn: RustConnection,
+    atoms: Atoms,
+    root: Window,
+}
+
+impl Display {
+    /// Read cardinal or window property
+    ///
+    /// # Arguments
+    ///
+    /// * `win` - Window of the property
+    /// * `atom` - Property atom
+    /// * `kind` - Property type
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`Vec`] of values on success or otherwise [`anyhow::Error`]
+    fn get_property32(&self, win: Window, atom: Atom, kind: impl Into<Atom>) -> Result<Vec<u32>> {
+        Ok(self.conn.get_property(false, win, atom, kind, 0, u32::MAX)?
+            .reply()?
+            .value32()
+            .map(|values| values.collect())
+            .unwrap_or_default())
+    }
+
+    /// Read string property
+    ///
+    /// # Arguments
+    ///
+    /// * `win` - Window of the property
+    /// * `atom` - Property atom
+    /// * `kind` - Property type
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either raw [`Vec`] on success or otherwise [`anyhow::Error`]
+    fn get_property8(&self, win: Window, atom: Atom, kind: impl Into<Atom>) -> Result<Vec<u8>> {
+        Ok(self.conn.get_property(false, win, atom, kind, 0, u32::MAX)?.reply()?.value)
+    }
+
+    /// Send client message to the window manager
+    ///
+    /// # Arguments
+    ///
+    /// * `win` - Window the message is about
+    /// * `message_type` - Message type
+    /// * `data32` - Data to send
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    fn send_message(&self, win: Window, message_type: Atom, data32: [u32; 5]) -> Result<()> {
+        self.conn.send_event(false, self.root,
+                             EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                             ClientMessageEvent::new(32, win, message_type, data32))?.check()?;
+
+        self.conn.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Format tags bitmask as list of tag indices
+///
+/// # Arguments
+///
+/// * `tags` - Tags bitmask
+///
+/// # Returns
+///
+/// A [`String`] with the comma-separated tag indices
+fn format_tags(tags: u32) -> String {
+    (0..u32::BITS).filter(|bit| 0 != tags & (1 << bit))
+        .map(|bit| bit.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Assemble flags of a stacking request
+///
+/// # Arguments
+///
+/// * `focus` - Whether to focus the window
+/// * `force` - Whether to override the stacking levels
+///
+/// # Returns
+///
+/// The flags of the request
+fn calc_stack_flags(focus: bool, force: bool) -> u32 {
+    (if focus { STACK_FOCUS } else { 0 }) | (if force { STACK_FORCE } else { 0 })
+}
+
+/// Print managed clients
+///
+/// # Arguments
+///
+/// * `display` - Connection and atoms
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn print_clients(display: &Display) -> Result<()> {
+    let atoms = &display.atoms;
+
+    let wins = display.get_property32(display.root, atoms._NET_CLIENT_LIST, AtomEnum::WINDOW)?;
+    let geoms = ClientGeometry::decode_all(&display.get_property32(display.root,
+        atoms.SUBTLE_CLIENT_GEOMETRIES, AtomEnum::CARDINAL)?);
+
+    println!("{:<10} {:>6} {:>6} {:>6} {:>6} {:>4} {:>4} {:<10} NAME",
+             "WIN", "X", "Y", "WIDTH", "HEIGHT", "GRAV", "SCR", "TAGS");
+
+    for (win, geom) in wins.iter().zip(geoms.iter()) {
+        let mut name = display.get_property8(*win, atoms._NET_WM_NAME, atoms.UTF8_STRING)?;
+
+        if name.is_empty() {
+            name = display.get_property8(*win, atoms.WM_NAME, AtomEnum::STRING)?;
+        }
+
+        let tags = display.get_property32(*win, atoms.SUBTLE_CLIENT_TAGS, AtomEnum::CARDINAL)?;
+
+        println!("{:<#10x} {:>6} {:>6} {:>6} {:>6} {:>4} {:>4} {:<10} {}",
+                 win, geom.x, geom.y, geom.width, geom.height, geom.gravity_idx, geom.screen_idx,
+                 format_tags(tags.first().copied().unwrap_or_default()), String::from_utf8_lossy(&name));
+    }
+
+    Ok(())
+}
+
+/// Print views and mark the current one
+///
+/// # Arguments
+///
+/// * `display` - Connection and atoms
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn print_views(display: &Display) -> Result<()> {
+    let atoms = &display.atoms;
+
+    let names = split_names(&display.get_property8(display.root, atoms._NET_DESKTOP_NAMES,
+                                                   AtomEnum::STRING)?);
+    let tags = display.get_property32(display.root, atoms.SUBTLE_VIEW_TAGS, AtomEnum::CARDINAL)?;
+    let current = display.get_property32(display.root, atoms._NET_CURRENT_DESKTOP, AtomEnum::CARDINAL)?;
+
+    println!("{:<4} {:<1} {:<10} NAME", "IDX", "", "TAGS");
+
+    for (view_idx, name) in names.iter().enumerate() {
+        let marker = if current.first().is_some_and(|idx| *idx as usize == view_idx) { "*" } else { "" };
+
+        println!("{:<4} {:<1} {:<10} {}", view_idx, marker,
+                 format_tags(tags.get(view_idx).copied().unwrap_or_default()), name);
+    }
+
+    Ok(())
+}
+
+/// Print names of a list property with their index
+///
+/// # Arguments
+///
+/// * `display` - Connection and atoms
+/// * `atom` - Property atom of the list
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn print_list(display: &Display, atom: Atom) -> Result<()> {
+    println!("{:<4} NAME", "IDX");
+
+    for (idx, name) in split_names(&display.get_property8(display.root, atom, AtomEnum::STRING)?)
+        .iter().enumerate()
+    {
+        println!("{:<4} {}", idx, name);
+    }
+
+    Ok(())
+}
+
+/// Run command of the command line
+///
+/// # Arguments
+///
+/// * `cli` - Parsed command line
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn run(cli: &Cli) -> Result<()> {
+    let (conn, screen_num) = x11rb::connect(cli.display.as_deref())
+        .with_context(|| format!("Failed to connect to display `{}`", cli.display.clone()
+            .or_else(|| std::env::var("DISPLAY").ok()).unwrap_or_default()))?;
+
+    let atoms = Atoms::new(&conn)?.reply()?;
+    let root = conn.setup().roots[screen_num].root;
+
+    let display = Display { conn, atoms, root };
+
+    match cli.command {
+        Command::Clients => print_clients(&display)?,
+        Command::Views => print_views(&display)?,
+        Command::Tags => print_list(&display, display.atoms.SUBTLE_TAG_LIST)?,
+        Command::Gravities => print_list(&display, display.atoms.SUBTLE_GRAVITY_LIST)?,
+        Command::Close { win } => display.send_message(win, display.atoms._NET_CLOSE_WINDOW,
+                                                       [CURRENT_TIME, 2, 0, 0, 0])?,
+        Command::Raise { win, focus, force } => display.send_message(display.root,
+            display.atoms.SUBTLE_WINDOW_RAISE, [win, calc_stack_flags(focus, force), 0, 0, 0])?,
+        Command::Lower { win, force } => display.send_message(display.root,
+            display.atoms.SUBTLE_WINDOW_LOWER, [win, calc_stack_flags(false, force), 0, 0, 0])?,
+        Command::Dump { win } => display.send_message(display.root,
+            display.atoms.SUBTLE_WINDOW_DUMP, [win.unwrap_or(NONE), 0, 0, 0, 0])?,
+        Command::Jump { view_idx } => display.send_message(display.root, display.atoms._NET_CURRENT_DESKTOP,
+                                                           [view_idx, CURRENT_TIME, 0, 0, 0])?,
+    }
+
+    Ok(())
+}
+
+/// Main function
+///
+/// # Returns
+///
+/// The [`ExitCode`] of the process
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(&cli) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{:?}", err);
+
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -11,36 +11,88 @@
 
 use std::fmt;
 use std::cmp::{Ordering, PartialEq};
-use std::ops::{BitAnd, BitOr, BitXor};
-use x11rb::protocol::xproto::{Atom, AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent, ConfigureWindowAux, ConnectionExt, EventMask, GrabMode, InputFocus, PropMode, QueryPointerReply, Rectangle, SetMode, StackMode, Window, CLIENT_MESSAGE_EVENT};
+use x11rb::protocol::xproto::{Atom, AtomEnum, ChangeWindowAttributesAux, ConfigWindow, ConfigureNotifyEvent, ConfigureRequestEvent, ConfigureWindowAux, ConnectionExt, EventMask, GetGeometryReply, GetPropertyReply, GrabMode, Keycode, Keysym, ModMask, PropMode, QueryPointerReply, Rectangle, SetMode, StackMode, Window, CONFIGURE_NOTIFY_EVENT};
 use bitflags::bitflags;
 use anyhow::{anyhow, Context, Result};
 use easy_min_max::max;
-use log::debug;
+use log::{debug, info, warn};
 use stdext::function_name;
 use strum_macros::FromRepr;
 use x11rb::connection::Connection;
-use x11rb::{CURRENT_TIME, NONE};
+use x11rb::errors::ReplyError;
+use x11rb::protocol::ErrorKind;
+use x11rb::NONE;
 use x11rb::properties::{WmHints, WmSizeHints, WmSizeHintsSpecification};
-use x11rb::protocol::Event;
+use x11rb::protocol::{screensaver, Event};
+use x11rb::rust_connection::RustConnection;
 use x11rb::wrapper::ConnectionExt as ConnectionExtWrapper;
-use crate::{ewmh, grab, screen};
-use crate::ewmh::{EWMHStateFlags, WMState};
-use crate::grab::{DirectionOrder, GrabFlags};
+use crate::{corner, effect, ewmh, handoff, ipc, screen, session, tag};
+use crate::effect::{Effect, Prop};
+use crate::ewmh::{AllowedActions, Atoms, EWMHStateFlags, WMState};
+use crate::ipc::ClientGeometry;
+use crate::geometry::{calc_centered, calc_shrunk, checked_to_i16, checked_to_u16};
+use crate::grab::{DirectionOrder, Grab, GrabAction, GrabFlags, KeyMap};
 use crate::subtle::{Subtle, SubtleFlags};
-use crate::gravity::GravityFlags;
-use crate::screen::{Screen, ScreenFlags};
-use crate::tagging::Tagging;
+use crate::gravity::{Gravity, GravityFlags};
+use crate::handoff::HandoffState;
+use crate::screen::{calc_migrated_geom, Screen, ScreenFlags};
+use crate::tag::TagFlags;
+use crate::tagging;
+use crate::tagging::{Tagging, DEFAULT_TAG_IDX};
+use crate::view::View;
+use crate::validate::ValidViewIdx;
 
 const MIN_WIDTH: u16 = 1;
 const MIN_HEIGHT: u16 = 1;
 
+/// Keysyms of the keys of a keyboard drag
+const XK_RETURN: Keysym = 0xFF0D;
+const XK_KP_ENTER: Keysym = 0xFF8D;
+const XK_ESCAPE: Keysym = 0xFF1B;
+const XK_LEFT: Keysym = 0xFF51;
+const XK_UP: Keysym = 0xFF52;
+const XK_RIGHT: Keysym = 0xFF53;
+const XK_DOWN: Keysym = 0xFF54;
+
+/// Desktop index of windows on all desktops
+pub(crate) const ALL_DESKTOPS: u32 = 0xFFFFFFFF;
+
+/// Source indication of activation requests from applications
+pub(crate) const SOURCE_APPLICATION: u32 = 1;
+
+/// Actions of _NET_WM_STATE messages
+pub(crate) const NET_WM_STATE_REMOVE: u32 = 0;
+pub(crate) const NET_WM_STATE_ADD: u32 = 1;
+pub(crate) const NET_WM_STATE_TOGGLE: u32 = 2;
+
 macro_rules! ignore_if_dead {
     ($client:tt) => {
         if $client.flags.contains(ClientFlags::DEAD) { return Ok(()); }
     };
 }
 
+/// Reaction to an activation request
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Activation {
+    /// Focus the visible client
+    Focus,
+    /// Switch to a view of the client first
+    Jump,
+    /// Just mark the client as urgent
+    MarkUrgent,
+}
+
+/// Reaction to a stacking request of another client
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Restacking {
+    /// Refuse the request
+    Refuse,
+    /// Restack the client within its stacking level
+    Restack(RestackOrder),
+    /// Raise the client above all stacking levels
+    RaiseAbove,
+}
+
 #[repr(u8)]
 #[derive(Default, Debug, Copy, Clone, PartialEq, FromRepr)]
 pub(crate) enum RestackOrder {
@@ -50,6 +102,16 @@ pub(crate) enum RestackOrder {
     Up = 2,
 }
 
+impl From<StackMode> for RestackOrder {
+    fn from(stack_mode: StackMode) -> Self {
+        match stack_mode {
+            StackMode::ABOVE | StackMode::TOP_IF => RestackOrder::Up,
+            StackMode::BELOW | StackMode::BOTTOM_IF => RestackOrder::Down,
+            _ => RestackOrder::None,
+        }
+    }
+}
+
 #[repr(u8)]
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub(crate) enum DragMode {
@@ -118,12 +180,27 @@ bitflags! {
         /// Dialog type
         const TYPE_DIALOG = 1 << 21;
 
+        /// Ignore size increments mode
+        const MODE_IGNORE_HINTS = 1 << 22;
+
+        /// Tags inherited from the transient parent
+        const PARENT_TAGS = 1 << 23;
+
+        /// Taskbar that handles iconified clients
+        const HANDLED_ICONS = 1 << 24;
+
         /// Catch all for modes
         const ALL_MODES = Self::MODE_FULL.bits() | Self::MODE_FLOAT.bits()
             | Self::MODE_STICK.bits() | Self::MODE_STICK_SCREEN.bits()
             | Self::MODE_URGENT.bits() | Self::MODE_RESIZE.bits()
             | Self::MODE_ZAPHOD.bits() | Self::MODE_FIXED.bits()
-            | Self::MODE_CENTER.bits() | Self::MODE_BORDERLESS.bits();
+            | Self::MODE_CENTER.bits() | Self::MODE_BORDERLESS.bits()
+            | Self::MODE_IGNORE_HINTS.bits();
+
+        /// Catch all for types
+        const ALL_TYPES = Self::TYPE_NORMAL.bits() | Self::TYPE_DESKTOP.bits()
+            | Self::TYPE_DOCK.bits() | Self::TYPE_TOOLBAR.bits()
+            | Self::TYPE_SPLASH.bits() | Self::TYPE_DIALOG.bits();
     }
 }
 
@@ -134,6 +211,7 @@ pub(crate) struct Client {
 
     pub(crate) win: Window,
     pub(crate) leader: Window,
+    pub(crate) transient_for: Window,
 
     pub(crate) name: String,
     pub(crate) instance: String,
@@ -156,11 +234,116 @@ pub(crate) struct Client {
     pub(crate) gravity_idx: isize,
 
     pub(crate) geom: Rectangle,
+    pub(crate) saved_geom: Option<Rectangle>,
     pub(crate) order: RestackOrder,
 
     pub(crate) gravities: Vec<usize>,
 }
 
+/// Properties of a new client read in one batch
+#[derive(Default, Debug)]
+pub(crate) struct ClientProps {
+    pub(crate) size_hints: Option<WmSizeHints>,
+    pub(crate) wm_hints: Option<WmHints>,
+
+    pub(crate) net_wm_name: Vec<u8>,
+    pub(crate) wm_name: Vec<u8>,
+    pub(crate) wm_role: Vec<u8>,
+    pub(crate) wm_klass: Vec<u8>,
+
+    pub(crate) protocols: Vec<Atom>,
+    pub(crate) wm_types: Vec<Atom>,
+    pub(crate) states: Vec<Atom>,
+    pub(crate) motif_hints: Vec<u32>,
+    pub(crate) has_handled_icons: bool,
+
+    pub(crate) transient_for: Option<Window>,
+    pub(crate) leader: Option<Window>,
+}
+
+impl ClientProps {
+    /// Fetch all properties of a new client
+    ///
+    /// All requests are sent before the first reply is read, so the server answers them
+    /// in one go: Reading them one after another took a round-trip per property (~12),
+    /// now it's just one on top of the geometry reply.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to X11
+    /// * `atoms` - Known atoms
+    /// * `win` - Client window
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`Some`] properties, [`None`] when the window is gone or otherwise [`anyhow::Error`]
+    pub(crate) fn fetch(conn: &RustConnection, atoms: &Atoms, win: Window) -> Result<Option<Self>> {
+        let size_hints_cookie = WmSizeHints::get_normal_hints(conn, win)?;
+        let wm_hints_cookie = WmHints::get(conn, win)?;
+
+        let requests: [(Atom, Atom, u32); 11] = [
+            (atoms._NET_WM_NAME, atoms.UTF8_STRING, u32::MAX),
+            (atoms.WM_NAME, AtomEnum::ANY.into(), u32::MAX),
+            (atoms.WM_WINDOW_ROLE, AtomEnum::STRING.into(), u32::MAX),
+            (atoms.WM_CLASS, AtomEnum::STRING.into(), u32::MAX),
+            (atoms.WM_PROTOCOLS, AtomEnum::ATOM.into(), u32::MAX),
+            (atoms._NET_WM_WINDOW_TYPE, AtomEnum::ATOM.into(), 5),
+            (atoms._NET_WM_STATE, AtomEnum::ATOM.into(), 4),
+            (atoms._MOTIF_WM_HINTS, atoms._MOTIF_WM_HINTS, 1),
+            (atoms._NET_WM_HANDLED_ICONS, AtomEnum::ANY.into(), 1),
+            (AtomEnum::WM_TRANSIENT_FOR.into(), AtomEnum::WINDOW.into(), 1),
+            (atoms.WM_CLIENT_LEADER, AtomEnum::WINDOW.into(), 1),
+        ];
+
+        let cookies = requests.iter()
+            .map(|(prop, prop_type, len)| conn.get_property(false, win, *prop, *prop_type, 0, *len))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Replies arrive in order of the requests, just the first one has to wait
+        let Some(size_hints) = reply_unless_gone(size_hints_cookie.reply())? else {
+            return Ok(None);
+        };
+
+        let Some(wm_hints) = reply_unless_gone(wm_hints_cookie.reply())? else {
+            return Ok(None);
+        };
+
+        let Some(replies) = cookies.into_iter()
+            .map(|cookie| reply_unless_gone(cookie.reply()))
+            .collect::<Result<Option<Vec<_>>>>()? else {
+            return Ok(None);
+        };
+
+        let [net_wm_name, wm_name, wm_role, wm_klass, protocols, wm_types, states,
+            motif_hints, handled_icons, transient_for, leader] = <[GetPropertyReply; 11]>::try_from(replies)
+            .map_err(|_| anyhow!("Missing property replies"))?;
+
+        let props = Self {
+            size_hints,
+            wm_hints,
+
+            net_wm_name: net_wm_name.value,
+            wm_name: wm_name.value,
+            wm_role: wm_role.value,
+            wm_klass: wm_klass.value,
+
+            protocols: protocols.value32().map(|atoms| atoms.collect()).unwrap_or_default(),
+            wm_types: wm_types.value32().map(|atoms| atoms.collect()).unwrap_or_default(),
+            states: states.value32().map(|atoms| atoms.collect()).unwrap_or_default(),
+            motif_hints: motif_hints.value32().map(|hints| hints.collect()).unwrap_or_default(),
+            // EWMH: Presence of the property is the hint, the value is irrelevant
+            has_handled_icons: NONE != handled_icons.type_,
+
+            transient_for: transient_for.value32().and_then(|mut wins| wins.next()).filter(|win| NONE != *win),
+            leader: leader.value32().and_then(|mut wins| wins.next()).filter(|win| NONE != *win),
+        };
+
+        debug!("{}: win={}", function_name!(), win);
+
+        Ok(Some(props))
+    }
+}
+
 impl Client {
     /// Create a new instance
     ///
@@ -177,26 +360,14 @@ impl Client {
         let atoms = subtle.atoms.get().unwrap();
 
         conn.grab_server()?;
-        conn.change_save_set(SetMode::INSERT, win)?;
-
-        // X Properties
-        let geom_reply = conn.get_geometry(win)?.reply()?;
 
-        let aux = ChangeWindowAttributesAux::default()
-            .border_pixel(subtle.clients_style.bg as u32)
-            .event_mask(EventMask::PROPERTY_CHANGE
-                | EventMask::FOCUS_CHANGE
-                | EventMask::ENTER_WINDOW);
-
-        conn.change_window_attributes(win, &aux)?.check()?;
-
-        let aux = ConfigureWindowAux::default()
-            .border_width(subtle.clients_style.border.top as u32);
-
-        conn.configure_window(win, &aux)?.check()?;
+        // Always ungrab again, the window might be gone already
+        let maybe_geom_reply = Self::select_win(subtle, win);
 
         conn.ungrab_server()?;
 
+        let geom_reply = maybe_geom_reply?;
+
         let mut client = Self {
             flags: ClientFlags::INPUT,
             win,
@@ -210,64 +381,170 @@ impl Client {
                 width: max!(MIN_WIDTH, geom_reply.width),
                 height: max!(MIN_HEIGHT, geom_reply.height),
             },
-            gravities: Vec::with_capacity(subtle.views.len()),
+            gravities: Vec::with_capacity(subtle.views.borrow().len()),
             ..Self::default()
         };
 
         // Init gravities
         let grav = subtle.get_default_gravity();
 
-        for _i in 0..subtle.views.len() {
+        for _i in 0..subtle.views.borrow().len() {
             client.gravities.push(grav as usize);
         }
 
         // Update client
         let mut mode_flags = ClientFlags::empty();
 
+        let props = ClientProps::fetch(conn, atoms, win)?
+            .ok_or_else(|| anyhow!("Window {} is gone", win))?;
+
         //client.set_strut(subtle)?;
-        client.set_size_hints(subtle, &mut mode_flags)?;
-        client.set_wm_name(subtle)?;
+        client.update_props(subtle, &props, &mut mode_flags)?;
         client.set_wm_state(subtle, WMState::Withdrawn)?;
-        client.set_wm_protocols(subtle)?;
-        client.set_wm_type(subtle, &mut mode_flags)?;
-        client.set_wm_hints(subtle, &mut mode_flags)?;
-        client.set_motif_wm_hints(subtle, &mut mode_flags)?;
-        client.set_net_wm_state(subtle, &mut mode_flags)?;
-        client.set_transient(subtle, &mut mode_flags)?;
-        client.retag(subtle, &mut mode_flags)?;
-        client.toggle(subtle, &mut mode_flags, false)?;
+
+        client.manage(subtle, &mut mode_flags)?;
+
+        // EWMH: Allowed actions
+        effect::apply(subtle, Effect::SetAllowedActions { win: client.win,
+            actions: calc_allowed_actions(client.flags) })?;
+
+        debug!("{}: client={}", function_name!(), client);
+
+        Ok(client)
+    }
+
+    /// Select events and set border of a new client window
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `win` - Client window
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`GetGeometryReply`] on success or otherwise [`anyhow::Error`]
+    fn select_win(subtle: &Subtle, win: Window) -> Result<GetGeometryReply> {
+        let conn = subtle.conn.get().context("Failed to get connection")?;
+
+        conn.change_save_set(SetMode::INSERT, win)?;
+
+        // X Properties
+        let geom_reply = conn.get_geometry(win)?.reply()?;
+
+        let aux = ChangeWindowAttributesAux::default()
+            .event_mask(EventMask::PROPERTY_CHANGE
+                | EventMask::FOCUS_CHANGE
+                | EventMask::ENTER_WINDOW);
+
+        conn.change_window_attributes(win, &aux)?.check()?;
+
+        effect::apply(subtle, Effect::SetBorderColor { win, pixel: subtle.clients_style.bg as u32 })?;
+        effect::apply(subtle, Effect::SetBorderWidth { win, width: subtle.clients_style.border.top as u16 })?;
+
+        debug!("{}: win={}", function_name!(), win);
+
+        Ok(geom_reply)
+    }
+
+    /// Update client from properties fetched in one batch
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `props` - Fetched client properties
+    /// * `mode_flags` - Mode flags collected from the client properties
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn update_props(&mut self, subtle: &Subtle, props: &ClientProps, mode_flags: &mut ClientFlags) -> Result<()> {
+        let atoms = subtle.atoms.get().context("Failed to get atoms")?;
+
+        self.update_normal_hints(subtle, props.size_hints.as_ref(), mode_flags)?;
+        self.update_wm_name(&props.net_wm_name, &props.wm_name, &props.wm_role, &props.wm_klass);
+        self.update_wm_protocols(atoms, &props.protocols);
+        self.update_wm_type(atoms, &props.wm_types, mode_flags);
+        self.flags.set(ClientFlags::HANDLED_ICONS, props.has_handled_icons);
+        self.update_wm_hints(subtle, props.wm_hints.as_ref(), mode_flags);
+        self.update_motif_wm_hints(&props.motif_hints, mode_flags);
+        self.update_net_wm_state(atoms, &props.states, mode_flags);
+        self.update_transient(subtle, props.transient_for, mode_flags);
 
         // Set leader window
-        let leader = conn.get_property(false, client.win, atoms.WM_CLIENT_LEADER,
-                                       AtomEnum::WINDOW, 0, 1)?.reply()?.value;
+        if let Some(leader) = props.leader {
+            self.leader = leader;
+        }
+
+        debug!("{}: client={}, mode_flags={:?}", function_name!(), self, mode_flags);
+
+        Ok(())
+    }
+
+    /// Apply tagging and mode decisions to a new client after all properties have been fetched
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `mode_flags` - Mode flags collected from the client properties
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn manage(&mut self, subtle: &Subtle, mode_flags: &mut ClientFlags) -> Result<()> {
+        // Trust state of the previous instance or otherwise start from scratch
+        if let Some(state) = handoff::load(subtle, self.win)? {
+            self.adopt(subtle, &state, mode_flags)?;
+        } else {
+            self.retag(subtle, mode_flags)?;
 
-        if !leader.is_empty() && NONE != leader[0] as u32 {
-            client.leader = leader[0] as Window;
+            session::restore_gravities(subtle, self);
+            session::restore_geom(subtle, self, mode_flags);
         }
 
-        // EWMH: Gravity, screen, desktop, extents
-        let data: [u32; 1] = [client.gravity_idx as u32];
+        self.toggle(subtle, mode_flags, false, subtle.find_focus_screen_idx())?;
 
-        conn.change_property32(PropMode::REPLACE, client.win, atoms.SUBTLE_CLIENT_GRAVITY,
-            AtomEnum::CARDINAL, &data)?.check()?;
+        // EWMH: Gravity, screen, desktop (extents are set on toggle)
+        effect::apply(subtle, Effect::SetProperty { win: self.win, prop: Prop::ClientGravity,
+            data: vec![self.gravity_idx as u32] })?;
+        effect::apply(subtle, Effect::SetProperty { win: self.win, prop: Prop::ClientScreen,
+            data: vec![self.screen_idx as u32] })?;
+        effect::apply(subtle, Effect::SetProperty { win: self.win, prop: Prop::WmDesktop,
+            data: vec![0] })?;
 
-        let data: [u32; 1] = [client.screen_idx as u32];
+        debug!("{}: client={}, mode_flags={:?}", function_name!(), self, mode_flags);
 
-        conn.change_property32(PropMode::REPLACE, client.win, atoms.SUBTLE_CLIENT_SCREEN,
-                               AtomEnum::CARDINAL, &data)?.check()?;
+        Ok(())
+    }
 
-        let data: [u32; 1] = [0];
+    /// Adopt tags, gravity, screen and modes from the state of the previous instance
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `state` - Validated state of the previous instance
+    /// * `mode_flags` - Mode flags to set for this client
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn adopt(&mut self, subtle: &Subtle, state: &HandoffState, mode_flags: &mut ClientFlags) -> Result<()> {
+        self.tags = state.tags;
+        self.gravity_idx = state.gravity_idx;
+        self.screen_idx = state.screen_idx;
 
-        conn.change_property32(PropMode::REPLACE, client.win, atoms._NET_WM_DESKTOP,
-            AtomEnum::CARDINAL, &data)?.check()?;
+        for gravity_idx in self.gravities.iter_mut() {
+            *gravity_idx = state.gravity_idx as usize;
+        }
 
-        // TODO Struts
-        //conn.change_property32(PropMode::REPLACE, client.win, atoms._NET_FRAME_EXTENTS
-        //                       AtomEnum::CARDINAL, &data)?.check()?;
+        mode_flags.insert(state.mode_flags);
 
-        debug!("{}: client={}", function_name!(), client);
+        // EWMH: Tags
+        effect::apply(subtle, Effect::SetProperty { win: self.win, prop: Prop::ClientTags,
+            data: vec![self.tags.bits()] })?;
 
-        Ok(client)
+        debug!("{}: client={}, mode_flags={:?}", function_name!(), self, mode_flags);
+
+        Ok(())
     }
 
     /// Set and evaluate strut values for client
@@ -319,9 +596,64 @@ impl Client {
     pub(crate) fn set_size_hints(&mut self, subtle: &Subtle, mode_flags: &mut ClientFlags) -> Result<()> {
         let conn = subtle.conn.get().unwrap();
 
+        // Size hints - no idea why it's called normal hints
+        let Some(size_hints) = reply_unless_gone(WmSizeHints::get_normal_hints(conn, self.win)?.reply())? else {
+            return Ok(());
+        };
+
+        self.update_normal_hints(subtle, size_hints.as_ref(), mode_flags)
+    }
+
+    /// Evaluate fetched size hints and sanitize position
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `size_hints` - Fetched size hints if any
+    /// * `mode_flags` - Mode flags to set for this type
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn update_normal_hints(&mut self, subtle: &Subtle, size_hints: Option<&WmSizeHints>,
+                                      mode_flags: &mut ClientFlags) -> Result<()>
+    {
         // Assume first screen
         let screen = subtle.screens.first().context("No screens")?;
 
+        // Check for specific position and size
+        let honor_geom = subtle.flags.contains(SubtleFlags::RESIZE)
+            || self.flags.contains(ClientFlags::MODE_FLOAT | ClientFlags::MODE_RESIZE | ClientFlags::TYPE_DOCK);
+
+        self.update_size_hints(size_hints, &screen.geom, subtle.panel_height, honor_geom, mode_flags);
+
+        // Sanitize positions for stupid clients like GIMP
+        if honor_geom && size_hints.is_some() {
+            self.resize(subtle, &screen.geom, true)?;
+        }
+
+        debug!("{}: client={}, minw={}, minh={}, maxw={}, maxh={}, \
+            minr={}, maxr={}, incw={}, inch={}, basew={}, baseh={}",
+            function_name!(), self, self.min_width, self.min_height,
+            self.max_width, self.max_height,
+            self.min_ratio, self.max_ratio, self.width_inc, self.height_inc,
+            self.base_width, self.base_height);
+
+        Ok(())
+    }
+
+    /// Update size hints values from fetched size hints
+    ///
+    /// # Arguments
+    ///
+    /// * `size_hints` - Fetched size hints if any
+    /// * `screen_geom` - Geometry of the screen to limit sizes
+    /// * `panel_height` - Height of the panel
+    /// * `honor_geom` - Whether to apply requested position and size
+    /// * `mode_flags` - Mode flags to set for this type
+    pub(crate) fn update_size_hints(&mut self, size_hints: Option<&WmSizeHints>, screen_geom: &Rectangle,
+                                    panel_height: u16, honor_geom: bool, mode_flags: &mut ClientFlags)
+    {
         // Set default values
         self.min_width = MIN_WIDTH;
         self.min_height = MIN_HEIGHT;
@@ -334,96 +666,82 @@ impl Client {
         self.base_width = 0;
         self.base_height = 0;
 
-        // Size hints - no idea why it's called normal hints
-        if let Some(size_hints) = WmSizeHints::get_normal_hints(conn, self.win)?.reply()? {
-
-            // Program min size - limit min size to screen size if larger
-           if let Some((min_width, min_height)) = size_hints.min_size {
-               self.min_width = if self.min_width > screen.geom.width {
-                   screen.geom.width } else { max!(MIN_WIDTH, min_width as u16) };
+        let Some(size_hints) = size_hints else {
+            return;
+        };
 
-               self.min_height = if self.min_height > screen.geom.height {
-                   screen.geom.height } else { max!(MIN_HEIGHT, min_height as u16) };
-           }
+        // Program min size - limit min size to screen size if larger
+        if let Some((min_width, min_height)) = size_hints.min_size {
+            self.min_width = if self.min_width > screen_geom.width {
+                screen_geom.width } else { max!(MIN_WIDTH, min_width as u16) };
 
-            // Program max size - limit max size to screen if larger
-            if let Some((max_width, max_height)) = size_hints.max_size {
-                self.max_width = if max_width > screen.geom.width as i32 {
-                    screen.geom.width as i16 } else { max_width as i16 };
+            self.min_height = if self.min_height > screen_geom.height {
+                screen_geom.height } else { max!(MIN_HEIGHT, min_height as u16) };
+        }
 
-                self.max_height = if max_height > screen.geom.height as i32 {
-                    screen.geom.height as i16 - subtle.panel_height as i16
-                } else { max_height as i16 };
-            }
+        // Program max size - limit max size to screen if larger
+        if let Some((max_width, max_height)) = size_hints.max_size {
+            self.max_width = if max_width > screen_geom.width as i32 {
+                screen_geom.width as i16 } else { max_width as i16 };
 
-            // Set float when min == max size (EWMH: Fixed size windows)
-            if let Some((min_width, min_height)) = size_hints.min_size
-                && let Some((max_width, max_height)) = size_hints.max_size
-            {
-                if min_width == max_width && min_height == max_height && !self.flags.contains(ClientFlags::TYPE_DESKTOP) {
-                    mode_flags.insert(ClientFlags::MODE_FLOAT | ClientFlags::MODE_FIXED);
-                }
-            }
+            self.max_height = if max_height > screen_geom.height as i32 {
+                screen_geom.height as i16 - panel_height as i16
+            } else { max_height as i16 };
+        }
 
-            // Aspect ratios
-            if let Some((min_aspect, max_aspect)) = size_hints.aspect {
-                self.min_ratio = min_aspect.numerator as f32 / min_aspect.denominator as f32;
-                self.max_ratio = max_aspect.numerator as f32 / max_aspect.denominator as f32;
+        // Set float when min == max size (EWMH: Fixed size windows)
+        if let Some((min_width, min_height)) = size_hints.min_size
+            && let Some((max_width, max_height)) = size_hints.max_size
+        {
+            if min_width == max_width && min_height == max_height && !self.flags.contains(ClientFlags::TYPE_DESKTOP) {
+                mode_flags.insert(ClientFlags::MODE_FLOAT | ClientFlags::MODE_FIXED);
             }
+        }
 
-            // Resize increment steps
-            if let Some((width_inc, height_inc)) = size_hints.size_increment {
-                self.width_inc = width_inc as u16;
-                self.height_inc = height_inc as u16;
+        // Aspect ratios
+        if let Some((min_aspect, max_aspect)) = size_hints.aspect {
+            self.min_ratio = min_aspect.numerator as f32 / min_aspect.denominator as f32;
+            self.max_ratio = max_aspect.numerator as f32 / max_aspect.denominator as f32;
+        }
 
-            }
+        // Resize increment steps
+        if let Some((width_inc, height_inc)) = size_hints.size_increment {
+            self.width_inc = width_inc as u16;
+            self.height_inc = height_inc as u16;
+        }
 
-            // Base sizes
-            if let Some((base_width, base_height)) = size_hints.base_size {
-                self.base_width = base_width as u16;
-                self.base_height = base_height as u16;
-            }
+        // Base sizes
+        if let Some((base_width, base_height)) = size_hints.base_size {
+            self.base_width = base_width as u16;
+            self.base_height = base_height as u16;
+        }
 
-            // Check for specific position and size
-            if subtle.flags.contains(SubtleFlags::RESIZE)
-                || self.flags.contains(ClientFlags::MODE_FLOAT | ClientFlags::MODE_RESIZE | ClientFlags::TYPE_DOCK)
-            {
-                // User/program position
-                if let Some((hint_spec, x, y)) = size_hints.position {
-                    match hint_spec {
-                        WmSizeHintsSpecification::UserSpecified | WmSizeHintsSpecification::ProgramSpecified => {
-                            self.geom.x = x as i16;
-                            self.geom.y = y as i16;
-                        }
+        if honor_geom {
+            // User/program position
+            if let Some((hint_spec, x, y)) = size_hints.position {
+                match hint_spec {
+                    WmSizeHintsSpecification::UserSpecified | WmSizeHintsSpecification::ProgramSpecified => {
+                        self.geom.x = x as i16;
+                        self.geom.y = y as i16;
                     }
                 }
+            }
 
-                // User/program size
-                if let Some((hint_spec, x, y)) = size_hints.size {
-                    match hint_spec {
-                        WmSizeHintsSpecification::UserSpecified | WmSizeHintsSpecification::ProgramSpecified => {
-                            self.geom.width = x as u16;
-                            self.geom.height = y as u16;
-                        }
+            // User/program size
+            if let Some((hint_spec, x, y)) = size_hints.size {
+                match hint_spec {
+                    WmSizeHintsSpecification::UserSpecified | WmSizeHintsSpecification::ProgramSpecified => {
+                        self.geom.width = x as u16;
+                        self.geom.height = y as u16;
                     }
                 }
-
-                // Sanitize positions for stupid clients like GIMP
-                self.resize(subtle, &screen.geom, true)?;
             }
         }
-
-        debug!("{}: client={}, minw={}, minh={}, maxw={}, maxh={}, \
-            minr={}, maxr={}, incw={}, inch={}, basew={}, baseh={}",
-            function_name!(), self, self.min_width, self.min_height,
-            self.max_width, self.max_height,
-            self.min_ratio, self.max_ratio, self.width_inc, self.height_inc,
-            self.base_width, self.base_height);
-
-        Ok(())
     }
 
-    /// Set WM_NAME for client
+    /// Set name, role, instance and class for client
+    ///
+    /// Prefers _NET_WM_NAME over WM_NAME and tolerates missing or malformed properties.
     ///
     /// # Arguments
     ///
@@ -436,32 +754,56 @@ impl Client {
         let conn = subtle.conn.get().unwrap();
         let atoms = subtle.atoms.get().unwrap();
 
-        let wm_name = conn.get_property(false, self.win,
-                                        atoms.WM_NAME, AtomEnum::STRING,
-                                        0, u32::MAX)?.reply()?.value;
+        // Fetch everything first to keep the client untouched on error
+        let Some(net_wm_name) = reply_unless_gone(conn.get_property(false, self.win,
+                                                                    atoms._NET_WM_NAME, atoms.UTF8_STRING,
+                                                                    0, u32::MAX)?.reply())? else {
+            return Ok(());
+        };
+
+        let Some(wm_name) = reply_unless_gone(conn.get_property(false, self.win,
+                                                                atoms.WM_NAME, AtomEnum::ANY,
+                                                                0, u32::MAX)?.reply())? else {
+            return Ok(());
+        };
 
-        let wm_role= conn.get_property(false, self.win, AtomEnum::STRING,
-                                       atoms.WM_WINDOW_ROLE, 0, u32::MAX)?.reply()?.value;
+        let Some(wm_role) = reply_unless_gone(conn.get_property(false, self.win, atoms.WM_WINDOW_ROLE,
+                                                                AtomEnum::STRING, 0, u32::MAX)?.reply())? else {
+            return Ok(());
+        };
 
-        let wm_klass = conn.get_property(false, self.win, atoms.WM_CLASS,
-                                         AtomEnum::STRING, 0, u32::MAX)?.reply()?.value;
+        let Some(wm_klass) = reply_unless_gone(conn.get_property(false, self.win, atoms.WM_CLASS,
+                                                                 AtomEnum::STRING, 0, u32::MAX)?.reply())? else {
+            return Ok(());
+        };
 
+        // Finally update client
+        self.update_wm_name(&net_wm_name.value, &wm_name.value, &wm_role.value, &wm_klass.value);
 
-        let inst_klass = String::from_utf8(wm_klass)
-            .expect("UTF-8 string should be valid UTF-8")
-            .trim_matches('\0')
-            .split('\0')
-            .map(|s| s.to_string())
-            .collect::<Vec<_>>();
+        Ok(())
+    }
 
-        self.name = String::from_utf8(wm_name)?;
-        self.role = String::from_utf8(wm_role)?;
-        self.instance =  inst_klass[0].to_string();
-        self.klass = inst_klass[1].to_string();
+    /// Update name, role, instance and class from fetched property values
+    ///
+    /// # Arguments
+    ///
+    /// * `net_wm_name` - Value of _NET_WM_NAME
+    /// * `wm_name` - Value of WM_NAME
+    /// * `wm_role` - Value of WM_WINDOW_ROLE
+    /// * `wm_klass` - Value of WM_CLASS
+    pub(crate) fn update_wm_name(&mut self, net_wm_name: &[u8], wm_name: &[u8], wm_role: &[u8], wm_klass: &[u8]) {
+        let (instance, klass) = parse_wm_class(wm_klass);
+
+        self.name = if net_wm_name.is_empty() {
+            decode_text(wm_name)
+        } else {
+            decode_text(net_wm_name)
+        };
+        self.role = decode_text(wm_role);
+        self.instance = instance;
+        self.klass = klass;
 
         debug!("{}: client={}", function_name!(), self);
-
-        Ok(())
     }
 
     /// Set WM_STATE for client
@@ -475,115 +817,132 @@ impl Client {
     ///
     /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
     pub(crate) fn set_wm_state(&self, subtle: &Subtle, state: WMState) -> Result<()> {
-        let conn = subtle.conn.get().unwrap();
-        let atoms = subtle.atoms.get().unwrap();
-
-        let data: [u8; 2] = [state as u8, NONE as u8];
-
-        conn.change_property(PropMode::REPLACE,
-                             self.win, atoms.WM_STATE, atoms.WM_STATE, 8, 2, &data)?;
+        effect::apply(subtle, Effect::SetWmState { win: self.win, state })?;
 
         debug!("{}: client={}", function_name!(), self);
 
         Ok(())
     }
 
-    /// Set and evaluate wm protocols for client
+    /// Evaluate fetched wm protocols for client
     ///
     /// # Arguments
     ///
-    /// * `subtle` - Global state object
-    ///
-    /// # Returns
-    ///
-    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-    pub(crate) fn set_wm_protocols(&mut self, subtle: &Subtle) -> Result<()> {
-        let conn = subtle.conn.get().unwrap();
-        let atoms = subtle.atoms.get().unwrap();
-
-        let protocols = conn.get_property(false, self.win, atoms.WM_PROTOCOLS,
-                                          AtomEnum::ATOM, 0, u32::MAX)?.reply()?.value;
-
+    /// * `atoms` - Known atoms
+    /// * `protocols` - Value of WM_PROTOCOLS
+    pub(crate) fn update_wm_protocols(&mut self, atoms: &Atoms, protocols: &[Atom]) {
         for protocol in protocols {
-            if atoms.WM_TAKE_FOCUS == protocol as u32 {
+            if atoms.WM_TAKE_FOCUS == *protocol {
                 self.flags.insert(ClientFlags::FOCUS);
-            } else if atoms.WM_DELETE_WINDOW == protocol as u32 {
+            } else if atoms.WM_DELETE_WINDOW == *protocol {
                 self.flags.insert(ClientFlags::CLOSE);
             }
         }
 
         debug!("{}: client={}", function_name!(), self);
-
-        Ok(())
     }
 
-    /// Set wm type for client
+    /// Evaluate fetched wm type for client
     ///
     /// # Arguments
     ///
-    /// * `subtle` - Global state object
+    /// * `atoms` - Known atoms
+    /// * `wm_types` - Value of _NET_WM_WINDOW_TYPE
     /// * `mode_flags` - Mode flags to set for this type
-    ///
-    /// # Returns
-    ///
-    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-    pub(crate) fn set_wm_type(&mut self, subtle: &Subtle, mode_flags: &mut ClientFlags) -> Result<()> {
-        let conn = subtle.conn.get().unwrap();
-        let atoms = subtle.atoms.get().unwrap();
-
-        let wm_types = conn.get_property(false, self.win, AtomEnum::ATOM,
-                                         atoms._NET_WM_WINDOW_TYPE, 0, 5)?.reply()?.value;
-
-        for wm_type in wm_types {
-            if atoms._NET_WM_WINDOW_TYPE_DESKTOP == wm_type as u32 {
+    pub(crate) fn update_wm_type(&mut self, atoms: &Atoms, wm_types: &[Atom], mode_flags: &mut ClientFlags) {
+        for wm_type in wm_types.iter().copied() {
+            if atoms._NET_WM_WINDOW_TYPE_DESKTOP == wm_type {
                 self.flags.insert(ClientFlags::TYPE_DESKTOP);
                 mode_flags.insert(ClientFlags::MODE_FIXED | ClientFlags::MODE_STICK);
-            } else if atoms._NET_WM_WINDOW_TYPE_DOCK == wm_type as u32 {
+            } else if atoms._NET_WM_WINDOW_TYPE_DOCK == wm_type {
                 self.flags.insert(ClientFlags::TYPE_DOCK);
                 mode_flags.insert(ClientFlags::MODE_FIXED | ClientFlags::MODE_STICK);
-            } else if atoms._NET_WM_WINDOW_TYPE_TOOLBAR == wm_type as u32 {
+            } else if atoms._NET_WM_WINDOW_TYPE_TOOLBAR == wm_type {
                 self.flags.insert(ClientFlags::TYPE_TOOLBAR);
-            } else if atoms._NET_WM_WINDOW_TYPE_SPLASH == wm_type as u32 {
+            } else if atoms._NET_WM_WINDOW_TYPE_SPLASH == wm_type {
                 self.flags.insert(ClientFlags::TYPE_SPLASH);
                 mode_flags.insert(ClientFlags::MODE_FLOAT | ClientFlags::MODE_CENTER);
-            } else if atoms._NET_WM_WINDOW_TYPE_DIALOG == wm_type as u32 {
+            } else if atoms._NET_WM_WINDOW_TYPE_DIALOG == wm_type {
                 self.flags.insert(ClientFlags::TYPE_DIALOG);
                 mode_flags.insert(ClientFlags::MODE_FLOAT | ClientFlags::MODE_CENTER);
+            } else if atoms._NET_WM_WINDOW_TYPE_NORMAL == wm_type {
+                self.flags.insert(ClientFlags::TYPE_NORMAL);
             }
         }
 
-        debug!("{}: client={}, mode_flags={:?}", function_name!(), self, mode_flags);
+        // EWMH: Windows without known type are normal
+        if !self.flags.intersects(ClientFlags::ALL_TYPES) {
+            self.flags.insert(ClientFlags::TYPE_NORMAL);
+        }
 
-        Ok(())
+        debug!("{}: client={}, mode_flags={:?}", function_name!(), self, mode_flags);
     }
 
-    /// Set and evaluate wm hints for client
+    /// Set whether client handles icons of iconified clients
     ///
     /// # Arguments
     ///
     /// * `subtle` - Global state object
-    /// * `mode_flags` - Mode flags to set for this type
     ///
     /// # Returns
     ///
     /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-    pub(crate) fn set_wm_hints(&mut self, subtle: &Subtle, mode_flags: &mut ClientFlags) -> Result<()> {
+    pub(crate) fn set_handled_icons(&mut self, subtle: &Subtle) -> Result<()> {
         let conn = subtle.conn.get().unwrap();
+        let atoms = subtle.atoms.get().unwrap();
 
-        // Window manager hints (ICCCM 4.1.7)
-        if let Some(wm_hints) = WmHints::get(conn, self.win)?.reply()? {
-            // Handle urgency hint:
-            // Set urgency if window hasn't got focus and remove it after getting focus
-            if wm_hints.urgent && let Some(focus_win) = subtle.focus_history.borrow(0)
-                && focus_win != self.win
-            {
-                self.flags.remove(ClientFlags::MODE_URGENT);
-            }
+        // EWMH: Presence of the property is the hint, the value is irrelevant
+        let reply = conn.get_property(false, self.win, atoms._NET_WM_HANDLED_ICONS,
+                                      AtomEnum::ANY, 0, 1)?.reply()?;
 
-            // Handle window group hint
-            if let Some(window_group) = wm_hints.window_group {
-                if let Some(group_lead) = subtle.find_client(window_group) {
-                    self.flags = group_lead.flags; // TODO *flags |= (k->flags & MODES_ALL);
+        self.flags.set(ClientFlags::HANDLED_ICONS, NONE != reply.type_);
+
+        debug!("{}: client={}, handled_icons={}", function_name!(), self,
+            self.flags.contains(ClientFlags::HANDLED_ICONS));
+
+        Ok(())
+    }
+
+    /// Set and evaluate wm hints for client
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `mode_flags` - Mode flags to set for this type
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn set_wm_hints(&mut self, subtle: &Subtle, mode_flags: &mut ClientFlags) -> Result<()> {
+        let conn = subtle.conn.get().unwrap();
+
+        let Some(wm_hints) = reply_unless_gone(WmHints::get(conn, self.win)?.reply())? else {
+            return Ok(());
+        };
+
+        self.update_wm_hints(subtle, wm_hints.as_ref(), mode_flags);
+
+        Ok(())
+    }
+
+    /// Evaluate fetched wm hints for client
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `wm_hints` - Fetched wm hints if any
+    /// * `mode_flags` - Mode flags to set for this type
+    pub(crate) fn update_wm_hints(&mut self, subtle: &Subtle, wm_hints: Option<&WmHints>, mode_flags: &mut ClientFlags) {
+        // Window manager hints (ICCCM 4.1.7)
+        if let Some(wm_hints) = wm_hints {
+            // Handle urgency hint: Set urgency if window hasn't got focus
+            mode_flags.insert(calc_urgent_mode(wm_hints.urgent,
+                                               subtle.focus_history.first() == Some(self.win)));
+
+            // Handle window group hint
+            if let Some(window_group) = wm_hints.window_group {
+                if let Some(group_lead) = subtle.find_client(window_group) {
+                    self.flags = group_lead.flags; // TODO *flags |= (k->flags & MODES_ALL);
                     self.tags = group_lead.tags;
                     self.screen_idx = group_lead.screen_idx;
                 }
@@ -598,8 +957,6 @@ impl Client {
         }
 
         debug!("{}: client={}, mode_flags={:?}", function_name!(), self, mode_flags);
-
-        Ok(())
     }
 
     /// Set and evaluate _MOTIF_WM_HINTS for client
@@ -616,67 +973,55 @@ impl Client {
         let conn = subtle.conn.get().unwrap();
         let atoms = subtle.atoms.get().unwrap();
 
-        let _hints = conn.get_property(false, self.win, atoms._MOTIF_WM_HINTS,
-                                      atoms._MOTIF_WM_HINTS, 0, 1)?.reply()?.value;
-
-        // TODO
+        let hints = conn.get_property(false, self.win, atoms._MOTIF_WM_HINTS,
+                                      atoms._MOTIF_WM_HINTS, 0, 1)?.reply()?;
 
-        debug!("{}: client={}, mode_flags={:?}", function_name!(), self, mode_flags);
+        self.update_motif_wm_hints(&hints.value32().map(|hints| hints.collect::<Vec<_>>()).unwrap_or_default(),
+                                   mode_flags);
 
         Ok(())
     }
 
-    /// Set and evaluate _NET_WM_STATE for client
+    /// Evaluate fetched _MOTIF_WM_HINTS for client
     ///
     /// # Arguments
     ///
-    /// * `subtle` - Global state object
+    /// * `_hints` - Value of _MOTIF_WM_HINTS
     /// * `mode_flags` - Mode flags to set for this type
-    ///
-    /// # Returns
-    ///
-    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-    pub(crate) fn set_net_wm_state(&self, subtle: &Subtle, mode_flags: &mut ClientFlags) -> Result<()> {
-        let conn = subtle.conn.get().unwrap();
-        let atoms = subtle.atoms.get().unwrap();
+    pub(crate) fn update_motif_wm_hints(&self, _hints: &[u32], mode_flags: &mut ClientFlags) {
+        // TODO
 
-        let states = conn.get_property(false, self.win, AtomEnum::ATOM,
-                                       atoms._NET_WM_STATE, 0, 4)?.reply()?.value;
+        debug!("{}: client={}, mode_flags={:?}", function_name!(), self, mode_flags);
+    }
 
+    /// Evaluate fetched _NET_WM_STATE for client
+    ///
+    /// # Arguments
+    ///
+    /// * `atoms` - Known atoms
+    /// * `states` - Value of _NET_WM_STATE
+    /// * `mode_flags` - Mode flags to set for this type
+    pub(crate) fn update_net_wm_state(&self, atoms: &Atoms, states: &[Atom], mode_flags: &mut ClientFlags) {
         for state in states {
-            if atoms._NET_WM_STATE_FULLSCREEN == state as Atom {
-                mode_flags.insert(ClientFlags::MODE_FULL);
-            } else if atoms._NET_WM_STATE_ABOVE == state as Atom {
-                mode_flags.insert(ClientFlags::MODE_FLOAT);
-            } else if atoms._NET_WM_STATE_STICKY == state as Atom {
-                mode_flags.insert(ClientFlags::MODE_STICK);
-            } else if atoms._NET_WM_STATE_DEMANDS_ATTENTION == state as Atom {
-                mode_flags.insert(ClientFlags::MODE_URGENT);
+            if let Some(state_flag) = get_state_flag(atoms, *state) {
+                mode_flags.insert(state_flag);
             }
         }
 
         debug!("{}: client={}, mode_flags={:?}", function_name!(), self, mode_flags);
-
-        Ok(())
     }
 
-    /// Set transient state for client
+    /// Evaluate fetched transient state for client
     ///
     /// # Arguments
     ///
     /// * `subtle` - Global state object
+    /// * `transient_for` - Value of WM_TRANSIENT_FOR if any
     /// * `mode_flags` - Mode flags to set for this type
-    ///
-    /// # Returns
-    ///
-    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-    pub(crate) fn set_transient(&mut self, subtle: &Subtle, mode_flags: &mut ClientFlags) -> Result<()> {
-        let conn = subtle.conn.get().unwrap();
-
-        let trans = conn.get_property(false, self.win, AtomEnum::WM_TRANSIENT_FOR,
-                          AtomEnum::WINDOW, 0, 1)?.reply()?.value;
+    pub(crate) fn update_transient(&mut self, subtle: &Subtle, transient_for: Option<Window>, mode_flags: &mut ClientFlags) {
+        if let Some(transient_for) = transient_for {
+            self.transient_for = transient_for;
 
-        if !trans.is_empty() {
             // Check if transient windows should be urgent
             mode_flags.insert(if subtle.flags.intersects(SubtleFlags::URGENT) {
                 ClientFlags::MODE_FLOAT | ClientFlags::MODE_URGENT
@@ -685,17 +1030,20 @@ impl Client {
             });
 
             // Find parent window
-            if let Some(parent) = subtle.find_client(trans[0] as Window) {
+            if let Some(parent) = subtle.find_client(transient_for) {
                mode_flags.insert(parent.flags & ClientFlags::ALL_MODES);
 
-                self.tags.insert(parent.tags);
+                self.tags = calc_transient_tags(self.tags, parent.tags, subtle.transients_follow_parent);
                 self.screen_idx = parent.screen_idx;
+
+                // Keep tag rules from moving the transient away from its parent
+                if subtle.transients_follow_parent {
+                    self.flags.insert(ClientFlags::PARENT_TAGS);
+                }
             }
         }
 
         debug!("{}: client={}, mode_flags={:?}", function_name!(), self, mode_flags);
-
-        Ok(())
     }
 
     /// Set focus to client on active screen
@@ -709,62 +1057,38 @@ impl Client {
     ///
     /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
     pub(crate) fn focus(&self, subtle: &Subtle, warp_pointer: bool) -> Result<()> {
-        if !self.is_visible(subtle) {
+        // Callers may have picked this client before a configure unmapped or killed it
+        if !self.is_focusable(subtle) {
+            debug!("{}: skip client={}", function_name!(), self);
+
             return Ok(());
         }
 
-        let conn = subtle.conn.get().unwrap();
-        let atoms = subtle.atoms.get().unwrap();
-
         // Unset current focus
-        if let Some(win) = subtle.focus_history.borrow(0) && self.win != *win {
-            if let Some(focus) = subtle.find_client(*win) {
-                grab::unset(subtle, focus.win)?;
+        if let Some(win) = subtle.focus_history.first() && self.win != win {
+            if let Some(focus) = subtle.find_client(win) {
+                effect::apply(subtle, Effect::Ungrab(focus.win))?;
 
-                // Reorder focus history
-                // TODO
-
-                if !focus.flags.contains(ClientFlags::TYPE_DESKTOP) {
-                    let aux = ChangeWindowAttributesAux::default()
-                        .border_pixel(subtle.clients_style.bg as u32);
-
-                    conn.change_window_attributes(focus.win, &aux)?.check()?;
-                }
+                focus.update_border(subtle, false)?;
             }
         }
 
         // Check client input focus type (see ICCCM 4.1.7, 4.1.2.7, 4.2.8)
         if !self.flags.contains(ClientFlags::INPUT) && self.flags.contains(ClientFlags::FOCUS) {
-            conn.send_event(false, self.win, EventMask::NO_EVENT, ClientMessageEvent {
-                response_type: CLIENT_MESSAGE_EVENT,
-                format: 32,
-                sequence: 0,
-                window: self.win,
-                type_: atoms.WM_PROTOCOLS,
-                data: [atoms.WM_TAKE_FOCUS, CURRENT_TIME, 0, 0, 0].into(),
-            })?.check()?;
+            effect::apply(subtle, Effect::TakeFocus(self.win))?;
         } else if self.flags.contains(ClientFlags::INPUT) {
-            conn.set_input_focus(InputFocus::POINTER_ROOT, self.win, CURRENT_TIME)?.check()?;
+            effect::apply(subtle, Effect::Focus(self.win))?;
         }
 
         // Update focus
-        //subtle.focus_history.remove()
-        grab::set(subtle, self.win, GrabFlags::IS_MOUSE)?;
+        subtle.focus_history.push(self.win);
+        effect::apply(subtle, Effect::GrabMouse(self.win))?;
 
-        // Exclude desktop and dock type windows
-        if !self.flags.intersects(ClientFlags::TYPE_DESKTOP | ClientFlags::TYPE_DOCK) {
-            conn.change_window_attributes(self.win, &ChangeWindowAttributesAux::default()
-                .border_pixel(subtle.clients_style.fg as u32))?.check()?;
-        }
+        self.update_border(subtle, true)?;
 
         // EWMH: Active window
-        let default_screen = &conn.setup().roots[subtle.screen_num];
-
-        let list = subtle.focus_history.inner().iter()
-            .map(|elem| elem.get() as u32).collect::<Vec<_>>();
-
-        conn.change_property32(PropMode::REPLACE, default_screen.root, atoms._NET_ACTIVE_WINDOW,
-                               AtomEnum::WINDOW, list.as_slice())?.check()?;
+        effect::apply(subtle, Effect::SetRootProperty { prop: Prop::ActiveWindow,
+            data: subtle.focus_history.to_vec() })?;
 
         // Warp pointer
         if warp_pointer && !subtle.flags.intersects(SubtleFlags::SKIP_POINTER_WARP) {
@@ -776,6 +1100,30 @@ impl Client {
         Ok(())
     }
 
+    /// Update border color of client
+    ///
+    /// Desktop and dock type windows never get a border color, see [`calc_border_pixel`].
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `is_focused` - Whether the client has the focus
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn update_border(&self, subtle: &Subtle, is_focused: bool) -> Result<()> {
+        if let Some(pixel) = calc_border_pixel(self.flags, is_focused,
+            subtle.clients_style.fg, subtle.clients_style.bg)
+        {
+            effect::apply(subtle, Effect::SetBorderColor { win: self.win, pixel })?;
+        }
+
+        debug!("{}: client={}, is_focused={}", function_name!(), self, is_focused);
+
+        Ok(())
+    }
+
     /// Toggle mode flags for client
     ///
     /// # Arguments
@@ -783,17 +1131,18 @@ impl Client {
     /// * `subtle` - Global state object
     /// * `mode_flags` - Mode flags to toggle for this type
     /// * `set_gravity` - Whether to also set gravity
+    /// * `focus_screen_idx` - Screen of the focus client to stick to
     ///
     /// # Returns
     ///
     /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-    pub(crate) fn toggle(&mut self, subtle: &Subtle, mode_flags: &mut ClientFlags, set_gravity: bool) -> Result<()> {
-        let conn = subtle.conn.get().unwrap();
-        let atoms = subtle.atoms.get().unwrap();
-
+    pub(crate) fn toggle(&mut self, subtle: &Subtle, mode_flags: &mut ClientFlags, set_gravity: bool,
+                         focus_screen_idx: Option<isize>) -> Result<()>
+    {
         // Set arrange for certain modes
         if mode_flags.intersects(ClientFlags::MODE_FLOAT | ClientFlags::MODE_STICK | ClientFlags::MODE_FULL
-            | ClientFlags::MODE_ZAPHOD | ClientFlags::MODE_BORDERLESS | ClientFlags::MODE_CENTER)
+            | ClientFlags::MODE_ZAPHOD | ClientFlags::MODE_BORDERLESS | ClientFlags::MODE_CENTER
+            | ClientFlags::MODE_IGNORE_HINTS)
         {
             self.flags.insert(ClientFlags::ARRANGE);
         }
@@ -801,15 +1150,11 @@ impl Client {
         // Handle sticky mode
         if mode_flags.contains(ClientFlags::MODE_STICK) {
             // Unset stick mode
-            if self.flags.contains(ClientFlags::MODE_STICK) {
-                if self.flags.contains(ClientFlags::MODE_URGENT) {
-                    //subtle.urgent_tags.remove(self.tags); // TODO urgent
-                }
-            } else {
+            if !self.flags.contains(ClientFlags::MODE_STICK) {
                 if set_gravity {
                     // Set gravity for untagged views
-                    for (view_idx, view) in subtle.views.iter().enumerate() {
-                        if !view.tags.contains(self.tags) && -1 != self.gravity_idx {
+                    for (view_idx, view) in subtle.views.borrow().iter().enumerate() {
+                        if !view.tags.get().contains(self.tags) && -1 != self.gravity_idx {
                             self.gravities[view_idx] = self.gravity_idx as usize;
                         }
                     }
@@ -817,16 +1162,15 @@ impl Client {
 
                 // Set screen when required
                 if !self.flags.contains(ClientFlags::MODE_STICK_SCREEN) {
-                    // Find screen: Prefer screen of current window
+                    // Find screen: Prefer screen of current window, passed in since the clients
+                    // are usually borrowed by the caller
                     if subtle.flags.contains(SubtleFlags::SKIP_POINTER_WARP)  {
-                        if let Some(win) = subtle.focus_history.borrow(0) {
-                            if let Some(focus) = subtle.find_client(*win) {
-                                if focus.is_visible(subtle) {
-                                    self.screen_idx = focus.screen_idx;
-                                }
-                            }
+                        if let Some(screen_idx) = focus_screen_idx {
+                            self.translate_saved_geom(subtle, screen_idx);
+                            self.screen_idx = screen_idx;
                         }
                     } else if let Some((idx, _)) = subtle.find_screen_by_pointer() {
+                        self.translate_saved_geom(subtle, idx as isize);
                         self.screen_idx = idx as isize;
                     }
                 }
@@ -835,14 +1179,8 @@ impl Client {
 
         // Handle fullscreen mode
         if mode_flags.contains(ClientFlags::MODE_FULL) {
-            if self.flags.contains(ClientFlags::MODE_FULL) {
-                if !self.flags.contains(ClientFlags::MODE_BORDERLESS) {
-                    let aux = ConfigureWindowAux::default()
-                        .border_width(subtle.clients_style.border.top as u32);
-
-                    conn.configure_window(self.win, &aux)?.check()?;
-                }
-            } else {
+            // Set fullscreen
+            if !self.flags.contains(ClientFlags::MODE_FULL) {
                 // Normally, you'd expect that a fixed size window wants to keep the size.
                 // Apparently, some broken clients just violate that, so we exclude fixed
                 // windows with min != screen size from fullscreen
@@ -854,30 +1192,32 @@ impl Client {
                     }
                 }
 
-                let aux = ChangeWindowAttributesAux::default()
-                    .border_pixel(0);
-
-                conn.change_window_attributes(self.win, &aux)?.check()?;
+                // Save geometry unless a tiled client would overwrite the floating one
+                if mode_flags.contains(ClientFlags::MODE_FULL)
+                    && (self.flags.contains(ClientFlags::MODE_FLOAT) || self.saved_geom.is_none())
+                {
+                    self.saved_geom = Some(self.geom);
+                }
             }
         }
 
-        // Handle borderless
-        if mode_flags.contains(ClientFlags::MODE_BORDERLESS) {
-            let mut aux = ConfigureWindowAux::default();
+        let is_leaving_full = mode_flags.contains(ClientFlags::MODE_FULL)
+            && self.flags.contains(ClientFlags::MODE_FULL);
+        let is_leaving_center = mode_flags.contains(ClientFlags::MODE_CENTER)
+            && self.flags.contains(ClientFlags::MODE_CENTER);
+        let is_entering_float = mode_flags.contains(ClientFlags::MODE_FLOAT)
+            && !mode_flags.contains(ClientFlags::MODE_CENTER) && !self.flags.contains(ClientFlags::MODE_FLOAT);
 
-            // Unset borderless
-            if !self.flags.contains(ClientFlags::MODE_BORDERLESS) {
-                aux = aux.border_width(subtle.clients_style.border.top as u32);
-            } else {
-                aux = aux.border_width(0);
-            }
-
-            conn.configure_window(self.win, &aux)?.check()?;
+        // Handle float mode: Save geometry when float is left, fullscreen keeps the one saved on entry
+        if mode_flags.contains(ClientFlags::MODE_FLOAT) && !mode_flags.contains(ClientFlags::MODE_CENTER)
+            && self.flags.contains(ClientFlags::MODE_FLOAT) && !self.flags.contains(ClientFlags::MODE_FULL)
+        {
+            self.saved_geom = Some(self.geom);
         }
 
-        // Handle urgent
-        if mode_flags.contains(ClientFlags::MODE_URGENT) {
-            //subtle.urgent_tags.insert(self.tags) // TODO urgent
+        // Handle urgent: Removal requires a recompute over all clients, see [`update_urgent_tags`]
+        if mode_flags.contains(ClientFlags::MODE_URGENT) && !self.flags.contains(ClientFlags::MODE_URGENT) {
+            publish_urgent_tags(subtle, subtle.urgent_tags.get() | self.tags)?;
         }
 
         // Handle center mode
@@ -888,11 +1228,12 @@ impl Client {
             } else {
                 if let Some(screen) = subtle.screens.get(self.screen_idx as usize) {
                     debug!("client={}, screen={}", self, screen);
-                    // Set to screen center
-                    self.geom.x = screen.geom.x + (screen.geom.width as i16 - self.geom.width as i16
-                        - 2 * self.get_border_width(subtle)) / 2;
-                    self.geom.y = screen.geom.y + (screen.geom.height as i16 - self.geom.height as i16
-                        - 2 * self.get_border_width(subtle)) / 2;
+                    self.saved_geom = Some(self.geom);
+
+                    // Set to screen center and keep it reachable
+                    self.geom = calc_centered_dialog(&screen.geom, &self.geom,
+                        self.get_border_width(subtle) as u16, (self.min_width, self.min_height),
+                        subtle.dialog_min_visible, subtle.shrink_oversized_dialogs);
 
                     mode_flags.insert(ClientFlags::MODE_FLOAT);
                     self.flags.insert(ClientFlags::ARRANGE);
@@ -902,10 +1243,7 @@ impl Client {
 
         // Handle desktop and dock type (one way)
         if mode_flags.contains(ClientFlags::TYPE_DESKTOP | ClientFlags::TYPE_DOCK) {
-            let aux = ConfigureWindowAux::default()
-                .border_width(0);
-
-            conn.configure_window(self.win, &aux)?.check()?;
+            effect::apply(subtle, Effect::SetBorderWidth { win: self.win, width: 0 })?;
 
             // Special treatment
             if mode_flags.contains(ClientFlags::TYPE_DESKTOP) {
@@ -926,10 +1264,26 @@ impl Client {
         }
 
         // Finally toggle mode flags only
-        // TODO  c->flags = ((c->flags & ~MODES_ALL) | ((c->flags & MODES_ALL) ^ (flags & MODES_ALL)));
-        self.flags = self.flags.bitand(ClientFlags::ALL_MODES.complement())
-            .bitor(self.flags.bitand(ClientFlags::ALL_MODES))
-            .bitxor(mode_flags.bitand(ClientFlags::ALL_MODES));
+        let old_actions = calc_allowed_actions(self.flags);
+
+        self.flags = calc_toggled_flags(self.flags, *mode_flags);
+
+        // Restore saved geometry when fullscreen or center is left or float entered
+        if ((is_leaving_full || is_entering_float) && self.flags.contains(ClientFlags::MODE_FLOAT)
+            && !self.flags.contains(ClientFlags::MODE_FULL)) || is_leaving_center
+        {
+            self.restore_saved_geom(subtle);
+        }
+
+        // Handle fullscreen and borderless: Border depends on the toggled flags
+        if mode_flags.intersects(ClientFlags::MODE_FULL | ClientFlags::MODE_BORDERLESS)
+            && !self.flags.intersects(ClientFlags::TYPE_DESKTOP | ClientFlags::TYPE_DOCK)
+        {
+            effect::apply(subtle, Effect::SetBorderWidth { win: self.win,
+                width: calc_border_width(self.flags, subtle.clients_style.border.top) })?;
+
+            self.update_border(subtle, subtle.focus_history.first() == Some(self.win))?;
+        }
 
         // Sort for keeping stacking order
         if self.flags.contains(ClientFlags::MODE_FLOAT | ClientFlags::MODE_FULL
@@ -941,36 +1295,45 @@ impl Client {
         }
 
         // EWMH: State and flags
-        let mut state_atoms: Vec<Atom> = Vec::default();
         let mut ewmh_state = EWMHStateFlags::empty();
 
         if self.flags.contains(ClientFlags::MODE_FULL) {
-            state_atoms.push(atoms._NET_WM_STATE_FULLSCREEN);
             ewmh_state.insert(EWMHStateFlags::FULL);
         }
 
         if self.flags.contains(ClientFlags::MODE_FLOAT) {
-            state_atoms.push(atoms._NET_WM_STATE_ABOVE);
             ewmh_state.insert(EWMHStateFlags::FLOAT);
         }
 
         if self.flags.contains(ClientFlags::MODE_STICK) {
-            state_atoms.push(atoms._NET_WM_STATE_STICKY);
             ewmh_state.insert(EWMHStateFlags::STICK);
         }
 
         if self.flags.contains(ClientFlags::MODE_URGENT) {
-            state_atoms.push(atoms._NET_WM_STATE_DEMANDS_ATTENTION);
             ewmh_state.insert(EWMHStateFlags::URGENT);
         }
 
-        conn.change_property32(PropMode::REPLACE, self.win, atoms._NET_WM_STATE,
-                               AtomEnum::ATOM, state_atoms.as_slice())?.check()?;
+        if self.flags.contains(ClientFlags::MODE_IGNORE_HINTS) {
+            ewmh_state.insert(EWMHStateFlags::IGNORE_HINTS);
+        }
+
+        effect::apply(subtle, Effect::SetNetWmState { win: self.win, state: ewmh_state })?;
+
+        // EWMH: Allowed actions
+        let actions = calc_allowed_actions(self.flags);
+
+        if actions != old_actions {
+            effect::apply(subtle, Effect::SetAllowedActions { win: self.win, actions })?;
+        }
+
+        effect::apply(subtle, Effect::SetProperty { win: self.win, prop: Prop::ClientFlags,
+            data: vec![ewmh_state.bits()] })?;
 
-        conn.change_property32(PropMode::REPLACE, self.win, atoms.SUBTLE_CLIENT_FLAGS,
-                                AtomEnum::CARDINAL, &[ewmh_state.bits()])?.check()?;
+        // EWMH: Frame extents of the effective border
+        effect::apply(subtle, Effect::SetProperty { win: self.win, prop: Prop::FrameExtents,
+            data: calc_frame_extents(self.flags, subtle.clients_style.border.top) })?;
 
-        conn.flush()?;
+        effect::flush(subtle)?;
 
         debug!("{}: client={}, mode_flags={:?}, gravity={}", function_name!(),
             self, mode_flags, set_gravity);
@@ -993,10 +1356,33 @@ impl Client {
         ignore_if_dead!(self);
 
         // Update tags and client mode flags
-        if let Some(tag) = subtle.tags.get(tag_idx) {
-            self.tags |= Tagging::from_bits_retain(1 << tag_idx);
+        if let Some(tag) = subtle.tags.borrow().get(tag_idx) {
+            self.tags |= tagging::tag_bit(tag_idx);
 
             mode_flags.insert(tag.mode_flags);
+
+            // Set gravity on views with this tag
+            if tag.flags.contains(TagFlags::GRAVITY) {
+                let view_tags: Vec<Tagging> = subtle.views.borrow().iter()
+                    .map(|view| view.tags.get())
+                    .collect();
+
+                tag::apply_gravity(&mut self.gravities, &view_tags, tag_idx, tag.gravity_id);
+            }
+
+            // Pin client to screen
+            if tag.flags.contains(TagFlags::SCREEN) {
+                self.translate_saved_geom(subtle, tag.screen_id as isize);
+                self.screen_idx = tag.screen_id as isize;
+                self.flags.insert(ClientFlags::MODE_STICK_SCREEN);
+            }
+
+            // Set floating geometry or just position
+            if let Some(geom) = tag.calc_geom(&self.geom) {
+                self.geom = geom;
+
+                mode_flags.insert(ClientFlags::MODE_FLOAT);
+            }
         }
 
         debug!("{}: client={}, mode_flags={:?}", function_name!(), self, mode_flags);
@@ -1015,40 +1401,153 @@ impl Client {
     ///
     /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
     pub(crate) fn retag(&mut self, subtle: &Subtle, mode_flags: &mut ClientFlags) -> Result<()> {
-        let conn = subtle.conn.get().unwrap();
-        let atoms = subtle.atoms.get().unwrap();
-
-        for (tag_idx, tag) in subtle.tags.iter().enumerate() {
-            if tag.matches(self) {
-                self.tag(subtle, tag_idx, mode_flags)?;
+        // Tags of the transient parent are authoritative
+        if !self.flags.contains(ClientFlags::PARENT_TAGS) {
+            for (tag_idx, tag) in subtle.tags.borrow().iter().enumerate() {
+                if tag.matches(self) {
+                    self.tag(subtle, tag_idx, mode_flags)?;
+                }
             }
         }
 
         if self.flags.contains(ClientFlags::MODE_STICK) && !mode_flags.contains(ClientFlags::MODE_STICK) {
             let mut visible: u8 = 0;
 
-            for view in subtle.views.iter() {
-                if view.tags.contains(self.tags) {
+            for view in subtle.views.borrow().iter() {
+                if view.tags.get().contains(self.tags) {
                     visible += 1;
                 }
             }
 
             if 0 == visible {
-                self.tag(subtle,0, mode_flags)?;
+                self.tag(subtle, DEFAULT_TAG_IDX, mode_flags)?;
             }
         }
 
-        // EWMH: Tags
-        let data: [u32; 1] = [self.tags.bits()];
+        // Fall back to the default tag to keep the client visible somewhere
+        if self.tags.is_empty() {
+            self.tag(subtle, DEFAULT_TAG_IDX, mode_flags)?;
+        }
 
-        conn.change_property32(PropMode::REPLACE, self.win,
-                               atoms.SUBTLE_CLIENT_TAGS, AtomEnum::CARDINAL, &data)?.check()?;
+        // EWMH: Tags
+        effect::apply(subtle, Effect::SetProperty { win: self.win, prop: Prop::ClientTags,
+            data: vec![self.tags.bits()] })?;
 
         debug!("{}: client={}, mode_flags={:?}", function_name!(), self, mode_flags);
 
         Ok(())
     }
 
+    /// Add or remove a single tag of this client at runtime
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `tag_idx` - Tag index
+    /// * `is_add` - Whether to add or remove the tag
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn change_tag(&mut self, subtle: &Subtle, tag_idx: usize, is_add: bool) -> Result<()> {
+        ignore_if_dead!(self);
+
+        if subtle.tags.borrow().get(tag_idx).is_none_or(|tag| tag.is_dead()) {
+            return Err(anyhow!("Tag {} not found", tag_idx));
+        }
+
+        self.tags = calc_tag_change(self.tags, tag_idx, is_add);
+
+        // EWMH: Tags
+        effect::apply(subtle, Effect::SetProperty { win: self.win, prop: Prop::ClientTags,
+            data: vec![self.tags.bits()] })?;
+
+        debug!("{}: client={}, tag_idx={}, add={}", function_name!(), self, tag_idx, is_add);
+
+        Ok(())
+    }
+
+    /// Add tags of a view to this client or remove them again
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `view_tags` - Tags of the view
+    /// * `is_pin` - Whether to pin or unpin
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`true`] when the tags changed, [`false`] when refused or otherwise [`anyhow::Error`]
+    pub(crate) fn pin(&mut self, subtle: &Subtle, view_tags: Tagging, is_pin: bool) -> Result<bool> {
+        let Some(tags) = calc_pinned_tags(self.tags, view_tags, is_pin) else {
+            return Ok(false);
+        };
+
+        self.tags = tags;
+
+        // EWMH: Tags and desktop
+        effect::apply(subtle, Effect::SetProperty { win: self.win, prop: Prop::ClientTags,
+            data: vec![self.tags.bits()] })?;
+
+        if let Some(desktop_idx) = calc_wm_desktop(&subtle.views.borrow(), self.tags) {
+            effect::apply(subtle, Effect::SetProperty { win: self.win, prop: Prop::WmDesktop,
+                data: vec![desktop_idx] })?;
+        }
+
+        debug!("{}: client={}, view_tags={:?}, pin={}", function_name!(), self, view_tags, is_pin);
+
+        Ok(true)
+    }
+
+    /// Move this client to the view of the given desktop index
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `desktop_idx` - EWMH desktop index or [`ALL_DESKTOPS`]
+    /// * `focus_screen_idx` - Screen of the focus client to stick to
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn set_desktop(&mut self, subtle: &Subtle, desktop_idx: u32,
+                              focus_screen_idx: Option<isize>) -> Result<()>
+    {
+        ignore_if_dead!(self);
+
+        let conn = subtle.conn.get().unwrap();
+        let atoms = subtle.atoms.get().unwrap();
+
+        let mut mode_flags = ClientFlags::MODE_STICK;
+
+        if ALL_DESKTOPS == desktop_idx {
+            if !self.flags.contains(ClientFlags::MODE_STICK) {
+                self.toggle(subtle, &mut mode_flags, true, focus_screen_idx)?;
+            }
+        } else if let Some(tags) = calc_desktop_tags(&subtle.views.borrow(), desktop_idx) {
+            // Leave all desktops
+            if self.flags.contains(ClientFlags::MODE_STICK) {
+                self.toggle(subtle, &mut mode_flags, true, focus_screen_idx)?;
+            }
+
+            self.tags = tags;
+
+            // EWMH: Tags
+            conn.change_property32(PropMode::REPLACE, self.win, atoms.SUBTLE_CLIENT_TAGS,
+                                   AtomEnum::CARDINAL, &[self.tags.bits()])?.check()?;
+        } else {
+            return Ok(());
+        }
+
+        // EWMH: Desktop
+        conn.change_property32(PropMode::REPLACE, self.win, atoms._NET_WM_DESKTOP,
+                               AtomEnum::CARDINAL, &[desktop_idx])?.check()?;
+
+        debug!("{}: client={}, desktop_idx={}", function_name!(), self, desktop_idx);
+
+        Ok(())
+    }
+
     /// Update and re-arrange this client
     ///
     /// # Arguments
@@ -1064,32 +1563,24 @@ impl Client {
     pub(crate) fn arrange(&mut self, subtle: &Subtle, gravity_idx: isize, screen_idx: isize) -> Result<()> {
         ignore_if_dead!(self);
 
-        let conn = subtle.conn.get().unwrap();
-        let atoms = subtle.atoms.get().unwrap();
-
         let screen = subtle.screens.get(screen_idx as usize)
             .context("Screen not found?")?;
 
         // Check flags
         if self.flags.intersects(ClientFlags::MODE_FULL) {
-            let mut aux = ConfigureWindowAux::default();
-
             // Use all screens in zaphod mode
-            if self.flags.contains(ClientFlags::MODE_ZAPHOD) {
-                aux = aux.x(0)
-                    .y(0)
-                    .width(subtle.width as u32)
-                    .height(subtle.height as u32)
-                    .stack_mode(StackMode::ABOVE);
-            } else if let Some(screen) = subtle.screens.get(self.screen_idx as usize) {
-                aux = aux.x(screen.base.x as i32)
-                    .y(screen.base.y as i32)
-                    .width(screen.base.width as u32)
-                    .height(screen.base.height as u32)
-                    .stack_mode(StackMode::ABOVE);
-            }
+            let full_geom = if self.flags.contains(ClientFlags::MODE_ZAPHOD) {
+                Some(Rectangle { x: 0, y: 0, width: subtle.width, height: subtle.height })
+            } else {
+                subtle.screens.get(self.screen_idx as usize).map(|screen| screen.base)
+            };
+
+            if let Some(geom) = full_geom {
+                effect::apply(subtle, Effect::ConfigureAbove { win: self.win, x: geom.x, y: geom.y,
+                    width: geom.width, height: geom.height })?;
 
-            conn.configure_window(self.win, &aux)?.check()?;
+                corner::raise(subtle)?;
+            }
         } else if self.flags.intersects(ClientFlags::MODE_FLOAT) {
             if self.flags.intersects(ClientFlags::ARRANGE)
                 || (-1 != screen_idx && self.screen_idx != screen_idx)
@@ -1100,6 +1591,7 @@ impl Client {
                     if screen_idx != self.screen_idx {
                         self.geom.x = self.geom.x - old_screen.geom.x + screen.geom.x;
                         self.geom.y = self.geom.y - old_screen.geom.y + screen.geom.y;
+                        self.translate_saved_geom(subtle, screen_idx);
                         self.screen_idx = screen_idx;
                     }
                 }
@@ -1107,11 +1599,7 @@ impl Client {
                 // Finally resize window
                 self.resize(subtle, &screen.geom, true)?;
 
-                conn.configure_window(self.win, &ConfigureWindowAux::default()
-                    .x(self.geom.x as i32)
-                    .y(self.geom.y as i32)
-                    .width(self.geom.width as u32)
-                    .height(self.geom.height as u32))?.check()?;
+                effect::apply(subtle, self.configure_effect())?;
             }
         } else if self.flags.intersects(ClientFlags::TYPE_DESKTOP | ClientFlags::TYPE_DOCK) {
             if self.flags.intersects(ClientFlags::TYPE_DESKTOP) {
@@ -1119,48 +1607,34 @@ impl Client {
             }
 
             // Just use screen size for desktop windows
-            conn.configure_window(self.win, &ConfigureWindowAux::default()
-                .x(self.geom.x as i32)
-                .y(self.geom.y as i32)
-                .width(self.geom.width as u32)
-                .height(self.geom.height as u32))?.check()?;
+            effect::apply(subtle, self.configure_effect())?;
 
             //XLowerWindow() // TODO
         } else {
             if self.flags.intersects(ClientFlags::ARRANGE) || self.gravity_idx != gravity_idx
                 || self.screen_idx != screen_idx
             {
-                let old_gravity_id = self.gravity_idx;
-                let old_screen_id = self.screen_idx;
-
                 // Set values
                 if -1 != screen_idx {
+                    self.translate_saved_geom(subtle, screen_idx);
                     self.screen_idx = screen_idx;
                 }
 
                 if -1 != gravity_idx {
+                    let is_changed = self.gravity_idx != gravity_idx;
+
                     self.gravity_idx = gravity_idx;
                     self.gravities[screen.view_idx.get() as usize] = gravity_idx as usize;
-                }
 
-                // Gravity tiling
-                let maybe_old_gravity = subtle.gravities.get(old_gravity_id as usize);
-
-                if -1 != old_screen_id && (subtle.flags.contains(SubtleFlags::GRAVITY_TILING)
-                    || maybe_old_gravity.is_some() &&
-                    maybe_old_gravity.unwrap().flags.contains(GravityFlags::HORZ | GravityFlags::VERT))
-                {
-                    self.gravity_tile(subtle, old_gravity_id, old_screen_id)?;
+                    if is_changed {
+                        session::record_gravities(subtle, self);
+                    }
                 }
 
                 let maybe_gravity = subtle.gravities.get(gravity_idx as usize);
 
-                if subtle.flags.contains(SubtleFlags::GRAVITY_TILING)
-                    && (maybe_gravity.is_some()
-                    && maybe_gravity.unwrap().flags.contains(GravityFlags::HORZ | GravityFlags::VERT))
-                {
-                    self.gravity_tile(subtle, gravity_idx, if -1 == screen_idx { 0 } else { screen_idx })?;
-                } else {
+                // Tiled gravities are arranged by [`gravity_tile`] once the clients are released
+                if !maybe_gravity.is_some_and(|gravity| is_tiled(subtle, gravity)) {
                     let mut geom = screen.geom;
 
                     // Set size for bounds
@@ -1178,16 +1652,54 @@ impl Client {
         }
 
         // EWMH: Gravity
-        conn.change_property32(PropMode::REPLACE, self.win, atoms.SUBTLE_CLIENT_GRAVITY,
-                               AtomEnum::CARDINAL,&[self.gravity_idx as u32])?.check()?;
+        effect::apply(subtle, Effect::SetProperty { win: self.win, prop: Prop::ClientGravity,
+            data: vec![self.gravity_idx as u32] })?;
+
+        self.update_border(subtle, subtle.focus_history.first() == Some(self.win))?;
 
-        conn.flush()?;
+        effect::flush(subtle)?;
 
         debug!("{}: client={}", function_name!(), self);
 
         Ok(())
     }
 
+    /// Restore saved geometry or center it when it doesn't fit on the screen anymore
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    pub(crate) fn restore_saved_geom(&mut self, subtle: &Subtle) {
+        if let Some(saved_geom) = self.saved_geom.take()
+            && let Some(screen) = subtle.screens.get(self.screen_idx as usize)
+        {
+            self.geom = calc_restored_geom(&screen.geom, &saved_geom, self.get_border_width(subtle) as u16,
+                (self.min_width, self.min_height), subtle.dialog_min_visible, subtle.shrink_oversized_dialogs);
+
+            self.flags.insert(ClientFlags::ARRANGE);
+        }
+
+        debug!("{}: client={}", function_name!(), self);
+    }
+
+    /// Translate saved geometry before the client moves to another screen
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `screen_idx` - Index of the new screen
+    pub(crate) fn translate_saved_geom(&mut self, subtle: &Subtle, screen_idx: isize) {
+        if screen_idx != self.screen_idx
+            && let Some(saved_geom) = self.saved_geom
+            && let Some(old_screen) = subtle.screens.get(self.screen_idx as usize)
+            && let Some(new_screen) = subtle.screens.get(screen_idx as usize)
+        {
+            self.saved_geom = Some(calc_migrated_geom(saved_geom, old_screen.base, new_screen.base));
+        }
+
+        debug!("{}: client={}, screen_idx={}", function_name!(), self, screen_idx);
+    }
+
     /// Resize client window
     ///
     /// # Arguments
@@ -1218,22 +1730,22 @@ impl Client {
             }
 
             // Check whether window fits into bounds
-            let max_x = bounds.x + bounds.width as i16;
-            let max_y = bounds.y + bounds.height as i16;
+            let max_x = bounds.x as i32 + bounds.width as i32;
+            let max_y = bounds.y as i32 + bounds.height as i32;
 
             // Check x and center
-            if geom.x < bounds.x || geom.x > max_x || geom.x + geom.width as i16  > max_x {
+            if geom.x < bounds.x || geom.x as i32 + geom.width as i32 > max_x {
                 if self.flags.contains(ClientFlags::MODE_FLOAT) {
-                    geom.x = bounds.x + ((bounds.width as i16 - geom.width as i16) / 2);
+                    geom.x = calc_centered(bounds.x as i32, bounds.width as i32, geom.width as i32);
                 } else {
                     geom.x = bounds.x;
                 }
             }
 
             // Check y and center
-            if geom.y < bounds.y || geom.y > max_y || geom.y + geom.height as i16 > max_y {
+            if geom.y < bounds.y || geom.y as i32 + geom.height as i32 > max_y {
                 if self.flags.contains(ClientFlags::MODE_FLOAT) {
-                    geom.y = bounds.y + ((bounds.height as i16 - geom.height as i16) / 2);
+                    geom.y = calc_centered(bounds.y as i32, bounds.height as i32, geom.height as i32);
                 } else {
                     geom.y = bounds.y;
                 }
@@ -1273,23 +1785,14 @@ impl Client {
     pub(crate) fn snap(&self, subtle: &Subtle, screen: &Screen, geom: &mut Rectangle) -> Result<()> {
         ignore_if_dead!(self);
 
-        // Snap to screen border when value is in snap margin - X axis
-        if (screen.geom.x - geom.x).abs() <= subtle.snap_size as i16 {
-            geom.x = screen.geom.x + self.get_border_width(subtle);
-        } else if ((screen.geom.x + screen.geom.width as i16)
-            - (geom.x + geom.width as i16 + self.get_border_width(subtle))).abs() <= subtle.snap_size as i16
-        {
-            geom.x = screen.geom.x + (screen.geom.width - geom.width) as i16 - self.get_border_width(subtle);
+        // Snap size 0 disables snapping
+        if 0 == subtle.snap_size {
+            return Ok(());
         }
 
-        // Snap to screen border when value is in snap margin - > Y Axis
-        if (screen.geom.y - geom.y).abs() <= subtle.snap_size as i16 {
-            geom.y = screen.geom.y + self.get_border_width(subtle);
-        } else if ((screen.geom.y + screen.geom.height as i16)
-            - (geom.y + geom.height as i16 + self.get_border_width(subtle))).abs() <= subtle.snap_size as i16
-        {
-             geom.y = screen.geom.y + (screen.geom.height - geom.height) as i16 - self.get_border_width(subtle);
-        }
+        *geom = calc_screen_snap(geom, self.get_border_width(subtle), &screen.geom, subtle.snap_size);
+
+        debug!("{}: client={}, geom={:?}", function_name!(), self, geom);
 
         Ok(())
     }
@@ -1306,13 +1809,10 @@ impl Client {
     pub(crate) fn warp_pointer(&self, subtle: &Subtle) -> Result<()> {
         ignore_if_dead!(self);
 
-        let conn = subtle.conn.get().unwrap();
-
-        let default_screen = &conn.setup().roots[subtle.screen_num];
-
-        conn.warp_pointer(NONE, default_screen.root, 0, 0, 0, 0,
-                          self.geom.x + self.geom.width as i16 / 2,
-                          self.geom.y + self.geom.height as i16 / 2)?.check()?;
+        effect::apply(subtle, Effect::WarpPointer {
+            x: calc_centered(self.geom.x as i32, self.geom.width as i32, 0),
+            y: calc_centered(self.geom.y as i32, self.geom.height as i32, 0),
+        })?;
 
         debug!("{}: client={}", function_name!(), self);
 
@@ -1326,19 +1826,34 @@ impl Client {
     /// * `subtle` - Global state object
     /// * `drag_mode` - Dragging mode
     /// * `drag_dir` - Dragging direction
+    /// * `transients` - Transients to move along during the drag
+    /// * `siblings` - Outer geometries of clients to snap to
+    /// * `snap_modifier` - Modifier to bypass snapping while held
     ///
     /// # Returns
     ///
     /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-    pub(crate) fn drag(&mut self, subtle: &Subtle, drag_mode: DragMode, drag_dir: DirectionOrder) -> Result<()> {
+    pub(crate) fn drag(&mut self, subtle: &Subtle, drag_mode: DragMode, drag_dir: DirectionOrder,
+                       transients: &[(Window, Rectangle)], siblings: &[Rectangle],
+                       snap_modifier: ModMask) -> Result<()>
+    {
         ignore_if_dead!(self);
 
+        // Fixed clients can't be resized, desktop and dock not dragged at all
+        if !is_draggable(self.flags, drag_mode) {
+            return Ok(());
+        }
+
         let conn = subtle.conn.get().unwrap();
         let query_reply = conn.query_pointer(self.win)?.reply()?;
 
+        // Position of the outer border corner like X expects it
+        let (x, y) = calc_drag_origin(query_reply.root_x, query_reply.root_y,
+                                      query_reply.win_x, query_reply.win_y, self.get_border_width(subtle));
+
         let mut geom = Rectangle {
-            x: query_reply.root_x - query_reply.win_x,
-            y: query_reply.root_y - query_reply.win_y,
+            x,
+            y,
             width: self.geom.width,
             height: self.geom.height,
         };
@@ -1346,97 +1861,118 @@ impl Client {
         let screen = subtle.screens.get(self.screen_idx as usize)
             .context("Can't get screen")?;
 
-        // Select starting edge
-        let drag_edge = if query_reply.win_x < (geom.width / 2) as i16 {
-                DragEdge::LEFT } else { DragEdge::RIGHT }
-            | if query_reply.win_y < (geom.height / 2) as i16 {
-                DragEdge::TOP } else { DragEdge::BOTTOM };
-
-        // Set variables according to mode
-        let cursor = match drag_mode {
-            DragMode::MOVE => subtle.move_cursor,
-            DragMode::RESIZE => subtle.resize_cursor,
-        };
-
-        // Grab pointer and server
-        conn.grab_pointer(true, self.win, EventMask::BUTTON_PRESS
-            | EventMask::BUTTON_RELEASE
-            | EventMask::POINTER_MOTION, GrabMode::ASYNC, GrabMode::ASYNC,
-                          NONE, cursor, CURRENT_TIME)?;
-        conn.grab_server()?;
-
         match drag_dir {
-            DirectionOrder::Up => {
-                if DragMode::RESIZE == drag_mode {
-                    geom.y -= self.height_inc as i16;
-                    geom.height += self.height_inc;
-                } else {
-                    geom.y -= subtle.step_size;
-                }
-
-                self.snap(subtle, screen, &mut geom)?;
-                self.apply_size_hints(subtle, &screen.geom,
-                                      false, false, &mut geom);
+            DirectionOrder::Mouse => {
+                // Select starting edge
+                let drag_edge = if query_reply.win_x < (geom.width / 2) as i16 {
+                        DragEdge::LEFT } else { DragEdge::RIGHT }
+                    | if query_reply.win_y < (geom.height / 2) as i16 {
+                        DragEdge::TOP } else { DragEdge::BOTTOM };
+
+                // Set variables according to mode
+                let cursor = match drag_mode {
+                    DragMode::MOVE => subtle.move_cursor,
+                    DragMode::RESIZE => subtle.resize_cursor,
+                };
+
+                // Grab pointer and server just for interactive drags
+                conn.grab_pointer(true, self.win, EventMask::BUTTON_PRESS
+                    | EventMask::BUTTON_RELEASE
+                    | EventMask::POINTER_MOTION, GrabMode::ASYNC, GrabMode::ASYNC,
+                                  NONE, cursor, subtle.last_timestamp.get())?;
+                conn.grab_server()?;
+
+                drag_interactively(subtle, screen, self, &mut geom, &query_reply, drag_mode, drag_edge,
+                                   transients, siblings, snap_modifier)?;
+
+                // Remove grabs
+                conn.ungrab_pointer(subtle.last_timestamp.get())?;
+                conn.ungrab_server()?;
             },
-            DirectionOrder::Right => {
-                if DragMode::RESIZE == drag_mode {
-                    geom.height += self.height_inc;
-                } else {
-                    geom.y += subtle.step_size;
-                }
+            _ => {
+                geom = calc_drag_step(drag_mode, drag_dir, subtle.step_size,
+                                      self.width_inc, self.height_inc, &geom);
 
-                self.snap(subtle, screen, &mut geom)?;
-                self.apply_size_hints(subtle, &screen.geom,
-                                      false, false, &mut geom);
-            },
-            DirectionOrder::Down => {
-                if DragMode::RESIZE == drag_mode {
-                    geom.x -= self.width_inc as i16;
-                    geom.width += self.width_inc;
-                } else {
-                    geom.x -= subtle.step_size;
-                }
+                // Pointer query carries the modifiers of the key event
+                let snap = is_snapping(u16::from(query_reply.mask), snap_modifier, subtle.snap_size);
 
-                self.snap(subtle, screen, &mut geom)?;
-                self.apply_size_hints(subtle, &screen.geom,
-                                      false, false, &mut geom);
-            },
-            DirectionOrder::Left => {
-                if DragMode::RESIZE == drag_mode {
-                    geom.x -= self.width_inc as i16;
-                    geom.width += self.width_inc;
-                } else {
-                    geom.x -= subtle.step_size;
+                if snap {
+                    self.snap(subtle, screen, &mut geom)?;
                 }
-
-                self.snap(subtle, screen, &mut geom)?;
                 self.apply_size_hints(subtle, &screen.geom,
                                       false, false, &mut geom);
             },
-            DirectionOrder::Mouse => {
-                drag_interactively(subtle, screen, self, &mut geom, &query_reply, drag_mode, drag_edge)?;
-
-                // Subtract border width
-                if !self.flags.intersects(ClientFlags::MODE_BORDERLESS) {
-                    geom.x -= subtle.clients_style.border.top;
-                    geom.y -= subtle.clients_style.border.top;
-                }
-            }
         }
 
         // Finally move and resize window
         self.move_resize(subtle, &geom, false)?;
+        self.reconcile_screen(subtle)?;
 
-        // Remove grabs
-        conn.ungrab_pointer(CURRENT_TIME)?;
-        conn.ungrab_server()?;
+        debug!("{}: client={}, drag_mode={:?}", function_name!(), self, drag_mode);
 
-        println!("{}: client={}", function_name!(), self);
+        Ok(())
+    }
+
+    /// Apply geometry of a keyboard drag step or commit
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `geom` - Geometry to apply
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn apply_drag_geom(&mut self, subtle: &Subtle, geom: &Rectangle) -> Result<()> {
+        ignore_if_dead!(self);
+
+        self.move_resize(subtle, geom, false)?;
+        self.reconcile_screen(subtle)?;
+
+        debug!("{}: client={}", function_name!(), self);
 
         Ok(())
     }
 
 
+    /// Update screen of client when the geometry mainly is on another screen
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn reconcile_screen(&mut self, subtle: &Subtle) -> Result<()> {
+        if self.flags.intersects(ClientFlags::MODE_STICK_SCREEN
+            | ClientFlags::TYPE_DOCK | ClientFlags::TYPE_DESKTOP)
+        {
+            return Ok(());
+        }
+
+        let screen_geoms: Vec<Rectangle> = subtle.screens.iter()
+            .map(|screen| screen.base)
+            .collect();
+
+        if let Some(screen_idx) = screen::find_by_overlap(&self.geom, &screen_geoms)
+            && screen_idx as isize != self.screen_idx
+        {
+            let conn = subtle.conn.get().unwrap();
+            let atoms = subtle.atoms.get().unwrap();
+
+            self.translate_saved_geom(subtle, screen_idx as isize);
+            self.screen_idx = screen_idx as isize;
+
+            conn.change_property32(PropMode::REPLACE, self.win, atoms.SUBTLE_CLIENT_SCREEN,
+                                   AtomEnum::CARDINAL, &[screen_idx as u32])?.check()?;
+
+            debug!("{}: client={}, screen_idx={}", function_name!(), self, screen_idx);
+        }
+
+        Ok(())
+    }
+
     /// Map client window on display
     ///
     /// # Arguments
@@ -1447,9 +1983,38 @@ impl Client {
     ///
     /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
     pub(crate) fn map(&self, subtle: &Subtle) -> Result<()> {
+        effect::apply(subtle, Effect::Map(self.win))?;
+
+        debug!("{}: client={}", function_name!(), self);
+
+        Ok(())
+    }
+
+    /// Send synthetic configure notify with current geometry (see ICCCM 4.1.5)
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn send_configure(&self, subtle: &Subtle) -> Result<()> {
         let conn = subtle.conn.get().unwrap();
 
-        conn.map_window(self.win)?.check()?;
+        conn.send_event(false, self.win, EventMask::STRUCTURE_NOTIFY, ConfigureNotifyEvent {
+            response_type: CONFIGURE_NOTIFY_EVENT,
+            sequence: 0,
+            event: self.win,
+            window: self.win,
+            above_sibling: NONE,
+            x: self.geom.x,
+            y: self.geom.y,
+            width: self.geom.width,
+            height: self.geom.height,
+            border_width: self.get_border_width(subtle) as u16,
+            override_redirect: false,
+        })?.check()?;
 
         debug!("{}: client={}", function_name!(), self);
 
@@ -1466,9 +2031,7 @@ impl Client {
     ///
     /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
     pub(crate) fn unmap(&self, subtle: &Subtle) -> Result<()> {
-        let conn = subtle.conn.get().unwrap();
-
-        conn.unmap_window(self.win)?.check()?;
+        effect::apply(subtle, Effect::Unmap(self.win))?;
 
         debug!("{}: client={}", function_name!(), self);
 
@@ -1489,6 +2052,19 @@ impl Client {
             || self.flags.intersects(ClientFlags::TYPE_DESKTOP | ClientFlags::MODE_STICK)
     }
 
+    /// Whether client is alive and visible and can receive the focus
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    ///
+    /// # Returns
+    ///
+    /// Either [`true`] if the client can be focused or otherwise [`false`]
+    pub(crate) fn is_focusable(&self, subtle: &Subtle) -> bool {
+        self.is_alive() && self.is_visible(subtle)
+    }
+
     /// Whether client is marked as dead (aka ignore further events)
     ///
     /// # Arguments
@@ -1549,7 +2125,7 @@ impl Client {
         // Honor window preferences (see ICCCM 4.1.2.7, 4.2.8.1)
         if self.flags.intersects(ClientFlags::CLOSE) {
            ewmh::send_message(subtle, self.win, atoms.WM_PROTOCOLS,
-                              &[atoms.WM_DELETE_WINDOW, CURRENT_TIME, 0, 0, 0])?;
+                              &[atoms.WM_DELETE_WINDOW, subtle.last_timestamp.get(), 0, 0, 0])?;
         } else {
             let _screen_idx = if let Some(focus_client) = subtle.find_focus_client()
                 && focus_client.win == self.win { self.screen_idx } else { -1 };
@@ -1582,6 +2158,14 @@ impl Client {
         let conn = subtle.conn.get().unwrap();
         let atoms = subtle.atoms.get().unwrap();
 
+        // Remember float geometry and gravities for next session
+        session::record_geom(subtle, self);
+        session::record_gravities(subtle, self);
+
+        if let Err(err) = session::save(subtle) {
+            warn!("Failed to save session: {}", err);
+        }
+
         // Remove _NET_WM_STATE (see EWMH 1.3)
         conn.delete_property(self.win, atoms._NET_WM_STATE)?;
 
@@ -1589,22 +2173,6 @@ impl Client {
         conn.change_window_attributes(self.win, &ChangeWindowAttributesAux::default()
             .event_mask(EventMask::NO_EVENT))?;
 
-        // Remove client tags from urgent tags
-        if self.flags.contains(ClientFlags::MODE_URGENT) {
-            subtle.urgent_tags.replace(subtle.urgent_tags.get() - self.tags);
-        }
-
-        // Tile remaining clients if necessary
-        if self.is_visible(subtle) {
-            if let Some(gravity) = subtle.gravities.get(self.gravity_idx as usize) {
-               if subtle.flags.contains(SubtleFlags::GRAVITY_TILING)
-                   || gravity.flags.contains(GravityFlags::HORZ | GravityFlags::VERT)
-               {
-                   self.gravity_tile(subtle, self.gravity_idx, self.screen_idx)?;
-               }
-            }
-        }
-
         debug!("{}: client={}", function_name!(), self);
 
         Ok(())
@@ -1622,114 +2190,44 @@ impl Client {
     ///
     /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
     fn move_resize(&mut self, subtle: &Subtle, geom: &Rectangle, apply_border_and_gaps: bool) -> Result<()> {
-        let conn = subtle.conn.get().unwrap();
-
         // Update border and gap
         if apply_border_and_gaps {
-            self.geom.x += subtle.clients_style.margin.left;
-            self.geom.y += subtle.clients_style.margin.left;
-            self.geom.width -= (2 * self.get_border_width(subtle) + subtle.clients_style.margin.left
-                + subtle.clients_style.margin.right) as u16;
-            self.geom.height -= (2 * self.get_border_width(subtle) + subtle.clients_style.margin.top
-                + subtle.clients_style.margin.bottom) as u16;
+            let margin = subtle.clients_margin.get();
+            let border_width = 2 * self.get_border_width(subtle) as i32;
+
+            self.geom.x = checked_to_i16(self.geom.x as i32 + margin.left as i32);
+            self.geom.y = checked_to_i16(self.geom.y as i32 + margin.top as i32);
+            self.geom.width = calc_shrunk(self.geom.width,
+                                          border_width + margin.left as i32 + margin.right as i32, MIN_WIDTH);
+            self.geom.height = calc_shrunk(self.geom.height,
+                                           border_width + margin.top as i32 + margin.bottom as i32, MIN_HEIGHT);
         }
 
         self.resize(subtle, geom, true)?;
 
-        let aux = ConfigureWindowAux::default()
-            .x(self.geom.x as i32)
-            .y(self.geom.y as i32)
-            .width(self.geom.width as u32)
-            .height(self.geom.height as u32);
+        effect::apply(subtle, self.configure_effect())?;
 
-        conn.configure_window(self.win, &aux)?.check()?;
+        // Clients are borrowed here, so publish later
+        subtle.client_geoms_changed.set(true);
 
         debug!("{}: client={}", function_name!(), self);
 
         Ok(())
     }
 
-    /// Tile client windows with the same gravity
-    ///
-    /// # Arguments
-    ///
-    /// * `subtle` - Global state object
-    /// * `gravity_id` - Gravity index
-    /// * `screen_id` - Screen index
+    /// Convenience method to create the effect to move and resize to the current geometry
     ///
     /// # Returns
     ///
-    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-    fn gravity_tile(&self, subtle: &Subtle, gravity_id: isize, screen_id: isize) -> Result<()> {
-        let gravity = subtle.gravities.get(gravity_id as usize)
-            .ok_or(anyhow!("Gravity not found"))?;
-        let screen = subtle.screens.get(screen_id as usize)
-            .ok_or(anyhow!("Screen not found"))?;
-
-        // Pass 1: Count clients with this gravity
-        let mut used = 0u16;
-
-        for client in subtle.clients.borrow().iter() {
-            if client.gravity_idx == gravity_id && client.screen_idx == screen_id
-                && subtle.visible_tags.get().contains(client.tags)
-                && !client.flags.contains(ClientFlags::MODE_FLOAT | ClientFlags::MODE_FULL)
-            {
-                used += 1;
-            }
-        }
-
-        if 0 == used {
-            return Ok(());
-        }
-
-        // Calculate tiled gravity value and rounding fix
-        let mut geom: Rectangle = Rectangle::default();
-
-        gravity.apply_size(&screen.geom, &mut geom);
-
-        let mut calc = 0;
-        let mut round_fix = 0;
-
-        if gravity.flags.contains(GravityFlags::HORZ) {
-            calc = geom.width / used;
-            round_fix = geom.width - calc * used;
-        } else if gravity.flags.contains(GravityFlags::VERT) {
-            calc = geom.height / used;
-            round_fix = geom.height - calc * used;
-        }
-
-        // Pass 2: Update geometry of every client with this gravity
-        let mut pos = 0;
-
-        for (client_idx, client) in subtle.clients.borrow().iter().enumerate() {
-            if client.gravity_idx == gravity_id && client.screen_idx == screen_id
-                && subtle.visible_tags.get().contains(client.tags)
-                && !client.flags.contains(ClientFlags::MODE_FLOAT | ClientFlags::MODE_FULL)
-            {
-                let mut geom = Rectangle::default();
-
-                if gravity.flags.contains(GravityFlags::HORZ) {
-                    geom.x += (pos * calc) as i16;
-                    geom.width = if pos == used { calc + round_fix } else { calc };
-
-                    pos += 1;
-                } else if gravity.flags.contains(GravityFlags::VERT) {
-                    geom.y += (pos * calc) as i16;
-                    geom.height = if pos == used { calc + round_fix } else { calc };
-
-                    pos +=1;
-                }
-
-                // Finally update client
-                if let Some(mut_client) = subtle.clients.borrow_mut().get_mut(client_idx) {
-                    mut_client.geom = geom;
-
-                    mut_client.move_resize(subtle, &screen.geom, true)?;
-                }
-            }
+    /// The [`Effect`] to configure the client window
+    fn configure_effect(&self) -> Effect {
+        Effect::Configure {
+            win: self.win,
+            x: self.geom.x,
+            y: self.geom.y,
+            width: self.geom.width,
+            height: self.geom.height,
         }
-
-        Ok(())
     }
 
     /// Convenience method to get the border width of the client
@@ -1758,63 +2256,98 @@ impl Client {
     /// * `adjust_x` - Whether to update x position
     /// * `adjust_y` - Whether to update y position
     /// * `geom` - Geometry to update
-    fn apply_size_hints(&self, subtle: &Subtle, bounds: &Rectangle,
-                        adjust_x: bool, adjust_y: bool, geom: &mut Rectangle)
+    pub(crate) fn apply_size_hints(&self, subtle: &Subtle, bounds: &Rectangle,
+                                   adjust_x: bool, adjust_y: bool, geom: &mut Rectangle)
     {
+        // Floating clients honor size hints when enabled globally
         if !self.flags.contains(ClientFlags::MODE_FIXED)
             && (self.flags.contains(ClientFlags::MODE_RESIZE)
-            || self.flags.contains(ClientFlags::MODE_FLOAT | ClientFlags::MODE_RESIZE))
+            || (self.flags.contains(ClientFlags::MODE_FLOAT) && subtle.flags.contains(SubtleFlags::RESIZE)))
         {
-            let border_width = (2 * self.get_border_width(subtle)
-                + subtle.clients_style.margin.left
-                + subtle.clients_style.margin.right) as u16;
+            let margin = subtle.clients_margin.get();
+            let border_width = 2 * self.get_border_width(subtle) as i32 + margin.left as i32 + margin.right as i32;
 
             // Calculate max width and max height for bounds
             let max_width = if -1 == self.max_width {
-                bounds.width - border_width } else { self.max_width as u16 };
+                calc_shrunk(bounds.width, border_width, MIN_WIDTH) } else { checked_to_u16(self.max_width as i32) };
             let max_height = if -1 == self.max_height {
-                bounds.height - border_width } else { self.max_height as u16 };
+                calc_shrunk(bounds.height, border_width, MIN_HEIGHT) } else { checked_to_u16(self.max_height as i32) };
 
-            // Limit width and height
-            if geom.width < self.min_width {
-                geom.width = self.min_width;
-            }
+            self.calc_hinted_geom(max_width, max_height, adjust_x, adjust_y,
+                                  self.flags.contains(ClientFlags::MODE_IGNORE_HINTS), geom);
+        }
+    }
 
-            if geom.width > max_width {
-                geom.width = max_width;
-            }
+    /// Limit geometry to size hints
+    ///
+    /// # Arguments
+    ///
+    /// * `max_width` - Max width within bounds
+    /// * `max_height` - Max height within bounds
+    /// * `adjust_x` - Whether to update x position
+    /// * `adjust_y` - Whether to update y position
+    /// * `ignore_increments` - Whether to skip rounding to size increments
+    /// * `geom` - Geometry to update
+    pub(crate) fn calc_hinted_geom(&self, max_width: u16, max_height: u16, adjust_x: bool, adjust_y: bool,
+                                   ignore_increments: bool, geom: &mut Rectangle)
+    {
+        // Limit width and height, max wins over inverted min
+        if geom.width < self.min_width {
+            geom.width = self.min_width;
+        }
 
-            if geom.height < self.min_height {
-               geom.height = self.min_height;
-            }
+        if geom.width > max_width {
+            geom.width = max_width;
+        }
 
-            if geom.height > max_height {
-                geom.height = max_height;
-            }
+        if geom.height < self.min_height {
+           geom.height = self.min_height;
+        }
+
+        if geom.height > max_height {
+            geom.height = max_height;
+        }
 
-            // Adjust based on increment values (see ICCCM 4.1.2.3)
-            let diff_width = (geom.width - self.base_width) % self.width_inc;
-            let diff_height = (geom.height - self.base_height) % self.height_inc;
+        // Adjust based on increment values (see ICCCM 4.1.2.3)
+        if !ignore_increments {
+            let diff_width = geom.width.saturating_sub(self.base_width) % self.width_inc.max(1);
+            let diff_height = geom.height.saturating_sub(self.base_height) % self.height_inc.max(1);
 
             // Adjust x and/or y
             if adjust_x {
-                geom.x += diff_width as i16;
+                geom.x = checked_to_i16(geom.x as i32 + diff_width as i32);
             }
 
             if adjust_y {
-                geom.y += diff_height as i16;
+                geom.y = checked_to_i16(geom.y as i32 + diff_height as i32);
             }
 
             geom.width -= diff_width;
             geom.height -= diff_height;
+        }
 
-            // Check aspect ratios
-            if 0f32 < self.min_ratio && self.geom.height as f32 * self.min_ratio > self.geom.width as f32 {
-                geom.width = (geom.height as f32 * self.min_ratio) as u16;
-            }
+        geom.width = geom.width.max(MIN_WIDTH);
+        geom.height = geom.height.max(MIN_HEIGHT);
+
+        // Check aspect ratios of the candidate geometry
+        let ratio = if 0f32 < self.min_ratio && geom.height as f32 * self.min_ratio > geom.width as f32 {
+            Some(self.min_ratio)
+        } else if 0f32 < self.max_ratio && geom.height as f32 * self.max_ratio < geom.width as f32 {
+            Some(self.max_ratio)
+        } else {
+            None
+        };
+
+        if let Some(ratio) = ratio {
+            let min_width = self.min_width.max(MIN_WIDTH).min(max_width);
+            let min_height = self.min_height.max(MIN_HEIGHT).min(max_height);
 
-            if 0f32 < self.max_ratio && self.geom.height as f32 * self.max_ratio < self.geom.width as f32 {
-                geom.width = (geom.height as f32 * self.max_ratio) as u16;
+            geom.width = (geom.height as f32 * ratio).round() as u16;
+
+            // Adjust height instead when the width leaves its limits
+            if !(min_width..=max_width).contains(&geom.width) {
+                geom.width = geom.width.clamp(min_width, max_width);
+                geom.height = ((geom.width as f32 / ratio).round() as u16).clamp(min_height, max_height);
             }
         }
     }
@@ -1913,7 +2446,7 @@ impl Ord for Client {
 /// # Returns
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-fn draw_mask(subtle: &Subtle, geom: &Rectangle) -> Result<()> {
+pub(crate) fn draw_mask(subtle: &Subtle, geom: &Rectangle) -> Result<()> {
     let conn = subtle.conn.get().unwrap();
 
     let default_screen = &conn.setup().roots[subtle.screen_num];
@@ -1928,183 +2461,1689 @@ fn draw_mask(subtle: &Subtle, geom: &Rectangle) -> Result<()> {
     Ok(())
 }
 
-/// Drag client window interactively
-///
-/// # Arguments
-///
-/// * `subtle` - Global state object
-/// * `screen` - Screen to drag on
-/// * `client` - Client to drag
-/// * `geom` - Geometry of the mask
-/// * `query_reply` - Cached query reply
-/// * `drag_mode` - Dragging mode
-/// * `drag_edge` - Dragging starting edge
-///
-/// # Returns
-///
-/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-fn drag_interactively(subtle: &Subtle, screen: &Screen, client: &Client, geom: &mut Rectangle,
-                      query_reply: &QueryPointerReply, drag_mode: DragMode, drag_edge: DragEdge) -> Result<()>
-{
-    let conn = subtle.conn.get().unwrap();
+/// Bookkeeping of the XOR mask that is drawn while dragging
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct MaskState {
+    /// Geometry of the mask currently on screen
+    drawn: Option<Rectangle>,
+}
 
-    let mut fx = 0;
-    let mut fy = 0;
-    let mut dx = 0;
-    let mut dy = 0;
+impl MaskState {
+    /// Move mask to new geometry
+    ///
+    /// # Arguments
+    ///
+    /// * `geom` - Geometry of the mask
+    ///
+    /// # Returns
+    ///
+    /// A [`Vec`] with the geometries to draw, erasing the previous mask first
+    pub(crate) fn show(&mut self, geom: &Rectangle) -> Vec<Rectangle> {
+        let mut geoms: Vec<Rectangle> = self.hide().into_iter().collect();
 
-    // Set starting point
-    if drag_edge.intersects(DragEdge::LEFT) {
-        fx = geom.x + geom.width as i16;
-        dx = query_reply.root_x - client.geom.x;
-    } else if drag_edge.intersects(DragEdge::RIGHT) {
-        fx = geom.x;
-        dx = geom.x + geom.width as i16 - query_reply.root_x;
-    }
+        geoms.push(*geom);
 
-    if drag_edge.intersects(DragEdge::TOP) {
-        fy = geom.y + geom.height as i16;
-        dy = query_reply.root_y - client.geom.y;
-    } else if drag_edge.intersects(DragEdge::BOTTOM) {
-        fy = geom.y;
-        dy = geom.y + geom.height as i16 - query_reply.root_y;
-    }
+        self.drawn = Some(*geom);
 
-    draw_mask(subtle, geom)?;
+        geoms
+    }
 
-    // Start event loop
-    'dragging: loop {
-        if let Ok(event) = conn.wait_for_event() {
-            match event {
-                Event::ButtonRelease(_evt) => {
-                    break 'dragging;
-                },
-                Event::MotionNotify(evt) => {
-                    draw_mask(subtle, geom)?;
+    /// Remove mask
+    ///
+    /// # Returns
+    ///
+    /// A [`Option`] with either [`Some`] geometry to draw to erase the mask or otherwise [`None`]
+    pub(crate) fn hide(&mut self) -> Option<Rectangle> {
+        self.drawn.take()
+    }
 
-                    if DragMode::MOVE == drag_mode {
-                        geom.x = (query_reply.root_x - query_reply.win_x)
-                            - (query_reply.root_x - evt.root_x);
-                        geom.y = (query_reply.root_y - query_reply.win_y)
-                            - (query_reply.root_y - evt.root_y);
+    /// Forget mask after the server repainted the screen
+    ///
+    /// # Returns
+    ///
+    /// A [`Option`] with either [`Some`] geometry to draw again or otherwise [`None`]
+    pub(crate) fn invalidate(&mut self) -> Option<Rectangle> {
+        self.drawn.take()
+    }
 
-                        client.snap(subtle, screen, geom)?;
-                    } else {
-                        // Handle resize based on edge
-                        if drag_edge.intersects(DragEdge::LEFT) {
-                            geom.x = evt.root_x - dx;
-                            geom.width = (evt.root_x + dx) as u16;
-                        } else if drag_edge.intersects(DragEdge::RIGHT) {
-                            geom.x = fx;
-                            geom.width = (evt.root_x - fx + dx) as u16;
-                        }
+    /// Check whether the mask is on screen
+    ///
+    /// # Returns
+    ///
+    /// Either [`true`] if the mask is drawn or otherwise [`false`]
+    pub(crate) fn is_drawn(&self) -> bool {
+        self.drawn.is_some()
+    }
+}
 
-                        if drag_edge.intersects(DragEdge::TOP) {
-                            geom.y = evt.root_y - dy;
-                            geom.height = (fy - evt.root_y + dy) as u16;
-                        } else {
-                            geom.y = fy;
-                            geom.height = (evt.root_y - fy + dy) as u16;
-                        }
+/// Keys of a keyboard drag
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum DragKey {
+    /// Apply the pending geometry
+    Commit,
+    /// Restore the geometry before the drag
+    Cancel,
+    /// Adjust the pending geometry towards the direction
+    Step(DirectionOrder),
+}
 
-                        // Adjust bounds based on edge
-                        client.apply_size_hints(subtle, &screen.geom,
-                                              drag_edge.intersects(DragEdge::LEFT),
-                                              drag_edge.intersects(DragEdge::TOP), geom);
-                    }
+/// Running keyboard move or resize of a client
+#[derive(Debug, Clone)]
+pub(crate) struct KeyboardDrag {
+    /// Window of the dragged client
+    pub(crate) win: Window,
+    /// Dragging mode
+    pub(crate) drag_mode: DragMode,
+    /// Geometry before the drag
+    pub(crate) origin: Rectangle,
+    /// Geometry to apply on commit
+    pub(crate) pending: Rectangle,
+    /// Keycodes of the drag keys
+    pub(crate) keys: Vec<(Keycode, DragKey)>,
+    /// Whether to show an outline instead of applying each step
+    pub(crate) outline: bool,
+    /// Outline bookkeeping
+    pub(crate) mask: MaskState,
+    /// Transients to move along
+    pub(crate) transients: Vec<(Window, Rectangle)>,
+    /// Modifier to bypass snapping while held
+    pub(crate) snap_modifier: ModMask,
+}
 
-                    draw_mask(subtle, geom)?;
-                },
-                _ => {},
-            }
+impl KeyboardDrag {
+    /// Create a new instance
+    ///
+    /// # Arguments
+    ///
+    /// * `win` - Window of the dragged client
+    /// * `drag_mode` - Dragging mode
+    /// * `geom` - Geometry before the drag
+    /// * `keys` - Keycodes of the drag keys
+    /// * `outline` - Whether to show an outline instead of applying each step
+    ///
+    /// # Returns
+    ///
+    /// A new [`KeyboardDrag`]
+    pub(crate) fn new(win: Window, drag_mode: DragMode, geom: Rectangle,
+                      keys: Vec<(Keycode, DragKey)>, outline: bool) -> Self
+    {
+        KeyboardDrag {
+            win,
+            drag_mode,
+            origin: geom,
+            pending: geom,
+            keys,
+            outline,
+            mask: MaskState::default(),
+            transients: Vec::new(),
+            snap_modifier: ModMask::default(),
         }
     }
 
-    // Redraw mask to erase it on exit
-    draw_mask(subtle, geom)?;
+    /// Find drag key of keycode
+    ///
+    /// # Arguments
+    ///
+    /// * `keycode` - Keycode of the pressed key
+    ///
+    /// # Returns
+    ///
+    /// A [`Option`] with either [`Some`] drag key or otherwise [`None`]
+    pub(crate) fn find_key(&self, keycode: Keycode) -> Option<DragKey> {
+        self.keys.iter()
+            .find(|(key_keycode, _)| *key_keycode == keycode)
+            .map(|(_, key)| *key)
+    }
+
+    /// Adjust pending geometry by a single step
+    ///
+    /// # Arguments
+    ///
+    /// * `drag_dir` - Dragging direction
+    /// * `step_size` - Distance to move
+    /// * `width_inc` - Width increment to resize
+    /// * `height_inc` - Height increment to resize
+    ///
+    /// # Returns
+    ///
+    /// The new pending [`Rectangle`]
+    pub(crate) fn step(&mut self, drag_dir: DirectionOrder, step_size: i16,
+                       width_inc: u16, height_inc: u16) -> Rectangle
+    {
+        self.pending = calc_drag_step(self.drag_mode, drag_dir, step_size,
+                                      width_inc.max(1), height_inc.max(1), &self.pending);
+
+        self.pending
+    }
+
+    /// Calculate distance of the given geometry to the geometry before the drag
+    ///
+    /// # Arguments
+    ///
+    /// * `geom` - Geometry to compare
+    ///
+    /// # Returns
+    ///
+    /// A tuple with the delta on the x and y axis
+    pub(crate) fn calc_delta(&self, geom: &Rectangle) -> (i32, i32) {
+        (geom.x as i32 - self.origin.x as i32, geom.y as i32 - self.origin.y as i32)
+    }
+
+    /// Get geometry to apply when the drag is finished
+    ///
+    /// # Arguments
+    ///
+    /// * `commit` - Whether the drag is committed or cancelled
+    ///
+    /// # Returns
+    ///
+    /// A [`Option`] with either [`Some`] geometry to apply or otherwise [`None`] when the client is up to date
+    pub(crate) fn calc_finish_geom(&self, commit: bool) -> Option<Rectangle> {
+        match (commit, self.outline) {
+            (true, true) => Some(self.pending),
+            (false, false) => Some(self.origin),
+            _ => None,
+        }
+    }
+}
+
+/// Map keys of a keyboard drag to keycodes
+///
+/// # Arguments
+///
+/// * `keysyms_to_keycode` - Lookup table to map keysyms to keycodes
+/// * `grabs` - Grabs to find the directional keys of window selection
+///
+/// # Returns
+///
+/// A [`Vec`] of keycodes and drag keys, arrow keys first
+pub(crate) fn calc_drag_keys(keysyms_to_keycode: &KeyMap, grabs: &[Grab]) -> Vec<(Keycode, DragKey)> {
+    let mut keys: Vec<(Keycode, DragKey)> = [
+        (XK_RETURN, DragKey::Commit),
+        (XK_KP_ENTER, DragKey::Commit),
+        (XK_ESCAPE, DragKey::Cancel),
+        (XK_UP, DragKey::Step(DirectionOrder::Up)),
+        (XK_RIGHT, DragKey::Step(DirectionOrder::Right)),
+        (XK_DOWN, DragKey::Step(DirectionOrder::Down)),
+        (XK_LEFT, DragKey::Step(DirectionOrder::Left)),
+    ].iter()
+        .filter_map(|(keysym, key)| keysyms_to_keycode.get(keysym)
+            .map(|(keycode, _)| (*keycode, *key)))
+        .collect();
+
+    // Directional keys of window selection adjust the outline as well
+    for grab in grabs.iter() {
+        if grab.flags.contains(GrabFlags::IS_KEY | GrabFlags::WINDOW_SELECT)
+            && let GrabAction::Index(order) = grab.action
+            && let Some(drag_dir) = DirectionOrder::from_repr(order as u8)
+            && !matches!(drag_dir, DirectionOrder::Mouse)
+            && !keys.iter().any(|(keycode, _)| *keycode == grab.keycode)
+        {
+            keys.push((grab.keycode, DragKey::Step(drag_dir)));
+        }
+    }
+
+    keys
+}
+
+/// Mask on the root window that is erased on drop, so early exits can't leave it behind
+struct MaskGuard<'a> {
+    /// Global state object
+    subtle: &'a Subtle,
+    /// Root window
+    root: Window,
+    /// Event mask of the root window before the drag
+    event_mask: EventMask,
+    /// Mask bookkeeping
+    state: MaskState,
+}
+
+impl<'a> MaskGuard<'a> {
+    /// Create a new instance and listen for repaints of the root window
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`MaskGuard`] on success or otherwise [`anyhow::Error`]
+    fn new(subtle: &'a Subtle) -> Result<Self> {
+        let conn = subtle.conn.get().context("Failed to get connection")?;
+        let root = conn.setup().roots[subtle.screen_num].root;
+
+        let event_mask = conn.get_window_attributes(root)?.reply()?.your_event_mask;
+
+        conn.change_window_attributes(root, &ChangeWindowAttributesAux::default()
+            .event_mask(event_mask | EventMask::EXPOSURE | EventMask::VISIBILITY_CHANGE))?.check()?;
+
+        Ok(Self {
+            subtle,
+            root,
+            event_mask,
+            state: MaskState::default(),
+        })
+    }
+
+    /// Move mask to new geometry
+    ///
+    /// # Arguments
+    ///
+    /// * `geom` - Geometry of the mask
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    fn draw(&mut self, geom: &Rectangle) -> Result<()> {
+        for mask_geom in self.state.show(geom) {
+            draw_mask(self.subtle, &mask_geom)?;
+        }
+
+        Ok(())
+    }
+
+    /// Clear root window and draw the mask again after the server repainted the screen
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    fn repaint(&mut self) -> Result<()> {
+        if let Some(geom) = self.state.invalidate() {
+            let conn = self.subtle.conn.get().context("Failed to get connection")?;
+
+            conn.clear_area(false, self.root, 0, 0, 0, 0)?.check()?;
+
+            self.draw(&geom)?;
+        }
+
+        debug!("{}: drawn={}", function_name!(), self.state.is_drawn());
+
+        Ok(())
+    }
+
+    /// Erase mask
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    fn erase(&mut self) -> Result<()> {
+        if let Some(geom) = self.state.hide() {
+            draw_mask(self.subtle, &geom)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for MaskGuard<'_> {
+    fn drop(&mut self) {
+        if let Err(err) = self.erase() {
+            warn!("Failed to erase drag mask: {}", err);
+        }
+
+        if let Some(conn) = self.subtle.conn.get()
+            && let Err(err) = conn.change_window_attributes(self.root, &ChangeWindowAttributesAux::default()
+                .event_mask(self.event_mask))
+        {
+            warn!("Failed to restore root event mask: {}", err);
+        }
+    }
+}
+
+/// Drag client window interactively
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `screen` - Screen to drag on
+/// * `client` - Client to drag
+/// * `geom` - Geometry of the mask
+/// * `query_reply` - Cached query reply
+/// * `drag_mode` - Dragging mode
+/// * `drag_edge` - Dragging starting edge
+/// * `transients` - Transients to move along during the drag
+/// * `siblings` - Outer geometries of clients to snap to
+/// * `snap_modifier` - Modifier to bypass snapping while held
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+#[allow(clippy::too_many_arguments)]
+fn drag_interactively(subtle: &Subtle, screen: &Screen, client: &Client, geom: &mut Rectangle,
+                      query_reply: &QueryPointerReply, drag_mode: DragMode, drag_edge: DragEdge,
+                      transients: &[(Window, Rectangle)], siblings: &[Rectangle],
+                      snap_modifier: ModMask) -> Result<()>
+{
+    let conn = subtle.conn.get().unwrap();
+
+    let mut fx: i32 = 0;
+    let mut fy: i32 = 0;
+    let mut dx: i32 = 0;
+    let mut dy: i32 = 0;
+
+    // Set starting point
+    if drag_edge.intersects(DragEdge::LEFT) {
+        fx = geom.x as i32 + geom.width as i32;
+        dx = query_reply.root_x as i32 - client.geom.x as i32;
+    } else if drag_edge.intersects(DragEdge::RIGHT) {
+        fx = geom.x as i32;
+        dx = geom.x as i32 + geom.width as i32 - query_reply.root_x as i32;
+    }
+
+    if drag_edge.intersects(DragEdge::TOP) {
+        fy = geom.y as i32 + geom.height as i32;
+        dy = query_reply.root_y as i32 - client.geom.y as i32;
+    } else if drag_edge.intersects(DragEdge::BOTTOM) {
+        fy = geom.y as i32;
+        dy = geom.y as i32 + geom.height as i32 - query_reply.root_y as i32;
+    }
+
+    let screen_geoms: Vec<Rectangle> = subtle.screens.iter()
+        .map(|screen| screen.base)
+        .collect();
+
+    let start_geom = *geom;
+    let border_width = client.get_border_width(subtle);
+
+    let mut mask = MaskGuard::new(subtle)?;
+
+    mask.draw(geom)?;
+
+    // Start event loop
+    'dragging: loop {
+        if let Ok(event) = conn.wait_for_event() {
+            match event {
+                Event::ButtonRelease(_evt) => {
+                    break 'dragging;
+                },
+                // Server repainted the root window or unblanked the screen
+                Event::Expose(evt) if evt.window == mask.root && 0 == evt.count => {
+                    mask.repaint()?;
+                },
+                Event::VisibilityNotify(evt) if evt.window == mask.root => {
+                    mask.repaint()?;
+                },
+                Event::ScreensaverNotify(evt) if screensaver::State::OFF == evt.state => {
+                    mask.repaint()?;
+                },
+                Event::MotionNotify(evt) => {
+                    if DragMode::MOVE == drag_mode {
+                        (geom.x, geom.y) = calc_drag_origin(evt.root_x, evt.root_y,
+                                                            query_reply.win_x, query_reply.win_y, border_width);
+
+                        if is_snapping(u16::from(evt.state), snap_modifier, subtle.snap_size) {
+                            let unsnapped = *geom;
+
+                            client.snap(subtle, screen, geom)?;
+
+                            // Snap to siblings on axes the screen border didn't claim
+                            let (snap_x, snap_y) = calc_sibling_snap(geom, border_width, siblings, subtle.snap_size);
+
+                            if let Some(x) = snap_x && unsnapped.x == geom.x {
+                                geom.x = x;
+                            }
+
+                            if let Some(y) = snap_y && unsnapped.y == geom.y {
+                                geom.y = y;
+                            }
+                        }
+
+                        *geom = screen::calc_edge_resistance(geom, &screen_geoms, subtle.edge_resistance);
+
+                        // Move transients along
+                        if !subtle.transients_drag_on_release {
+                            for (win, trans_geom) in transients.iter() {
+                                conn.configure_window(*win, &ConfigureWindowAux::default()
+                                    .x(trans_geom.x as i32 + geom.x as i32 - start_geom.x as i32)
+                                    .y(trans_geom.y as i32 + geom.y as i32 - start_geom.y as i32))?;
+                            }
+                        }
+                    } else {
+                        // Handle resize based on edge
+                        let root_x = evt.root_x as i32;
+                        let root_y = evt.root_y as i32;
+
+                        if drag_edge.intersects(DragEdge::LEFT) {
+                            geom.x = checked_to_i16(root_x - dx);
+                            geom.width = checked_to_u16(root_x + dx);
+                        } else if drag_edge.intersects(DragEdge::RIGHT) {
+                            geom.x = checked_to_i16(fx);
+                            geom.width = checked_to_u16(root_x - fx + dx);
+                        }
+
+                        if drag_edge.intersects(DragEdge::TOP) {
+                            geom.y = checked_to_i16(root_y - dy);
+                            geom.height = checked_to_u16(fy - root_y + dy);
+                        } else {
+                            geom.y = checked_to_i16(fy);
+                            geom.height = checked_to_u16(root_y - fy + dy);
+                        }
+
+                        // Adjust bounds based on edge
+                        client.apply_size_hints(subtle, &screen.geom,
+                                              drag_edge.intersects(DragEdge::LEFT),
+                                              drag_edge.intersects(DragEdge::TOP), geom);
+                    }
+
+                    mask.draw(geom)?;
+                },
+                _ => {},
+            }
+        }
+    }
+
+    // Redraw mask to erase it on exit
+    mask.erase()?;
 
     Ok(())
 }
 
-/// Convenience method to calculate the zaphod mode size
+/// Calculate outer border corner of a dragged client from the pointer position
+///
+/// # Arguments
+///
+/// * `root_x` - Pointer x position on the root window
+/// * `root_y` - Pointer y position on the root window
+/// * `win_x` - Pointer x position inside of the client window
+/// * `win_y` - Pointer y position inside of the client window
+/// * `border_width` - Border width of the client
+///
+/// # Returns
+///
+/// A tuple with the x and y position of the outer border corner
+pub(crate) fn calc_drag_origin(root_x: i16, root_y: i16, win_x: i16, win_y: i16, border_width: i16) -> (i16, i16) {
+    (checked_to_i16(root_x as i32 - win_x as i32 - border_width as i32),
+     checked_to_i16(root_y as i32 - win_y as i32 - border_width as i32))
+}
+
+/// Check whether a client can be dragged in given mode
+///
+/// # Arguments
+///
+/// * `flags` - Flags of the client
+/// * `drag_mode` - Drag mode
+///
+/// # Returns
+///
+/// Either [`true`] if the client can be dragged or otherwise [`false`]
+pub(crate) fn is_draggable(flags: ClientFlags, drag_mode: DragMode) -> bool {
+    !flags.intersects(ClientFlags::TYPE_DESKTOP | ClientFlags::TYPE_DOCK)
+        && (DragMode::RESIZE != drag_mode || !flags.contains(ClientFlags::MODE_FIXED))
+}
+
+/// Check whether to snap while dragging
+///
+/// # Arguments
+///
+/// * `state` - Modifier state of the event
+/// * `snap_modifier` - Modifier to bypass snapping while held
+/// * `snap_size` - Snap size, 0 disables snapping
+///
+/// # Returns
+///
+/// Either [`true`] if snapping applies or otherwise [`false`]
+pub(crate) fn is_snapping(state: u16, snap_modifier: ModMask, snap_size: u16) -> bool {
+    let snap_modifier = u16::from(snap_modifier);
+
+    0 != snap_size && (0 == snap_modifier || snap_modifier != state & snap_modifier)
+}
+
+/// Strip modifiers of the grab from the snap modifier, they are held during the whole drag
+///
+/// # Arguments
+///
+/// * `snap_modifier` - Configured modifier to bypass snapping
+/// * `grab_modifiers` - Modifiers of the drag grab
+///
+/// # Returns
+///
+/// The [`ModMask`] that bypasses snapping during this drag
+pub(crate) fn calc_snap_modifier(snap_modifier: ModMask, grab_modifiers: ModMask) -> ModMask {
+    ModMask::from(u16::from(snap_modifier) & !u16::from(grab_modifiers))
+}
+
+/// Calculate centered geometry of a dialog that stays reachable on the screen
+///
+/// # Arguments
+///
+/// * `bounds` - Geometry of the screen
+/// * `geom` - Geometry of the dialog without border
+/// * `border_width` - Border width of the dialog
+/// * `min_size` - Min width and height of the dialog
+/// * `min_visible` - Min width of the dialog that stays on screen
+/// * `shrink` - Whether to shrink dialogs bigger than the screen
+///
+/// # Returns
+///
+/// A [`Rectangle`] centered on the screen with the top edge on screen
+pub(crate) fn calc_centered_dialog(bounds: &Rectangle, geom: &Rectangle, border_width: u16,
+                                   min_size: (u16, u16), min_visible: u16, shrink: bool) -> Rectangle
+{
+    let mut dialog_geom = *geom;
+
+    // Shrink to screen size but not below min size
+    if shrink {
+        let max_width = bounds.width.saturating_sub(2 * border_width);
+        let max_height = bounds.height.saturating_sub(2 * border_width);
+
+        dialog_geom.width = dialog_geom.width.min(max_width).max(min_size.0.min(dialog_geom.width));
+        dialog_geom.height = dialog_geom.height.min(max_height).max(min_size.1.min(dialog_geom.height));
+    }
+
+    let outer_width = dialog_geom.width as i32 + 2 * border_width as i32;
+    let outer_height = dialog_geom.height as i32 + 2 * border_width as i32;
+
+    let x = calc_centered(bounds.x as i32, bounds.width as i32, outer_width) as i32;
+    let y = calc_centered(bounds.y as i32, bounds.height as i32, outer_height) as i32;
+
+    // Keep a strip of the width on screen
+    let visible = (min_visible as i32).min(outer_width).min(bounds.width as i32);
+    let min_x = bounds.x as i32 - (outer_width - visible);
+    let max_x = bounds.x as i32 + bounds.width as i32 - visible;
+
+    // Keep top edge on screen
+    let max_y = bounds.y as i32 + (bounds.height as i32 - outer_height).max(0);
+
+    dialog_geom.x = checked_to_i16(x.clamp(min_x, max_x.max(min_x)));
+    dialog_geom.y = checked_to_i16(y.clamp(bounds.y as i32, max_y));
+
+    dialog_geom
+}
+
+/// Calculate saved geometry to restore on the screen
+///
+/// # Arguments
+///
+/// * `bounds` - Geometry of the screen
+/// * `saved_geom` - Saved geometry without border
+/// * `border_width` - Border width of the client
+/// * `min_size` - Min width and height of the client
+/// * `min_visible` - Min width of the client that stays on screen
+/// * `shrink` - Whether to shrink clients bigger than the screen
+///
+/// # Returns
+///
+/// Either the saved [`Rectangle`] when it still fits on the screen or otherwise a centered one
+pub(crate) fn calc_restored_geom(bounds: &Rectangle, saved_geom: &Rectangle, border_width: u16,
+                                 min_size: (u16, u16), min_visible: u16, shrink: bool) -> Rectangle
+{
+    let outer_width = saved_geom.width as i32 + 2 * border_width as i32;
+    let outer_height = saved_geom.height as i32 + 2 * border_width as i32;
+
+    let is_inside = bounds.x <= saved_geom.x && bounds.y <= saved_geom.y
+        && saved_geom.x as i32 + outer_width <= bounds.x as i32 + bounds.width as i32
+        && saved_geom.y as i32 + outer_height <= bounds.y as i32 + bounds.height as i32;
+
+    if is_inside {
+        *saved_geom
+    } else {
+        calc_centered_dialog(bounds, saved_geom, border_width, min_size, min_visible, shrink)
+    }
+}
+
+/// Calculate geometry of a single keyboard drag step
+///
+/// # Arguments
+///
+/// * `drag_mode` - Dragging mode
+/// * `drag_dir` - Dragging direction
+/// * `step_size` - Distance to move
+/// * `width_inc` - Width increment to resize
+/// * `height_inc` - Height increment to resize
+/// * `geom` - Current geometry
+///
+/// # Returns
+///
+/// A [`Rectangle`] moved or grown towards the direction
+pub(crate) fn calc_drag_step(drag_mode: DragMode, drag_dir: DirectionOrder, step_size: i16,
+                             width_inc: u16, height_inc: u16, geom: &Rectangle) -> Rectangle
+{
+    let mut step_geom = *geom;
+
+    match (drag_mode, drag_dir) {
+        (DragMode::MOVE, DirectionOrder::Up) => step_geom.y = geom.y.saturating_sub(step_size),
+        (DragMode::MOVE, DirectionOrder::Right) => step_geom.x = geom.x.saturating_add(step_size),
+        (DragMode::MOVE, DirectionOrder::Down) => step_geom.y = geom.y.saturating_add(step_size),
+        (DragMode::MOVE, DirectionOrder::Left) => step_geom.x = geom.x.saturating_sub(step_size),
+        (DragMode::RESIZE, DirectionOrder::Up) => {
+            step_geom.y = geom.y.saturating_sub(height_inc as i16);
+            step_geom.height = geom.height.saturating_add(height_inc);
+        },
+        (DragMode::RESIZE, DirectionOrder::Right) => step_geom.width = geom.width.saturating_add(width_inc),
+        (DragMode::RESIZE, DirectionOrder::Down) => step_geom.height = geom.height.saturating_add(height_inc),
+        (DragMode::RESIZE, DirectionOrder::Left) => {
+            step_geom.x = geom.x.saturating_sub(width_inc as i16);
+            step_geom.width = geom.width.saturating_add(width_inc);
+        },
+        (_, DirectionOrder::Mouse) => {},
+    }
+
+    step_geom
+}
+
+/// Find outer geometries of visible clients on the screen of the given window
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `win` - Window of the dragged client
+/// * `transients` - Transients that are dragged along
+///
+/// # Returns
+///
+/// A [`Vec`] with the outer geometries including borders
+pub(crate) fn find_sibling_geoms(subtle: &Subtle, win: Window, transients: &[(Window, Rectangle)]) -> Vec<Rectangle> {
+    let clients = subtle.clients.borrow();
+
+    let Some(screen_idx) = clients.iter().find(|c| c.win == win).map(|c| c.screen_idx) else {
+        return Vec::new();
+    };
+
+    clients.iter()
+        .filter(|c| c.win != win && c.screen_idx == screen_idx && c.is_alive() && c.is_visible(subtle)
+            && !transients.iter().any(|(trans_win, _)| *trans_win == c.win))
+        .map(|c| {
+            let border_width = 2 * c.get_border_width(subtle) as u16;
+
+            Rectangle {
+                x: c.geom.x,
+                y: c.geom.y,
+                width: c.geom.width.saturating_add(border_width),
+                height: c.geom.height.saturating_add(border_width),
+            }
+        })
+        .collect()
+}
+
+/// Find closest distance of the edges of a range to the edges of other ranges
+///
+/// # Arguments
+///
+/// * `start` - Start of the range
+/// * `len` - Length of the range
+/// * `others` - Start and length of the other ranges
+/// * `snap_size` - Max distance to snap
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] delta or otherwise [`None`] when no edge is close enough
+fn calc_edge_delta(start: i32, len: i32, others: &[(i32, i32)], snap_size: u16) -> Option<i32> {
+    others.iter()
+        .flat_map(|(other_start, other_len)| {
+            let other_end = other_start + other_len;
+
+            // Edge to edge and edge to same edge
+            [other_end - start, *other_start - start, *other_start - (start + len), other_end - (start + len)]
+        })
+        .filter(|delta| delta.abs() <= snap_size as i32)
+        .min_by_key(|delta| delta.abs())
+}
+
+/// Calculate position snapped to a screen edge on one axis
+///
+/// # Arguments
+///
+/// * `start` - Start of the outer geometry
+/// * `len` - Length of the outer geometry
+/// * `bounds_start` - Start of the screen
+/// * `bounds_len` - Length of the screen
+/// * `snap_size` - Max distance to snap
+///
+/// # Returns
+///
+/// The snapped or otherwise unchanged start
+fn calc_edge_snap(start: i32, len: i32, bounds_start: i32, bounds_len: i32, snap_size: u16) -> i32 {
+    if (bounds_start - start).abs() <= snap_size as i32 {
+        bounds_start
+    } else if (bounds_start + bounds_len - (start + len)).abs() <= snap_size as i32 {
+        bounds_start + bounds_len - len
+    } else {
+        start
+    }
+}
+
+/// Calculate geometry snapped to the edges of the screen
+///
+/// The position is the outer corner of the border like X expects it, so the outer
+/// edges of the border land on the screen edges.
+///
+/// # Arguments
+///
+/// * `geom` - Geometry of the client with position of the outer border corner
+/// * `border_width` - Border width of the client
+/// * `bounds` - Geometry of the screen
+/// * `snap_size` - Max distance to snap
+///
+/// # Returns
+///
+/// A [`Rectangle`] with the snapped position
+pub(crate) fn calc_screen_snap(geom: &Rectangle, border_width: i16, bounds: &Rectangle, snap_size: u16) -> Rectangle {
+    let outer_width = geom.width as i32 + 2 * border_width as i32;
+    let outer_height = geom.height as i32 + 2 * border_width as i32;
+
+    Rectangle {
+        x: checked_to_i16(calc_edge_snap(geom.x as i32, outer_width,
+                                         bounds.x as i32, bounds.width as i32, snap_size)),
+        y: checked_to_i16(calc_edge_snap(geom.y as i32, outer_height,
+                                         bounds.y as i32, bounds.height as i32, snap_size)),
+        ..*geom
+    }
+}
+
+/// Calculate position snapped to the edges of sibling clients
+///
+/// # Arguments
+///
+/// * `geom` - Geometry of the dragged client with position of the outer border corner
+/// * `border_width` - Border width of the dragged client
+/// * `siblings` - Outer geometries of the sibling clients
+/// * `snap_size` - Max distance to snap
+///
+/// # Returns
+///
+/// A tuple with either [`Some`] snapped x and y position or otherwise [`None`] per axis
+pub(crate) fn calc_sibling_snap(geom: &Rectangle, border_width: i16, siblings: &[Rectangle],
+                                snap_size: u16) -> (Option<i16>, Option<i16>)
+{
+    let outer_x = geom.x as i32;
+    let outer_y = geom.y as i32;
+    let outer_width = geom.width as i32 + 2 * border_width as i32;
+    let outer_height = geom.height as i32 + 2 * border_width as i32;
+
+    // Only siblings next to each other on the other axis can be aligned
+    let overlaps = |start: i32, len: i32, other_start: i32, other_len: i32| {
+        start <= other_start + other_len + snap_size as i32 && other_start <= start + len + snap_size as i32
+    };
+
+    let x_ranges: Vec<(i32, i32)> = siblings.iter()
+        .filter(|s| overlaps(outer_y, outer_height, s.y as i32, s.height as i32))
+        .map(|s| (s.x as i32, s.width as i32))
+        .collect();
+
+    let y_ranges: Vec<(i32, i32)> = siblings.iter()
+        .filter(|s| overlaps(outer_x, outer_width, s.x as i32, s.width as i32))
+        .map(|s| (s.y as i32, s.height as i32))
+        .collect();
+
+    (calc_edge_delta(outer_x, outer_width, &x_ranges, snap_size)
+         .map(|delta| checked_to_i16(geom.x as i32 + delta)),
+     calc_edge_delta(outer_y, outer_height, &y_ranges, snap_size)
+         .map(|delta| checked_to_i16(geom.y as i32 + delta)))
+}
+
+/// Convenience method to calculate the zaphod mode size
+///
+/// The bounds are the union of all screens minus the panels along the top and bottom edges
+/// of the union, panels of screens that don't touch these edges don't overlap a spanning window
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `geom` - Geometry to update
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn calc_zaphod(subtle: &Subtle, geom: &mut Rectangle) -> Result<()> {
+    // Union of all screens or the root window without screens
+    let (mut x1, mut y1, mut x2, mut y2) = (0i32, 0i32, subtle.width as i32, subtle.height as i32);
+
+    if !subtle.screens.is_empty() {
+        (x1, y1, x2, y2) = subtle.screens.iter().fold((i32::MAX, i32::MAX, i32::MIN, i32::MIN),
+            |(x1, y1, x2, y2), screen| (
+                x1.min(screen.base.x as i32),
+                y1.min(screen.base.y as i32),
+                x2.max(screen.base.x as i32 + screen.base.width as i32),
+                y2.max(screen.base.y as i32 + screen.base.height as i32)));
+    }
+
+    // Find panels along the edges of the union
+    let mut top_inset = 0;
+    let mut bottom_inset = 0;
+
+    for screen in subtle.screens.iter() {
+        if screen.flags.contains(ScreenFlags::TOP_PANEL) && y1 == screen.base.y as i32 {
+            top_inset = top_inset.max(subtle.panel_height as i32);
+        }
+
+        if screen.flags.contains(ScreenFlags::BOTTOM_PANEL)
+            && y2 == screen.base.y as i32 + screen.base.height as i32
+        {
+            bottom_inset = bottom_inset.max(subtle.panel_height as i32);
+        }
+    }
+
+    // Update bounds according to styles
+    let padding = &subtle.clients_style.padding;
+
+    geom.x = checked_to_i16(x1 + padding.left as i32);
+    geom.y = checked_to_i16(y1 + top_inset + padding.top as i32);
+    geom.width = calc_shrunk(checked_to_u16(x2 - x1), padding.left as i32
+        + padding.right as i32, MIN_WIDTH);
+    geom.height = calc_shrunk(checked_to_u16(y2 - y1), top_inset + bottom_inset
+        + padding.top as i32 + padding.bottom as i32, MIN_HEIGHT);
+
+    debug!("{}: geom={:?}", function_name!(), geom);
+
+    Ok(())
+}
+
+/// Move transients by the delta of their dragged parent
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `transients` - Window and geometry of the transients before the drag
+/// * `delta_x` - Delta on the x axis
+/// * `delta_y` - Delta on the y axis
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn move_transients(subtle: &Subtle, transients: &[(Window, Rectangle)],
+                              delta_x: i32, delta_y: i32) -> Result<()>
+{
+    for (win, trans_geom) in transients.iter() {
+        if let Some(mut client) = subtle.find_client_mut(*win) {
+            let geom = Rectangle {
+                x: checked_to_i16(trans_geom.x as i32 + delta_x),
+                y: checked_to_i16(trans_geom.y as i32 + delta_y),
+                ..*trans_geom
+            };
+
+            // Update screen first to use the right bounds
+            client.geom = geom;
+            client.reconcile_screen(subtle)?;
+            client.move_resize(subtle, &geom, false)?;
+        }
+    }
+
+    debug!("{}: transients={}, delta_x={}, delta_y={}", function_name!(),
+        transients.len(), delta_x, delta_y);
+
+    Ok(())
+}
+
+/// Publish and export all relevant atoms to allow IPC
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `restack_windows` - Whether to restackl/sort window list
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn publish(subtle: &Subtle, restack_windows: bool) -> Result<()> {
+    let conn = subtle.conn.get().unwrap();
+    let atoms = subtle.atoms.get().unwrap();
+
+    let default_screen = &conn.setup().roots[subtle.screen_num];
+
+    let clients = subtle.clients.borrow();
+    let mut wins: Vec<u32> = Vec::with_capacity(clients.len());
+
+    // Sort clients from top to bottom
+    for client in clients.iter() {
+        wins.push(client.win);
+    }
+
+    // EWMH: Client list and stacking list (same for us)
+    conn.change_property32(PropMode::REPLACE, default_screen.root, atoms._NET_CLIENT_LIST,
+                           AtomEnum::WINDOW, &wins)?;
+    conn.change_property32(PropMode::REPLACE, default_screen.root, atoms._NET_CLIENT_LIST_STACKING,
+                           AtomEnum::WINDOW, &wins)?;
+
+    // subtle: Geometries in the same order
+    effect::apply(subtle, Effect::SetRootProperty { prop: Prop::ClientGeometries,
+        data: calc_client_geometries(&clients) })?;
+
+    // Restack windows? We assembled the array anyway
+    if restack_windows {
+        // TODO
+        //XRestackWindows
+    }
+
+    conn.flush()?;
+
+    debug!("{}: nclients={}, restack={}", function_name!(), clients.len(), restack_windows);
+
+    Ok(())
+}
+
+/// Publish geometries of all clients
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn publish_geometries(subtle: &Subtle) -> Result<()> {
+    let clients = subtle.clients.borrow();
+
+    effect::apply(subtle, Effect::SetRootProperty { prop: Prop::ClientGeometries,
+        data: calc_client_geometries(&clients) })?;
+
+    debug!("{}: nclients={}", function_name!(), clients.len());
+
+    Ok(())
+}
+
+/// Serialize geometry, gravity and screen of clients for IPC
+///
+/// # Arguments
+///
+/// * `clients` - Clients in order of the client list
+///
+/// # Returns
+///
+/// A [`Vec`] with the concatenated [`ClientGeometry`] values
+pub(crate) fn calc_client_geometries(clients: &[Client]) -> Vec<u32> {
+    clients.iter()
+        .flat_map(|client| ClientGeometry {
+            x: client.geom.x,
+            y: client.geom.y,
+            width: client.geom.width,
+            height: client.geom.height,
+            gravity_idx: client.gravity_idx,
+            screen_idx: client.screen_idx,
+        }.encode())
+        .collect()
+}
+
+/// Apply the fields of a configure request selected by its value mask
+///
+/// # Arguments
+///
+/// * `geom` - Current geometry
+/// * `event` - Configure request
+///
+/// # Returns
+///
+/// The requested [`Rectangle`]
+pub(crate) fn calc_requested_geom(geom: &Rectangle, event: &ConfigureRequestEvent) -> Rectangle {
+    let mut requested = *geom;
+
+    if event.value_mask.contains(ConfigWindow::X) {
+        requested.x = event.x;
+    }
+
+    if event.value_mask.contains(ConfigWindow::Y) {
+        requested.y = event.y;
+    }
+
+    if event.value_mask.contains(ConfigWindow::WIDTH) {
+        requested.width = max!(MIN_WIDTH, event.width);
+    }
+
+    if event.value_mask.contains(ConfigWindow::HEIGHT) {
+        requested.height = max!(MIN_HEIGHT, event.height);
+    }
+
+    requested
+}
+
+/// Get reply unless the window is gone in the meantime
+///
+/// # Arguments
+///
+/// * `reply` - Reply of a request on a client window
+///
+/// # Returns
+///
+/// A [`Result`] with either [`Some`] reply, [`None`] when the window is gone or otherwise [`anyhow::Error`]
+pub(crate) fn reply_unless_gone<T>(reply: Result<T, ReplyError>) -> Result<Option<T>> {
+    match reply {
+        Ok(value) => Ok(Some(value)),
+        Err(ReplyError::X11Error(err)) if ErrorKind::Window == err.error_kind => {
+            debug!("{}: window gone, bad_value={}", function_name!(), err.bad_value);
+
+            Ok(None)
+        },
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Map _NET_WM_STATE atom to the corresponding mode flag
+///
+/// # Arguments
+///
+/// * `atoms` - Known atoms
+/// * `atom` - State atom to map
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] mode flag or otherwise [`None`] for unknown atoms
+pub(crate) fn get_state_flag(atoms: &Atoms, atom: Atom) -> Option<ClientFlags> {
+    if atoms._NET_WM_STATE_FULLSCREEN == atom {
+        Some(ClientFlags::MODE_FULL)
+    } else if atoms._NET_WM_STATE_ABOVE == atom {
+        Some(ClientFlags::MODE_FLOAT)
+    } else if atoms._NET_WM_STATE_STICKY == atom {
+        Some(ClientFlags::MODE_STICK)
+    } else if atoms._NET_WM_STATE_DEMANDS_ATTENTION == atom {
+        Some(ClientFlags::MODE_URGENT)
+    } else {
+        None
+    }
+}
+
+/// Calculate mode flags to toggle for a _NET_WM_STATE message
+///
+/// # Arguments
+///
+/// * `current_flags` - Current flags of the client
+/// * `action` - Remove, add or toggle action of the message
+/// * `requested_flags` - Mode flags of the state atoms of the message
+///
+/// # Returns
+///
+/// The mode flags that need to be toggled to reach the requested state
+pub(crate) fn calc_state_delta(current_flags: ClientFlags, action: u32, requested_flags: ClientFlags) -> ClientFlags {
+    match action {
+        NET_WM_STATE_REMOVE => requested_flags.intersection(current_flags),
+        NET_WM_STATE_ADD => requested_flags.difference(current_flags),
+        NET_WM_STATE_TOGGLE => requested_flags,
+        _ => ClientFlags::empty(),
+    }
+}
+
+/// Decide how to handle an activation request
+///
+/// Requests of pagers and legacy clients always activate the client, whereas applications
+/// can't steal the focus from hidden clients and just mark them as urgent.
+///
+/// # Arguments
+///
+/// * `source` - Source indication of the request
+/// * `is_visible` - Whether the client is on a visible view
+///
+/// # Returns
+///
+/// The [`Activation`] to apply
+pub(crate) fn calc_activation(source: u32, is_visible: bool) -> Activation {
+    if is_visible {
+        Activation::Focus
+    } else if SOURCE_APPLICATION == source {
+        Activation::MarkUrgent
+    } else {
+        Activation::Jump
+    }
+}
+
+/// Calculate frame extents of the effective border
+///
+/// # Arguments
+///
+/// * `flags` - Flags of the client
+/// * `border_width` - Border width of the client style
+///
+/// # Returns
+///
+/// A [`Vec`] with the left, right, top and bottom extents
+pub(crate) fn calc_frame_extents(flags: ClientFlags, border_width: i16) -> Vec<u32> {
+    vec![calc_border_width(flags, border_width) as u32; 4]
+}
+
+/// Decide how to handle a stacking request of another client
+///
+/// Desktop windows can't be lowered below themselves and docks can't be raised above
+/// fullscreen clients unless the request is forced.
+///
+/// # Arguments
+///
+/// * `order` - Requested order
+/// * `client_flags` - Flags of the client or [`None`] if the window isn't managed
+/// * `has_fullscreen` - Whether a fullscreen client is visible
+/// * `request_flags` - Flags of the request
+///
+/// # Returns
+///
+/// A tuple with the [`Restacking`] to apply and whether to focus the client afterwards
+pub(crate) fn calc_restacking(order: RestackOrder, client_flags: Option<ClientFlags>,
+                              has_fullscreen: bool, request_flags: u32) -> (Restacking, bool)
+{
+    let Some(client_flags) = client_flags else {
+        return (Restacking::Refuse, false);
+    };
+
+    let is_forced = 0 != request_flags & ipc::STACK_FORCE;
+
+    let restacking = match order {
+        RestackOrder::None => Restacking::Refuse,
+        RestackOrder::Down if client_flags.contains(ClientFlags::TYPE_DESKTOP) && !is_forced => Restacking::Refuse,
+        RestackOrder::Up if client_flags.contains(ClientFlags::TYPE_DOCK) && has_fullscreen => {
+            if is_forced { Restacking::RaiseAbove } else { Restacking::Refuse }
+        },
+        _ => Restacking::Restack(order),
+    };
+
+    let focus = RestackOrder::Up == order && Restacking::Refuse != restacking
+        && 0 != request_flags & ipc::STACK_FOCUS;
+
+    (restacking, focus)
+}
+
+/// Translate EWMH desktop index into tags of the matching view
+///
+/// # Arguments
+///
+/// * `views` - Views to check
+/// * `desktop_idx` - EWMH desktop index
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] tags of the view or otherwise [`None`] when out of range
+pub(crate) fn calc_desktop_tags(views: &[View], desktop_idx: u32) -> Option<Tagging> {
+    ValidViewIdx::new(desktop_idx, views.len()).ok()
+        .map(|view_idx| views[view_idx.get()].tags.get())
+}
+
+/// Select EWMH desktop of a client, the lowest view for clients on multiple views
+///
+/// # Arguments
+///
+/// * `views` - Views to check
+/// * `tags` - Tags of the client
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] desktop index or otherwise [`None`]
+pub(crate) fn calc_wm_desktop(views: &[View], tags: Tagging) -> Option<u32> {
+    views.iter()
+        .position(|view| view.tags.get().intersects(tags))
+        .map(|view_idx| view_idx as u32)
+}
+
+/// Calculate tags after pinning a client to a view or unpinning it
+///
+/// # Arguments
+///
+/// * `tags` - Current tags of the client
+/// * `view_tags` - Tags of the view
+/// * `is_pin` - Whether to pin or unpin
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] new tags or otherwise [`None`] when unpinning would remove the last tag
+pub(crate) fn calc_pinned_tags(tags: Tagging, view_tags: Tagging, is_pin: bool) -> Option<Tagging> {
+    if is_pin {
+        Some(tags | view_tags)
+    } else {
+        Some(tags.difference(view_tags)).filter(|tags| !tags.is_empty())
+    }
+}
+
+/// Calculate tags of a transient based on the tags of its parent
+///
+/// # Arguments
+///
+/// * `tags` - Current tags of the transient
+/// * `parent_tags` - Tags of the parent
+/// * `follow_parent` - Whether to replace or just extend the tags
+///
+/// # Returns
+///
+/// The new [`Tagging`] of the transient
+pub(crate) fn calc_transient_tags(tags: Tagging, parent_tags: Tagging, follow_parent: bool) -> Tagging {
+    if follow_parent {
+        parent_tags
+    } else {
+        tags | parent_tags
+    }
+}
+
+/// Add or remove tag and fall back to the default tag when no tag is left like [`Client::retag`]
+///
+/// # Arguments
+///
+/// * `tags` - Current tags
+/// * `tag_idx` - Tag index
+/// * `is_add` - Whether to add or remove the tag
+///
+/// # Returns
+///
+/// The changed [`Tagging`]
+pub(crate) fn calc_tag_change(tags: Tagging, tag_idx: usize, is_add: bool) -> Tagging {
+    let tag = tagging::tag_bit(tag_idx);
+
+    if is_add {
+        tags | tag
+    } else {
+        let tags = tags.difference(tag);
+
+        if tags.is_empty() { tagging::default_tag() } else { tags }
+    }
+}
+
+/// Map name of a window type to its client flag
+///
+/// # Arguments
+///
+/// * `name` - Name of the window type like `dialog`
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] flag or otherwise [`None`]
+pub(crate) fn parse_window_type(name: &str) -> Option<ClientFlags> {
+    match name {
+        "normal" => Some(ClientFlags::TYPE_NORMAL),
+        "desktop" => Some(ClientFlags::TYPE_DESKTOP),
+        "dock" => Some(ClientFlags::TYPE_DOCK),
+        "toolbar" => Some(ClientFlags::TYPE_TOOLBAR),
+        "splash" => Some(ClientFlags::TYPE_SPLASH),
+        "dialog" => Some(ClientFlags::TYPE_DIALOG),
+        _ => None,
+    }
+}
+
+/// Check whether clients of a gravity are tiled
 ///
 /// # Arguments
 ///
 /// * `subtle` - Global state object
-/// * `geom` - Geometry to update
+/// * `gravity` - Gravity to check
 ///
 /// # Returns
 ///
-/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-fn calc_zaphod(subtle: &Subtle, geom: &mut Rectangle) -> Result<()> {
-    let mut flags = ScreenFlags::TOP_PANEL | ScreenFlags::BOTTOM_PANEL;
+/// Either [`true`] if the gravity is tiled or otherwise [`false`]
+pub(crate) fn is_tiled(subtle: &Subtle, gravity: &Gravity) -> bool {
+    subtle.flags.contains(SubtleFlags::GRAVITY_TILING)
+        || gravity.flags.intersects(GravityFlags::HORZ | GravityFlags::VERT)
+}
 
-    // Update bounds according to styles
-    geom.x = subtle.clients_style.padding.left;
-    geom.y = subtle.clients_style.padding.top;
-    geom.width = subtle.width - (subtle.clients_style.padding.left -
-        subtle.clients_style.padding.right) as u16;
-    geom.height = subtle.height - (subtle.clients_style.padding.top -
-        subtle.clients_style.padding.bottom) as u16;
-
-    // Iterate over screens to find fitting square
-    for screen in subtle.screens.iter() {
-        if screen.flags.contains(flags) {
-            if screen.flags.contains(ScreenFlags::TOP_PANEL) {
-                geom.y += subtle.panel_height as i16;
-                geom.height -= subtle.panel_height;
-            }
+/// Decode text property and replace invalid UTF-8
+///
+/// # Arguments
+///
+/// * `value` - Raw property value
+///
+/// # Returns
+///
+/// The decoded text without trailing NUL bytes
+pub(crate) fn decode_text(value: &[u8]) -> String {
+    String::from_utf8_lossy(value)
+        .trim_end_matches('\0')
+        .to_string()
+}
 
-            if screen.flags.contains(ScreenFlags::BOTTOM_PANEL) {
-                geom.height -= subtle.panel_height;
-            }
+/// Parse instance and class from WM_CLASS
+///
+/// # Arguments
+///
+/// * `value` - Raw property value with NUL-separated instance and class
+///
+/// # Returns
+///
+/// A tuple with instance and class, missing parts are empty
+pub(crate) fn parse_wm_class(value: &[u8]) -> (String, String) {
+    let text = decode_text(value);
+    let mut parts = text.trim_matches('\0').split('\0');
+
+    let instance = parts.next().unwrap_or_default().to_string();
+    let klass = parts.next().unwrap_or_default().to_string();
 
-            flags &= !(screen.flags & (ScreenFlags::TOP_PANEL | ScreenFlags::BOTTOM_PANEL));
+    (instance, klass)
+}
+
+/// Mark all visible clients for arrangement e.g. after the gaps changed
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// The number of marked clients
+pub(crate) fn mark_visible_arrange(subtle: &Subtle) -> usize {
+    let mut clients = subtle.clients.borrow_mut();
+    let mut nmarked = 0;
+
+    for client in clients.iter_mut() {
+        if client.is_alive() && client.is_visible(subtle) {
+            client.flags.insert(ClientFlags::ARRANGE);
+
+            nmarked += 1;
         }
     }
 
-    Ok(())
+    debug!("{}: nmarked={}", function_name!(), nmarked);
+
+    nmarked
 }
 
-/// Publish and export all relevant atoms to allow IPC
+/// Map urgency hint to mode flags
+///
+/// # Arguments
+///
+/// * `is_urgent_hint` - Whether the urgency hint is set
+/// * `is_focused` - Whether the client currently has the focus
+///
+/// # Returns
+///
+/// Either [`ClientFlags::MODE_URGENT`] for unfocused urgent clients or otherwise empty flags
+pub(crate) fn calc_urgent_mode(is_urgent_hint: bool, is_focused: bool) -> ClientFlags {
+    if is_urgent_hint && !is_focused {
+        ClientFlags::MODE_URGENT
+    } else {
+        ClientFlags::empty()
+    }
+}
+
+/// Get EWMH actions pagers may offer for a client
+///
+/// # Arguments
+///
+/// * `flags` - Client flags
+///
+/// # Returns
+///
+/// The [`AllowedActions`] of the client
+pub(crate) fn calc_allowed_actions(flags: ClientFlags) -> AllowedActions {
+    let mut actions = AllowedActions::STICK | AllowedActions::CLOSE;
+
+    if flags.intersects(ClientFlags::TYPE_DESKTOP | ClientFlags::TYPE_DOCK) {
+        return actions;
+    }
+
+    actions.insert(AllowedActions::FULLSCREEN);
+
+    // Just floating clients can be moved or resized
+    if flags.intersects(ClientFlags::MODE_FLOAT | ClientFlags::MODE_RESIZE)
+        && !flags.contains(ClientFlags::MODE_FULL)
+    {
+        actions.insert(AllowedActions::MOVE);
+
+        if !flags.contains(ClientFlags::MODE_FIXED) {
+            actions.insert(AllowedActions::RESIZE);
+        }
+    }
+
+    actions
+}
+
+/// Get border color of client
+///
+/// The color is also set for borderless clients, so it is already right once the border
+/// becomes visible again.
+///
+/// # Arguments
+///
+/// * `flags` - Client flags
+/// * `is_focused` - Whether the client has the focus
+/// * `fg` - Border color of focused clients
+/// * `bg` - Border color of unfocused clients
+///
+/// # Returns
+///
+/// Either [`Some`] with the pixel value or [`None`] for desktop and dock type windows
+pub(crate) fn calc_border_pixel(flags: ClientFlags, is_focused: bool, fg: i32, bg: i32) -> Option<u32> {
+    if flags.intersects(ClientFlags::TYPE_DESKTOP | ClientFlags::TYPE_DOCK) {
+        None
+    } else if is_focused {
+        Some(fg as u32)
+    } else {
+        Some(bg as u32)
+    }
+}
+
+/// Get effective border width of client
+///
+/// # Arguments
+///
+/// * `flags` - Client flags
+/// * `border_width` - Configured border width
+///
+/// # Returns
+///
+/// Either the configured width or 0 for fullscreen, borderless, desktop and dock type windows
+pub(crate) fn calc_border_width(flags: ClientFlags, border_width: i16) -> u16 {
+    if flags.intersects(ClientFlags::MODE_FULL | ClientFlags::MODE_BORDERLESS
+        | ClientFlags::TYPE_DESKTOP | ClientFlags::TYPE_DOCK)
+    {
+        0
+    } else {
+        border_width.max(0) as u16
+    }
+}
+
+/// Toggle mode bits of client flags
+///
+/// # Arguments
+///
+/// * `flags` - Current client flags
+/// * `mode_flags` - Mode flags to toggle
+///
+/// # Returns
+///
+/// The client flags with only the mode bits of `mode_flags` flipped
+pub(crate) fn calc_toggled_flags(flags: ClientFlags, mode_flags: ClientFlags) -> ClientFlags {
+    flags ^ (mode_flags & ClientFlags::ALL_MODES)
+}
+
+/// Split area of a gravity into tiles
+///
+/// Only the tiled axis is subdivided, the last tile receives the rounding remainder.
+///
+/// # Arguments
+///
+/// * `area` - Area of the gravity on the screen
+/// * `is_horz` - Whether to tile horizontally or otherwise vertically
+/// * `count` - Number of tiles
+///
+/// # Returns
+///
+/// A [`Vec`] with the geometry of every tile
+pub(crate) fn calc_tile_geoms(area: Rectangle, is_horz: bool, count: u16) -> Vec<Rectangle> {
+    if 0 == count {
+        return Vec::new();
+    }
+
+    let extent = if is_horz { area.width } else { area.height };
+    let calc = extent / count;
+    let round_fix = extent - calc * count;
+
+    (0..count).map(|pos| {
+        let size = if pos == count - 1 { calc + round_fix } else { calc };
+        let offset = checked_to_i16(pos as i32 * calc as i32);
+
+        if is_horz {
+            Rectangle { x: area.x.saturating_add(offset), width: size, ..area }
+        } else {
+            Rectangle { y: area.y.saturating_add(offset), height: size, ..area }
+        }
+    }).collect()
+}
+
+/// Add an index to the entry of the given key of a tally
+///
+/// # Arguments
+///
+/// * `tally` - Tally of keys and collected indices
+/// * `key` - Key to add the index to
+/// * `idx` - Index to add
+pub(crate) fn tally<K: PartialEq>(tally: &mut Vec<(K, Vec<usize>)>, key: K, idx: usize) {
+    match tally.iter_mut().find(|(tally_key, _)| *tally_key == key) {
+        Some((_, idxs)) => idxs.push(idx),
+        None => tally.push((key, vec![idx])),
+    }
+}
+
+/// Tile visible clients of all tiled gravities
+///
+/// Must be called without outstanding borrows of the clients.
 ///
 /// # Arguments
 ///
 /// * `subtle` - Global state object
-/// * `restack_windows` - Whether to restackl/sort window list
 ///
 /// # Returns
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-pub(crate) fn publish(subtle: &Subtle, restack_windows: bool) -> Result<()> {
-    let conn = subtle.conn.get().unwrap();
-    let atoms = subtle.atoms.get().unwrap();
+pub(crate) fn gravity_tile(subtle: &Subtle) -> Result<()> {
+    // Pass 1: Collect client indices per gravity and screen
+    let mut tiles: Vec<((isize, isize), Vec<usize>)> = Vec::new();
+
+    for (client_idx, client) in subtle.clients.borrow().iter().enumerate() {
+        if !client.is_alive() || !client.is_visible(subtle)
+            || client.flags.intersects(ClientFlags::MODE_FLOAT | ClientFlags::MODE_FULL
+                | ClientFlags::TYPE_DESKTOP | ClientFlags::TYPE_DOCK)
+            || !subtle.gravities.get(client.gravity_idx as usize)
+                .is_some_and(|gravity| is_tiled(subtle, gravity))
+        {
+            continue;
+        }
 
-    let default_screen = &conn.setup().roots[subtle.screen_num];
+        tally(&mut tiles, (client.gravity_idx, client.screen_idx), client_idx);
+    }
 
-    let clients = subtle.clients.borrow();
-    let mut wins: Vec<u32> = Vec::with_capacity(clients.len());
+    // Pass 2: Update geometry of every client
+    for ((gravity_idx, screen_idx), client_idxs) in tiles {
+        let (Some(gravity), Some(screen)) = (subtle.gravities.get(gravity_idx as usize),
+                                             subtle.screens.get(screen_idx as usize)) else {
+            continue;
+        };
 
-    // Sort clients from top to bottom
-    for client in clients.iter() {
-        wins.push(client.win);
+        let mut area = Rectangle::default();
+
+        gravity.apply_size(&screen.geom, &mut area);
+
+        let geoms = calc_tile_geoms(area, gravity.flags.contains(GravityFlags::HORZ),
+                                    client_idxs.len() as u16);
+
+        for (client_idx, geom) in client_idxs.into_iter().zip(geoms) {
+            if let Some(client) = subtle.clients.borrow_mut().get_mut(client_idx) {
+                client.geom = geom;
+
+                client.move_resize(subtle, &screen.geom, true)?;
+            }
+        }
     }
 
-    // EWMH: Client list and stacking list (same for us)
-    conn.change_property32(PropMode::REPLACE, default_screen.root, atoms._NET_CLIENT_LIST,
-                           AtomEnum::WINDOW, &wins)?;
-    conn.change_property32(PropMode::REPLACE, default_screen.root, atoms._NET_CLIENT_LIST_STACKING,
-                           AtomEnum::WINDOW, &wins)?;
+    debug!("{}", function_name!());
 
-    // Restack windows? We assembled the array anyway
-    if restack_windows {
-        // TODO
-        //XRestackWindows
+    Ok(())
+}
+
+/// Collect tags of all urgent clients
+///
+/// # Arguments
+///
+/// * `clients` - Clients to check
+///
+/// # Returns
+///
+/// Combined [`Tagging`] of all alive and urgent clients
+pub(crate) fn calc_urgent_tags(clients: &[Client]) -> Tagging {
+    clients.iter()
+        .filter(|c| c.is_alive() && c.flags.contains(ClientFlags::MODE_URGENT))
+        .fold(Tagging::empty(), |tags, c| tags | c.tags)
+}
+
+/// Check whether a managed taskbar handles iconified clients
+///
+/// # Arguments
+///
+/// * `clients` - Clients to check
+///
+/// # Returns
+///
+/// Either [`true`] if an alive dock client set the hint or otherwise [`false`]
+pub(crate) fn calc_handled_icons(clients: &[Client]) -> bool {
+    clients.iter().any(|c| c.is_alive()
+        && c.flags.contains(ClientFlags::TYPE_DOCK | ClientFlags::HANDLED_ICONS))
+}
+
+/// Recompute whether an external taskbar handles iconified clients
+///
+/// Internal visualizations of iconified clients must defer to the taskbar while this is set.
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+pub(crate) fn update_handled_icons(subtle: &Subtle) {
+    let handled_icons = calc_handled_icons(&subtle.clients.borrow());
+
+    if handled_icons != subtle.handled_icons.replace(handled_icons) {
+        info!("{} icons of iconified clients", if handled_icons { "Taskbar handles" } else { "Handling" });
     }
 
-    conn.flush()?;
+    debug!("{}: handled_icons={}", function_name!(), handled_icons);
+}
 
-    debug!("{}: nclients={}, restack={}", function_name!(), clients.len(), restack_windows);
+/// Recompute urgent tags from all clients and publish them
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn update_urgent_tags(subtle: &Subtle) -> Result<()> {
+    let urgent_tags = calc_urgent_tags(&subtle.clients.borrow());
+
+    publish_urgent_tags(subtle, urgent_tags)
+}
+
+/// Store urgent tags and publish them only when they changed
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `urgent_tags` - New urgent tags
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn publish_urgent_tags(subtle: &Subtle, urgent_tags: Tagging) -> Result<()> {
+    if urgent_tags.bits() != subtle.urgent_tags.replace(urgent_tags).bits() {
+        effect::apply(subtle, Effect::SetRootProperty { prop: Prop::UrgentTags,
+            data: vec![urgent_tags.bits()] })?;
+    }
+
+    debug!("{}: urgent_tags={:?}", function_name!(), urgent_tags);
+
+    Ok(())
+}
+
+/// Check whether the focus window is gone and no client is left on its screen to take over
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `was_focus` - Whether the gone window had the focus
+/// * `screen_idx` - Screen index of the gone window
+///
+/// # Returns
+///
+/// Either [`true`] if the focus needs a fallback or otherwise [`false`]
+pub(crate) fn is_last_focus_gone(subtle: &Subtle, was_focus: bool, screen_idx: isize) -> bool {
+    was_focus && subtle.find_next_client(screen_idx, false).is_none()
+}
+
+/// Move focus to the root window after the last client is gone
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn focus_fallback(subtle: &Subtle) -> Result<()> {
+    // Keep the focus off windows that are already destroyed
+    effect::apply(subtle, Effect::FocusRoot)?;
+
+    subtle.focus_history.unset_first();
+
+    // EWMH: Active window
+    effect::apply(subtle, Effect::SetRootProperty { prop: Prop::ActiveWindow,
+        data: vec![NONE] })?;
+
+    debug!("{}", function_name!());
 
     Ok(())
 }
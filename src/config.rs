@@ -9,11 +9,15 @@
 //! See the file LICENSE for details.
 
 
+use clap::Parser;
 use clap_config_file::ClapConfigFile;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use anyhow::{anyhow, Result};
+use bitflags::bitflags;
+use log::warn;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub(crate) enum MixedConfigVal {
     S(String),
@@ -39,6 +43,66 @@ impl From<&MixedConfigVal> for String {
     }
 }
 
+bitflags! {
+    /// Optional features selected at compile time
+    #[derive(Default, Debug, Copy, Clone, PartialEq)]
+    pub(crate) struct FeatureFlags: u32 {
+        /// Panels and panel items
+        const PANEL = 1 << 0;
+        /// Systray support
+        const TRAY = 1 << 1;
+        /// Panel plugins
+        const PLUGINS = 1 << 2;
+    }
+}
+
+impl FeatureFlags {
+    /// Get features this binary is built with
+    ///
+    /// # Returns
+    ///
+    /// A [`FeatureFlags`] with the enabled features
+    pub(crate) fn compiled() -> Self {
+        let mut features = FeatureFlags::empty();
+
+        features.set(FeatureFlags::PANEL, cfg!(feature = "panel"));
+        features.set(FeatureFlags::TRAY, cfg!(feature = "tray"));
+        features.set(FeatureFlags::PLUGINS, cfg!(feature = "plugins"));
+
+        features
+    }
+}
+
+/// Commands that run instead of the window manager
+#[derive(Parser, Debug)]
+#[command(name = "subtle-rs", no_binary_name = true)]
+pub(crate) enum Command {
+    /// Load plugin NAME or URL, call it once, print the output and exit
+    PluginTest {
+        /// Name of a configured plugin or path or file url to a wasm file
+        name_or_url: String,
+    },
+}
+
+impl Command {
+    /// Parse command from the positional args
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Positional args of the command line
+    ///
+    /// # Returns
+    ///
+    /// A [`clap::error::Result`] with either [`Some`] command, [`None`] without args or otherwise [`clap::Error`]
+    pub(crate) fn parse_args(args: &[String]) -> clap::error::Result<Option<Command>> {
+        if args.is_empty() {
+            return Ok(None);
+        }
+
+        Command::try_parse_from(args).map(Some)
+    }
+}
+
 #[derive(ClapConfigFile)]
 #[config_file_name = "subtle"]
 #[config_file_formats = "yaml,toml,json"]
@@ -59,9 +123,32 @@ pub(crate) struct Config {
     #[config_arg(short = 'D', default_value = false, accept_from = "cli_only")]
     pub(crate) debug: bool,
 
+    /// Log window-management decisions instead of executing them (e.g. inside of Xephyr)
+    #[config_arg(short = 'n', default_value = false, accept_from = "cli_only")]
+    pub(crate) dry_run: bool,
+
+    /// Check config and exit
+    #[config_arg(short = 'k', default_value = false, accept_from = "cli_only")]
+    pub(crate) check: bool,
+
+    /// Run inside of a nested X server like Xephyr
+    #[config_arg(default_value = false, accept_from = "cli_only")]
+    pub(crate) nested: bool,
+
+    /// Adopt clients from the state of the previous instance on restart
+    #[config_arg(default_value = false, accept_from = "cli_only")]
+    pub(crate) handoff: bool,
+
+    /// Run COMMAND instead of the window manager, e.g. plugin-test NAME
+    #[config_arg(name = "COMMAND", positional)]
+    pub(crate) command: Vec<String>,
+
     #[config_arg(multi_value_behavior = "extend", accept_from = "config_only")]
     pub(crate) subtle: HashMap<String, MixedConfigVal>,
 
+    #[config_arg(multi_value_behavior = "extend", accept_from = "config_only")]
+    pub(crate) ignore: Vec<String>,
+
     #[config_arg(name = "style", multi_value_behavior = "extend", accept_from = "config_only")]
     pub(crate) styles: Vec<HashMap<String, MixedConfigVal>>,
 
@@ -83,3 +170,155 @@ pub(crate) struct Config {
     #[config_arg(name = "screen", multi_value_behavior = "extend", accept_from = "config_only")]
     pub(crate) screens: Vec<HashMap<String, MixedConfigVal>>,
 }
+
+/// Check that no config item has an empty name
+///
+/// # Arguments
+///
+/// * `kind` - Kind of the config items for error messages
+/// * `names` - Names of the config items
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn check_empty_names(kind: &str, names: &[String]) -> Result<()> {
+    let empty: Vec<String> = names.iter()
+        .enumerate()
+        .filter(|(_, name)| name.trim().is_empty())
+        .map(|(idx, _)| idx.to_string())
+        .collect();
+
+    if !empty.is_empty() {
+        return Err(anyhow!("Empty {} names at index {}", kind, empty.join(", ")));
+    }
+
+    Ok(())
+}
+
+/// Check that config item names are unique and suffix duplicates when lenient
+///
+/// # Arguments
+///
+/// * `kind` - Kind of the config items for error messages
+/// * `names` - Names of the config items
+/// * `lenient` - Whether to suffix duplicates instead of failing
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn check_duplicate_names(kind: &str, names: &mut [String], lenient: bool) -> Result<()> {
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut duplicates: Vec<String> = Vec::new();
+
+    for idx in 0..names.len() {
+        if seen.insert(names[idx].clone()) {
+            continue;
+        }
+
+        if !lenient {
+            if !duplicates.contains(&names[idx]) {
+                duplicates.push(names[idx].clone());
+            }
+
+            continue;
+        }
+
+        // Find next free suffix, names later in the list might already use it
+        let mut suffix = 2;
+
+        while seen.contains(&format!("{}_{}", names[idx], suffix))
+            || names[idx + 1..].contains(&format!("{}_{}", names[idx], suffix))
+        {
+            suffix += 1;
+        }
+
+        let new_name = format!("{}_{}", names[idx], suffix);
+
+        warn!("Renaming duplicate {} `{}` to `{}`", kind, names[idx], new_name);
+
+        names[idx] = new_name.clone();
+        seen.insert(new_name);
+    }
+
+    if !duplicates.is_empty() {
+        return Err(anyhow!("Duplicate {} names: {}", kind, duplicates.join(", ")));
+    }
+
+    Ok(())
+}
+
+/// Check whether a screen has a tray item in one of its panels
+///
+/// # Arguments
+///
+/// * `values` - Screen config values
+///
+/// # Returns
+///
+/// Either `true` if there is a tray item or otherwise `false`
+pub(crate) fn has_tray_item(values: &HashMap<String, MixedConfigVal>) -> bool {
+    ["top_panel", "bottom_panel"].iter()
+        .filter_map(|key| match values.get(*key) {
+            Some(MixedConfigVal::VS(items)) => Some(items),
+            _ => None,
+        })
+        .flatten()
+        // Strip position marker like the panel parser does
+        .map(|name| if 1 < name.len() { name.strip_prefix(['<', '=', '>']).unwrap_or(name) } else { name })
+        .any(|name| "tray" == name)
+}
+
+/// Collect warnings for config values that need a feature missing in the build
+///
+/// # Arguments
+///
+/// * `screens` - Screen config values
+/// * `plugins` - Plugin config values
+/// * `features` - Features the binary is built with
+///
+/// # Returns
+///
+/// A [`Vec`] with a warning per ignored config value
+pub(crate) fn calc_feature_warnings(screens: &[HashMap<String, MixedConfigVal>],
+                                    plugins: &[HashMap<String, MixedConfigVal>],
+                                    features: FeatureFlags) -> Vec<String>
+{
+    let mut warnings = Vec::new();
+
+    for (screen_idx, values) in screens.iter().enumerate() {
+        let items: Vec<&String> = ["top_panel", "bottom_panel"].iter()
+            .filter_map(|key| match values.get(*key) {
+                Some(MixedConfigVal::VS(items)) => Some(items),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+
+        if !features.contains(FeatureFlags::PANEL) && !items.is_empty() {
+            warnings.push(format!("Ignoring panels of screen `{}`: built without feature `panel`", screen_idx));
+        }
+
+        if !features.contains(FeatureFlags::TRAY)
+            && (has_tray_item(values) || matches!(values.get("tray"), Some(MixedConfigVal::B(true))))
+        {
+            warnings.push(format!("Ignoring tray of screen `{}`: built without feature `tray`", screen_idx));
+        }
+    }
+
+    if !features.contains(FeatureFlags::PLUGINS) && !plugins.is_empty() {
+        warnings.push(format!("Ignoring {} plugin(s): built without feature `plugins`", plugins.len()));
+    }
+
+    warnings
+}
+
+/// Warn about config values that need a feature missing in the build
+///
+/// # Arguments
+///
+/// * `config` - Config values read either from args or config file
+pub(crate) fn check_features(config: &Config) {
+    for warning in calc_feature_warnings(&config.screens, &config.plugins, FeatureFlags::compiled()) {
+        warn!("{}", warning);
+    }
+}
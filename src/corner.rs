@@ -0,0 +1,299 @@
+//!
+//! @package subtle-rs
+//!
+//! @file Hot corner functions
+//! @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+//! @version $Id$
+//!
+//! This program can be distributed under the terms of the GNU GPLv3.
+//! See the file LICENSE for details.
+//!
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use anyhow::{bail, Context, Result};
+use log::{debug, warn};
+use stdext::function_name;
+use x11rb::connection::Connection;
+use x11rb::COPY_DEPTH_FROM_PARENT;
+use x11rb::protocol::xproto::{ConnectionExt, CreateWindowAux, EventMask, Rectangle, Window, WindowClass};
+use crate::config::{Config, MixedConfigVal};
+use crate::effect;
+use crate::effect::Effect;
+use crate::grab;
+use crate::grab::{GrabAction, GrabFlags};
+use crate::subtle::Subtle;
+
+/// Default edge length of corner windows
+pub(crate) const DEFAULT_CORNER_SIZE: u16 = 1;
+
+/// Default time until a corner can be triggered again
+pub(crate) const DEFAULT_CORNER_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum CornerPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl TryFrom<&str> for CornerPosition {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        Ok(match value {
+            "top_left" => CornerPosition::TopLeft,
+            "top_right" => CornerPosition::TopRight,
+            "bottom_left" => CornerPosition::BottomLeft,
+            "bottom_right" => CornerPosition::BottomRight,
+            _ => bail!("Unknown corner `{}`", value),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct CornerAction {
+    /// Position on every screen
+    pub(crate) position: CornerPosition,
+    /// Grab flags of the action
+    pub(crate) flags: GrabFlags,
+    /// Action to dispatch
+    pub(crate) action: GrabAction,
+}
+
+#[derive(Debug)]
+pub(crate) struct Corner {
+    /// Input-only corner window
+    pub(crate) win: Window,
+    /// Index of the screen
+    pub(crate) screen_idx: usize,
+    /// Index of the corner action
+    pub(crate) action_idx: usize,
+    /// Point in time of the last trigger
+    pub(crate) triggered_at: Cell<Option<Instant>>,
+}
+
+/// Calculate geometry of a corner window on a screen
+///
+/// # Arguments
+///
+/// * `screen_geom` - Full geometry of the screen
+/// * `position` - Corner of the screen
+/// * `size` - Edge length of the corner window
+///
+/// # Returns
+///
+/// The geometry of the corner window clamped to the screen
+pub(crate) fn calc_corner_geom(screen_geom: Rectangle, position: CornerPosition, size: u16) -> Rectangle {
+    let width = size.clamp(1, screen_geom.width.max(1));
+    let height = size.clamp(1, screen_geom.height.max(1));
+
+    let right = screen_geom.x as i32 + screen_geom.width as i32 - width as i32;
+    let bottom = screen_geom.y as i32 + screen_geom.height as i32 - height as i32;
+
+    let (x, y) = match position {
+        CornerPosition::TopLeft => (screen_geom.x as i32, screen_geom.y as i32),
+        CornerPosition::TopRight => (right, screen_geom.y as i32),
+        CornerPosition::BottomLeft => (screen_geom.x as i32, bottom),
+        CornerPosition::BottomRight => (right, bottom),
+    };
+
+    Rectangle {
+        x: x as i16,
+        y: y as i16,
+        width,
+        height,
+    }
+}
+
+/// Check whether a corner can be triggered again and remember the trigger
+///
+/// # Arguments
+///
+/// * `triggered_at` - Point in time of the last trigger
+/// * `now` - Current point in time
+/// * `delay` - Time until a corner can be triggered again
+///
+/// # Returns
+///
+/// Either [`true`] if the corner triggers or otherwise [`false`]
+pub(crate) fn should_trigger(triggered_at: &Cell<Option<Instant>>, now: Instant, delay: Duration) -> bool {
+    if triggered_at.get().is_some_and(|at| now.saturating_duration_since(at) < delay) {
+        return false;
+    }
+
+    triggered_at.set(Some(now));
+
+    true
+}
+
+/// Create corner windows for all screens
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn create(subtle: &Subtle) -> Result<()> {
+    let conn = subtle.conn.get().context("Failed to get connection")?;
+    let default_screen = &conn.setup().roots[subtle.screen_num];
+
+    let aux = CreateWindowAux::default()
+        .event_mask(EventMask::ENTER_WINDOW)
+        .override_redirect(1);
+
+    let mut corners = subtle.corners.borrow_mut();
+
+    for (screen_idx, screen) in subtle.screens.iter().enumerate() {
+        for (action_idx, corner_action) in subtle.corner_actions.iter().enumerate() {
+            let geom = calc_corner_geom(screen.base, corner_action.position, subtle.corner_size);
+            let win = conn.generate_id()?;
+
+            conn.create_window(COPY_DEPTH_FROM_PARENT, win, default_screen.root,
+                               geom.x, geom.y, geom.width, geom.height, 0,
+                               WindowClass::INPUT_ONLY, default_screen.root_visual, &aux)?.check()?;
+
+            effect::apply(subtle, Effect::Map(win))?;
+
+            corners.push(Corner {
+                win,
+                screen_idx,
+                action_idx,
+                triggered_at: Cell::new(None),
+            });
+        }
+    }
+
+    debug!("{}: ncorners={}", function_name!(), corners.len());
+
+    Ok(())
+}
+
+/// Destroy all corner windows
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn destroy(subtle: &Subtle) -> Result<()> {
+    if let Some(conn) = subtle.conn.get() {
+        for corner in subtle.corners.borrow_mut().drain(..) {
+            conn.destroy_window(corner.win)?;
+        }
+    }
+
+    debug!("{}", function_name!());
+
+    Ok(())
+}
+
+/// Recreate corner windows e.g. after the screens changed
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn recreate(subtle: &Subtle) -> Result<()> {
+    destroy(subtle)?;
+    create(subtle)?;
+
+    debug!("{}", function_name!());
+
+    Ok(())
+}
+
+/// Raise corner windows above fullscreen clients when enabled
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn raise(subtle: &Subtle) -> Result<()> {
+    if subtle.corners_over_fullscreen {
+        for corner in subtle.corners.borrow().iter() {
+            effect::apply(subtle, Effect::Raise(corner.win))?;
+        }
+    }
+
+    debug!("{}", function_name!());
+
+    Ok(())
+}
+
+/// Parse actions of hot corners and skip invalid ones
+///
+/// # Arguments
+///
+/// * `corners` - Config values of the corners
+///
+/// # Returns
+///
+/// A [`Vec`] with all valid [`CornerAction`]
+pub(crate) fn parse_actions(corners: &HashMap<String, MixedConfigVal>) -> Vec<CornerAction> {
+    let mut actions = Vec::new();
+
+    for (name, value) in corners.iter() {
+        let MixedConfigVal::S(action_name) = value else {
+            warn!("Invalid action for corner `{}`", name);
+
+            continue;
+        };
+
+        match CornerPosition::try_from(name.as_str()) {
+            Ok(position) => match grab::parse_name(action_name) {
+                Ok((flags, action)) => actions.push(CornerAction { position, flags, action }),
+                Err(err) => warn!("Skipping action of corner `{}`: {}", name, err),
+            },
+            Err(err) => warn!("{}", err),
+        }
+    }
+
+    actions
+}
+
+/// Check config and init all hot corner related options
+///
+/// # Arguments
+///
+/// * `config` - Config values read either from args or config file
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
+    if let Some(MixedConfigVal::I(size)) = config.subtle.get("hot_corner_size") && 0 < *size {
+        subtle.corner_size = *size as u16;
+    }
+
+    if let Some(MixedConfigVal::I(delay)) = config.subtle.get("hot_corner_delay") && 0 <= *delay {
+        subtle.corner_delay = Duration::from_millis(*delay as u64);
+    }
+
+    if let Some(MixedConfigVal::B(over_fullscreen)) = config.subtle.get("corners_over_fullscreen") {
+        subtle.corners_over_fullscreen = *over_fullscreen;
+    }
+
+    if let Some(MixedConfigVal::MSS(corners)) = config.subtle.get("hot_corners") {
+        subtle.corner_actions = parse_actions(corners);
+    }
+
+    create(subtle)?;
+
+    debug!("{}: nactions={}", function_name!(), subtle.corner_actions.len());
+
+    Ok(())
+}
@@ -0,0 +1,376 @@
+//!
+//! @package subtle-rs
+//!
+//! @file Cursor functions
+//! @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+//! @version $Id$
+//!
+//! This program can be distributed under the terms of the GNU GPLv3.
+//! See the file LICENSE for details.
+//!
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use log::{debug, warn};
+use stdext::function_name;
+use x11rb::connection::Connection;
+use x11rb::protocol::render::{ConnectionExt as RenderConnectionExt, CreatePictureAux, PictType, Picture};
+use x11rb::protocol::xproto::{ConnectionExt, CreateGCAux, Cursor, Gcontext, ImageFormat, ImageOrder, Pixmap, Screen};
+use x11rb::rust_connection::RustConnection;
+
+// Taken from Xcursor(3)
+const XCURSOR_MAGIC: &[u8; 4] = b"Xcur";
+const XCURSOR_IMAGE_TYPE: u32 = 0xfffd0002;
+const XCURSOR_IMAGE_HEADER_LEN: usize = 36;
+const XCURSOR_DEFAULT_SIZE: u32 = 24;
+const XCURSOR_DEFAULT_PATH: &str = "~/.local/share/icons:~/.icons:/usr/share/icons:/usr/share/pixmaps";
+
+#[derive(Default, Debug, Clone)]
+pub(crate) struct CursorImage {
+    /// Width of the image
+    pub(crate) width: u16,
+    /// Height of the image
+    pub(crate) height: u16,
+    /// X coordinate of the hotspot
+    pub(crate) xhot: u16,
+    /// Y coordinate of the hotspot
+    pub(crate) yhot: u16,
+    /// Premultiplied ARGB pixels
+    pub(crate) pixels: Vec<u32>,
+}
+
+/// Read little-endian value from buffer
+///
+/// # Arguments
+///
+/// * `data` - Data buffer
+/// * `offset` - Offset of the value
+///
+/// # Returns
+///
+/// A [`Result`] with either [`u32`] on success or otherwise [`anyhow::Error`]
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    let bytes = data.get(offset..offset + 4)
+        .context("Unexpected end of cursor file")?;
+
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Parse image from Xcursor file data
+///
+/// # Arguments
+///
+/// * `data` - File content
+/// * `size` - Preferred nominal size
+///
+/// # Returns
+///
+/// A [`Result`] with either [`CursorImage`] closest to size on success or otherwise [`anyhow::Error`]
+pub(crate) fn parse_xcursor(data: &[u8], size: u32) -> Result<CursorImage> {
+    if data.len() < 16 || &data[0..4] != XCURSOR_MAGIC {
+        return Err(anyhow!("Invalid cursor file magic"));
+    }
+
+    let header_len = read_u32(data, 4)? as usize;
+    let ntoc = read_u32(data, 12)? as usize;
+
+    // Find image with the closest nominal size
+    let mut best: Option<(u32, usize)> = None;
+
+    for toc_idx in 0..ntoc {
+        let toc_offset = header_len + toc_idx * 12;
+
+        if XCURSOR_IMAGE_TYPE != read_u32(data, toc_offset)? {
+            continue;
+        }
+
+        let nominal_size = read_u32(data, toc_offset + 4)?;
+        let position = read_u32(data, toc_offset + 8)? as usize;
+
+        if best.is_none_or(|(best_size, _)| nominal_size.abs_diff(size) < best_size.abs_diff(size)) {
+            best = Some((nominal_size, position));
+        }
+    }
+
+    let (_, position) = best.context("No image found in cursor file")?;
+
+    // Read image chunk
+    if XCURSOR_IMAGE_TYPE != read_u32(data, position + 4)? {
+        return Err(anyhow!("Invalid cursor image chunk"));
+    }
+
+    let width = read_u32(data, position + 16)?;
+    let height = read_u32(data, position + 20)?;
+    let xhot = read_u32(data, position + 24)?;
+    let yhot = read_u32(data, position + 28)?;
+
+    if 0 == width || 0 == height || 0x7fff < width || 0x7fff < height || xhot > width || yhot > height {
+        return Err(anyhow!("Invalid cursor image size"));
+    }
+
+    let pixels_offset = position + XCURSOR_IMAGE_HEADER_LEN;
+    let pixels = (0..(width * height) as usize)
+        .map(|pixel_idx| read_u32(data, pixels_offset + pixel_idx * 4))
+        .collect::<Result<Vec<u32>>>()?;
+
+    Ok(CursorImage {
+        width: width as u16,
+        height: height as u16,
+        xhot: xhot as u16,
+        yhot: yhot as u16,
+        pixels,
+    })
+}
+
+/// Parse inherited themes from index.theme
+///
+/// # Arguments
+///
+/// * `index_theme` - Content of index.theme
+///
+/// # Returns
+///
+/// A [`Vec`] with names of inherited themes
+pub(crate) fn parse_inherits(index_theme: &str) -> Vec<String> {
+    index_theme.lines()
+        .filter_map(|line| line.trim().strip_prefix("Inherits"))
+        .filter_map(|rest| rest.trim_start().strip_prefix('='))
+        .flat_map(|value| value.split([',', ';', ' ']))
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Get search paths for cursor themes
+///
+/// # Arguments
+///
+/// * `xcursor_path` - Value of XCURSOR_PATH
+/// * `home` - Value of HOME
+/// * `xdg_data_home` - Value of XDG_DATA_HOME
+///
+/// # Returns
+///
+/// A [`Vec`] with all paths to search in order
+pub(crate) fn get_search_paths(xcursor_path: Option<&str>, home: Option<&str>,
+                               xdg_data_home: Option<&str>) -> Vec<PathBuf>
+{
+    let mut paths = Vec::new();
+
+    // XCURSOR_PATH replaces the default path completely
+    if xcursor_path.is_none() && let Some(xdg_data_home) = xdg_data_home {
+        paths.push(Path::new(xdg_data_home).join("icons"));
+    }
+
+    for path in xcursor_path.unwrap_or(XCURSOR_DEFAULT_PATH).split(':') {
+        if path.is_empty() {
+            continue;
+        }
+
+        let path = if let Some(rest) = path.strip_prefix("~/") {
+            match home {
+                Some(home) => Path::new(home).join(rest),
+                None => continue,
+            }
+        } else {
+            PathBuf::from(path)
+        };
+
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+
+    paths
+}
+
+/// Find cursor file in theme and inherited themes
+///
+/// # Arguments
+///
+/// * `search_paths` - Paths to search for themes
+/// * `theme` - Name of the theme
+/// * `name` - Name of the cursor
+/// * `visited` - Already visited themes
+///
+/// # Returns
+///
+/// Either [`Some`] with path of the cursor file or otherwise [`None`]
+fn find_in_theme(search_paths: &[PathBuf], theme: &str, name: &str,
+                 visited: &mut HashSet<String>) -> Option<PathBuf>
+{
+    if !visited.insert(theme.to_string()) {
+        return None;
+    }
+
+    // Check cursors in all theme dirs before descending
+    for path in search_paths.iter() {
+        let cursor_path = path.join(theme).join("cursors").join(name);
+
+        if cursor_path.is_file() {
+            return Some(cursor_path);
+        }
+    }
+
+    for path in search_paths.iter() {
+        if let Ok(index_theme) = std::fs::read_to_string(path.join(theme).join("index.theme")) {
+            for parent in parse_inherits(&index_theme) {
+                if let Some(cursor_path) = find_in_theme(search_paths, &parent, name, visited) {
+                    return Some(cursor_path);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Find cursor file by theme and name
+///
+/// # Arguments
+///
+/// * `search_paths` - Paths to search for themes
+/// * `theme` - Name of the theme
+/// * `name` - Name of the cursor
+///
+/// # Returns
+///
+/// Either [`Some`] with path of the cursor file or otherwise [`None`]
+pub(crate) fn find_cursor_file(search_paths: &[PathBuf], theme: &str, name: &str) -> Option<PathBuf> {
+    let mut visited = HashSet::new();
+
+    find_in_theme(search_paths, theme, name, &mut visited)
+        .or_else(|| find_in_theme(search_paths, "default", name, &mut visited))
+}
+
+/// Temporary resources of a cursor upload that are freed on drop
+struct UploadGuard<'a> {
+    /// Connection to X11
+    conn: &'a RustConnection,
+    /// Pixmap with the image
+    pixmap: Pixmap,
+    /// Graphics context to upload the image
+    gc: Option<Gcontext>,
+    /// Picture of the pixmap
+    picture: Option<Picture>,
+}
+
+impl Drop for UploadGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(picture) = self.picture
+            && let Err(err) = self.conn.render_free_picture(picture)
+        {
+            warn!("Failed to free cursor picture: {}", err);
+        }
+
+        if let Some(gc) = self.gc
+            && let Err(err) = self.conn.free_gc(gc)
+        {
+            warn!("Failed to free cursor gc: {}", err);
+        }
+
+        if let Err(err) = self.conn.free_pixmap(self.pixmap) {
+            warn!("Failed to free cursor pixmap: {}", err);
+        }
+    }
+}
+
+/// Create cursor from image via render extension
+///
+/// # Arguments
+///
+/// * `conn` - Connection to X11
+/// * `screen` - Screen to create the cursor on
+/// * `image` - Cursor image
+///
+/// # Returns
+///
+/// A [`Result`] with either [`Cursor`] on success or otherwise [`anyhow::Error`]
+fn create_cursor(conn: &RustConnection, screen: &Screen, image: &CursorImage) -> Result<Cursor> {
+    conn.render_query_version(0, 11)?.reply()?;
+
+    // Find ARGB32 format
+    let format = conn.render_query_pict_formats()?.reply()?.formats.iter()
+        .find(|format| PictType::DIRECT == format.type_ && 32 == format.depth
+            && 24 == format.direct.alpha_shift && 0xff == format.direct.alpha_mask
+            && 16 == format.direct.red_shift && 8 == format.direct.green_shift
+            && 0 == format.direct.blue_shift)
+        .map(|format| format.id)
+        .context("Failed to find ARGB32 picture format")?;
+
+    let data: Vec<u8> = image.pixels.iter()
+        .flat_map(|pixel| if ImageOrder::LSB_FIRST == conn.setup().image_byte_order {
+            pixel.to_le_bytes()
+        } else {
+            pixel.to_be_bytes()
+        })
+        .collect();
+
+    // Upload image
+    let pixmap = conn.generate_id()?;
+
+    conn.create_pixmap(32, pixmap, screen.root, image.width, image.height)?.check()?;
+
+    // Free temporary resources on every path from here
+    let mut guard = UploadGuard { conn, pixmap, gc: None, picture: None };
+
+    let gc = conn.generate_id()?;
+
+    conn.create_gc(gc, pixmap, &CreateGCAux::default())?.check()?;
+    guard.gc = Some(gc);
+
+    conn.put_image(ImageFormat::Z_PIXMAP, pixmap, gc, image.width, image.height,
+                   0, 0, 0, 32, &data)?.check()?;
+
+    // Create cursor from picture
+    let picture = conn.generate_id()?;
+
+    conn.render_create_picture(picture, pixmap, format, &CreatePictureAux::default())?.check()?;
+    guard.picture = Some(picture);
+
+    let cursor = conn.generate_id()?;
+
+    conn.render_create_cursor(cursor, picture, image.xhot, image.yhot)?.check()?;
+
+    Ok(cursor)
+}
+
+/// Load cursor from theme
+///
+/// # Arguments
+///
+/// * `conn` - Connection to X11
+/// * `screen` - Screen to create the cursor on
+/// * `theme` - Name of the theme or [`None`] for XCURSOR_THEME
+/// * `size` - Size of the cursor or [`None`] for XCURSOR_SIZE
+/// * `name` - Name of the cursor
+///
+/// # Returns
+///
+/// A [`Result`] with either [`Cursor`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn load(conn: &RustConnection, screen: &Screen, theme: Option<&str>,
+                   size: Option<u32>, name: &str) -> Result<Cursor>
+{
+    let theme = theme.map(String::from)
+        .or_else(|| std::env::var("XCURSOR_THEME").ok())
+        .unwrap_or_else(|| String::from("default"));
+    let size = size
+        .or_else(|| std::env::var("XCURSOR_SIZE").ok().and_then(|size| size.parse().ok()))
+        .unwrap_or(XCURSOR_DEFAULT_SIZE);
+
+    let search_paths = get_search_paths(std::env::var("XCURSOR_PATH").ok().as_deref(),
+                                        std::env::var("HOME").ok().as_deref(),
+                                        std::env::var("XDG_DATA_HOME").ok().as_deref());
+
+    let cursor_path = find_cursor_file(&search_paths, &theme, name)
+        .with_context(|| format!("Failed to find cursor `{}` in theme `{}`", name, theme))?;
+
+    let image = parse_xcursor(&std::fs::read(&cursor_path)?, size)?;
+    let cursor = create_cursor(conn, screen, &image)?;
+
+    debug!("{}: theme={}, size={}, name={}, path={:?}", function_name!(),
+        theme, size, name, cursor_path);
+
+    Ok(cursor)
+}
@@ -11,16 +11,21 @@
 
 use std::process;
 use anyhow::{anyhow, Context, Result};
-use log::{debug, info};
+use log::{debug, info, warn};
 use stdext::function_name;
 use struct_iterable::Iterable;
 use x11rb::connection::Connection;
 use x11rb::{COPY_DEPTH_FROM_PARENT, CURRENT_TIME, NONE};
-use x11rb::protocol::xproto::{AtomEnum, CapStyle, ChangeWindowAttributesAux, ConnectionExt, CreateGCAux, CreateWindowAux, EventMask, FillStyle, FontWrapper, InputFocus, JoinStyle, LineStyle, MapState, PropMode, SubwindowMode, Time, WindowClass, GX};
+use x11rb::protocol::randr::{ConnectionExt as RandrConnectionExt, NotifyMask};
+use x11rb::protocol::screensaver;
+use x11rb::protocol::screensaver::ConnectionExt as ScreensaverConnectionExt;
+use x11rb::protocol::xproto::{AtomEnum, CapStyle, ChangeWindowAttributesAux, ConnectionExt, CreateGCAux, CreateWindowAux, Cursor, EventMask, FillStyle, Font, FontWrapper, InputFocus, JoinStyle, LineStyle, MapState, PropMode, Screen, SubwindowMode, Time, Window, WindowClass, GX};
+use x11rb::rust_connection::RustConnection;
 use x11rb::wrapper::ConnectionExt as ConnectionWrapperExt;
-use crate::{client, ewmh, Config, Subtle};
+use crate::{client, config, cursor, ewmh, ignore, Config, Subtle};
 use crate::client::Client;
 use crate::config::MixedConfigVal;
+use crate::error::{Classify, InitError};
 use crate::subtle::SubtleFlags;
 
 // Taken from /usr/include/X11/cursorfont.h
@@ -28,6 +33,54 @@ const XC_LEFT_PTR: u16 = 68;
 const XC_DOTBOX: u16 = 40;
 const XC_SIZING: u16 = 120;
 
+/// Create cursor from theme or fall back to cursor font
+///
+/// # Arguments
+///
+/// * `conn` - Connection to X11
+/// * `screen` - Screen to create the cursor on
+/// * `config` - Config values read either from args or config file
+/// * `font` - Cursor font for fallback
+/// * `name_key` - Config key of the cursor name
+/// * `default_name` - Default name of the cursor
+/// * `glyph` - Fallback glyph of the cursor font
+///
+/// # Returns
+///
+/// A [`Result`] with either [`Cursor`] on success or otherwise [`anyhow::Error`]
+fn create_cursor(conn: &RustConnection, screen: &Screen, config: &Config, font: Font,
+                 name_key: &str, default_name: &str, glyph: u16) -> Result<Cursor>
+{
+    let theme = match config.subtle.get("cursor_theme") {
+        Some(MixedConfigVal::S(theme)) => Some(theme.as_str()),
+        _ => None,
+    };
+
+    let size = match config.subtle.get("cursor_size") {
+        Some(MixedConfigVal::I(size)) if 0 < *size => Some(*size as u32),
+        _ => None,
+    };
+
+    let name = match config.subtle.get(name_key) {
+        Some(MixedConfigVal::S(name)) => name.as_str(),
+        _ => default_name,
+    };
+
+    match cursor::load(conn, screen, theme, size, name) {
+        Ok(cursor) => Ok(cursor),
+        Err(err) => {
+            warn!("Failed to load cursor `{}`, using cursor font: {}", name, err);
+
+            let cursor = conn.generate_id()?;
+
+            conn.create_glyph_cursor(cursor, font, font, glyph, glyph + 1, 0, 0, 0,
+                                     u16::MAX, u16::MAX, u16::MAX)?.check()?;
+
+            Ok(cursor)
+        }
+    }
+}
+
 /// Check config and init all display related options
 ///
 /// # Arguments
@@ -39,7 +92,7 @@ const XC_SIZING: u16 = 120;
 ///
 /// A `Result` with either `Unit` on success or otherwise `Error
 pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
-    let (conn, screen_num) = x11rb::connect(Some(&*config.display))?;
+    let (conn, screen_num) = x11rb::connect(Some(&*config.display)).fatal()?;
 
     let default_screen = &conn.setup().roots[screen_num];
 
@@ -63,17 +116,6 @@ pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
                               AtomEnum::STRING, wm_name.as_bytes())?.check()?;
     }
 
-    // Create tray window
-    subtle.tray_win = conn.generate_id()?;
-
-    let aux = CreateWindowAux::default()
-        .event_mask(EventMask::KEY_PRESS | EventMask::BUTTON_PRESS)
-        .override_redirect(1);
-
-    conn.create_window(COPY_DEPTH_FROM_PARENT, subtle.tray_win, default_screen.root,
-                       0, 0, 1, 1, 0,
-                       WindowClass::INPUT_OUTPUT, default_screen.root_visual, &aux)?.check()?;
-
     // Create double buffer id and create/resize later
     subtle.panel_double_buffer = conn.generate_id()?;
 
@@ -87,9 +129,20 @@ pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
     if conn.query_extension("RANDR".as_ref())?.reply()?.present {
         subtle.flags.insert(SubtleFlags::XRANDR);
 
+        // Get notified about connected or disconnected outputs
+        conn.randr_select_input(default_screen.root, NotifyMask::SCREEN_CHANGE
+            | NotifyMask::CRTC_CHANGE | NotifyMask::OUTPUT_CHANGE)?.check()?;
+
         debug!("Found xrandr extension");
     }
 
+    if conn.query_extension("MIT-SCREEN-SAVER".as_ref())?.reply()?.present {
+        // Get notified when the screen is unblanked
+        conn.screensaver_select_input(default_screen.root, screensaver::Event::NOTIFY_MASK)?.check()?;
+
+        debug!("Found screensaver extension");
+    }
+
     // Create GCs
     let aux = CreateGCAux::default()
         .function(GX::INVERT)
@@ -114,20 +167,12 @@ pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
     // Create cursors
     let font_wrapper = FontWrapper::open_font(&conn, "cursor".as_bytes())?;
 
-    subtle.arrow_cursor = conn.generate_id()?;
-    conn.create_glyph_cursor(subtle.arrow_cursor, font_wrapper.font(), font_wrapper.font(),
-                             XC_LEFT_PTR, XC_LEFT_PTR + 1, 0, 0, 0,
-                             u16::MAX, u16::MAX, u16::MAX)?.check()?;
-
-    subtle.move_cursor = conn.generate_id()?;
-    conn.create_glyph_cursor(subtle.move_cursor, font_wrapper.font(), font_wrapper.font(),
-                             XC_DOTBOX, XC_DOTBOX + 1, 0, 0, 0,
-                             u16::MAX, u16::MAX, u16::MAX)?.check()?;
-
-    subtle.resize_cursor = conn.generate_id()?;
-    conn.create_glyph_cursor(subtle.resize_cursor, font_wrapper.font(), font_wrapper.font(),
-                             XC_SIZING, XC_SIZING + 1, 0, 0, 0,
-                             u16::MAX, u16::MAX, u16::MAX)?.check()?;
+    subtle.arrow_cursor = create_cursor(&conn, default_screen, config, font_wrapper.font(),
+                                        "arrow_cursor", "left_ptr", XC_LEFT_PTR)?;
+    subtle.move_cursor = create_cursor(&conn, default_screen, config, font_wrapper.font(),
+                                       "move_cursor", "dotbox", XC_DOTBOX)?;
+    subtle.resize_cursor = create_cursor(&conn, default_screen, config, font_wrapper.font(),
+                                         "resize_cursor", "sizing", XC_SIZING)?;
 
     drop(font_wrapper);
 
@@ -149,11 +194,46 @@ pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
     subtle.screen_num = screen_num;
     subtle.conn.set(conn).map_err(|_e| anyhow!("Connection already set?"))?;
 
+    // Create tray window only when a panel can host it
+    if subtle.flags.intersects(SubtleFlags::TRAY) && config.screens.iter().any(config::has_tray_item) {
+        let conn = subtle.conn.get().context("Failed to get connection")?;
+
+        subtle.tray_selection = conn.intern_atom(false,
+                                                 calc_tray_selection_name(screen_num).as_bytes())?.reply()?.atom;
+        subtle.tray_win = conn.generate_id()?;
+
+        create_tray_win(subtle)?;
+    } else {
+        subtle.flags.remove(SubtleFlags::TRAY);
+    }
+
     info!("Display ({}) is {}x{}", config.display, subtle.width, subtle.height);
 
     Ok(())
 }
 
+/// Check whether another window manager owns the display
+///
+/// # Arguments
+///
+/// * `owner` - Owner of the session selection
+/// * `is_replace` - Whether to replace a running window manager
+///
+/// # Returns
+///
+/// A [`Result`] with either [`true`] if the owner must be replaced or otherwise [`InitError`]
+pub(crate) fn check_wm_owner(owner: Window, is_replace: bool) -> Result<bool, InitError> {
+    if NONE == owner {
+        return Ok(false);
+    }
+
+    if !is_replace {
+        return Err(InitError::Fatal(anyhow!("Found a running window manager")));
+    }
+
+    Ok(true)
+}
+
 /// Claim display selection
 ///
 /// # Arguments
@@ -170,11 +250,7 @@ pub(crate) fn claim(subtle: &Subtle) -> Result<()> {
 
     let owner = conn.get_selection_owner(session)?.reply()?.owner;
 
-    if NONE != owner {
-        if !subtle.flags.contains(SubtleFlags::REPLACE) {
-            return Err(anyhow!("Found a running window manager"))
-        }
-
+    if check_wm_owner(owner, subtle.flags.contains(SubtleFlags::REPLACE))? {
         let aux = ChangeWindowAttributesAux::default()
             .event_mask(EventMask::STRUCTURE_NOTIFY);
         conn.change_window_attributes(owner, &aux)?.check()?;
@@ -214,7 +290,7 @@ pub(crate) fn scan(subtle: &Subtle) -> Result<()> {
         if !attr.override_redirect {
             #[allow(clippy::single_match)]
             match attr.map_state {
-                MapState::VIEWABLE => {
+                MapState::VIEWABLE if !ignore::check_win(subtle, win)? => {
                     let client = Client::new(subtle, win)?;
 
                     subtle.add_client(client);
@@ -224,6 +300,7 @@ pub(crate) fn scan(subtle: &Subtle) -> Result<()> {
         }
     }
 
+    client::update_handled_icons(subtle);
     client::publish(subtle, false)?;
 
     debug!("{}", function_name!());
@@ -231,6 +308,45 @@ pub(crate) fn scan(subtle: &Subtle) -> Result<()> {
     Ok(())
 }
 
+/// Calculate name of the tray selection of a screen
+///
+/// # Arguments
+///
+/// * `screen_num` - X11 screen number
+///
+/// # Returns
+///
+/// Name of the selection atom
+pub(crate) fn calc_tray_selection_name(screen_num: usize) -> String {
+    format!("_NET_SYSTEM_TRAY_S{}", screen_num)
+}
+
+/// Create tray window with the id stored in the global state
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn create_tray_win(subtle: &Subtle) -> Result<()> {
+    let conn = subtle.conn.get().context("Failed to get connection")?;
+    let default_screen = &conn.setup().roots[subtle.screen_num];
+
+    let aux = CreateWindowAux::default()
+        .event_mask(EventMask::KEY_PRESS | EventMask::BUTTON_PRESS | EventMask::STRUCTURE_NOTIFY)
+        .override_redirect(1);
+
+    conn.create_window(COPY_DEPTH_FROM_PARENT, subtle.tray_win, default_screen.root,
+                       0, 0, 1, 1, 0,
+                       WindowClass::INPUT_OUTPUT, default_screen.root_visual, &aux)?.check()?;
+
+    debug!("{}: win={}", function_name!(), subtle.tray_win);
+
+    Ok(())
+}
+
 /// Get tray selection for display
 ///
 /// # Arguments
@@ -245,9 +361,9 @@ pub(crate) fn select_tray(subtle: &Subtle) -> Result<()> {
     let atoms = subtle.atoms.get().unwrap();
 
     // Acquire tray selection
-    conn.set_selection_owner(subtle.tray_win, atoms._NET_SYSTEM_TRAY_S0, CURRENT_TIME)?.check()?;
+    conn.set_selection_owner(subtle.tray_win, subtle.tray_selection, CURRENT_TIME)?.check()?;
 
-    if conn.get_selection_owner(atoms._NET_SYSTEM_TRAY_S0)?.reply()?.owner != subtle.tray_win {
+    if conn.get_selection_owner(subtle.tray_selection)?.reply()?.owner != subtle.tray_win {
         return Err(anyhow!("Failed getting system tray selection"))
     }
 
@@ -255,7 +371,7 @@ pub(crate) fn select_tray(subtle: &Subtle) -> Result<()> {
     let default_screen = &conn.setup().roots[subtle.screen_num];
 
     ewmh::send_message(subtle, default_screen.root, atoms.MANAGER, &[CURRENT_TIME,
-        atoms._NET_SYSTEM_TRAY_S0, subtle.tray_win, 0, 0])?;
+        subtle.tray_selection, subtle.tray_win, 0, 0])?;
 
     debug!("{}", function_name!());
 
@@ -273,14 +389,13 @@ pub(crate) fn select_tray(subtle: &Subtle) -> Result<()> {
 /// A `Result` with either `Unit` on success or otherwise `Error
 pub(crate) fn deselect_tray(subtle: &Subtle) -> Result<()> {
     let conn = subtle.conn.get().unwrap();
-    let atoms = subtle.atoms.get().unwrap();
 
-    if conn.get_selection_owner(atoms._NET_SYSTEM_TRAY_S0)?.reply()?.owner == subtle.tray_win {
-        conn.set_selection_owner(NONE, atoms._NET_SYSTEM_TRAY_S0, CURRENT_TIME)?.check()?;
+    if conn.get_selection_owner(subtle.tray_selection)?.reply()?.owner == subtle.tray_win {
+        conn.set_selection_owner(NONE, subtle.tray_selection, CURRENT_TIME)?.check()?;
 
         let default_screen = &conn.setup().roots[subtle.screen_num];
 
-        conn.delete_property(default_screen.root, atoms._NET_SYSTEM_TRAY_S0)?.check()?;
+        conn.delete_property(default_screen.root, subtle.tray_selection)?.check()?;
     }
 
     debug!("{}", function_name!());
@@ -341,12 +456,6 @@ pub(crate) fn publish(subtle: &Subtle) -> Result<()> {
     conn.change_property8(PropMode::REPLACE, subtle.support_win, atoms.SUBTLE_VERSION,
                           AtomEnum::STRING, env!("CARGO_PKG_VERSION").as_bytes())?.check()?;
 
-    // EWMH: Desktop geometry
-    let data: [u32; 2] = [subtle.width as u32, subtle.height as u32];
-
-    conn.change_property32(PropMode::REPLACE, default_screen.root, atoms._NET_DESKTOP_GEOMETRY,
-                           AtomEnum::CARDINAL, &data)?.check()?;
-
     conn.flush()?;
 
     debug!("{}", function_name!());
@@ -381,7 +490,10 @@ pub(crate) fn finish(subtle: &mut Subtle) -> Result<()> {
 
         // Destroy windows
         conn.destroy_window(subtle.support_win)?;
-        conn.destroy_window(subtle.tray_win)?;
+
+        if NONE != subtle.tray_win {
+            conn.destroy_window(subtle.tray_win)?;
+        }
 
         // Destroy pixmaps
         if 0 != subtle.panel_double_buffer {
@@ -394,6 +506,21 @@ pub(crate) fn finish(subtle: &mut Subtle) -> Result<()> {
         for font in subtle.fonts.iter() {
             font.kill(conn)?;
         }
+
+        // Free icons of views and panels
+        for view in subtle.views.borrow().iter() {
+            if let Some(icon) = view.icon.as_ref() {
+                icon.kill(conn)?;
+            }
+        }
+
+        for screen in subtle.screens.iter() {
+            for panel in screen.panels.iter() {
+                if let Some(icon) = panel.icon.as_ref() {
+                    icon.kill(conn)?;
+                }
+            }
+        }
     }
 
     debug!("{}", function_name!());
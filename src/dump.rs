@@ -0,0 +1,197 @@
+//!
+//! @package subtle-rs
+//!
+//! @file Debug dump functions
+//! @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+//! @version $Id$
+//!
+//! This program can be distributed under the terms of the GNU GPLv3.
+//! See the file LICENSE for details.
+//!
+
+use anyhow::Result;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use stdext::function_name;
+use x11rb::NONE;
+use x11rb::protocol::xproto::{Rectangle, Window};
+use crate::client::Client;
+use crate::screen::Screen;
+use crate::subtle::Subtle;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct GeomDump {
+    pub(crate) x: i16,
+    pub(crate) y: i16,
+    pub(crate) width: u16,
+    pub(crate) height: u16,
+}
+
+impl From<&Rectangle> for GeomDump {
+    fn from(geom: &Rectangle) -> Self {
+        GeomDump { x: geom.x, y: geom.y, width: geom.width, height: geom.height }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SizeHintsDump {
+    pub(crate) min_ratio: f32,
+    pub(crate) max_ratio: f32,
+    pub(crate) min_width: u16,
+    pub(crate) min_height: u16,
+    pub(crate) max_width: i16,
+    pub(crate) max_height: i16,
+    pub(crate) width_inc: u16,
+    pub(crate) height_inc: u16,
+    pub(crate) base_width: u16,
+    pub(crate) base_height: u16,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ClientDump {
+    pub(crate) win: Window,
+    pub(crate) leader: Window,
+    pub(crate) transient_for: Window,
+    pub(crate) name: String,
+    pub(crate) instance: String,
+    pub(crate) klass: String,
+    pub(crate) role: String,
+    /// Flag names in bit order
+    pub(crate) flags: Vec<String>,
+    pub(crate) tags: u32,
+    pub(crate) geom: GeomDump,
+    pub(crate) size_hints: SizeHintsDump,
+    pub(crate) screen_idx: isize,
+    pub(crate) gravity_idx: isize,
+    /// Gravity per view
+    pub(crate) gravities: Vec<usize>,
+    pub(crate) order: String,
+}
+
+impl From<&Client> for ClientDump {
+    fn from(client: &Client) -> Self {
+        ClientDump {
+            win: client.win,
+            leader: client.leader,
+            transient_for: client.transient_for,
+            name: client.name.clone(),
+            instance: client.instance.clone(),
+            klass: client.klass.clone(),
+            role: client.role.clone(),
+            flags: client.flags.iter_names().map(|(name, _)| name.to_string()).collect(),
+            tags: client.tags.bits(),
+            geom: GeomDump::from(&client.geom),
+            size_hints: SizeHintsDump {
+                min_ratio: client.min_ratio,
+                max_ratio: client.max_ratio,
+                min_width: client.min_width,
+                min_height: client.min_height,
+                max_width: client.max_width,
+                max_height: client.max_height,
+                width_inc: client.width_inc,
+                height_inc: client.height_inc,
+                base_width: client.base_width,
+                base_height: client.base_height,
+            },
+            screen_idx: client.screen_idx,
+            gravity_idx: client.gravity_idx,
+            gravities: client.gravities.clone(),
+            order: format!("{:?}", client.order),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ScreenDump {
+    pub(crate) geom: GeomDump,
+    pub(crate) base: GeomDump,
+    pub(crate) view_idx: isize,
+    /// Flag names in bit order
+    pub(crate) flags: Vec<String>,
+}
+
+impl From<&Screen> for ScreenDump {
+    fn from(screen: &Screen) -> Self {
+        ScreenDump {
+            geom: GeomDump::from(&screen.geom),
+            base: GeomDump::from(&screen.base),
+            view_idx: screen.view_idx.get(),
+            flags: screen.flags.iter_names().map(|(name, _)| name.to_string()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct StateDump {
+    pub(crate) visible_tags: u32,
+    pub(crate) visible_views: u32,
+    pub(crate) urgent_tags: u32,
+    pub(crate) focus_win: Option<Window>,
+    pub(crate) screens: Vec<ScreenDump>,
+}
+
+impl From<&Subtle> for StateDump {
+    fn from(subtle: &Subtle) -> Self {
+        StateDump {
+            visible_tags: subtle.visible_tags.get().bits(),
+            visible_views: subtle.visible_views.get().bits(),
+            urgent_tags: subtle.urgent_tags.get().bits(),
+            focus_win: subtle.focus_history.first(),
+            screens: subtle.screens.iter().map(ScreenDump::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Dump {
+    pub(crate) client: ClientDump,
+    pub(crate) state: StateDump,
+}
+
+/// Serialize dump into pretty JSON
+///
+/// # Arguments
+///
+/// * `dump` - Dump to serialize
+///
+/// # Returns
+///
+/// A [`Result`] with either [`String`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn to_json(dump: &Dump) -> Result<String> {
+    Ok(serde_json::to_string_pretty(dump)?)
+}
+
+/// Dump state of a client and the relevant global state to the log
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `win` - Client window or [`NONE`] for the focus client
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn dump_client(subtle: &Subtle, win: Window) -> Result<()> {
+    let maybe_client = if NONE == win {
+        subtle.find_focus_client()
+    } else {
+        subtle.find_client(win)
+    };
+
+    let Some(client) = maybe_client else {
+        warn!("Failed to find window `{}` to dump", win);
+
+        return Ok(());
+    };
+
+    let dump = Dump {
+        client: ClientDump::from(&*client),
+        state: StateDump::from(subtle),
+    };
+
+    info!("Dump of window `{}`:\n{}", client.win, to_json(&dump)?);
+
+    debug!("{}: win={}", function_name!(), client.win);
+
+    Ok(())
+}
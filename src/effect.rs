@@ -0,0 +1,351 @@
+//!
+//! @package subtle-rs
+//!
+//! @file Effect functions
+//! @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+//! @version $Id$
+//!
+//! This program can be distributed under the terms of the GNU GPLv3.
+//! See the file LICENSE for details.
+//!
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use anyhow::{Context, Result};
+use log::{debug, info};
+use stdext::function_name;
+use x11rb::connection::Connection;
+use x11rb::NONE;
+use x11rb::protocol::xproto::{Atom, AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent, ConfigureWindowAux, ConnectionExt, EventMask, InputFocus, PropMode, StackMode, Window, CLIENT_MESSAGE_EVENT};
+use x11rb::wrapper::ConnectionExt as ConnectionExtWrapper;
+use crate::ewmh::{AllowedActions, Atoms, EWMHStateFlags, WMState};
+use crate::grab;
+use crate::grab::GrabFlags;
+use crate::subtle::Subtle;
+
+/// Max number of effects the logging executor keeps
+pub(crate) const MAX_RECORDED: usize = 1024;
+
+/// Properties written by effects
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Prop {
+    /// Tags of a client
+    ClientTags,
+    /// Gravity of a client
+    ClientGravity,
+    /// Screen of a client
+    ClientScreen,
+    /// Mode flags of a client
+    ClientFlags,
+    /// Versioned state of a client for a handoff
+    ClientState,
+    /// Desktop of a client
+    WmDesktop,
+    /// Currently selected desktop
+    CurrentDesktop,
+    /// Focus history
+    ActiveWindow,
+    /// Tags of urgent clients
+    UrgentTags,
+    /// Tags of visible views
+    VisibleTags,
+    /// Visible views
+    VisibleViews,
+    /// Geometries of all clients
+    ClientGeometries,
+    /// Border widths around a window
+    FrameExtents,
+    /// Views of all screens
+    ScreenViews,
+}
+
+impl Prop {
+    /// Get atom and type of the property
+    ///
+    /// # Arguments
+    ///
+    /// * `atoms` - Interned atoms
+    ///
+    /// # Returns
+    ///
+    /// A tuple with the property atom and its type
+    fn get_atoms(&self, atoms: &Atoms) -> (Atom, Atom) {
+        match self {
+            Prop::ClientTags => (atoms.SUBTLE_CLIENT_TAGS, AtomEnum::CARDINAL.into()),
+            Prop::ClientGravity => (atoms.SUBTLE_CLIENT_GRAVITY, AtomEnum::CARDINAL.into()),
+            Prop::ClientScreen => (atoms.SUBTLE_CLIENT_SCREEN, AtomEnum::CARDINAL.into()),
+            Prop::ClientFlags => (atoms.SUBTLE_CLIENT_FLAGS, AtomEnum::CARDINAL.into()),
+            Prop::ClientState => (atoms.SUBTLE_CLIENT_STATE, AtomEnum::CARDINAL.into()),
+            Prop::WmDesktop => (atoms._NET_WM_DESKTOP, AtomEnum::CARDINAL.into()),
+            Prop::CurrentDesktop => (atoms._NET_CURRENT_DESKTOP, AtomEnum::CARDINAL.into()),
+            Prop::ActiveWindow => (atoms._NET_ACTIVE_WINDOW, AtomEnum::WINDOW.into()),
+            Prop::UrgentTags => (atoms.SUBTLE_URGENT_TAGS, AtomEnum::CARDINAL.into()),
+            Prop::VisibleTags => (atoms.SUBTLE_VISIBLE_TAGS, AtomEnum::CARDINAL.into()),
+            Prop::VisibleViews => (atoms.SUBTLE_VISIBLE_VIEWS, AtomEnum::CARDINAL.into()),
+            Prop::ClientGeometries => (atoms.SUBTLE_CLIENT_GEOMETRIES, AtomEnum::CARDINAL.into()),
+            Prop::FrameExtents => (atoms._NET_FRAME_EXTENTS, AtomEnum::CARDINAL.into()),
+            Prop::ScreenViews => (atoms.SUBTLE_SCREEN_VIEWS, AtomEnum::CARDINAL.into()),
+        }
+    }
+}
+
+/// Mutating calls of the window-management decisions
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Effect {
+    /// Move and resize window
+    Configure { win: Window, x: i16, y: i16, width: u16, height: u16 },
+    /// Move and resize window and raise it
+    ConfigureAbove { win: Window, x: i16, y: i16, width: u16, height: u16 },
+    /// Set border width of window
+    SetBorderWidth { win: Window, width: u16 },
+    /// Set border color of window
+    SetBorderColor { win: Window, pixel: u32 },
+    /// Map window
+    Map(Window),
+    /// Unmap window
+    Unmap(Window),
+    /// Restack window below its siblings
+    Lower(Window),
+    /// Restack window above its siblings
+    Raise(Window),
+    /// Replace property of window
+    SetProperty { win: Window, prop: Prop, data: Vec<u32> },
+    /// Replace property of the root window
+    SetRootProperty { prop: Prop, data: Vec<u32> },
+    /// Set ICCCM state of window
+    SetWmState { win: Window, state: WMState },
+    /// Set EWMH state of window
+    SetNetWmState { win: Window, state: EWMHStateFlags },
+    /// Set EWMH allowed actions of window
+    SetAllowedActions { win: Window, actions: AllowedActions },
+    /// Set input focus to window
+    Focus(Window),
+    /// Set input focus to the root window when no client is left
+    FocusRoot,
+    /// Ask window to take the focus itself (see ICCCM 4.1.7)
+    TakeFocus(Window),
+    /// Bind mouse grabs to window
+    GrabMouse(Window),
+    /// Unbind all grabs from window
+    Ungrab(Window),
+    /// Warp pointer to root position
+    WarpPointer { x: i16, y: i16 },
+}
+
+/// Executor of effects selected at startup
+#[derive(Debug, Default)]
+pub(crate) enum Executor {
+    /// Execute effects on the X server
+    #[default]
+    Real,
+    /// Log and record effects instead of executing them
+    Logging(RefCell<VecDeque<Effect>>),
+}
+
+impl Executor {
+    /// Create a new executor
+    ///
+    /// # Arguments
+    ///
+    /// * `dry_run` - Whether to just log effects
+    ///
+    /// # Returns
+    ///
+    /// A new [`Executor`]
+    pub(crate) fn new(dry_run: bool) -> Self {
+        if dry_run {
+            Executor::Logging(RefCell::new(VecDeque::new()))
+        } else {
+            Executor::Real
+        }
+    }
+
+    /// Take all recorded effects
+    ///
+    /// # Returns
+    ///
+    /// A [`Vec`] with the recorded effects in order of their execution
+    pub(crate) fn take_recorded(&self) -> Vec<Effect> {
+        match self {
+            Executor::Real => Vec::new(),
+            Executor::Logging(recorded) => recorded.borrow_mut().drain(..).collect(),
+        }
+    }
+}
+
+/// Execute or log effect based on the selected executor
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `effect` - Effect to apply
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn apply(subtle: &Subtle, effect: Effect) -> Result<()> {
+    match &subtle.executor {
+        Executor::Real => execute(subtle, &effect)?,
+        Executor::Logging(recorded) => {
+            info!("Dry-run: {:?}", effect);
+
+            let mut recorded = recorded.borrow_mut();
+
+            if MAX_RECORDED <= recorded.len() {
+                recorded.pop_front();
+            }
+
+            recorded.push_back(effect);
+        }
+    }
+
+    Ok(())
+}
+
+/// Flush pending requests unless nothing has been executed
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn flush(subtle: &Subtle) -> Result<()> {
+    if let Executor::Real = subtle.executor {
+        subtle.conn.get().context("Failed to get connection")?.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Execute effect on the X server
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `effect` - Effect to execute
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn execute(subtle: &Subtle, effect: &Effect) -> Result<()> {
+    let conn = subtle.conn.get().context("Failed to get connection")?;
+    let atoms = subtle.atoms.get().context("Failed to get atoms")?;
+
+    let default_screen = &conn.setup().roots[subtle.screen_num];
+
+    match effect {
+        Effect::Configure { win, x, y, width, height } => {
+            conn.configure_window(*win, &ConfigureWindowAux::default()
+                .x(*x as i32)
+                .y(*y as i32)
+                .width(*width as u32)
+                .height(*height as u32))?.check()?;
+        },
+        Effect::ConfigureAbove { win, x, y, width, height } => {
+            conn.configure_window(*win, &ConfigureWindowAux::default()
+                .x(*x as i32)
+                .y(*y as i32)
+                .width(*width as u32)
+                .height(*height as u32)
+                .stack_mode(StackMode::ABOVE))?.check()?;
+        },
+        Effect::SetBorderWidth { win, width } => {
+            conn.configure_window(*win, &ConfigureWindowAux::default()
+                .border_width(*width as u32))?.check()?;
+        },
+        Effect::SetBorderColor { win, pixel } => {
+            conn.change_window_attributes(*win, &ChangeWindowAttributesAux::default()
+                .border_pixel(*pixel))?.check()?;
+        },
+        Effect::Map(win) => {
+            conn.map_window(*win)?.check()?;
+        },
+        Effect::Unmap(win) => {
+            conn.unmap_window(*win)?.check()?;
+        },
+        Effect::Lower(win) => {
+            conn.configure_window(*win, &ConfigureWindowAux::default()
+                .stack_mode(StackMode::BELOW))?;
+        },
+        Effect::Raise(win) => {
+            conn.configure_window(*win, &ConfigureWindowAux::default()
+                .stack_mode(StackMode::ABOVE))?;
+        },
+        Effect::SetProperty { win, prop, data } => {
+            let (atom, kind) = prop.get_atoms(atoms);
+
+            conn.change_property32(PropMode::REPLACE, *win, atom, kind, data)?.check()?;
+        },
+        Effect::SetRootProperty { prop, data } => {
+            let (atom, kind) = prop.get_atoms(atoms);
+
+            conn.change_property32(PropMode::REPLACE, default_screen.root, atom, kind, data)?.check()?;
+        },
+        Effect::SetWmState { win, state } => {
+            let data: [u8; 2] = [*state as u8, 0];
+
+            conn.change_property(PropMode::REPLACE,
+                                 *win, atoms.WM_STATE, atoms.WM_STATE, 8, 2, &data)?;
+        },
+        Effect::SetNetWmState { win, state } => {
+            let state_atoms: Vec<Atom> = [
+                (EWMHStateFlags::FULL, atoms._NET_WM_STATE_FULLSCREEN),
+                (EWMHStateFlags::FLOAT, atoms._NET_WM_STATE_ABOVE),
+                (EWMHStateFlags::STICK, atoms._NET_WM_STATE_STICKY),
+                (EWMHStateFlags::URGENT, atoms._NET_WM_STATE_DEMANDS_ATTENTION),
+            ].iter()
+                .filter(|(flag, _)| state.contains(*flag))
+                .map(|(_, atom)| *atom)
+                .collect();
+
+            conn.change_property32(PropMode::REPLACE, *win, atoms._NET_WM_STATE,
+                                   AtomEnum::ATOM, state_atoms.as_slice())?.check()?;
+        },
+        Effect::SetAllowedActions { win, actions } => {
+            let action_atoms: Vec<Atom> = [
+                (AllowedActions::MOVE, atoms._NET_WM_ACTION_MOVE),
+                (AllowedActions::RESIZE, atoms._NET_WM_ACTION_RESIZE),
+                (AllowedActions::FULLSCREEN, atoms._NET_WM_ACTION_FULLSCREEN),
+                (AllowedActions::STICK, atoms._NET_WM_ACTION_STICK),
+                (AllowedActions::CLOSE, atoms._NET_WM_ACTION_CLOSE),
+            ].iter()
+                .filter(|(flag, _)| actions.contains(*flag))
+                .map(|(_, atom)| *atom)
+                .collect();
+
+            conn.change_property32(PropMode::REPLACE, *win, atoms._NET_WM_ALLOWED_ACTIONS,
+                                   AtomEnum::ATOM, action_atoms.as_slice())?.check()?;
+        },
+        Effect::Focus(win) => {
+            conn.set_input_focus(InputFocus::POINTER_ROOT, *win, subtle.last_timestamp.get())?.check()?;
+        },
+        Effect::FocusRoot => {
+            conn.set_input_focus(InputFocus::POINTER_ROOT, default_screen.root, subtle.last_timestamp.get())?.check()?;
+        },
+        Effect::TakeFocus(win) => {
+            conn.send_event(false, *win, EventMask::NO_EVENT, ClientMessageEvent {
+                response_type: CLIENT_MESSAGE_EVENT,
+                format: 32,
+                sequence: 0,
+                window: *win,
+                type_: atoms.WM_PROTOCOLS,
+                data: [atoms.WM_TAKE_FOCUS, subtle.last_timestamp.get(), 0, 0, 0].into(),
+            })?.check()?;
+        },
+        Effect::GrabMouse(win) => {
+            grab::set(subtle, *win, GrabFlags::IS_MOUSE)?;
+        },
+        Effect::Ungrab(win) => {
+            grab::unset(subtle, *win)?;
+        },
+        Effect::WarpPointer { x, y } => {
+            conn.warp_pointer(NONE, default_screen.root, 0, 0, 0, 0, *x, *y)?.check()?;
+        },
+    }
+
+    debug!("{}: effect={:?}", function_name!(), effect);
+
+    Ok(())
+}
@@ -0,0 +1,159 @@
+//!
+//! @package subtle-rs
+//!
+//! @file Error functions
+//! @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+//! @version $Id$
+//!
+//! This program can be distributed under the terms of the GNU GPLv3.
+//! See the file LICENSE for details.
+//!
+
+use std::fmt;
+use log::{debug, warn};
+use stdext::function_name;
+use x11rb::errors::{ConnectionError, ReplyError};
+use x11rb::protocol::ErrorKind;
+use crate::subtle::Subtle;
+
+/// Errors of the subsystem init classified by their impact
+#[derive(Debug)]
+pub(crate) enum InitError {
+    /// Subtle cannot run and must abort
+    Fatal(anyhow::Error),
+    /// Subsystem can continue with defaults
+    Recoverable(anyhow::Error),
+}
+
+impl InitError {
+    /// Check whether error must abort the startup
+    ///
+    /// # Returns
+    ///
+    /// Either [`true`] if the error is fatal or otherwise [`false`]
+    pub(crate) fn is_fatal(&self) -> bool {
+        matches!(self, InitError::Fatal(_))
+    }
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InitError::Fatal(err) => write!(f, "{:#}", err),
+            InitError::Recoverable(err) => write!(f, "{:#}", err),
+        }
+    }
+}
+
+impl std::error::Error for InitError {}
+
+/// Classify errors at their sites
+pub(crate) trait Classify<T> {
+    /// Mark error as fatal
+    fn fatal(self) -> Result<T, InitError>;
+
+    /// Mark error as recoverable
+    fn recoverable(self) -> Result<T, InitError>;
+}
+
+impl<T, E: Into<anyhow::Error>> Classify<T> for Result<T, E> {
+    fn fatal(self) -> Result<T, InitError> {
+        self.map_err(|err| InitError::Fatal(err.into()))
+    }
+
+    fn recoverable(self) -> Result<T, InitError> {
+        self.map_err(|err| InitError::Recoverable(err.into()))
+    }
+}
+
+/// Check whether an error aborts the startup, unclassified errors are fatal
+///
+/// # Arguments
+///
+/// * `err` - Error to check
+///
+/// # Returns
+///
+/// Either [`true`] if the error is fatal or otherwise [`false`]
+pub(crate) fn is_fatal(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<InitError>().is_none_or(|init_err| init_err.is_fatal())
+}
+
+/// Collect and log recoverable errors and pass on fatal ones
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `result` - Classified result of an init step
+///
+/// # Returns
+///
+/// A [`Result`] with either [`Some`] value, [`None`] for recoverable errors or otherwise [`anyhow::Error`]
+pub(crate) fn collect<T>(subtle: &Subtle, result: Result<T, InitError>) -> anyhow::Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(InitError::Recoverable(err)) => {
+            warn!("{:#}", err);
+
+            subtle.init_errors.borrow_mut().push(err);
+
+            debug!("{}: nerrors={}", function_name!(), subtle.init_errors.borrow().len());
+
+            Ok(None)
+        },
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Check whether an error is an X error that is expected when a window is gone in the meantime
+///
+/// # Arguments
+///
+/// * `err` - Error to check
+///
+/// # Returns
+///
+/// Either [`true`] for BadWindow, BadDrawable and BadMatch or otherwise [`false`]
+pub(crate) fn is_transient(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| matches!(cause.downcast_ref::<ReplyError>(),
+        Some(ReplyError::X11Error(x11_err)) if matches!(x11_err.error_kind,
+            ErrorKind::Window | ErrorKind::Drawable | ErrorKind::Match)))
+}
+
+/// Check whether an error means the connection to the X server is lost
+///
+/// # Arguments
+///
+/// * `err` - Error to check
+///
+/// # Returns
+///
+/// Either [`true`] if the connection is lost or otherwise [`false`]
+pub(crate) fn is_connection_lost(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| cause.is::<ConnectionError>()
+        || matches!(cause.downcast_ref::<ReplyError>(), Some(ReplyError::ConnectionError(_))))
+}
+
+/// Log transient X errors of per-window operations and pass on all others
+///
+/// # Arguments
+///
+/// * `result` - Result of an operation on a window
+/// * `action` - Description of the operation for the log
+///
+/// # Returns
+///
+/// A [`Result`] with either [`Some`] value, [`None`] for transient errors or otherwise [`anyhow::Error`]
+pub(crate) fn skip_transient<T>(result: anyhow::Result<T>, action: &str) -> anyhow::Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if is_transient(&err) => {
+            warn!("Failed to {}: {:#}", action, err);
+
+            debug!("{}: action={}", function_name!(), action);
+
+            Ok(None)
+        },
+        Err(err) => Err(err),
+    }
+}
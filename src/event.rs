@@ -9,23 +9,164 @@
 //! See the file LICENSE for details.
 //!
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use std::sync::atomic;
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::io::{ErrorKind, Read};
+use std::os::fd::{AsFd, BorrowedFd};
+use std::os::unix::net::UnixStream;
 use std::process::{Command, Stdio};
-use log::{debug, warn};
+use std::time::{Duration, Instant};
+use log::{debug, info, warn};
+use rustix::event::{poll, PollFd, PollFlags, Timespec};
+use rustix::io::Errno;
 use stdext::function_name;
 use x11rb::connection::Connection;
-use x11rb::CURRENT_TIME;
-use x11rb::protocol::xproto::{ButtonPressEvent, ClientMessageEvent, ConfigureNotifyEvent, ConfigureRequestEvent, ConfigureWindowAux, ConnectionExt, DestroyNotifyEvent, EnterNotifyEvent, ExposeEvent, FocusInEvent, KeyPressEvent, LeaveNotifyEvent, MapNotifyEvent, MapRequestEvent, Mapping, MappingNotifyEvent, ModMask, PropertyNotifyEvent, SelectionClearEvent, UnmapNotifyEvent, Window};
+use x11rb::NONE;
+use x11rb::protocol::xproto::{Allow, ButtonPressEvent, Timestamp, ClientMessageEvent, ConfigWindow, ConfigureNotifyEvent, ConfigureRequestEvent, ConfigureWindowAux, ConnectionExt, DestroyNotifyEvent, EnterNotifyEvent, ExposeEvent, FocusInEvent, GrabMode, GrabStatus, Keycode, KeyPressEvent, KeyReleaseEvent, LeaveNotifyEvent, MapNotifyEvent, MapRequestEvent, Mapping, MappingNotifyEvent, ModMask, MotionNotifyEvent, PropertyNotifyEvent, SelectionClearEvent, UnmapNotifyEvent, Window};
 use x11rb::protocol::Event;
 use crate::subtle::{SubtleFlags, Subtle};
-use crate::client::{Client, ClientFlags, DragMode, RestackOrder};
-use crate::{client, display, ewmh, grab, panel, screen, tray};
+use crate::client::{Activation, Client, ClientFlags, DragKey, DragMode, KeyboardDrag, RestackOrder, Restacking};
+use crate::{client, corner, display, dump, effect, error, flash, grab, history, ignore, panel, screen, style, tag, view};
+#[cfg(feature = "tray")]
+use crate::tray;
 use crate::ewmh::WMState;
-use crate::grab::{DirectionOrder, GrabAction, GrabFlags};
+use crate::grab::{CycleOrder, DirectionOrder, GapOrder, Grab, GrabAction, GrabFlags, GrabOutcome};
+use crate::history::WindowCycle;
 use crate::panel::PanelAction;
-use crate::tray::{Tray, TrayFlags, XEmbed, XEmbedFocus};
+#[cfg(feature = "tray")]
+use crate::tray::{SystemTrayOpcode, Tray, TrayFlags, XEmbed};
+use crate::effect::{Effect, Prop};
+use crate::screen::ScreenFlags;
+use crate::validate::{ValidScreenIdx, ValidTagIdx, ValidViewIdx};
+
+/// Whether a viewport change request has been logged already
+static VIEWPORT_IGNORED: AtomicBool = AtomicBool::new(false);
+
+/// Focus next client after focus window is gone and no enter event follows
+///
+/// When no client is left on the screen, the focus falls back to the root window.
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `was_focus` - Whether the gone window had the focus
+/// * `screen_idx` - Screen index of the gone window
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn focus_next_unless_entered(subtle: &Subtle, was_focus: bool, screen_idx: isize) -> Result<()> {
+    if !was_focus {
+        return Ok(());
+    }
+
+    subtle.focus_history.unset_first();
+
+    if client::is_last_focus_gone(subtle, was_focus, screen_idx) {
+        client::focus_fallback(subtle)?;
+    } else if !subtle.focus_model.focus_on_enter()
+        && let Some(next_client) = subtle.find_next_client(screen_idx, false)
+    {
+        next_client.focus(subtle, false)?;
+    }
+
+    Ok(())
+}
+
+/// Close tray icon of window if any
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `win` - Window of the tray icon
+///
+/// # Returns
+///
+/// A [`Result`] with either [`bool`] whether the window is a tray icon or otherwise [`anyhow::Error`]
+#[cfg(feature = "tray")]
+fn close_tray(subtle: &Subtle, win: Window) -> Result<bool> {
+    let Some(tray) = subtle.find_tray(win) else {
+        return Ok(false);
+    };
+
+    tray.close(subtle)?;
+
+    Ok(true)
+}
+
+#[cfg(not(feature = "tray"))]
+fn close_tray(_subtle: &Subtle, _win: Window) -> Result<bool> {
+    Ok(false)
+}
+
+/// Remove tray icon of destroyed window if any
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `win` - Window of the tray icon
+///
+/// # Returns
+///
+/// A [`Result`] with either [`bool`] whether a tray icon was removed or otherwise [`anyhow::Error`]
+#[cfg(feature = "tray")]
+fn remove_tray(subtle: &Subtle, win: Window) -> Result<bool> {
+    let Some(tray) = subtle.find_tray(win) else {
+        return Ok(false);
+    };
+
+    tray.kill(subtle)?;
+
+    drop(tray);
+
+    subtle.remove_tray_by_win(win);
+
+    tray::publish(subtle)?;
+
+    Ok(true)
+}
+
+#[cfg(not(feature = "tray"))]
+fn remove_tray(_subtle: &Subtle, _win: Window) -> Result<bool> {
+    Ok(false)
+}
+
+/// Withdraw tray icon of unmapped window and remove it unless we unmapped it
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `win` - Window of the tray icon
+///
+/// # Returns
+///
+/// A [`Result`] with either [`bool`] whether a tray icon was removed or otherwise [`anyhow::Error`]
+#[cfg(feature = "tray")]
+fn withdraw_tray(subtle: &Subtle, win: Window) -> Result<bool> {
+    let Some(mut tray) = subtle.find_tray_mut(win) else {
+        return Ok(false);
+    };
+
+    // Set withdrawn state (see ICCCM 4.1.4)
+    tray.set_wm_state(subtle, WMState::Withdrawn)?;
+
+    // Ignore our generated unmap events
+    if tray.flags.contains(TrayFlags::UNMAP) {
+        tray.flags.remove(TrayFlags::UNMAP);
+
+        return Ok(false);
+    }
+
+    drop(tray);
+
+    remove_tray(subtle, win)
+}
+
+#[cfg(not(feature = "tray"))]
+fn withdraw_tray(_subtle: &Subtle, _win: Window) -> Result<bool> {
+    Ok(false)
+}
 
 /// Handle button press events
 ///
@@ -38,42 +179,88 @@ use crate::tray::{Tray, TrayFlags, XEmbed, XEmbedFocus};
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
 fn handle_button_press(subtle: &Subtle, event: ButtonPressEvent) -> Result<()> {
-    if let Some((_, screen)) = subtle.find_screen_by_panel_win(event.event) {
+    let conn = subtle.conn.get().context("Failed to get connection")?;
+
+    if let Some((screen_idx, screen)) = subtle.find_screen_by_panel_win(event.event) {
         screen.handle_action(subtle, &PanelAction::MouseDown(event.event_x, event.event_y, event.detail as i8),
             screen.bottom_panel_win == event.event)?;
 
         // Finally configure, update and render
         screen::configure(subtle)?;
-        screen::publish(subtle, false)?;
+        view::publish_current(subtle, screen_idx)?;
+        panel::update(subtle)?;
+        panel::render(subtle)?;
+    } else if subtle.focus_model.focus_on_press(subtle.find_focus_win(), event.event)
+        && subtle.find_client(event.event).is_some()
+    {
+        if subtle.raise_on_click {
+            if let Some(mut client) = subtle.find_client_mut(event.event) {
+                client.restack(RestackOrder::Up);
+            }
+
+            subtle.restack_windows()?;
+        }
+
+        if let Some(client) = subtle.find_client(event.event) {
+            client.focus(subtle, false)?;
+        }
+
         panel::update(subtle)?;
         panel::render(subtle)?;
     } else {
         // Limit mod mask to relevant ones
         let relevant_modifiers = ModMask::from(event.state.bits()
-            & (ModMask::SHIFT | ModMask::CONTROL | ModMask::M1 | ModMask::M4));
+            & (ModMask::SHIFT | ModMask::CONTROL | ModMask::M1 | ModMask::M4
+                | subtle.transients_drag_modifier));
 
-        if let Some(grab) = subtle.find_grab(event.detail, relevant_modifiers) {
+        let (maybe_grab, with_transients) = find_grab_with_transients(subtle, event.detail, relevant_modifiers);
+
+        if let Some(grab) = maybe_grab {
             let flag = grab.flags.difference(GrabFlags::IS_KEY | GrabFlags::IS_MOUSE);
 
             match flag {
                 GrabFlags::WINDOW_MOVE | GrabFlags::WINDOW_RESIZE => {
+                    let transients = if with_transients && GrabFlags::WINDOW_MOVE == flag {
+                        subtle.find_transients(subtle.find_focus_win())
+                    } else {
+                        Vec::new()
+                    };
+
+                    // Collect siblings before the focus client is borrowed
+                    let siblings = if subtle.client_snap && GrabFlags::WINDOW_MOVE == flag {
+                        client::find_sibling_geoms(subtle, subtle.find_focus_win(), &transients)
+                    } else {
+                        Vec::new()
+                    };
+
+                    let drag_mode = if GrabFlags::WINDOW_MOVE == flag {
+                        DragMode::MOVE } else { DragMode::RESIZE };
+
                     if let Some(mut focus_client) = subtle.find_focus_client_mut() {
+                       // Check before grabbing, so the resize cursor is never shown for fixed clients
                        if !focus_client.flags.intersects(ClientFlags::MODE_FULL)
-                           && !(GrabFlags::WINDOW_RESIZE == flag
-                           && focus_client.flags.intersects(ClientFlags::MODE_FIXED))
+                           && client::is_draggable(focus_client.flags, drag_mode)
                        {
                            if !focus_client.flags.intersects(ClientFlags::MODE_FLOAT) {
                                let mut mode_flags = ClientFlags::MODE_FLOAT;
 
-                               focus_client.toggle(subtle, &mut mode_flags, true)?;
+                               focus_client.toggle(subtle, &mut mode_flags, true, None)?;
                            }
 
+                           let old_geom = focus_client.geom;
+
                            // Translate flags
-                           focus_client.drag(subtle, if GrabFlags::WINDOW_MOVE == flag {
-                               DragMode::MOVE } else { DragMode::RESIZE }, DirectionOrder::Mouse)?;
+                           focus_client.drag(subtle, drag_mode, DirectionOrder::Mouse,
+                               &transients, &siblings,
+                               client::calc_snap_modifier(subtle.snap_modifier, grab.modifiers))?;
+
+                           let delta_x = focus_client.geom.x as i32 - old_geom.x as i32;
+                           let delta_y = focus_client.geom.y as i32 - old_geom.y as i32;
 
                            drop(focus_client);
 
+                           client::move_transients(subtle, &transients, delta_x, delta_y)?;
+
                            panel::update(subtle)?;
                            panel::render(subtle)?;
                        }
@@ -86,6 +273,11 @@ fn handle_button_press(subtle: &Subtle, event: ButtonPressEvent) -> Result<()> {
         }
     }
 
+    // Replay click to the client, the click-to-focus grab freezes the pointer
+    if subtle.focus_model.focus_on_click() && subtle.find_client(event.event).is_some() {
+        conn.allow_events(Allow::REPLAY_POINTER, event.time)?.check()?;
+    }
+
     debug!("{}: win={}, x={}, y={}", function_name!(), event.event, event.event_x, event.event_y);
 
     Ok(())
@@ -101,7 +293,15 @@ fn handle_button_press(subtle: &Subtle, event: ButtonPressEvent) -> Result<()> {
 /// # Returns
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-fn handle_configure_notify(_subtle: &Subtle, event: ConfigureNotifyEvent) -> Result<()> {
+fn handle_configure_notify(subtle: &Subtle, event: ConfigureNotifyEvent) -> Result<()> {
+    let conn = subtle.conn.get().context("Failed to get connection")?;
+    let default_screen = &conn.setup().roots[subtle.screen_num];
+
+    // Root size changes e.g. on RandR changes
+    if default_screen.root == event.window {
+        subtle.screens_changed.set(true);
+    }
+
     debug!("{}: win={}", function_name!(), event.window);
 
     Ok(())
@@ -126,13 +326,44 @@ fn handle_configure_request(subtle: &Subtle, event: ConfigureRequestEvent) -> Re
     // Resize       -> Real ConfigureNotify
 
     // Check if we know the window
-    if let Some(client) = subtle.find_client_mut(event.window) {
+    if let Some(mut client) = subtle.find_client_mut(event.window) {
+        let geom_mask = ConfigWindow::X | ConfigWindow::Y | ConfigWindow::WIDTH | ConfigWindow::HEIGHT;
+
         // Check flags if the request is important
         if !client.flags.contains(ClientFlags::MODE_FULL)
-            && subtle.flags.contains(SubtleFlags::RESIZE)
-            || client.flags.contains(ClientFlags::MODE_FLOAT | ClientFlags::MODE_RESIZE)
+            && (subtle.flags.contains(SubtleFlags::RESIZE)
+            || client.flags.intersects(ClientFlags::MODE_FLOAT | ClientFlags::MODE_RESIZE))
+            && event.value_mask.intersects(geom_mask)
+            && let Some(screen) = subtle.screens.get(client.screen_idx as usize)
         {
-            let _maybe_screen = subtle.screens.get(client.screen_idx as usize);
+            client.geom = client::calc_requested_geom(&client.geom, &event);
+
+            client.resize(subtle, &screen.geom, true)?;
+
+            conn.configure_window(client.win, &ConfigureWindowAux::default()
+                .x(client.geom.x as i32)
+                .y(client.geom.y as i32)
+                .width(client.geom.width as u32)
+                .height(client.geom.height as u32))?.check()?;
+
+            client.reconcile_screen(subtle)?;
+
+            // Moves without resize don't generate a real event
+            if !event.value_mask.intersects(ConfigWindow::WIDTH | ConfigWindow::HEIGHT) {
+                client.send_configure(subtle)?;
+            }
+        } else {
+            // Tell client the unchanged geometry
+            client.send_configure(subtle)?;
+        }
+
+        // Translate stacking requests
+        if event.value_mask.contains(ConfigWindow::STACK_MODE) {
+            client.restack(RestackOrder::from(event.stack_mode));
+
+            drop(client);
+
+            subtle.restack_windows()?;
         }
     // Unmanaged window
     } else {
@@ -140,6 +371,8 @@ fn handle_configure_request(subtle: &Subtle, event: ConfigureRequestEvent) -> Re
                               &ConfigureWindowAux::from_configure_request(&event))?.check()?;
     }
 
+    debug!("{}: win={}, mask={:?}", function_name!(), event.window, event.value_mask);
+
     Ok(())
 }
 
@@ -158,13 +391,38 @@ fn handle_client_message(subtle: &Subtle, event: ClientMessageEvent) -> Result<(
     let atoms = subtle.atoms.get().unwrap();
     let default_screen = &conn.setup().roots[subtle.screen_num];
 
+    // EWMH: Answer with the extents a window would get before it's mapped
+    if atoms._NET_REQUEST_FRAME_EXTENTS == event.type_ {
+        let flags = subtle.find_client(event.window).map_or(ClientFlags::empty(), |client| client.flags);
+
+        effect::apply(subtle, Effect::SetProperty { win: event.window, prop: Prop::FrameExtents,
+            data: client::calc_frame_extents(flags, subtle.clients_style.border.top) })?;
+        effect::flush(subtle)?;
+    }
     // Check if we know the window
-    if default_screen.root == event.window {
+    else if default_screen.root == event.window {
         // ICCCM
         if atoms._NET_CURRENT_DESKTOP == event.type_ {
-            println!("_NET_CURRENT_DESKTOP");
-        } else if atoms._NET_ACTIVE_WINDOW == event.type_ {
-            println!("_NET_ACTIVE_WINDOW");
+            // Data is the view index
+            let nviews = subtle.views.borrow().len();
+
+            match ValidViewIdx::new(event.data.as_data32()[0], nviews) {
+                Ok(view_idx) => {
+                    if let Some(view) = subtle.views.borrow().get(view_idx.get()) {
+                        let screen_idx = view::find_current_screen_idx(subtle);
+
+                        view.focus(subtle, screen_idx, true, true)?;
+
+                        panel::render(subtle)?;
+                    }
+                },
+                Err(err) => warn!("Failed to switch view: {}", err),
+            }
+        } else if atoms._NET_DESKTOP_VIEWPORT == event.type_ {
+            // EWMH: Views are never larger than the screen, so there is nothing to pan
+            if !VIEWPORT_IGNORED.swap(true, Ordering::Relaxed) {
+                info!("Ignoring viewport changes, views don't span large desktops");
+            }
         } else if atoms._NET_RESTACK_WINDOW == event.type_ {
             println!("_NET_RESTACK_WINDOW");
         }
@@ -178,6 +436,72 @@ fn handle_client_message(subtle: &Subtle, event: ClientMessageEvent) -> Result<(
             println!("SUBTLE_CLIENT_GRAVITY");
         } else if atoms.SUBTLE_CLIENT_FLAGS == event.type_ {
             println!("SUBTLE_CLIENT_FLAGS");
+        } else if atoms.SUBTLE_WINDOW_TAG == event.type_ || atoms.SUBTLE_WINDOW_UNTAG == event.type_ {
+            // Data is client window and tag index
+            let data = event.data.as_data32();
+
+            let ntags = subtle.tags.borrow().len();
+
+            let res = ValidTagIdx::new(data[1], ntags)
+                .and_then(|tag_idx| match subtle.find_client_mut(data[0] as Window) {
+                    Some(mut client) => client.change_tag(subtle, tag_idx.get(),
+                                                          atoms.SUBTLE_WINDOW_TAG == event.type_),
+                    None => Err(anyhow!("Client not found")),
+                });
+
+            match res {
+                Ok(_) => {
+                    screen::configure(subtle)?;
+                    panel::update(subtle)?;
+                    panel::render(subtle)?;
+                },
+                Err(err) => warn!("Failed to change tags of window `{}`: {}", data[0], err),
+            }
+        } else if atoms.SUBTLE_WINDOW_RAISE == event.type_ || atoms.SUBTLE_WINDOW_LOWER == event.type_ {
+            // Data is client window and request flags
+            let data = event.data.as_data32();
+            let win = data[0] as Window;
+
+            let order = if atoms.SUBTLE_WINDOW_RAISE == event.type_ {
+                RestackOrder::Up
+            } else {
+                RestackOrder::Down
+            };
+
+            let has_fullscreen = subtle.clients.borrow().iter()
+                .any(|client| client.flags.contains(ClientFlags::MODE_FULL) && client.is_visible(subtle));
+
+            let (restacking, focus) = client::calc_restacking(order,
+                subtle.find_client(win).map(|client| client.flags), has_fullscreen, data[1]);
+
+            match restacking {
+                Restacking::Refuse => warn!("Refused to restack window `{}`", win),
+                Restacking::Restack(_) | Restacking::RaiseAbove => {
+                    if let Some(mut client) = subtle.find_client_mut(win) {
+                        client.restack(order);
+                    }
+
+                    subtle.restack_windows()?;
+
+                    // Stacking levels keep docks below fullscreen clients
+                    if Restacking::RaiseAbove == restacking {
+                        effect::apply(subtle, Effect::Raise(win))?;
+                    }
+
+                    client::publish(subtle, false)?;
+
+                    if focus && let Some(client) = subtle.find_client(win)
+                        && client.is_visible(subtle)
+                    {
+                        client.focus(subtle, !subtle.flags.contains(SubtleFlags::SKIP_POINTER_WARP))?;
+                    }
+                },
+            }
+        } else if atoms.SUBTLE_WINDOW_DUMP == event.type_ {
+            // Data is client window or none for the focus client
+            let data = event.data.as_data32();
+
+            dump::dump_client(subtle, data[0] as Window)?;
         }
 
         // subtle: Gravity
@@ -191,23 +515,65 @@ fn handle_client_message(subtle: &Subtle, event: ClientMessageEvent) -> Result<(
 
         // subtle: Screen
         else if atoms.SUBTLE_SCREEN_JUMP == event.type_ {
-            println!("SUBTLE_SCREEN_JUMP");
+            // Data is the screen index
+            let raw_screen_idx = event.data.as_data32()[0];
+
+            if let Err(err) = ValidScreenIdx::new(raw_screen_idx, subtle.screens.len())
+                .and_then(|screen_idx| screen::jump(subtle, screen_idx.get()))
+            {
+                warn!("Failed to jump to screen `{}`: {}", raw_screen_idx, err);
+            }
+
+            panel::render(subtle)?;
         }
 
         // subtle:: Tag
-        else if atoms.SUBTLE_TAG_NEW == event.type_ {
-            println!("SUBTLE_TAG_NEW");
-        } else if atoms.SUBTLE_TAG_KILL == event.type_ {
-            println!("SUBTLE_TAG_KILL");
+        else if atoms.SUBTLE_TAG_NEW == event.type_ || atoms.SUBTLE_TAG_KILL == event.type_ {
+            // Data is NUL-separated: name and either regex or `force`
+            if let Some((name, arg)) = tag::parse_message(&event.data.as_data8()) {
+                let res = if atoms.SUBTLE_TAG_NEW == event.type_ {
+                    tag::add(subtle, &name, arg.as_deref()).map(|_| ())
+                } else {
+                    tag::remove(subtle, &name, arg.is_some_and(|arg| "force" == arg)).map(|_| ())
+                };
+
+                match res {
+                    Ok(_) => {
+                        screen::configure(subtle)?;
+                        panel::update(subtle)?;
+                        panel::render(subtle)?;
+                    },
+                    Err(err) => warn!("Failed to change tag `{}`: {}", name, err),
+                }
+            }
         }
 
         // subtle: View
-        else if atoms.SUBTLE_VIEW_NEW == event.type_ {
-            println!("SUBTLE_VIEW_NEW");
+        else if atoms.SUBTLE_VIEW_NEW == event.type_ || atoms.SUBTLE_VIEW_KILL == event.type_ {
+            let res = if atoms.SUBTLE_VIEW_NEW == event.type_ {
+                // Data is NUL-separated: name and optional regex
+                match tag::parse_message(&event.data.as_data8()) {
+                    Some((name, pattern)) => view::add(subtle, &name, pattern.as_deref()).map(|_| ()),
+                    None => Err(anyhow!("Invalid view name")),
+                }
+            } else {
+                let nviews = subtle.views.borrow().len();
+
+                ValidViewIdx::new(event.data.as_data32()[0], nviews)
+                    .and_then(|view_idx| view::remove(subtle, view_idx.get()))
+            };
+
+            match res {
+                Ok(_) => {
+                    screen::configure(subtle)?;
+                    screen::publish(subtle, false)?;
+                    panel::update(subtle)?;
+                    panel::render(subtle)?;
+                },
+                Err(err) => warn!("Failed to change views: {}", err),
+            }
         } else if atoms.SUBTLE_VIEW_TAGS == event.type_ {
             println!("SUBTLE_VIEW_TAGS");
-        } else if atoms.SUBTLE_VIEW_KILL == event.type_ {
-            println!("SUBTLE_VIEW_KILL");
         }
 
         // subtle: Main
@@ -221,13 +587,15 @@ fn handle_client_message(subtle: &Subtle, event: ClientMessageEvent) -> Result<(
             println!("SUBTLE_QUIT");
         }
     } else if event.window == subtle.tray_win {
+        #[cfg(feature = "tray")]
         if atoms._NET_SYSTEM_TRAY_OPCODE == event.type_ {
             let data = event.data.as_data32();
 
-            match XEmbed::from_repr(data[1] as u8).context("Unknown tray opcode")? {
-                XEmbed::EmbeddedNotify => {
+            match SystemTrayOpcode::from_repr(data[1] as u8).context("Unknown tray opcode")? {
+                SystemTrayOpcode::RequestDock => {
                     if subtle.find_tray(data[2] as Window).is_none() {
-                        if let Ok(tray) = Tray::new(subtle, data[2] as Window) {
+                        if let Some(tray) = error::skip_transient(Tray::new(subtle, data[2] as Window),
+                                                                  "embed tray window")? {
                             subtle.add_tray(tray);
 
                             panel::update(subtle)?;
@@ -235,11 +603,18 @@ fn handle_client_message(subtle: &Subtle, event: ClientMessageEvent) -> Result<(
                         }
                     }
                 },
-                XEmbed::WindowActivate => {
-                    ewmh::send_message(subtle, data[2] as Window,
-                                       atoms._XEMBED, &[CURRENT_TIME, XEmbed::FocusIn as u32,
-                                           XEmbedFocus::Current as u32, 0, 0])?;
-                },
+                // Balloon messages aren't supported
+                SystemTrayOpcode::BeginMessage | SystemTrayOpcode::CancelMessage => {},
+            }
+        } else if atoms._XEMBED == event.type_ {
+            // Icons send their requests to the embedder
+            let data = event.data.as_data32();
+
+            match XEmbed::from_repr(data[1] as u8) {
+                Some(XEmbed::RequestFocus) => tray::request_focus(subtle)?,
+                Some(XEmbed::FocusNext) => tray::cycle_focus(subtle, CycleOrder::Next)?,
+                Some(XEmbed::FocusPrev) => tray::cycle_focus(subtle, CycleOrder::Prev)?,
+                // Modality needs no handling, the tray has no own widgets to block
                 _ => {},
             }
         }
@@ -247,17 +622,106 @@ fn handle_client_message(subtle: &Subtle, event: ClientMessageEvent) -> Result<(
         if atoms._NET_CLOSE_WINDOW == event.type_ {
             client.close(subtle)?;
 
+            drop(client);
+
             screen::configure(subtle)?;
             panel::update(subtle)?;
             panel::render(subtle)?;
-        }
-    } else if let Some(tray) = subtle.find_tray(event.window) {
-        if atoms._NET_CLOSE_WINDOW == event.type_ {
-            tray.close(subtle)?;
+        } else if atoms._NET_WM_DESKTOP == event.type_ {
+            drop(client);
+
+            // Look up the focus screen before the client is borrowed for sticking
+            let focus_screen_idx = subtle.find_focus_screen_idx();
+
+            if let Some(mut client) = subtle.find_client_mut(event.window) {
+                client.set_desktop(subtle, event.data.as_data32()[0], focus_screen_idx)?;
+            }
+
+            screen::configure(subtle)?;
+            panel::update(subtle)?;
+            panel::render(subtle)?;
+        } else if atoms._NET_WM_STATE == event.type_ {
+            let data = event.data.as_data32();
+
+            // Collect flags of both property atoms and skip unknown ones
+            let requested_flags = [data[1], data[2]].iter()
+                .filter_map(|atom| client::get_state_flag(atoms, *atom))
+                .fold(ClientFlags::empty(), |flags, state_flag| flags | state_flag);
+
+            let mut mode_flags = client::calc_state_delta(client.flags, data[0], requested_flags);
+
+            drop(client);
+
+            // Look up the focus screen before the client is borrowed for sticking
+            let focus_screen_idx = subtle.find_focus_screen_idx();
+
+            if !mode_flags.is_empty()
+                && let Some(mut client) = subtle.find_client_mut(event.window)
+            {
+                client.toggle(subtle, &mut mode_flags, true, focus_screen_idx)?;
+            }
+
+            screen::configure(subtle)?;
+            panel::update(subtle)?;
+            panel::render(subtle)?;
+        } else if atoms._NET_ACTIVE_WINDOW == event.type_ {
+            let activation = client::calc_activation(event.data.as_data32()[0], client.is_visible(subtle));
+            let client_tags = client.tags;
+            let client_screen_idx = client.screen_idx;
+
+            drop(client);
+
+            match activation {
+                Activation::MarkUrgent => {
+                    if let Some(mut client) = subtle.find_client_mut(event.window)
+                        && !client.flags.contains(ClientFlags::MODE_URGENT)
+                    {
+                        let mut mode_flags = ClientFlags::MODE_URGENT;
+
+                        client.toggle(subtle, &mut mode_flags, false, None)?;
+                    }
+
+                    screen::configure(subtle)?;
+                },
+                Activation::Jump | Activation::Focus => {
+                    // Switch screen of client or current screen to view with client tags first
+                    if Activation::Jump == activation
+                        && let Some(view) = subtle.views.borrow().iter().find(|view| view.tags.get().intersects(client_tags))
+                    {
+                        let screen_idx = if 0 <= client_screen_idx
+                            && (client_screen_idx as usize) < subtle.screens.len()
+                        {
+                            client_screen_idx as usize
+                        } else {
+                            subtle.find_screen_by_pointer().map_or(0, |(screen_idx, _)| screen_idx)
+                        };
+
+                        view.focus(subtle, screen_idx, false, false)?;
+
+                        screen::configure(subtle)?;
+                    }
+
+                    // Raise and focus
+                    if let Some(mut client) = subtle.find_client_mut(event.window) {
+                        client.restack(RestackOrder::Up);
+                    }
+
+                    subtle.restack_windows()?;
+
+                    if let Some(client) = subtle.find_client(event.window)
+                        && client.is_visible(subtle)
+                    {
+                        client.focus(subtle, true)?;
+                    }
+                },
+            }
 
             panel::update(subtle)?;
             panel::render(subtle)?;
         }
+    } else if atoms._NET_CLOSE_WINDOW == event.type_ && close_tray(subtle, event.window)? {
+        panel::update(subtle)?;
+        panel::render(subtle)?;
     }
 
     debug!("{}: win={}", function_name!(), event.window);
@@ -277,30 +741,34 @@ fn handle_client_message(subtle: &Subtle, event: ClientMessageEvent) -> Result<(
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
 fn handle_destroy_notify(subtle: &Subtle, event: DestroyNotifyEvent) -> Result<()> {
     // Check if we know the window
-    if let Some(client) = subtle.find_client(event.window) {
-        client.kill(subtle)?;
+    if subtle.is_ignored_win(event.window) {
+        subtle.remove_ignored_win(event.window);
+    } else if let Some(client) = subtle.find_client(event.window) {
+        let screen_idx = client.screen_idx;
+        let was_focus = subtle.find_focus_win() == event.window;
+
+        error::skip_transient(client.kill(subtle), "release destroyed window")?;
 
         drop(client);
 
         subtle.remove_client_by_win(event.window);
 
+        client::update_handled_icons(subtle);
         client::publish(subtle, false)?;
 
         screen::configure(subtle)?;
+        focus_next_unless_entered(subtle, was_focus, screen_idx)?;
         panel::update(subtle)?;
         panel::render(subtle)?;
-    } else if let Some(tray) = subtle.find_tray(event.window) {
-        tray.kill(subtle)?;
-
-        drop(tray);
-
-        subtle.remove_tray_by_win(event.window);
-
-        tray::publish(subtle)?;
-
+    } else if remove_tray(subtle, event.window)? {
         screen::configure(subtle)?;
         panel::update(subtle)?;
         panel::render(subtle)?;
+    } else if let Some(own_win) = screen::find_own_win(event.window, event.event,
+        &subtle.screens.iter().map(|screen| (screen.top_panel_win, screen.bottom_panel_win)).collect::<Vec<_>>(),
+        subtle.tray_win)
+    {
+        screen::recreate_win(subtle, own_win)?;
     } else {
         // Check if window is client leader
         for client in subtle.clients.borrow_mut().iter_mut() {
@@ -326,8 +794,29 @@ fn handle_destroy_notify(subtle: &Subtle, event: DestroyNotifyEvent) -> Result<(
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
 fn handle_enter_notify(subtle: &Subtle, event: EnterNotifyEvent) -> Result<()> {
-    if let Some(client) = subtle.find_client(event.event) {
-        if !subtle.flags.intersects(SubtleFlags::CLICK_TO_FOCUS) {
+    if let Some((_, screen)) = subtle.find_screen_by_panel_win(event.event) {
+        screen.handle_action(subtle, &PanelAction::MouseOver(event.event_x, event.event_y),
+                             screen.bottom_panel_win == event.event)?;
+
+        #[cfg(feature = "tray")]
+        if event.event == subtle.tray_parent_win.get() && tray::is_panel_crossing(event.detail) {
+            tray::activate(subtle, true)?;
+        }
+
+        panel::update(subtle)?;
+        panel::render(subtle)?;
+    } else if let Some(action_idx) = subtle.find_corner(event.event)
+        .filter(|corner| corner::should_trigger(&corner.triggered_at, Instant::now(), subtle.corner_delay))
+        .map(|corner| corner.action_idx)
+    {
+        if let Some(corner_action) = subtle.corner_actions.get(action_idx) {
+            let outcome = handle_grab_action(subtle, corner_action.flags, &corner_action.action,
+                                             event.root_x, event.root_y, 1)?;
+
+            flash::handle_outcome(subtle, outcome)?;
+        }
+    } else if let Some(client) = subtle.find_client(event.event) {
+        if subtle.focus_model.focus_on_enter() {
             client.focus(subtle, false)?;
         }
     }
@@ -338,6 +827,36 @@ fn handle_enter_notify(subtle: &Subtle, event: EnterNotifyEvent) -> Result<()> {
     Ok(())
 }
 
+/// Handle motion notify events
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `event` - Event to handle
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn handle_motion_notify(subtle: &Subtle, event: MotionNotifyEvent) -> Result<()> {
+    if let Some((_, screen)) = subtle.find_screen_by_panel_win(event.event) {
+        let hovered_before = screen.find_hovered_views();
+
+        screen.handle_action(subtle, &PanelAction::MouseOver(event.event_x, event.event_y),
+                             screen.bottom_panel_win == event.event)?;
+
+        // Skip rendering unless hover changed
+        if hovered_before != screen.find_hovered_views() {
+            panel::update(subtle)?;
+            panel::render(subtle)?;
+        }
+    }
+
+    debug!("{}: event={}, x={}, y={}", function_name!(),
+        event.event, event.event_x, event.event_y);
+
+    Ok(())
+}
+
 /// Handle leave notify events
 ///
 /// # Arguments
@@ -350,8 +869,16 @@ fn handle_enter_notify(subtle: &Subtle, event: EnterNotifyEvent) -> Result<()> {
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
 fn handle_leave_notify(subtle: &Subtle, event: LeaveNotifyEvent) -> Result<()> {
     if let Some((_, screen)) = subtle.find_screen_by_panel_win(event.event) {
-            screen.handle_action(subtle, &PanelAction::MouseOut,
-                                 screen.bottom_panel_win == event.event)?;
+        screen.handle_action(subtle, &PanelAction::MouseOut,
+                             screen.bottom_panel_win == event.event)?;
+
+        #[cfg(feature = "tray")]
+        if event.event == subtle.tray_parent_win.get() && tray::is_panel_crossing(event.detail) {
+            tray::activate(subtle, false)?;
+        }
+
+        panel::update(subtle)?;
+        panel::render(subtle)?;
     }
 
     debug!("{}: event={}, child={}, root={}", function_name!(),
@@ -370,236 +897,974 @@ fn handle_leave_notify(subtle: &Subtle, event: LeaveNotifyEvent) -> Result<()> {
 /// # Returns
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-fn handle_expose(subtle: &Subtle, event: ExposeEvent) -> Result<()> {
-    // Render only once
-    if 0 == event.count {
+fn handle_expose(subtle: &Subtle, event: ExposeEvent) -> Result<()> {
+    // Render only once
+    if 0 == event.count {
+        panel::render(subtle)?;
+    }
+
+    debug!("{}: win={}, count={}", function_name!(), event.window, event.count);
+
+    Ok(())
+}
+
+/// Handle focus in events
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `event` - Event to handle
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn handle_focus_in(subtle: &Subtle, event: FocusInEvent) -> Result<()> {
+    if let Some(mut client) = subtle.find_client_mut(event.event) {
+
+        // Remove urgent after getting focus
+        let was_urgent = client.flags.intersects(ClientFlags::MODE_URGENT);
+
+        client.flags.remove(ClientFlags::MODE_URGENT);
+
+        drop(client);
+
+        // Other urgent clients might share the tags
+        if was_urgent {
+            client::update_urgent_tags(subtle)?;
+        }
+
+        // Update focus history
+        subtle.focus_history.push(event.event);
+
+        // Update screen
+        panel::update(subtle)?;
+        panel::render(subtle)?;
+    }
+
+    debug!("{}: win={}", function_name!(), event.event);
+
+    Ok(())
+}
+
+/// Get timestamp of user events
+///
+/// # Arguments
+///
+/// * `event` - Event to check
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] timestamp or otherwise [`None`]
+pub(crate) fn get_timestamp(event: &Event) -> Option<Timestamp> {
+    match event {
+        Event::KeyPress(evt) => Some(evt.time),
+        Event::ButtonPress(evt) => Some(evt.time),
+        Event::ButtonRelease(evt) => Some(evt.time),
+        Event::MotionNotify(evt) => Some(evt.time),
+        Event::EnterNotify(evt) => Some(evt.time),
+        Event::LeaveNotify(evt) => Some(evt.time),
+        Event::PropertyNotify(evt) => Some(evt.time),
+        _ => None,
+    }
+}
+
+/// Dispatch grab action
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `flag` - Grab flag without input type
+/// * `action` - Action of the grab
+/// * `x` - Pointer x position on root
+/// * `y` - Pointer y position on root
+/// * `count` - Repeat factor of the action
+///
+/// # Returns
+///
+/// A [`Result`] with either [`GrabOutcome`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn handle_grab_action(subtle: &Subtle, flag: GrabFlags, action: &GrabAction,
+                                 x: i16, y: i16, count: u32) -> Result<GrabOutcome>
+{
+    Ok(match flag {
+        GrabFlags::COUNT_PREFIX => {
+            if let GrabAction::Index(digit) = *action {
+                let mut count_prefix = subtle.count_prefix.get();
+
+                count_prefix.push_digit(digit, Instant::now());
+
+                subtle.count_prefix.set(count_prefix);
+
+                // Show pending count
+                panel::update(subtle)?;
+                panel::render(subtle)?;
+            }
+
+            GrabOutcome::Done
+        },
+
+        GrabFlags::VIEW_CYCLE => {
+            let mut outcome = GrabOutcome::NoOp;
+
+            // Either next and prev or relative jumps with steps
+            let maybe_order_steps = match action {
+                GrabAction::Index(order) => Some((*order, 1)),
+                GrabAction::List(order_steps) if 2 == order_steps.len() =>
+                    Some((order_steps[0] as u32, order_steps[1] as u32)),
+                _ => None,
+            };
+
+            if let Some((order, steps)) = maybe_order_steps
+                && let Some((screen_idx, screen)) = subtle.find_screen_by_xy(x, y)
+            {
+                let order = CycleOrder::from_repr(order as u8).context("Unknown order")?;
+                let current_idx = usize::try_from(screen.view_idx.get()).ok();
+
+                if let Some(view_idx) = grab::calc_cycle_idx(subtle.views.borrow().len(), current_idx,
+                                                             count.saturating_mul(steps), order)
+                    && Some(view_idx) != current_idx
+                    && let Some(view) = subtle.views.borrow().get(view_idx)
+                {
+                    view.focus(subtle, screen_idx, false, true)?;
+
+                    // Finally render
+                    panel::render(subtle)?;
+
+                    outcome = GrabOutcome::Done;
+                }
+            }
+
+            outcome
+        },
+
+        GrabFlags::WINDOW_CYCLE => {
+            let mut outcome = GrabOutcome::NoOp;
+
+            if let GrabAction::Index(order) = *action {
+                let order = CycleOrder::from_repr(order as u8).context("Unknown order")?;
+
+                // Collect visible clients in list order
+                let wins = subtle.clients.borrow().iter()
+                    .filter(|client| client.is_alive() && client.is_visible(subtle)
+                        && !client.flags.intersects(ClientFlags::TYPE_DESKTOP | ClientFlags::TYPE_DOCK))
+                    .map(|client| client.win)
+                    .collect::<Vec<Window>>();
+
+                let current_idx = subtle.focus_history.first()
+                    .and_then(|focus_win| wins.iter().position(|win| *win == focus_win));
+
+                if let Some(win_idx) = grab::calc_cycle_idx(wins.len(), current_idx, count, order)
+                    && Some(win_idx) != current_idx
+                    && let Some(client) = subtle.find_client(wins[win_idx])
+                {
+                    client.focus(subtle, !subtle.flags.intersects(SubtleFlags::SKIP_POINTER_WARP))?;
+
+                    outcome = GrabOutcome::Done;
+                }
+            }
+
+            outcome
+        },
+
+        GrabFlags::VIEW_JUMP | GrabFlags::VIEW_SWITCH | GrabFlags::VIEW_SELECT => {
+            let mut outcome = GrabOutcome::NoOp;
+
+            if let GrabAction::Index(idx) = *action {
+                if let Some(view) = subtle.views.borrow().get(idx as usize - 1) {
+                    let mut screen_idx: isize = -1;
+
+                    // Find screen: Prefer screen of current window
+                    if subtle.flags.intersects(SubtleFlags::SKIP_POINTER_WARP)
+                        && let Some(focus_client) = subtle.find_focus_client()
+                        && focus_client.is_visible(subtle)
+                    {
+                        screen_idx = focus_client.screen_idx;
+                    } else if let Some((maybe_screen_id, _)) = subtle.find_screen_by_xy(x, y) {
+                        screen_idx = maybe_screen_id as isize;
+                    }
+
+                    // Skip jumps to the current view of the screen
+                    if subtle.screens.get(screen_idx as usize)
+                        .is_none_or(|screen| screen.view_idx.get() != idx as isize - 1)
+                    {
+                        // Configures screens before focusing the next client
+                        view.focus(subtle, screen_idx as usize,
+                                   GrabFlags::VIEW_SWITCH == flag, true)?;
+
+                        // Finally render
+                        panel::render(subtle)?;
+
+                        outcome = GrabOutcome::Done;
+                    }
+                }
+            }
+
+            outcome
+        },
+
+        GrabFlags::SCREEN_JUMP => {
+            let mut outcome = GrabOutcome::NoOp;
+
+            if let GrabAction::Index(idx) = *action
+                && let Some(screen_idx) = (idx as usize).checked_sub(1)
+                && screen_idx < subtle.screens.len()
+            {
+                screen::jump(subtle, screen_idx)?;
+
+                // Finally render
+                panel::render(subtle)?;
+
+                outcome = GrabOutcome::Done;
+            }
+
+            outcome
+        },
+
+        GrabFlags::WINDOW_MODE => {
+            let mut outcome = GrabOutcome::NoOp;
+            let focus_screen_idx = subtle.find_focus_screen_idx();
+
+            if let Some(mut focus_client) = subtle.find_focus_client_mut() {
+                if let GrabAction::Index(bits) = *action {
+                    let mut mode_flags = ClientFlags::from_bits(bits)
+                        .context("Unknown client flags")?;
+
+                    focus_client.toggle(subtle, &mut mode_flags, true, focus_screen_idx)?;
+
+                    outcome = GrabOutcome::Done;
+
+                    // Update screen and focus
+                    if focus_client.is_visible(subtle) || ClientFlags::MODE_STICK == mode_flags {
+                        // Store values and drop reference
+                        let is_visible = focus_client.is_visible(subtle);
+                        let screen_idx = focus_client.screen_idx;
+
+                        drop(focus_client);
+
+                        screen::configure(subtle)?;
+
+                        // Find next and focus
+                        if !is_visible {
+                            if let Some(next_client) = subtle.find_next_client(screen_idx, false) {
+                                next_client.focus(subtle, true)?;
+                            }
+                        }
+
+                        // Finally update and render
+                        panel::update(subtle)?;
+                        panel::render(subtle)?;
+                    }
+                }
+            }
+
+            outcome
+        },
+
+        GrabFlags::WINDOW_RESTACK => {
+            let mut outcome = GrabOutcome::NoOp;
+
+            if let Some(mut focus_client) = subtle.find_focus_client_mut() {
+                if let GrabAction::Index(order) = *action {
+                    focus_client.restack(RestackOrder::from_repr(order as u8)
+                        .context("Unknown order")?);
+
+                    drop(focus_client);
+
+                    subtle.restack_windows()?;
+
+                    outcome = GrabOutcome::Done;
+                }
+            }
+
+            outcome
+        },
+
+        GrabFlags::WINDOW_GRAVITY => {
+            let mut outcome = GrabOutcome::NoOp;
+
+            if let Some(mut focus_client) = subtle.find_focus_client_mut() {
+                // Gravity has no effect on fullscreen clients
+                if let GrabAction::List(gravity_ids) = action
+                    && !focus_client.flags.contains(ClientFlags::MODE_FULL)
+                {
+                    // Remove float mode
+                    if focus_client.flags.contains(ClientFlags::MODE_FLOAT) {
+                        let mut mode_flags = ClientFlags::MODE_FLOAT;
+                        focus_client.toggle(subtle, &mut mode_flags, true, None)?;
+
+                        focus_client.gravity_idx = -1; // Reset
+                    }
+
+                    // Skip count entries with wrap-around or fallback to first
+                    let current_idx = gravity_ids.iter()
+                        .position(|gravity_id| focus_client.gravity_idx == *gravity_id as isize);
+
+                    let new_gravity_id = grab::calc_cycle_idx(gravity_ids.len(), current_idx,
+                                                              count, CycleOrder::Next)
+                        .and_then(|idx| gravity_ids.get(idx))
+                        .copied()
+                        .context("No gravity ID")?;
+
+                    // Finally update client
+                    let screen_id = focus_client.screen_idx;
+
+                    focus_client.arrange(subtle, new_gravity_id as isize, screen_id)?;
+                    focus_client.restack(RestackOrder::Up);
+
+                    if !subtle.flags.intersects(SubtleFlags::SKIP_POINTER_WARP) {
+                        focus_client.warp_pointer(subtle)?;
+                    }
+
+                    drop(focus_client);
+
+                    subtle.restack_windows()?;
+                    screen::configure(subtle)?;
+                    panel::update(subtle)?;
+
+                    outcome = GrabOutcome::Done;
+                }
+            }
+
+            outcome
+        },
+
+        GrabFlags::WINDOW_PIN => {
+            let mut outcome = GrabOutcome::NoOp;
+
+            let screen_idx = view::find_current_screen_idx(subtle);
+
+            let view_tags = subtle.screens.get(screen_idx)
+                .and_then(|screen| usize::try_from(screen.view_idx.get()).ok())
+                .and_then(|view_idx| subtle.views.borrow().get(view_idx).map(|view| view.tags.get()));
+
+            if let GrabAction::Index(is_pin) = *action
+                && let Some(view_tags) = view_tags
+                && let Some(mut focus_client) = subtle.find_focus_client_mut()
+                && focus_client.pin(subtle, view_tags, 1 == is_pin)?
+            {
+                drop(focus_client);
+
+                screen::configure(subtle)?;
+                panel::update(subtle)?;
+                panel::render(subtle)?;
+
+                // Update focus when the client left the view
+                if subtle.find_focus_client().is_none_or(|focus_client| !focus_client.is_visible(subtle))
+                    && let Some(next_client) = subtle.find_next_client(screen_idx as isize, false)
+                {
+                    next_client.focus(subtle, true)?;
+                }
+
+                outcome = GrabOutcome::Done;
+            }
+
+            outcome
+        },
+
+        GrabFlags::PANEL_TOGGLE => {
+            let mut outcome = GrabOutcome::NoOp;
+
+            // Defer toggle, resizing screens requires exclusive access
+            if let GrabAction::Index(panels) = *action
+                && let Some((screen_idx, _)) = subtle.find_screen_by_xy(x, y)
+            {
+                subtle.panel_toggle.set(Some((screen_idx, ScreenFlags::from_bits_truncate(panels))));
+
+                outcome = GrabOutcome::Done;
+            }
+
+            outcome
+        },
+
+        GrabFlags::WINDOW_DUMP => {
+            let mut outcome = GrabOutcome::NoOp;
+
+            if subtle.find_focus_client().is_some() {
+                dump::dump_client(subtle, NONE)?;
+
+                outcome = GrabOutcome::Done;
+            }
+
+            outcome
+        },
+
+        GrabFlags::WINDOW_KILL => {
+            let mut outcome = GrabOutcome::NoOp;
+
+            if let Some(focus_client) = subtle.find_focus_client() {
+                let (win, screen_idx) = (focus_client.win, focus_client.screen_idx);
+
+                focus_client.close(subtle)?;
+
+                drop(focus_client);
+
+                screen::configure(subtle)?;
+                panel::update(subtle)?;
+                panel::render(subtle)?;
+
+                // Pick next client after configure, the closed one may still linger until it unmaps
+                if let Some(next_client) = subtle.find_next_client_except(screen_idx, false, win) {
+                    next_client.focus(subtle, true)?;
+                }
+
+                outcome = GrabOutcome::Done;
+            }
+
+            outcome
+        },
+
+        GrabFlags::GAP_ADJUST => {
+            let mut outcome = GrabOutcome::NoOp;
+
+            if let GrabAction::Index(order) = *action {
+                let order = GapOrder::from_repr(order as u8).context("Unknown order")?;
+                let step = subtle.gap_step.saturating_mul(count.min(i16::MAX as u32) as i16);
+
+                let margin = style::calc_gap_margin(subtle.clients_margin.get(),
+                                                    subtle.clients_style.margin, order, step);
+
+                if margin != subtle.clients_margin.get() {
+                    subtle.clients_margin.set(margin);
+
+                    client::mark_visible_arrange(subtle);
+                    screen::configure(subtle)?;
+
+                    outcome = GrabOutcome::Done;
+                }
+            }
+
+            outcome
+        },
+
+        GrabFlags::SUBTLE_QUIT => {
+            subtle.shutdown.store(true, Ordering::Relaxed);
+
+            GrabOutcome::Done
+        },
+
+        GrabFlags::COMMAND => {
+            if let GrabAction::Command(cmd) = action {
+                debug!("{}: command={}", function_name!(), cmd);
+
+                Command::new(cmd)
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()?;
+            }
+
+            GrabOutcome::Done
+        }
+
+        _ => GrabOutcome::Done,
+    })
+}
+
+/// Find grab and check whether the transients modifier was held additionally
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `code` - Keycode or button of the event
+/// * `modifiers` - Relevant modifiers of the event
+///
+/// # Returns
+///
+/// A tuple with the [`Grab`] if any and whether to drag transients along
+fn find_grab_with_transients(subtle: &Subtle, code: Keycode, modifiers: ModMask) -> (Option<&Grab>, bool) {
+    match subtle.find_grab(code, modifiers) {
+        None if 0 != u16::from(subtle.transients_drag_modifier)
+            && modifiers.contains(subtle.transients_drag_modifier) =>
+        {
+            (subtle.find_grab(code, modifiers.remove(subtle.transients_drag_modifier)), true)
+        },
+        maybe_grab => (maybe_grab, false),
+    }
+}
+
+/// Handle key press events
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `event` - Event to handle
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn handle_key_press(subtle: &Subtle, event: KeyPressEvent) -> Result<()> {
+    // Keys just adjust a running keyboard drag
+    if subtle.keyboard_drag.borrow().is_some() {
+        handle_keyboard_drag(subtle, event.detail, u16::from(event.state))?;
+
+        debug!("{}: win={}, keycode={}", function_name!(), event.event, event.detail);
+
+        return Ok(());
+    }
+
+    // Limit mod mask to relevant ones, M5 is required for AltGr levels
+    let relevant_modifiers = ModMask::from(event.state.bits()
+        & (ModMask::SHIFT | ModMask::CONTROL | ModMask::M1 | ModMask::M4 | ModMask::M5
+            | subtle.transients_drag_modifier));
+
+    let (maybe_grab, with_transients) = find_grab_with_transients(subtle, event.detail, relevant_modifiers);
+
+    if let Some(grab) = maybe_grab {
+        let flag = grab.flags.difference(GrabFlags::IS_KEY | GrabFlags::IS_MOUSE);
+
+        // Consume count prefix as repeat factor only in grabs that use it
+        let count = if flag.intersects(GrabFlags::COUNTED) {
+            let mut count_prefix = subtle.count_prefix.get();
+            let is_pending = count_prefix.get(Instant::now()).is_some();
+            let count = count_prefix.take(Instant::now());
+
+            subtle.count_prefix.set(count_prefix);
+
+            // Hide consumed count
+            if is_pending {
+                panel::update(subtle)?;
+                panel::render(subtle)?;
+            }
+
+            count
+        } else {
+            1
+        };
+
+        let outcome = if GrabFlags::WINDOW_SWITCH == flag {
+            handle_window_cycle(subtle, grab, count)?
+        } else if grab.flags.contains(GrabFlags::IS_KEY)
+            && matches!(flag, GrabFlags::WINDOW_MOVE | GrabFlags::WINDOW_RESIZE)
+        {
+            start_keyboard_drag(subtle, grab, flag, with_transients)?
+        } else {
+            handle_grab_action(subtle, flag, &grab.action, event.event_x, event.event_y, count)?
+        };
+
+        println!("grab={:?}", grab);
+
+        flash::handle_outcome(subtle, outcome)?;
+    }
+
+    panel::update(subtle)?;
+    panel::render(subtle)?;
+
+    // Restore binds
+    let conn = subtle.conn.get().context("Failed to get connection")?;
+    let default_screen = &conn.setup().roots[subtle.screen_num];
+
+    grab::unset(subtle, default_screen.root)?;
+    grab::set(subtle, default_screen.root, GrabFlags::IS_KEY)?;
+
+    debug!("{}: win={}, keycode={}", function_name!(), event.event, event.detail);
+
+    Ok(())
+}
+
+/// Handle key release events
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `event` - Event to handle
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn handle_key_release(subtle: &Subtle, event: KeyReleaseEvent) -> Result<()> {
+    // Release of a held modifier ends the window cycle
+    let is_modifier = subtle.window_cycle.borrow().as_ref()
+        .is_some_and(|window_cycle| window_cycle.modifier_keycodes.contains(&event.detail));
+
+    if is_modifier {
+        finish_window_cycle(subtle)?;
+
+        panel::update(subtle)?;
         panel::render(subtle)?;
     }
 
-    debug!("{}: win={}, count={}", function_name!(), event.window, event.count);
+    debug!("{}: win={}, keycode={}", function_name!(), event.event, event.detail);
 
     Ok(())
 }
 
-/// Handle focus in events
+/// Start or advance the window cycle in most-recently-used order
 ///
 /// # Arguments
 ///
 /// * `subtle` - Global state object
-/// * `event` - Event to handle
+/// * `grab` - Grab of the cycle
+/// * `count` - Number of steps
 ///
 /// # Returns
 ///
-/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-fn handle_focus_in(subtle: &Subtle, event: FocusInEvent) -> Result<()> {
-    if let Some(mut client) = subtle.find_client_mut(event.event) {
+/// A [`Result`] with either [`GrabOutcome`] on success or otherwise [`anyhow::Error`]
+fn handle_window_cycle(subtle: &Subtle, grab: &Grab, count: u32) -> Result<GrabOutcome> {
+    let GrabAction::Index(order) = grab.action else {
+        return Ok(GrabOutcome::NoOp);
+    };
 
-        // Remove urgent after getting focus
-        if client.flags.intersects(ClientFlags::MODE_URGENT) {
-            client.flags.remove(ClientFlags::MODE_URGENT);
-            subtle.urgent_tags.replace(subtle.urgent_tags.get() - client.tags);
+    let order = CycleOrder::from_repr(order as u8).context("Unknown order")?;
+
+    let conn = subtle.conn.get().context("Failed to get connection")?;
+    let default_screen = &conn.setup().roots[subtle.screen_num];
+
+    if subtle.window_cycle.borrow().is_none() {
+        let screen_idx = subtle.find_focus_client().map(|client| client.screen_idx)
+            .or_else(|| subtle.find_screen_by_pointer().map(|(screen_idx, _)| screen_idx as isize))
+            .unwrap_or(0);
+
+        // Collect visible clients of the current screen in stacking order
+        let stacking = subtle.clients.borrow().iter()
+            .filter(|client| client.screen_idx == screen_idx && client.is_alive() && client.is_visible(subtle)
+                && !client.flags.intersects(ClientFlags::TYPE_DESKTOP | ClientFlags::TYPE_DOCK))
+            .map(|client| client.win)
+            .collect::<Vec<Window>>();
+
+        let history = subtle.focus_history.to_vec();
+        let wins = history::calc_mru_order(&history, &stacking);
+
+        if wins.len() < 2 {
+            return Ok(GrabOutcome::NoOp);
         }
 
-        drop(client);
+        // Shift just reverses the order, so the cycle continues without it
+        let without_shift = ModMask::from(u16::from(grab.modifiers) & !u16::from(ModMask::SHIFT));
+        let held_modifiers = if 0 != u16::from(without_shift) { without_shift } else { grab.modifiers };
 
-        // Update focus history
-        if let Some(mut focus_win) = subtle.focus_history.borrow_mut(0) {
-            *focus_win = event.event;
+        let mapping = conn.get_modifier_mapping()?.reply()?;
+        let modifier_keycodes = grab::find_modifier_keycodes(&mapping.keycodes,
+            mapping.keycodes_per_modifier(), held_modifiers);
+
+        // Grab keyboard to receive the release of the modifiers
+        if !modifier_keycodes.is_empty() {
+            conn.grab_keyboard(false, default_screen.root, subtle.last_timestamp.get(),
+                               GrabMode::ASYNC, GrabMode::ASYNC)?.reply()?;
         }
 
-        // Update screen
-        panel::update(subtle)?;
-        panel::render(subtle)?;
+        let idx = wins.iter().position(|win| subtle.find_focus_win() == *win).unwrap_or(wins.len() - 1);
+
+        subtle.window_cycle.replace(Some(WindowCycle { wins, idx, modifier_keycodes, history }));
     }
 
-    debug!("{}: win={}", function_name!(), event.event);
+    let (win, is_held) = {
+        let mut window_cycle = subtle.window_cycle.borrow_mut();
+        let window_cycle = window_cycle.as_mut().context("No window cycle")?;
 
-    Ok(())
+        window_cycle.idx = grab::calc_cycle_idx(window_cycle.wins.len(), Some(window_cycle.idx), count, order)
+            .unwrap_or_default();
+
+        (window_cycle.wins[window_cycle.idx], !window_cycle.modifier_keycodes.is_empty())
+    };
+
+    if let Some(client) = subtle.find_client(win) {
+        client.focus(subtle, !subtle.flags.intersects(SubtleFlags::SKIP_POINTER_WARP))?;
+    }
+
+    // Grabs without modifiers cannot be held
+    if !is_held {
+        finish_window_cycle(subtle)?;
+    }
+
+    debug!("{}: win={}, count={}", function_name!(), win, count);
+
+    Ok(GrabOutcome::Done)
 }
 
-/// Handle key press events
+/// Finish the window cycle and move the selected window to the front of the history
 ///
 /// # Arguments
 ///
 /// * `subtle` - Global state object
-/// * `event` - Event to handle
 ///
 /// # Returns
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-fn handle_key_press(subtle: &Subtle, event: KeyPressEvent) -> Result<()> {
-    // Limit mod mask to relevant ones
-    let relevant_modifiers = ModMask::from(event.state.bits()
-        & (ModMask::SHIFT | ModMask::CONTROL | ModMask::M1 | ModMask::M4));
+fn finish_window_cycle(subtle: &Subtle) -> Result<()> {
+    let Some(window_cycle) = subtle.window_cycle.take() else {
+        return Ok(());
+    };
 
-    if let Some(grab) = subtle.find_grab(event.detail, relevant_modifiers) {
-        let flag = grab.flags.difference(GrabFlags::IS_KEY | GrabFlags::IS_MOUSE);
+    let conn = subtle.conn.get().context("Failed to get connection")?;
 
-        match flag {
-            GrabFlags::VIEW_SWITCH | GrabFlags::VIEW_SELECT => {
-                if let GrabAction::Index(idx) = grab.action {
-                    if let Some(view) = subtle.views.get(idx as usize - 1) {
-                        let mut screen_idx: isize = -1;
+    if !window_cycle.modifier_keycodes.is_empty() {
+        conn.ungrab_keyboard(subtle.last_timestamp.get())?;
+    }
 
-                        // Find screen: Prefer screen of current window
-                        if subtle.flags.intersects(SubtleFlags::SKIP_POINTER_WARP)
-                            && let Some(focus_client) = subtle.find_focus_client()
-                            && focus_client.is_visible(subtle)
-                        {
-                            screen_idx = focus_client.screen_idx;
-                        } else if let Some((maybe_screen_id, _)) = subtle.find_screen_by_xy(
-                            event.event_x, event.event_y)
-                        {
-                            screen_idx = maybe_screen_id as isize;
-                        }
+    // Restore order of the history without the intermediate selections
+    subtle.focus_history.replace(window_cycle.history.into_iter()
+        .filter(|win| subtle.find_client(*win).is_some())
+        .collect());
 
-                        view.focus(subtle, screen_idx as usize,
-                                   GrabFlags::VIEW_SWITCH == flag, true)?;
+    let win = window_cycle.wins[window_cycle.idx];
 
-                        // Finally configure and render
-                        screen::configure(subtle)?;
-                        panel::render(subtle)?;
-                    }
-                }
-            },
+    if subtle.find_client(win).is_some() {
+        subtle.focus_history.push(win);
+    }
 
-            GrabFlags::WINDOW_MODE => {
-                if let Some(mut focus_client) = subtle.find_focus_client_mut() {
-                    if let GrabAction::Index(bits) = grab.action {
-                        let mut mode_flags = ClientFlags::from_bits(bits)
-                            .context("Unknown client flags")?;
+    debug!("{}: win={}", function_name!(), win);
 
-                        focus_client.toggle(subtle, &mut mode_flags, true)?;
+    Ok(())
+}
 
-                        // Update screen and focus
-                        if focus_client.is_visible(subtle) || ClientFlags::MODE_STICK == mode_flags {
-                            // Store values and drop reference
-                            let is_visible = focus_client.is_visible(subtle);
-                            let screen_idx = focus_client.screen_idx;
+/// Keyboard and server grab of a keyboard drag, released on drop unless kept for the drag
+struct DragGrabGuard<'a> {
+    /// Global state object
+    subtle: &'a Subtle,
+    /// Whether the server is grabbed as well
+    is_server_grabbed: bool,
+    /// Whether the grabs outlive the guard
+    is_kept: bool,
+}
 
-                            drop(focus_client);
+impl<'a> DragGrabGuard<'a> {
+    /// Create a new instance and grab the keyboard
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`DragGrabGuard`] on success or otherwise [`anyhow::Error`]
+    fn new(subtle: &'a Subtle) -> Result<Self> {
+        let conn = subtle.conn.get().context("Failed to get connection")?;
+        let default_screen = &conn.setup().roots[subtle.screen_num];
 
-                            // Find next and focus
-                            if !is_visible {
-                                if let Some(next_client) = subtle.find_next_client(screen_idx, false) {
-                                    next_client.focus(subtle, true)?;
-                                }
-                            }
+        let status = conn.grab_keyboard(false, default_screen.root, subtle.last_timestamp.get(),
+                                        GrabMode::ASYNC, GrabMode::ASYNC)?.reply()?.status;
 
-                            // Finally configure, update and render
-                            screen::configure(subtle)?;
-                            panel::update(subtle)?;
-                            panel::render(subtle)?;
-                        }
-                    }
-                }
-            },
+        if GrabStatus::SUCCESS != status {
+            return Err(anyhow!("Failed to grab keyboard: {:?}", status));
+        }
 
-            GrabFlags::WINDOW_RESTACK => {
-                if let Some(mut focus_client) = subtle.find_focus_client_mut() {
-                    if let GrabAction::Index(order) = grab.action {
-                        focus_client.restack(RestackOrder::from_repr(order as u8)
-                            .context("Unknown order")?);
+        Ok(Self {
+            subtle,
+            is_server_grabbed: false,
+            is_kept: false,
+        })
+    }
 
-                        drop(focus_client);
+    /// Grab server as well
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    fn grab_server(&mut self) -> Result<()> {
+        let conn = self.subtle.conn.get().context("Failed to get connection")?;
 
-                        subtle.restack_windows()?;
-                    }
-                }
-            },
+        conn.grab_server()?;
 
-            GrabFlags::WINDOW_GRAVITY => {
-                if let Some(mut focus_client) = subtle.find_focus_client_mut() {
-                    if let GrabAction::List(gravity_ids) = &grab.action {
-                        // Remove float and fullscreen mode
-                        if focus_client.flags.intersects(ClientFlags::MODE_FLOAT | ClientFlags::MODE_FULL) {
-                            let mut mode_flags = focus_client.flags & (ClientFlags::MODE_FLOAT | ClientFlags::MODE_FULL);
-                            focus_client.toggle(subtle, &mut mode_flags, true)?;
+        self.is_server_grabbed = true;
 
-                            focus_client.gravity_idx = -1; // Reset
-                        }
+        Ok(())
+    }
 
-                        // Find next gravity or fallback to first
-                        let mut new_gravity_id = *gravity_ids.first().context("No gravity ID")?;
+    /// Keep grabs until the drag is finished
+    fn keep(mut self) {
+        self.is_kept = true;
+    }
+}
 
-                        for (idx, gravity_id) in gravity_ids.iter().enumerate() {
-                            if focus_client.gravity_idx == *gravity_id as isize {
-                                if idx < gravity_ids.len() {
-                                    new_gravity_id = idx + 1;
-                                }
+impl Drop for DragGrabGuard<'_> {
+    fn drop(&mut self) {
+        if self.is_kept {
+            return;
+        }
 
-                                break;
-                            }
-                        }
+        if let Some(conn) = self.subtle.conn.get() {
+            if self.is_server_grabbed && let Err(err) = conn.ungrab_server() {
+                warn!("Failed to ungrab server: {}", err);
+            }
 
-                        // Finally update client
-                        let screen_id = focus_client.screen_idx;
+            if let Err(err) = conn.ungrab_keyboard(self.subtle.last_timestamp.get()) {
+                warn!("Failed to ungrab keyboard: {}", err);
+            }
+        }
+    }
+}
 
-                        focus_client.arrange(subtle, new_gravity_id as isize, screen_id)?;
-                        focus_client.restack(RestackOrder::Up);
+/// Start keyboard move or resize of the focus client
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `grab` - Grab that started the drag
+/// * `flag` - Move or resize grab flag
+/// * `with_transients` - Whether to move transients along
+///
+/// # Returns
+///
+/// A [`Result`] with either [`GrabOutcome`] on success or otherwise [`anyhow::Error`]
+fn start_keyboard_drag(subtle: &Subtle, grab: &Grab, flag: GrabFlags, with_transients: bool) -> Result<GrabOutcome> {
+    let drag_mode = if GrabFlags::WINDOW_MOVE == flag { DragMode::MOVE } else { DragMode::RESIZE };
 
-                        if !subtle.flags.intersects(SubtleFlags::SKIP_POINTER_WARP) {
-                            focus_client.warp_pointer(subtle)?;
-                        }
+    let Some(focus_client) = subtle.find_focus_client() else {
+        return Ok(GrabOutcome::NoOp);
+    };
 
-                        drop(focus_client);
+    if focus_client.flags.intersects(ClientFlags::MODE_FULL) || !client::is_draggable(focus_client.flags, drag_mode) {
+        return Ok(GrabOutcome::NoOp);
+    }
 
-                        subtle.restack_windows()?;
-                        screen::configure(subtle)?;
-                        panel::update(subtle)?;
-                    }
-                }
-            },
+    let win = focus_client.win;
 
-            GrabFlags::WINDOW_KILL => {
-                if let Some(focus_client) = subtle.find_focus_client_mut() {
-                    let screen_idx = focus_client.screen_idx;
+    drop(focus_client);
 
-                    focus_client.close(subtle)?;
+    // Grab keyboard to receive the drag keys before anything is changed
+    let mut grabs = DragGrabGuard::new(subtle)?;
 
-                    screen::configure(subtle)?;
-                    panel::update(subtle)?;
-                    panel::render(subtle)?;
+    let transients = if with_transients && DragMode::MOVE == drag_mode {
+        subtle.find_transients(win)
+    } else {
+        Vec::new()
+    };
 
-                    // Update focus if necessary
-                    if let Some(next_client) = subtle.find_next_client(screen_idx, false) {
-                        next_client.focus(subtle, true)?;
-                    }
+    let Some(mut focus_client) = subtle.find_client_mut(win) else {
+        return Ok(GrabOutcome::NoOp);
+    };
+
+    if !focus_client.flags.intersects(ClientFlags::MODE_FLOAT) {
+        let mut mode_flags = ClientFlags::MODE_FLOAT;
+
+        focus_client.toggle(subtle, &mut mode_flags, true, None)?;
+    }
+
+    let keysyms_to_keycode = grab::build_reverse_keymap(subtle)?;
+
+    let mut keyboard_drag = KeyboardDrag::new(focus_client.win, drag_mode, focus_client.geom,
+        client::calc_drag_keys(&keysyms_to_keycode, &subtle.grabs), subtle.keyboard_drag_outline);
+
+    keyboard_drag.transients = transients;
+    keyboard_drag.snap_modifier = client::calc_snap_modifier(subtle.snap_modifier, grab.modifiers);
+
+    drop(focus_client);
+
+    // Grab server, so the outline isn't painted over
+    if keyboard_drag.outline {
+        grabs.grab_server()?;
+
+        for geom in keyboard_drag.mask.show(&keyboard_drag.pending) {
+            client::draw_mask(subtle, &geom)?;
+        }
+    }
+
+    // Grabs are released when the drag is finished
+    grabs.keep();
+
+    debug!("{}: win={}, drag_mode={:?}", function_name!(), keyboard_drag.win, drag_mode);
+
+    subtle.keyboard_drag.replace(Some(keyboard_drag));
+
+    Ok(GrabOutcome::Done)
+}
+
+/// Adjust, commit or cancel the keyboard drag
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `keycode` - Keycode of the pressed key
+/// * `state` - Modifier state of the key event
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn handle_keyboard_drag(subtle: &Subtle, keycode: Keycode, state: u16) -> Result<()> {
+    let maybe_key = subtle.keyboard_drag.borrow().as_ref()
+        .and_then(|keyboard_drag| keyboard_drag.find_key(keycode));
+
+    match maybe_key {
+        Some(DragKey::Step(drag_dir)) => {
+            let mut keyboard_drag = subtle.keyboard_drag.borrow_mut();
+            let keyboard_drag = keyboard_drag.as_mut().context("No keyboard drag")?;
+
+            // Client might be gone in the meantime
+            let Some(mut client) = subtle.find_client_mut(keyboard_drag.win) else {
+                return Ok(());
+            };
+
+            let mut geom = keyboard_drag.step(drag_dir, subtle.step_size, client.width_inc, client.height_inc);
+
+            // Keep outline in line with the screen edges and size hints
+            if let Some(screen) = subtle.screens.get(client.screen_idx as usize) {
+                if client::is_snapping(state, keyboard_drag.snap_modifier, subtle.snap_size) {
+                    client.snap(subtle, screen, &mut geom)?;
                 }
-            },
 
-            GrabFlags::SUBTLE_QUIT => {
-                subtle.shutdown.store(true, Ordering::Relaxed);
-            },
+                client.apply_size_hints(subtle, &screen.geom, false, false, &mut geom);
+
+                keyboard_drag.pending = geom;
+            }
+
+            if keyboard_drag.outline {
+                for mask_geom in keyboard_drag.mask.show(&geom) {
+                    client::draw_mask(subtle, &mask_geom)?;
+                }
+            } else {
+                client.apply_drag_geom(subtle, &geom)?;
+
+                drop(client);
 
-            GrabFlags::COMMAND => {
-                if let GrabAction::Command(cmd) = &grab.action {
-                    debug!("{}: command={}", function_name!(), cmd);
+                // Move transients along
+                if !subtle.transients_drag_on_release {
+                    let (delta_x, delta_y) = keyboard_drag.calc_delta(&geom);
 
-                    Command::new(cmd)
-                        .stdout(Stdio::null())
-                        .stderr(Stdio::null())
-                        .spawn()?;
+                    client::move_transients(subtle, &keyboard_drag.transients, delta_x, delta_y)?;
                 }
             }
+        },
+        Some(DragKey::Commit) => finish_keyboard_drag(subtle, true)?,
+        Some(DragKey::Cancel) => finish_keyboard_drag(subtle, false)?,
+        None => {},
+    }
+
+    debug!("{}: keycode={}, key={:?}", function_name!(), keycode, maybe_key);
+
+    Ok(())
+}
+
+/// Finish the keyboard drag and apply or restore the geometry
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `commit` - Whether to commit or cancel the drag
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn finish_keyboard_drag(subtle: &Subtle, commit: bool) -> Result<()> {
+    let Some(mut keyboard_drag) = subtle.keyboard_drag.take() else {
+        return Ok(());
+    };
+
+    let conn = subtle.conn.get().context("Failed to get connection")?;
 
-            _ => {},
+    if keyboard_drag.outline {
+        if let Some(mask_geom) = keyboard_drag.mask.hide() {
+            client::draw_mask(subtle, &mask_geom)?;
         }
 
-        println!("grab={:?}", grab);
+        conn.ungrab_server()?;
     }
 
-    panel::update(subtle)?;
-    panel::render(subtle)?;
+    conn.ungrab_keyboard(subtle.last_timestamp.get())?;
 
-    // Restore binds
-    let conn = subtle.conn.get().context("Failed to get connection")?;
-    let default_screen = &conn.setup().roots[subtle.screen_num];
+    if let Some(geom) = keyboard_drag.calc_finish_geom(commit)
+        && let Some(mut client) = subtle.find_client_mut(keyboard_drag.win)
+    {
+        client.apply_drag_geom(subtle, &geom)?;
+    }
 
-    grab::unset(subtle, default_screen.root)?;
-    grab::set(subtle, default_screen.root, GrabFlags::IS_KEY)?;
+    // Move transients to the final position of their parent
+    let final_geom = if commit { keyboard_drag.pending } else { keyboard_drag.origin };
+    let (delta_x, delta_y) = keyboard_drag.calc_delta(&final_geom);
 
-    debug!("{}: win={}, keycode={}", function_name!(), event.event, event.detail);
+    client::move_transients(subtle, &keyboard_drag.transients, delta_x, delta_y)?;
+
+    panel::update(subtle)?;
+    panel::render(subtle)?;
+
+    debug!("{}: win={}, commit={}", function_name!(), keyboard_drag.win, commit);
 
     Ok(())
 }
@@ -616,6 +1881,7 @@ fn handle_key_press(subtle: &Subtle, event: KeyPressEvent) -> Result<()> {
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
 fn handle_map_notify(subtle: &Subtle, event: MapNotifyEvent) -> Result<()> {
     // Check if we know the window
+    #[cfg(feature = "tray")]
     if let Some(mut tray) = subtle.find_tray_mut(event.window) {
         tray.flags.remove(TrayFlags::DEAD);
 
@@ -671,12 +1937,16 @@ fn handle_mapping_notify(subtle: &Subtle, event: MappingNotifyEvent) -> Result<(
 fn handle_property_notify(subtle: &Subtle, event: PropertyNotifyEvent) -> Result<()> {
     let atoms = subtle.atoms.get().unwrap();
 
-    if atoms.WM_NAME == event.atom {
+    if subtle.is_ignored_win(event.window) {
+        return Ok(());
+    }
+
+    if atoms.WM_NAME == event.atom || atoms._NET_WM_NAME == event.atom {
         if let Some(mut client) = subtle.find_client_mut(event.window) {
             client.set_wm_name(subtle)?;
 
-            if let Some(win) = subtle.focus_history.borrow(0)
-                && event.window == *win
+            if let Some(win) = subtle.focus_history.first()
+                && event.window == win
             {
                 drop(client);
 
@@ -692,7 +1962,7 @@ fn handle_property_notify(subtle: &Subtle, event: PropertyNotifyEvent) -> Result
 
             let mut enable_only = client.flags.complement().intersection(mode_flags);
 
-            client.toggle(subtle, &mut enable_only, true)?;
+            client.toggle(subtle, &mut enable_only, true, None)?;
 
             if client.is_visible(subtle) {
                 drop(client);
@@ -707,13 +1977,19 @@ fn handle_property_notify(subtle: &Subtle, event: PropertyNotifyEvent) -> Result
 
             client.set_wm_hints(subtle, &mut mode_flags)?;
 
-            let mut enable_only = client.flags.complement().intersection(mode_flags);
+            // Enable new modes and clear urgency once the hint is withdrawn
+            let mut toggle_flags = client.flags.complement().intersection(mode_flags)
+                | client.flags.intersection(ClientFlags::MODE_URGENT).difference(mode_flags);
 
-            client.toggle(subtle, &mut enable_only, true)?;
+            client.toggle(subtle, &mut toggle_flags, true, None)?;
 
-            if client.is_visible(subtle) || client.flags.contains(ClientFlags::MODE_URGENT) {
-                drop(client);
+            let needs_update = client.is_visible(subtle) || client.flags.contains(ClientFlags::MODE_URGENT);
+
+            drop(client);
+
+            client::update_urgent_tags(subtle)?;
 
+            if needs_update {
                 panel::update(subtle)?;
                 panel::render(subtle)?;
             }
@@ -732,10 +2008,19 @@ fn handle_property_notify(subtle: &Subtle, event: PropertyNotifyEvent) -> Result
             let mut mode_flags = ClientFlags::empty();
             let mut enable_only = client.flags.complement().intersection(mode_flags);
 
-            client.toggle(subtle, &mut enable_only, true)?;
+            client.toggle(subtle, &mut enable_only, true, None)?;
             client.set_motif_wm_hints(subtle, &mut mode_flags)?;
         }
+    } else if atoms._NET_WM_HANDLED_ICONS == event.atom {
+        if let Some(mut client) = subtle.find_client_mut(event.window) {
+            client.set_handled_icons(subtle)?;
+
+            drop(client);
+
+            client::update_handled_icons(subtle);
+        }
     } else if atoms._XEMBED_INFO == event.atom {
+        #[cfg(feature = "tray")]
         if let Some(mut tray) = subtle.find_tray_mut(event.window) {
             tray.set_state(subtle)?;
 
@@ -762,19 +2047,28 @@ fn handle_property_notify(subtle: &Subtle, event: PropertyNotifyEvent) -> Result
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
 fn handle_map_request(subtle: &Subtle, event: MapRequestEvent) -> Result<()> {
-    // Check if we know the window
+    let conn = subtle.conn.get().context("Failed to get connection")?;
+
+    // Check if we know the window before asking for ignore rules
     if let Some(mut client) = subtle.find_client_mut(event.window) {
         client.flags.remove(ClientFlags::DEAD);
         client.flags.insert(ClientFlags::ARRANGE);
 
         drop(client);
 
+        client::update_handled_icons(subtle);
         screen::configure(subtle)?;
         panel::update(subtle)?;
         panel::render(subtle)?;
-    } else if let Ok(client) = Client::new(subtle, event.window) {
+    } else if ignore::check_win(subtle, event.window)? {
+        // Just map ignored windows unmanaged
+        error::skip_transient(conn.map_window(event.window)?.check().map_err(Into::into),
+                              "map ignored window")?;
+    } else if let Some(client) = error::skip_transient(Client::new(subtle, event.window),
+                                                       "manage window")? {
         subtle.add_client(client);
 
+        client::update_handled_icons(subtle);
         screen::configure(subtle)?;
         panel::update(subtle)?;
         panel::render(subtle)?;
@@ -797,6 +2091,10 @@ fn handle_map_request(subtle: &Subtle, event: MapRequestEvent) -> Result<()> {
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
 fn handle_unmap_notify(subtle: &Subtle, event: UnmapNotifyEvent) -> Result<()> {
+    if subtle.is_ignored_win(event.window) {
+        return Ok(());
+    }
+
     // Check if we know the window
     if let Some(mut client) = subtle.find_client_mut(event.window) {
         // Set withdrawn state (see ICCCM 4.1.4)
@@ -806,38 +2104,27 @@ fn handle_unmap_notify(subtle: &Subtle, event: UnmapNotifyEvent) -> Result<()> {
         if client.flags.contains(ClientFlags::UNMAP) {
             client.flags.remove(ClientFlags::UNMAP);
         } else {
+            let screen_idx = client.screen_idx;
+            let was_focus = subtle.find_focus_win() == event.window;
+
             client.kill(subtle)?;
 
             drop(client);
 
             subtle.remove_client_by_win(event.window);
 
+            client::update_handled_icons(subtle);
             client::publish(subtle, false)?;
 
             screen::configure(subtle)?;
+            focus_next_unless_entered(subtle, was_focus, screen_idx)?;
             panel::update(subtle)?;
             panel::render(subtle)?;
         }
-    } else if let Some(mut tray) = subtle.find_tray_mut(event.window) {
-        // Set withdrawn state (see ICCCM 4.1.4)
-        tray.set_wm_state(subtle, WMState::Withdrawn)?;
-
-        // Ignore our generated unmap events
-        if tray.flags.contains(TrayFlags::UNMAP) {
-            tray.flags.remove(TrayFlags::UNMAP);
-        } else {
-            tray.kill(subtle)?;
-
-            drop(tray);
-
-            subtle.remove_tray_by_win(event.window);
-
-            tray::publish(subtle)?;
-
-            screen::configure(subtle)?;
-            panel::update(subtle)?;
-            panel::render(subtle)?;
-        }
+    } else if withdraw_tray(subtle, event.window)? {
+        screen::configure(subtle)?;
+        panel::update(subtle)?;
+        panel::render(subtle)?;
     }
 
     debug!("{}: win={}", function_name!(), event.window);
@@ -870,6 +2157,96 @@ fn handle_selection_clear(subtle: &Subtle, event: SelectionClearEvent) -> Result
     Ok(())
 }
 
+/// Dispatch event to its handler
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `event` - Event to handle
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn handle_event(subtle: &Subtle, event: Event) -> Result<()> {
+    match event {
+        Event::ButtonPress(evt) => handle_button_press(subtle, evt)?,
+        Event::ConfigureNotify(evt) => handle_configure_notify(subtle, evt)?,
+        Event::ConfigureRequest(evt) => handle_configure_request(subtle, evt)?,
+        Event::ClientMessage(evt) => handle_client_message(subtle, evt)?,
+        Event::DestroyNotify(evt) => handle_destroy_notify(subtle, evt)?,
+        Event::EnterNotify(evt) => handle_enter_notify(subtle, evt)?,
+        Event::LeaveNotify(evt) => handle_leave_notify(subtle, evt)?,
+        Event::Expose(evt) => handle_expose(subtle, evt)?,
+        Event::FocusIn(evt) => handle_focus_in(subtle, evt)?,
+        Event::KeyPress(evt) => handle_key_press(subtle, evt)?,
+        Event::KeyRelease(evt) => handle_key_release(subtle, evt)?,
+        Event::MapNotify(evt) => handle_map_notify(subtle, evt)?,
+        Event::MappingNotify(evt) => handle_mapping_notify(subtle, evt)?,
+        Event::MotionNotify(evt) => handle_motion_notify(subtle, evt)?,
+        Event::MapRequest(evt) => handle_map_request(subtle, evt)?,
+        Event::PropertyNotify(evt) => handle_property_notify(subtle, evt)?,
+        Event::SelectionClear(evt) => handle_selection_clear(subtle, evt)?,
+        Event::UnmapNotify(evt) => handle_unmap_notify(subtle, evt)?,
+        Event::RandrScreenChangeNotify(_) | Event::RandrNotify(_) => subtle.screens_changed.set(true),
+
+        _ => {
+            if subtle.flags.intersects(SubtleFlags::DEBUG) {
+                warn!("Unhandled event: {:?}", event)
+            }
+        },
+    }
+
+    Ok(())
+}
+
+/// Block until the connection has input, a signal wakes us up or the timeout expires
+///
+/// # Arguments
+///
+/// * `conn_fd` - File descriptor of the connection to X11
+/// * `wakeup` - Read end of the self-pipe signal handlers write to
+/// * `maybe_timeout` - Timeout to wait at most or otherwise [`None`] to wait forever
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn wait_for_input(conn_fd: BorrowedFd, wakeup: Option<&UnixStream>,
+                             maybe_timeout: Option<Duration>) -> Result<()>
+{
+    let mut fds = vec![PollFd::from_borrowed_fd(conn_fd, PollFlags::IN)];
+
+    if let Some(wakeup) = wakeup {
+        fds.push(PollFd::new(wakeup, PollFlags::IN));
+    }
+
+    let timeout = maybe_timeout.map(Timespec::try_from).transpose()?;
+
+    // Signals interrupt the call as well, the caller checks the shutdown flag anyway
+    match poll(&mut fds, timeout.as_ref()) {
+        Ok(_) | Err(Errno::INTR) => {},
+        Err(err) => return Err(err.into()),
+    }
+
+    // Drain the pipe to block again next time
+    if let Some(mut wakeup) = wakeup {
+        let mut buf = [0u8; 16];
+
+        loop {
+            match wakeup.read(&mut buf) {
+                Ok(0) => break,
+                Ok(_) => continue,
+                Err(err) if ErrorKind::WouldBlock == err.kind() => break,
+                Err(err) if ErrorKind::Interrupted == err.kind() => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    debug!("{}: timeout={:?}", function_name!(), maybe_timeout);
+
+    Ok(())
+}
+
 /// Run event loop and handle events
 ///
 /// # Arguments
@@ -880,7 +2257,7 @@ fn handle_selection_clear(subtle: &Subtle, event: SelectionClearEvent) -> Result
 /// # Returns
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-pub(crate) fn event_loop(subtle: &Subtle) -> Result<()> {
+pub(crate) fn event_loop(subtle: &mut Subtle) -> Result<()> {
     let conn = subtle.conn.get().context("Failed to get connection")?;
 
     // Update screen and panels
@@ -905,32 +2282,58 @@ pub(crate) fn event_loop(subtle: &Subtle) -> Result<()> {
     }
 
     while !subtle.shutdown.load(atomic::Ordering::SeqCst) {
+        // Re-detect screens after a layout change
+        if subtle.screens_changed.replace(false) {
+            screen::update(subtle)?;
+        }
+
+        // Toggle panels, this needs to resize the screens
+        if let Some((screen_idx, panels)) = subtle.panel_toggle.take() {
+            screen::toggle_panels(subtle, screen_idx, panels)?;
+        }
+
+        let subtle: &Subtle = subtle;
+        let conn = subtle.conn.get().context("Failed to get connection")?;
+
+        // Publish geometries of clients moved while handling the last event
+        if subtle.client_geoms_changed.replace(false) {
+            client::publish_geometries(subtle)?;
+        }
+
         conn.flush()?;
 
-        if let Ok(event) = conn.wait_for_event() {
-            match event {
-                Event::ButtonPress(evt) => handle_button_press(subtle, evt)?,
-                Event::ConfigureNotify(evt) => handle_configure_notify(subtle, evt)?,
-                Event::ConfigureRequest(evt) => handle_configure_request(subtle, evt)?,
-                Event::ClientMessage(evt) => handle_client_message(subtle, evt)?,
-                Event::DestroyNotify(evt) => handle_destroy_notify(subtle, evt)?,
-                Event::EnterNotify(evt) => handle_enter_notify(subtle, evt)?,
-                Event::LeaveNotify(evt) => handle_leave_notify(subtle, evt)?,
-                Event::Expose(evt) => handle_expose(subtle, evt)?,
-                Event::FocusIn(evt) => handle_focus_in(subtle, evt)?,
-                Event::KeyPress(evt) => handle_key_press(subtle, evt)?,
-                Event::MapNotify(evt) => handle_map_notify(subtle, evt)?,
-                Event::MappingNotify(evt) => handle_mapping_notify(subtle, evt)?,
-                Event::MapRequest(evt) => handle_map_request(subtle, evt)?,
-                Event::PropertyNotify(evt) => handle_property_notify(subtle, evt)?,
-                Event::SelectionClear(evt) => handle_selection_clear(subtle, evt)?,
-                Event::UnmapNotify(evt) => handle_unmap_notify(subtle, evt)?,
-
-                _ => {
-                    if subtle.flags.intersects(SubtleFlags::DEBUG) {
-                        warn!("Unhandled event: {:?}", event)
-                    }
-                },
+        // Wake up in time to revert a pending flash or count
+        let now = Instant::now();
+
+        let maybe_timeout = [flash::calc_timeout(&subtle.flash, now),
+            subtle.count_prefix.get().calc_timeout(now)].into_iter().flatten().min();
+
+        // Just block when nothing is queued already
+        let maybe_event = match conn.poll_for_event()? {
+            Some(event) => Some(event),
+            None => {
+                wait_for_input(conn.stream().as_fd(), subtle.wakeup.get(), maybe_timeout)?;
+
+                conn.poll_for_event()?
+            },
+        };
+
+        flash::revert(subtle)?;
+        grab::expire_count(subtle)?;
+
+        if let Some(event) = maybe_event {
+            // Record timestamp before acting on the event
+            if let Some(timestamp) = get_timestamp(&event) {
+                subtle.last_timestamp.set(timestamp);
+            }
+
+            // Keep running, a window may just have raced away
+            if let Err(err) = handle_event(subtle, event) {
+                if error::is_connection_lost(&err) {
+                    return Err(err);
+                }
+
+                warn!("Failed to handle event: {:#}", err);
             }
         }
     }
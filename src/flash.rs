@@ -0,0 +1,228 @@
+//!
+//! @package subtle-rs
+//!
+//! @file Flash functions
+//! @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+//! @version $Id$
+//!
+//! This program can be distributed under the terms of the GNU GPLv3.
+//! See the file LICENSE for details.
+//!
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+use anyhow::{Context, Result};
+use log::debug;
+use stdext::function_name;
+use x11rb::protocol::xproto::{ChangeGCAux, ChangeWindowAttributesAux, ConnectionExt, Rectangle, Window};
+use crate::client::ClientFlags;
+use crate::grab::GrabOutcome;
+use crate::panel;
+use crate::screen::ScreenFlags;
+use crate::subtle::Subtle;
+
+/// Duration of a flash
+pub(crate) const FLASH_DURATION: Duration = Duration::from_millis(150);
+
+/// Height of the flashed panel edge
+const FLASH_PANEL_EDGE: u16 = 2;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum FlashTarget {
+    /// Border of a client window
+    Client(Window),
+    /// Edge of a panel window
+    Panel(Window),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Flash {
+    /// Flashed window
+    pub(crate) target: FlashTarget,
+    /// Point in time to revert the flash
+    pub(crate) deadline: Instant,
+}
+
+/// Schedule a flash unless another one is still running
+///
+/// # Arguments
+///
+/// * `pending` - Currently pending flash
+/// * `target` - Window to flash
+/// * `now` - Current point in time
+///
+/// # Returns
+///
+/// Either [`true`] if the flash has been scheduled or otherwise [`false`]
+pub(crate) fn schedule(pending: &Cell<Option<Flash>>, target: FlashTarget, now: Instant) -> bool {
+    if pending.get().is_some() {
+        return false;
+    }
+
+    pending.set(Some(Flash {
+        target,
+        deadline: now + FLASH_DURATION,
+    }));
+
+    true
+}
+
+/// Take pending flash once it is expired
+///
+/// # Arguments
+///
+/// * `pending` - Currently pending flash
+/// * `now` - Current point in time
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] expired flash or otherwise [`None`]
+pub(crate) fn take_expired(pending: &Cell<Option<Flash>>, now: Instant) -> Option<Flash> {
+    match pending.get() {
+        Some(flash) if flash.deadline <= now => pending.take(),
+        _ => None,
+    }
+}
+
+/// Calculate time until the pending flash must be reverted
+///
+/// # Arguments
+///
+/// * `pending` - Currently pending flash
+/// * `now` - Current point in time
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] remaining time or otherwise [`None`]
+pub(crate) fn calc_timeout(pending: &Cell<Option<Flash>>, now: Instant) -> Option<Duration> {
+    pending.get().map(|flash| flash.deadline.saturating_duration_since(now))
+}
+
+/// Whether an outcome should be flashed
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `outcome` - Outcome of the grab handler
+///
+/// # Returns
+///
+/// Either [`true`] if flash is enabled and the grab had no effect or otherwise [`false`]
+pub(crate) fn should_flash(subtle: &Subtle, outcome: GrabOutcome) -> bool {
+    subtle.flash_on_noop && GrabOutcome::NoOp == outcome
+}
+
+/// Find target to flash
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] on success or otherwise [`None`]
+pub(crate) fn find_target(subtle: &Subtle) -> Option<FlashTarget> {
+    // Prefer focus client with a border and fall back to the panel of the current screen
+    if let Some(focus_client) = subtle.find_focus_client()
+        && !focus_client.flags.intersects(ClientFlags::TYPE_DESKTOP | ClientFlags::TYPE_DOCK)
+    {
+        Some(FlashTarget::Client(focus_client.win))
+    } else if let Some((_, screen)) = subtle.find_screen_by_pointer() {
+        if screen.flags.intersects(ScreenFlags::TOP_PANEL) {
+            Some(FlashTarget::Panel(screen.top_panel_win))
+        } else if screen.flags.intersects(ScreenFlags::BOTTOM_PANEL) {
+            Some(FlashTarget::Panel(screen.bottom_panel_win))
+        } else {
+            None
+        }
+    } else {
+        None
+    }
+}
+
+/// Flash focus client or panel edge to indicate a grab without effect
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `outcome` - Outcome of the grab handler
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn handle_outcome(subtle: &Subtle, outcome: GrabOutcome) -> Result<()> {
+    if !should_flash(subtle, outcome) {
+        return Ok(());
+    }
+
+    let conn = subtle.conn.get().context("Failed to get connection")?;
+
+    let Some(target) = find_target(subtle) else {
+        return Ok(());
+    };
+
+    if !schedule(&subtle.flash, target, Instant::now()) {
+        return Ok(());
+    }
+
+    match target {
+        FlashTarget::Client(win) => {
+            conn.change_window_attributes(win, &ChangeWindowAttributesAux::default()
+                .border_pixel(subtle.urgent_style.fg as u32))?.check()?;
+        },
+        FlashTarget::Panel(win) => {
+            let (_, screen) = subtle.find_screen_by_panel_win(win)
+                .context("Failed to find screen")?;
+
+            conn.change_gc(subtle.draw_gc, &ChangeGCAux::default()
+                .foreground(subtle.urgent_style.fg as u32))?.check()?;
+
+            conn.poly_fill_rectangle(win, subtle.draw_gc, &[Rectangle {
+                x: 0,
+                y: if screen.top_panel_win == win {
+                    (subtle.panel_height - FLASH_PANEL_EDGE.min(subtle.panel_height)) as i16
+                } else {
+                    0
+                },
+                width: screen.base.width,
+                height: FLASH_PANEL_EDGE,
+            }])?.check()?;
+        },
+    }
+
+    debug!("{}: target={:?}", function_name!(), target);
+
+    Ok(())
+}
+
+/// Revert pending flash once it is expired
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn revert(subtle: &Subtle) -> Result<()> {
+    let Some(flash) = take_expired(&subtle.flash, Instant::now()) else {
+        return Ok(());
+    };
+
+    match flash.target {
+        FlashTarget::Client(win) => {
+            // Skip clients that are gone in the meantime
+            if let Some(client) = subtle.find_client(win) {
+                let is_focus = subtle.focus_history.first()
+                    .is_some_and(|focus_win| focus_win == win);
+
+                client.update_border(subtle, is_focus)?;
+            }
+        },
+        FlashTarget::Panel(_) => panel::render(subtle)?,
+    }
+
+    debug!("{}: flash={:?}", function_name!(), flash);
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+//!
+//! @package subtle-rs
+//!
+//! @file Geometry functions
+//! @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+//! @version $Id$
+//!
+//! This program can be distributed under the terms of the GNU GPLv3.
+//! See the file LICENSE for details.
+//!
+
+use log::debug;
+use stdext::function_name;
+
+/// Narrow coordinate to protocol range and clamp on overflow
+///
+/// # Arguments
+///
+/// * `value` - Value to narrow
+///
+/// # Returns
+///
+/// Either the value or the nearest bound of [`i16`]
+pub(crate) fn checked_to_i16(value: i32) -> i16 {
+    i16::try_from(value).unwrap_or_else(|_| {
+        let clamped = value.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+
+        debug!("{}: value={}, clamped={}", function_name!(), value, clamped);
+
+        clamped
+    })
+}
+
+/// Narrow size to protocol range and clamp on overflow
+///
+/// # Arguments
+///
+/// * `value` - Value to narrow
+///
+/// # Returns
+///
+/// Either the value or the nearest bound of [`u16`]
+pub(crate) fn checked_to_u16(value: i32) -> u16 {
+    u16::try_from(value).unwrap_or_else(|_| {
+        let clamped = value.clamp(u16::MIN as i32, u16::MAX as i32) as u16;
+
+        debug!("{}: value={}, clamped={}", function_name!(), value, clamped);
+
+        clamped
+    })
+}
+
+/// Shrink extent without wrapping around
+///
+/// # Arguments
+///
+/// * `extent` - Extent to shrink
+/// * `by` - Amount to subtract, negative values grow the extent
+/// * `min` - Lower bound of the result
+///
+/// # Returns
+///
+/// The shrunk extent clamped between `min` and [`u16::MAX`]
+pub(crate) fn calc_shrunk(extent: u16, by: i32, min: u16) -> u16 {
+    checked_to_u16(extent as i32 - by).max(min)
+}
+
+/// Calculate origin to center inner extent inside of outer one
+///
+/// # Arguments
+///
+/// * `origin` - Origin of the outer extent
+/// * `outer` - Outer extent
+/// * `inner` - Inner extent
+///
+/// # Returns
+///
+/// The centered origin of the inner extent
+pub(crate) fn calc_centered(origin: i32, outer: i32, inner: i32) -> i16 {
+    checked_to_i16(origin + (outer - inner) / 2)
+}
+
+/// Calculate position in percent of an extent
+///
+/// # Arguments
+///
+/// * `origin` - Origin of the extent
+/// * `extent` - Length of the extent
+/// * `percent` - Percentage
+///
+/// # Returns
+///
+/// The position relative to the origin
+pub(crate) fn calc_percent(origin: i32, extent: i32, percent: i32) -> i32 {
+    origin + extent * percent / 100
+}
@@ -11,17 +11,80 @@
 
 use std::fmt;
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use bitflags::bitflags;
 use anyhow::{Context, Result, bail};
-use log::debug;
+use log::{debug, warn};
 use stdext::function_name;
+use strum_macros::FromRepr;
 use x11rb::connection::Connection;
 use x11rb::NONE;
 use x11rb::protocol::xproto::{ButtonIndex, ConnectionExt, EventMask, GrabMode, Keycode, Keysym, ModMask, Window};
 use crate::client;
 use crate::client::ClientFlags;
+use crate::screen::ScreenFlags;
 use crate::config::{Config, MixedConfigVal};
-use crate::subtle::{Subtle, SubtleFlags};
+use crate::gravity::Gravity;
+use crate::panel;
+use crate::error;
+use crate::error::Classify;
+use crate::subtle::Subtle;
+
+/// Time until a count prefix gets stale
+pub(crate) const COUNT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Upper bound of a count prefix
+const MAX_COUNT: u32 = 999;
+
+/// Keysym of the digit zero
+const XK_0: Keysym = 0x30;
+
+/// Number of shift levels of the core keyboard mapping with known modifiers
+const MAX_LEVELS: usize = 6;
+
+/// Multimedia keysyms the keysymdef lookup lacks (see XF86keysym.h)
+const XF86_KEYSYMS: [(&str, Keysym); 12] = [
+    ("XF86MonBrightnessUp", 0x1008_FF02),
+    ("XF86MonBrightnessDown", 0x1008_FF03),
+    ("XF86AudioLowerVolume", 0x1008_FF11),
+    ("XF86AudioMute", 0x1008_FF12),
+    ("XF86AudioRaiseVolume", 0x1008_FF13),
+    ("XF86AudioPlay", 0x1008_FF14),
+    ("XF86AudioStop", 0x1008_FF15),
+    ("XF86AudioPrev", 0x1008_FF16),
+    ("XF86AudioNext", 0x1008_FF17),
+    ("XF86AudioPause", 0x1008_FF31),
+    ("XF86AudioMicMute", 0x1008_FFB2),
+    ("XF86Calculator", 0x1008_FF1D),
+];
+
+/// Friendly aliases of keysym names
+const KEY_ALIASES: [(&str, &str); 21] = [
+    ("volume_up", "XF86AudioRaiseVolume"),
+    ("volume_down", "XF86AudioLowerVolume"),
+    ("mute", "XF86AudioMute"),
+    ("play", "XF86AudioPlay"),
+    ("next", "XF86AudioNext"),
+    ("prev", "XF86AudioPrev"),
+    ("brightness_up", "XF86MonBrightnessUp"),
+    ("brightness_down", "XF86MonBrightnessDown"),
+    ("kp_0", "KP_0"),
+    ("kp_1", "KP_1"),
+    ("kp_2", "KP_2"),
+    ("kp_3", "KP_3"),
+    ("kp_4", "KP_4"),
+    ("kp_5", "KP_5"),
+    ("kp_6", "KP_6"),
+    ("kp_7", "KP_7"),
+    ("kp_8", "KP_8"),
+    ("kp_9", "KP_9"),
+    ("kp_plus", "KP_Add"),
+    ("kp_minus", "KP_Subtract"),
+    ("kp_enter", "KP_Enter"),
+];
+
+/// Lookup table of keysyms to keycode and the modifiers of their shift level
+pub(crate) type KeyMap = HashMap<Keysym, (Keycode, ModMask)>;
 
 bitflags! {
     /// Config and state-flags for [`Grab`]
@@ -61,11 +124,31 @@ bitflags! {
         const WINDOW_GRAVITY = 1 << 15;
         /// Kill window
         const WINDOW_KILL = 1 << 16;
+        /// Cycle windows
+        const WINDOW_CYCLE = 1 << 17;
+        /// Cycle views
+        const VIEW_CYCLE = 1 << 18;
+        /// Add digit to count prefix
+        const COUNT_PREFIX = 1 << 19;
+        /// Adjust gaps between clients
+        const GAP_ADJUST = 1 << 20;
+        /// Cycle windows by most recent use while the modifiers are held
+        const WINDOW_SWITCH = 1 << 21;
+        /// Add tags of the current view to window or remove them
+        const WINDOW_PIN = 1 << 22;
+        /// Toggle panels of screen
+        const PANEL_TOGGLE = 1 << 23;
+        /// Dump state of window to the log
+        const WINDOW_DUMP = 1 << 24;
+
+        /// Mask of grabs that consume the count prefix as repeat factor
+        const COUNTED = Self::WINDOW_GRAVITY.bits() | Self::WINDOW_CYCLE.bits() | Self::VIEW_CYCLE.bits()
+            | Self::GAP_ADJUST.bits() | Self::WINDOW_SWITCH.bits();
     }
 }
 
 #[repr(u8)]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, FromRepr)]
 pub(crate) enum DirectionOrder {
     Mouse = 0,
     Up = 1,
@@ -74,6 +157,30 @@ pub(crate) enum DirectionOrder {
     Left = 4,
 }
 
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, FromRepr)]
+pub(crate) enum CycleOrder {
+    Next = 0,
+    Prev = 1,
+}
+
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, FromRepr)]
+pub(crate) enum GapOrder {
+    Increase = 0,
+    Decrease = 1,
+    Reset = 2,
+}
+
+/// Outcome of a grab handler
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum GrabOutcome {
+    /// Grab had an effect
+    Done,
+    /// Grab had no effect
+    NoOp,
+}
+
 #[derive(Default, Debug)]
 pub(crate) enum GrabAction {
     #[default]
@@ -83,6 +190,91 @@ pub(crate) enum GrabAction {
     Command(String),
 }
 
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub(crate) struct CountPrefix {
+    /// Accumulated count
+    pub(crate) count: u32,
+    /// Point in time of the last digit
+    pub(crate) updated_at: Option<Instant>,
+}
+
+impl CountPrefix {
+    /// Append digit to the count and start over when stale
+    ///
+    /// # Arguments
+    ///
+    /// * `digit` - Digit to append
+    /// * `now` - Current point in time
+    pub(crate) fn push_digit(&mut self, digit: u32, now: Instant) {
+        if self.is_stale(now) {
+            *self = Self::default();
+        }
+
+        self.count = (self.count * 10 + digit).min(MAX_COUNT);
+        self.updated_at = Some(now);
+    }
+
+    /// Whether the count has timed out
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - Current point in time
+    ///
+    /// # Returns
+    ///
+    /// Either [`true`] if the last digit is too old or otherwise [`false`]
+    pub(crate) fn is_stale(&self, now: Instant) -> bool {
+        self.updated_at.is_some_and(|updated_at| now.saturating_duration_since(updated_at) >= COUNT_TIMEOUT)
+    }
+
+    /// Get current count
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - Current point in time
+    ///
+    /// # Returns
+    ///
+    /// A [`Option`] with either [`Some`] count or otherwise [`None`] when unset or stale
+    pub(crate) fn get(&self, now: Instant) -> Option<u32> {
+        if self.updated_at.is_some() && !self.is_stale(now) && 0 < self.count {
+            Some(self.count)
+        } else {
+            None
+        }
+    }
+
+    /// Consume count as repeat factor and reset it
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - Current point in time
+    ///
+    /// # Returns
+    ///
+    /// The current count or 1 when unset
+    pub(crate) fn take(&mut self, now: Instant) -> u32 {
+        let count = self.get(now).unwrap_or(1);
+
+        *self = Self::default();
+
+        count
+    }
+
+    /// Calculate time until the count gets stale
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - Current point in time
+    ///
+    /// # Returns
+    ///
+    /// A [`Option`] with either [`Some`] remaining time or otherwise [`None`]
+    pub(crate) fn calc_timeout(&self, now: Instant) -> Option<Duration> {
+        self.updated_at.map(|updated_at| (updated_at + COUNT_TIMEOUT).saturating_duration_since(now))
+    }
+}
+
 #[derive(Default, Debug)]
 pub(crate) struct Grab {
     /// Config and state-flags
@@ -100,26 +292,21 @@ pub(crate) struct Grab {
 /// # Arguments
 ///
 /// * `keys` - Keys to parse
-/// * `keysyms_to_keycode` - Mapping table for keysyms to keycode
+/// * `keysyms_to_keycode` - Mapping table for keysyms to keycode and level modifiers
 ///
 /// # Returns
 ///
 /// A [`Result`] with either ([`Keycode`], [`ModMask`], [`bool`]) on success or otherwise [`anyhow::Error`]
-pub(crate) fn parse_keys(keys: &str, keysyms_to_keycode: &HashMap<Keysym, Keycode>) -> Result<(Keycode, ModMask, bool)> {
+pub(crate) fn parse_keys(keys: &str, keysyms_to_keycode: &KeyMap) -> Result<(Keycode, ModMask, bool)> {
     let mut keycode: Keycode = 0;
     let mut modifiers = ModMask::default();
     let mut is_mouse = false;
 
     for key in keys.split("-") {
-        match key {
+        match parse_modifier(key) {
             // Handle modifier keys
-            "S" => modifiers |= ModMask::SHIFT,
-            "C" => modifiers |= ModMask::CONTROL,
-            "A" => modifiers |= ModMask::M1,
-            "M" => modifiers |= ModMask::M3,
-            "W" => modifiers |= ModMask::M4,
-            "G" => modifiers |= ModMask::M5,
-            _ => {
+            Some(modifier) => modifiers |= modifier,
+            None => {
                 // Handle mouse buttons
                 if 2 == key.len() && key.starts_with("B") {
                     keycode = Keycode::from(ButtonIndex::try_from(
@@ -128,10 +315,15 @@ pub(crate) fn parse_keys(keys: &str, keysyms_to_keycode: &HashMap<Keysym, Keycod
                     is_mouse = true;
                 // Handle other keys
                 } else {
-                    let record = x11_keysymdef::lookup_by_name(key)
+                    let keysym = lookup_keysym(key)
                         .context(format!("Key name not found: {}", key))?;
 
-                    keycode = *keysyms_to_keycode.get(&record.keysym).context("Keysym not found")?;
+                    let (level_keycode, level_modifiers) = *keysyms_to_keycode.get(&keysym)
+                        .context(format!("Keysym not found in keyboard mapping: {}", key))?;
+
+                    // Add modifiers of the shift level the keysym lives on
+                    keycode = level_keycode;
+                    modifiers |= level_modifiers;
                 }
             }
         }
@@ -140,6 +332,146 @@ pub(crate) fn parse_keys(keys: &str, keysyms_to_keycode: &HashMap<Keysym, Keycod
     Ok((keycode, modifiers, is_mouse))
 }
 
+/// Look up keysym of key name, aliases and multimedia keys
+///
+/// # Arguments
+///
+/// * `key` - Key name or alias
+///
+/// # Returns
+///
+/// Either [`Some`] with the [`Keysym`] or otherwise [`None`]
+pub(crate) fn lookup_keysym(key: &str) -> Option<Keysym> {
+    let name = KEY_ALIASES.iter()
+        .find(|(alias, _)| *alias == key)
+        .map_or(key, |(_, name)| *name);
+
+    XF86_KEYSYMS.iter()
+        .find(|(xf86_name, _)| *xf86_name == name)
+        .map(|(_, keysym)| *keysym)
+        .or_else(|| x11_keysymdef::lookup_by_name(name).map(|record| record.keysym))
+}
+
+/// Parse modifier key
+///
+/// # Arguments
+///
+/// * `key` - Key to parse
+///
+/// # Returns
+///
+/// Either [`Some`] with the [`ModMask`] of the modifier or otherwise [`None`]
+pub(crate) fn parse_modifier(key: &str) -> Option<ModMask> {
+    match key {
+        "S" => Some(ModMask::SHIFT),
+        "C" => Some(ModMask::CONTROL),
+        "A" => Some(ModMask::M1),
+        "M" => Some(ModMask::M3),
+        "W" => Some(ModMask::M4),
+        "G" => Some(ModMask::M5),
+        _ => None,
+    }
+}
+
+/// Action of a built-in grab
+#[derive(Clone, Copy)]
+enum BuiltinAction {
+    /// Grab without action
+    None,
+    /// Grab with fixed index
+    Index(u32),
+    /// Grab with index taken from the suffix of its name
+    Suffix,
+}
+
+/// Built-in grabs, also used to catch typos that would otherwise become commands
+const BUILTIN_GRABS: [(&str, GrabFlags, BuiltinAction); 39] = [
+    ("subtle_reload", GrabFlags::SUBTLE_RELOAD, BuiltinAction::None),
+    ("subtle_restart", GrabFlags::SUBTLE_RESTART, BuiltinAction::None),
+    ("subtle_quit", GrabFlags::SUBTLE_QUIT, BuiltinAction::None),
+
+    ("window_toggle", GrabFlags::WINDOW_MODE, BuiltinAction::None),
+    ("window_stack", GrabFlags::WINDOW_RESTACK, BuiltinAction::None),
+    ("window_select", GrabFlags::WINDOW_SELECT, BuiltinAction::None),
+    ("window_gravity", GrabFlags::WINDOW_GRAVITY, BuiltinAction::None),
+    ("window_kill", GrabFlags::WINDOW_KILL, BuiltinAction::None),
+    ("window_dump", GrabFlags::WINDOW_DUMP, BuiltinAction::None),
+
+    // Window and view cycling
+    ("window_next", GrabFlags::WINDOW_CYCLE, BuiltinAction::Index(CycleOrder::Next as u32)),
+    ("window_prev", GrabFlags::WINDOW_CYCLE, BuiltinAction::Index(CycleOrder::Prev as u32)),
+    ("view_next", GrabFlags::VIEW_CYCLE, BuiltinAction::Index(CycleOrder::Next as u32)),
+    ("view_prev", GrabFlags::VIEW_CYCLE, BuiltinAction::Index(CycleOrder::Prev as u32)),
+    ("window_cycle", GrabFlags::WINDOW_SWITCH, BuiltinAction::Index(CycleOrder::Next as u32)),
+    ("window_cycle_reverse", GrabFlags::WINDOW_SWITCH, BuiltinAction::Index(CycleOrder::Prev as u32)),
+
+    // Window modes
+    ("window_float", GrabFlags::WINDOW_MODE, BuiltinAction::Index(ClientFlags::MODE_FLOAT.bits())),
+    ("window_full", GrabFlags::WINDOW_MODE, BuiltinAction::Index(ClientFlags::MODE_FULL.bits())),
+    ("window_stick", GrabFlags::WINDOW_MODE, BuiltinAction::Index(ClientFlags::MODE_STICK.bits())),
+    ("window_zaphod", GrabFlags::WINDOW_MODE, BuiltinAction::Index(ClientFlags::MODE_ZAPHOD.bits())),
+    ("window_ignore_hints", GrabFlags::WINDOW_MODE, BuiltinAction::Index(ClientFlags::MODE_IGNORE_HINTS.bits())),
+
+    // Window restack
+    ("window_raise", GrabFlags::WINDOW_RESTACK, BuiltinAction::Index(client::RestackOrder::Up as u32)),
+    ("window_lower", GrabFlags::WINDOW_RESTACK, BuiltinAction::Index(client::RestackOrder::Down as u32)),
+
+    // Window pin
+    ("window_pin", GrabFlags::WINDOW_PIN, BuiltinAction::Index(1)),
+    ("window_unpin", GrabFlags::WINDOW_PIN, BuiltinAction::Index(0)),
+
+    // Window select
+    ("window_left", GrabFlags::WINDOW_SELECT, BuiltinAction::Index(DirectionOrder::Left as u32)),
+    ("window_down", GrabFlags::WINDOW_SELECT, BuiltinAction::Index(DirectionOrder::Down as u32)),
+    ("window_right", GrabFlags::WINDOW_SELECT, BuiltinAction::Index(DirectionOrder::Right as u32)),
+    ("window_up", GrabFlags::WINDOW_SELECT, BuiltinAction::Index(DirectionOrder::Up as u32)),
+
+    // Panels
+    ("panel_toggle", GrabFlags::PANEL_TOGGLE,
+     BuiltinAction::Index(ScreenFlags::TOP_PANEL.bits() | ScreenFlags::BOTTOM_PANEL.bits())),
+    ("panel_toggle_top", GrabFlags::PANEL_TOGGLE, BuiltinAction::Index(ScreenFlags::TOP_PANEL.bits())),
+    ("panel_toggle_bottom", GrabFlags::PANEL_TOGGLE, BuiltinAction::Index(ScreenFlags::BOTTOM_PANEL.bits())),
+
+    // Gaps
+    ("gap_increase", GrabFlags::GAP_ADJUST, BuiltinAction::Index(GapOrder::Increase as u32)),
+    ("gap_decrease", GrabFlags::GAP_ADJUST, BuiltinAction::Index(GapOrder::Decrease as u32)),
+    ("gap_reset", GrabFlags::GAP_ADJUST, BuiltinAction::Index(GapOrder::Reset as u32)),
+
+    // Window dragging
+    ("window_move", GrabFlags::WINDOW_MOVE, BuiltinAction::None),
+    ("window_resize", GrabFlags::WINDOW_RESIZE, BuiltinAction::None),
+
+    // Grabs with index
+    ("view_jump", GrabFlags::VIEW_JUMP, BuiltinAction::Suffix),
+    ("view_switch", GrabFlags::VIEW_SWITCH, BuiltinAction::Suffix),
+    ("screen_jump", GrabFlags::SCREEN_JUMP, BuiltinAction::Suffix),
+];
+
+/// Max edit distance of a command grab name to a built-in one to count as typo
+const GRAB_NAME_MAX_DISTANCE: usize = 2;
+
+/// Find built-in grab name a command grab name is most likely a typo of
+///
+/// # Arguments
+///
+/// * `name` - Name of the grab
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] built-in name or otherwise [`None`]
+pub(crate) fn find_similar_name(name: &str) -> Option<&'static str> {
+    // Commands usually come with arguments
+    if name.contains(char::is_whitespace) || BUILTIN_GRABS.iter().any(|(known_name, _, _)| *known_name == name) {
+        return None;
+    }
+
+    BUILTIN_GRABS.iter()
+        .map(|(known_name, _, _)| (strsim::damerau_levenshtein(name, known_name), *known_name))
+        .filter(|(distance, _)| (1..=GRAB_NAME_MAX_DISTANCE).contains(distance))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, known_name)| known_name)
+}
+
 /// Parse names of grabs
 ///
 /// # Arguments
@@ -150,54 +482,139 @@ pub(crate) fn parse_keys(keys: &str, keysyms_to_keycode: &HashMap<Keysym, Keycod
 ///
 /// A [`Result`] with either ([`GrabFlags`], [`GrabAction`]) on success or otherwise [`anyhow::Error`]
 pub(crate) fn parse_name(name: &str) -> Result<(GrabFlags, GrabAction)> {
-    Ok(match name {
-        "subtle_reload" => (GrabFlags::SUBTLE_RELOAD, GrabAction::None),
-        "subtle_restart" => (GrabFlags::SUBTLE_RESTART, GrabAction::None),
-        "subtle_quit" => (GrabFlags::SUBTLE_QUIT, GrabAction::None),
-
-        "window_toggle" => (GrabFlags::WINDOW_MODE, GrabAction::None),
-        "window_stack" => (GrabFlags::WINDOW_RESTACK, GrabAction::None),
-        "window_select" => (GrabFlags::WINDOW_SELECT, GrabAction::None),
-        "window_gravity" => (GrabFlags::WINDOW_GRAVITY, GrabAction::None),
-        "window_kill" => (GrabFlags::WINDOW_KILL, GrabAction::None),
-
-        // Window modes
-        "window_float" => (GrabFlags::WINDOW_MODE, GrabAction::Index(ClientFlags::MODE_FLOAT.bits())),
-        "window_full" => (GrabFlags::WINDOW_MODE, GrabAction::Index(ClientFlags::MODE_FULL.bits())),
-        "window_stick" => (GrabFlags::WINDOW_MODE, GrabAction::Index(ClientFlags::MODE_STICK.bits())),
-        "window_zaphod" => (GrabFlags::WINDOW_MODE, GrabAction::Index(ClientFlags::MODE_ZAPHOD.bits())),
-
-        // Window restack
-        "window_raise" => (GrabFlags::WINDOW_RESTACK,
-                           GrabAction::Index(client::RestackOrder::Up as u32)),
-        "window_lower" => (GrabFlags::WINDOW_RESTACK,
-                           GrabAction::Index(client::RestackOrder::Down as u32)),
-
-        // Window select
-        "window_left" => (GrabFlags::WINDOW_SELECT, GrabAction::Index(DirectionOrder::Left as u32)),
-        "window_down" => (GrabFlags::WINDOW_SELECT, GrabAction::Index(DirectionOrder::Down as u32)),
-        "window_right" => (GrabFlags::WINDOW_SELECT, GrabAction::Index(DirectionOrder::Right as u32)),
-        "window_up" => (GrabFlags::WINDOW_SELECT, GrabAction::Index(DirectionOrder::Up as u32)),
-
-        // Window dragging
-        "window_move" => (GrabFlags::WINDOW_MOVE, GrabAction::None),
-        "window_resize" => (GrabFlags::WINDOW_RESIZE, GrabAction::None),
-
-        _ => {
-            // Handle grabs with index
-            if let Some(stripped) = name.strip_prefix("view_jump") {
-                (GrabFlags::VIEW_JUMP, GrabAction::Index(stripped.parse()?))
-            } else if let Some(stripped) = name.strip_prefix("view_switch") {
-                (GrabFlags::VIEW_SWITCH, GrabAction::Index(stripped.parse()?))
-            } else if let Some(stripped) =name.strip_prefix("screen_jump") {
-                (GrabFlags::SCREEN_JUMP, GrabAction::Index(stripped.parse()?))
-            } else {
-                (GrabFlags::COMMAND, GrabAction::Command(name.to_string()))
-            }
+    for (known_name, flags, action) in BUILTIN_GRABS.iter() {
+        match action {
+            BuiltinAction::None if *known_name == name => return Ok((*flags, GrabAction::None)),
+            BuiltinAction::Index(idx) if *known_name == name => return Ok((*flags, GrabAction::Index(*idx))),
+            BuiltinAction::Suffix => {
+                // Handle grabs with index
+                let Some(stripped) = name.strip_prefix(known_name) else {
+                    continue;
+                };
+
+                // Relative jumps cycle by the given number of views
+                if GrabFlags::VIEW_JUMP == *flags && let Some((order, steps)) = parse_relative_jump(stripped)? {
+                    return Ok((GrabFlags::VIEW_CYCLE, GrabAction::List(vec![order as usize, steps as usize])));
+                }
+
+                return Ok((*flags, GrabAction::Index(stripped.parse()?)));
+            },
+            _ => {},
         }
+    }
+
+    if let Some(known_name) = find_similar_name(name) {
+        bail!("Unknown grab name `{}`, did you mean `{}`?", name, known_name)
+    }
+
+    Ok((GrabFlags::COMMAND, GrabAction::Command(name.to_string())))
+}
+
+/// Parse suffix of a relative jump like `+2` or `-1`
+///
+/// # Arguments
+///
+/// * `suffix` - Suffix of the grab name
+///
+/// # Returns
+///
+/// A [`Result`] with either [`Some`] order and number of steps, [`None`] for absolute jumps
+/// or otherwise [`anyhow::Error`]
+pub(crate) fn parse_relative_jump(suffix: &str) -> Result<Option<(CycleOrder, u32)>> {
+    let (order, steps) = if let Some(steps) = suffix.strip_prefix('+') {
+        (CycleOrder::Next, steps)
+    } else if let Some(steps) = suffix.strip_prefix('-') {
+        (CycleOrder::Prev, steps)
+    } else {
+        return Ok(None);
+    };
+
+    match steps.parse()? {
+        0 => bail!("Invalid relative jump `{}`, expected at least one step", suffix),
+        steps => Ok(Some((order, steps))),
+    }
+}
+
+/// Calculate position after cycling with wrap-around
+///
+/// # Arguments
+///
+/// * `len` - Length of the cycled list
+/// * `current_idx` - Current position if any
+/// * `count` - Number of steps
+/// * `order` - Direction to cycle
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] new position or otherwise [`None`] when the list is empty
+pub(crate) fn calc_cycle_idx(len: usize, current_idx: Option<usize>, count: u32, order: CycleOrder) -> Option<usize> {
+    if 0 == len {
+        return None;
+    }
+
+    let steps = count as usize % len;
+
+    Some(match (current_idx, order) {
+        (Some(idx), CycleOrder::Next) => (idx + steps) % len,
+        (Some(idx), CycleOrder::Prev) => (idx + len - steps) % len,
+
+        // Start from the first or last entry respectively
+        (None, CycleOrder::Next) => (count.max(1) as usize - 1) % len,
+        (None, CycleOrder::Prev) => len - 1 - (count.max(1) as usize - 1) % len,
     })
 }
 
+/// Find keycodes of the given modifiers in the modifier mapping
+///
+/// # Arguments
+///
+/// * `keycodes` - Keycodes of the modifier mapping
+/// * `keycodes_per_modifier` - Number of keycodes per modifier
+/// * `modifiers` - Modifiers to find
+///
+/// # Returns
+///
+/// A [`Vec`] with the keycodes of the modifiers
+pub(crate) fn find_modifier_keycodes(keycodes: &[Keycode], keycodes_per_modifier: u8,
+                                     modifiers: ModMask) -> Vec<Keycode>
+{
+    if 0 == keycodes_per_modifier {
+        return Vec::new();
+    }
+
+    // Modifier mapping is ordered like the mask bits (Shift, Lock, Control, Mod1-Mod5)
+    keycodes.chunks(keycodes_per_modifier as usize)
+        .enumerate()
+        .filter(|(mod_idx, _)| *mod_idx < 8 && 0 != u16::from(modifiers) & (1 << mod_idx))
+        .flat_map(|(_, chunk)| chunk.iter().copied())
+        .filter(|keycode| 0 != *keycode)
+        .collect()
+}
+
+/// Reset stale count prefix and refresh the panels
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn expire_count(subtle: &Subtle) -> Result<()> {
+    let count_prefix = subtle.count_prefix.get();
+
+    if count_prefix.is_stale(Instant::now()) {
+        subtle.count_prefix.take();
+
+        panel::update(subtle)?;
+        panel::render(subtle)?;
+
+        debug!("{}: count={}", function_name!(), count_prefix.count);
+    }
+
+    Ok(())
+}
+
 impl Grab {
     /// Create a new instance
     ///
@@ -210,7 +627,7 @@ impl Grab {
     /// # Returns
     ///
     /// A [`Result`] with either [`Grab`] on success or otherwise [`anyhow::Error`]
-    pub(crate) fn new(name: &str, keys: &str, keysyms_to_keycode: &HashMap<Keysym, Keycode>) -> Result<Self> {
+    pub(crate) fn new(name: &str, keys: &str, keysyms_to_keycode: &KeyMap) -> Result<Self> {
 
         // Parse name and keys
         let (flags, action) = parse_name(name)?;
@@ -236,6 +653,64 @@ impl fmt::Display for Grab {
     }
 }
 
+/// Get additional modifiers of a shift level in the core keyboard mapping
+///
+/// # Arguments
+///
+/// * `level` - Index of the keysym of a keycode
+///
+/// # Returns
+///
+/// A [`ModMask`] with Shift and/or AltGr (Mode_switch) modifiers
+fn get_level_modifiers(level: usize) -> ModMask {
+    match level {
+        1 => ModMask::SHIFT,
+        2 | 4 => ModMask::M5,
+        3 | 5 => ModMask::M5 | ModMask::SHIFT,
+        _ => ModMask::default(),
+    }
+}
+
+/// Build a reverse map of keysyms to keycode and level modifiers from a keyboard mapping
+///
+/// # Arguments
+///
+/// * `min_keycode` - Keycode of the first chunk
+/// * `keysyms_per_keycode` - Number of keysyms per keycode
+/// * `keysyms` - Keysyms of all keycodes
+///
+/// # Returns
+///
+/// A [`KeyMap`] with the lowest shift level of every keysym
+pub(crate) fn calc_reverse_keymap(min_keycode: Keycode, keysyms_per_keycode: u8, keysyms: &[Keysym]) -> KeyMap {
+    let mut keysyms_to_keycode: KeyMap = HashMap::new();
+    let mut levels: HashMap<Keysym, usize> = HashMap::new();
+
+    if 0 == keysyms_per_keycode {
+        return keysyms_to_keycode;
+    }
+
+    for (idx, chunk) in keysyms.chunks(keysyms_per_keycode as usize).enumerate() {
+        let keycode = min_keycode.saturating_add(idx as u8);
+
+        if 0 == keycode {
+            continue;
+        }
+
+        for (level, &keysym) in chunk.iter().enumerate().take(MAX_LEVELS) {
+            // Skip NoSymbol and keep the lowest level of symbols on multiple keycodes
+            if 0 == keysym || levels.get(&keysym).is_some_and(|known| *known < level) {
+                continue;
+            }
+
+            levels.insert(keysym, level);
+            keysyms_to_keycode.insert(keysym, (keycode, get_level_modifiers(level)));
+        }
+    }
+
+    keysyms_to_keycode
+}
+
 /// Build a reverse map of keysyms to keycode to ease lookups
 ///
 /// # Arguments
@@ -244,30 +719,164 @@ impl fmt::Display for Grab {
 ///
 /// # Returns
 ///
-/// A [`Result`] with either [`HashMap<Keysym, Keycode>`] on success or otherwise [`anyhow::Error`]
-fn build_reverse_keymap(subtle: &Subtle) -> Result<HashMap<Keysym, Keycode>> {
+/// A [`Result`] with either [`KeyMap`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn build_reverse_keymap(subtle: &Subtle) -> Result<KeyMap> {
     let conn = subtle.conn.get().context("Failed to get connection")?;
 
     // Get keyboard mapping
     let mapping = conn.get_keyboard_mapping(conn.setup().min_keycode,
         conn.setup().max_keycode - conn.setup().min_keycode + 1)?.reply()?;
 
-    // Build reverse map of keysyms to keycode
-    let mut keysyms_to_keycode = HashMap::new();
+    let keysyms_to_keycode = calc_reverse_keymap(conn.setup().min_keycode,
+                                                 mapping.keysyms_per_keycode, &mapping.keysyms);
 
-    for (idx, chunk) in mapping.keysyms
-        .chunks(mapping.keysyms_per_keycode as usize)
-        .enumerate()
-    {
-        let keycode = conn.setup().min_keycode + idx as u8;
+    debug!("{}: nkeysyms={}", function_name!(), keysyms_to_keycode.len());
+
+    Ok(keysyms_to_keycode)
+}
+
+/// Parse gravity names of a grab name like `window_gravity top,top66,top33`
+///
+/// # Arguments
+///
+/// * `name` - Name of the grab
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] list of gravity names or otherwise [`None`]
+pub(crate) fn parse_gravity_list(name: &str) -> Option<Vec<String>> {
+    name.strip_prefix("window_gravity ").map(|list| list.split(',')
+        .map(|grav_name| grav_name.trim())
+        .filter(|grav_name| !grav_name.is_empty())
+        .map(String::from)
+        .collect())
+}
 
-        // Just copy the first sym without modifiers
-        if let Some(&keysym) = chunk.first() && 0 != keycode {
-            keysyms_to_keycode.insert(keysym, keycode);
+/// Resolve gravity names to gravity ids and drop unknown ones
+///
+/// # Arguments
+///
+/// * `grav_names` - Ordered list of gravity names
+/// * `gravities` - Known gravities
+///
+/// # Returns
+///
+/// A [`Vec`] with the gravity ids in order of the names
+pub(crate) fn resolve_gravity_names(grav_names: &[String], gravities: &[Gravity]) -> Vec<usize> {
+    grav_names.iter()
+        .filter_map(|grav_name| {
+            let grav_id = gravities.iter().position(|grav| grav.name.eq(grav_name));
+
+            if grav_id.is_none() {
+                warn!("Unknown gravity `{}`", grav_name);
+            }
+
+            grav_id
+        })
+        .collect()
+}
+
+/// Create gravity grab cycling through the given gravities
+///
+/// # Arguments
+///
+/// * `keys` - Keys as String (A-F5)
+/// * `grav_names` - Ordered list of gravity names
+/// * `gravities` - Known gravities
+/// * `keysyms_to_keycode` - Lookup table to map keysyms to keycodes
+///
+/// # Returns
+///
+/// A [`Result`] with either [`Grab`] on success or otherwise [`anyhow::Error`]
+fn new_gravity_grab(keys: &str, grav_names: &[String], gravities: &[Gravity],
+                    keysyms_to_keycode: &KeyMap) -> Result<Grab>
+{
+    let gravity_ids = resolve_gravity_names(grav_names, gravities);
+
+    if gravity_ids.is_empty() {
+        bail!("No known gravities");
+    }
+
+    let mut grab = Grab::new("window_gravity", keys, keysyms_to_keycode)?;
+
+    grab.action = GrabAction::List(gravity_ids);
+
+    Ok(grab)
+}
+
+/// Parse all grabs of the config and collect the errors of invalid ones
+///
+/// # Arguments
+///
+/// * `grabs` - Grab config values
+/// * `gravities` - Known gravities
+/// * `keysyms_to_keycode` - Lookup table to map keysyms to keycodes
+///
+/// # Returns
+///
+/// A tuple of grabs with their name and keys in name order and the parse errors
+pub(crate) fn parse_grabs(grabs: &HashMap<String, MixedConfigVal>, gravities: &[Gravity],
+                          keysyms_to_keycode: &KeyMap) -> (Vec<(String, String, Grab)>, Vec<anyhow::Error>)
+{
+    let mut parsed = Vec::new();
+    let mut errors = Vec::new();
+
+    // Sort by name, so shadowing doesn't depend on the hash order
+    let mut entries: Vec<(&String, &MixedConfigVal)> = grabs.iter().collect();
+
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (grab_name, value) in entries {
+        let items: Vec<(&String, Result<Grab>)> = match value {
+            MixedConfigVal::S(grab_keys) => {
+                // Handle gravity lists like `window_gravity top,top66,top33`
+                vec![(grab_keys, match parse_gravity_list(grab_name) {
+                    Some(grav_names) => new_gravity_grab(grab_keys, &grav_names, gravities, keysyms_to_keycode),
+                    None => Grab::new(grab_name, grab_keys, keysyms_to_keycode),
+                })]
+            },
+            MixedConfigVal::MVS(items) => {
+                let mut items: Vec<(&String, &Vec<String>)> = items.iter().collect();
+
+                items.sort_by(|a, b| a.0.cmp(b.0));
+
+                items.into_iter()
+                    .map(|(grab_keys, grav_names)| (grab_keys,
+                        new_gravity_grab(grab_keys, grav_names, gravities, keysyms_to_keycode)))
+                    .collect()
+            },
+            _ => Vec::new(),
+        };
+
+        for (grab_keys, res) in items {
+            match res {
+                Ok(grab) => parsed.push((grab_name.clone(), grab_keys.clone(), grab)),
+                Err(err) => errors.push(err.context(format!("Invalid grab `{}` ({})", grab_name, grab_keys))),
+            }
         }
     }
 
-    Ok(keysyms_to_keycode)
+    (parsed, errors)
+}
+
+/// Find grabs that are shadowed by an earlier grab with the same keys
+///
+/// # Arguments
+///
+/// * `grabs` - Grabs in bind order
+///
+/// # Returns
+///
+/// A [`Vec`] of pairs of the shadowed grab index and the index of the grab shadowing it
+pub(crate) fn calc_shadowed_grabs(grabs: &[&Grab]) -> Vec<(usize, usize)> {
+    let input_flags = GrabFlags::IS_KEY | GrabFlags::IS_MOUSE;
+
+    grabs.iter().enumerate()
+        .filter_map(|(idx, grab)| grabs[..idx].iter()
+            .position(|other| other.keycode == grab.keycode && other.modifiers == grab.modifiers
+                && (other.flags & input_flags) == (grab.flags & input_flags))
+            .map(|other_idx| (idx, other_idx)))
+        .collect()
 }
 
 /// Check config and init all gravity related options
@@ -281,41 +890,38 @@ fn build_reverse_keymap(subtle: &Subtle) -> Result<HashMap<Keysym, Keycode>> {
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
 pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
-    let keysyms_to_keycode = build_reverse_keymap(subtle)?;
+    let keysyms_to_keycode = build_reverse_keymap(subtle).fatal()?;
 
     // Parse grabs
-    for (grab_name, value) in config.grabs.iter() {
-        match value {
-            MixedConfigVal::S(grab_keys) => {
-                if let Ok(grab) = Grab::new(grab_name, grab_keys, &keysyms_to_keycode) {
-                    subtle.grabs.push(grab);
-                }
-            }
-            MixedConfigVal::MVS(items) => {
-                for (grab_keys, gravities) in items.iter() {
-                    if let Ok(mut grab) = Grab::new("window_gravity", grab_keys, &keysyms_to_keycode) {
-                        let mut gravity_ids = Vec::with_capacity(gravities.len());
+    let (parsed, errors) = parse_grabs(&config.grabs, &subtle.gravities, &keysyms_to_keycode);
 
-                        for grav_name in gravities {
-                            if let Some(grav_id) = subtle.gravities.iter()
-                                .position(|grav| grav.name.eq(grav_name))
-                            {
-                                gravity_ids.push(grav_id);
-                            }
-                        }
+    for err in errors {
+        error::collect(subtle, Err::<(), _>(err).recoverable())?;
+    }
 
-                        grab.action = GrabAction::List(gravity_ids);
+    if parsed.is_empty() {
+        bail!("No grabs found");
+    }
 
-                        subtle.grabs.push(grab);
-                    }
-                }
-            }
-            _ => {}
-        }
+    for (shadowed_idx, idx) in calc_shadowed_grabs(&parsed.iter().map(|(_, _, grab)| grab).collect::<Vec<_>>()) {
+        warn!("Grab `{}` ({}) is shadowed by grab `{}` ({})",
+            parsed[shadowed_idx].0, parsed[shadowed_idx].1, parsed[idx].0, parsed[idx].1);
     }
 
-    if subtle.gravities.is_empty() {
-        bail!("No grabs found");
+    subtle.grabs.extend(parsed.into_iter().map(|(_, _, grab)| grab));
+
+    // Add digit grabs for count prefixes
+    if 0 != u16::from(subtle.count_modifier) {
+        for digit in 0..10 {
+            if let Some((keycode, level_modifiers)) = keysyms_to_keycode.get(&(XK_0 + digit)) {
+                subtle.grabs.push(Grab {
+                    flags: GrabFlags::IS_KEY | GrabFlags::COUNT_PREFIX,
+                    keycode: *keycode,
+                    modifiers: subtle.count_modifier | *level_modifiers,
+                    action: GrabAction::Index(digit),
+                });
+            }
+        }
     }
 
     debug!("{}", function_name!());
@@ -340,7 +946,7 @@ pub(crate) fn set(subtle: &Subtle, win: Window, grab_mask: GrabFlags) -> Result<
     let default_screen = &conn.setup().roots[subtle.screen_num];
 
     // Unbind click-to-focus grab
-    if subtle.flags.intersects(SubtleFlags::CLICK_TO_FOCUS) && default_screen.root != win {
+    if subtle.focus_model.focus_on_click() && default_screen.root != win {
         conn.ungrab_button(ButtonIndex::ANY, win, ModMask::ANY)?.check()?;
     }
 
@@ -365,6 +971,19 @@ pub(crate) fn set(subtle: &Subtle, win: Window, grab_mask: GrabFlags) -> Result<
                                      GrabMode::ASYNC, GrabMode::ASYNC, NONE, NONE,
                                      ButtonIndex::from(grab.keycode),
                                      grab.modifiers | *mod_state)?.check()?;
+
+                    // Also grab move with transients
+                    if grab.flags.intersects(GrabFlags::WINDOW_MOVE)
+                        && 0 != u16::from(subtle.transients_drag_modifier)
+                        && !grab.modifiers.contains(subtle.transients_drag_modifier)
+                    {
+                        conn.grab_button(false, win,
+                                         EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE,
+                                         GrabMode::ASYNC, GrabMode::ASYNC, NONE, NONE,
+                                         ButtonIndex::from(grab.keycode),
+                                         grab.modifiers | subtle.transients_drag_modifier
+                                             | *mod_state)?.check()?;
+                    }
                 }
             }
         }
@@ -394,11 +1013,11 @@ pub(crate) fn unset(subtle: &Subtle, win: Window) -> Result<()> {
     conn.ungrab_key(Keycode::from(0), win, ModMask::ANY)?.check()?;
     conn.ungrab_button(ButtonIndex::ANY, win, ModMask::ANY)?.check()?;
 
-    // Bind click-to-focus grab
-    if subtle.flags.intersects(SubtleFlags::CLICK_TO_FOCUS) && default_screen.root != win {
+    // Bind click-to-focus grab, pointer is frozen until the click is replayed
+    if subtle.focus_model.focus_on_click() && default_screen.root != win {
         conn.grab_button(false, win,
                          EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE,
-                         GrabMode::ASYNC, GrabMode::ASYNC, NONE, NONE,
+                         GrabMode::SYNC, GrabMode::ASYNC, NONE, NONE,
                          ButtonIndex::ANY, ModMask::ANY)?.check()?;
     }
 
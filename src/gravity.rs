@@ -9,17 +9,20 @@
 //! See the file LICENSE for details.
 //!
 
+use std::collections::HashMap;
 use std::fmt;
 use bitflags::bitflags;
 use easy_min_max::{min, max, clamp};
 use anyhow::{anyhow, Result};
-use log::debug;
+use log::{debug, warn};
 use stdext::function_name;
 use x11rb::connection::Connection;
 use x11rb::protocol::xproto::{AtomEnum, PropMode, Rectangle};
 use x11rb::wrapper::ConnectionExt;
 use crate::Config;
+use crate::config;
 use crate::config::MixedConfigVal;
+use crate::error::InitError;
 use crate::subtle::Subtle;
 
 bitflags! {
@@ -39,6 +42,10 @@ pub(crate) struct Gravity {
     pub(crate) flags: GravityFlags,
     pub(crate) name: String,
     pub geom: Rectangle,
+    /// Max number of tiled clients per screen and view; 0 is unlimited
+    pub(crate) max_clients: usize,
+    /// Gravity to use once this gravity is full
+    pub(crate) overflow_idx: Option<usize>,
 }
 
 impl Gravity {
@@ -88,11 +95,152 @@ impl Gravity {
 
 impl fmt::Display for Gravity {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "(name={}, geom=(x={}, y={}, width={}, height={}))",
-               self.name, self.geom.x, self.geom.y, self.geom.width, self.geom.height)
+        write!(f, "(name={}, geom=(x={}, y={}, width={}, height={}), max_clients={}, overflow_idx={:?})",
+               self.name, self.geom.x, self.geom.y, self.geom.width, self.geom.height,
+               self.max_clients, self.overflow_idx)
     }
 }
 
+/// Parse gravity from config values
+///
+/// # Arguments
+///
+/// * `values` - Config values of the gravity
+///
+/// # Returns
+///
+/// Either [`Some`] with the [`Gravity`] or otherwise [`None`] when a required value is missing
+pub(crate) fn parse_gravity(values: &HashMap<String, MixedConfigVal>) -> Option<Gravity> {
+    let (Some(MixedConfigVal::S(name)), Some(MixedConfigVal::I(x)), Some(MixedConfigVal::I(y)),
+        Some(MixedConfigVal::I(width)), Some(MixedConfigVal::I(height))) = (values.get("name"),
+            values.get("x"), values.get("y"), values.get("width"), values.get("height")) else {
+        return None;
+    };
+
+    let mut gravity = Gravity::new(name, *x as u16, *y as u16, *width as u16, *height as u16);
+
+    if let Some(MixedConfigVal::I(max_clients)) = values.get("max_clients") {
+        gravity.max_clients = max!(*max_clients, 0) as usize;
+    }
+
+    let is_set = |key: &str| matches!(values.get(key), Some(MixedConfigVal::B(true)));
+
+    gravity.flags = calc_tiling_flags(name, is_set("horz"), is_set("vert"));
+
+    Some(gravity)
+}
+
+/// Calculate tiling flags of a gravity
+///
+/// # Arguments
+///
+/// * `name` - Name of the gravity for the warning
+/// * `horz` - Whether to tile horizontally
+/// * `vert` - Whether to tile vertically
+///
+/// # Returns
+///
+/// The tiling [`GravityFlags`], tiling is limited to one axis and prefers [`GravityFlags::HORZ`]
+pub(crate) fn calc_tiling_flags(name: &str, horz: bool, vert: bool) -> GravityFlags {
+    match (horz, vert) {
+        (true, true) => {
+            warn!("Gravity `{}` cannot tile horizontally and vertically, using horz", name);
+
+            GravityFlags::HORZ
+        },
+        (true, false) => GravityFlags::HORZ,
+        (false, true) => GravityFlags::VERT,
+        (false, false) => GravityFlags::empty(),
+    }
+}
+
+/// Format gravities for the gravity list
+///
+/// Tiled gravities get the tiling axis as suffix, e.g. `0x0+50+100#left#horz`.
+///
+/// # Arguments
+///
+/// * `gravities` - Gravities to format
+///
+/// # Returns
+///
+/// A [`Vec`] with one entry per gravity
+pub(crate) fn calc_gravity_list(gravities: &[Gravity]) -> Vec<String> {
+    gravities.iter().map(|gravity| {
+        let suffix = if gravity.flags.contains(GravityFlags::HORZ) {
+            "#horz"
+        } else if gravity.flags.contains(GravityFlags::VERT) {
+            "#vert"
+        } else {
+            ""
+        };
+
+        format!("{}x{}+{}+{}#{}{}", gravity.geom.x, gravity.geom.y,
+                gravity.geom.width, gravity.geom.height, gravity.name, suffix)
+    }).collect()
+}
+
+/// Find the gravity a client goes to when its gravity is full
+///
+/// Follows the overflow chain until a gravity with a free slot is found. When the chain ends
+/// or loops back on itself, the direct overflow of the wanted gravity is used regardless of
+/// its limit.
+///
+/// # Arguments
+///
+/// * `gravities` - All gravities
+/// * `counts` - Number of clients per gravity index on the screen and view
+/// * `gravity_idx` - Wanted gravity index
+///
+/// # Returns
+///
+/// Index of the gravity to use
+pub(crate) fn calc_overflow_idx(gravities: &[Gravity], counts: &[usize], gravity_idx: usize) -> usize {
+    let mut visited = vec![false; gravities.len()];
+    let mut idx = gravity_idx;
+
+    while let Some(gravity) = gravities.get(idx) {
+        if 0 == gravity.max_clients || counts.get(idx).copied().unwrap_or(0) < gravity.max_clients {
+            return idx;
+        }
+
+        visited[idx] = true;
+
+        match gravity.overflow_idx {
+            Some(next_idx) if next_idx < gravities.len() && !visited[next_idx] => idx = next_idx,
+            _ => break,
+        }
+    }
+
+    // Final fallback: Allow overflow
+    gravities.get(gravity_idx)
+        .and_then(|gravity| gravity.overflow_idx)
+        .filter(|overflow_idx| *overflow_idx < gravities.len())
+        .unwrap_or(gravity_idx)
+}
+
+/// Resolve overflow names of parsed gravities to their index
+///
+/// # Arguments
+///
+/// * `gravities` - Parsed gravities
+/// * `overflow_names` - Overflow name per parsed gravity if any
+pub(crate) fn resolve_overflow(gravities: &mut [Gravity], overflow_names: &[Option<String>]) {
+    for (gravity_idx, overflow_name) in overflow_names.iter().enumerate() {
+        let Some(overflow_name) = overflow_name else {
+            continue;
+        };
+
+        match gravities.iter().position(|grav| grav.name.eq(overflow_name)) {
+            Some(overflow_idx) => gravities[gravity_idx].overflow_idx = Some(overflow_idx),
+            None => warn!("Ignoring unknown overflow gravity `{}` of gravity `{}`",
+                overflow_name, gravities[gravity_idx].name),
+        }
+    }
+
+    debug!("{}: ngravities={}", function_name!(), gravities.len());
+}
+
 /// Check config and init all gravity related options
 ///
 /// # Arguments
@@ -104,21 +252,31 @@ impl fmt::Display for Gravity {
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
 pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
+    let mut overflow_names = Vec::new();
+
     for gravity_values in config.gravities.iter() {
-        if let (Some(MixedConfigVal::S(name)), Some(MixedConfigVal::I(x)),
-            Some(MixedConfigVal::I(y)), Some(MixedConfigVal::I(width)),
-            Some(MixedConfigVal::I(height))) = (gravity_values.get("name"), gravity_values.get("x"),
-                                                gravity_values.get("y"), gravity_values.get("width"), gravity_values.get("height"))
-        {
-            subtle.gravities.push(Gravity::new(name, *x as u16, *y as u16, *width as u16, *height as u16));
+        if let Some(gravity) = parse_gravity(gravity_values) {
+            overflow_names.push(match gravity_values.get("overflow") {
+                Some(MixedConfigVal::S(overflow_name)) => Some(overflow_name.clone()),
+                _ => None,
+            });
+
+            subtle.gravities.push(gravity);
         }
     }
 
+    // Resolve overflow gravities once all names are known
+    resolve_overflow(&mut subtle.gravities, &overflow_names);
+
     // Check gravities
     if subtle.gravities.is_empty() {
-        return Err(anyhow!("No gravities found"));
+        return Err(InitError::Fatal(anyhow!("No gravities found")).into());
     }
 
+    config::check_empty_names("gravity", &subtle.gravities.iter()
+        .map(|grav| grav.name.clone())
+        .collect::<Vec<String>>())?;
+
     // Find default gravity
     if let Some(MixedConfigVal::S(grav_name)) = config.subtle.get("default_gravity") {
         if let Some(grav_id) = subtle.gravities.iter().position(|grav| grav.name.eq(grav_name)) {
@@ -150,12 +308,7 @@ pub(crate) fn publish(subtle: &Subtle) -> Result<()> {
 
     let default_screen = &conn.setup().roots[subtle.screen_num];
 
-    let mut gravities: Vec<String> = Vec::with_capacity(subtle.gravities.len());
-
-    for gravity in subtle.gravities.iter() {
-        gravities.push(format!("{}x{}+{}+{}#{}", gravity.geom.x, gravity.geom.y,
-                               gravity.geom.width, gravity.geom.height, gravity.name));
-    }
+    let gravities = calc_gravity_list(&subtle.gravities);
 
     conn.change_property8(PropMode::REPLACE, default_screen.root, atoms.SUBTLE_GRAVITY_LIST,
                           AtomEnum::STRING, gravities.join("\0").as_bytes())?.check()?;
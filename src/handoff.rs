@@ -0,0 +1,185 @@
+//!
+//! @package subtle-rs
+//!
+//! @file Handoff functions
+//! @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+//! @version $Id$
+//!
+//! This program can be distributed under the terms of the GNU GPLv3.
+//! See the file LICENSE for details.
+//!
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use stdext::function_name;
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt, Window};
+use crate::client::ClientFlags;
+use crate::effect;
+use crate::effect::{Effect, Prop};
+use crate::subtle::{Subtle, SubtleFlags};
+use crate::tagging::Tagging;
+use crate::validate::{ValidGravityIdx, ValidScreenIdx, ValidTagging};
+
+/// Version of the serialized client state, bump on format changes
+pub(crate) const SUBTLE_STATE_VERSION: u32 = 1;
+
+/// Command line flag to signal a handoff to the new instance
+pub(crate) const HANDOFF_ARG: &str = "--handoff";
+
+/// Number of values of the serialized client state
+const STATE_LEN: usize = 5;
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct HandoffState {
+    /// Tags of the client
+    pub(crate) tags: Tagging,
+    /// Current gravity of the client
+    pub(crate) gravity_idx: isize,
+    /// Screen of the client
+    pub(crate) screen_idx: isize,
+    /// Mode flags of the client
+    pub(crate) mode_flags: ClientFlags,
+}
+
+impl HandoffState {
+    /// Serialize state into property values
+    ///
+    /// # Returns
+    ///
+    /// A [`Vec`] with the version followed by the state values
+    pub(crate) fn encode(&self) -> Vec<u32> {
+        vec![
+            SUBTLE_STATE_VERSION,
+            self.tags.bits(),
+            self.gravity_idx as u32,
+            self.screen_idx as u32,
+            (self.mode_flags & ClientFlags::ALL_MODES).bits(),
+        ]
+    }
+
+    /// Parse state from property values
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Property values
+    ///
+    /// # Returns
+    ///
+    /// A [`Option`] with either [`Some`] state or otherwise [`None`] on version or length mismatch
+    pub(crate) fn decode(data: &[u32]) -> Option<Self> {
+        if STATE_LEN != data.len() || SUBTLE_STATE_VERSION != data[0] {
+            return None;
+        }
+
+        Some(HandoffState {
+            tags: Tagging::from_bits_retain(data[1]),
+            gravity_idx: data[2] as i32 as isize,
+            screen_idx: data[3] as i32 as isize,
+            mode_flags: ClientFlags::from_bits_truncate(data[4]) & ClientFlags::ALL_MODES,
+        })
+    }
+
+    /// Check whether state fits to the current config
+    ///
+    /// # Arguments
+    ///
+    /// * `ntags` - Number of tags
+    /// * `ngravities` - Number of gravities
+    /// * `nscreens` - Number of screens
+    ///
+    /// # Returns
+    ///
+    /// Either [`true`] if all indices are in range or otherwise [`false`]
+    pub(crate) fn is_valid(&self, ntags: usize, ngravities: usize, nscreens: usize) -> bool {
+        ValidTagging::new(self.tags.bits(), ntags).is_ok()
+            && ValidGravityIdx::new(self.gravity_idx as u32, ngravities).is_ok()
+            && ValidScreenIdx::new(self.screen_idx as u32, nscreens).is_ok()
+    }
+}
+
+/// Add handoff flag to the arguments of the new instance
+///
+/// # Arguments
+///
+/// * `args` - Arguments of the current instance
+///
+/// # Returns
+///
+/// A [`Vec`] with the arguments including the handoff flag exactly once
+pub(crate) fn build_args(args: Vec<String>) -> Vec<String> {
+    let mut args = args;
+
+    if !args.iter().skip(1).any(|arg| HANDOFF_ARG == arg) {
+        args.push(HANDOFF_ARG.to_string());
+    }
+
+    args
+}
+
+/// Store state of all clients on their windows before restart
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn save(subtle: &Subtle) -> Result<()> {
+    let clients = subtle.clients.borrow();
+
+    for client in clients.iter() {
+        let state = HandoffState {
+            tags: client.tags,
+            gravity_idx: client.gravity_idx,
+            screen_idx: client.screen_idx,
+            mode_flags: client.flags,
+        };
+
+        effect::apply(subtle, Effect::SetProperty { win: client.win, prop: Prop::ClientState,
+            data: state.encode() })?;
+    }
+
+    effect::flush(subtle)?;
+
+    debug!("{}: nclients={}", function_name!(), clients.len());
+
+    Ok(())
+}
+
+/// Load and remove state of the previous instance from a window
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `win` - Window to adopt
+///
+/// # Returns
+///
+/// A [`Result`] with either [`Some`] valid state, [`None`] for a cold adoption or otherwise [`anyhow::Error`]
+pub(crate) fn load(subtle: &Subtle, win: Window) -> Result<Option<HandoffState>> {
+    if !subtle.flags.contains(SubtleFlags::HANDOFF) {
+        return Ok(None);
+    }
+
+    let conn = subtle.conn.get().context("Failed to get connection")?;
+    let atoms = subtle.atoms.get().context("Failed to get atoms")?;
+
+    let data: Vec<u32> = conn.get_property(true, win, atoms.SUBTLE_CLIENT_STATE,
+                                           AtomEnum::CARDINAL, 0, u32::MAX)?
+        .reply()?
+        .value32()
+        .map(|values| values.collect())
+        .unwrap_or_default();
+
+    let state = HandoffState::decode(&data).filter(|state| state.is_valid(subtle.tags.borrow().len(),
+        subtle.gravities.len(), subtle.screens.len()));
+
+    if state.is_none() && !data.is_empty() {
+        warn!("Ignoring incompatible handoff state of window {}", win);
+    }
+
+    debug!("{}: win={}, state={:?}", function_name!(), win, state);
+
+    Ok(state)
+}
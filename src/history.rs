@@ -0,0 +1,168 @@
+//!
+//! @package subtle-rs
+//!
+//! @file History functions
+//! @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+//! @version $Id$
+//!
+//! This program can be distributed under the terms of the GNU GPLv3.
+//! See the file LICENSE for details.
+//!
+
+use std::cell::RefCell;
+use log::debug;
+use stdext::function_name;
+use x11rb::NONE;
+use x11rb::protocol::xproto::{Keycode, Window};
+
+#[derive(Debug)]
+pub(crate) struct FocusHistory {
+    /// Windows ordered from most to least recently focused, padded with [`NONE`]
+    wins: RefCell<Vec<Window>>,
+}
+
+impl FocusHistory {
+    /// Create a new history
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - Number of windows to remember
+    ///
+    /// # Returns
+    ///
+    /// A new [`FocusHistory`]
+    pub(crate) fn new(size: usize) -> Self {
+        FocusHistory {
+            wins: RefCell::new(vec![NONE; size]),
+        }
+    }
+
+    /// Move window to the front and drop the oldest entry on overflow
+    ///
+    /// # Arguments
+    ///
+    /// * `win` - Focused window
+    pub(crate) fn push(&self, win: Window) {
+        let mut wins = self.wins.borrow_mut();
+        let size = wins.len();
+
+        if 0 == size || NONE == win {
+            return;
+        }
+
+        // Drop the gap of an unset focus as well
+        wins.retain(|w| *w != win && NONE != *w);
+        wins.insert(0, win);
+        wins.resize(size, NONE);
+
+        debug!("{}: win={}, wins={:?}", function_name!(), win, wins);
+    }
+
+    /// Unset most recently focused window and keep the older entries
+    pub(crate) fn unset_first(&self) {
+        let mut wins = self.wins.borrow_mut();
+        let size = wins.len();
+
+        if 0 == size || NONE == wins[0] {
+            return;
+        }
+
+        wins.insert(0, NONE);
+        wins.resize(size, NONE);
+
+        debug!("{}: wins={:?}", function_name!(), wins);
+    }
+
+    /// Remove window from the history entirely
+    ///
+    /// # Arguments
+    ///
+    /// * `win` - Window to remove
+    pub(crate) fn remove(&self, win: Window) {
+        let mut wins = self.wins.borrow_mut();
+        let size = wins.len();
+
+        if NONE == win {
+            return;
+        }
+
+        wins.retain(|w| *w != win);
+        wins.resize(size, NONE);
+
+        debug!("{}: win={}, wins={:?}", function_name!(), win, wins);
+    }
+
+    /// Get most recently focused window
+    ///
+    /// # Returns
+    ///
+    /// A [`Option`] with either [`Some`] window or otherwise [`None`]
+    pub(crate) fn first(&self) -> Option<Window> {
+        self.wins.borrow().first()
+            .copied()
+            .filter(|win| NONE != *win)
+    }
+
+    /// Get copy of all entries including padding
+    ///
+    /// # Returns
+    ///
+    /// A [`Vec`] with all windows from most to least recently focused
+    pub(crate) fn to_vec(&self) -> Vec<Window> {
+        self.wins.borrow().clone()
+    }
+
+    /// Replace all entries and keep the size
+    ///
+    /// # Arguments
+    ///
+    /// * `wins` - Windows ordered from most to least recently focused
+    pub(crate) fn replace(&self, wins: Vec<Window>) {
+        let mut history = self.wins.borrow_mut();
+        let size = history.len();
+
+        *history = wins;
+        history.retain(|win| NONE != *win);
+        history.resize(size, NONE);
+
+        debug!("{}: wins={:?}", function_name!(), history);
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct WindowCycle {
+    /// Candidates ordered from most to least recently used
+    pub(crate) wins: Vec<Window>,
+    /// Index of the selected candidate
+    pub(crate) idx: usize,
+    /// Keycodes of the held modifiers that end the cycle
+    pub(crate) modifier_keycodes: Vec<Keycode>,
+    /// Focus history before the cycle
+    pub(crate) history: Vec<Window>,
+}
+
+/// Order windows by most recent use and fall back to the stacking order
+///
+/// # Arguments
+///
+/// * `history` - Focus history from most to least recently focused
+/// * `stacking` - Candidate windows from bottom to top
+///
+/// # Returns
+///
+/// A [`Vec`] with the candidates from most to least recently used
+pub(crate) fn calc_mru_order(history: &[Window], stacking: &[Window]) -> Vec<Window> {
+    let mut wins: Vec<Window> = history.iter()
+        .filter(|win| NONE != **win && stacking.contains(win))
+        .copied()
+        .collect();
+
+    // Add windows that aren't in the history from top to bottom
+    for win in stacking.iter().rev() {
+        if !wins.contains(win) {
+            wins.push(*win);
+        }
+    }
+
+    wins
+}
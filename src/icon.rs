@@ -1,7 +1,7 @@
 //!
 //! @package subtle-rs
 //!
-//! @file Xbm functions
+//! @file Icon functions
 //! @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
 //! @version $Id$
 //!
@@ -10,9 +10,16 @@
 //!
 
 use std::fmt;
-use anyhow::{Context, Result};
+use std::io::Cursor;
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use stdext::function_name;
 use x11rb::connection::Connection;
 use x11rb::protocol::xproto::{ConnectionExt, ImageFormat, Pixmap};
+use x11rb::rust_connection::RustConnection;
+use x11rb::NONE;
+use crate::error::{Classify, InitError};
+use crate::style::{CalcSpacing, Style};
 use crate::subtle::Subtle;
 
 #[derive(Default, Debug, Clone)]
@@ -23,14 +30,40 @@ pub(crate) struct Icon {
     pub(crate) width: u16,
     /// Height of the icon
     pub(crate) height: u16,
+    /// Color depth of the icon
+    pub(crate) depth: u8,
+    /// Unscaled pixels of the icon
+    pub(crate) source: Vec<u32>,
+    /// Unscaled width of the icon
+    pub(crate) source_width: u16,
+    /// Unscaled height of the icon
+    pub(crate) source_height: u16,
 }
 
-/// Load icon from file
+/// Magic bytes of PNG files
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Color depth of bitmap icons drawn with the style colors
+pub(crate) const BITMAP_DEPTH: u8 = 1;
+
+/// Check whether data is a PNG image
 ///
 /// # Arguments
 ///
-/// * `bits_per_pixel` - Number of bits per pixel
-/// * `file_path` - Path to icon file
+/// * `data` - Content of the icon file
+///
+/// # Returns
+///
+/// Either [`true`] if the data starts with the PNG magic or otherwise [`false`]
+pub(crate) fn is_png(data: &[u8]) -> bool {
+    data.starts_with(PNG_MAGIC)
+}
+
+/// Parse XBM image into ARGB pixels
+///
+/// # Arguments
+///
+/// * `content` - Content of the xbm file
 ///
 /// # Example
 ///
@@ -40,20 +73,16 @@ pub(crate) struct Icon {
 ///   0x10, 0x00, 0x38, 0x00, 0x44, 0x00, 0xd6, 0x00, 0xdf, 0x01, 0xee, 0x00,
 ///   0x7c, 0x00, 0x28, 0x00, 0x10, 0x00 };
 ///
-/// # Links
-///
-/// * https://www.collabora.com/news-and-blog/blog/2016/02/16/a-programmers-view-on-digital-images-the-essentials/
-///
 /// # Returns
 ///
-/// A [`Result`] with either [`(Vec<u8>, u16, u16)`] on success or otherwise [`anyhow::Error`]
+/// A [`Result`] with either [`(Vec<u32>, u16, u16)`] on success or otherwise [`anyhow::Error`]
 #[allow(clippy::manual_div_ceil)]
-fn load_from_file(bits_per_pixel: usize, file_path: &str) -> Result<(Vec<u8>, u16, u16)> {
+pub(crate) fn parse_xbm(content: &str) -> Result<(Vec<u32>, u16, u16)> {
     let mut width = 0;
     let mut height = 0;
     let mut bits: Vec<u8> = vec![];
 
-    for line in std::fs::read_to_string(file_path)?.lines() {
+    for line in content.lines() {
         // Extract width & height
         if line.contains("_width") {
             width = line.split_whitespace().last()
@@ -76,6 +105,178 @@ fn load_from_file(bits_per_pixel: usize, file_path: &str) -> Result<(Vec<u8>, u1
         }
     }
 
+    if bits.len() < height * ((width + 7) / 8) {
+        return Err(anyhow!("Failed to find enough bits for {}x{}", width, height));
+    }
+
+    let mut pixels = vec![0u32; width * height];
+
+    for y in 0..height {
+        for x in 0..width {
+            let byte_index = y * ((width + 7) / 8) + (x / 8);
+
+            // Set bits are opaque white, so they end up in the copied plane
+            if 0 != (bits[byte_index] >> (x % 8)) & 1 {
+                pixels[y * width + x] = u32::MAX;
+            }
+        }
+    }
+
+    Ok((pixels, width as u16, height as u16))
+}
+
+/// Parse PNG image into ARGB pixels
+///
+/// # Arguments
+///
+/// * `data` - Content of the png file
+///
+/// # Returns
+///
+/// A [`Result`] with either [`(Vec<u32>, u16, u16)`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn parse_png(data: &[u8]) -> Result<(Vec<u32>, u16, u16)> {
+    let mut decoder = png::Decoder::new(Cursor::new(data));
+
+    // Expand palettes and low bit depths and strip 16-bit channels
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0u8; reader.output_buffer_size().context("Failed to get buffer size")?];
+    let info = reader.next_frame(&mut buf)?;
+
+    let pixels: Vec<u32> = match info.color_type {
+        png::ColorType::Grayscale => buf[..info.buffer_size()].iter()
+            .map(|g| 0xFF000000 | (*g as u32) << 16 | (*g as u32) << 8 | *g as u32)
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buf[..info.buffer_size()].chunks_exact(2)
+            .map(|p| (p[1] as u32) << 24 | (p[0] as u32) << 16 | (p[0] as u32) << 8 | p[0] as u32)
+            .collect(),
+        png::ColorType::Rgb => buf[..info.buffer_size()].chunks_exact(3)
+            .map(|p| 0xFF000000 | (p[0] as u32) << 16 | (p[1] as u32) << 8 | p[2] as u32)
+            .collect(),
+        png::ColorType::Rgba => buf[..info.buffer_size()].chunks_exact(4)
+            .map(|p| (p[3] as u32) << 24 | (p[0] as u32) << 16 | (p[1] as u32) << 8 | p[2] as u32)
+            .collect(),
+        png::ColorType::Indexed => return Err(anyhow!("Failed to expand indexed colors")),
+    };
+
+    let width = u16::try_from(info.width).context("Icon too wide")?;
+    let height = u16::try_from(info.height).context("Icon too high")?;
+
+    Ok((pixels, width, height))
+}
+
+/// Calculate size of an image scaled down to fit into the given height
+///
+/// # Arguments
+///
+/// * `width` - Width of the image
+/// * `height` - Height of the image
+/// * `max_height` - Max height of the image
+///
+/// # Returns
+///
+/// A tuple with the scaled width and height
+pub(crate) fn calc_scaled_size(width: u16, height: u16, max_height: u16) -> (u16, u16) {
+    let max_height = max_height.max(1);
+
+    if height <= max_height {
+        return (width, height);
+    }
+
+    (((width as u32 * max_height as u32) / height as u32).max(1) as u16, max_height)
+}
+
+/// Calculate max height of icons drawn in the panel
+///
+/// # Arguments
+///
+/// * `panel_height` - Height of the panel
+/// * `style` - Style the icon is drawn with
+///
+/// # Returns
+///
+/// The max height of the icon
+pub(crate) fn calc_max_height(panel_height: u16, style: &Style) -> u16 {
+    (panel_height as i16 - style.calc_spacing(CalcSpacing::Height)).max(1) as u16
+}
+
+/// Scale pixels down to fit into the given height and keep the aspect ratio
+///
+/// # Arguments
+///
+/// * `pixels` - ARGB pixels
+/// * `width` - Width of the image
+/// * `height` - Height of the image
+/// * `max_height` - Max height of the image
+///
+/// # Returns
+///
+/// A tuple with the scaled pixels, width and height
+pub(crate) fn calc_scaled(pixels: Vec<u32>, width: u16, height: u16, max_height: u16) -> (Vec<u32>, u16, u16) {
+    let (new_width, new_height) = calc_scaled_size(width, height, max_height);
+
+    if new_height == height {
+        return (pixels, width, height);
+    }
+
+    // Pick nearest source pixel
+    let mut scaled = Vec::with_capacity(new_width as usize * new_height as usize);
+
+    for y in 0..new_height as usize {
+        let src_y = y * height as usize / new_height as usize;
+
+        for x in 0..new_width as usize {
+            let src_x = x * width as usize / new_width as usize;
+
+            scaled.push(pixels[src_y * width as usize + src_x]);
+        }
+    }
+
+    (scaled, new_width, new_height)
+}
+
+/// Composite ARGB pixel against background color
+///
+/// # Arguments
+///
+/// * `pixel` - ARGB pixel
+/// * `bg` - RGB background color
+///
+/// # Returns
+///
+/// The opaque RGB pixel
+pub(crate) fn calc_blended(pixel: u32, bg: u32) -> u32 {
+    let alpha = pixel >> 24;
+
+    [16, 8, 0].iter().fold(0, |blended, shift| {
+        let fg_channel = (pixel >> shift) & 0xFF;
+        let bg_channel = (bg >> shift) & 0xFF;
+
+        blended | ((fg_channel * alpha + bg_channel * (255 - alpha) + 127) / 255) << shift
+    })
+}
+
+/// Pack RGB pixels into image data of the display format
+///
+/// # Arguments
+///
+/// * `pixels` - RGB pixels
+/// * `width` - Width of the image
+/// * `height` - Height of the image
+/// * `bits_per_pixel` - Number of bits per pixel
+///
+/// # Links
+///
+/// * https://www.collabora.com/news-and-blog/blog/2016/02/16/a-programmers-view-on-digital-images-the-essentials/
+///
+/// # Returns
+///
+/// A [`Vec`] with the padded image data
+#[allow(clippy::manual_div_ceil)]
+pub(crate) fn pack_pixels(pixels: &[u32], width: u16, height: u16, bits_per_pixel: usize) -> Vec<u8> {
+    let (width, height) = (width as usize, height as usize);
+
     // Calculate display bytes and stride
     let bytes_per_pixel = bits_per_pixel / 8;
     let stride = ((width * bits_per_pixel + 31) / 32) * 4;
@@ -85,31 +286,27 @@ fn load_from_file(bits_per_pixel: usize, file_path: &str) -> Result<(Vec<u8>, u1
 
     for y in 0..height {
         for x in 0..width {
-            let byte_index = y * ((width + 7) / 8) + (x / 8);
-            let bit = (bits[byte_index] >> (x % 8)) & 1;
+            let color = pixels[y * width + x];
 
             let pixel_offset = y * stride + x * bytes_per_pixel;
             let pixel = &mut img_data[pixel_offset..];
 
-            // Set colors if required
-            let color = if 0 != bit { 255 } else { 0 };
-
             // Blue
-            pixel[0] = color;
+            pixel[0] = color as u8;
 
             // Green
             if bytes_per_pixel > 1 {
-                pixel[1] = color;
+                pixel[1] = (color >> 8) as u8;
             }
 
             // Red
             if bytes_per_pixel > 2 {
-                pixel[2] = color;
+                pixel[2] = (color >> 16) as u8;
             }
         }
     }
 
-    Ok((img_data, width as u16, height as u16))
+    img_data
 }
 
 impl Icon {
@@ -119,12 +316,117 @@ impl Icon {
     ///
     /// * `subtle` - Global state object
     /// * `file_path` - Path to icon file
+    /// * `style` - Style the icon is drawn with
     ///
     /// # Returns
     ///
     /// A [`Result`] with either [`Icon`] on success or otherwise [`anyhow::Error`]
-    pub(crate) fn new(subtle: &Subtle, file_path: &str) -> Result<Icon> {
-        let conn = subtle.conn.get().unwrap();
+    pub(crate) fn new(subtle: &Subtle, file_path: &str, style: &Style) -> Result<Icon> {
+        let conn = subtle.conn.get().context("Failed to get connection")?;
+        let default_screen = &conn.setup().roots[subtle.screen_num];
+
+        // Decide format by magic and fall back to xbm
+        let data = std::fs::read(file_path)?;
+
+        let (pixels, width, height, depth) = if is_png(&data) {
+            let (pixels, width, height) = parse_png(&data)?;
+            let bg = if -1 == style.bg { 0 } else { style.bg as u32 };
+
+            (pixels.iter().map(|pixel| calc_blended(*pixel, bg)).collect(),
+             width, height, default_screen.root_depth)
+        } else {
+            let (pixels, width, height) = parse_xbm(&String::from_utf8_lossy(&data))?;
+
+            (pixels, width, height, BITMAP_DEPTH)
+        };
+
+        // Keep icons inside of the panel
+        let mut icon = Icon::from_pixels(pixels, width, height, depth,
+                                         calc_max_height(subtle.panel_height, style));
+
+        icon.upload(subtle)?;
+
+        debug!("{}: file_path={}, depth={}", function_name!(), file_path, depth);
+
+        Ok(icon)
+    }
+
+    /// Create a new instance from pixels without a pixmap
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` - ARGB pixels
+    /// * `width` - Width of the image
+    /// * `height` - Height of the image
+    /// * `depth` - Color depth of the icon
+    /// * `max_height` - Max height of the icon
+    ///
+    /// # Returns
+    ///
+    /// A new [`Icon`] scaled into max height
+    pub(crate) fn from_pixels(pixels: Vec<u32>, width: u16, height: u16, depth: u8, max_height: u16) -> Icon {
+        let (scaled_width, scaled_height) = calc_scaled_size(width, height, max_height);
+
+        Icon {
+            pixmap: NONE,
+            width: scaled_width,
+            height: scaled_height,
+            depth,
+            source: pixels,
+            source_width: width,
+            source_height: height,
+        }
+    }
+
+    /// Calculate new size when the max height has changed
+    ///
+    /// # Arguments
+    ///
+    /// * `max_height` - Max height of the icon
+    ///
+    /// # Returns
+    ///
+    /// Either [`Some`] new width and height or otherwise [`None`] when the size is unchanged
+    pub(crate) fn calc_rescaled(&self, max_height: u16) -> Option<(u16, u16)> {
+        let size = calc_scaled_size(self.source_width, self.source_height, max_height);
+
+        (size != (self.width, self.height)).then_some(size)
+    }
+
+    /// Scale icon into new max height and replace its pixmap
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `max_height` - Max height of the icon
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn rescale(&mut self, subtle: &Subtle, max_height: u16) -> Result<()> {
+        if let Some((width, height)) = self.calc_rescaled(max_height) {
+            self.width = width;
+            self.height = height;
+
+            self.upload(subtle)?;
+        }
+
+        debug!("{}: icon={}, max_height={}", function_name!(), self, max_height);
+
+        Ok(())
+    }
+
+    /// Upload scaled pixels into a new pixmap
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    fn upload(&mut self, subtle: &Subtle) -> Result<()> {
+        let conn = subtle.conn.get().context("Failed to get connection")?;
         let default_screen = &conn.setup().roots[subtle.screen_num];
 
         // Find pixmap format for default depth
@@ -134,8 +436,10 @@ impl Icon {
             .context("Failed to find pixmap format for depth")?;
         let bits_per_pixel = fmt.bits_per_pixel as usize;
 
-        let (img_data, width, height) =
-            load_from_file(bits_per_pixel, file_path)?;
+        let (pixels, width, height) = calc_scaled(self.source.clone(), self.source_width,
+                                                  self.source_height, self.height);
+
+        let img_data = pack_pixels(&pixels, width, height, bits_per_pixel);
 
         // Create pixmap and put image
         let pixmap = conn.generate_id()?;
@@ -146,16 +450,64 @@ impl Icon {
         conn.put_image(ImageFormat::Z_PIXMAP, pixmap, subtle.draw_gc, width,
             height, 0, 0, 0, default_screen.root_depth, &img_data)?.check()?;
 
-        Ok(Self {
-            pixmap,
-            width,
-            height,
-        })
+        // Free pixmap of the old size
+        if NONE != self.pixmap {
+            conn.free_pixmap(self.pixmap)?;
+        }
+
+        self.pixmap = pixmap;
+
+        debug!("{}: icon={}", function_name!(), self);
+
+        Ok(())
+    }
+
+    /// Load icon from config, missing icons aren't fatal
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `file_path` - Path to icon file
+    /// * `style` - Style the icon is drawn with
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`Icon`] on success or otherwise recoverable [`InitError`]
+    pub(crate) fn load(subtle: &Subtle, file_path: &str, style: &Style) -> Result<Icon, InitError> {
+        Icon::new(subtle, file_path, style)
+            .with_context(|| format!("Failed to load icon `{}`", file_path))
+            .recoverable()
+    }
+
+    /// Get width and height for external consumers
+    ///
+    /// # Returns
+    ///
+    /// An array with the width and height of the icon
+    pub(crate) fn get_geometry(&self) -> [u32; 2] {
+        [self.width as u32, self.height as u32]
+    }
+
+    /// Free icon pixmap
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to display
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn kill(&self, conn: &RustConnection) -> Result<()> {
+        conn.free_pixmap(self.pixmap)?.check()?;
+
+        debug!("{}: icon={}", function_name!(), self);
+
+        Ok(())
     }
 }
 
 impl fmt::Display for Icon {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "(pixmap={}, width={:?}, height={:?})", self.pixmap, self.width, self.height)
+        write!(f, "(pixmap={}, width={:?}, height={:?}, depth={})", self.pixmap, self.width, self.height, self.depth)
     }
 }
@@ -0,0 +1,102 @@
+//!
+//! @package subtle-rs
+//!
+//! @file Ignore functions
+//! @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+//! @version $Id$
+//!
+//! This program can be distributed under the terms of the GNU GPLv3.
+//! See the file LICENSE for details.
+//!
+
+use regex::{Regex, RegexBuilder};
+use anyhow::Result;
+use log::debug;
+use stdext::function_name;
+use x11rb::properties::WmClass;
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt, Window};
+use crate::config::Config;
+use crate::subtle::Subtle;
+use crate::tag;
+
+/// Check whether any of the rules matches the window names
+///
+/// # Arguments
+///
+/// * `rules` - Ignore rules
+/// * `name` - WM_NAME of the window
+/// * `instance` - Instance part of WM_CLASS
+/// * `klass` - Class part of WM_CLASS
+///
+/// # Returns
+///
+/// Either [`true`] on match and otherwise [`false`]
+pub(crate) fn is_match(rules: &[Regex], name: &str, instance: &str, klass: &str) -> bool {
+    rules.iter().any(|regex| tag::matches_any(regex, name, instance, klass))
+}
+
+/// Check whether window must not be managed and remember it
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `win` - Window to check
+///
+/// # Returns
+///
+/// A [`Result`] with either [`bool`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn check_win(subtle: &Subtle, win: Window) -> Result<bool> {
+    // Known windows stay ignored even when the rules change
+    if subtle.is_ignored_win(win) {
+        return Ok(true);
+    }
+
+    if subtle.ignores.is_empty() {
+        return Ok(false);
+    }
+
+    let conn = subtle.conn.get().unwrap();
+    let atoms = subtle.atoms.get().unwrap();
+
+    let name = String::from_utf8_lossy(&conn.get_property(false, win, atoms.WM_NAME,
+        AtomEnum::STRING, 0, u32::MAX)?.reply()?.value).to_string();
+
+    let (instance, klass) = match WmClass::get(conn, win)?.reply()? {
+        Some(wm_class) => (String::from_utf8_lossy(wm_class.instance()).to_string(),
+                           String::from_utf8_lossy(wm_class.class()).to_string()),
+        None => (String::new(), String::new()),
+    };
+
+    let ignored = is_match(&subtle.ignores, &name, &instance, &klass);
+
+    if ignored {
+        subtle.add_ignored_win(win);
+    }
+
+    debug!("{}: win={}, name={}, instance={}, class={}, ignored={}",
+        function_name!(), win, name, instance, klass, ignored);
+
+    Ok(ignored)
+}
+
+/// Check config and init all ignore related options
+///
+/// # Arguments
+///
+/// * `config` - Config values read either from args or config file
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
+    for value in config.ignore.iter() {
+        subtle.ignores.push(RegexBuilder::new(value)
+            .case_insensitive(true)
+            .build()?);
+    }
+
+    debug!("{}: nignores={}", function_name!(), subtle.ignores.len());
+
+    Ok(())
+}
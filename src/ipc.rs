@@ -0,0 +1,157 @@
+//!
+//! @package subtle-rs
+//!
+//! @file IPC definitions
+//! @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+//! @version $Id$
+//!
+//! This program can be distributed under the terms of the GNU GPLv3.
+//! See the file LICENSE for details.
+//!
+//! Shared with subtler, so keep this free of other modules.
+//!
+
+use struct_iterable::Iterable;
+
+/// Number of values per client in SUBTLE_CLIENT_GEOMETRIES
+pub(crate) const CLIENT_GEOMETRY_LEN: usize = 6;
+
+/// Flag of SUBTLE_WINDOW_RAISE to focus the raised window
+pub(crate) const STACK_FOCUS: u32 = 1 << 0;
+
+/// Flag of SUBTLE_WINDOW_RAISE/LOWER to override the stacking levels
+pub(crate) const STACK_FORCE: u32 = 1 << 1;
+
+x11rb::atom_manager! {
+    #[derive(Iterable, Default)]
+    pub Atoms: AtomsCookie {
+        // ICCCM
+        WM_NAME, WM_CLASS, WM_STATE, WM_PROTOCOLS, WM_TAKE_FOCUS,
+        WM_DELETE_WINDOW, WM_NORMAL_HINTS, WM_SIZE_HINTS, WM_HINTS,
+        WM_WINDOW_ROLE, WM_CLIENT_LEADER,
+
+        // EWMH
+        _NET_SUPPORTED, _NET_CLIENT_LIST, _NET_CLIENT_LIST_STACKING,
+        _NET_NUMBER_OF_DESKTOPS, _NET_DESKTOP_NAMES, _NET_DESKTOP_GEOMETRY,
+        _NET_DESKTOP_VIEWPORT, _NET_CURRENT_DESKTOP, _NET_ACTIVE_WINDOW,
+        _NET_WORKAREA, _NET_SUPPORTING_WM_CHECK, _NET_WM_FULL_PLACEMENT,
+        _NET_FRAME_EXTENTS, _NET_REQUEST_FRAME_EXTENTS,
+
+        // Client
+        _NET_CLOSE_WINDOW, _NET_RESTACK_WINDOW, _NET_MOVERESIZE_WINDOW,
+        _NET_WM_NAME, _NET_WM_PID, _NET_WM_DESKTOP, _NET_WM_STRUT,
+
+        // Types
+        _NET_WM_WINDOW_TYPE, _NET_WM_WINDOW_TYPE_DOCK, _NET_WM_WINDOW_TYPE_DESKTOP,
+        _NET_WM_WINDOW_TYPE_TOOLBAR, _NET_WM_WINDOW_TYPE_SPLASH,
+        _NET_WM_WINDOW_TYPE_DIALOG, _NET_WM_WINDOW_TYPE_NORMAL,
+
+        // States
+        _NET_WM_STATE, _NET_WM_STATE_FULLSCREEN, _NET_WM_STATE_ABOVE,
+        _NET_WM_STATE_STICKY, _NET_WM_STATE_DEMANDS_ATTENTION,
+
+        // Actions
+        _NET_WM_ALLOWED_ACTIONS, _NET_WM_ACTION_MOVE, _NET_WM_ACTION_RESIZE,
+        _NET_WM_ACTION_FULLSCREEN, _NET_WM_ACTION_STICK, _NET_WM_ACTION_CLOSE,
+
+        // Tray
+        _NET_SYSTEM_TRAY_OPCODE, _NET_SYSTEM_TRAY_MESSAGE_DATA,
+
+        // Misc
+        UTF8_STRING, MANAGER, _MOTIF_WM_HINTS, _NET_WM_HANDLED_ICONS,
+
+        // XEmbed
+        _XEMBED, _XEMBED_INFO,
+
+        // subtle
+        SUBTLE_CLIENT_TAGS, SUBTLE_CLIENT_RETAG, SUBTLE_CLIENT_GRAVITY,
+        SUBTLE_CLIENT_SCREEN, SUBTLE_CLIENT_FLAGS, SUBTLE_CLIENT_STATE, SUBTLE_CLIENT_GEOMETRIES, SUBTLE_WINDOW_TAG, SUBTLE_WINDOW_UNTAG,
+        SUBTLE_WINDOW_RAISE, SUBTLE_WINDOW_LOWER, SUBTLE_WINDOW_DUMP, SUBTLE_GRAVITY_NEW,
+        SUBTLE_GRAVITY_FLAGS, SUBTLE_GRAVITY_LIST, SUBTLE_GRAVITY_KILL,
+        SUBTLE_TAG_NEW, SUBTLE_TAG_LIST, SUBTLE_TAG_KILL, SUBTLE_TRAY_LIST,
+        SUBTLE_VIEW_NEW, SUBTLE_VIEW_TAGS, SUBTLE_VIEW_STYLE, SUBTLE_VIEW_ICONS, SUBTLE_VIEW_ICON_GEOMETRY,
+        SUBTLE_VIEW_KILL, SUBTLE_SUBLET_UPDATE, SUBTLE_SUBLET_DATA,
+        SUBTLE_SUBLET_STYLE, SUBTLE_SUBLET_FLAGS, SUBTLE_SUBLET_LIST,
+        SUBTLE_SUBLET_KILL, SUBTLE_SCREEN_PANELS, SUBTLE_SCREEN_VIEWS,
+        SUBTLE_SCREEN_JUMP, SUBTLE_PANEL_LAYOUT, SUBTLE_VISIBLE_TAGS, SUBTLE_VISIBLE_VIEWS, SUBTLE_URGENT_TAGS,
+        SUBTLE_RENDER, SUBTLE_RELOAD, SUBTLE_RESTART, SUBTLE_QUIT, SUBTLE_COLORS,
+        SUBTLE_FONT, SUBTLE_DATA, SUBTLE_VERSION,
+    }
+}
+
+/// Geometry and placement of a client as published on the root window
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub(crate) struct ClientGeometry {
+    /// X position
+    pub(crate) x: i16,
+    /// Y position
+    pub(crate) y: i16,
+    /// Width
+    pub(crate) width: u16,
+    /// Height
+    pub(crate) height: u16,
+    /// Gravity index
+    pub(crate) gravity_idx: isize,
+    /// Screen index
+    pub(crate) screen_idx: isize,
+}
+
+impl ClientGeometry {
+    /// Serialize geometry into property values
+    ///
+    /// # Returns
+    ///
+    /// An array with x, y, width, height, gravity and screen
+    pub(crate) fn encode(&self) -> [u32; CLIENT_GEOMETRY_LEN] {
+        [
+            self.x as i32 as u32,
+            self.y as i32 as u32,
+            self.width as u32,
+            self.height as u32,
+            self.gravity_idx as i32 as u32,
+            self.screen_idx as i32 as u32,
+        ]
+    }
+
+    /// Parse all geometries from property values
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Property values
+    ///
+    /// # Returns
+    ///
+    /// A [`Vec`] with the geometries, incomplete trailing values are ignored
+    pub(crate) fn decode_all(data: &[u32]) -> Vec<Self> {
+        data.chunks_exact(CLIENT_GEOMETRY_LEN)
+            .map(|values| ClientGeometry {
+                x: values[0] as i32 as i16,
+                y: values[1] as i32 as i16,
+                width: values[2] as u16,
+                height: values[3] as u16,
+                gravity_idx: values[4] as i32 as isize,
+                screen_idx: values[5] as i32 as isize,
+            })
+            .collect()
+    }
+}
+
+/// Split null-separated string property into names
+///
+/// # Arguments
+///
+/// * `data` - Property value
+///
+/// # Returns
+///
+/// A [`Vec`] with the names
+pub(crate) fn split_names(data: &[u8]) -> Vec<String> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+
+    data.strip_suffix(&[0]).unwrap_or(data)
+        .split(|byte| 0 == *byte)
+        .map(|name| String::from_utf8_lossy(name).to_string())
+        .collect()
+}
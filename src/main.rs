@@ -38,6 +38,8 @@ mod config;
 mod grab;
 /// EWMH module
 mod ewmh;
+/// IPC module shared with subtler
+mod ipc;
 /// Helper module to ease tagging
 mod tagging;
 /// Style module
@@ -51,17 +53,46 @@ mod spacing;
 /// Icon module
 mod icon;
 /// Tray module
+#[cfg(feature = "tray")]
 mod tray;
+/// Ignore module
+mod ignore;
+/// Helper module for geometry
+mod geometry;
+/// Cursor module
+mod cursor;
+/// Flash feedback module
+mod flash;
+/// Focus history module
+mod history;
+/// Session module
+mod session;
+/// Effect module
+mod effect;
+/// Hot corner module
+mod corner;
+/// Error report module
+mod report;
+/// Restart handoff module
+mod handoff;
+/// Init error module
+mod error;
+/// Debug dump module
+mod dump;
+/// Validation module
+mod validate;
 /// Plugin module
 #[cfg(feature = "plugins")]
 mod plugin;
 
 use std::env;
 use std::env::current_exe;
+use std::os::unix::net::UnixStream;
+use std::process::ExitCode;
 use std::sync::Arc;
 use anyhow::{anyhow, Context, Result};
 use log::{debug, error, info};
-use crate::config::Config;
+use crate::config::{Command, Config};
 use crate::font::Font;
 use crate::style::StyleFlags;
 use crate::subtle::{SubtleFlags, Subtle};
@@ -84,6 +115,19 @@ fn install_signal_handler(subtle: &mut Subtle) -> Result<()> {
     signal_hook::flag::register(signal_hook::consts::SIGTERM, Arc::clone(&subtle.shutdown))
         .map_err(|e| anyhow!("Failed to register SIGTERM handler: {}", e))?;
 
+    // Wake up the event loop after the flag is set
+    let (wakeup_read, wakeup_write) = UnixStream::pair()?;
+
+    wakeup_read.set_nonblocking(true)?;
+
+    signal_hook::low_level::pipe::register(signal_hook::consts::SIGINT, wakeup_write.try_clone()?)
+        .map_err(|e| anyhow!("Failed to register SIGINT wakeup: {}", e))?;
+
+    signal_hook::low_level::pipe::register(signal_hook::consts::SIGTERM, wakeup_write)
+        .map_err(|e| anyhow!("Failed to register SIGTERM wakeup: {}", e))?;
+
+    subtle.wakeup.set(wakeup_read).map_err(|_e| anyhow!("Wakeup already set?"))?;
+
     Ok(())
 }
 
@@ -108,7 +152,7 @@ fn sanity_check(subtle: &mut Subtle) -> Result<()> {
 
     // Check and update screens
     for (screen_idx, screen) in subtle.screens.iter_mut().enumerate() {
-        screen.view_idx.set(if screen_idx < subtle.views.len() { screen_idx as isize } else { -1 });
+        screen.view_idx.set(if screen_idx < subtle.views.borrow().len() { screen_idx as isize } else { -1 });
     }
 
     // Enforce sane defaults
@@ -142,6 +186,8 @@ fn sanity_check(subtle: &mut Subtle) -> Result<()> {
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
 fn configure(config: &Config, subtle: &mut Subtle) -> Result<()> {
+    config::check_features(config);
+
     display::init(config, subtle)?;
     ewmh::init(config, subtle)?;
     style::init(config, subtle)?;
@@ -150,8 +196,11 @@ fn configure(config: &Config, subtle: &mut Subtle) -> Result<()> {
     screen::init(config, subtle)?;
     gravity::init(config, subtle)?;
     tag::init(config, subtle)?;
+    session::init(subtle)?;
     view::init(config, subtle)?;
     grab::init(config, subtle)?;
+    corner::init(config, subtle)?;
+    ignore::init(config, subtle)?;
 
     sanity_check(subtle)?;
 
@@ -177,57 +226,166 @@ fn run(subtle: &mut Subtle) -> Result<()> {
     display::publish(subtle)?;
     display::scan(subtle)?;
 
+    // Clients mapped from now on are new
+    subtle.flags.remove(SubtleFlags::HANDOFF);
+
     // Run event handler
     event::event_loop(subtle)?;
 
     Ok(())
 }
 
-/// Main function
+/// Start logging and configure sub-systems
+///
+/// # Arguments
+///
+/// * `config` - Config values read either from args or config file
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn start(config: &Config, subtle: &mut Subtle) -> Result<()> {
+    subtle::check_dry_run(&subtle.flags)?;
+
+    install_signal_handler(subtle)?;
+    print_version();
+
+    // Carry on with recoverable errors that escaped their subsystem
+    if let Err(err) = configure(config, subtle) {
+        if error::is_fatal(&err) {
+            return Err(err.context("Failed to configure"));
+        }
+
+        subtle.init_errors.borrow_mut().push(err);
+    }
+
+    for err in subtle.init_errors.borrow().iter() {
+        report::report(subtle, err);
+    }
+
+    Ok(())
+}
+
+/// Tidy up sub-systems
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
 ///
 /// # Returns
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-fn main() -> Result<()> {
+fn finish(subtle: &mut Subtle) -> Result<()> {
+    corner::destroy(subtle)?;
+    ewmh::finish(subtle)?;
+    display::finish(subtle)?;
+
+    Ok(())
+}
+
+/// Run command instead of the window manager
+///
+/// # Arguments
+///
+/// * `config` - Config values read either from args or config file
+/// * `command` - Command to run
+///
+/// # Returns
+///
+/// The [`ExitCode`] of the process
+fn run_command(#[cfg_attr(not(feature = "plugins"), allow(unused_variables))] config: &Config,
+               command: Command) -> ExitCode {
+    let result: Result<()> = match command {
+        #[cfg(feature = "plugins")]
+        Command::PluginTest { name_or_url } => plugin::test_plugin(config, &name_or_url)
+            .context("Failed to test plugin"),
+        #[cfg(not(feature = "plugins"))]
+        Command::PluginTest { .. } => Err(anyhow!("Failed to test plugin: built without feature `plugins`")),
+    };
+
+    match result {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(err) => {
+            error!("{:?}", err);
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Main function
+///
+/// # Returns
+///
+/// The [`ExitCode`] of the process
+fn main() -> ExitCode {
     // Load config
     let (config, path, _format) = Config::parse_info();
 
-    logger::init(&config)?;
+    // Without logger there is just the error file left
+    if let Err(err) = logger::init(&config) {
+        report::report(&Subtle::default(), &err);
+
+        return ExitCode::FAILURE;
+    }
 
     info!("Reading file `{:?}'", path.unwrap_or_default());
     debug!("Config: {:?}", config);
 
+    // Run commands without connecting to the display
+    if let Some(command) = Command::parse_args(&config.command).unwrap_or_else(|err| err.exit()) {
+        return run_command(&config, command);
+    }
+
     let mut subtle = Subtle::from(&config);
+    let mut exit_code = ExitCode::SUCCESS;
 
-    install_signal_handler(&mut subtle)?;
-    print_version();
+    // Report startup errors to the user, runtime errors are just logged
+    if let Err(err) = start(&config, &mut subtle) {
+        report::report(&subtle, &err);
 
-    // Run and handle errors gracefully
-    if let Err(err) = configure(&config, &mut subtle) {
-        error!("Failed to configure: {:?}", err);
+        exit_code = ExitCode::FAILURE;
+    } else if subtle.flags.contains(SubtleFlags::CHECK) {
+        info!("Config OK");
     } else {
         drop(config);
 
         if let Err(err) = run(&mut subtle) {
             error!("Failed to run: {:?}", err);
+
+            exit_code = ExitCode::FAILURE;
         }
     }
 
+    // Leave state of clients for the new instance
+    if subtle.flags.contains(SubtleFlags::RESTART) && let Err(err) = handoff::save(&subtle) {
+        error!("Failed to save handoff: {:?}", err);
+    }
+
     // Tidy up
-    ewmh::finish(&subtle)?;
-    display::finish(&mut subtle)?;
+    if let Err(err) = finish(&mut subtle) {
+        error!("Failed to finish: {:?}", err);
+    }
 
     // Restart if necessary
     if subtle.flags.contains(SubtleFlags::RESTART) {
         info!("Restarting");
 
         // When this actually returns something went wrong
-        let err = exec::execvp(current_exe()?.as_os_str(), env::args());
+        match current_exe() {
+            Ok(exe) => {
+                let err = exec::execvp(exe.as_os_str(), handoff::build_args(env::args().collect()));
+
+                error!("Failed to restart: {:?}", err);
+            },
+            Err(err) => error!("Failed to restart: {:?}", err),
+        }
 
-        error!("Failed to restart: {:?}", err);
+        exit_code = ExitCode::FAILURE;
     }
 
     info!("Exit");
 
-    Ok(())
+    exit_code
 }
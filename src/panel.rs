@@ -9,20 +9,26 @@
 //! See the file LICENSE for details.
 //!
 
+use std::cell::Cell;
 use std::fmt;
+use std::process::{Command, Stdio};
+use std::time::Instant;
 use bitflags::bitflags;
-use log::debug;
+use log::{debug, warn};
 use anyhow::{Context, Result};
 use easy_min_max::max;
 use stdext::function_name;
 use x11rb::connection::Connection;
-use x11rb::protocol::xproto::{ChangeGCAux, ConnectionExt, Drawable, Rectangle};
-use crate::client::ClientFlags;
-use crate::icon::Icon;
-use crate::screen::Screen;
-use crate::style::{CalcSpacing, Style};
+use x11rb::protocol::xproto::{AtomEnum, ChangeGCAux, ConnectionExt, Drawable, PropMode, Rectangle};
+use x11rb::wrapper::ConnectionExt as ConnectionExtWrapper;
+use crate::client::{Client, ClientFlags};
+use crate::geometry::{calc_centered, checked_to_i16, checked_to_u16};
+use crate::icon::{Icon, BITMAP_DEPTH};
+use crate::style::{CalcSpacing, Style, StyleFlags};
 use crate::subtle::Subtle;
+use crate::tagging;
 use crate::tagging::Tagging;
+#[cfg(feature = "tray")]
 use crate::tray::TrayFlags;
 use crate::view::{View, ViewFlags};
 
@@ -60,11 +66,23 @@ bitflags! {
         const MOUSE_OVER = 1 << 13;
         /// Mouse out action
         const MOUSE_OUT = 1 << 14;
+        /// Tray item hosting the tray window
+        const TRAY_HOST = 1 << 15;
     }
 }
 
+/// Text shown for panel items without printable text
+pub(crate) const PLACEHOLDER_TEXT: &str = "?";
+
+/// Max width of a text item in percent of the screen width
+pub(crate) const MAX_TEXT_WIDTH_PERCENT: usize = 50;
+
+/// Ellipsis for capped text, the core fonts just support single bytes
+const ELLIPSIS: &str = "...";
+
+#[allow(clippy::enum_variant_names)]
 pub(crate) enum PanelAction {
-    _MouseOver(i16, i16),
+    MouseOver(i16, i16),
     MouseDown(i16, i16, i8),
     MouseOut,
 }
@@ -83,8 +101,12 @@ pub(crate) struct Panel {
     pub(crate) screen_idx: usize,
     #[cfg(feature = "plugins")]
     pub(crate) plugin_idx: usize,
+    pub(crate) click_command: Option<String>,
     pub(crate) text: Option<String>,
     pub(crate) text_widths: Vec<u16>,
+    pub(crate) urgent_counts: Vec<usize>,
+    pub(crate) icon: Option<Icon>,
+    pub(crate) hovered_view_idx: Cell<Option<usize>>,
 }
 
 impl Panel {
@@ -103,11 +125,16 @@ impl Panel {
     fn pick_style(&self, subtle: &Subtle, style: &mut Style, view_idx: usize, view: &View) {
         style.reset(-1);
 
+        // Hover takes precedence
+        if Some(view_idx) == self.hovered_view_idx.get() {
+            style.inherit(&subtle.views_hover_style);
+        }
+
         // Pick base style
         if let Some(current_screen) = subtle.screens.get(self.screen_idx) {
             if current_screen.view_idx.get() == view_idx as isize {
                 style.inherit(&subtle.views_active_style);
-            } else if subtle.client_tags.get().intersects(view.tags) {
+            } else if subtle.client_tags.get().intersects(view.tags.get()) {
                 style.inherit(&subtle.views_occupied_style);
             }
         }
@@ -115,11 +142,11 @@ impl Panel {
         style.inherit(&subtle.views_style);
 
         // Apply modifier styles
-        if subtle.urgent_tags.get().intersects(view.tags) {
+        if subtle.urgent_tags.get().intersects(view.tags.get()) {
             style.inherit(&subtle.urgent_style);
         }
 
-        if subtle.visible_views.get().intersects(Tagging::from_bits_retain(1 << (view_idx + 1))) {
+        if subtle.visible_views.get().intersects(tagging::view_bit(view_idx)) {
             style.inherit(&subtle.views_visible_style);
         }
     }
@@ -146,26 +173,27 @@ impl Panel {
             return Ok(());
         }
 
-        let margin_width = style.margin.left + style.margin.right;
-        let margin_height: i16 = style.margin.top + style.margin.bottom;
+        let inner_width = checked_to_u16(width as i32 - style.margin.left as i32 - style.margin.right as i32);
+        let inner_height = checked_to_u16(subtle.panel_height as i32 - style.margin.top as i32
+            - style.margin.bottom as i32);
 
         // Filling
         conn.change_gc(subtle.draw_gc, &ChangeGCAux::default()
             .foreground(style.bg as u32))?.check()?;
         conn.poly_fill_rectangle(drawable, subtle.draw_gc, &[Rectangle {
-            x: (self.x as u16 + style.margin.left as u16 + offset_x) as i16,
+            x: checked_to_i16(self.x as i32 + style.margin.left as i32 + offset_x as i32),
             y: style.margin.top,
-            width: width - margin_width as u16,
-            height: subtle.panel_height - margin_height as u16,
+            width: inner_width,
+            height: inner_height,
         }])?.check()?;
 
         // Borders: Top
         conn.change_gc(subtle.draw_gc, &ChangeGCAux::default()
             .foreground(style.top as u32))?.check()?;
         conn.poly_fill_rectangle(drawable, subtle.draw_gc, &[Rectangle {
-            x: (self.x as u16 + style.margin.left as u16 + offset_x) as i16,
+            x: checked_to_i16(self.x as i32 + style.margin.left as i32 + offset_x as i32),
             y: style.margin.top,
-            width: width - margin_width as u16,
+            width: inner_width,
             height: style.border.top as u16,
         }])?.check()?;
 
@@ -173,19 +201,20 @@ impl Panel {
         conn.change_gc(subtle.draw_gc, &ChangeGCAux::default()
             .foreground(style.right as u32))?.check()?;
         conn.poly_fill_rectangle(drawable, subtle.draw_gc, &[Rectangle {
-            x: self.x + width as i16 - style.border.right - style.margin.right + offset_x as i16,
+            x: checked_to_i16(self.x as i32 + width as i32 - style.border.right as i32
+                - style.margin.right as i32 + offset_x as i32),
             y: style.margin.top,
             width: style.border.right as u16,
-            height: subtle.panel_height - margin_height as u16,
+            height: inner_height,
         }])?.check()?;
 
         // Borders: Bottom
         conn.change_gc(subtle.draw_gc, &ChangeGCAux::default()
             .foreground(style.bottom as u32))?.check()?;
         conn.poly_fill_rectangle(drawable, subtle.draw_gc, &[Rectangle {
-            x: self.x + style.margin.left + offset_x as i16,
+            x: checked_to_i16(self.x as i32 + style.margin.left as i32 + offset_x as i32),
             y: subtle.panel_height as i16 - style.border.bottom - style.margin.bottom,
-            width: width - margin_width as u16,
+            width: inner_width,
             height: style.border.bottom as u16,
         }])?.check()?;
 
@@ -193,10 +222,10 @@ impl Panel {
         conn.change_gc(subtle.draw_gc, &ChangeGCAux::default()
             .foreground(style.left as u32))?.check()?;
         conn.poly_fill_rectangle(drawable, subtle.draw_gc, &[Rectangle {
-            x: self.x + style.margin.left + offset_x as i16,
+            x: checked_to_i16(self.x as i32 + style.margin.left as i32 + offset_x as i32),
             y: style.margin.top,
             width: style.border.left as u16,
-            height: subtle.panel_height - margin_height as u16,
+            height: inner_height,
         }])?.check()?;
 
         Ok(())
@@ -227,7 +256,8 @@ impl Panel {
                 .background(style.bg as u32))?.check()?;
 
             conn.image_text8(drawable, subtle.draw_gc,
-                             (self.x as u16 + style.calc_spacing(CalcSpacing::Left) as u16 + offset_x) as i16,
+                             checked_to_i16(self.x as i32 + style.calc_spacing(CalcSpacing::Left) as i32
+                                 + offset_x as i32),
                              font.y as i16 + style.calc_spacing(CalcSpacing::Top),
                              text.as_bytes())?.check()?;
         }
@@ -257,14 +287,73 @@ impl Panel {
             .foreground(style.fg as u32)
             .background(style.bg as u32))?.check()?;
 
-        conn.copy_plane(icon.pixmap, drawable, subtle.draw_gc, 0, 0,
-                        self.x + offset_x as i16 + style.calc_spacing(CalcSpacing::Left),
-                        ((subtle.panel_height - icon.height) / 2) as i16,
-                        icon.width, icon.height, 1)?.check()?;
+        let x = checked_to_i16(self.x as i32 + offset_x as i32
+            + style.calc_spacing(CalcSpacing::Left) as i32);
+        let y = calc_centered(0, subtle.panel_height as i32, icon.height as i32);
+
+        // Bitmaps just use the style colors
+        if BITMAP_DEPTH == icon.depth {
+            conn.copy_plane(icon.pixmap, drawable, subtle.draw_gc, 0, 0, x, y,
+                            icon.width, icon.height, 1)?.check()?;
+        } else {
+            conn.copy_area(icon.pixmap, drawable, subtle.draw_gc, 0, 0, x, y,
+                           icon.width, icon.height)?.check()?;
+        }
 
         Ok(())
     }
 
+    /// Draw separator on panel
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `drawable` - Drawable to use
+    /// * `offset_x` - X offset on panel
+    /// * `style` - Style to use
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    fn draw_separator(&self, subtle: &Subtle, drawable: Drawable, offset_x: u16, style: &Style) -> Result<()> {
+        if let Some(sep_string) = &style.sep_string {
+            self.draw_rect(subtle, drawable, offset_x, style.sep_width, style)?;
+            self.draw_text(subtle, drawable, offset_x, sep_string, style)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get name of the panel item type or plugin
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    ///
+    /// # Returns
+    ///
+    /// The name of the panel item
+    pub(crate) fn get_name(&self, subtle: &Subtle) -> String {
+        if self.flags.intersects(PanelFlags::TITLE) {
+            String::from("title")
+        } else if self.flags.intersects(PanelFlags::VIEWS) {
+            String::from("views")
+        } else if self.flags.intersects(PanelFlags::TRAY) {
+            String::from("tray")
+        } else if self.flags.intersects(PanelFlags::ICON) {
+            String::from("icon")
+        } else if self.flags.intersects(PanelFlags::PLUGIN) {
+            #[cfg(feature = "plugins")]
+            if let Some(plugin) = subtle.plugins.get(self.plugin_idx) {
+                return plugin.name.clone();
+            }
+
+            String::from("plugin")
+        } else {
+            String::from("separator")
+        }
+    }
+
     /// Create a new instance
     ///
     /// # Arguments
@@ -293,15 +382,25 @@ impl Panel {
         match &name[pos_idx..] {
             "tray" => panel.flags = PanelFlags::TRAY | pos_flags,
             "title" => {
-                panel.flags = PanelFlags::TITLE | pos_flags;
+                panel.flags = PanelFlags::TITLE | PanelFlags::MOUSE_DOWN | pos_flags;
                 panel.text_widths.resize(2, Default::default());
             },
             "views" => {
                 panel.flags = PanelFlags::VIEWS | PanelFlags::MOUSE_DOWN | pos_flags;
             },
+            icon_path if icon_path.starts_with("!") => {
+                panel.flags = PanelFlags::ICON | pos_flags;
+                panel.text = Some(icon_path[1..].to_string());
+            },
             plug_name if plug_name.starts_with("$") => {
                 panel.flags = PanelFlags::PLUGIN | pos_flags;
                 panel.text_widths.resize(1, Default::default());
+
+                // Clicks are just handled with a command for this placement
+                if let (_, Some(click_command)) = parse_plugin_item(plug_name) {
+                    panel.flags.insert(PanelFlags::MOUSE_DOWN);
+                    panel.click_command = Some(click_command.to_string());
+                }
             },
             _ => {
                 panel.flags = PanelFlags::SEPARATOR | pos_flags;
@@ -315,6 +414,50 @@ impl Panel {
         Ok(panel)
     }
 
+    /// Measure text and shorten it when it exceeds the allowed share of the screen width
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `text` - Sanitized text
+    /// * `style` - Style to measure with
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either the fitting [`String`] on success or otherwise [`anyhow::Error`]
+    fn fit_text(&mut self, subtle: &Subtle, text: String, style: &Style) -> Result<String> {
+        let conn = subtle.conn.get().context("Failed to get connection")?;
+
+        let Some(font) = style.get_font(subtle) else {
+            return Ok(text);
+        };
+
+        let mut text = text;
+
+        if let Ok((width, _, _)) = font.calc_text_width(conn, &text, false) {
+            self.text_widths[0] = width;
+
+            // Panel side is unknown here, so use the narrower one
+            let panel_width = subtle.screens.get(self.screen_idx)
+                .map(|screen| screen.get_panel_geom(false).1.min(screen.get_panel_geom(true).1));
+
+            if let Some(panel_width) = panel_width
+                && let Some(fitting_len) = calc_fitting_len(text.chars().count(), width, panel_width)
+            {
+                warn!("Panel text too wide: width={}, panel_width={}", width, panel_width);
+
+                text = truncate_text(&text, fitting_len);
+
+                if let Ok((width, _, _)) = font.calc_text_width(conn, &text, false) {
+                    self.text_widths[0] = width.min((panel_width as usize
+                        * MAX_TEXT_WIDTH_PERCENT / 100) as u16);
+                }
+            }
+        }
+
+        Ok(text)
+    }
+
     /// Render the panel
     ///
     /// # Arguments
@@ -328,47 +471,57 @@ impl Panel {
         let conn = subtle.conn.get().context("Failed to get connection")?;
 
         // Handle panel item type
-        if self.flags.intersects(PanelFlags::PLUGIN) {
+        if self.flags.intersects(PanelFlags::ICON) {
+            if let Some(icon) = &self.icon {
+                self.width = icon.width
+                    + subtle.separator_style.calc_spacing(CalcSpacing::Width) as u16;
+            }
+        } else if self.flags.intersects(PanelFlags::PLUGIN) {
+            #[cfg(feature = "plugins")]
             if let Some(plugin) = subtle.plugins.get(self.plugin_idx) {
-                if let Ok(res) = plugin.update() {
-                    if let Some(font) = subtle.views_style.get_font(subtle) {
-                        if let Ok((width, _, _)) = font.calc_text_width(conn, &res, false) {
-                            self.text_widths[0] = width;
+                // Run plugin once per refresh and show placeholder instead of stale text on error
+                let text = subtle.plugin_cache.borrow_mut().get_or_update(self.plugin_idx, || {
+                    match plugin.update() {
+                        Ok(res) => sanitize_text(&res, subtle.panel_text_max_length),
+                        Err(err) => {
+                            warn!("Failed to update plugin `{}`: {}", plugin.name, err);
+
+                            String::from(PLACEHOLDER_TEXT)
                         }
                     }
+                });
 
-                    // Finally update actual length
-                    self.width = self.text_widths[0]
-                        + subtle.views_style.calc_spacing(CalcSpacing::Width) as u16;
+                self.text = Some(self.fit_text(subtle, text, &subtle.views_style)?);
 
-                    self.text = Some(res);
-                }
+                // Finally update actual length
+                self.width = self.text_widths[0]
+                    + subtle.views_style.calc_spacing(CalcSpacing::Width) as u16;
             }
         } else if self.flags.intersects(PanelFlags::SEPARATOR) {
-            if let Some(text) = &self.text {
-                if let Some(font) = subtle.separator_style.get_font(subtle) {
-                    if let Ok((width, _, _)) = font.calc_text_width(conn, text, false) {
-                        self.text_widths[0] = width;
-                    }
-                }
+            if let Some(text) = self.text.take() {
+                let text = sanitize_text(text.as_bytes(), subtle.panel_text_max_length);
+
+                self.text = Some(self.fit_text(subtle, text, &subtle.separator_style)?);
 
                 // Finally update actual length
                 self.width = self.text_widths[0]
                     + subtle.separator_style.calc_spacing(CalcSpacing::Width) as u16;
             }
-        } else if self.flags.intersects(PanelFlags::TRAY) {
-            self.width = subtle.tray_style.calc_spacing(CalcSpacing::Width) as u16;
-            self.flags.remove(PanelFlags::HIDDEN);
+        } else if self.flags.contains(PanelFlags::TRAY | PanelFlags::TRAY_HOST) {
+            let spacing_width = subtle.tray_style.calc_spacing(CalcSpacing::Width) as u16;
 
-            if let Ok(trays) = subtle.trays.try_borrow() && !trays.is_empty() {
-                for tray_idx in 0..trays.len() {
-                    let tray = trays.get(tray_idx).unwrap();
+            self.width = spacing_width;
+            self.flags.remove(PanelFlags::HIDDEN);
 
-                    if tray.flags.intersects(TrayFlags::DEAD) {
+            #[cfg(feature = "tray")]
+            if let Ok(mut trays) = subtle.trays.try_borrow_mut() && !trays.is_empty() {
+                // Place icons next to each other inside of the tray win
+                for tray in trays.iter_mut() {
+                    if tray.flags.intersects(TrayFlags::DEAD | TrayFlags::HIDDEN) {
                         continue;
                     }
 
-                    tray.resize(subtle, self.width as i32)?;
+                    tray.resize(subtle, (self.width - spacing_width) as i32)?;
 
                     self.width += tray.width;
                 }
@@ -377,6 +530,10 @@ impl Panel {
 
                 self.flags.insert(PanelFlags::HIDDEN);
             }
+        } else if self.flags.intersects(PanelFlags::TRAY) {
+            // Just one tray item can host the tray
+            self.width = 0;
+            self.flags.insert(PanelFlags::HIDDEN);
         } else if self.flags.intersects(PanelFlags::TITLE) {
             self.width = 0;
 
@@ -385,7 +542,7 @@ impl Panel {
                 if focus_client.is_alive() && focus_client.is_visible(subtle)
                     && !focus_client.flags.intersects(ClientFlags::TYPE_DESKTOP)
                 {
-                    let mode_str = focus_client.mode_string();
+                    let mode_str = format_modes(subtle, &focus_client);
 
                     // Font offset, panel border and padding
                     if let Some(font) = subtle.title_style.get_font(subtle) {
@@ -416,20 +573,33 @@ impl Panel {
             self.width = 0;
 
             // Resize in case the length has changed
-            if self.text_widths.capacity() != subtle.views.len() {
-                self.text_widths.resize(subtle.views.len(), Default::default());
+            if self.text_widths.capacity() != subtle.views.borrow().len() {
+                self.text_widths.resize(subtle.views.borrow().len(), Default::default());
             }
 
             let mut style = Style::default();
+            let mut visible_views = 0;
 
-            for (view_idx, view) in subtle.views.iter().enumerate() {
+            // Count urgent clients per view
+            let urgent_client_tags: Vec<Tagging> = subtle.clients.borrow().iter()
+                .filter(|client| client.is_alive() && client.flags.contains(ClientFlags::MODE_URGENT))
+                .map(|client| client.tags)
+                .collect();
+
+            self.urgent_counts = calc_urgent_counts(&subtle.views.borrow().iter()
+                .map(|view| view.tags.get())
+                .collect::<Vec<Tagging>>(), &urgent_client_tags);
+
+            for (view_idx, view) in subtle.views.borrow().iter().enumerate() {
                 // Skip dynamic
                 if view.flags.intersects(ViewFlags::MODE_DYNAMIC)
-                    && !subtle.client_tags.get().intersects(view.tags)
+                    && !subtle.client_tags.get().intersects(view.tags.get())
                 {
                     continue;
                 }
 
+                visible_views += 1;
+
                 self.pick_style(subtle, &mut style, view_idx, view);
 
                 // Update view width
@@ -441,8 +611,12 @@ impl Panel {
                     view_width += icon.width;
                 } else {
                     if let Some(font) = style.get_font(subtle) {
-                        // Cache length of view name
-                        if let Ok((width, _, _)) = font.calc_text_width(conn, &view.name, false) {
+                        // Cache length of view name including urgent marker
+                        let view_name = format_view_name(&view.name,
+                            self.urgent_counts.get(view_idx).copied().unwrap_or_default(),
+                            &subtle.urgent_view_marker);
+
+                        if let Ok((width, _, _)) = font.calc_text_width(conn, &view_name, false) {
                             self.text_widths[view_idx] = width;
                         }
 
@@ -461,10 +635,10 @@ impl Panel {
                     + style.calc_spacing(CalcSpacing::Width) as u16;
             }
 
-            // TODO Add width of view separator if any
-            //if subtle.views_style.sep_string.is_some() {
-            //    self.width += (subtle.views.len() - 1) as u16 * subtle.views_style.sep_width as u16;
-            //}
+            // Add width of view separators between views
+            if subtle.views_style.flags.intersects(StyleFlags::SEPARATOR) && 1 < visible_views {
+                self.width += (visible_views - 1) * subtle.views_style.sep_width;
+            }
         }
 
         debug!("{}: panel={}", function_name!(), self);
@@ -484,7 +658,10 @@ impl Panel {
     pub(crate) fn render(&mut self, subtle: &Subtle) -> Result<()> {
         // Handle panel item type
         if self.flags.intersects(PanelFlags::ICON) {
-            todo!(); // TODO icon
+            if let Some(icon) = &self.icon {
+                self.draw_rect(subtle, subtle.panel_double_buffer, 0, self.width, &subtle.separator_style)?;
+                self.draw_icon(subtle, icon, subtle.panel_double_buffer, 0, &subtle.separator_style)?;
+            }
         } else if self.flags.intersects(PanelFlags::PLUGIN) {
             self.draw_rect(subtle, subtle.panel_double_buffer,0, self.width, &subtle.views_style)?;
 
@@ -513,7 +690,7 @@ impl Panel {
                                    self.width, &subtle.title_style)?;
 
                     // Draw modes and title
-                    let mode_str= focus_client.mode_string();
+                    let mode_str = format_modes(subtle, &focus_client);
 
                     self.draw_text(subtle, subtle.panel_double_buffer, 0,
                                    &mode_str, &subtle.title_style)?;
@@ -530,16 +707,26 @@ impl Panel {
         } else if self.flags.intersects(PanelFlags::VIEWS) {
             let mut style = Style::default();
             let mut offset_x = 0;
+            let mut is_first = true;
 
-            for (view_idx, view) in subtle.views.iter().enumerate() {
+            for (view_idx, view) in subtle.views.borrow().iter().enumerate() {
 
                 // Skip dynamic
                 if view.flags.intersects(ViewFlags::MODE_DYNAMIC)
-                    && !subtle.client_tags.get().intersects(view.tags)
+                    && !subtle.client_tags.get().intersects(view.tags.get())
                 {
                     continue;
                 }
 
+                // Draw view separator between views
+                if !is_first && subtle.views_style.flags.intersects(StyleFlags::SEPARATOR) {
+                    self.draw_separator(subtle, subtle.panel_double_buffer, offset_x, &subtle.views_style)?;
+
+                    offset_x += subtle.views_style.sep_width;
+                }
+
+                is_first = false;
+
                 self.pick_style(subtle, &mut style, view_idx, view);
 
                 // Calculate view width
@@ -584,18 +771,15 @@ impl Panel {
                             + style.calc_spacing(CalcSpacing::Left) as u16;
                     }
 
+                    let view_name = format_view_name(&view.name,
+                        self.urgent_counts.get(view_idx).copied().unwrap_or_default(),
+                        &subtle.urgent_view_marker);
+
                     self.draw_text(subtle, subtle.panel_double_buffer,
-                                   offset_x + icon_offset_x, &view.name, &style)?;
+                                   offset_x + icon_offset_x, &view_name, &style)?;
                 }
 
                 offset_x += max!(style.min_width as u16, view_width);
-
-                // TODO Draw view separator if any
-                //if subtle.views_style.sep_string.is_some() && view_idx < subtle.views.len() - 1 {
-                //    self.draw_separator(subtle, subtle.panel_double_buffer, offset_x, &style)?;
-                //
-                //    offset_x += subtle.views_style.sep_width as u16;
-                //}
             }
         }
 
@@ -604,6 +788,76 @@ impl Panel {
         Ok(())
     }
 
+    /// Check whether x is inside of the panel
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - X coordinate on panel
+    ///
+    /// # Returns
+    ///
+    /// Either [`true`] if x is inside of the panel or otherwise [`false`]
+    fn contains_x(&self, x: i16) -> bool {
+        x >= self.x && x as i32 <= self.x as i32 + self.width as i32
+    }
+
+    /// Find view at position of views panel
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `x` - X coordinate on panel
+    ///
+    /// # Returns
+    ///
+    /// Either [`Some`] with index of the view or otherwise [`None`]
+    fn find_view_at(&self, subtle: &Subtle, x: i16) -> Option<usize> {
+        let mut offset_x = self.x;
+        let mut is_first = true;
+
+        let mut style = Style::default();
+
+        for (view_idx, view) in subtle.views.borrow().iter().enumerate() {
+            // Skip dynamic views
+            if view.flags.intersects(ViewFlags::MODE_DYNAMIC)
+                && !subtle.client_tags.get().intersects(view.tags.get())
+            {
+                continue;
+            }
+
+            // Skip view separator between views
+            if !is_first && subtle.views_style.flags.intersects(StyleFlags::SEPARATOR) {
+                offset_x += subtle.views_style.sep_width as i16;
+            }
+
+            is_first = false;
+
+            self.pick_style(subtle, &mut style, view_idx, view);
+
+            let mut view_width = style.calc_spacing(CalcSpacing::Width);
+
+            // Add space between icon and text
+            if view.flags.intersects(ViewFlags::MODE_ICON)
+                && let Some(icon) = view.icon.as_ref()
+            {
+                view_width += icon.width as i16 + style.calc_spacing(CalcSpacing::Left);
+            }
+
+            if !view.flags.intersects(ViewFlags::MODE_ICON_ONLY) {
+                view_width += self.text_widths[view_idx] as i16;
+            }
+
+            // Check if x is in view rect
+            if x >= offset_x && x <= offset_x + view_width {
+                return Some(view_idx);
+            }
+
+            offset_x += view_width;
+        }
+
+        None
+    }
+
     /// Handle the panel action
     ///
     /// # Arguments
@@ -616,57 +870,62 @@ impl Panel {
     ///
     /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
     pub(crate) fn handle_action(&self, subtle: &Subtle, action: &PanelAction, _is_bottom: bool) -> Result<()> {
-        if let &PanelAction::MouseDown(x, _y, _button) = action {
+        if let &PanelAction::MouseDown(x, _y, button) = action {
 
             // Check if x is in boundry box of panel
-            if x >= self.x && x <= self.x + self.width as i16 {
+            if self.contains_x(x) {
 
                 // Handle panel type
                 if self.flags.intersects(PanelFlags::VIEWS) {
-                    let mut offset_x = self.x;
-
-                    let mut style = Style::default();
-
-                    for (view_idx, view) in subtle.views.iter().enumerate() {
-                        // Skip dynamic views
-                        if view.flags.intersects(ViewFlags::MODE_DYNAMIC)
-                            && !subtle.client_tags.get().intersects(view.tags)
-                        {
-                            continue;
-                        }
-
-                        self.pick_style(subtle, &mut style, view_idx, view);
-
-                        let mut view_width = style.calc_spacing(CalcSpacing::Width);
+                    if let Some(view_idx) = self.find_view_at(subtle, x)
+                        && let Some(view) = subtle.views.borrow().get(view_idx)
+                    {
+                        view.focus(subtle, self.screen_idx, true, false)?;
+                    }
+                } else if self.flags.intersects(PanelFlags::TITLE) {
+                    let focus_screen_idx = subtle.find_focus_screen_idx();
 
-                        // Add space between icon and text
-                        if view.flags.intersects(ViewFlags::MODE_ICON)
-                            && let Some(icon) = view.icon.as_ref()
+                    if let Some(mut focus_client) = subtle.find_focus_client_mut() {
+                        if !focus_client.is_alive()
+                            || focus_client.flags.intersects(ClientFlags::TYPE_DESKTOP)
                         {
-                            view_width += icon.width as i16 + style.calc_spacing(CalcSpacing::Left);
+                            return Ok(());
                         }
 
-                        if !view.flags.intersects(ViewFlags::MODE_ICON_ONLY) {
-                            view_width += self.text_widths[view_idx] as i16;
+                        // Left toggles float, middle closes and right toggles stick
+                        let mut mode_flags = match button {
+                            1 => ClientFlags::MODE_FLOAT,
+                            3 => ClientFlags::MODE_STICK,
+                            _ => ClientFlags::empty(),
+                        };
+
+                        if 2 == button {
+                            focus_client.close(subtle)?;
+                        } else if !mode_flags.is_empty() {
+                            focus_client.toggle(subtle, &mut mode_flags, true, focus_screen_idx)?;
                         }
-
-
-                        // Check if x is in view rect
-                        if x >= offset_x && x <= offset_x + view_width {
-                            view.focus(subtle, self.screen_idx, true, false)?;
-
-                            break;
-                        }
-
-                        // TODO Add view separator width if any
-                        //if subtle.views_style.sep_string.is_some() {
-                        //    view_width += subtle.views_style.sep_width;
-                        //}
-
-                        offset_x += view_width;
+                    }
+                } else if self.flags.intersects(PanelFlags::PLUGIN) {
+                    if let Some(click_command) = &self.click_command {
+                        debug!("{}: command={}", function_name!(), click_command);
+
+                        Command::new(click_command)
+                            .stdout(Stdio::null())
+                            .stderr(Stdio::null())
+                            .spawn()?;
                     }
                 }
             }
+        } else if let &PanelAction::MouseOver(x, _y) = action {
+            if self.flags.intersects(PanelFlags::VIEWS) {
+                self.hovered_view_idx.set(if self.contains_x(x) {
+                    self.find_view_at(subtle, x)
+                } else {
+                    None
+                });
+            }
+        } else if let PanelAction::MouseOut = action {
+            self.hovered_view_idx.set(None);
         }
 
         debug!("{}: panel={}", function_name!(), self);
@@ -687,13 +946,13 @@ impl fmt::Display for Panel {
 /// # Arguments
 ///
 /// * `subtle` - Global state object
-/// * `screen` - Screen for drawing
+/// * `panel_width` - Width of the panel to draw
 /// * `style` - Style for clearing
 ///
 /// # Returns
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-fn clear_double_buffer(subtle: &Subtle, screen: &Screen, style: &Style) -> Result<()> {
+fn clear_double_buffer(subtle: &Subtle, panel_width: u16, style: &Style) -> Result<()> {
     let conn = subtle.conn.get().context("Failed to get connection")?;
 
     conn.change_gc(subtle.draw_gc, &ChangeGCAux::default().foreground(style.bg as u32))?.check()?;
@@ -702,7 +961,7 @@ fn clear_double_buffer(subtle: &Subtle, screen: &Screen, style: &Style) -> Resul
     conn.poly_fill_rectangle(subtle.panel_double_buffer, subtle.draw_gc, &[Rectangle {
         x: 0,
         y: 0,
-        width: screen.base.width,
+        width: panel_width,
         height: subtle.panel_height
     }])?.check()?;
 
@@ -725,8 +984,12 @@ pub(crate) fn resize_double_buffer(subtle: &Subtle) -> Result<()> {
     let mut width = 0;
 
     for screen in subtle.screens.iter() {
-        if screen.base.width > width {
-            width = screen.base.width;
+        for is_bottom in [false, true] {
+            let (_, panel_width) = screen.get_panel_geom(is_bottom);
+
+            if panel_width > width {
+                width = panel_width;
+            }
         }
     }
 
@@ -743,6 +1006,212 @@ pub(crate) fn resize_double_buffer(subtle: &Subtle) -> Result<()> {
     Ok(())
 }
 
+/// Format modes of the client and a pending count prefix
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `client` - Client to format
+///
+/// # Returns
+///
+/// The mode string prefixed with the pending count if any
+pub(crate) fn format_modes(subtle: &Subtle, client: &Client) -> String {
+    match subtle.count_prefix.get().get(Instant::now()) {
+        Some(count) => format!("[{}]{}", count, client.mode_string()),
+        None => client.mode_string(),
+    }
+}
+
+/// Serialize layout of panel items
+///
+/// Each visible item is encoded as `x+width#name`, where name is either the item type or the
+/// name of the plugin, and items are separated by a null byte like the other list properties.
+/// Items outside of the panel are skipped and overflowing ones are clipped to the panel width.
+///
+/// # Arguments
+///
+/// * `items` - Name, x position and width of each panel item
+/// * `panel_width` - Width of the panel
+///
+/// # Returns
+///
+/// The serialized layout
+pub(crate) fn serialize_layout(items: &[(String, i16, u16)], panel_width: u16) -> String {
+    items.iter()
+        .filter_map(|(name, x, width)| {
+            let start = (*x as i32).max(0);
+            let end = (*x as i32 + *width as i32).min(panel_width as i32);
+
+            if start < end {
+                Some(format!("{}+{}#{}", start, end - start, name))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\0")
+}
+
+/// Calculate start offsets of the default, center and right positioned items
+///
+/// # Arguments
+///
+/// * `panel_width` - Width of the panel
+/// * `left_width` - Total width of left positioned items
+/// * `center_width` - Total width of center positioned items
+/// * `right_width` - Total width of right positioned items
+///
+/// # Returns
+///
+/// A tuple with the default, center and right start offset
+pub(crate) fn calc_start_offsets(panel_width: u16, left_width: u16, center_width: u16,
+                                 right_width: u16) -> (i16, i16, i16)
+{
+    (checked_to_i16(left_width as i32),
+     calc_centered(0, panel_width as i32, center_width as i32),
+     checked_to_i16(panel_width as i32 - right_width as i32))
+}
+
+/// Split a plugin panel item into the plugin and an optional click command
+///
+/// # Arguments
+///
+/// * `name` - Plugin item without position like `$cpu:click=htop`
+///
+/// # Returns
+///
+/// A tuple with the plugin item and either [`Some`] click command or [`None`]
+pub(crate) fn parse_plugin_item(name: &str) -> (&str, Option<&str>) {
+    match name.split_once(":click=") {
+        Some((plugin_name, click_command)) if !click_command.trim().is_empty() =>
+            (plugin_name, Some(click_command.trim())),
+        Some((plugin_name, _)) => (plugin_name, None),
+        None => (name, None),
+    }
+}
+
+/// Sanitize panel text from plugins or config before layout
+///
+/// Invalid UTF-8 sequences are replaced, control characters are stripped, whitespace runs are
+/// collapsed and the result is capped at the given number of characters with an ellipsis.
+///
+/// # Arguments
+///
+/// * `data` - Raw text
+/// * `max_len` - Max number of characters
+///
+/// # Returns
+///
+/// The sanitized text or [`PLACEHOLDER_TEXT`] when nothing printable is left
+pub(crate) fn sanitize_text(data: &[u8], max_len: usize) -> String {
+    let mut text = String::with_capacity(data.len().min(max_len));
+    let mut is_space = false;
+
+    for ch in String::from_utf8_lossy(data).chars() {
+        if ch.is_whitespace() {
+            is_space = true;
+        } else if !ch.is_control() {
+            if is_space && !text.is_empty() {
+                text.push(' ');
+            }
+
+            text.push(if char::REPLACEMENT_CHARACTER == ch { '?' } else { ch });
+            is_space = false;
+        }
+    }
+
+    if text.is_empty() && !data.is_empty() {
+        return String::from(PLACEHOLDER_TEXT);
+    }
+
+    truncate_text(&text, max_len)
+}
+
+/// Cap text at the given number of characters and append an ellipsis on overflow
+///
+/// # Arguments
+///
+/// * `text` - Text to cap
+/// * `max_len` - Max number of characters
+///
+/// # Returns
+///
+/// Either the unchanged or the shortened text
+pub(crate) fn truncate_text(text: &str, max_len: usize) -> String {
+    if text.chars().count() <= max_len {
+        return text.to_string();
+    }
+
+    if max_len <= ELLIPSIS.len() {
+        return text.chars().take(max_len).collect();
+    }
+
+    let mut truncated: String = text.chars().take(max_len - ELLIPSIS.len()).collect();
+
+    truncated.push_str(ELLIPSIS);
+
+    truncated
+}
+
+/// Calculate max number of characters that fit into the allowed width
+///
+/// # Arguments
+///
+/// * `len` - Number of characters of the text
+/// * `width` - Measured width of the text
+/// * `screen_width` - Width of the screen
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] fitting length when the text is too wide or otherwise [`None`]
+pub(crate) fn calc_fitting_len(len: usize, width: u16, screen_width: u16) -> Option<usize> {
+    let max_width = screen_width as usize * MAX_TEXT_WIDTH_PERCENT / 100;
+
+    if width as usize <= max_width || 0 == width {
+        return None;
+    }
+
+    Some(len * max_width / width as usize)
+}
+
+/// Count urgent clients per view
+///
+/// # Arguments
+///
+/// * `view_tags` - Tags of every view
+/// * `urgent_client_tags` - Tags of every urgent client
+///
+/// # Returns
+///
+/// A [`Vec`] with the number of urgent clients of every view
+pub(crate) fn calc_urgent_counts(view_tags: &[Tagging], urgent_client_tags: &[Tagging]) -> Vec<usize> {
+    view_tags.iter()
+        .map(|tags| urgent_client_tags.iter()
+            .filter(|client_tags| client_tags.intersects(*tags))
+            .count())
+        .collect()
+}
+
+/// Append urgent marker to view name when the view has urgent clients
+///
+/// # Arguments
+///
+/// * `name` - Name of the view
+/// * `urgent_count` - Number of urgent clients on the view
+/// * `marker` - Marker format, `%d` is replaced with the count
+///
+/// # Returns
+///
+/// The view name with marker if necessary
+pub(crate) fn format_view_name(name: &str, urgent_count: usize, marker: &str) -> String {
+    if 0 == urgent_count {
+        return name.to_string();
+    }
+
+    format!("{}{}", name, marker.replace("%d", &urgent_count.to_string()))
+}
+
 /// Update all panels
 ///
 /// # Arguments
@@ -753,6 +1222,11 @@ pub(crate) fn resize_double_buffer(subtle: &Subtle) -> Result<()> {
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
 pub(crate) fn update(subtle: &Subtle) -> Result<()> {
+    let mut has_tray_host = false;
+
+    // Plugins shared by several panel items just run once per refresh
+    #[cfg(feature = "plugins")]
+    subtle.plugin_cache.borrow_mut().next_generation();
 
     // Update screens
     for screen in subtle.screens.iter() {
@@ -774,13 +1248,18 @@ pub(crate) fn update(subtle: &Subtle) -> Result<()> {
 
                 mut_panel.update(subtle)?;
 
+                has_tray_host |= mut_panel.flags.contains(PanelFlags::TRAY | PanelFlags::TRAY_HOST);
+
                 // Collect width based on position
                 if mut_panel.flags.intersects(PanelFlags::LEFT_POS) {
-                    left_pos[selected_panel_num].width += mut_panel.width;
+                    left_pos[selected_panel_num].width = left_pos[selected_panel_num].width
+                        .saturating_add(mut_panel.width);
                 } else if mut_panel.flags.intersects(PanelFlags::CENTER_POS) {
-                    center_pos[selected_panel_num].width += mut_panel.width;
+                    center_pos[selected_panel_num].width = center_pos[selected_panel_num].width
+                        .saturating_add(mut_panel.width);
                 } else if mut_panel.flags.intersects(PanelFlags::RIGHT_POS) {
-                    right_pos[selected_panel_num].width += mut_panel.width;
+                    right_pos[selected_panel_num].width = right_pos[selected_panel_num].width
+                        .saturating_add(mut_panel.width);
                 }
             }
         }
@@ -788,17 +1267,18 @@ pub(crate) fn update(subtle: &Subtle) -> Result<()> {
         // Reset values before next pass
         selected_panel_num = 0;
 
-        // Calculate start positions
-        default_pos[0].offset_x = left_pos[0].width as i16;
-        default_pos[1].offset_x = left_pos[1].width as i16;
-
-        center_pos[0].offset_x = ((screen.base.width - center_pos[0].width) / 2) as i16;
-        center_pos[1].offset_x = ((screen.base.width - center_pos[1].width) / 2) as i16;
+        let panel_widths = [screen.get_panel_geom(false).1, screen.get_panel_geom(true).1];
 
-        right_pos[0].offset_x = (screen.base.width - right_pos[0].width) as i16;
-        right_pos[1].offset_x = (screen.base.width - right_pos[1].width) as i16;
+        // Calculate start positions
+        for pos_idx in 0..2 {
+            (default_pos[pos_idx].offset_x, center_pos[pos_idx].offset_x, right_pos[pos_idx].offset_x)
+                = calc_start_offsets(panel_widths[pos_idx], left_pos[pos_idx].width,
+                                     center_pos[pos_idx].width, right_pos[pos_idx].width);
+        }
 
         // Pass 2: Move and resize items
+        let mut layouts: [Vec<(String, i16, u16)>; 2] = Default::default();
+
         for panel_idx in 0..screen.panels.len() {
             if let Some(mut mut_panel) = screen.panels.borrow_mut(panel_idx) {
 
@@ -816,25 +1296,29 @@ pub(crate) fn update(subtle: &Subtle) -> Result<()> {
                 if mut_panel.flags.intersects(PanelFlags::LEFT_POS) {
                     mut_panel.x = left_pos[selected_panel_num].offset_x;
 
-                    left_pos[selected_panel_num].offset_x += mut_panel.width as i16;
+                    left_pos[selected_panel_num].offset_x = checked_to_i16(
+                        left_pos[selected_panel_num].offset_x as i32 + mut_panel.width as i32);
                 } else if mut_panel.flags.intersects(PanelFlags::CENTER_POS) {
                     mut_panel.x = center_pos[selected_panel_num].offset_x;
 
-                    center_pos[selected_panel_num].offset_x += mut_panel.width as i16;
+                    center_pos[selected_panel_num].offset_x = checked_to_i16(
+                        center_pos[selected_panel_num].offset_x as i32 + mut_panel.width as i32);
                 } else if mut_panel.flags.intersects(PanelFlags::RIGHT_POS) {
                     mut_panel.x = right_pos[selected_panel_num].offset_x;
 
-                    right_pos[selected_panel_num].offset_x += mut_panel.width as i16;
+                    right_pos[selected_panel_num].offset_x = checked_to_i16(
+                        right_pos[selected_panel_num].offset_x as i32 + mut_panel.width as i32);
                 } else {
                     mut_panel.x = default_pos[selected_panel_num].offset_x;
 
-                    default_pos[selected_panel_num].offset_x += mut_panel.width as i16;
+                    default_pos[selected_panel_num].offset_x = checked_to_i16(
+                        default_pos[selected_panel_num].offset_x as i32 + mut_panel.width as i32);
                 };
 
-                // Special aftercare
-                if mut_panel.flags.intersects(PanelFlags::TRAY) {
+                layouts[selected_panel_num].push((mut_panel.get_name(subtle), mut_panel.x, mut_panel.width));
 
-                    // FIXME: Last one wins if used multiple times
+                // Special aftercare
+                if mut_panel.flags.contains(PanelFlags::TRAY | PanelFlags::TRAY_HOST) {
                     let selected_panel_win = if 0 == selected_panel_num {
                         screen.top_panel_win
                     } else {
@@ -846,6 +1330,22 @@ pub(crate) fn update(subtle: &Subtle) -> Result<()> {
                 }
             }
         }
+
+        // Publish final layout for external tools
+        let conn = subtle.conn.get().context("Failed to get connection")?;
+        let atoms = subtle.atoms.get().context("Failed to get atoms")?;
+
+        for ((panel_win, layout), panel_width) in [screen.top_panel_win, screen.bottom_panel_win].iter()
+            .zip(layouts.iter()).zip(panel_widths)
+        {
+            conn.change_property8(PropMode::REPLACE, *panel_win, atoms.SUBTLE_PANEL_LAYOUT,
+                                  AtomEnum::STRING, serialize_layout(layout, panel_width).as_bytes())?.check()?;
+        }
+    }
+
+    // Take tray back once no tray item is left
+    if !has_tray_host {
+        subtle.release_tray_win()?;
     }
 
     debug!("{}", function_name!());
@@ -868,8 +1368,9 @@ pub(crate) fn render(subtle: &Subtle) -> Result<()> {
     // Update screens
     for screen in subtle.screens.iter() {
         let mut panel_win = screen.top_panel_win;
+        let (_, mut panel_width) = screen.get_panel_geom(false);
 
-        clear_double_buffer(subtle, screen, &subtle.top_panel_style)?;
+        clear_double_buffer(subtle, panel_width, &subtle.top_panel_style)?;
 
         // Render panel items
         for (panel_idx, panel) in screen.panels.iter().enumerate() {
@@ -878,10 +1379,12 @@ pub(crate) fn render(subtle: &Subtle) -> Result<()> {
             if panel.flags.intersects(PanelFlags::BOTTOM_START_MARKER) {
                 conn.copy_area(subtle.panel_double_buffer, panel_win, subtle.draw_gc,
                                0, 0, 0, 0,
-                               screen.base.width, subtle.panel_height
+                               panel_width, subtle.panel_height
                 )?.check()?;
 
-                clear_double_buffer(subtle, screen, &subtle.bottom_panel_style)?;
+                (_, panel_width) = screen.get_panel_geom(true);
+
+                clear_double_buffer(subtle, panel_width, &subtle.bottom_panel_style)?;
 
                 panel_win = screen.bottom_panel_win;
             }
@@ -900,7 +1403,7 @@ pub(crate) fn render(subtle: &Subtle) -> Result<()> {
 
         conn.copy_area(subtle.panel_double_buffer, panel_win, subtle.draw_gc,
                        0, 0, 0, 0,
-                       screen.base.width, subtle.panel_height)?.check()?;
+                       panel_width, subtle.panel_height)?.check()?;
     }
 
     conn.flush()?;
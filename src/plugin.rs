@@ -13,7 +13,7 @@ use std::fmt;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use extism::{host_fn, Manifest, UserData, Wasm, PTR};
 use anyhow::{Context, Result};
 use chrono::{DateTime, Local};
@@ -23,8 +23,9 @@ use log::{debug, info};
 use stdext::function_name;
 use itertools::Itertools;
 use regex::Regex;
-use lazy_static::lazy_static;
 use crate::config::{Config, MixedConfigVal};
+use crate::error;
+use crate::error::{Classify, InitError};
 use crate::subtle::Subtle;
 
 #[derive(Debug)]
@@ -37,6 +38,15 @@ pub(crate) struct Plugin {
     pub(crate) plugin: Rc<RefCell<extism::Plugin>>,
 }
 
+/// Results of the plugins shared by all panels of a refresh cycle
+#[derive(Default, Debug)]
+pub(crate) struct PluginCache {
+    /// Current refresh cycle
+    pub(crate) generation: u64,
+    /// Last result and its refresh cycle per plugin index
+    entries: HashMap<usize, (u64, String)>,
+}
+
 #[derive(Builder)]
 #[builder(name = "PluginBuilder", build_fn(skip))]
 pub(crate) struct PluginBuilderSeed {
@@ -48,61 +58,186 @@ pub(crate) struct PluginBuilderSeed {
     pub(crate) interval: i32,
     /// Plugin config
     pub(crate) config: HashMap<String, String>,
+    /// Host functions of the plugin
+    registry: HostRegistry,
 }
 
-/// Lazy global for all instances of this plugin
+/// Per-core cpu values
 type CpuUserData = Vec<(i32, i32, i32)>;
 
-lazy_static! {
-    static ref CPU_USER_DATA: UserData<CpuUserData> = UserData::new(CpuUserData::new());
+/// Provider of the data host functions pass to plugins
+pub(crate) trait HostProvider {
+    /// Get current local time
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Format string of the time
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`String`] on success or otherwise [`anyhow::Error`]
+    fn get_formatted_time(&mut self, format: &str) -> Result<String>;
+
+    /// Get total, available and free memory
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`String`] on success or otherwise [`anyhow::Error`]
+    fn get_memory(&mut self) -> Result<String>;
+
+    /// Get full and current charge of a battery
+    ///
+    /// # Arguments
+    ///
+    /// * `battery_slot` - Slot of the battery
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`String`] on success or otherwise [`anyhow::Error`]
+    fn get_battery(&mut self, battery_slot: &str) -> Result<String>;
+
+    /// Sample cpu values
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`bool`] on success or otherwise [`anyhow::Error`]
+    fn get_cpu(&mut self) -> Result<bool>;
+}
+
+/// Provider that reads the data from the running system
+#[derive(Default)]
+pub(crate) struct SystemProvider {
+    /// Last sampled cpu values
+    cpu_data: CpuUserData,
 }
 
-host_fn!(get_formatted_time(_user_data: (); format: String) -> String {
-    let current_local: DateTime<Local> = Local::now();
+impl HostProvider for SystemProvider {
+    fn get_formatted_time(&mut self, format: &str) -> Result<String> {
+        let current_local: DateTime<Local> = Local::now();
 
-    Ok(current_local.format(&format).to_string())
-});
+        Ok(current_local.format(format).to_string())
+    }
+
+    fn get_memory(&mut self) -> Result<String> {
+        let (mem_available, mem_total, mem_free) = std::fs::read_to_string("/proc/meminfo")?
+            .lines()
+            .filter(|line| line.starts_with("MemAvailable") || line.starts_with("MemTotal") || line.starts_with("MemFree"))
+            .map(|line| line.split_whitespace().nth(1).and_then(|v| v.parse::<i32>().ok()))
+            .collect_tuple()
+            .context("Cannot read `/proc/meminfo`")?;
+
+        Ok(format!("{} {} {}", mem_total.unwrap_or(1), mem_available.unwrap_or(0), mem_free.unwrap_or(0)))
+    }
+
+    fn get_battery(&mut self, battery_slot: &str) -> Result<String> {
+        let charge_full = std::fs::read_to_string(
+            format!("/sys/class/power_supply/BAT{}/charge_full", battery_slot))?;
+        let charge_now = std::fs::read_to_string(
+            format!("/sys/class/power_supply/BAT{}/charge_now", battery_slot))?;
+
+        Ok(format!("{} {}", charge_full.trim(), charge_now.trim()))
+    }
+
+    fn get_cpu(&mut self) -> Result<bool> {
+        self.cpu_data.clear();
+
+        let regex = Regex::new(r"cpu(\d+) (\d+) (\d+) (\d+)")?;
 
-host_fn!(get_memory(_user_data: ()) -> String {
-    let (mem_available, mem_total, mem_free) = std::fs::read_to_string("/proc/meminfo")?
-        .lines()
-        .filter(|line| line.starts_with("MemAvailable") || line.starts_with("MemTotal") || line.starts_with("MemFree"))
-        .map(|line| line.split_whitespace().nth(1).and_then(|v| v.parse::<i32>().ok()))
-        .collect_tuple()
-        .context("Cannot read `/proc/meminfo`")?;
+        for line in std::fs::read_to_string("/proc/stat")?.lines() {
+            if let Some(cap) = regex.captures(line) {
+                let cpu_user = cap.get(1).map_or(0, |v| v.as_str().parse::<i32>().unwrap_or(0));
+                let cpu_nice = cap.get(2).map_or(0, |v| v.as_str().parse::<i32>().unwrap_or(0));
+                let cpu_system = cap.get(3).map_or(0, |v| v.as_str().parse::<i32>().unwrap_or(0));
 
-   Ok(format!("{} {} {}", mem_total.unwrap_or(1), mem_available.unwrap_or(0), mem_free.unwrap_or(0)))
+                self.cpu_data.push((cpu_user, cpu_nice, cpu_system));
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Provider shared by all host functions of a registry
+type HostUserData = Box<dyn HostProvider>;
+
+host_fn!(get_formatted_time(user_data: HostUserData; format: String) -> String {
+    let provider = user_data.get()?;
+    let mut provider = provider.lock().unwrap();
+
+    provider.get_formatted_time(&format)
 });
 
-host_fn!(get_battery(_user_data: (); battery_slot: String) -> String {
-    let charge_full = std::fs::read_to_string(
-        format!("/sys/class/power_supply/BAT{}/charge_full", battery_slot))?;
-    let charge_now = std::fs::read_to_string(
-        format!("/sys/class/power_supply/BAT{}/charge_now", battery_slot))?;
+host_fn!(get_memory(user_data: HostUserData;) -> String {
+    let provider = user_data.get()?;
+    let mut provider = provider.lock().unwrap();
 
-    Ok(format!("{} {}", charge_full.trim(), charge_now.trim()))
+    provider.get_memory()
 });
 
-host_fn!(get_cpu(user_data: CpuUserData;) -> bool {
-    let plug_data = user_data.get()?;
-    let mut plug_data = plug_data.lock().unwrap();
+host_fn!(get_battery(user_data: HostUserData; battery_slot: String) -> String {
+    let provider = user_data.get()?;
+    let mut provider = provider.lock().unwrap();
 
-    plug_data.clear();
+    provider.get_battery(&battery_slot)
+});
 
-    let regex = Regex::new(r"cpu(\d+) (\d+) (\d+) (\d+)")?;
+host_fn!(get_cpu(user_data: HostUserData;) -> bool {
+    let provider = user_data.get()?;
+    let mut provider = provider.lock().unwrap();
 
-    for line in std::fs::read_to_string("/proc/stat")?.lines() {
-        if let Some(cap) = regex.captures(line) {
-            let cpu_user = cap.get(1).map_or(0, |v| v.as_str().parse::<i32>().unwrap_or(0));
-            let cpu_nice = cap.get(2).map_or(0, |v| v.as_str().parse::<i32>().unwrap_or(0));
-            let cpu_system = cap.get(3).map_or(0, |v| v.as_str().parse::<i32>().unwrap_or(0));
+    provider.get_cpu()
+});
 
-            plug_data.push((cpu_user, cpu_nice, cpu_system));
+/// Registry of the host functions available to plugins
+#[derive(Clone)]
+pub(crate) struct HostRegistry {
+    /// Provider of the host functions
+    user_data: UserData<HostUserData>,
+}
+
+impl HostRegistry {
+    /// Create a new instance
+    ///
+    /// # Arguments
+    ///
+    /// * `provider` - Provider of the host function data
+    ///
+    /// # Returns
+    ///
+    /// A new [`HostRegistry`]
+    pub(crate) fn new(provider: impl HostProvider + 'static) -> Self {
+        Self {
+            user_data: UserData::new(Box::new(provider)),
         }
     }
 
-   Ok(true)
-});
+    /// Register all host functions at the extism builder
+    ///
+    /// # Arguments
+    ///
+    /// * `builder` - Extism plugin builder
+    ///
+    /// # Returns
+    ///
+    /// The extism builder with the host functions
+    pub(crate) fn register<'a>(&self, builder: extism::PluginBuilder<'a>) -> extism::PluginBuilder<'a> {
+        builder
+            .with_function("get_formatted_time", [PTR], [PTR],
+                           self.user_data.clone(), get_formatted_time)
+            .with_function("get_memory", [PTR], [PTR],
+                           self.user_data.clone(), get_memory)
+            .with_function("get_battery", [PTR], [PTR],
+                           self.user_data.clone(), get_battery)
+            .with_function("get_cpu", [PTR], [I32],
+                           self.user_data.clone(), get_cpu)
+    }
+}
+
+impl Default for HostRegistry {
+    fn default() -> Self {
+        Self::new(SystemProvider::default())
+    }
+}
 
 impl PluginBuilder {
 
@@ -120,6 +255,7 @@ impl PluginBuilder {
         let url = self.url.clone().context("Url not set")?;
 
         let config = self.config.take().unwrap_or_default();
+        let registry = self.registry.take().unwrap_or_default();
 
         // Load wasm plugin
         let wasm = Wasm::file(url);
@@ -127,23 +263,15 @@ impl PluginBuilder {
             .with_timeout(Duration::from_secs(5))
             .with_config(config.into_iter());
 
-        let plugin = extism::PluginBuilder::new(&manifest)
-            .with_wasi(true)
-            .with_function("get_formatted_time", [PTR], [PTR],
-                           UserData::default(), get_formatted_time)
-            .with_function("get_memory", [PTR], [PTR],
-                           UserData::default(), get_memory)
-            .with_function("get_battery", [PTR], [PTR],
-                           UserData::default(), get_battery)
-            .with_function("get_cpu", [PTR], [I32],
-                           CPU_USER_DATA.clone(), get_cpu)
+        let plugin = registry.register(extism::PluginBuilder::new(&manifest)
+            .with_wasi(true))
             .build()?;
 
         debug!("{}", function_name!());
 
         Ok(Plugin {
             name: self.name.clone().context("Name not set")?,
-            interval: self.interval.unwrap(),
+            interval: self.interval.context("Interval not set")?,
             plugin: Rc::new(RefCell::new(plugin)),
         })
     }
@@ -155,14 +283,72 @@ impl Plugin {
     ///
     /// # Returns
     ///
-    /// A [`Result`] with either [`String`] on success or otherwise [`anyhow::Error`]
-    pub(crate) fn update(&self) -> Result<String> {
-       let res = self.plugin.borrow_mut().call("run", "")?;
+    /// A [`Result`] with either raw [`Vec`] output on success or otherwise [`anyhow::Error`]
+    pub(crate) fn update(&self) -> Result<Vec<u8>> {
+        // Keep raw bytes, output isn't necessarily valid UTF-8
+        let res: Vec<u8> = self.plugin.borrow_mut().call("run", "")?;
 
-        debug!("{}: res={}", function_name!(), res);
+        debug!("{}: res={:?}", function_name!(), String::from_utf8_lossy(&res));
 
         Ok(res)
     }
+
+    /// Call an optional method of the plugin
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the exported method
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`Some`] raw output, [`None`] when not exported or otherwise [`anyhow::Error`]
+    pub(crate) fn call_optional(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let mut plugin = self.plugin.borrow_mut();
+
+        if !plugin.function_exists(name) {
+            return Ok(None);
+        }
+
+        let res: Vec<u8> = plugin.call(name, "")?;
+
+        debug!("{}: name={}, res={:?}", function_name!(), name, String::from_utf8_lossy(&res));
+
+        Ok(Some(res))
+    }
+}
+
+impl PluginCache {
+    /// Start a new refresh cycle, results of the last one become stale
+    pub(crate) fn next_generation(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+
+        debug!("{}: generation={}", function_name!(), self.generation);
+    }
+
+    /// Get result of a plugin and only run it once per refresh cycle
+    ///
+    /// # Arguments
+    ///
+    /// * `plugin_idx` - Index of the plugin
+    /// * `run` - Run the plugin when the cached result is stale
+    ///
+    /// # Returns
+    ///
+    /// The result of the current refresh cycle
+    pub(crate) fn get_or_update(&mut self, plugin_idx: usize, run: impl FnOnce() -> String) -> String {
+        let generation = self.generation;
+
+        match self.entries.get(&plugin_idx) {
+            Some((entry_generation, text)) if *entry_generation == generation => text.clone(),
+            _ => {
+                let text = run();
+
+                self.entries.insert(plugin_idx, (generation, text.clone()));
+
+                text
+            }
+        }
+    }
 }
 
 impl fmt::Display for Plugin {
@@ -171,6 +357,47 @@ impl fmt::Display for Plugin {
     }
 }
 
+/// Parse and load plugin from config, broken plugins aren't fatal
+///
+/// # Arguments
+///
+/// * `values` - Config values of the plugin
+/// * `registry` - Host functions of the plugin
+///
+/// # Returns
+///
+/// A [`Result`] with either [`Plugin`] on success or otherwise recoverable [`InitError`]
+pub(crate) fn parse_plugin(values: &HashMap<String, MixedConfigVal>, registry: &HostRegistry) -> Result<Plugin, InitError> {
+    let mut builder = PluginBuilder::default();
+
+    builder.registry(registry.clone());
+
+    if let Some(MixedConfigVal::S(value)) = values.get("name") {
+        builder.name(value.to_string());
+    }
+
+    if let Some(MixedConfigVal::S(value)) = values.get("url") {
+        builder.url(value.to_string());
+    }
+
+    if let Some(MixedConfigVal::I(value)) = values.get("interval") {
+        builder.interval(*value);
+    }
+
+    if let Some(MixedConfigVal::MSS(values)) = values.get("config") {
+        let config: HashMap<String, String> = values.iter()
+            .map(|entry| (String::from(entry.0), String::from(entry.1)))
+            .collect();
+
+        builder.config(config);
+    }
+
+    // Finally create actual plugin
+    builder.build()
+        .with_context(|| format!("Failed to load plugin `{}`", builder.name.clone().unwrap_or_default()))
+        .recoverable()
+}
+
 /// Check config and init all plugin related options
 ///
 /// # Arguments
@@ -182,38 +409,70 @@ impl fmt::Display for Plugin {
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
 pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
+    let registry = HostRegistry::default();
+
     for values in config.plugins.iter() {
-        let mut builder = PluginBuilder::default();
+        if let Some(plugin) = error::collect(subtle, parse_plugin(values, &registry))? {
+            info!("Loaded plugin ({})", plugin.name);
 
-        if let Some(MixedConfigVal::S(value)) = values.get("name") {
-            builder.name(value.to_string());
+            subtle.plugins.push(plugin);
         }
+    }
 
-        if let Some(MixedConfigVal::S(value)) = values.get("url") {
-            builder.url(value.to_string());
-        }
+    debug!("{}", function_name!());
 
-        if let Some(MixedConfigVal::I(value)) = values.get("interval") {
-            builder.interval(*value);
-        }
+    Ok(())
+}
 
-        if let Some(MixedConfigVal::MSS(values)) = values.get("config") {
-            let config: HashMap<String, String> = values.iter()
-                .map(|entry| (String::from(entry.0), String::from(entry.1)))
-                .collect();
+/// Find config values of a plugin by name or create them for a url
+///
+/// # Arguments
+///
+/// * `plugins` - Config values of all plugins
+/// * `name_or_url` - Name of a configured plugin or path or file url to a wasm file
+///
+/// # Returns
+///
+/// A [`HashMap`] with the config values of the plugin
+pub(crate) fn find_plugin_values(plugins: &[HashMap<String, MixedConfigVal>],
+                                 name_or_url: &str) -> HashMap<String, MixedConfigVal>
+{
+    plugins.iter()
+        .find(|values| matches!(values.get("name"), Some(MixedConfigVal::S(name)) if name == name_or_url))
+        .cloned()
+        .unwrap_or_else(|| HashMap::from([
+            ("name".to_string(), MixedConfigVal::S(name_or_url.to_string())),
+            ("url".to_string(), MixedConfigVal::S(name_or_url.to_string())),
+            ("interval".to_string(), MixedConfigVal::I(0)),
+        ]))
+}
 
-            builder.config(config);
-        }
+/// Load plugin like on startup, call its methods once and print the output
+///
+/// # Arguments
+///
+/// * `config` - Config values read either from args or config file
+/// * `name_or_url` - Name of a configured plugin or path or file url to a wasm file
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn test_plugin(config: &Config, name_or_url: &str) -> Result<()> {
+    let values = find_plugin_values(&config.plugins, name_or_url);
+    let plugin = parse_plugin(&values, &HostRegistry::default())?;
 
-        // Finally create actual plugin
-        let plugin = builder.build()?;
+    println!("Loaded plugin ({})", plugin);
 
-        info!("Loaded plugin ({})", plugin.name);
+    for name in ["run", "click", "over"] {
+        let start = Instant::now();
 
-        subtle.plugins.push(plugin);
+        match plugin.call_optional(name)? {
+            Some(res) => println!("{}: {:?} ({:?})", name, String::from_utf8_lossy(&res), start.elapsed()),
+            None => println!("{}: not exported", name),
+        }
     }
 
-    debug!("{}", function_name!());
+    debug!("{}: name_or_url={}", function_name!(), name_or_url);
 
     Ok(())
 }
@@ -0,0 +1,281 @@
+//!
+//! @package subtle-rs
+//!
+//! @file Error report functions
+//! @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+//! @version $Id$
+//!
+//! This program can be distributed under the terms of the GNU GPLv3.
+//! See the file LICENSE for details.
+//!
+
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+use anyhow::{Context, Result};
+use log::{debug, error, warn};
+use stdext::function_name;
+use x11rb::connection::Connection;
+use x11rb::{COPY_DEPTH_FROM_PARENT, CURRENT_TIME};
+use x11rb::protocol::Event;
+use x11rb::protocol::xproto::{ConnectionExt, CreateGCAux, CreateWindowAux, EventMask, GrabMode, Rectangle, WindowClass};
+use crate::geometry::calc_centered;
+use crate::subtle::Subtle;
+
+/// Time to show the error window unless a key is pressed
+pub(crate) const REPORT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Max number of characters per line
+const MAX_LINE_CHARS: usize = 100;
+
+/// Max number of lines
+const MAX_LINES: usize = 40;
+
+/// Padding around the text
+const PADDING: u16 = 10;
+
+/// Name of the core font
+const CORE_FONT_NAME: &str = "fixed";
+
+/// Wrap error text into lines
+///
+/// # Arguments
+///
+/// * `text` - Text to wrap
+/// * `max_chars` - Max number of characters per line
+/// * `max_lines` - Max number of lines
+///
+/// # Returns
+///
+/// A [`Vec`] with the wrapped lines, the last line is replaced with an ellipsis on overflow
+pub(crate) fn wrap_lines(text: &str, max_chars: usize, max_lines: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut lines: Vec<String> = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+
+            // Break words that don't fit into a line at all
+            while word.len() > max_chars {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+
+                lines.push(word.drain(..max_chars).collect());
+            }
+
+            let word: String = word.into_iter().collect();
+
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > max_chars {
+                lines.push(std::mem::take(&mut line));
+            }
+
+            if !line.is_empty() {
+                line.push(' ');
+            }
+
+            line.push_str(&word);
+        }
+
+        lines.push(line);
+    }
+
+    if lines.len() > max_lines {
+        lines.truncate(max_lines.saturating_sub(1));
+        lines.push("...".to_string());
+    }
+
+    lines
+}
+
+/// Calculate geometry of the error window centered on the screen
+///
+/// # Arguments
+///
+/// * `screen_geom` - Geometry of the screen
+/// * `lines` - Wrapped lines
+/// * `char_width` - Width of a character of the font
+/// * `line_height` - Height of a line of the font
+///
+/// # Returns
+///
+/// The geometry of the error window clamped to the screen
+pub(crate) fn calc_report_geom(screen_geom: Rectangle, lines: &[String], char_width: u16,
+                               line_height: u16) -> Rectangle
+{
+    let max_chars = lines.iter().map(|line| line.chars().count()).max().unwrap_or_default();
+
+    let width = (max_chars as u32 * char_width as u32 + 2 * PADDING as u32)
+        .min(screen_geom.width as u32) as u16;
+    let height = (lines.len() as u32 * line_height as u32 + 2 * PADDING as u32)
+        .min(screen_geom.height as u32) as u16;
+
+    Rectangle {
+        x: calc_centered(screen_geom.x as i32, screen_geom.width as i32, width as i32),
+        y: calc_centered(screen_geom.y as i32, screen_geom.height as i32, height as i32),
+        width: width.max(1),
+        height: height.max(1),
+    }
+}
+
+/// Get path of the last error file
+///
+/// # Arguments
+///
+/// * `xdg_runtime_dir` - Value of XDG_RUNTIME_DIR
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] path or otherwise [`None`]
+pub(crate) fn get_path(xdg_runtime_dir: Option<&str>) -> Option<PathBuf> {
+    xdg_runtime_dir.filter(|path| !path.is_empty())
+        .map(|path| PathBuf::from(path).join("subtle-rs").join("last-error.txt"))
+}
+
+/// Write error to the last error file
+///
+/// # Arguments
+///
+/// * `text` - Error text
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn write_file(text: &str) -> Result<()> {
+    let path = get_path(std::env::var("XDG_RUNTIME_DIR").ok().as_deref())
+        .context("Failed to find runtime dir")?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(&path, text)?;
+
+    debug!("{}: path={:?}", function_name!(), path);
+
+    Ok(())
+}
+
+/// Show error in a window until a key is pressed or the timeout is reached
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `text` - Error text
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn show_window(subtle: &Subtle, text: &str) -> Result<()> {
+    let conn = subtle.conn.get().context("Failed to get connection")?;
+    let default_screen = &conn.setup().roots[subtle.screen_num];
+
+    // Load core font to get along without styles
+    let font = conn.generate_id()?;
+
+    conn.open_font(font, CORE_FONT_NAME.as_bytes())?.check()?;
+
+    let font_info = conn.query_font(font)?.reply()?;
+    let char_width = font_info.max_bounds.character_width.max(1) as u16;
+    let line_height = (font_info.font_ascent + font_info.font_descent).max(1) as u16;
+
+    // Center on primary screen
+    let screen_geom = subtle.screens.first()
+        .map(|screen| screen.base)
+        .unwrap_or(Rectangle {
+            x: 0,
+            y: 0,
+            width: default_screen.width_in_pixels,
+            height: default_screen.height_in_pixels,
+        });
+
+    let lines = wrap_lines(text, MAX_LINE_CHARS, MAX_LINES);
+    let geom = calc_report_geom(screen_geom, &lines, char_width, line_height);
+
+    let win = conn.generate_id()?;
+
+    conn.create_window(COPY_DEPTH_FROM_PARENT, win, default_screen.root,
+                       geom.x, geom.y, geom.width, geom.height, 1,
+                       WindowClass::INPUT_OUTPUT, default_screen.root_visual,
+                       &CreateWindowAux::default()
+                           .background_pixel(default_screen.black_pixel)
+                           .border_pixel(default_screen.white_pixel)
+                           .event_mask(EventMask::KEY_PRESS | EventMask::EXPOSURE)
+                           .override_redirect(1))?.check()?;
+
+    let gc = conn.generate_id()?;
+
+    conn.create_gc(gc, win, &CreateGCAux::default()
+        .foreground(default_screen.white_pixel)
+        .background(default_screen.black_pixel)
+        .font(font))?.check()?;
+
+    conn.map_window(win)?.check()?;
+    conn.grab_keyboard(false, win, CURRENT_TIME, GrabMode::ASYNC, GrabMode::ASYNC)?.reply()?;
+    conn.flush()?;
+
+    let deadline = Instant::now() + REPORT_TIMEOUT;
+
+    while Instant::now() < deadline {
+        match conn.poll_for_event()? {
+            Some(Event::Expose(_)) => {
+                for (line_idx, line) in lines.iter().enumerate() {
+                    let bytes: Vec<u8> = line.bytes().take(u8::MAX as usize).collect();
+
+                    conn.image_text8(win, gc, PADDING as i16,
+                                     (PADDING + line_idx as u16 * line_height) as i16 + font_info.font_ascent,
+                                     &bytes)?;
+                }
+
+                conn.flush()?;
+            },
+            Some(Event::KeyPress(_)) => break,
+            Some(_) => {},
+            None => thread::sleep(Duration::from_millis(50)),
+        }
+    }
+
+    conn.ungrab_keyboard(CURRENT_TIME)?;
+    conn.free_gc(gc)?;
+    conn.close_font(font)?;
+    conn.destroy_window(win)?;
+    conn.flush()?;
+
+    debug!("{}: geom={:?}, nlines={}", function_name!(), geom, lines.len());
+
+    Ok(())
+}
+
+/// Report fatal or recoverable error in the log and to the user
+///
+/// The error is shown in a window when there is a connection, otherwise
+/// it is written to the last error file.
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `err` - Error to report
+pub(crate) fn report(subtle: &Subtle, err: &anyhow::Error) {
+    error!("{:?}", err);
+
+    let text = format!("{:?}", err);
+
+    let shown = subtle.conn.get().is_some() && match show_window(subtle, &text) {
+        Ok(_) => true,
+        Err(show_err) => {
+            warn!("Failed to show error: {}", show_err);
+
+            false
+        }
+    };
+
+    if !shown && let Err(write_err) = write_file(&text) {
+        warn!("Failed to write error: {}", write_err);
+    }
+
+    debug!("{}: shown={}", function_name!(), shown);
+}
@@ -10,9 +10,11 @@
 //!
 
 use std::fmt;
+#[cfg(feature = "panel")]
+use std::collections::HashMap;
 use std::cell::Cell;
 use bitflags::bitflags;
-use log::{debug, info};
+use log::{debug, info, warn};
 use anyhow::{Context, Result};
 use stdext::function_name;
 use veccell::VecCell;
@@ -24,12 +26,23 @@ use x11rb::protocol::xproto::{AtomEnum, BackPixmap, ConfigureWindowAux, Connecti
 use x11rb::wrapper::ConnectionExt as ConnectionExtWrapper;
 use crate::config::{Config, MixedConfigVal};
 use crate::subtle::{SubtleFlags, Subtle};
+use crate::{client, corner, display, effect, gravity};
+#[cfg(feature = "panel")]
+use crate::error;
+#[cfg(feature = "panel")]
+use crate::error::Classify;
+use crate::effect::{Effect, Prop};
 use crate::client::ClientFlags;
 use crate::ewmh::WMState;
+use crate::geometry::{calc_centered, calc_percent, checked_to_i16, checked_to_u16};
+#[cfg(feature = "panel")]
+use crate::icon::Icon;
 use crate::panel;
 use crate::panel::{Panel, PanelAction, PanelFlags};
-use crate::plugin::Plugin;
+use crate::tagging;
 use crate::tagging::Tagging;
+use crate::view;
+use crate::view::ViewFlags;
 
 bitflags! {
     /// Config and state-flags for [`Screen`]
@@ -41,9 +54,24 @@ bitflags! {
         const BOTTOM_PANEL = 1 << 1;
         /// Screen is virtual
         const VIRTUAL = 1 << 2;
+        /// Screen panel1 temporarily hidden
+        const HIDDEN_TOP_PANEL = 1 << 3;
+        /// Screen panel2 temporarily hidden
+        const HIDDEN_BOTTOM_PANEL = 1 << 4;
     }
 }
 
+/// Own windows that are recreated when destroyed
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum OwnWin {
+    /// Top panel window of a screen
+    TopPanel(usize),
+    /// Bottom panel window of a screen
+    BottomPanel(usize),
+    /// Tray window
+    Tray,
+}
+
 #[derive(Debug)]
 pub(crate) struct Screen {
     /// Config and state-flags
@@ -54,6 +82,10 @@ pub(crate) struct Screen {
     pub(crate) top_panel_win: Window,
     /// Bottom panel window
     pub(crate) bottom_panel_win: Window,
+    /// Configured x offset and width of the top panel
+    pub(crate) top_panel_geometry: Option<(i32, i32)>,
+    /// Configured x offset and width of the bottom panel
+    pub(crate) bottom_panel_geometry: Option<(i32, i32)>,
     /// Screen geometry
     pub(crate) geom: Rectangle,
     /// Screen base geometry
@@ -93,36 +125,90 @@ impl Screen {
         };
 
         // Create panel windows
-        let default_screen = &conn.setup().roots[subtle.screen_num];
-
-        let aux = CreateWindowAux::default()
-            .event_mask(EventMask::BUTTON_PRESS
-                | EventMask::ENTER_WINDOW
-                | EventMask::LEAVE_WINDOW
-                | EventMask::EXPOSURE)
-            .override_redirect(1)
-            .background_pixmap(BackPixmap::PARENT_RELATIVE);
-
         screen.top_panel_win = conn.generate_id()?;
 
-        conn.create_window(COPY_DEPTH_FROM_PARENT, screen.top_panel_win, default_screen.root,
-                           0, 0, 1, 1, 0,
-                           WindowClass::INPUT_OUTPUT, default_screen.root_visual, &aux)?.check()?;
+        create_panel_win(subtle, screen.top_panel_win)?;
 
         screen.bottom_panel_win = conn.generate_id()?;
 
-        conn.create_window(COPY_DEPTH_FROM_PARENT, screen.bottom_panel_win, default_screen.root,
-                           0, 0, 1, 1, 0,
-                           WindowClass::INPUT_OUTPUT, default_screen.root_visual, &aux)?.check()?;
+        create_panel_win(subtle, screen.bottom_panel_win)?;
 
         debug!("{}: screen={}", function_name!(), screen);
 
         Ok(screen)
     }
 
+    /// Get x offset and width of a panel relative to the screen
+    ///
+    /// # Arguments
+    ///
+    /// * `is_bottom` - Whether to get the bottom panel
+    ///
+    /// # Returns
+    ///
+    /// A tuple with the clamped x offset and width
+    pub(crate) fn get_panel_geom(&self, is_bottom: bool) -> (u16, u16) {
+        calc_panel_geom(self.base.width, if is_bottom {
+            self.bottom_panel_geometry
+        } else {
+            self.top_panel_geometry
+        })
+    }
+
+    /// Warp pointer to center of screen
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn warp(&self, subtle: &Subtle) -> Result<()> {
+        effect::apply(subtle, Effect::WarpPointer {
+            x: calc_centered(self.geom.x as i32, self.geom.width as i32, 0),
+            y: calc_centered(self.geom.y as i32, self.geom.height as i32, 0),
+        })?;
+
+        debug!("{}: screen={}", function_name!(), self);
+
+        Ok(())
+    }
+
+    /// Find hovered views of all panels
+    ///
+    /// # Returns
+    ///
+    /// A [`Vec`] with the hovered view index of each panel
+    pub(crate) fn find_hovered_views(&self) -> Vec<Option<usize>> {
+        self.panels.iter()
+            .map(|panel| panel.hovered_view_idx.get())
+            .collect()
+    }
+
+    /// Pass panel action to the panels of the top or bottom panel
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `action` - Action to handle
+    /// * `is_bottom` - Whether the action happened on the bottom panel
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
     pub(crate) fn handle_action(&self, subtle: &Subtle, action: &PanelAction, is_bottom: bool) -> Result<()> {
+        let mut is_bottom_panel = false;
+
         for panel in self.panels.iter() {
-            panel.handle_action(subtle, action, is_bottom)?;
+            // Bottom panels start with the marker
+            if panel.flags.intersects(PanelFlags::BOTTOM_START_MARKER) {
+                is_bottom_panel = true;
+            }
+
+            if is_bottom_panel == is_bottom {
+                panel.handle_action(subtle, action, is_bottom)?;
+            }
         }
 
         debug!("{}: screen={}", function_name!(), self);
@@ -141,6 +227,9 @@ impl Default for Screen {
             top_panel_win: Window::default(),
             bottom_panel_win: Window::default(),
 
+            top_panel_geometry: None,
+            bottom_panel_geometry: None,
+
             geom: Rectangle::default(),
             base: Rectangle::default(),
             panels: VecCell::new(),
@@ -156,19 +245,217 @@ impl fmt::Display for Screen {
     }
 }
 
+/// Find screen that contains the largest part of the given area
+///
+/// # Arguments
+///
+/// * `geom` - Area to check
+/// * `screen_geoms` - Geometries of the screens
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] screen index on success or otherwise [`None`]
+pub(crate) fn find_by_overlap(geom: &Rectangle, screen_geoms: &[Rectangle]) -> Option<usize> {
+    let mut best: Option<(usize, i32)> = None;
+
+    for (screen_idx, screen_geom) in screen_geoms.iter().enumerate() {
+        let overlap_width = (geom.x as i32 + geom.width as i32)
+            .min(screen_geom.x as i32 + screen_geom.width as i32)
+            - (geom.x as i32).max(screen_geom.x as i32);
+        let overlap_height = (geom.y as i32 + geom.height as i32)
+            .min(screen_geom.y as i32 + screen_geom.height as i32)
+            - (geom.y as i32).max(screen_geom.y as i32);
+
+        if 0 < overlap_width && 0 < overlap_height {
+            let area = overlap_width * overlap_height;
+
+            if best.is_none_or(|(_, best_area)| area > best_area) {
+                best = Some((screen_idx, area));
+            }
+        }
+    }
+
+    best.map(|(screen_idx, _)| screen_idx)
+}
+
+/// Check whether the area is fully covered by the union of the screens
+///
+/// # Arguments
+///
+/// * `geom` - Area to check
+/// * `screen_geoms` - Geometries of the screens
+///
+/// # Returns
+///
+/// Either [`true`] if no part is outside of the screens or otherwise [`false`]
+pub(crate) fn is_covered(geom: &Rectangle, screen_geoms: &[Rectangle]) -> bool {
+    let (x1, y1) = (geom.x as i32, geom.y as i32);
+    let (x2, y2) = (x1 + geom.width as i32, y1 + geom.height as i32);
+
+    // Split area into cells along all screen edges inside of it
+    let mut xs = vec![x1, x2];
+    let mut ys = vec![y1, y2];
+
+    for screen_geom in screen_geoms.iter() {
+        for x in [screen_geom.x as i32, screen_geom.x as i32 + screen_geom.width as i32] {
+            if x > x1 && x < x2 {
+                xs.push(x);
+            }
+        }
+
+        for y in [screen_geom.y as i32, screen_geom.y as i32 + screen_geom.height as i32] {
+            if y > y1 && y < y2 {
+                ys.push(y);
+            }
+        }
+    }
+
+    xs.sort_unstable();
+    xs.dedup();
+    ys.sort_unstable();
+    ys.dedup();
+
+    // Each cell is either completely inside of a screen or not at all
+    xs.windows(2).all(|xw| ys.windows(2).all(|yw| {
+        let (cx, cy) = (xw[0] + xw[1], yw[0] + yw[1]);
+
+        screen_geoms.iter().any(|screen_geom| {
+            cx >= 2 * screen_geom.x as i32 && cx < 2 * (screen_geom.x as i32 + screen_geom.width as i32)
+                && cy >= 2 * screen_geom.y as i32 && cy < 2 * (screen_geom.y as i32 + screen_geom.height as i32)
+        })
+    }))
+}
+
+/// Resist moving an area into regions that aren't covered by any screen
+///
+/// # Arguments
+///
+/// * `geom` - Area to check
+/// * `screen_geoms` - Geometries of the screens
+/// * `resistance` - Distance after which the resistance releases
+///
+/// # Returns
+///
+/// Either the nearest covered [`Rectangle`] within resistance or otherwise the unchanged one
+pub(crate) fn calc_edge_resistance(geom: &Rectangle, screen_geoms: &[Rectangle], resistance: u16) -> Rectangle {
+    if 0 == resistance || is_covered(geom, screen_geoms) {
+        return *geom;
+    }
+
+    // Collect candidate positions along the screen edges
+    let mut xs = vec![geom.x];
+    let mut ys = vec![geom.y];
+
+    for screen_geom in screen_geoms.iter() {
+        xs.push(screen_geom.x);
+        xs.push(checked_to_i16(screen_geom.x as i32 + screen_geom.width as i32 - geom.width as i32));
+        ys.push(screen_geom.y);
+        ys.push(checked_to_i16(screen_geom.y as i32 + screen_geom.height as i32 - geom.height as i32));
+    }
+
+    let mut nearest: Option<(Rectangle, i32)> = None;
+
+    for x in xs.iter() {
+        for y in ys.iter() {
+            let candidate = Rectangle { x: *x, y: *y, width: geom.width, height: geom.height };
+            let distance = (*x as i32 - geom.x as i32).abs() + (*y as i32 - geom.y as i32).abs();
+
+            if distance <= resistance as i32
+                && nearest.is_none_or(|(_, nearest_distance)| distance < nearest_distance)
+                && is_covered(&candidate, screen_geoms)
+            {
+                nearest = Some((candidate, distance));
+            }
+        }
+    }
+
+    nearest.map_or(*geom, |(candidate, _)| candidate)
+}
+
+/// Calculate geometry of a virtual screen
+///
+/// # Arguments
+///
+/// * `geom` - Geometry of the physical screen
+/// * `percents` - Position and size in percent of the physical screen
+///
+/// # Returns
+///
+/// The calculated [`Rectangle`]
+pub(crate) fn calc_virtual_geom(geom: &Rectangle, percents: &[i32]) -> Rectangle {
+    Rectangle {
+        x: checked_to_i16(calc_percent(geom.x as i32, geom.width as i32, percents[0])),
+        y: checked_to_i16(calc_percent(geom.y as i32, geom.height as i32, percents[1])),
+        width: checked_to_u16(calc_percent(0, geom.width as i32, percents[2])),
+        height: checked_to_u16(calc_percent(0, geom.height as i32, percents[3])),
+    }
+}
+
+/// Calculate x offset and width of a panel and clamp both to the screen
+///
+/// # Arguments
+///
+/// * `screen_width` - Width of the screen
+/// * `geometry` - Configured x offset and width, a width of zero or less uses the remaining space
+///
+/// # Returns
+///
+/// A tuple with the clamped x offset and width
+pub(crate) fn calc_panel_geom(screen_width: u16, geometry: Option<(i32, i32)>) -> (u16, u16) {
+    let Some((offset_x, width)) = geometry else {
+        return (0, screen_width);
+    };
+
+    if 0 == screen_width {
+        return (0, 0);
+    }
+
+    let offset_x = offset_x.clamp(0, screen_width as i32 - 1);
+    let remaining = screen_width as i32 - offset_x;
+
+    let width = if 0 >= width { remaining } else { width.min(remaining) };
+
+    (offset_x as u16, width as u16)
+}
+
+/// Parse panel geometry
+///
+/// # Arguments
+///
+/// * `values` - Config values of the screen
+/// * `key` - Config key of the geometry
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] x offset and width or otherwise [`None`]
+#[cfg(feature = "panel")]
+fn parse_panel_geometry(values: &HashMap<String, MixedConfigVal>, key: &str) -> Option<(i32, i32)> {
+    match values.get(key) {
+        Some(MixedConfigVal::VI(geometry)) if 2 == geometry.len() => Some((geometry[0], geometry[1])),
+        Some(_) => {
+            warn!("Invalid panel geometry: key={}", key);
+
+            None
+        },
+        None => None,
+    }
+}
+
 /// Parse panel list
 ///
 /// # Arguments
 ///
-/// * `screen` - Referenced screen
+/// * `subtle` - Global state object
 /// * `panel_list` - List of panels
 /// * `screen_idx` - Screen index
 /// * `is_bottom` - Whether the panel is at the bottom
 ///
 /// # Returns
 ///
-/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-fn parse_panels(screen: &mut Screen, panel_list: &Vec<String>, plugin_list: &Vec<Plugin>, screen_idx: usize,  is_bottom: bool) {
+/// A [`Result`] with either [`Vec`] of all successfully parsed [`Panel`] or otherwise [`anyhow::Error`]
+#[cfg(feature = "panel")]
+fn parse_panels(subtle: &Subtle, panel_list: &Vec<String>, screen_idx: usize,  is_bottom: bool) -> Result<Vec<Panel>> {
+    let mut panels = Vec::new();
     let mut flags = PanelFlags::empty();
 
     // Add bottom marker to first panel on bottom panel in linear vec
@@ -179,89 +466,247 @@ fn parse_panels(screen: &mut Screen, panel_list: &Vec<String>, plugin_list: &Vec
     for panel_name in panel_list.iter() {
 
         // Create panel
-        if let Ok(mut panel) = Panel::new(panel_name) {
+        if let Some(mut panel) = error::collect(subtle, Panel::new(panel_name).recoverable())? {
             panel.flags |= flags;
             panel.screen_idx = screen_idx;
 
+            // Tray items need the tray feature, the config check warns about them
+            #[cfg(not(feature = "tray"))]
+            if panel.flags.intersects(PanelFlags::TRAY) {
+                continue;
+            }
+
             if panel.flags.intersects(PanelFlags::PLUGIN) {
-                if let Some(idx) = plugin_list.iter()
-                    .position(|p| panel_name.ends_with(&format!("${}", p.name)))
+                #[cfg(feature = "plugins")]
                 {
-                    panel.plugin_idx = idx;
+                    let (plugin_item, _) = panel::parse_plugin_item(panel_name);
+                    let maybe_plugin_idx = subtle.plugins.iter()
+                        .position(|p| plugin_item.ends_with(&format!("${}", p.name)));
+
+                    // Skip panels of plugins that failed to load
+                    let Some(plugin_idx) = error::collect(subtle, maybe_plugin_idx
+                        .with_context(|| format!("Failed to find plugin of panel `{}`", panel_name))
+                        .recoverable())? else {
+                        continue;
+                    };
+
+                    panel.plugin_idx = plugin_idx;
                 }
+
+                // Plugins are never loaded without the feature
+                #[cfg(not(feature = "plugins"))]
+                continue;
+            } else if panel.flags.intersects(PanelFlags::ICON) {
+                let icon_path = panel.text.clone().unwrap_or_default();
+
+                // Skip icons that cannot be loaded
+                let Some(icon) = error::collect(subtle, Icon::load(subtle, &icon_path, &subtle.separator_style))? else {
+                    continue;
+                };
+
+                panel.icon = Some(icon);
             }
 
-            screen.panels.push(panel);
+            panels.push(panel);
             flags.remove(PanelFlags::BOTTOM_START_MARKER);
         }
     }
+
+    Ok(panels)
 }
 
-/// Check config and init all screen related options
+/// Find first view that isn't shown on any other screen
+///
+/// # Arguments
+///
+/// * `used_views` - Indices of views of the other screens
+/// * `nviews` - Number of views
+///
+/// # Returns
+///
+/// The index of the free view or -1 if all views are in use
+pub(crate) fn find_free_view(used_views: &[isize], nviews: usize) -> isize {
+    (0..nviews as isize)
+        .find(|view_idx| !used_views.contains(view_idx))
+        .unwrap_or(-1)
+}
+
+/// Translate geometry from one screen to another and keep it visible
+///
+/// # Arguments
+///
+/// * `geom` - Geometry to translate
+/// * `old_base` - Base geometry of the old screen
+/// * `new_base` - Base geometry of the new screen
+///
+/// # Returns
+///
+/// The translated geometry clamped to the new screen
+pub(crate) fn calc_migrated_geom(geom: Rectangle, old_base: Rectangle, new_base: Rectangle) -> Rectangle {
+    let width = geom.width.min(new_base.width).max(1);
+    let height = geom.height.min(new_base.height).max(1);
+
+    let x = (new_base.x as i32 + geom.x as i32 - old_base.x as i32)
+        .min(new_base.x as i32 + new_base.width as i32 - width as i32)
+        .max(new_base.x as i32);
+    let y = (new_base.y as i32 + geom.y as i32 - old_base.y as i32)
+        .min(new_base.y as i32 + new_base.height as i32 - height as i32)
+        .max(new_base.y as i32);
+
+    Rectangle {
+        x: checked_to_i16(x),
+        y: checked_to_i16(y),
+        width,
+        height,
+    }
+}
+
+/// Query geometries of all physical screens
 ///
 /// # Arguments
 ///
-/// * `config` - Config values read either from args or config file
 /// * `subtle` - Global state object
 ///
 /// # Returns
 ///
-/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
+/// A [`Result`] with either [`Vec`] of geometries on success or otherwise [`anyhow::Error`]
+fn query_geoms(subtle: &Subtle) -> Result<Vec<Rectangle>> {
     let conn = subtle.conn.get().context("Failed to get connection")?;
+    let default_screen = &conn.setup().roots[subtle.screen_num];
+
+    let mut geoms: Vec<Rectangle> = Vec::new();
 
-    // Check xrandr support
+    // Check xrandr support and skip disabled crtcs
     if subtle.flags.intersects(SubtleFlags::XRANDR) {
-        let default_screen = &conn.setup().roots[subtle.screen_num];
-        let crtcs= conn.randr_get_screen_resources_current(default_screen.root)?.reply()?.crtcs;
+        let crtcs = conn.randr_get_screen_resources_current(default_screen.root)?.reply()?.crtcs;
 
         for crtc in crtcs.iter() {
             let screen_size = conn.randr_get_crtc_info(*crtc, CURRENT_TIME)?.reply()?;
 
-            if let Ok(screen) = Screen::new(subtle, screen_size.x, screen_size.y,
-                                            screen_size.width, screen_size.height)
-            {
-                subtle.screens.push(screen);
+            if 0 < screen_size.width && 0 < screen_size.height {
+                geoms.push(Rectangle {
+                    x: screen_size.x,
+                    y: screen_size.y,
+                    width: screen_size.width,
+                    height: screen_size.height,
+                });
             }
         }
     }
 
     // Check xinerama support, but prefer xrandr
-    if subtle.flags.intersects(SubtleFlags::XINERAMA) && subtle.screens.is_empty() {
-        if 0 != conn.xinerama_is_active()?.reply()?.state {
-            let screens = conn.xinerama_query_screens()?.reply()?.screen_info;
+    if subtle.flags.intersects(SubtleFlags::XINERAMA) && geoms.is_empty()
+        && 0 != conn.xinerama_is_active()?.reply()?.state
+    {
+        for screen_info in conn.xinerama_query_screens()?.reply()?.screen_info.iter() {
+            geoms.push(Rectangle {
+                x: screen_info.x_org,
+                y: screen_info.y_org,
+                width: screen_info.width,
+                height: screen_info.height,
+            });
+        }
+    }
 
-            for screen_info in screens.iter() {
-                if let Ok(screen) = Screen::new(subtle, screen_info.x_org, screen_info.y_org,
-                                                screen_info.width, screen_info.height)
-                {
-                    subtle.screens.push(screen);
-                }
-            }
+    // Fall back to default screen
+    if geoms.is_empty() {
+        geoms.push(Rectangle {
+            x: 0,
+            y: 0,
+            width: subtle.width,
+            height: subtle.height,
+        });
+    }
 
-        }
+    debug!("{}: ngeoms={}", function_name!(), geoms.len());
+
+    Ok(geoms)
+}
+
+/// Calculate size of the desktop spanning all screens
+///
+/// # Arguments
+///
+/// * `root_width` - Width of the root window
+/// * `root_height` - Height of the root window
+/// * `screen_geoms` - Base geometries of all screens
+///
+/// # Returns
+///
+/// A tuple with the width and height of the desktop, the root size without screens
+pub(crate) fn calc_desktop_size(root_width: u16, root_height: u16, screen_geoms: &[Rectangle]) -> (u16, u16) {
+    if screen_geoms.is_empty() {
+        return (root_width, root_height);
     }
 
-    // Create default screen
-    if subtle.screens.is_empty() {
-        if let Ok(screen) = Screen::new(subtle, 0, 0, subtle.width, subtle.height) {
+    screen_geoms.iter().fold((0, 0), |(width, height), geom| {
+        (width.max((geom.x.max(0) as u16).saturating_add(geom.width)),
+         height.max((geom.y.max(0) as u16).saturating_add(geom.height)))
+    })
+}
+
+/// Update global desktop size from the screens and the root window
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `root_width` - Width of the root window
+/// * `root_height` - Height of the root window
+pub(crate) fn update_desktop_size(subtle: &mut Subtle, root_width: u16, root_height: u16) {
+    let screen_geoms: Vec<Rectangle> = subtle.screens.iter().map(|screen| screen.base).collect();
+
+    (subtle.width, subtle.height) = calc_desktop_size(root_width, root_height, &screen_geoms);
+
+    debug!("{}: width={}, height={}", function_name!(), subtle.width, subtle.height);
+}
+
+/// Check config and init all screen related options
+///
+/// # Arguments
+///
+/// * `config` - Config values read either from args or config file
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
+    // Create screens, query_geoms falls back to the default screen
+    for geom in query_geoms(subtle)? {
+        if let Ok(screen) = Screen::new(subtle, geom.x, geom.y, geom.width, geom.height) {
             subtle.screens.push(screen);
         }
     }
 
+    let physical_count = subtle.screens.len();
+    let mut tray_screen_idx = None;
+    let mut config_screen_idxs = Vec::with_capacity(config.screens.len());
+
     // Load screen config
     for (screen_idx, values) in config.screens.iter().enumerate() {
+        // Single virtual screen on top of its physical parent, configured by this entry
+        if let Some(MixedConfigVal::VI(virt_geom_ary)) = values.get("virtual") {
+            let parent_geom = subtle.screens.get(screen_idx.min(physical_count.saturating_sub(1)))
+                .context("Cannot get screen?")?.base;
+            let calc_geom = calc_virtual_geom(&parent_geom, virt_geom_ary);
+
+            let mut vscreen = Screen::new(subtle, calc_geom.x, calc_geom.y,
+                                          calc_geom.width, calc_geom.height)?;
+
+            vscreen.flags.insert(ScreenFlags::VIRTUAL);
+            subtle.screens.push(vscreen);
+
+            config_screen_idxs.push(Some(subtle.screens.len() - 1));
+        } else {
+            config_screen_idxs.push(None);
+        }
+
         // Handle virtual screens
         if let Some(MixedConfigVal::VVI(virtuals)) = values.get("virtual") {
             let orig_geom = subtle.screens.get(screen_idx).context("Cannot get screen?")?.geom;
 
             for (virt_idx, virt_geom_ary) in virtuals.iter().enumerate() {
-                let calc_geom = Rectangle {
-                    x: orig_geom.x + (orig_geom.width as i16 * virt_geom_ary[0] as i16 / 100),
-                    y: orig_geom.y + (orig_geom.height as i16 * virt_geom_ary[1] as i16 / 100),
-                    width: orig_geom.width * virt_geom_ary[2] as u16 / 100,
-                    height: orig_geom.height * virt_geom_ary[3] as u16 / 100,
-                };
+                let calc_geom = calc_virtual_geom(&orig_geom, virt_geom_ary);
 
                 // Update original screen or split into virtual one
                 if 0 < virt_idx {
@@ -279,12 +724,34 @@ pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
                 }
             }
         }
+    }
+
+    // Handle panels after virtual screens
+    for (values, screen_idx) in config.screens.iter().zip(calc_config_screen_idxs(&config_screen_idxs)) {
+        let Some(screen_idx) = screen_idx else {
+            continue;
+        };
+
+        // First screen that asks for the tray gets it
+        if let Some(MixedConfigVal::B(true)) = values.get("tray") && tray_screen_idx.is_none() {
+            tray_screen_idx = Some(screen_idx);
+        }
+
+        #[cfg(feature = "panel")]
+        if screen_idx < subtle.screens.len() {
+            let screen = &mut subtle.screens[screen_idx];
+
+            screen.top_panel_geometry = parse_panel_geometry(values, "top_panel_geometry");
+            screen.bottom_panel_geometry = parse_panel_geometry(values, "bottom_panel_geometry");
 
-        // Handle panels after virtual screens
-        if let Some(screen) = subtle.screens.get_mut(screen_idx) {
             if let Some(MixedConfigVal::VS(top_panels)) = values.get("top_panel") {
                 if !top_panels.is_empty() {
-                    parse_panels(screen, top_panels, &subtle.plugins, screen_idx, false);
+                    let panels = parse_panels(subtle, top_panels, screen_idx, false)?;
+                    let screen = &mut subtle.screens[screen_idx];
+
+                    for panel in panels {
+                        screen.panels.push(panel);
+                    }
 
                     screen.flags.insert(ScreenFlags::TOP_PANEL);
                 }
@@ -292,7 +759,12 @@ pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
 
             if let Some(MixedConfigVal::VS(bottom_panels)) = values.get("bottom_panel") {
                 if !bottom_panels.is_empty() {
-                    parse_panels(screen, bottom_panels, &subtle.plugins, screen_idx, true);
+                    let panels = parse_panels(subtle, bottom_panels, screen_idx, true)?;
+                    let screen = &mut subtle.screens[screen_idx];
+
+                    for panel in panels {
+                        screen.panels.push(panel);
+                    }
 
                     screen.flags.insert(ScreenFlags::BOTTOM_PANEL);
                 }
@@ -300,6 +772,11 @@ pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
         }
     }
 
+    // Select the one tray item that hosts the tray window
+    select_tray_host(subtle, tray_screen_idx);
+
+    update_desktop_size(subtle, subtle.width, subtle.height);
+
     publish(subtle, true)?;
 
     info!("Running on {} screen(s)", subtle.screens.len());
@@ -309,6 +786,102 @@ pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
     Ok(())
 }
 
+/// Select the tray item that hosts the tray window
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `tray_screen_idx` - Screen designated for the tray in the config
+pub(crate) fn select_tray_host(subtle: &Subtle, tray_screen_idx: Option<usize>) {
+    let tray_panels = subtle.screens.iter().enumerate()
+        .flat_map(|(screen_idx, screen)| screen.panels.iter().enumerate()
+            .filter(|(_, panel)| panel.flags.intersects(PanelFlags::TRAY))
+            .map(move |(panel_idx, _)| (screen_idx, panel_idx)))
+        .collect::<Vec<(usize, usize)>>();
+
+    if let Some((screen_idx, panel_idx)) = calc_tray_host(&tray_panels, tray_screen_idx)
+        && let Some(mut panel) = subtle.screens[screen_idx].panels.borrow_mut(panel_idx)
+    {
+        panel.flags.insert(PanelFlags::TRAY_HOST);
+    }
+
+    debug!("{}: tray_screen_idx={:?}", function_name!(), tray_screen_idx);
+}
+
+/// Map screen config entries to the screens they configure
+///
+/// Entries with a single virtual geometry configure the virtual screen created for them,
+/// all other entries configure the screen at their own index unless that is such a
+/// virtual screen.
+///
+/// # Arguments
+///
+/// * `virtual_idxs` - Index of the virtual screen created per config entry
+///
+/// # Returns
+///
+/// A [`Vec`] with either [`Some`] screen index per config entry or [`None`] when unused
+pub(crate) fn calc_config_screen_idxs(virtual_idxs: &[Option<usize>]) -> Vec<Option<usize>> {
+    virtual_idxs.iter().enumerate().map(|(entry_idx, virtual_idx)| {
+        virtual_idx.or_else(|| (!virtual_idxs.contains(&Some(entry_idx))).then_some(entry_idx))
+    }).collect()
+}
+
+/// Find the most specific screen at a position
+///
+/// Virtual screens overlap their physical parent, so the smallest screen wins.
+///
+/// # Arguments
+///
+/// * `screen_geoms` - Base geometries of all screens
+/// * `x` - X coordinate
+/// * `y` - Y coordinate
+///
+/// # Returns
+///
+/// Either [`Some`] with the screen index or [`None`] when no screen contains the position
+pub(crate) fn calc_screen_at(screen_geoms: &[Rectangle], x: i16, y: i16) -> Option<usize> {
+    screen_geoms.iter().enumerate()
+        .filter(|(_, geom)| x >= geom.x && (x as i32) < geom.x as i32 + geom.width as i32
+            && y >= geom.y && (y as i32) < geom.y as i32 + geom.height as i32)
+        .min_by_key(|(_, geom)| geom.width as u32 * geom.height as u32)
+        .map(|(screen_idx, _)| screen_idx)
+}
+
+/// Find the tray item that hosts the tray window
+///
+/// # Arguments
+///
+/// * `tray_panels` - Screen and panel index of every tray item in screen order
+/// * `tray_screen_idx` - Screen designated for the tray in the config
+///
+/// # Returns
+///
+/// Either the first tray item of the designated screen, the first tray item at all or [`None`]
+pub(crate) fn calc_tray_host(tray_panels: &[(usize, usize)], tray_screen_idx: Option<usize>) -> Option<(usize, usize)> {
+    tray_screen_idx
+        .and_then(|tray_screen_idx| tray_panels.iter().find(|(screen_idx, _)| *screen_idx == tray_screen_idx))
+        .or_else(|| tray_panels.first())
+        .copied()
+}
+
+/// Check whether a client may be shown on a screen
+///
+/// # Arguments
+///
+/// * `client_flags` - Flags of the client
+/// * `client_screen_idx` - Screen index of the client
+/// * `screen_idx` - Screen index to check
+///
+/// # Returns
+///
+/// Either [`true`] unless the client is pinned to another screen or otherwise [`false`]
+pub(crate) fn is_screen_allowed(client_flags: ClientFlags, client_screen_idx: isize, screen_idx: usize) -> bool {
+    !client_flags.contains(ClientFlags::MODE_STICK_SCREEN)
+        || client_flags.contains(ClientFlags::MODE_STICK)
+        || client_screen_idx == screen_idx as isize
+}
+
 /// Publish and export all relevant atoms to allow IPC
 ///
 /// # Arguments
@@ -319,13 +892,13 @@ pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
 pub(crate) fn configure(subtle: &Subtle) -> Result<()> {
-    let conn = subtle.conn.get().unwrap();
-    let atoms = subtle.atoms.get().unwrap();
-
     let mut visible_tags = Tagging::empty();
     let mut visible_views = Tagging::empty();
     let mut client_tags = Tagging::empty();
 
+    // Tiled clients per gravity, screen and view to respect gravity limits
+    let mut occupancy: Vec<((usize, usize, usize), Vec<usize>)> = Vec::new();
+
     // Either check each client or just get visible clients
     let mut clients = subtle.clients.borrow_mut();
 
@@ -347,13 +920,18 @@ pub(crate) fn configure(subtle: &Subtle) -> Result<()> {
             client_tags.insert(client.tags);
 
             for (screen_idx, screen) in subtle.screens.iter().enumerate() {
-                if -1 != screen.view_idx.get() && let Some(view) = subtle.views.get(screen.view_idx.get() as usize) {
+                // Skip other screens for clients pinned to a screen
+                if !is_screen_allowed(client.flags, client.screen_idx, screen_idx) {
+                    continue;
+                }
+
+                if -1 != screen.view_idx.get() && let Some(view) = subtle.views.borrow().get(screen.view_idx.get() as usize) {
 
                     // Set visible tags and views to ease lookups
-                    visible_tags.insert(view.tags);
-                    visible_views.insert(Tagging::from_bits_retain(1 << (screen.view_idx.get() + 1)));
+                    visible_tags.insert(view.tags.get());
+                    visible_views.insert(tagging::view_bit(screen.view_idx.get() as usize));
 
-                    if view.tags.intersects(client.tags) ||
+                    if view.tags.get().intersects(client.tags) ||
                         client.flags.intersects(ClientFlags::MODE_STICK | ClientFlags::TYPE_DESKTOP)
                     {
                         // Keep screen when sticky
@@ -375,6 +953,24 @@ pub(crate) fn configure(subtle: &Subtle) -> Result<()> {
 
             // After all screens are checked..
             if 0 < visible {
+                // Move client to overflow gravity when the gravity is full
+                if 0 <= new_gravity_idx && !client.flags.intersects(ClientFlags::MODE_FLOAT
+                    | ClientFlags::MODE_FULL | ClientFlags::TYPE_DESKTOP | ClientFlags::TYPE_DOCK)
+                {
+                    let counts = (0..subtle.gravities.len()).map(|gravity_idx| {
+                        occupancy.iter()
+                            .find(|(key, _)| *key == (gravity_idx, new_screen_idx, new_view_idx))
+                            .map_or(0, |(_, client_idxs)| client_idxs.len())
+                    }).collect::<Vec<usize>>();
+
+                    let gravity_idx = gravity::calc_overflow_idx(&subtle.gravities, &counts,
+                                                                 new_gravity_idx as usize);
+
+                    client::tally(&mut occupancy, (gravity_idx, new_screen_idx, new_view_idx), client_idx);
+
+                    new_gravity_idx = gravity_idx as isize;
+                }
+
                 client.arrange(subtle, new_gravity_idx, new_screen_idx as isize)?;
                 client.set_wm_state(subtle, WMState::Normal)?;
                 client.map(subtle)?;
@@ -387,12 +983,17 @@ pub(crate) fn configure(subtle: &Subtle) -> Result<()> {
                     client.warp_pointer(subtle)?;
                 }
 
-                // EWMH: Desktop, screen
-                conn.change_property32(PropMode::REPLACE, client.win, atoms._NET_WM_DESKTOP,
-                                       AtomEnum::CARDINAL, &[new_view_idx as u32])?.check()?;
+                // EWMH: Desktop, screen - lowest view for clients on multiple views
+                let desktop_idx = if client.flags.intersects(ClientFlags::MODE_STICK) {
+                    None
+                } else {
+                    client::calc_wm_desktop(&subtle.views.borrow(), client.tags)
+                };
 
-                conn.change_property32(PropMode::REPLACE, client.win, atoms.SUBTLE_CLIENT_SCREEN,
-                                       AtomEnum::CARDINAL, &[new_screen_idx as u32])?.check()?;
+                effect::apply(subtle, Effect::SetProperty { win: client.win, prop: Prop::WmDesktop,
+                    data: vec![desktop_idx.unwrap_or(new_view_idx as u32)] })?;
+                effect::apply(subtle, Effect::SetProperty { win: client.win, prop: Prop::ClientScreen,
+                    data: vec![new_screen_idx as u32] })?;
 
                 client.arrange(subtle, new_gravity_idx, new_screen_idx as isize)?;
             } else {
@@ -409,27 +1010,36 @@ pub(crate) fn configure(subtle: &Subtle) -> Result<()> {
         // Check views of each screen
         for screen in subtle.screens.iter() {
             if -1 != screen.view_idx.get()
-                && let Some(view) = subtle.views.get(screen.view_idx.get() as usize)
+                && let Some(view) = subtle.views.borrow().get(screen.view_idx.get() as usize)
             {
-                visible_tags |= view.tags;
-                visible_views |= Tagging::from_bits_retain(1 << (screen.view_idx.get() + 1));
+                visible_tags |= view.tags.get();
+                visible_views |= tagging::view_bit(screen.view_idx.get() as usize);
             }
         }
     }
 
+    drop(clients);
+
     subtle.visible_tags.replace(visible_tags);
     subtle.visible_views.replace(visible_views);
-    subtle.client_tags.replace(client_tags);
+
+    let old_client_tags = subtle.client_tags.replace(client_tags);
+
+    client::update_urgent_tags(subtle)?;
+    client::gravity_tile(subtle)?;
 
     // EWMH: Visible tags, views
-    let default_screen = &conn.setup().roots[subtle.screen_num];
+    effect::apply(subtle, Effect::SetRootProperty { prop: Prop::VisibleTags,
+        data: vec![visible_tags.bits()] })?;
+    effect::apply(subtle, Effect::SetRootProperty { prop: Prop::VisibleViews,
+        data: vec![visible_views.bits()] })?;
 
-    conn.change_property32(PropMode::REPLACE, default_screen.root, atoms.SUBTLE_VISIBLE_TAGS,
-                           AtomEnum::CARDINAL, &[visible_tags.bits()])?.check()?;
-    conn.change_property32(PropMode::REPLACE, default_screen.root, atoms.SUBTLE_VISIBLE_VIEWS,
-                           AtomEnum::CARDINAL, &[visible_views.bits()])?.check()?;
+    effect::flush(subtle)?;
 
-    conn.flush()?;
+    // Leave dynamic views that just lost their last client
+    if old_client_tags.bits() != client_tags.bits() {
+        leave_dynamic_views(subtle, old_client_tags, client_tags)?;
+    }
 
     debug!("{}: visible_tags={:?}, visible_views={:?}, client_tags={:?}",
         function_name!(), visible_tags, visible_views, client_tags);
@@ -437,6 +1047,198 @@ pub(crate) fn configure(subtle: &Subtle) -> Result<()> {
     Ok(())
 }
 
+/// Switch screens showing an emptied dynamic view to a fallback view
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `old_client_tags` - Tags of all clients before the change
+/// * `client_tags` - Tags of all clients after the change
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn leave_dynamic_views(subtle: &Subtle, old_client_tags: Tagging, client_tags: Tagging) -> Result<()> {
+    let view_tags: Vec<Tagging> = subtle.views.borrow().iter()
+        .map(|view| view.tags.get())
+        .collect();
+
+    for (screen_idx, screen) in subtle.screens.iter().enumerate() {
+        let screen_views: Vec<isize> = subtle.screens.iter()
+            .map(|screen| screen.view_idx.get())
+            .collect();
+
+        let maybe_fallback_idx = subtle.views.borrow().get(screen.view_idx.get() as usize)
+            .filter(|view| view.flags.intersects(ViewFlags::MODE_DYNAMIC))
+            .and_then(|_| view::calc_fallback_view(screen.view_idx.get() as usize, &view_tags,
+                                                   old_client_tags, client_tags, &screen_views));
+
+        if let Some(fallback_idx) = maybe_fallback_idx
+            && let Some(view) = subtle.views.borrow().get(fallback_idx)
+        {
+            view.focus(subtle, screen_idx, false, true)?;
+        }
+    }
+
+    debug!("{}", function_name!());
+
+    Ok(())
+}
+
+/// Create panel window with given id
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `win` - Id of the panel window
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn create_panel_win(subtle: &Subtle, win: Window) -> Result<()> {
+    let conn = subtle.conn.get().context("Failed to get connection")?;
+    let default_screen = &conn.setup().roots[subtle.screen_num];
+
+    let aux = CreateWindowAux::default()
+        .event_mask(EventMask::BUTTON_PRESS
+            | EventMask::POINTER_MOTION
+            | EventMask::ENTER_WINDOW
+            | EventMask::LEAVE_WINDOW
+            | EventMask::EXPOSURE)
+        .override_redirect(1)
+        .background_pixmap(BackPixmap::PARENT_RELATIVE);
+
+    conn.create_window(COPY_DEPTH_FROM_PARENT, win, default_screen.root,
+                       0, 0, 1, 1, 0,
+                       WindowClass::INPUT_OUTPUT, default_screen.root_visual, &aux)?.check()?;
+
+    debug!("{}: win={}", function_name!(), win);
+
+    Ok(())
+}
+
+/// Move and map panel window when enabled or otherwise unmap it
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `screen` - Screen of the panel
+/// * `is_bottom` - Whether to configure the bottom panel
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn configure_panel_win(subtle: &Subtle, screen: &Screen, is_bottom: bool) -> Result<()> {
+    let conn = subtle.conn.get().context("Failed to get connection")?;
+
+    let (win, flag, y) = if is_bottom {
+        (screen.bottom_panel_win, ScreenFlags::BOTTOM_PANEL,
+         screen.base.y as i32 + screen.base.height as i32 - subtle.panel_height as i32)
+    } else {
+        (screen.top_panel_win, ScreenFlags::TOP_PANEL, screen.base.y as i32)
+    };
+
+    if screen.flags.intersects(flag) {
+        let (offset_x, width) = screen.get_panel_geom(is_bottom);
+
+        let aux = ConfigureWindowAux::default()
+            .x(screen.base.x as i32 + offset_x as i32)
+            .y(y)
+            .width(width as u32)
+            .height(subtle.panel_height as u32)
+            .stack_mode(StackMode::ABOVE);
+
+        conn.configure_window(win, &aux)?.check()?;
+        conn.map_window(win)?.check()?;
+    } else {
+        conn.unmap_window(win)?.check()?;
+    }
+
+    debug!("{}: win={}, is_bottom={}", function_name!(), win, is_bottom);
+
+    Ok(())
+}
+
+/// Find own window that needs to be recreated after it has been destroyed
+///
+/// # Arguments
+///
+/// * `win` - Destroyed window
+/// * `event_win` - Window the event has been reported on
+/// * `panel_wins` - Top and bottom panel window of every screen
+/// * `tray_win` - Tray window
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] own window or otherwise [`None`]
+pub(crate) fn find_own_win(win: Window, event_win: Window, panel_wins: &[(Window, Window)],
+                           tray_win: Window) -> Option<OwnWin>
+{
+    // The tray is reported on root and itself while it isn't embedded
+    if win == tray_win {
+        return (event_win == tray_win).then_some(OwnWin::Tray);
+    }
+
+    panel_wins.iter().enumerate().find_map(|(screen_idx, (top_win, bottom_win))| {
+        if *top_win == win {
+            Some(OwnWin::TopPanel(screen_idx))
+        } else if *bottom_win == win {
+            Some(OwnWin::BottomPanel(screen_idx))
+        } else {
+            None
+        }
+    })
+}
+
+/// Recreate own window with the same id after it has been destroyed
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `own_win` - Destroyed own window
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn recreate_win(subtle: &Subtle, own_win: OwnWin) -> Result<()> {
+    let conn = subtle.conn.get().context("Failed to get connection")?;
+
+    warn!("Recreating destroyed window {:?}", own_win);
+
+    match own_win {
+        OwnWin::TopPanel(screen_idx) | OwnWin::BottomPanel(screen_idx) => {
+            let screen = subtle.screens.get(screen_idx).context("Screen not found?")?;
+            let is_bottom = matches!(own_win, OwnWin::BottomPanel(_));
+
+            create_panel_win(subtle, if is_bottom { screen.bottom_panel_win } else { screen.top_panel_win })?;
+            configure_panel_win(subtle, screen, is_bottom)?;
+        },
+        OwnWin::Tray => {
+            display::create_tray_win(subtle)?;
+
+            // Embed surviving tray icons again
+            #[cfg(feature = "tray")]
+            for tray in subtle.trays.borrow().iter() {
+                if let Err(err) = conn.reparent_window(tray.win, subtle.tray_win, 0, 0)?.check() {
+                    warn!("Failed to reparent tray icon {}: {}", tray.win, err);
+                }
+            }
+
+            // Selection is lost along with its owner
+            if subtle.flags.intersects(SubtleFlags::TRAY) {
+                display::select_tray(subtle)?;
+            }
+        },
+    }
+
+    panel::update(subtle)?;
+    panel::render(subtle)?;
+
+    debug!("{}: own_win={:?}", function_name!(), own_win);
+
+    Ok(())
+}
+
 /// Resize screen
 ///
 /// # Arguments
@@ -447,56 +1249,33 @@ pub(crate) fn configure(subtle: &Subtle) -> Result<()> {
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
 pub(crate) fn resize(subtle: &mut Subtle) -> Result<()> {
-    let conn = subtle.conn.get().unwrap();
-
     for screen in subtle.screens.iter_mut() {
 
         // Add strut
-        screen.geom.x = screen.base.x + subtle.clients_style.padding.left;
-        screen.geom.y = screen.base.y + subtle.clients_style.padding.top;
-        screen.geom.width = (screen.base.width as i16 - subtle.clients_style.padding.left
-            - subtle.clients_style.padding.right) as u16;
-        screen.geom.height = (screen.base.height as i16 - subtle.clients_style.padding.top
-            - subtle.clients_style.padding.bottom) as u16;
-
-        // Update panels
+        screen.geom.x = checked_to_i16(screen.base.x as i32 + subtle.clients_style.padding.left as i32);
+        screen.geom.y = checked_to_i16(screen.base.y as i32 + subtle.clients_style.padding.top as i32);
+        screen.geom.width = checked_to_u16(screen.base.width as i32
+            - subtle.clients_style.padding.left as i32 - subtle.clients_style.padding.right as i32);
+        screen.geom.height = checked_to_u16(screen.base.height as i32
+            - subtle.clients_style.padding.top as i32 - subtle.clients_style.padding.bottom as i32);
+
+        // Update height, the whole height is reserved to keep clients clear of bars next to it
         if screen.flags.intersects(ScreenFlags::TOP_PANEL) {
-            let aux = ConfigureWindowAux::default()
-                .x(screen.base.x as i32)
-                .y(screen.base.y as i32)
-                .width(screen.base.width as u32)
-                .height(subtle.panel_height as u32)
-                .stack_mode(StackMode::ABOVE);
-
-            conn.configure_window(screen.top_panel_win, &aux)?.check()?;
-            conn.map_window(screen.top_panel_win)?.check()?;
-
-            // Update height
             screen.geom.y += subtle.panel_height as i16;
             screen.geom.height -= subtle.panel_height;
-        } else {
-            conn.unmap_window(screen.top_panel_win)?.check()?;
         }
 
         if screen.flags.intersects(ScreenFlags::BOTTOM_PANEL) {
-            let aux = ConfigureWindowAux::default()
-                .x(screen.base.x as i32)
-                .y(screen.base.y as i32 + screen.base.height as i32
-                    - subtle.panel_height as i32)
-                .width(screen.base.width as u32)
-                .height(subtle.panel_height as u32)
-                .stack_mode(StackMode::ABOVE);
-
-            conn.configure_window(screen.bottom_panel_win, &aux)?.check()?;
-            conn.map_window(screen.bottom_panel_win)?.check()?;
-
-            // Update height
             screen.geom.height -= subtle.panel_height;
-        } else {
-            conn.unmap_window(screen.bottom_panel_win)?.check()?;
         }
     }
 
+    // Update panels
+    for screen in subtle.screens.iter() {
+        configure_panel_win(subtle, screen, false)?;
+        configure_panel_win(subtle, screen, true)?;
+    }
+
     panel::resize_double_buffer(subtle)?;
 
     debug!("{}", function_name!());
@@ -504,6 +1283,71 @@ pub(crate) fn resize(subtle: &mut Subtle) -> Result<()> {
     Ok(())
 }
 
+/// Hide configured panels or show hidden ones again
+///
+/// # Arguments
+///
+/// * `flags` - Flags of the screen
+/// * `panels` - Panels to toggle
+///
+/// # Returns
+///
+/// The toggled [`ScreenFlags`]
+pub(crate) fn calc_toggled_panels(flags: ScreenFlags, panels: ScreenFlags) -> ScreenFlags {
+    let mut flags = flags;
+
+    for (shown, hidden) in [(ScreenFlags::TOP_PANEL, ScreenFlags::HIDDEN_TOP_PANEL),
+        (ScreenFlags::BOTTOM_PANEL, ScreenFlags::HIDDEN_BOTTOM_PANEL)]
+    {
+        // Screens without configured panel stay as they are
+        if panels.contains(shown) && flags.intersects(shown | hidden) {
+            flags.toggle(shown | hidden);
+        }
+    }
+
+    flags
+}
+
+/// Toggle panels of a screen and rearrange clients into the freed or consumed space
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `screen_idx` - Index of the screen
+/// * `panels` - Panels to toggle
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn toggle_panels(subtle: &mut Subtle, screen_idx: usize, panels: ScreenFlags) -> Result<()> {
+    let screen = subtle.screens.get_mut(screen_idx).context("Unknown screen")?;
+    let flags = calc_toggled_panels(screen.flags, panels);
+
+    if flags.bits() == screen.flags.bits() {
+        return Ok(());
+    }
+
+    screen.flags = flags;
+
+    // Bounds of clients on this screen and of zaphod clients depend on the panels
+    for client in subtle.clients.borrow_mut().iter_mut() {
+        if screen_idx as isize == client.screen_idx || client.flags.intersects(ClientFlags::MODE_ZAPHOD) {
+            client.flags.insert(ClientFlags::ARRANGE);
+        }
+    }
+
+    resize(subtle)?;
+    configure(subtle)?;
+    publish(subtle, true)?;
+
+    panel::update(subtle)?;
+    panel::render(subtle)?;
+
+    debug!("{}: screen_idx={}, panels={:?}", function_name!(), screen_idx, panels);
+
+    Ok(())
+}
+
 /// Publish and export all relevant atoms to allow IPC
 ///
 /// # Arguments
@@ -523,7 +1367,6 @@ pub(crate) fn publish(subtle: &Subtle, publish_all: bool) -> Result<()> {
     if publish_all {
         let mut workareas: Vec<u32> = Vec::with_capacity(4 * subtle.screens.len());
         let mut panels: Vec<u32> = Vec::with_capacity(2 * subtle.screens.len());
-        let mut viewports: Vec<u32> = Vec::with_capacity(2 * subtle.screens.len());
 
         for screen in subtle.screens.iter() {
             workareas.push(screen.geom.x as u32);
@@ -535,9 +1378,6 @@ pub(crate) fn publish(subtle: &Subtle, publish_all: bool) -> Result<()> {
                 subtle.panel_height as u32 } else { 0 });
             panels.push(if screen.flags.intersects(ScreenFlags::BOTTOM_PANEL) {
                 subtle.panel_height as u32 } else { 0 });
-
-            viewports.push(0);
-            viewports.push(0);
         }
 
         // EWMH: Workarea
@@ -548,9 +1388,11 @@ pub(crate) fn publish(subtle: &Subtle, publish_all: bool) -> Result<()> {
         conn.change_property32(PropMode::REPLACE, default_screen.root, atoms.SUBTLE_SCREEN_PANELS,
                                AtomEnum::CARDINAL, &panels)?.check()?;
 
-        // EWMH: Desktop viewport
-        conn.change_property32(PropMode::REPLACE, default_screen.root, atoms._NET_DESKTOP_VIEWPORT,
-                               AtomEnum::CARDINAL, &viewports)?.check()?;
+        // EWMH: Desktop geometry
+        let data: [u32; 2] = [subtle.width as u32, subtle.height as u32];
+
+        conn.change_property32(PropMode::REPLACE, default_screen.root, atoms._NET_DESKTOP_GEOMETRY,
+                               AtomEnum::CARDINAL, &data)?.check()?;
     }
 
     let mut views: Vec<u32> = Vec::with_capacity(subtle.screens.len());
@@ -569,3 +1411,141 @@ pub(crate) fn publish(subtle: &Subtle, publish_all: bool) -> Result<()> {
 
     Ok(())
 }
+
+/// Jump to screen and focus the next client there
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `screen_idx` - Index of the screen
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn jump(subtle: &Subtle, screen_idx: usize) -> Result<()> {
+    let screen = subtle.screens.get(screen_idx).context("Unknown screen")?;
+
+    screen.warp(subtle)?;
+
+    if let Some(focus_client) = subtle.find_next_client(screen_idx as isize, false) {
+        focus_client.focus(subtle, false)?;
+    }
+
+    view::publish_current(subtle, screen_idx)?;
+
+    debug!("{}: screen_idx={}", function_name!(), screen_idx);
+
+    Ok(())
+}
+
+/// Re-detect screens after the layout changed e.g. via xrandr
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn update(subtle: &mut Subtle) -> Result<()> {
+    let conn = subtle.conn.get().context("Failed to get connection")?;
+    let default_screen = &conn.setup().roots[subtle.screen_num];
+
+    // Update size of the root window
+    let root_geom = conn.get_geometry(default_screen.root)?.reply()?;
+
+    subtle.width = root_geom.width;
+    subtle.height = root_geom.height;
+
+    // Virtual screens are split from the config, so we cannot map them
+    if subtle.screens.iter().any(|screen| screen.flags.intersects(ScreenFlags::VIRTUAL)) {
+        warn!("Cannot update virtual screens, please restart");
+
+        update_desktop_size(subtle, root_geom.width, root_geom.height);
+
+        return publish(subtle, true);
+    }
+
+    let geoms = query_geoms(subtle)?;
+    let old_bases: Vec<Rectangle> = subtle.screens.iter().map(|screen| screen.base).collect();
+
+    // Release tray before its host panel is destroyed with the screen
+    let is_tray_host_removed = subtle.screens.iter().skip(geoms.len())
+        .any(|screen| screen.panels.iter()
+            .any(|panel| panel.flags.contains(PanelFlags::TRAY | PanelFlags::TRAY_HOST)));
+
+    if is_tray_host_removed {
+        subtle.release_tray_win()?;
+    }
+
+    // Destroy panel windows of removed screens
+    while subtle.screens.len() > geoms.len() {
+        if let Some(screen) = subtle.screens.pop() {
+            conn.destroy_window(screen.top_panel_win)?;
+            conn.destroy_window(screen.bottom_panel_win)?;
+        }
+    }
+
+    // Move tray to a surviving screen
+    if is_tray_host_removed {
+        select_tray_host(subtle, None);
+    }
+
+    // Update remaining screens and keep their views
+    for (screen, geom) in subtle.screens.iter_mut().zip(geoms.iter()) {
+        screen.base = *geom;
+        screen.geom = *geom;
+    }
+
+    // Create new screens with a free view
+    for geom in geoms.iter().skip(subtle.screens.len()) {
+        let screen = Screen::new(subtle, geom.x, geom.y, geom.width, geom.height)?;
+        let used_views: Vec<isize> = subtle.screens.iter().map(|screen| screen.view_idx.get()).collect();
+
+        screen.view_idx.set(find_free_view(&used_views, subtle.views.borrow().len()));
+
+        subtle.screens.push(screen);
+    }
+
+    // Move clients of removed screens to the first one
+    let nscreens = subtle.screens.len() as isize;
+    let first_base = subtle.screens[0].base;
+
+    for client in subtle.clients.borrow_mut().iter_mut() {
+        if nscreens <= client.screen_idx {
+            if client.flags.intersects(ClientFlags::MODE_FLOAT)
+                && let Some(old_base) = old_bases.get(client.screen_idx as usize)
+            {
+                client.geom = calc_migrated_geom(client.geom, *old_base, first_base);
+            }
+
+            if let Some(saved_geom) = client.saved_geom
+                && let Some(old_base) = old_bases.get(client.screen_idx as usize)
+            {
+                client.saved_geom = Some(calc_migrated_geom(saved_geom, *old_base, first_base));
+            }
+
+            client.screen_idx = 0;
+            client.flags.insert(ClientFlags::ARRANGE);
+        }
+    }
+
+    update_desktop_size(subtle, root_geom.width, root_geom.height);
+
+    resize(subtle)?;
+    configure(subtle)?;
+
+    panel::update(subtle)?;
+    panel::render(subtle)?;
+
+    publish(subtle, true)?;
+    view::publish_current(subtle, view::find_current_screen_idx(subtle))?;
+
+    corner::recreate(subtle)?;
+
+    info!("Running on {} screen(s)", subtle.screens.len());
+
+    debug!("{}: nscreens={}", function_name!(), subtle.screens.len());
+
+    Ok(())
+}
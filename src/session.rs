@@ -0,0 +1,539 @@
+//!
+//! @package subtle-rs
+//!
+//! @file Session functions
+//! @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+//! @version $Id$
+//!
+//! This program can be distributed under the terms of the GNU GPLv3.
+//! See the file LICENSE for details.
+//!
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::{Context, Result};
+use log::debug;
+use stdext::function_name;
+use x11rb::protocol::xproto::Rectangle;
+use crate::client::{Client, ClientFlags};
+use crate::subtle::Subtle;
+use crate::tag::TagFlags;
+use crate::tagging;
+use crate::tagging::Tagging;
+use crate::validate::ValidGravityIdx;
+
+/// Max age of session entries in seconds (30 days)
+pub(crate) const MAX_AGE: u64 = 30 * 24 * 60 * 60;
+
+/// Max number of session entries
+pub(crate) const MAX_ENTRIES: usize = 64;
+
+/// Kind of geometry lines in the session file
+const GEOMETRY_KIND: &str = "geometry";
+
+/// Kind of gravity lines in the session file
+const GRAVITY_KIND: &str = "gravity";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct SessionKey {
+    /// Window class
+    pub(crate) klass: String,
+    /// Window instance
+    pub(crate) instance: String,
+    /// Window role
+    pub(crate) role: String,
+}
+
+impl SessionKey {
+    /// Create key from identifying client properties
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - Client to create the key for
+    ///
+    /// # Returns
+    ///
+    /// A [`Option`] with either [`Some`] key or otherwise [`None`] for clients without class and instance
+    pub(crate) fn from_client(client: &Client) -> Option<Self> {
+        // Unnamed clients would all share one key
+        if client.klass.is_empty() && client.instance.is_empty() {
+            return None;
+        }
+
+        Some(SessionKey {
+            klass: client.klass.clone(),
+            instance: client.instance.clone(),
+            role: client.role.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct GeometryEntry {
+    /// Remembered float geometry
+    pub(crate) geom: Rectangle,
+    /// Unix time of the last update
+    pub(crate) updated_at: u64,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct GravityEntry {
+    /// Remembered gravity per view
+    pub(crate) gravities: Vec<usize>,
+    /// Unix time of the last use
+    pub(crate) updated_at: u64,
+}
+
+#[derive(Default, Debug)]
+pub(crate) struct SessionStore {
+    /// Remembered float geometries
+    pub(crate) geometries: HashMap<SessionKey, GeometryEntry>,
+    /// Remembered gravities
+    pub(crate) gravities: HashMap<SessionKey, GravityEntry>,
+}
+
+impl SessionStore {
+    /// Remember float geometry for key
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Identifying key
+    /// * `geom` - Geometry to remember
+    /// * `now` - Current unix time
+    pub(crate) fn remember_geom(&mut self, key: SessionKey, geom: Rectangle, now: u64) {
+        self.geometries.insert(key, GeometryEntry {
+            geom,
+            updated_at: now,
+        });
+    }
+
+    /// Look up remembered geometry for key
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Identifying key
+    ///
+    /// # Returns
+    ///
+    /// A [`Option`] with either [`Some`] geometry or otherwise [`None`]
+    pub(crate) fn lookup_geom(&self, key: &SessionKey) -> Option<Rectangle> {
+        self.geometries.get(key).map(|entry| entry.geom)
+    }
+
+    /// Remember gravities per view for key
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Identifying key
+    /// * `gravities` - Gravity per view
+    /// * `now` - Current unix time
+    pub(crate) fn remember_gravities(&mut self, key: SessionKey, gravities: &[usize], now: u64) {
+        self.gravities.insert(key, GravityEntry {
+            gravities: gravities.to_vec(),
+            updated_at: now,
+        });
+    }
+
+    /// Look up remembered gravities for key and mark them as used
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Identifying key
+    /// * `now` - Current unix time
+    ///
+    /// # Returns
+    ///
+    /// A [`Option`] with either [`Some`] gravities per view or otherwise [`None`]
+    pub(crate) fn lookup_gravities(&mut self, key: &SessionKey, now: u64) -> Option<Vec<usize>> {
+        self.gravities.get_mut(key).map(|entry| {
+            entry.updated_at = now;
+
+            entry.gravities.clone()
+        })
+    }
+
+    /// Evict entries that are too old and then the oldest ones beyond the limit
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - Current unix time
+    /// * `max_age` - Max age in seconds
+    /// * `max_entries` - Max number of entries per kind
+    pub(crate) fn evict(&mut self, now: u64, max_age: u64, max_entries: usize) {
+        evict_entries(&mut self.geometries, |entry| entry.updated_at, now, max_age, max_entries);
+        evict_entries(&mut self.gravities, |entry| entry.updated_at, now, max_age, max_entries);
+    }
+
+    /// Serialize store to session file format
+    ///
+    /// # Returns
+    ///
+    /// A [`String`] with one tab-separated entry per line
+    pub(crate) fn serialize(&self) -> String {
+        let mut lines: Vec<String> = self.geometries.iter()
+            .map(|(key, entry)| format!("{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}", GEOMETRY_KIND,
+                entry.updated_at, escape(&key.klass), escape(&key.instance), escape(&key.role),
+                entry.geom.x, entry.geom.y, entry.geom.width, entry.geom.height))
+            .chain(self.gravities.iter()
+                .map(|(key, entry)| format!("{}\t{}\t{}\t{}\t{}\t{}", GRAVITY_KIND,
+                    entry.updated_at, escape(&key.klass), escape(&key.instance), escape(&key.role),
+                    entry.gravities.iter().map(|gravity| gravity.to_string())
+                        .collect::<Vec<String>>().join(","))))
+            .collect();
+
+        lines.sort();
+
+        lines.join("\n")
+    }
+
+    /// Parse store from session file format and skip malformed lines
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Content of the session file
+    ///
+    /// # Returns
+    ///
+    /// A new [`SessionStore`]
+    pub(crate) fn parse(data: &str) -> Self {
+        let mut store = SessionStore::default();
+
+        for line in data.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+
+            if 6 == fields.len() && GRAVITY_KIND == fields[0] {
+                let (Ok(updated_at), Ok(gravities)) = (fields[1].parse::<u64>(), fields[5].split(',')
+                    .map(|gravity| gravity.parse::<usize>())
+                    .collect::<Result<Vec<usize>, _>>()) else {
+                    continue;
+                };
+
+                store.gravities.insert(SessionKey {
+                    klass: fields[2].to_string(),
+                    instance: fields[3].to_string(),
+                    role: fields[4].to_string(),
+                }, GravityEntry {
+                    gravities,
+                    updated_at,
+                });
+
+                continue;
+            }
+
+            if 9 != fields.len() || GEOMETRY_KIND != fields[0] {
+                continue;
+            }
+
+            let (Ok(updated_at), Ok(x), Ok(y), Ok(width), Ok(height)) = (fields[1].parse::<u64>(),
+                fields[5].parse::<i16>(), fields[6].parse::<i16>(),
+                fields[7].parse::<u16>(), fields[8].parse::<u16>()) else {
+                continue;
+            };
+
+            store.geometries.insert(SessionKey {
+                klass: fields[2].to_string(),
+                instance: fields[3].to_string(),
+                role: fields[4].to_string(),
+            }, GeometryEntry {
+                geom: Rectangle { x, y, width, height },
+                updated_at,
+            });
+        }
+
+        store
+    }
+}
+
+/// Evict entries of a map that are too old and then the oldest ones beyond the limit
+///
+/// # Arguments
+///
+/// * `entries` - Entries to evict from
+/// * `get_updated_at` - Get unix time of the last update of an entry
+/// * `now` - Current unix time
+/// * `max_age` - Max age in seconds
+/// * `max_entries` - Max number of entries
+fn evict_entries<T>(entries: &mut HashMap<SessionKey, T>, get_updated_at: impl Fn(&T) -> u64,
+                    now: u64, max_age: u64, max_entries: usize)
+{
+    entries.retain(|_, entry| now.saturating_sub(get_updated_at(entry)) <= max_age);
+
+    if entries.len() > max_entries {
+        let mut updates: Vec<(u64, SessionKey)> = entries.iter()
+            .map(|(key, entry)| (get_updated_at(entry), key.clone()))
+            .collect();
+
+        // Keep newest entries
+        updates.sort_unstable_by_key(|(updated_at, _)| Reverse(*updated_at));
+
+        for (_, key) in updates.into_iter().skip(max_entries) {
+            entries.remove(&key);
+        }
+    }
+}
+
+/// Replace separators of the session file format
+///
+/// # Arguments
+///
+/// * `value` - Value to escape
+///
+/// # Returns
+///
+/// The value without tabs and newlines
+fn escape(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+/// Select geometry of a managed client
+///
+/// # Arguments
+///
+/// * `remembered` - Geometry from the session store
+/// * `tag_geom` - Static geometry of a matching tag
+/// * `hint_geom` - Geometry requested by the client
+///
+/// # Returns
+///
+/// The geometry with precedence remembered > tag geometry > hints
+pub(crate) fn select_geom(remembered: Option<Rectangle>, tag_geom: Option<Rectangle>,
+                          hint_geom: Rectangle) -> Rectangle
+{
+    remembered.or(tag_geom).unwrap_or(hint_geom)
+}
+
+/// Select gravities per view of a managed client
+///
+/// # Arguments
+///
+/// * `remembered` - Gravities from the session store
+/// * `tagged` - Whether a tag set the gravity per view
+/// * `current` - Current gravities per view
+/// * `ngravities` - Number of gravities
+///
+/// # Returns
+///
+/// The gravities with precedence tag gravity > remembered > current
+pub(crate) fn select_gravities(remembered: &[usize], tagged: &[bool], current: &[usize],
+                               ngravities: usize) -> Vec<usize>
+{
+    current.iter()
+        .enumerate()
+        .map(|(view_idx, gravity)| {
+            if tagged.get(view_idx).is_some_and(|is_tagged| *is_tagged) {
+                *gravity
+            } else {
+                remembered.get(view_idx)
+                    .and_then(|remembered_gravity| u32::try_from(*remembered_gravity).ok())
+                    .and_then(|raw| ValidGravityIdx::new(raw, ngravities).ok())
+                    .map_or(*gravity, ValidGravityIdx::get)
+            }
+        })
+        .collect()
+}
+
+/// Get path of the session file
+///
+/// # Arguments
+///
+/// * `xdg_state_home` - Value of XDG_STATE_HOME
+/// * `home` - Value of HOME
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] path or otherwise [`None`]
+pub(crate) fn get_path(xdg_state_home: Option<&str>, home: Option<&str>) -> Option<PathBuf> {
+    xdg_state_home.filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home.map(|home| Path::new(home).join(".local").join("state")))
+        .map(|path| path.join("subtle-rs").join("session"))
+}
+
+/// Get current unix time
+///
+/// # Returns
+///
+/// Seconds since the unix epoch
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// Get tags that remember the geometry of matching clients
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Tagging`] of all tags with the remember option
+fn get_remember_tags(subtle: &Subtle) -> Tagging {
+    subtle.tags.borrow().iter()
+        .enumerate()
+        .filter(|(_, tag)| tag.flags.contains(TagFlags::REMEMBER))
+        .fold(Tagging::empty(), |acc, (tag_idx, _)| acc | tagging::tag_bit(tag_idx))
+}
+
+/// Apply remembered geometry to a newly managed client
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `client` - Client to apply the geometry to
+/// * `mode_flags` - Mode flags to set for this client
+pub(crate) fn restore_geom(subtle: &Subtle, client: &mut Client, mode_flags: &mut ClientFlags) {
+    if !client.tags.intersects(get_remember_tags(subtle)) {
+        return;
+    }
+
+    let Some(key) = SessionKey::from_client(client) else {
+        return;
+    };
+
+    let remembered = subtle.session.borrow().lookup_geom(&key);
+
+    let tag_geom = subtle.tags.borrow().iter()
+        .enumerate()
+        .find(|(tag_idx, tag)| tag.flags.contains(TagFlags::REMEMBER | TagFlags::GEOMETRY)
+            && client.tags.intersects(tagging::tag_bit(*tag_idx)))
+        .and_then(|(_, tag)| tag.geom);
+
+    if remembered.is_some() || tag_geom.is_some() {
+        client.geom = select_geom(remembered, tag_geom, client.geom);
+
+        mode_flags.insert(ClientFlags::MODE_FLOAT);
+    }
+
+    debug!("{}: key={:?}, remembered={:?}", function_name!(), key, remembered);
+}
+
+/// Apply remembered gravities to a newly managed client
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `client` - Client to apply the gravities to
+pub(crate) fn restore_gravities(subtle: &Subtle, client: &mut Client) {
+    let Some(key) = SessionKey::from_client(client) else {
+        return;
+    };
+
+    let remembered = subtle.session.borrow_mut().lookup_gravities(&key, now());
+
+    if let Some(remembered) = remembered.as_ref() {
+        // Tags with gravity take precedence
+        let gravity_tags = subtle.tags.borrow().iter()
+            .enumerate()
+            .filter(|(_, tag)| tag.flags.contains(TagFlags::GRAVITY))
+            .fold(Tagging::empty(), |acc, (tag_idx, _)| acc | tagging::tag_bit(tag_idx))
+            & client.tags;
+
+        let tagged: Vec<bool> = subtle.views.borrow().iter()
+            .map(|view| view.tags.get().intersects(gravity_tags))
+            .collect();
+
+        client.gravities = select_gravities(remembered, &tagged, &client.gravities, subtle.gravities.len());
+    }
+
+    debug!("{}: key={:?}, remembered={:?}", function_name!(), key, remembered);
+}
+
+/// Record gravities of a client
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `client` - Client to record
+pub(crate) fn record_gravities(subtle: &Subtle, client: &Client) {
+    let Some(key) = SessionKey::from_client(client) else {
+        return;
+    };
+
+    subtle.session.borrow_mut().remember_gravities(key.clone(), &client.gravities, now());
+
+    debug!("{}: key={:?}, gravities={:?}", function_name!(), key, client.gravities);
+}
+
+/// Record float geometry of a client that is about to be unmanaged
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `client` - Client to record
+pub(crate) fn record_geom(subtle: &Subtle, client: &Client) {
+    if !client.flags.contains(ClientFlags::MODE_FLOAT) || client.flags.contains(ClientFlags::MODE_FULL)
+        || !client.tags.intersects(get_remember_tags(subtle))
+    {
+        return;
+    }
+
+    let Some(key) = SessionKey::from_client(client) else {
+        return;
+    };
+
+    subtle.session.borrow_mut().remember_geom(key.clone(), client.geom, now());
+
+    debug!("{}: key={:?}, geom={:?}", function_name!(), key, client.geom);
+}
+
+/// Write session store to the session file
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn save(subtle: &Subtle) -> Result<()> {
+    let path = get_path(std::env::var("XDG_STATE_HOME").ok().as_deref(),
+                        std::env::var("HOME").ok().as_deref())
+        .context("Failed to find session path")?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Evict once per save instead of on every recorded change
+    let mut session = subtle.session.borrow_mut();
+
+    session.evict(now(), MAX_AGE, MAX_ENTRIES);
+
+    fs::write(&path, session.serialize())?;
+
+    debug!("{}: path={:?}", function_name!(), path);
+
+    Ok(())
+}
+
+/// Load session store from the session file
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn init(subtle: &mut Subtle) -> Result<()> {
+    let path = get_path(std::env::var("XDG_STATE_HOME").ok().as_deref(),
+                        std::env::var("HOME").ok().as_deref());
+
+    if let Some(path) = path.as_ref() && let Ok(data) = fs::read_to_string(path) {
+        let mut session = SessionStore::parse(&data);
+
+        session.evict(now(), MAX_AGE, MAX_ENTRIES);
+
+        *subtle.session.get_mut() = session;
+    }
+
+    debug!("{}: path={:?}, ngeometries={}, ngravities={}", function_name!(), path,
+        subtle.session.get_mut().geometries.len(), subtle.session.get_mut().gravities.len());
+
+    Ok(())
+}
@@ -10,7 +10,7 @@
 //!
 
 use bitflags::bitflags;
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use easy_min_max::max;
 use hex_color::HexColor;
 use log::{debug, warn};
@@ -21,9 +21,19 @@ use x11rb::protocol::xproto::{Colormap, ConnectionExt};
 use x11rb::rust_connection::RustConnection;
 use crate::config::{Config, MixedConfigVal};
 use crate::font::Font;
+use crate::grab::GapOrder;
+use crate::icon;
 use crate::spacing::Spacing;
+use crate::error;
+use crate::error::Classify;
 use crate::subtle::Subtle;
 
+/// Default step size of runtime gap adjustments
+pub(crate) const DEFAULT_GAP_STEP: i16 = 2;
+
+/// Upper bound of the gap on each side
+pub(crate) const MAX_GAP: i16 = 100;
+
 bitflags! {
     /// Config and state-flags for [`Style`]
     #[derive(Default, Debug, Clone)]
@@ -72,6 +82,10 @@ pub(crate) struct Style {
     pub(crate) margin: Spacing,
     /// Font id
     pub(crate) font_id: isize,
+    /// Separator string
+    pub(crate) sep_string: Option<String>,
+    /// Pixel width of the separator
+    pub(crate) sep_width: u16,
 }
 
 impl Style {
@@ -206,6 +220,9 @@ impl Default for Style {
             margin: Default::default(),
 
             font_id: -1,
+
+            sep_string: None,
+            sep_width: 0,
         }
     }
 }
@@ -372,6 +389,12 @@ fn parse_style(subtle: &mut Subtle, style_values: &HashMap<String, MixedConfigVa
         style.margin = Spacing::try_from(margin)?;
     }
 
+    // Handle separator
+    if let Some(MixedConfigVal::S(sep_string)) = style_values.get("separator") {
+        style.sep_string = Some(sep_string.clone());
+        style.flags.insert(StyleFlags::SEPARATOR);
+    }
+
     // Handle font
     if let Some(MixedConfigVal::S(font_name)) = style_values.get("font") {
         let font = Font::new(conn, font_name)?;
@@ -385,6 +408,149 @@ fn parse_style(subtle: &mut Subtle, style_values: &HashMap<String, MixedConfigVa
     Ok(style)
 }
 
+/// Get the name a style block can be referenced by
+///
+/// # Arguments
+///
+/// * `style_values` - Style values
+/// * `style_idx` - Index of the style block
+///
+/// # Returns
+///
+/// Either the name, the kind or a placeholder with the index of the block
+fn get_style_name(style_values: &HashMap<String, MixedConfigVal>, style_idx: usize) -> String {
+    match (style_values.get("name"), style_values.get("kind")) {
+        (Some(MixedConfigVal::S(name)), _) => name.clone(),
+        (_, Some(MixedConfigVal::S(kind))) => kind.clone(),
+        _ => format!("#{}", style_idx),
+    }
+}
+
+/// Resolve a single style block and all of its parents
+///
+/// # Arguments
+///
+/// * `styles` - Parsed style blocks
+/// * `style_idx` - Index of the block to resolve
+/// * `names` - Names of all blocks by index
+/// * `resolved` - Already resolved blocks
+/// * `path` - Indices of the blocks currently being resolved
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn resolve_style(styles: &[HashMap<String, MixedConfigVal>], style_idx: usize, names: &[String],
+                 resolved: &mut Vec<Option<HashMap<String, MixedConfigVal>>>,
+                 path: &mut Vec<usize>) -> Result<()>
+{
+    if resolved[style_idx].is_some() {
+        return Ok(());
+    }
+
+    // Check whether we already passed this block
+    if let Some(path_idx) = path.iter().position(|idx| *idx == style_idx) {
+        let cycle = path[path_idx..].iter()
+            .chain(std::iter::once(&style_idx))
+            .map(|idx| format!("`{}`", names[*idx]))
+            .collect::<Vec<_>>()
+            .join(" -> ");
+
+        return Err(anyhow!("Style inheritance cycle: {}", cycle));
+    }
+
+    let style_values = &styles[style_idx];
+    let mut values = HashMap::new();
+
+    if let Some(parent_val) = style_values.get("inherits") {
+        let parent_name = String::from(parent_val);
+
+        // Last block with a matching name wins, otherwise last one with a matching kind
+        let parent_idx = names.iter().rposition(|name| *name == parent_name)
+            .or_else(|| styles.iter().rposition(|values|
+                matches!(values.get("kind"), Some(MixedConfigVal::S(kind)) if *kind == parent_name)))
+            .ok_or_else(|| anyhow!("Style `{}` inherits from unknown style `{}`",
+                names[style_idx], parent_name))?;
+
+        path.push(style_idx);
+        resolve_style(styles, parent_idx, names, resolved, path)?;
+        path.pop();
+
+        // Copy values of parent except identifying ones
+        if let Some(parent_values) = &resolved[parent_idx] {
+            for (key, value) in parent_values.iter() {
+                if "kind" != key && "name" != key {
+                    values.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+
+    for (key, value) in style_values.iter() {
+        if "inherits" != key {
+            values.insert(key.clone(), value.clone());
+        }
+    }
+
+    resolved[style_idx] = Some(values);
+
+    Ok(())
+}
+
+/// Resolve inheritance between style blocks
+///
+/// # Arguments
+///
+/// * `styles` - Parsed style blocks
+///
+/// # Returns
+///
+/// A [`Result`] with either [`Vec`] of resolved blocks on success or otherwise [`anyhow::Error`]
+pub(crate) fn resolve_inheritance(styles: &[HashMap<String, MixedConfigVal>])
+    -> Result<Vec<HashMap<String, MixedConfigVal>>>
+{
+    let names: Vec<String> = styles.iter().enumerate()
+        .map(|(style_idx, style_values)| get_style_name(style_values, style_idx))
+        .collect();
+
+    let mut resolved = vec![None; styles.len()];
+    let mut path = Vec::new();
+
+    for style_idx in 0..styles.len() {
+        resolve_style(styles, style_idx, &names, &mut resolved, &mut path)?;
+    }
+
+    Ok(resolved.into_iter().flatten().collect())
+}
+
+/// Calculate client gaps after a runtime adjustment
+///
+/// # Arguments
+///
+/// * `margin` - Current gaps
+/// * `base` - Configured gaps to reset to
+/// * `order` - Whether to increase, decrease or reset
+/// * `step` - Step size on each side
+///
+/// # Returns
+///
+/// The new gaps clamped between zero and [`MAX_GAP`]
+pub(crate) fn calc_gap_margin(margin: Spacing, base: Spacing, order: GapOrder, step: i16) -> Spacing {
+    let delta = match order {
+        GapOrder::Increase => step,
+        GapOrder::Decrease => -step,
+        GapOrder::Reset => return base,
+    };
+
+    let adjust = |value: i16| value.saturating_add(delta).clamp(0, MAX_GAP);
+
+    Spacing {
+        top: adjust(margin.top),
+        right: adjust(margin.right),
+        bottom: adjust(margin.bottom),
+        left: adjust(margin.left),
+    }
+}
+
 /// Check config and init all style related options
 ///
 /// # Arguments
@@ -396,22 +562,36 @@ fn parse_style(subtle: &mut Subtle, style_values: &HashMap<String, MixedConfigVa
 ///
 /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
 pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
+    let styles = resolve_inheritance(&config.styles)?;
 
-    for style_values in config.styles.iter() {
+    for style_values in styles.iter() {
         if let Some(MixedConfigVal::S(kind)) = style_values.get("kind") {
+            // Ensure sane base values
+            let default_value = if matches!(kind.as_str(), "all" | "tray" | "clients") { 0 } else { -1 };
+
+            // Keep default style on invalid values
+            let result = parse_style(subtle, style_values, default_value)
+                .with_context(|| format!("Invalid style `{}`", kind))
+                .recoverable();
+
+            let Some(style) = error::collect(subtle, result)? else {
+                continue;
+            };
+
             match kind.as_str() {
-                "all" => subtle.all_style = parse_style(subtle, style_values, 0)?, // Ensure sane base values
-                "views" => subtle.views_style = parse_style(subtle, style_values, -1)?,
-                "active_views" => subtle.views_active_style = parse_style(subtle, style_values, -1)?,
-                "occupied_views" => subtle.views_occupied_style = parse_style(subtle, style_values, -1)?,
-                "visible_views" => subtle.views_visible_style = parse_style(subtle, style_values, -1)?,
-                "separator" => subtle.separator_style = parse_style(subtle, style_values, -1)?,
-                "top_panel" => subtle.top_panel_style = parse_style(subtle, style_values, -1)?,
-                "bottom_panel" => subtle.bottom_panel_style = parse_style(subtle, style_values, -1)?,
-                "tray" => subtle.tray_style = parse_style(subtle, style_values, 0)?,
-                "urgent" => subtle.urgent_style = parse_style(subtle, style_values, -1)?,
-                "clients" => subtle.clients_style = parse_style(subtle, style_values, 0)?,
-                "title" => subtle.title_style = parse_style(subtle, style_values, -1)?,
+                "all" => subtle.all_style = style,
+                "views" => subtle.views_style = style,
+                "active_views" => subtle.views_active_style = style,
+                "occupied_views" => subtle.views_occupied_style = style,
+                "visible_views" => subtle.views_visible_style = style,
+                "hover_views" => subtle.views_hover_style = style,
+                "separator" => subtle.separator_style = style,
+                "top_panel" => subtle.top_panel_style = style,
+                "bottom_panel" => subtle.bottom_panel_style = style,
+                "tray" => subtle.tray_style = style,
+                "urgent" => subtle.urgent_style = style,
+                "clients" => subtle.clients_style = style,
+                "title" => subtle.title_style = style,
                 _ => warn!("Unknown style kind `{}`", kind),
             }
         }
@@ -444,6 +624,41 @@ macro_rules! update_panel_height {
     };
 }
 
+/// Scale icons of views and panels into the panel height
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+fn rescale_icons(subtle: &Subtle) -> Result<()> {
+    let max_height = icon::calc_max_height(subtle.panel_height, &subtle.views_style);
+
+    for view in subtle.views.borrow_mut().iter_mut() {
+        if let Some(icon) = view.icon.as_mut() {
+            icon.rescale(subtle, max_height)?;
+        }
+    }
+
+    let max_height = icon::calc_max_height(subtle.panel_height, &subtle.separator_style);
+
+    for screen in subtle.screens.iter() {
+        for panel_idx in 0..screen.panels.len() {
+            if let Some(mut panel) = screen.panels.borrow_mut(panel_idx)
+                && let Some(icon) = panel.icon.as_mut()
+            {
+                icon.rescale(subtle, max_height)?;
+            }
+        }
+    }
+
+    debug!("{}: panel_height={}", function_name!(), subtle.panel_height);
+
+    Ok(())
+}
+
 /// Update all styles
 ///
 /// # Arguments
@@ -459,6 +674,7 @@ pub(crate) fn update(subtle: &mut Subtle) -> Result<()> {
     subtle.views_active_style.inherit(&subtle.views_style);
     subtle.views_occupied_style.inherit(&subtle.views_style);
     subtle.views_visible_style.inherit(&subtle.views_style);
+    subtle.views_hover_style.inherit(&subtle.views_style);
     subtle.title_style.inherit(&subtle.all_style);
     subtle.tray_style.inherit(&subtle.all_style);
     subtle.urgent_style.inherit(&subtle.all_style);
@@ -466,11 +682,26 @@ pub(crate) fn update(subtle: &mut Subtle) -> Result<()> {
     subtle.top_panel_style.inherit(&subtle.all_style);
     subtle.bottom_panel_style.inherit(&subtle.all_style);
 
+    // Update width of view separator
+    let mut sep_width = 0;
+
+    if subtle.views_style.flags.intersects(StyleFlags::SEPARATOR)
+        && let Some(sep_string) = &subtle.views_style.sep_string
+        && let Some(font) = subtle.views_style.get_font(subtle)
+        && let Some(conn) = subtle.conn.get()
+        && let Ok((width, _, _)) = font.calc_text_width(conn, sep_string, false)
+    {
+        sep_width = width + subtle.views_style.calc_spacing(CalcSpacing::Width) as u16;
+    }
+
+    subtle.views_style.sep_width = sep_width;
+
     // Update panel heights
     update_panel_height!(subtle, views_style);
     update_panel_height!(subtle, views_active_style);
     update_panel_height!(subtle, views_occupied_style);
     update_panel_height!(subtle, views_visible_style);
+    update_panel_height!(subtle, views_hover_style);
     update_panel_height!(subtle, title_style);
     update_panel_height!(subtle, tray_style);
     update_panel_height!(subtle, urgent_style);
@@ -478,6 +709,12 @@ pub(crate) fn update(subtle: &mut Subtle) -> Result<()> {
     update_panel_height!(subtle, top_panel_style);
     update_panel_height!(subtle, bottom_panel_style);
 
+    // Icons are loaded before the panel height is known
+    rescale_icons(subtle)?;
+
+    // Runtime gaps start with the configured ones
+    subtle.clients_margin.set(subtle.clients_style.margin);
+
     debug!("{}", function_name!());
 
     Ok(())
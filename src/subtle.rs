@@ -9,34 +9,50 @@
 //! See the file LICENSE for details.
 //!
 
-use crate::client::{Client, RestackOrder};
+use crate::client::{Client, KeyboardDrag, RestackOrder};
 use crate::config::{Config, MixedConfigVal};
 use crate::gravity::Gravity;
 use crate::tag::Tag;
 use crate::view::View;
 use bitflags::bitflags;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use std::cell::{Cell, OnceCell, Ref, RefCell, RefMut};
+use std::collections::HashMap;
+use std::os::unix::net::UnixStream;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::time::Duration;
 use easy_min_max::max;
-use log::debug;
+use log::{debug, warn};
+use regex::Regex;
 use stdext::function_name;
-use veccell::VecCell;
 use x11rb::connection::Connection;
-use x11rb::NONE;
-use x11rb::protocol::xproto::{ChangeWindowAttributesAux, ConfigureWindowAux, ConnectionExt, Cursor, Gcontext, Keycode, ModMask, Pixmap, StackMode, Window};
+use x11rb::{CURRENT_TIME, NONE};
+use x11rb::protocol::xproto::{Atom, ChangeWindowAttributesAux, ConfigureWindowAux, ConnectionExt, Cursor, Gcontext, Keycode, ModMask, Pixmap, Rectangle, StackMode, Timestamp, Window};
 use x11rb::rust_connection::RustConnection;
+use crate::corner::{Corner, CornerAction, DEFAULT_CORNER_DELAY, DEFAULT_CORNER_SIZE};
+use crate::effect;
+use crate::effect::{Effect, Executor};
 use crate::ewmh::Atoms;
+use crate::flash::Flash;
 use crate::font::Font;
-use crate::grab::Grab;
-use crate::plugin::Plugin;
-use crate::screen::Screen;
-use crate::style::{CalcSpacing, Style};
+use crate::grab;
+use crate::grab::{CountPrefix, Grab};
+#[cfg(feature = "plugins")]
+use crate::plugin::{Plugin, PluginCache};
+use crate::screen;
+use crate::screen::{Screen, ScreenFlags};
+use crate::spacing::Spacing;
+use crate::style::{CalcSpacing, Style, DEFAULT_GAP_STEP};
+use crate::history::{FocusHistory, WindowCycle};
+use crate::session::SessionStore;
 use crate::tagging::Tagging;
+#[cfg(feature = "tray")]
 use crate::tray::Tray;
 
 const HISTORY_SIZE: usize = 5;
+const DEFAULT_PANEL_TEXT_MAX_LENGTH: usize = 128;
+const DEFAULT_URGENT_VIEW_MARKER: &str = "*";
 
 bitflags! {
     /// Config and state-flags for [`Subtle`]
@@ -70,15 +86,112 @@ bitflags! {
         const TRAY = 1 << 11;
         /// Enable gravity tiling
         const GRAVITY_TILING = 1 << 12;
-        /// Click to focus
-        const CLICK_TO_FOCUS = 1 << 13;
         /// Skip pointer warp
-        const SKIP_POINTER_WARP = 1 << 14;
+        const SKIP_POINTER_WARP = 1 << 13;
         /// Skip urgent warp
-        const SKIP_URGENT_WARP = 1 << 15;
+        const SKIP_URGENT_WARP = 1 << 14;
+        /// Log effects instead of executing them
+        const DRY_RUN = 1 << 15;
+        /// Fix instead of reject ambiguous config
+        const LENIENT_CONFIG = 1 << 16;
+        /// Adopt clients from the state of the previous instance
+        const HANDOFF = 1 << 17;
+        /// Running inside of a nested X server
+        const NESTED = 1 << 18;
     }
 }
 
+/// Focus model to decide how clients receive focus
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub(crate) enum FocusModel {
+    /// Focus follows the pointer
+    #[default]
+    FollowMouse,
+    /// Focus on button press inside of a client
+    Click,
+    /// Focus only changes via grabs
+    Keyboard,
+}
+
+impl FocusModel {
+    /// Whether entering a client window moves the focus
+    ///
+    /// # Returns
+    ///
+    /// Either [`true`] if focus follows the pointer or otherwise [`false`]
+    pub(crate) fn focus_on_enter(&self) -> bool {
+        FocusModel::FollowMouse == *self
+    }
+
+    /// Whether a button grab is required to focus on click
+    ///
+    /// # Returns
+    ///
+    /// Either [`true`] if clicks move the focus or otherwise [`false`]
+    pub(crate) fn focus_on_click(&self) -> bool {
+        FocusModel::Click == *self
+    }
+
+    /// Whether a button press inside a client window moves the focus
+    ///
+    /// # Arguments
+    ///
+    /// * `focus_win` - Currently focused window
+    /// * `win` - Window of the button press
+    ///
+    /// # Returns
+    ///
+    /// Either [`true`] if the window should get the focus or otherwise [`false`]
+    pub(crate) fn focus_on_press(&self, focus_win: Window, win: Window) -> bool {
+        self.focus_on_click() && NONE != win && focus_win != win
+    }
+}
+
+impl TryFrom<&MixedConfigVal> for FocusModel {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &MixedConfigVal) -> Result<Self, Self::Error> {
+        match value {
+            MixedConfigVal::S(name) => match name.to_lowercase().as_str() {
+                "follow_mouse" => Ok(FocusModel::FollowMouse),
+                "click" => Ok(FocusModel::Click),
+                "keyboard" => Ok(FocusModel::Keyboard),
+                _ => Err(anyhow!("Unknown focus model: {}", name)),
+            },
+            _ => Err(anyhow!("Invalid type for focus model")),
+        }
+    }
+}
+
+/// Parse focus model from config values
+///
+/// The legacy `click_to_focus` flag is mapped onto [`FocusModel::Click`],
+/// an explicit `focus_model` always takes precedence.
+///
+/// # Arguments
+///
+/// * `values` - Config values of the subtle section
+///
+/// # Returns
+///
+/// The parsed [`FocusModel`]
+pub(crate) fn parse_focus_model(values: &HashMap<String, MixedConfigVal>) -> FocusModel {
+    let mut focus_model = FocusModel::default();
+
+    if let Some(MixedConfigVal::B(value)) = values.get("click_to_focus") && *value {
+        focus_model = FocusModel::Click;
+    }
+
+    if let Some(value) = values.get("focus_model") {
+        match FocusModel::try_from(value) {
+            Ok(model) => focus_model = model,
+            Err(err) => warn!("{}: {}", function_name!(), err),
+        }
+    }
+
+    focus_model
+}
+
 pub(crate) struct Subtle {
     /// Config and state-flags
     pub(crate) flags: SubtleFlags,
@@ -92,8 +205,52 @@ pub(crate) struct Subtle {
     pub(crate) step_size: i16,
     /// Snap size to screen bounds
     pub(crate) snap_size: u16,
+    /// Resistance distance at screen edges on drag
+    pub(crate) edge_resistance: u16,
+    /// Whether to snap to edges of other clients on drag
+    pub(crate) client_snap: bool,
+    /// Whether keyboard move/resize shows an outline until commit
+    pub(crate) keyboard_drag_outline: bool,
+    /// Modifier to bypass snapping while held during a move
+    pub(crate) snap_modifier: ModMask,
+    /// Min width of centered dialogs that stays on screen
+    pub(crate) dialog_min_visible: u16,
+    /// Whether to shrink centered dialogs bigger than the screen
+    pub(crate) shrink_oversized_dialogs: bool,
+    /// Modifier to drag transients along with their parent
+    pub(crate) transients_drag_modifier: ModMask,
+    /// Whether to move transients only after the drag
+    pub(crate) transients_drag_on_release: bool,
+    /// Whether transients just inherit the tags of their parent
+    pub(crate) transients_follow_parent: bool,
+    /// Whether to raise clients on click-to-focus
+    pub(crate) raise_on_click: bool,
+    /// Whether to flash when a grab has no effect
+    pub(crate) flash_on_noop: bool,
+    /// Currently pending flash
+    pub(crate) flash: Cell<Option<Flash>>,
+    /// Max number of characters of plugin and separator texts
+    pub(crate) panel_text_max_length: usize,
+    /// Marker appended to names of views with urgent clients
+    pub(crate) urgent_view_marker: String,
+    /// Modifier to enter count prefixes with digits
+    pub(crate) count_modifier: ModMask,
+    /// Count prefix for the next grab
+    pub(crate) count_prefix: Cell<CountPrefix>,
+    /// Timestamp of the last user event (see ICCCM 4.1.7)
+    pub(crate) last_timestamp: Cell<Timestamp>,
+    /// Whether the screen layout changed and needs a re-detection
+    pub(crate) screens_changed: Cell<bool>,
+    /// Whether client geometries changed and need to be published
+    pub(crate) client_geoms_changed: Cell<bool>,
+    /// Panels to toggle on a screen
+    pub(crate) panel_toggle: Cell<Option<(usize, ScreenFlags)>>,
+    /// Whether an external taskbar handles iconified clients
+    pub(crate) handled_icons: Cell<bool>,
     /// Default gravity for clients
     pub(crate) default_gravity: isize,
+    /// How clients receive focus
+    pub(crate) focus_model: FocusModel,
     /// Visible tags as taggings
     pub(crate) visible_tags: Cell<Tagging>,
     /// Visible views as taggings
@@ -104,20 +261,44 @@ pub(crate) struct Subtle {
     pub(crate) urgent_tags: Cell<Tagging>,
     /// Flag to indicate shutdown
     pub(crate) shutdown: Arc<AtomicBool>,
+    /// Read end of the self-pipe to wake up the event loop on signals
+    pub(crate) wakeup: OnceCell<UnixStream>,
     /// Connection to X11
     pub(crate) conn: OnceCell<RustConnection>,
     /// X11 screen number
     pub(crate) screen_num: usize,
     /// List of supported atoms
     pub(crate) atoms: OnceCell<Atoms>,
+    /// Executor of window-management effects
+    pub(crate) executor: Executor,
     /// Support window for EWMH
     pub(crate) support_win: Window,
     /// Support window for tray handling
     pub(crate) tray_win: Window,
+    /// Tray selection of the X11 screen
+    pub(crate) tray_selection: Atom,
+    /// Current parent of the tray window
+    pub(crate) tray_parent_win: Cell<Window>,
     /// Double buffer for panel drawing
     pub(crate) panel_double_buffer: Pixmap,
+    /// Hot corner windows
+    pub(crate) corners: RefCell<Vec<Corner>>,
+    /// Actions of the hot corners
+    pub(crate) corner_actions: Vec<CornerAction>,
+    /// Edge length of hot corner windows
+    pub(crate) corner_size: u16,
+    /// Time until a hot corner can be triggered again
+    pub(crate) corner_delay: Duration,
+    /// Whether to stack hot corners above fullscreen clients
+    pub(crate) corners_over_fullscreen: bool,
+    /// Remembered client state across sessions
+    pub(crate) session: RefCell<SessionStore>,
     /// Focus history list
-    pub(crate) focus_history: VecCell<Window>,
+    pub(crate) focus_history: FocusHistory,
+    /// Running window cycle while the modifiers are held
+    pub(crate) window_cycle: RefCell<Option<WindowCycle>>,
+    /// Running keyboard move or resize
+    pub(crate) keyboard_drag: RefCell<Option<KeyboardDrag>>,
     /// Graphic context to draw resize/move outlines
     pub(crate) invert_gc: Gcontext,
     /// Graphic context for general drawing
@@ -138,6 +319,8 @@ pub(crate) struct Subtle {
     pub(crate) views_occupied_style: Style,
     /// Style for currently visible views
     pub(crate) views_visible_style: Style,
+    /// Style for views below the pointer
+    pub(crate) views_hover_style: Style,
     /// Style for the title of clients in the panel
     pub(crate) title_style: Style,
     /// Style of urgent clients
@@ -146,6 +329,10 @@ pub(crate) struct Subtle {
     pub(crate) separator_style: Style,
     /// Style for clients like border
     pub(crate) clients_style: Style,
+    /// Runtime gaps between clients, reset on reload
+    pub(crate) clients_margin: Cell<Spacing>,
+    /// Step size of gap adjustments via keys
+    pub(crate) gap_step: i16,
     /// Style for tray icons in panel
     pub(crate) tray_style: Style,
     /// Style for the top panel
@@ -159,17 +346,28 @@ pub(crate) struct Subtle {
     /// Client list
     pub(crate) clients: RefCell<Vec<Client>>,
     /// Tras list
+    #[cfg(feature = "tray")]
     pub(crate) trays: RefCell<Vec<Tray>>,
     /// Gravity list
     pub(crate) gravities: Vec<Gravity>,
     /// Grab list
     pub(crate) grabs: Vec<Grab>,
     /// Tag list
-    pub(crate) tags: Vec<Tag>,
+    pub(crate) tags: RefCell<Vec<Tag>>,
     /// View list
-    pub(crate) views: Vec<View>,
+    pub(crate) views: RefCell<Vec<View>>,
     /// Plugins list
+    #[cfg(feature = "plugins")]
     pub(crate) plugins: Vec<Plugin>,
+    /// Plugin results of the current panel refresh
+    #[cfg(feature = "plugins")]
+    pub(crate) plugin_cache: RefCell<PluginCache>,
+    /// Rules of windows to ignore
+    pub(crate) ignores: Vec<Regex>,
+    /// Ignored window list
+    pub(crate) ignored_wins: RefCell<Vec<Window>>,
+    /// Recoverable errors of the subsystem init
+    pub(crate) init_errors: RefCell<Vec<anyhow::Error>>,
 }
 
 impl Subtle {
@@ -212,6 +410,7 @@ impl Subtle {
     /// # Returns
     ///
     /// A [`Option`] with either [`Some`] on success or otherwise [`None`]
+    #[cfg(feature = "tray")]
     pub(crate) fn find_tray(&'_ self, win: Window) -> Option<Ref<'_, Tray>> {
         Ref::filter_map(self.trays.borrow(), |trays| {
             trays.iter().find(|t| t.win == win)
@@ -227,6 +426,7 @@ impl Subtle {
     /// # Returns
     ///
     /// A [`Option`] with either [`Some`] on success or otherwise [`None`]
+    #[cfg(feature = "tray")]
     pub(crate) fn find_tray_mut(&'_ self, win: Window) -> Option<RefMut<'_, Tray>> {
         RefMut::filter_map(self.trays.borrow_mut(), |trays| {
             trays.iter_mut().find(|c| c.win == win)
@@ -239,13 +439,30 @@ impl Subtle {
     ///
     /// A [`Option`] with either [`Some`] on success or otherwise [`None`]
     pub(crate) fn find_next_client(&'_ self, screen_idx: isize, jump_to_win: bool) -> Option<Ref<'_, Client>> {
-        debug!("{}: screen_id={}, jump={}", function_name!(), screen_idx, jump_to_win);
+        self.find_next_client_except(screen_idx, jump_to_win, NONE)
+    }
+
+    /// Find next client other than the given window
+    ///
+    /// # Arguments
+    ///
+    /// * `screen_idx` - Screen to search first
+    /// * `jump_to_win` - Whether to search on any visible screen
+    /// * `except_win` - Window to skip, e.g. of a closing client
+    ///
+    /// # Returns
+    ///
+    /// A [`Option`] with either [`Some`] on success or otherwise [`None`]
+    pub(crate) fn find_next_client_except(&'_ self, screen_idx: isize, jump_to_win: bool,
+                                          except_win: Window) -> Option<Ref<'_, Client>>
+    {
+        debug!("{}: screen_id={}, jump={}, except={}", function_name!(), screen_idx, jump_to_win, except_win);
 
         // Pass 1: Check focus history of current screen
-        for win in self.focus_history.iter() {
-            if let Some(client) = self.find_client(*win)
+        for win in self.focus_history.to_vec() {
+            if let Some(client) = self.find_client(win)
                 && client.screen_idx == screen_idx && client.is_alive() && client.is_visible(self)
-                && self.find_focus_win() != client.win
+                && self.find_focus_win() != client.win && except_win != client.win
             {
                 return Some(client)
             }
@@ -253,7 +470,8 @@ impl Subtle {
 
         // Pass 2: Check client stacking list backwards of current screen
         if let Ok(client) = Ref::filter_map(self.clients.borrow(), |clients| {
-            clients.iter().find(|c| c.screen_idx == screen_idx && c.is_alive() && c.is_visible(self))
+            clients.iter().find(|c| c.screen_idx == screen_idx && c.is_alive() && c.is_visible(self)
+                && except_win != c.win)
         }) {
             return Some(client)
         }
@@ -261,7 +479,8 @@ impl Subtle {
         // Pass 3: Check client stacking list backwards of any visible screen
         if 1 < self.clients.borrow().len() && jump_to_win
             && let Ok(client) = Ref::filter_map(self.clients.borrow(), |clients| {
-                clients.iter().find(|c| c.is_alive() && c.is_visible(self) && self.find_focus_win() != c.win)
+                clients.iter().find(|c| c.is_alive() && c.is_visible(self) && self.find_focus_win() != c.win
+                    && except_win != c.win)
             }) {
                 return Some(client)
             }
@@ -276,8 +495,8 @@ impl Subtle {
     ///
     /// A [`Option`] with either [`Some`] on success or otherwise [`None`]
     pub(crate) fn find_focus_client(&'_ self) -> Option<Ref<'_, Client>> {
-        if let Some(win) = self.focus_history.borrow(0) {
-            return self.find_client(*win)
+        if let Some(win) = self.focus_history.first() {
+            return self.find_client(win)
         }
 
         None
@@ -289,26 +508,57 @@ impl Subtle {
     ///
     /// A [`Option`] with either [`Some`] on success or otherwise [`None`]
     pub(crate) fn find_focus_client_mut(&'_ self) -> Option<RefMut<'_, Client>> {
-        if let Some(win) = self.focus_history.borrow(0) {
-            return self.find_client_mut(*win)
+        if let Some(win) = self.focus_history.first() {
+            return self.find_client_mut(win)
         }
 
         None
     }
 
+    /// Find screen of the focus client when it is visible
+    ///
+    /// # Returns
+    ///
+    /// A [`Option`] with either [`Some`] on success or otherwise [`None`]
+    pub(crate) fn find_focus_screen_idx(&self) -> Option<isize> {
+        self.find_focus_client()
+            .filter(|focus| focus.is_visible(self))
+            .map(|focus| focus.screen_idx)
+    }
+
     /// Find focus window
     ///
     /// # Returns
     ///
     /// Either the found [`Window`] on success or otherwise [`NONE`]
     pub(crate) fn find_focus_win(&self) -> Window {
-        if let Some(win) = self.focus_history.borrow(0) {
-            return *win
+        if let Some(win) = self.focus_history.first() {
+            return win
         }
 
         NONE
     }
 
+    /// Find transients of given window
+    ///
+    /// # Arguments
+    ///
+    /// * `win` - Window of the parent
+    ///
+    /// # Returns
+    ///
+    /// A [`Vec`] with window and geometry of all transient clients
+    pub(crate) fn find_transients(&self, win: Window) -> Vec<(Window, Rectangle)> {
+        if NONE == win {
+            return Vec::new();
+        }
+
+        self.clients.borrow().iter()
+            .filter(|c| c.win != win && c.transient_for == win)
+            .map(|c| (c.win, c.geom))
+            .collect()
+    }
+
     /// Find mut tray by given window
     ///
     /// # Arguments
@@ -329,7 +579,22 @@ impl Subtle {
         None
     }
 
-    /// Find screen by x/x coordinates
+    /// Find hot corner by given window
+    ///
+    /// # Arguments
+    ///
+    /// * `win` - Window to search
+    ///
+    /// # Returns
+    ///
+    /// A [`Option`] with either [`Some`] on success or otherwise [`None`]
+    pub(crate) fn find_corner(&'_ self, win: Window) -> Option<Ref<'_, Corner>> {
+        Ref::filter_map(self.corners.borrow(), |corners| {
+            corners.iter().find(|c| c.win == win)
+        }).ok()
+    }
+
+    /// Find screen by x/x coordinates, virtual screens are preferred over their parent
     ///
     /// # Arguments
     ///
@@ -340,15 +605,9 @@ impl Subtle {
     ///
     /// A [`Option`] with either [`Some`] on success or otherwise [`None`]
     pub(crate) fn find_screen_by_xy(&self, x: i16, y:i16) -> Option<(usize, &Screen)> {
-        for (idx, screen) in self.screens.iter().enumerate() {
-            if x >= screen.base.x && x < screen.base.x + screen.base.width as i16
-                && y >= screen.base.y && y < screen.base.y + screen.base.height as i16
-            {
-                return Some((idx, screen))
-            }
-        }
+        let screen_geoms: Vec<Rectangle> = self.screens.iter().map(|screen| screen.base).collect();
 
-        None
+        screen::calc_screen_at(&screen_geoms, x, y).map(|idx| (idx, &self.screens[idx]))
     }
 
     /// Find screen based on current pointer position
@@ -409,6 +668,7 @@ impl Subtle {
     /// * `win` - Client window
     pub(crate) fn remove_client_by_win(&self, win: Window) {
         self.clients.borrow_mut().retain(|c| c.win != win);
+        self.focus_history.remove(win);
     }
 
     /// Add tray to internal list
@@ -416,6 +676,7 @@ impl Subtle {
     /// # Arguments
     ///
     /// * `tray` - Tray to add
+    #[cfg(feature = "tray")]
     pub(crate) fn add_tray(&self, tray: Tray) {
         self.trays.borrow_mut().push(tray);
     }
@@ -425,10 +686,44 @@ impl Subtle {
     /// # Arguments
     ///
     /// * `win` - Client window
+    #[cfg(feature = "tray")]
     pub(crate) fn remove_tray_by_win(&self, win: Window) {
         self.trays.borrow_mut().retain(|t| t.win != win);
     }
 
+    /// Whether window is known to be ignored
+    ///
+    /// # Arguments
+    ///
+    /// * `win` - Window to check
+    ///
+    /// # Returns
+    ///
+    /// Either [`true`] if ignored or otherwise [`false`]
+    pub(crate) fn is_ignored_win(&self, win: Window) -> bool {
+        self.ignored_wins.borrow().contains(&win)
+    }
+
+    /// Add window to ignored list
+    ///
+    /// # Arguments
+    ///
+    /// * `win` - Window to add
+    pub(crate) fn add_ignored_win(&self, win: Window) {
+        if !self.is_ignored_win(win) {
+            self.ignored_wins.borrow_mut().push(win);
+        }
+    }
+
+    /// Remove window from ignored list
+    ///
+    /// # Arguments
+    ///
+    /// * `win` - Window to remove
+    pub(crate) fn remove_ignored_win(&self, win: Window) {
+        self.ignored_wins.borrow_mut().retain(|w| *w != win);
+    }
+
     /// Update tray window attributes
     ///
     /// # Arguments
@@ -444,6 +739,8 @@ impl Subtle {
 
         conn.reparent_window(self.tray_win, parent_win, 0, 0,)?.check()?;
 
+        self.tray_parent_win.set(parent_win);
+
         let aux = ChangeWindowAttributesAux::default()
             .background_pixel(self.tray_style.bg as u32);
 
@@ -464,23 +761,39 @@ impl Subtle {
         Ok(())
     }
 
-    /// Reorder and restack windows based on sorting rules
+    /// Reparent tray window back to root and unmap it
     ///
     /// # Returns
     ///
     /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-    pub(crate) fn restack_windows(&self) -> Result<()> {
+    pub(crate) fn release_tray_win(&self) -> Result<()> {
+        if NONE == self.tray_parent_win.replace(NONE) {
+            return Ok(());
+        }
+
         let conn = self.conn.get().unwrap();
+        let default_screen = &conn.setup().roots[self.screen_num];
 
-        self.clients.borrow_mut().sort();
+        conn.unmap_window(self.tray_win)?.check()?;
+        conn.reparent_window(self.tray_win, default_screen.root, 0, 0)?.check()?;
 
-        let aux = ConfigureWindowAux::default()
-            .stack_mode(StackMode::BELOW);
+        debug!("{}: tray_win={}", function_name!(), self.tray_win);
+
+        Ok(())
+    }
+
+    /// Reorder and restack windows based on sorting rules
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn restack_windows(&self) -> Result<()> {
+        self.clients.borrow_mut().sort();
 
         for client in self.clients.borrow_mut().iter_mut().rev() {
             client.order = RestackOrder::None;
 
-            conn.configure_window(client.win, &aux)?;
+            effect::apply(self, Effect::Lower(client.win))?;
         }
 
         Ok(())
@@ -510,14 +823,36 @@ impl Subtle {
 impl Default for Subtle {
     fn default() -> Self {
         Subtle {
-            flags: SubtleFlags::TRAY,
+            flags: if cfg!(feature = "tray") { SubtleFlags::TRAY } else { SubtleFlags::empty() },
             width: 0,
             height: 0,
 
             panel_height: 1,
             step_size: 0,
             snap_size: 0,
+            edge_resistance: 0,
+            client_snap: true,
+            keyboard_drag_outline: false,
+            snap_modifier: ModMask::M1,
+            dialog_min_visible: 50,
+            shrink_oversized_dialogs: false,
+            transients_drag_modifier: ModMask::default(),
+            transients_drag_on_release: false,
+            transients_follow_parent: true,
+            raise_on_click: true,
+            flash_on_noop: true,
+            flash: Cell::new(None),
+            panel_text_max_length: DEFAULT_PANEL_TEXT_MAX_LENGTH,
+            urgent_view_marker: DEFAULT_URGENT_VIEW_MARKER.to_string(),
+            count_modifier: ModMask::default(),
+            count_prefix: Cell::new(CountPrefix::default()),
+            last_timestamp: Cell::new(CURRENT_TIME),
+            screens_changed: Cell::new(false),
+            client_geoms_changed: Cell::new(false),
+            panel_toggle: Cell::new(None),
+            handled_icons: Cell::new(false),
             default_gravity: 0,
+            focus_model: FocusModel::default(),
 
             visible_tags: Cell::new(Tagging::empty()),
             visible_views: Cell::new(Tagging::empty()),
@@ -525,15 +860,27 @@ impl Default for Subtle {
             urgent_tags: Cell::new(Tagging::empty()),
 
             shutdown: Arc::new(AtomicBool::new(false)),
+            wakeup: OnceCell::new(),
             conn: OnceCell::new(),
             screen_num: 0,
 
             atoms: OnceCell::new(),
 
+            executor: Executor::default(),
             support_win: Window::default(),
             tray_win: Window::default(),
+            tray_selection: Atom::default(),
+            tray_parent_win: Cell::new(NONE),
             panel_double_buffer: Pixmap::default(),
-            focus_history: VecCell::from(vec![NONE; HISTORY_SIZE]),
+            corners: RefCell::new(Vec::new()),
+            corner_actions: Vec::new(),
+            corner_size: DEFAULT_CORNER_SIZE,
+            corner_delay: DEFAULT_CORNER_DELAY,
+            corners_over_fullscreen: false,
+            session: RefCell::new(SessionStore::default()),
+            focus_history: FocusHistory::new(HISTORY_SIZE),
+            window_cycle: RefCell::new(None),
+            keyboard_drag: RefCell::new(None),
 
             invert_gc: Gcontext::default(),
             draw_gc: Gcontext::default(),
@@ -547,10 +894,13 @@ impl Default for Subtle {
             views_active_style: Style::default(),
             views_occupied_style: Style::default(),
             views_visible_style: Style::default(),
+            views_hover_style: Style::default(),
             title_style: Style::default(),
             urgent_style: Style::default(),
             separator_style: Style::default(),
             clients_style: Style::default(),
+            clients_margin: Cell::new(Spacing::default()),
+            gap_step: DEFAULT_GAP_STEP,
             tray_style: Style::default(),
             top_panel_style: Style::default(),
             bottom_panel_style: Style::default(),
@@ -558,12 +908,19 @@ impl Default for Subtle {
             fonts: Vec::new(),
             screens: Vec::new(),
             clients: RefCell::new(Vec::new()),
+            #[cfg(feature = "tray")]
             trays: RefCell::new(Vec::new()),
             gravities: Vec::new(),
             grabs: Vec::new(),
-            tags: Vec::new(),
-            views: Vec::new(),
+            tags: RefCell::new(Vec::new()),
+            views: RefCell::new(Vec::new()),
+            #[cfg(feature = "plugins")]
             plugins: Vec::new(),
+            #[cfg(feature = "plugins")]
+            plugin_cache: RefCell::new(PluginCache::default()),
+            ignores: Vec::new(),
+            ignored_wins: RefCell::new(Vec::new()),
+            init_errors: RefCell::new(Vec::new()),
         }
     }
 }
@@ -573,23 +930,111 @@ impl From<&Config> for Subtle {
         let mut subtle = Self::default();
 
         // CLI options
-        if config.replace {
+        if config.dry_run {
+            subtle.flags.insert(SubtleFlags::DRY_RUN);
+            subtle.executor = Executor::new(true);
+
+            if config.replace {
+                warn!("Ignoring replace in dry-run");
+            }
+        } else if config.replace {
             subtle.flags.insert(SubtleFlags::REPLACE);
         }
 
+        if config.check {
+            subtle.flags.insert(SubtleFlags::CHECK);
+        }
+
+        if config.nested {
+            subtle.flags.insert(SubtleFlags::NESTED);
+        }
+
         if config.debug {
             subtle.flags.insert(SubtleFlags::DEBUG);
         }
 
+        if config.handoff {
+            subtle.flags.insert(SubtleFlags::HANDOFF);
+        }
+
         // Config options
         if let Some(MixedConfigVal::I(step_size)) = config.subtle.get("increase_step") {
             subtle.step_size = *step_size as i16;
         }
 
+        if let Some(MixedConfigVal::I(gap_step)) = config.subtle.get("gap_step") && 0 < *gap_step {
+            subtle.gap_step = *gap_step as i16;
+        }
+
         if let Some(MixedConfigVal::I(snap_size)) = config.subtle.get("border_snap") {
             subtle.snap_size = *snap_size as u16;
         }
 
+        if let Some(MixedConfigVal::I(edge_resistance)) = config.subtle.get("edge_resistance") {
+            subtle.edge_resistance = *edge_resistance as u16;
+        }
+
+        if let Some(MixedConfigVal::B(client_snap)) = config.subtle.get("client_snap") {
+            subtle.client_snap = *client_snap;
+        }
+
+        if let Some(MixedConfigVal::B(keyboard_drag_outline)) = config.subtle.get("keyboard_drag_outline") {
+            subtle.keyboard_drag_outline = *keyboard_drag_outline;
+        }
+
+        if let Some(MixedConfigVal::S(modifier)) = config.subtle.get("snap_modifier") {
+            match grab::parse_modifier(modifier) {
+                Some(modifier) => subtle.snap_modifier = modifier,
+                None => warn!("Unknown snap modifier `{}`", modifier),
+            }
+        }
+
+        if let Some(MixedConfigVal::I(dialog_min_visible)) = config.subtle.get("dialog_min_visible") {
+            subtle.dialog_min_visible = *dialog_min_visible as u16;
+        }
+
+        if let Some(MixedConfigVal::B(shrink)) = config.subtle.get("shrink_oversized_dialogs") {
+            subtle.shrink_oversized_dialogs = *shrink;
+        }
+
+        if let Some(MixedConfigVal::S(modifier)) = config.subtle.get("transients_drag_modifier") {
+            match grab::parse_modifier(modifier) {
+                Some(modifier) => subtle.transients_drag_modifier = modifier,
+                None => warn!("Unknown transients drag modifier `{}`", modifier),
+            }
+        }
+
+        if let Some(MixedConfigVal::B(on_release)) = config.subtle.get("transients_drag_on_release") {
+            subtle.transients_drag_on_release = *on_release;
+        }
+
+        if let Some(MixedConfigVal::B(follow_parent)) = config.subtle.get("transients_follow_parent") {
+            subtle.transients_follow_parent = *follow_parent;
+        }
+
+        if let Some(MixedConfigVal::B(raise_on_click)) = config.subtle.get("raise_on_click") {
+            subtle.raise_on_click = *raise_on_click;
+        }
+
+        if let Some(MixedConfigVal::S(modifier)) = config.subtle.get("count_modifier") {
+            match grab::parse_modifier(modifier) {
+                Some(modifier) => subtle.count_modifier = modifier,
+                None => warn!("Unknown count modifier `{}`", modifier),
+            }
+        }
+
+        if let Some(MixedConfigVal::B(flash_on_noop)) = config.subtle.get("flash_on_noop") {
+            subtle.flash_on_noop = *flash_on_noop;
+        }
+
+        if let Some(MixedConfigVal::I(max_length)) = config.subtle.get("panel_text_max_length") && 0 < *max_length {
+            subtle.panel_text_max_length = *max_length as usize;
+        }
+
+        if let Some(MixedConfigVal::S(marker)) = config.subtle.get("urgent_view_marker") {
+            subtle.urgent_view_marker = marker.clone();
+        }
+
         // Config flags
         macro_rules! apply_config_flag {
             ($config_key:expr, $subtle_flag:path) => {
@@ -602,10 +1047,31 @@ impl From<&Config> for Subtle {
         apply_config_flag!("urgent_dialogs", SubtleFlags::URGENT);
         apply_config_flag!("honor_size_hints", SubtleFlags::RESIZE);
         apply_config_flag!("gravity_tiling", SubtleFlags::GRAVITY_TILING);
-        apply_config_flag!("click_to_focus", SubtleFlags::CLICK_TO_FOCUS);
         apply_config_flag!("skip_pointer_warp", SubtleFlags::SKIP_POINTER_WARP);
         apply_config_flag!("skip_urgent_warp", SubtleFlags::SKIP_URGENT_WARP);
+        apply_config_flag!("lenient_config", SubtleFlags::LENIENT_CONFIG);
+
+        subtle.focus_model = parse_focus_model(&config.subtle);
 
         subtle
     }
 }
+
+/// Check whether dry-run can be used safely
+///
+/// Dry-run swallows map requests, so on a live display new windows never appear.
+///
+/// # Arguments
+///
+/// * `flags` - Flags of the global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn check_dry_run(flags: &SubtleFlags) -> Result<()> {
+    if flags.contains(SubtleFlags::DRY_RUN) && !flags.intersects(SubtleFlags::CHECK | SubtleFlags::NESTED) {
+        return Err(anyhow!("Dry-run requires either --check or --nested"));
+    }
+
+    Ok(())
+}
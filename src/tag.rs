@@ -12,17 +12,23 @@
 use std::fmt;
 use bitflags::bitflags;
 use regex::{Regex, RegexBuilder};
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use derive_builder::Builder;
 use log::{debug, info, warn};
 use stdext::function_name;
-use switch_statement::switch;
 use x11rb::connection::Connection;
 use x11rb::protocol::xproto::{AtomEnum, PropMode, Rectangle};
 use x11rb::wrapper::ConnectionExt as ConnectionExtWrapper;
 use crate::client::{Client, ClientFlags};
 use crate::config::{Config, MixedConfigVal};
 use crate::subtle::Subtle;
+use crate::tagging;
+use crate::tagging::{Tagging, DEFAULT_TAG_IDX, DEFAULT_TAG_NAME};
+use crate::{client, config, error, view};
+use crate::error::Classify;
+
+/// Max number of tags that fit into [`Tagging`]
+pub(crate) const MAX_TAGS: usize = 32;
 
 bitflags! {
     /// Config and state-flags for [`Tags`]
@@ -36,6 +42,12 @@ bitflags! {
         const POSITION = 1 << 2;
         /// Tagging proc
         const PROC = 1 << 3;
+        /// Removed tag
+        const DEAD = 1 << 4;
+        /// Remember float geometry
+        const REMEMBER = 1 << 5;
+        /// Screen property
+        const SCREEN = 1 << 6;
     }
 }
 
@@ -57,11 +69,51 @@ pub(crate) struct Tag {
     pub(crate) geom: Option<Rectangle>,
     /// Client flags to apply on match
     pub(crate) mode_flags: ClientFlags,
+    /// Window types that never match
+    pub(crate) exclude_types: ClientFlags,
+    /// Window types that match exclusively
+    pub(crate) only_types: ClientFlags,
 }
 
 impl Tag {
+    /// Check whether this tag has been removed
+    ///
+    /// # Returns
+    ///
+    /// Either [`true`] when removed and otherwise [`false`]
+    pub(crate) fn is_dead(&self) -> bool {
+        self.flags.contains(TagFlags::DEAD)
+    }
+
+    /// Calculate floating geometry of this tag
+    ///
+    /// # Arguments
+    ///
+    /// * `geom` - Current geometry of the client
+    ///
+    /// # Returns
+    ///
+    /// A [`Option`] with either [`Some`] geometry or otherwise [`None`]
+    pub(crate) fn calc_geom(&self, geom: &Rectangle) -> Option<Rectangle> {
+        let tag_geom = self.geom?;
+
+        if self.flags.contains(TagFlags::GEOMETRY) {
+            Some(tag_geom)
+        } else if self.flags.contains(TagFlags::POSITION) {
+            Some(Rectangle {
+                x: tag_geom.x,
+                y: tag_geom.y,
+                ..*geom
+            })
+        } else {
+            None
+        }
+    }
+
     /// Check whether client is matching
     ///
+    /// The window type is checked before the regex, see [`matches_types`].
+    ///
     /// # Arguments
     ///
     /// * `client` - Client to check
@@ -70,22 +122,159 @@ impl Tag {
     ///
     /// Either [`true`] on success and otherwise [`false`]
     pub(crate) fn matches(&self, client: &Client) -> bool {
+        if !matches_types(client.flags, self.exclude_types, self.only_types) {
+            return false;
+        }
+
         if let Some(regex) = self.regex.as_ref() {
-            regex.is_match(&client.name)
-                || regex.is_match(&client.instance)
-                || regex.is_match(&client.klass)
+            matches_any(regex, &client.name, &client.instance, &client.klass)
         } else {
             false
         }
     }
 }
 
+/// Validate screen index of a tag and fall back to the first screen
+///
+/// # Arguments
+///
+/// * `screen_id` - Configured screen index
+/// * `nscreens` - Number of screens
+///
+/// # Returns
+///
+/// Either the valid index or `0`
+pub(crate) fn calc_screen_id(screen_id: i32, nscreens: usize) -> usize {
+    match usize::try_from(screen_id) {
+        Ok(screen_idx) if screen_idx < nscreens => screen_idx,
+        _ => {
+            warn!("Screen {} not found, falling back to screen 0", screen_id);
+
+            0
+        }
+    }
+}
+
+/// Set gravity of tag on all views that contain the tag
+///
+/// # Arguments
+///
+/// * `gravities` - Gravity per view of a client
+/// * `view_tags` - Tags per view
+/// * `tag_idx` - Index of the tag
+/// * `gravity_id` - Index of the gravity
+pub(crate) fn apply_gravity(gravities: &mut [usize], view_tags: &[Tagging], tag_idx: usize, gravity_id: usize) {
+    let tag_bit = tagging::tag_bit(tag_idx);
+
+    for (gravity, tags) in gravities.iter_mut().zip(view_tags) {
+        if tags.intersects(tag_bit) {
+            *gravity = gravity_id;
+        }
+    }
+}
+
+/// Check whether window type of a client is allowed
+///
+/// Excluded types always win: A client with an excluded type never matches,
+/// even when the type is also listed as only type.
+///
+/// # Arguments
+///
+/// * `client_flags` - Flags of the client including the window type
+/// * `exclude_types` - Window types that never match
+/// * `only_types` - Window types that match exclusively, empty allows all
+///
+/// # Returns
+///
+/// Either [`true`] if the type is allowed and otherwise [`false`]
+pub(crate) fn matches_types(client_flags: ClientFlags, exclude_types: ClientFlags, only_types: ClientFlags) -> bool {
+    if client_flags.intersects(exclude_types) {
+        return false;
+    }
+
+    only_types.is_empty() || client_flags.intersects(only_types)
+}
+
+/// Parse list of window type names
+///
+/// # Arguments
+///
+/// * `names` - Names of the window types
+///
+/// # Returns
+///
+/// The combined [`ClientFlags`] of all known window types
+pub(crate) fn parse_window_types(names: &[String]) -> ClientFlags {
+    names.iter().fold(ClientFlags::empty(), |flags, name| {
+        match client::parse_window_type(name) {
+            Some(type_flag) => flags | type_flag,
+            None => {
+                warn!("Window type `{}` not found", name);
+
+                flags
+            }
+        }
+    })
+}
+
+/// Check whether regex matches either name, instance or class
+///
+/// # Arguments
+///
+/// * `regex` - Regex to use
+/// * `name` - Window name
+/// * `instance` - Window instance
+/// * `klass` - Window class
+///
+/// # Returns
+///
+/// Either [`true`] on success and otherwise [`false`]
+pub(crate) fn matches_any(regex: &Regex, name: &str, instance: &str, klass: &str) -> bool {
+    regex.is_match(name)
+        || regex.is_match(instance)
+        || regex.is_match(klass)
+}
+
 impl fmt::Display for Tag {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "(name={}, regex={:?})", self.name, self.regex)
     }
 }
 
+/// Move the default tag to its index or add it there
+///
+/// # Arguments
+///
+/// * `tags` - Configured tags
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn ensure_default_tag(tags: &mut Vec<Tag>) -> Result<()> {
+    match tags.iter().position(|tag| DEFAULT_TAG_NAME == tag.name) {
+        Some(DEFAULT_TAG_IDX) => {},
+        Some(tag_idx) => {
+            let tag = tags.remove(tag_idx);
+
+            tags.insert(DEFAULT_TAG_IDX, tag);
+        },
+        None if MAX_TAGS <= tags.len() => {
+            return Err(anyhow!("Tag limit of {} reached, no room for default tag", MAX_TAGS));
+        },
+        None => {
+            let mut builder = TagBuilder::default();
+
+            builder.name(DEFAULT_TAG_NAME.into());
+
+            tags.insert(DEFAULT_TAG_IDX, builder.build()?);
+        },
+    }
+
+    debug!("{}: ntags={}", function_name!(), tags.len());
+
+    Ok(())
+}
+
 /// Check config and init all tag related options
 ///
 /// # Arguments
@@ -106,10 +295,13 @@ pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
             builder.name(value.to_string());
         }
 
+        // Keep tag without match on invalid patterns
         if let Some(MixedConfigVal::S(value)) = tag_values.get("match") {
-            builder.regex(Some(RegexBuilder::new(value)
+            builder.regex(error::collect(subtle, RegexBuilder::new(value)
                 .case_insensitive(true)
-                .build()?));
+                .build()
+                .with_context(|| format!("Invalid tag match `{}`", value))
+                .recoverable())?);
         }
 
         if let Some(MixedConfigVal::S(value)) = tag_values.get("gravity") {
@@ -121,6 +313,12 @@ pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
             }
         }
 
+        // Handle screen
+        if let Some(MixedConfigVal::I(value)) = tag_values.get("screen") {
+            flags.insert(TagFlags::SCREEN);
+            builder.screen_id(calc_screen_id(*value, subtle.screens.len()));
+        }
+
         // Handle geometry
         if let Some(MixedConfigVal::VI(value)) = tag_values.get("geometry") {
             if 4 == value.len() {
@@ -148,6 +346,11 @@ pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
             }
         }
 
+        // Handle remembered geometry
+        if let Some(MixedConfigVal::B(remember)) = tag_values.get("remember") && *remember {
+            flags.insert(TagFlags::REMEMBER);
+        }
+
         // Handle client modes
         macro_rules! set_client_flag {
             ($name:expr, $flag:expr) => {
@@ -162,39 +365,44 @@ pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
         set_client_flag!("borderless", ClientFlags::MODE_BORDERLESS);
         set_client_flag!("center", ClientFlags::MODE_CENTER);
         set_client_flag!("fixed", ClientFlags::MODE_FIXED);
+        set_client_flag!("float", ClientFlags::MODE_FLOAT);
         set_client_flag!("floating", ClientFlags::MODE_FLOAT);
         set_client_flag!("full", ClientFlags::MODE_FULL);
+        set_client_flag!("ignore_hints", ClientFlags::MODE_IGNORE_HINTS);
         set_client_flag!("resize", ClientFlags::MODE_RESIZE);
+        set_client_flag!("stick", ClientFlags::MODE_STICK);
         set_client_flag!("sticky", ClientFlags::MODE_STICK);
         set_client_flag!("urgent", ClientFlags::MODE_URGENT);
         set_client_flag!("zaphod", ClientFlags::MODE_ZAPHOD);
 
         // Handle window types
         if let Some(MixedConfigVal::S(window_type)) = tag_values.get("type") {
-            switch! { window_type;
-                "desktop" => mode_flags.insert(ClientFlags::TYPE_DESKTOP),
-                "dock" => mode_flags.insert(ClientFlags::TYPE_DOCK),
-                "toolbar" => mode_flags.insert(ClientFlags::TYPE_TOOLBAR),
-                "splash" => mode_flags.insert(ClientFlags::TYPE_SPLASH),
-                "dialog" => mode_flags.insert(ClientFlags::TYPE_DIALOG),
-                _ => info!("Window type not found")
+            match client::parse_window_type(window_type) {
+                Some(type_flag) if ClientFlags::TYPE_NORMAL != type_flag => mode_flags.insert(type_flag),
+                _ => info!("Window type not found"),
             }
         }
 
+        if let Some(MixedConfigVal::VS(names)) = tag_values.get("exclude_types") {
+            builder.exclude_types(parse_window_types(names));
+        }
+
+        if let Some(MixedConfigVal::VS(names)) = tag_values.get("only_types") {
+            builder.only_types(parse_window_types(names));
+        }
+
         builder.flags(flags);
         builder.mode_flags(mode_flags);
 
-        subtle.tags.push(builder.build()?);
+        subtle.tags.borrow_mut().push(builder.build()?);
     }
 
-    // Sanity check
-    if subtle.tags.is_empty() {
-        let mut builder = TagBuilder::default();
-
-        builder.name("default".into());
+    // Check names
+    config::check_empty_names("tag", &subtle.tags.borrow().iter()
+        .map(|tag| tag.name.clone())
+        .collect::<Vec<String>>())?;
 
-        subtle.tags.push(builder.build()?);
-    }
+    ensure_default_tag(&mut subtle.tags.borrow_mut())?;
 
     publish(subtle)?;
 
@@ -218,18 +426,221 @@ pub(crate) fn publish(subtle: &Subtle) -> Result<()> {
 
     let default_screen = &conn.setup().roots[subtle.screen_num];
 
-    let mut tags: Vec<&str> = Vec::with_capacity(subtle.tags.len());
+    let tags = subtle.tags.borrow();
+    let mut names: Vec<&str> = Vec::with_capacity(tags.len());
 
-    for tag in subtle.tags.iter() {
-        tags.push(&*tag.name);
+    // Tombstones are published with an empty name to keep the indices stable
+    for tag in tags.iter() {
+        names.push(&*tag.name);
     }
 
     conn.change_property8(PropMode::REPLACE, default_screen.root, atoms.SUBTLE_TAG_LIST,
-                          AtomEnum::STRING, tags.join("\0").as_bytes())?.check()?;
+                          AtomEnum::STRING, names.join("\0").as_bytes())?.check()?;
 
     conn.flush()?;
 
-    debug!("{}: ntags={}", function_name!(), subtle.tags.len());
+    debug!("{}: ntags={}", function_name!(), tags.len());
 
     Ok(())
 }
+
+/// Parse NUL-separated name and optional argument of a tag message
+///
+/// # Arguments
+///
+/// * `data` - Data of the client message
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] name and argument or otherwise [`None`]
+pub(crate) fn parse_message(data: &[u8]) -> Option<(String, Option<String>)> {
+    let mut fields = data.split(|byte| 0 == *byte)
+        .map(|field| String::from_utf8_lossy(field).trim().to_string());
+
+    let name = fields.next().filter(|name| !name.is_empty())?;
+    let arg = fields.next().filter(|arg| !arg.is_empty());
+
+    Some((name, arg))
+}
+
+/// Find index for a new tag and reuse tombstones first
+///
+/// # Arguments
+///
+/// * `tags` - Current tags
+/// * `name` - Name of the new tag
+///
+/// # Returns
+///
+/// A [`Result`] with either [`usize`] index on success or otherwise [`anyhow::Error`]
+pub(crate) fn find_free_idx(tags: &[Tag], name: &str) -> Result<usize> {
+    if name.is_empty() {
+        return Err(anyhow!("Tag name must not be empty"));
+    }
+
+    if tags.iter().any(|tag| !tag.is_dead() && tag.name == name) {
+        return Err(anyhow!("Tag `{}` already exists", name));
+    }
+
+    match tags.iter().position(|tag| tag.is_dead()) {
+        Some(tag_idx) => Ok(tag_idx),
+        None if tags.len() < MAX_TAGS => Ok(tags.len()),
+        None => Err(anyhow!("Tag limit of {} reached", MAX_TAGS)),
+    }
+}
+
+/// Find index of a tag that can be removed
+///
+/// # Arguments
+///
+/// * `tags` - Current tags
+/// * `name` - Name of the tag to remove
+/// * `used_tags` - Tags used by any client or view
+/// * `is_forced` - Whether tags in use can be removed
+///
+/// # Returns
+///
+/// A [`Result`] with either [`usize`] index on success or otherwise [`anyhow::Error`]
+pub(crate) fn find_removable_idx(tags: &[Tag], name: &str, used_tags: Tagging, is_forced: bool) -> Result<usize> {
+    let tag_idx = tags.iter()
+        .position(|tag| !tag.is_dead() && tag.name == name)
+        .ok_or_else(|| anyhow!("Tag `{}` not found", name))?;
+
+    // Never tombstone the default tag, retagging falls back to it
+    if DEFAULT_TAG_IDX == tag_idx {
+        return Err(anyhow!("Cannot remove default tag `{}`", name));
+    }
+
+    if 1 == tags.iter().filter(|tag| !tag.is_dead()).count() {
+        return Err(anyhow!("Cannot remove last tag `{}`", name));
+    }
+
+    if !is_forced && used_tags.intersects(tagging::tag_bit(tag_idx)) {
+        return Err(anyhow!("Tag `{}` is still in use", name));
+    }
+
+    Ok(tag_idx)
+}
+
+/// Add new tag at runtime and optionally retag all clients
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `name` - Name of the tag
+/// * `pattern` - Optional regex to match clients
+///
+/// # Returns
+///
+/// A [`Result`] with either [`usize`] index on success or otherwise [`anyhow::Error`]
+pub(crate) fn add(subtle: &Subtle, name: &str, pattern: Option<&str>) -> Result<usize> {
+    let conn = subtle.conn.get().unwrap();
+    let atoms = subtle.atoms.get().unwrap();
+
+    let regex = pattern.map(|pattern| RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build())
+        .transpose()?;
+
+    let tag_idx = find_free_idx(&subtle.tags.borrow(), name)?;
+
+    let mut builder = TagBuilder::default();
+
+    builder.name(name.into());
+    builder.regex(regex);
+
+    let tag = builder.build()?;
+
+    {
+        let mut tags = subtle.tags.borrow_mut();
+
+        if tag_idx < tags.len() {
+            tags[tag_idx] = tag;
+        } else {
+            tags.push(tag);
+        }
+    }
+
+    let tag_bit = tagging::tag_bit(tag_idx);
+
+    // Add tag to matching views
+    for view in subtle.views.borrow().iter() {
+        if let Some(regex) = view.regex.as_ref() && regex.is_match(name) {
+            view.tags.set(view.tags.get() | tag_bit);
+        }
+    }
+
+    // Retag clients against the new regex
+    if pattern.is_some() {
+        let tags = subtle.tags.borrow();
+
+        for client in subtle.clients.borrow_mut().iter_mut() {
+            if tags[tag_idx].matches(client) {
+                client.tags.insert(tag_bit);
+
+                conn.change_property32(PropMode::REPLACE, client.win, atoms.SUBTLE_CLIENT_TAGS,
+                                       AtomEnum::CARDINAL, &[client.tags.bits()])?.check()?;
+            }
+        }
+    }
+
+    publish(subtle)?;
+    view::publish(subtle)?;
+
+    debug!("{}: name={}, pattern={:?}, tag_idx={}", function_name!(), name, pattern, tag_idx);
+
+    Ok(tag_idx)
+}
+
+/// Remove tag at runtime
+///
+/// Removed tags are tombstoned instead of compacted, so the bitmasks of all clients and views stay
+/// valid and the index is reused by the next added tag.
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `name` - Name of the tag
+/// * `is_forced` - Whether to strip the tag from every client and view
+///
+/// # Returns
+///
+/// A [`Result`] with either [`usize`] index on success or otherwise [`anyhow::Error`]
+pub(crate) fn remove(subtle: &Subtle, name: &str, is_forced: bool) -> Result<usize> {
+    let conn = subtle.conn.get().unwrap();
+    let atoms = subtle.atoms.get().unwrap();
+
+    let used_tags = subtle.clients.borrow().iter()
+        .map(|client| client.tags)
+        .chain(subtle.views.borrow().iter().map(|view| view.tags.get()))
+        .fold(Tagging::empty(), |acc, tags| acc | tags);
+
+    let tag_idx = find_removable_idx(&subtle.tags.borrow(), name, used_tags, is_forced)?;
+    let tag_bit = tagging::tag_bit(tag_idx);
+
+    // Strip tag from everyone
+    for client in subtle.clients.borrow_mut().iter_mut() {
+        if client.tags.intersects(tag_bit) {
+            client.tags.remove(tag_bit);
+
+            conn.change_property32(PropMode::REPLACE, client.win, atoms.SUBTLE_CLIENT_TAGS,
+                                   AtomEnum::CARDINAL, &[client.tags.bits()])?.check()?;
+        }
+    }
+
+    for view in subtle.views.borrow().iter() {
+        view.tags.set(view.tags.get().difference(tag_bit));
+    }
+
+    subtle.tags.borrow_mut()[tag_idx] = Tag {
+        flags: TagFlags::DEAD,
+        ..Tag::default()
+    };
+
+    publish(subtle)?;
+    view::publish(subtle)?;
+
+    debug!("{}: name={}, is_forced={}, tag_idx={}", function_name!(), name, is_forced, tag_idx);
+
+    Ok(tag_idx)
+}
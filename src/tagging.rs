@@ -48,3 +48,44 @@ bitflags! {
         const TAG31 = 1 << 31;
     }
 }
+
+/// Index of the default tag for clients without any matching tag
+pub(crate) const DEFAULT_TAG_IDX: usize = 0;
+
+/// Name of the default tag
+pub(crate) const DEFAULT_TAG_NAME: &str = "default";
+
+/// Get bit of the default tag
+///
+/// # Returns
+///
+/// The [`Tagging`] of the default tag
+pub(crate) fn default_tag() -> Tagging {
+    tag_bit(DEFAULT_TAG_IDX)
+}
+
+/// Get bit of a tag, tags start at bit 0
+///
+/// # Arguments
+///
+/// * `tag_idx` - Index of the tag
+///
+/// # Returns
+///
+/// The [`Tagging`] of the tag or an empty one when out of range
+pub(crate) fn tag_bit(tag_idx: usize) -> Tagging {
+    Tagging::from_bits_retain(1u32.checked_shl(tag_idx as u32).unwrap_or(0))
+}
+
+/// Get bit of a view in view masks like the visible views, views start at bit 1
+///
+/// # Arguments
+///
+/// * `view_idx` - Index of the view
+///
+/// # Returns
+///
+/// The [`Tagging`] of the view or an empty one when out of range
+pub(crate) fn view_bit(view_idx: usize) -> Tagging {
+    Tagging::from_bits_retain(1u32.checked_shl(view_idx as u32 + 1).unwrap_or(0))
+}
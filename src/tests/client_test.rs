@@ -0,0 +1,1553 @@
+///
+/// @package subtle-rs
+///
+/// @file Client tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use proptest::prelude::*;
+use x11rb::protocol::xproto::{ConfigWindow, ConfigureRequestEvent, ModMask, Rectangle, StackMode};
+use x11rb::errors::ReplyError;
+use x11rb::properties::{WmSizeHints, WmSizeHintsSpecification};
+use x11rb::protocol::ErrorKind;
+use x11rb::x11_utils::X11Error;
+use crate::client::{reply_unless_gone, calc_allowed_actions, calc_border_pixel, calc_border_width, calc_centered_dialog, calc_drag_keys, calc_drag_origin, calc_drag_step, calc_frame_extents, calc_handled_icons, DragMode, calc_sibling_snap, calc_snap_modifier, calc_urgent_mode, is_draggable, is_snapping, tally, calc_zaphod, decode_text, mark_visible_arrange, parse_wm_class, calc_activation, calc_tag_change, calc_transient_tags, calc_tile_geoms, calc_toggled_flags, calc_desktop_tags, calc_pinned_tags, calc_requested_geom, calc_restacking, calc_restored_geom, calc_screen_snap, calc_state_delta, calc_urgent_tags, calc_wm_desktop, Activation, Client, ClientFlags, ClientProps, DragKey, KeyboardDrag, MaskState, RestackOrder, Restacking, ALL_DESKTOPS, NET_WM_STATE_ADD, NET_WM_STATE_REMOVE, NET_WM_STATE_TOGGLE, SOURCE_APPLICATION};
+use crate::effect::Executor;
+use crate::ewmh::AllowedActions;
+use std::collections::HashMap;
+use crate::grab::{DirectionOrder, Grab, GrabAction, GrabFlags, KeyMap};
+use crate::ipc::{Atoms, STACK_FOCUS, STACK_FORCE};
+use crate::screen::{Screen, ScreenFlags};
+use crate::subtle::{Subtle, SubtleFlags};
+use crate::tag;
+use crate::tag::TagBuilder;
+use crate::tagging::Tagging;
+use crate::view::ViewBuilder;
+
+fn create_client(tags: Tagging, flags: ClientFlags) -> Client {
+    Client {
+        tags,
+        flags,
+        ..Default::default()
+    }
+}
+
+fn create_request(value_mask: ConfigWindow, x: i16, y: i16, width: u16, height: u16) -> ConfigureRequestEvent {
+    ConfigureRequestEvent {
+        response_type: 0,
+        stack_mode: StackMode::ABOVE,
+        sequence: 0,
+        parent: 0,
+        window: 0,
+        sibling: 0,
+        x,
+        y,
+        width,
+        height,
+        border_width: 0,
+        value_mask,
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_keep_urgent_tags_of_other_clients(id1 in 0u16..31, id2 in 0u16..31) {
+        let tag1 = Tagging::from_bits_retain(1 << id1);
+        let tag2 = Tagging::from_bits_retain(1 << id2);
+
+        let mut clients = vec![
+            create_client(tag1, ClientFlags::MODE_URGENT),
+            create_client(tag1 | tag2, ClientFlags::MODE_URGENT),
+        ];
+
+        prop_assert_eq!(calc_urgent_tags(&clients).bits(), (tag1 | tag2).bits());
+
+        // Shared tag must stay urgent after first client loses urgency
+        clients[0].flags.remove(ClientFlags::MODE_URGENT);
+
+        prop_assert_eq!(calc_urgent_tags(&clients).bits(), (tag1 | tag2).bits());
+
+        clients[1].flags.remove(ClientFlags::MODE_URGENT);
+
+        prop_assert!(calc_urgent_tags(&clients).is_empty());
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_ignore_dead_urgent_clients(id in 0u16..31) {
+        let clients = vec![
+            create_client(Tagging::from_bits_retain(1 << id), ClientFlags::MODE_URGENT | ClientFlags::DEAD),
+        ];
+
+        prop_assert!(calc_urgent_tags(&clients).is_empty());
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_only_apply_requested_fields(x in -100i16..100, y in -100i16..100, width in 1u16..100, height in 1u16..100) {
+        let geom = Rectangle { x: 10, y: 20, width: 30, height: 40 };
+
+        // Move only
+        let requested = calc_requested_geom(&geom, &create_request(ConfigWindow::X | ConfigWindow::Y,
+            x, y, width, height));
+
+        prop_assert_eq!((requested.x, requested.y, requested.width, requested.height),
+            (x, y, geom.width, geom.height));
+
+        // Resize only
+        let requested = calc_requested_geom(&geom, &create_request(ConfigWindow::WIDTH | ConfigWindow::HEIGHT,
+            x, y, width, height));
+
+        prop_assert_eq!((requested.x, requested.y, requested.width, requested.height),
+            (geom.x, geom.y, width, height));
+
+        // Stacking only
+        let requested = calc_requested_geom(&geom, &create_request(ConfigWindow::STACK_MODE,
+            x, y, width, height));
+
+        prop_assert_eq!((requested.x, requested.y, requested.width, requested.height),
+            (geom.x, geom.y, geom.width, geom.height));
+    }
+}
+
+#[test]
+fn should_translate_stack_mode() {
+    assert_eq!(RestackOrder::from(StackMode::ABOVE), RestackOrder::Up);
+    assert_eq!(RestackOrder::from(StackMode::TOP_IF), RestackOrder::Up);
+    assert_eq!(RestackOrder::from(StackMode::BELOW), RestackOrder::Down);
+    assert_eq!(RestackOrder::from(StackMode::BOTTOM_IF), RestackOrder::Down);
+    assert_eq!(RestackOrder::from(StackMode::OPPOSITE), RestackOrder::None);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_translate_desktop_into_view_tags(desktop_idx in 0u32..3) {
+        let views = (0..3)
+            .map(|view_idx| ViewBuilder::default()
+                .name(format!("view{}", view_idx))
+                .tags(Tagging::from_bits_retain(1 << (view_idx + 1)))
+                .build().unwrap())
+            .collect::<Vec<_>>();
+
+        prop_assert_eq!(calc_desktop_tags(&views, desktop_idx).map(|tags| tags.bits()),
+            Some(1 << (desktop_idx + 1)));
+    }
+}
+
+#[test]
+fn should_ignore_out_of_range_desktops() {
+    let views = vec![ViewBuilder::default().name("www".to_string()).build().unwrap()];
+
+    assert!(calc_desktop_tags(&views, 0).is_some());
+    assert!(calc_desktop_tags(&views, 1).is_none());
+    assert!(calc_desktop_tags(&views, ALL_DESKTOPS).is_none());
+    assert!(calc_desktop_tags(&[], 0).is_none());
+}
+
+#[test]
+fn should_always_focus_visible_clients() {
+    // Legacy clients, applications and pagers
+    for source in [0, SOURCE_APPLICATION, 2] {
+        assert_eq!(calc_activation(source, true), Activation::Focus);
+    }
+}
+
+#[test]
+fn should_prevent_focus_stealing_of_applications() {
+    assert_eq!(calc_activation(SOURCE_APPLICATION, false), Activation::MarkUrgent);
+
+    // Pagers and legacy clients
+    assert_eq!(calc_activation(0, false), Activation::Jump);
+    assert_eq!(calc_activation(2, false), Activation::Jump);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_toggle_requested_states(bits in 0u32..16) {
+        let all_flags = [ClientFlags::MODE_FULL, ClientFlags::MODE_FLOAT,
+            ClientFlags::MODE_STICK, ClientFlags::MODE_URGENT];
+
+        let current_flags = all_flags.iter().enumerate()
+            .filter(|(idx, _)| 0 != bits & (1 << idx))
+            .fold(ClientFlags::empty(), |flags, (_, flag)| flags | *flag);
+
+        for requested_flags in [ClientFlags::MODE_FULL, ClientFlags::MODE_FLOAT | ClientFlags::MODE_STICK] {
+            let added = current_flags ^ calc_state_delta(current_flags, NET_WM_STATE_ADD, requested_flags);
+            let removed = current_flags ^ calc_state_delta(current_flags, NET_WM_STATE_REMOVE, requested_flags);
+            let toggled = current_flags ^ calc_state_delta(current_flags, NET_WM_STATE_TOGGLE, requested_flags);
+
+            prop_assert!(added.contains(requested_flags));
+            prop_assert!(!removed.intersects(requested_flags));
+            prop_assert_eq!(toggled.bits(), (current_flags ^ requested_flags).bits());
+        }
+    }
+}
+
+#[test]
+fn should_ignore_unknown_state_actions() {
+    assert!(calc_state_delta(ClientFlags::MODE_FULL, 3, ClientFlags::MODE_FULL).is_empty());
+    assert!(calc_state_delta(ClientFlags::empty(), NET_WM_STATE_ADD, ClientFlags::empty()).is_empty());
+}
+
+fn create_x11_error(error_kind: ErrorKind) -> ReplyError {
+    ReplyError::X11Error(X11Error {
+        error_kind,
+        error_code: 0,
+        sequence: 0,
+        bad_value: 42,
+        minor_opcode: 0,
+        major_opcode: 0,
+        extension_name: None,
+        request_name: None,
+    })
+}
+
+#[test]
+fn should_skip_reply_of_gone_window() {
+    assert_eq!(reply_unless_gone(Ok::<u32, ReplyError>(1)).unwrap(), Some(1));
+    assert_eq!(reply_unless_gone::<u32>(Err(create_x11_error(ErrorKind::Window))).unwrap(), None);
+    assert!(reply_unless_gone::<u32>(Err(create_x11_error(ErrorKind::Atom))).is_err());
+}
+
+#[test]
+fn should_update_size_hints() {
+    let mut client = create_client(Tagging::empty(), ClientFlags::empty());
+    let mut mode_flags = ClientFlags::empty();
+    let screen_geom = Rectangle { x: 0, y: 0, width: 800, height: 600 };
+
+    let mut size_hints = WmSizeHints::new();
+
+    size_hints.min_size = Some((200, 100));
+    size_hints.max_size = Some((200, 100));
+    size_hints.size_increment = Some((8, 16));
+    size_hints.size = Some((WmSizeHintsSpecification::UserSpecified, 320, 240));
+
+    client.update_size_hints(Some(&size_hints), &screen_geom, 10, false, &mut mode_flags);
+
+    assert_eq!((client.min_width, client.min_height, client.max_width, client.max_height), (200, 100, 200, 100));
+    assert_eq!((client.width_inc, client.height_inc), (8, 16));
+    assert_eq!((client.geom.width, client.geom.height), (0, 0));
+    assert!(mode_flags.contains(ClientFlags::MODE_FLOAT | ClientFlags::MODE_FIXED));
+
+    client.update_size_hints(Some(&size_hints), &screen_geom, 10, true, &mut mode_flags);
+
+    assert_eq!((client.geom.width, client.geom.height), (320, 240));
+
+    // Reset to defaults without hints
+    client.update_size_hints(None, &screen_geom, 10, true, &mut mode_flags);
+
+    assert_eq!((client.max_width, client.max_height, client.width_inc), (-1, -1, 1));
+}
+
+#[test]
+fn should_update_props_like_sequential_path() {
+    let geom = Rectangle { x: 0, y: 0, width: 800, height: 600 };
+    let subtle = Subtle { screens: vec![Screen { geom, base: geom, ..Screen::default() }], ..Subtle::default() };
+
+    subtle.atoms.set(Atoms {
+        WM_TAKE_FOCUS: 10,
+        WM_DELETE_WINDOW: 11,
+        _NET_WM_WINDOW_TYPE_DIALOG: 20,
+        _NET_WM_STATE_STICKY: 30,
+        ..Atoms::default()
+    }).unwrap();
+
+    let atoms = subtle.atoms.get().unwrap();
+
+    let mut size_hints = WmSizeHints::new();
+
+    size_hints.min_size = Some((200, 100));
+    size_hints.max_size = Some((200, 100));
+
+    let props = ClientProps {
+        size_hints: Some(size_hints),
+        net_wm_name: b"net name".to_vec(),
+        wm_name: b"name".to_vec(),
+        wm_role: b"role".to_vec(),
+        wm_klass: b"xterm\0XTerm\0".to_vec(),
+        protocols: vec![10, 11],
+        wm_types: vec![20],
+        states: vec![30],
+        has_handled_icons: true,
+        transient_for: Some(42),
+        leader: Some(43),
+        ..ClientProps::default()
+    };
+
+    // Batched path
+    let mut batched = create_client(Tagging::empty(), ClientFlags::INPUT);
+    let mut batched_mode_flags = ClientFlags::empty();
+
+    batched.update_props(&subtle, &props, &mut batched_mode_flags).unwrap();
+
+    // Sequential path
+    let mut sequential = create_client(Tagging::empty(), ClientFlags::INPUT);
+    let mut sequential_mode_flags = ClientFlags::empty();
+
+    sequential.update_normal_hints(&subtle, props.size_hints.as_ref(), &mut sequential_mode_flags).unwrap();
+    sequential.update_wm_name(&props.net_wm_name, &props.wm_name, &props.wm_role, &props.wm_klass);
+    sequential.update_wm_protocols(atoms, &props.protocols);
+    sequential.update_wm_type(atoms, &props.wm_types, &mut sequential_mode_flags);
+    sequential.flags.set(ClientFlags::HANDLED_ICONS, props.has_handled_icons);
+    sequential.update_wm_hints(&subtle, None, &mut sequential_mode_flags);
+    sequential.update_motif_wm_hints(&[], &mut sequential_mode_flags);
+    sequential.update_net_wm_state(atoms, &props.states, &mut sequential_mode_flags);
+    sequential.update_transient(&subtle, props.transient_for, &mut sequential_mode_flags);
+    sequential.leader = 43;
+
+    assert_eq!(batched_mode_flags, sequential_mode_flags);
+    assert_eq!(batched.flags, sequential.flags);
+    assert_eq!((&batched.name, &batched.role, &batched.instance, &batched.klass),
+               (&sequential.name, &sequential.role, &sequential.instance, &sequential.klass));
+    assert_eq!((batched.transient_for, batched.leader), (sequential.transient_for, sequential.leader));
+    assert_eq!((batched.min_width, batched.max_height), (sequential.min_width, sequential.max_height));
+
+    // Spot-check the evaluated values
+    assert_eq!((batched.name.as_str(), batched.klass.as_str()), ("net name", "XTerm"));
+    assert!(batched.flags.contains(ClientFlags::FOCUS | ClientFlags::CLOSE
+        | ClientFlags::TYPE_DIALOG | ClientFlags::HANDLED_ICONS));
+    assert!(batched_mode_flags.contains(ClientFlags::MODE_FLOAT | ClientFlags::MODE_FIXED
+        | ClientFlags::MODE_CENTER | ClientFlags::MODE_STICK));
+    assert_eq!((batched.transient_for, batched.leader), (42, 43));
+}
+
+#[test]
+fn should_detect_protocols_and_window_types() {
+    let atoms = Atoms {
+        WM_TAKE_FOCUS: 10,
+        WM_DELETE_WINDOW: 11,
+        _NET_WM_WINDOW_TYPE_DOCK: 21,
+        _NET_WM_WINDOW_TYPE_DIALOG: 22,
+        ..Atoms::default()
+    };
+
+    let mut client = create_client(Tagging::empty(), ClientFlags::empty());
+
+    client.update_wm_protocols(&atoms, &[11]);
+
+    assert!(client.flags.contains(ClientFlags::CLOSE));
+    assert!(!client.flags.contains(ClientFlags::FOCUS));
+
+    // Dialogs float
+    let mut mode_flags = ClientFlags::empty();
+
+    client.update_wm_type(&atoms, &[22], &mut mode_flags);
+
+    assert!(client.flags.contains(ClientFlags::TYPE_DIALOG));
+    assert!(mode_flags.contains(ClientFlags::MODE_FLOAT));
+
+    // Docks are fixed and sticky
+    let mut client = create_client(Tagging::empty(), ClientFlags::empty());
+    let mut mode_flags = ClientFlags::empty();
+
+    client.update_wm_type(&atoms, &[21], &mut mode_flags);
+
+    assert!(client.flags.contains(ClientFlags::TYPE_DOCK));
+    assert!(mode_flags.contains(ClientFlags::MODE_FIXED | ClientFlags::MODE_STICK));
+}
+
+#[test]
+fn should_stick_other_client_to_focus_screen_without_pointer_warp() {
+    let geom = Rectangle { x: 0, y: 0, width: 800, height: 600 };
+    let subtle = Subtle {
+        executor: Executor::new(true),
+        flags: SubtleFlags::SKIP_POINTER_WARP,
+        screens: vec![Screen { geom, base: geom, ..Screen::default() },
+            Screen { geom, base: geom, ..Screen::default() }],
+        ..Subtle::default()
+    };
+
+    subtle.visible_tags.set(Tagging::TAG1);
+
+    for (win, screen_idx) in [(1, 1), (2, 0)] {
+        subtle.clients.borrow_mut().push(Client { win, tags: Tagging::TAG1, screen_idx, ..Default::default() });
+    }
+
+    subtle.focus_history.push(1);
+
+    // Like a client message, toggle with the other client borrowed
+    let focus_screen_idx = subtle.find_focus_screen_idx();
+    let mut client = subtle.find_client_mut(2).unwrap();
+    let mut mode_flags = ClientFlags::MODE_STICK;
+
+    client.toggle(&subtle, &mut mode_flags, true, focus_screen_idx).unwrap();
+
+    assert!(client.flags.contains(ClientFlags::MODE_STICK));
+    assert_eq!(client.screen_idx, 1);
+}
+
+#[test]
+fn should_keep_float_geometry_when_unfloating_in_fullscreen() {
+    let geom = Rectangle { x: 0, y: 0, width: 800, height: 600 };
+    let subtle = Subtle {
+        executor: Executor::new(true),
+        screens: vec![Screen { geom, base: geom, ..Screen::default() }],
+        ..Subtle::default()
+    };
+
+    let mut client = Client {
+        geom: Rectangle { x: 100, y: 100, width: 200, height: 150 },
+        flags: ClientFlags::MODE_FLOAT,
+        ..Default::default()
+    };
+
+    // Enter fullscreen and take the screen geometry like arrange does
+    client.toggle(&subtle, &mut ClientFlags::MODE_FULL.clone(), false, None).unwrap();
+    client.geom = geom;
+
+    // Leaving float while in fullscreen keeps the geometry saved on entry
+    client.toggle(&subtle, &mut ClientFlags::MODE_FLOAT.clone(), false, None).unwrap();
+    client.toggle(&subtle, &mut ClientFlags::MODE_FULL.clone(), false, None).unwrap();
+
+    let saved_geom = client.saved_geom.unwrap();
+
+    assert_eq!((saved_geom.x, saved_geom.y, saved_geom.width, saved_geom.height), (100, 100, 200, 150));
+
+    client.toggle(&subtle, &mut ClientFlags::MODE_FLOAT.clone(), false, None).unwrap();
+
+    assert!(client.flags.contains(ClientFlags::MODE_FLOAT));
+    assert_eq!((client.geom.x, client.geom.y, client.geom.width, client.geom.height), (100, 100, 200, 150));
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_add_and_remove_tag(tag_idx in 1usize..32) {
+        let tags = calc_tag_change(Tagging::TAG1, tag_idx, true);
+
+        prop_assert_eq!(tags.bits(), 1 | 1 << tag_idx);
+        prop_assert_eq!(calc_tag_change(tags, tag_idx, false).bits(), 1);
+    }
+}
+
+#[test]
+fn should_fall_back_to_default_tag_when_untagged() {
+    assert_eq!(calc_tag_change(Tagging::TAG2, 1, false).bits(), Tagging::TAG1.bits());
+    assert_eq!(calc_tag_change(Tagging::TAG1, 0, false).bits(), Tagging::TAG1.bits());
+}
+
+#[test]
+fn should_tile_two_clients_with_odd_width() {
+    let area = Rectangle { x: 10, y: 20, width: 101, height: 50 };
+
+    let geoms: Vec<(i16, i16, u16, u16)> = calc_tile_geoms(area, true, 2).iter()
+        .map(|geom| (geom.x, geom.y, geom.width, geom.height))
+        .collect();
+
+    assert_eq!(geoms, vec![(10, 20, 50, 50), (60, 20, 51, 50)]);
+}
+
+#[test]
+fn should_tile_three_clients_with_odd_width() {
+    let area = Rectangle { x: 0, y: 0, width: 1001, height: 50 };
+
+    let geoms: Vec<(i16, u16)> = calc_tile_geoms(area, true, 3).iter()
+        .map(|geom| (geom.x, geom.width))
+        .collect();
+
+    assert_eq!(geoms, vec![(0, 333), (333, 333), (666, 335)]);
+}
+
+#[test]
+fn should_tile_vertically_and_keep_other_axis() {
+    let area = Rectangle { x: 5, y: 100, width: 300, height: 301 };
+
+    let geoms: Vec<(i16, i16, u16, u16)> = calc_tile_geoms(area, false, 3).iter()
+        .map(|geom| (geom.x, geom.y, geom.width, geom.height))
+        .collect();
+
+    assert_eq!(geoms, vec![(5, 100, 300, 100), (5, 200, 300, 100), (5, 300, 300, 101)]);
+    assert!(calc_tile_geoms(area, false, 0).is_empty());
+}
+
+#[test]
+fn should_keep_other_modes_when_toggling() {
+    let flags = ClientFlags::MODE_FLOAT | ClientFlags::MODE_STICK | ClientFlags::ARRANGE;
+
+    let toggled = calc_toggled_flags(flags, ClientFlags::MODE_FULL);
+
+    assert_eq!(toggled, flags | ClientFlags::MODE_FULL);
+    assert_eq!(calc_toggled_flags(toggled, ClientFlags::MODE_FULL), flags);
+}
+
+#[test]
+fn should_only_toggle_mode_bits() {
+    let flags = ClientFlags::MODE_FULL | ClientFlags::TYPE_DIALOG;
+
+    let toggled = calc_toggled_flags(flags, ClientFlags::MODE_FULL | ClientFlags::TYPE_DESKTOP
+        | ClientFlags::ARRANGE);
+
+    assert_eq!(toggled, ClientFlags::TYPE_DIALOG);
+}
+
+#[test]
+fn should_parse_wm_class() {
+    assert_eq!(parse_wm_class(b""), (String::new(), String::new()));
+    assert_eq!(parse_wm_class(b"xterm\0"), ("xterm".to_string(), String::new()));
+    assert_eq!(parse_wm_class(b"xterm\0XTerm\0"), ("xterm".to_string(), "XTerm".to_string()));
+    assert_eq!(parse_wm_class(b"x\xfferm\0XTerm\0"), ("x\u{fffd}erm".to_string(), "XTerm".to_string()));
+}
+
+#[test]
+fn should_decode_invalid_text_lossy() {
+    assert_eq!(decode_text(b"name\0"), "name");
+    assert_eq!(decode_text(b"\xc3\x28"), "\u{fffd}(");
+}
+
+#[test]
+fn should_mark_only_visible_clients_for_arrange() {
+    let subtle = Subtle::default();
+
+    subtle.visible_tags.set(Tagging::from_bits_retain(1 << 1));
+
+    subtle.clients.borrow_mut().extend([
+        create_client(Tagging::from_bits_retain(1 << 1), ClientFlags::empty()),
+        create_client(Tagging::from_bits_retain(1 << 2), ClientFlags::empty()),
+        create_client(Tagging::from_bits_retain(1 << 2), ClientFlags::MODE_STICK),
+        create_client(Tagging::from_bits_retain(1 << 1), ClientFlags::DEAD),
+    ]);
+
+    assert_eq!(mark_visible_arrange(&subtle), 2);
+
+    let marked: Vec<bool> = subtle.clients.borrow().iter()
+        .map(|client| client.flags.contains(ClientFlags::ARRANGE))
+        .collect();
+
+    assert_eq!(marked, vec![true, false, true, false]);
+}
+
+fn create_hinted_client() -> Client {
+    Client {
+        min_width: 50,
+        min_height: 50,
+        base_width: 2,
+        base_height: 4,
+        width_inc: 7,
+        height_inc: 13,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn should_round_to_size_increments() {
+    let client = create_hinted_client();
+    let mut geom = Rectangle { x: 0, y: 0, width: 101, height: 100 };
+
+    client.calc_hinted_geom(500, 500, true, true, false, &mut geom);
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (1, 5, 100, 95));
+}
+
+#[test]
+fn should_skip_size_increments_but_keep_limits() {
+    let client = create_hinted_client();
+    let mut geom = Rectangle { x: 0, y: 0, width: 100, height: 100 };
+
+    client.calc_hinted_geom(500, 500, true, true, true, &mut geom);
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (0, 0, 100, 100));
+
+    let mut geom = Rectangle { x: 0, y: 0, width: 10, height: 900 };
+
+    client.calc_hinted_geom(500, 500, false, false, true, &mut geom);
+
+    assert_eq!((geom.width, geom.height), (50, 500));
+}
+
+fn apply_extreme_hints(client: &Client, bounds: Rectangle, geom: Rectangle) -> Rectangle {
+    let subtle = Subtle::default();
+    let mut geom = geom;
+
+    client.apply_size_hints(&subtle, &bounds, true, true, &mut geom);
+
+    geom
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+
+    #[test]
+    fn should_survive_extreme_size_hints(base in 0u16..=u16::MAX, inc in 0u16..=u16::MAX,
+                                         width in 0u16..=u16::MAX)
+    {
+        let client = Client {
+            flags: ClientFlags::MODE_RESIZE,
+            base_width: base,
+            base_height: base,
+            width_inc: inc,
+            height_inc: inc,
+            max_width: -1,
+            max_height: -1,
+            ..Default::default()
+        };
+
+        let bounds = Rectangle { x: 0, y: 0, width, height: width };
+        let geom = apply_extreme_hints(&client, bounds, Rectangle { x: 0, y: 0, width, height: width });
+
+        prop_assert!(1 <= geom.width && geom.width <= width.max(1));
+        prop_assert!(1 <= geom.height && geom.height <= width.max(1));
+    }
+}
+
+#[test]
+fn should_ignore_zero_increments() {
+    let client = Client {
+        flags: ClientFlags::MODE_RESIZE,
+        max_width: -1,
+        max_height: -1,
+        ..Default::default()
+    };
+
+    let bounds = Rectangle { x: 0, y: 0, width: 500, height: 500 };
+    let geom = apply_extreme_hints(&client, bounds, Rectangle { x: 0, y: 0, width: 123, height: 45 });
+
+    assert_eq!((geom.width, geom.height), (123, 45));
+}
+
+#[test]
+fn should_prefer_max_over_inverted_min() {
+    let client = Client {
+        flags: ClientFlags::MODE_RESIZE,
+        min_width: 300,
+        min_height: 300,
+        max_width: 100,
+        max_height: 100,
+        base_width: 400,
+        base_height: 400,
+        width_inc: 1,
+        height_inc: 1,
+        ..Default::default()
+    };
+
+    let bounds = Rectangle { x: 0, y: 0, width: 500, height: 500 };
+    let geom = apply_extreme_hints(&client, bounds, Rectangle { x: 0, y: 0, width: 50, height: 50 });
+
+    assert_eq!((geom.width, geom.height), (100, 100));
+}
+
+#[test]
+fn should_clamp_zaphod_with_wide_padding() {
+    let mut subtle = Subtle {
+        width: 100,
+        height: 100,
+        ..Subtle::default()
+    };
+
+    subtle.clients_style.padding.left = 80;
+    subtle.clients_style.padding.right = 80;
+
+    let mut geom = Rectangle { x: 0, y: 0, width: 0, height: 0 };
+
+    calc_zaphod(&subtle, &mut geom).unwrap();
+
+    assert_eq!((geom.x, geom.width, geom.height), (80, 1, 100));
+}
+
+fn zaphod_with_screens(screens: &[(i16, u16, ScreenFlags)]) -> Rectangle {
+    let mut subtle = Subtle {
+        panel_height: 20,
+        ..Subtle::default()
+    };
+
+    for (x, width, flags) in screens {
+        let base = Rectangle { x: *x, y: 0, width: *width, height: 600 };
+
+        subtle.screens.push(Screen { flags: *flags, base, geom: base, ..Screen::default() });
+    }
+
+    let mut geom = Rectangle { x: 0, y: 0, width: 0, height: 0 };
+
+    calc_zaphod(&subtle, &mut geom).unwrap();
+
+    geom
+}
+
+#[test]
+fn should_subtract_panels_of_single_screen_for_zaphod() {
+    let geom = zaphod_with_screens(&[(0, 800, ScreenFlags::TOP_PANEL | ScreenFlags::BOTTOM_PANEL)]);
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (0, 20, 800, 560));
+
+    let geom = zaphod_with_screens(&[(0, 800, ScreenFlags::empty())]);
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (0, 0, 800, 600));
+}
+
+#[test]
+fn should_subtract_panel_of_second_screen_for_zaphod() {
+    let geom = zaphod_with_screens(&[
+        (0, 800, ScreenFlags::empty()),
+        (800, 1024, ScreenFlags::BOTTOM_PANEL),
+    ]);
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (0, 0, 1824, 580));
+}
+
+#[test]
+fn should_subtract_mixed_panels_of_three_screens_for_zaphod() {
+    let geom = zaphod_with_screens(&[
+        (0, 800, ScreenFlags::TOP_PANEL),
+        (800, 800, ScreenFlags::TOP_PANEL | ScreenFlags::BOTTOM_PANEL),
+        (1600, 800, ScreenFlags::BOTTOM_PANEL),
+    ]);
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (0, 20, 2400, 560));
+}
+
+#[test]
+fn should_ignore_panels_off_the_union_edges_for_zaphod() {
+    let mut subtle = Subtle {
+        panel_height: 20,
+        ..Subtle::default()
+    };
+
+    // Shorter right screen has its bottom panel above the bottom edge of the union
+    let left = Rectangle { x: 0, y: 0, width: 800, height: 600 };
+    let right = Rectangle { x: 800, y: 0, width: 800, height: 400 };
+
+    subtle.screens.push(Screen { base: left, geom: left, ..Screen::default() });
+    subtle.screens.push(Screen { flags: ScreenFlags::BOTTOM_PANEL, base: right, geom: right, ..Screen::default() });
+
+    subtle.clients_style.padding.left = 5;
+    subtle.clients_style.padding.right = 5;
+
+    let mut geom = Rectangle { x: 0, y: 0, width: 0, height: 0 };
+
+    calc_zaphod(&subtle, &mut geom).unwrap();
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (5, 0, 1590, 600));
+}
+
+#[test]
+fn should_map_urgency_hint_to_mode() {
+    assert_eq!(calc_urgent_mode(true, false), ClientFlags::MODE_URGENT);
+    assert_eq!(calc_urgent_mode(true, true), ClientFlags::empty());
+    assert_eq!(calc_urgent_mode(false, false), ClientFlags::empty());
+    assert_eq!(calc_urgent_mode(false, true), ClientFlags::empty());
+}
+
+fn create_transient_pair(follow_parent: bool) -> (Subtle, Client) {
+    let subtle = Subtle {
+        executor: Executor::new(true),
+        ..Subtle::default()
+    };
+
+    // Second tag matches the dialog and conflicts with the parent
+    for (name, regex) in [("editor", "^vim$"), ("files", "Save")] {
+        subtle.tags.borrow_mut().push(TagBuilder::default()
+            .name(name.to_string())
+            .regex(Some(regex::Regex::new(regex).unwrap()))
+            .build()
+            .unwrap());
+    }
+
+    let parent = create_client(Tagging::TAG1, ClientFlags::empty());
+
+    let mut client = Client {
+        name: "Save file".to_string(),
+        transient_for: 1,
+        ..Default::default()
+    };
+
+    client.tags = calc_transient_tags(client.tags, parent.tags, follow_parent);
+
+    if follow_parent {
+        client.flags.insert(ClientFlags::PARENT_TAGS);
+    }
+
+    (subtle, client)
+}
+
+#[test]
+fn should_keep_tags_of_transient_parent() {
+    let (subtle, mut client) = create_transient_pair(true);
+    let mut mode_flags = ClientFlags::empty();
+
+    client.retag(&subtle, &mut mode_flags).unwrap();
+
+    assert_eq!(client.tags.bits(), Tagging::TAG1.bits());
+}
+
+#[test]
+fn should_add_matching_tags_to_transient() {
+    let (subtle, mut client) = create_transient_pair(false);
+    let mut mode_flags = ClientFlags::empty();
+
+    client.retag(&subtle, &mut mode_flags).unwrap();
+
+    assert_eq!(client.tags.bits(), (Tagging::TAG1 | Tagging::TAG2).bits());
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_replace_or_extend_transient_tags(bits in 1u32..u32::MAX, parent_bits in 1u32..u32::MAX) {
+        let tags = Tagging::from_bits_retain(bits);
+        let parent_tags = Tagging::from_bits_retain(parent_bits);
+
+        prop_assert_eq!(calc_transient_tags(tags, parent_tags, true).bits(), parent_bits);
+        prop_assert_eq!(calc_transient_tags(tags, parent_tags, false).bits(), bits | parent_bits);
+    }
+}
+
+#[test]
+fn should_snap_edge_to_edge_of_sibling() {
+    // Sibling occupies x 0..100 with its border, dragged client starts 4px right of it
+    let sibling = Rectangle { x: 0, y: 0, width: 100, height: 100 };
+    let geom = Rectangle { x: 104, y: 20, width: 50, height: 50 };
+
+    assert_eq!(calc_sibling_snap(&geom, 2, &[sibling], 10), (Some(100), None));
+}
+
+#[test]
+fn should_snap_outer_corner_of_dragged_client() {
+    let bounds = Rectangle { x: 0, y: 0, width: 800, height: 600 };
+
+    // Pointer is 10px inside of the client with a 2px border
+    let (x, y) = calc_drag_origin(150, 120, 10, 10, 2);
+
+    assert_eq!((x, y), (138, 108));
+
+    // Outer border lands on the screen edge
+    let (x, y) = calc_drag_origin(15, 14, 10, 10, 2);
+    let snapped = calc_screen_snap(&Rectangle { x, y, width: 200, height: 100 }, 2, &bounds, 10);
+
+    assert_eq!((snapped.x, snapped.y), (0, 0));
+
+    // And next to the border of a sibling
+    let sibling = Rectangle { x: 0, y: 200, width: 100, height: 100 };
+    let (x, y) = calc_drag_origin(117, 230, 10, 10, 2);
+
+    assert_eq!(calc_sibling_snap(&Rectangle { x, y, width: 50, height: 50 }, 2, &[sibling], 10),
+               (Some(100), None));
+}
+
+#[test]
+fn should_snap_to_same_edge_of_sibling() {
+    let sibling = Rectangle { x: 200, y: 0, width: 100, height: 100 };
+    let geom = Rectangle { x: 205, y: 48, width: 50, height: 50 };
+
+    assert_eq!(calc_sibling_snap(&geom, 0, &[sibling], 10), (Some(200), Some(50)));
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_snap_outer_border_to_screen_edges(border_width in 0i16..8, offset in 0i16..=10) {
+        let bounds = Rectangle { x: 100, y: 50, width: 800, height: 600 };
+        let outer = 2 * border_width;
+
+        // Just inside the snap margin on the left and top
+        let geom = Rectangle { x: 100 + offset, y: 50 - offset, width: 200, height: 100 };
+        let snapped = calc_screen_snap(&geom, border_width, &bounds, 10);
+
+        prop_assert_eq!((snapped.x, snapped.y), (100, 50));
+
+        // Just inside the snap margin on the right and bottom
+        let geom = Rectangle { x: 900 - 200 - outer - offset, y: 650 - 100 - outer + offset, width: 200, height: 100 };
+        let snapped = calc_screen_snap(&geom, border_width, &bounds, 10);
+
+        prop_assert_eq!(snapped.x + snapped.width as i16 + outer, 900);
+        prop_assert_eq!(snapped.y + snapped.height as i16 + outer, 650);
+    }
+}
+
+#[test]
+fn should_not_snap_outside_of_snap_margin() {
+    let bounds = Rectangle { x: 100, y: 50, width: 800, height: 600 };
+
+    for border_width in [0, 2, 5] {
+        let outer = 2 * border_width;
+
+        let geom = Rectangle { x: 111, y: 39, width: 200, height: 100 };
+        let snapped = calc_screen_snap(&geom, border_width, &bounds, 10);
+
+        assert_eq!((snapped.x, snapped.y), (geom.x, geom.y));
+
+        let geom = Rectangle { x: 900 - 200 - outer - 11, y: 650 - 100 - outer + 11, width: 200, height: 100 };
+        let snapped = calc_screen_snap(&geom, border_width, &bounds, 10);
+
+        assert_eq!((snapped.x, snapped.y), (geom.x, geom.y));
+    }
+}
+
+#[test]
+fn should_snap_right_edge_with_and_without_border() {
+    let bounds = Rectangle { x: 0, y: 0, width: 800, height: 600 };
+    let geom = Rectangle { x: 595, y: 3, width: 200, height: 100 };
+
+    let snapped = calc_screen_snap(&geom, 0, &bounds, 10);
+
+    assert_eq!((snapped.x, snapped.y, snapped.width, snapped.height), (600, 0, 200, 100));
+
+    let snapped = calc_screen_snap(&geom, 3, &bounds, 10);
+
+    assert_eq!((snapped.x, snapped.y, snapped.width, snapped.height), (594, 0, 200, 100));
+}
+
+#[test]
+fn should_bypass_snapping_while_modifier_is_held() {
+    let state = u16::from(ModMask::M1) | u16::from(ModMask::SHIFT);
+
+    assert!(!is_snapping(state, ModMask::M1, 10));
+    assert!(is_snapping(u16::from(ModMask::SHIFT), ModMask::M1, 10));
+}
+
+#[test]
+fn should_not_snap_with_snap_size_zero() {
+    assert!(!is_snapping(0, ModMask::M1, 0));
+    assert!(!is_snapping(0, ModMask::default(), 0));
+
+    let subtle = Subtle { snap_size: 0, ..Subtle::default() };
+    let screen = Screen { geom: Rectangle { x: 0, y: 0, width: 800, height: 600 }, ..Screen::default() };
+    let client = Client::default();
+    let mut geom = Rectangle { x: 2, y: 3, width: 100, height: 100 };
+
+    client.snap(&subtle, &screen, &mut geom).unwrap();
+
+    assert_eq!((geom.x, geom.y), (2, 3));
+}
+
+#[test]
+fn should_ignore_snap_modifier_of_the_grab() {
+    // Move grab bound to Alt, so Alt can't bypass the snapping
+    let snap_modifier = calc_snap_modifier(ModMask::M1, ModMask::M1 | ModMask::M4);
+
+    assert!(is_snapping(u16::from(ModMask::M1), snap_modifier, 10));
+
+    let snap_modifier = calc_snap_modifier(ModMask::M1, ModMask::M4);
+
+    assert!(!is_snapping(u16::from(ModMask::M1 | ModMask::M4), snap_modifier, 10));
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_snap_without_modifier(state in 0u16..=u16::MAX, snap_size in 1u16..=u16::MAX) {
+        prop_assert!(is_snapping(state, ModMask::default(), snap_size));
+        prop_assert_eq!(is_snapping(state & !u16::from(ModMask::M1), ModMask::M1, snap_size), true);
+    }
+}
+
+#[test]
+fn should_prefer_closest_sibling_edge() {
+    let near = Rectangle { x: 0, y: 150, width: 100, height: 100 };
+    let far = Rectangle { x: 0, y: 150, width: 92, height: 100 };
+    let geom = Rectangle { x: 98, y: 200, width: 50, height: 50 };
+
+    assert_eq!(calc_sibling_snap(&geom, 0, &[far, near], 10).0, Some(100));
+    assert_eq!(calc_sibling_snap(&Rectangle { x: 94, ..geom }, 0, &[far, near], 10).0, Some(92));
+}
+
+#[test]
+fn should_ignore_siblings_out_of_reach() {
+    let sibling = Rectangle { x: 0, y: 0, width: 100, height: 100 };
+
+    // Too far away on the snap axis
+    assert_eq!(calc_sibling_snap(&Rectangle { x: 120, y: 20, width: 50, height: 50 },
+                                 0, &[sibling], 10).0, None);
+
+    // Not next to each other on the other axis
+    assert_eq!(calc_sibling_snap(&Rectangle { x: 102, y: 400, width: 50, height: 50 },
+                                 0, &[sibling], 10), (None, None));
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_snap_within_margin_only(offset in -20i16..20, snap_size in 0u16..15) {
+        let sibling = Rectangle { x: 0, y: 0, width: 100, height: 500 };
+        let geom = Rectangle { x: 100 + offset, y: 200, width: 50, height: 50 };
+
+        let (snap_x, _) = calc_sibling_snap(&geom, 0, &[sibling], snap_size);
+
+        if offset.unsigned_abs() <= snap_size {
+            prop_assert_eq!(snap_x, Some(100));
+        } else {
+            prop_assert_eq!(snap_x, None);
+        }
+    }
+}
+
+#[test]
+fn should_move_along_drag_direction() {
+    let geom = Rectangle { x: 100, y: 100, width: 200, height: 150 };
+    let step = |dir| calc_drag_step(DragMode::MOVE, dir, 5, 8, 16, &geom);
+
+    assert_eq!((step(DirectionOrder::Up).x, step(DirectionOrder::Up).y), (100, 95));
+    assert_eq!((step(DirectionOrder::Right).x, step(DirectionOrder::Right).y), (105, 100));
+    assert_eq!((step(DirectionOrder::Down).x, step(DirectionOrder::Down).y), (100, 105));
+    assert_eq!((step(DirectionOrder::Left).x, step(DirectionOrder::Left).y), (95, 100));
+    assert_eq!(step(DirectionOrder::Right).width, 200);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_grow_towards_drag_direction(x in -500i16..500, y in -500i16..500,
+                                          width_inc in 1u16..32, height_inc in 1u16..32)
+    {
+        let geom = Rectangle { x, y, width: 200, height: 150 };
+        let step = |dir| calc_drag_step(DragMode::RESIZE, dir, 5, width_inc, height_inc, &geom);
+
+        // Opposite edge stays in place
+        let up = step(DirectionOrder::Up);
+        prop_assert_eq!((up.y, up.height), (y - height_inc as i16, 150 + height_inc));
+        prop_assert_eq!(up.y as i32 + up.height as i32, y as i32 + 150);
+
+        let right = step(DirectionOrder::Right);
+        prop_assert_eq!((right.x, right.width, right.height), (x, 200 + width_inc, 150));
+
+        let down = step(DirectionOrder::Down);
+        prop_assert_eq!((down.y, down.height, down.width), (y, 150 + height_inc, 200));
+
+        let left = step(DirectionOrder::Left);
+        prop_assert_eq!((left.x, left.width), (x - width_inc as i16, 200 + width_inc));
+    }
+}
+
+#[test]
+fn should_center_dialog_fitting_on_screen() {
+    let bounds = Rectangle { x: 1920, y: 0, width: 1280, height: 800 };
+    let geom = Rectangle { x: 0, y: 0, width: 400, height: 300 };
+
+    let dialog_geom = calc_centered_dialog(&bounds, &geom, 2, (1, 1), 50, true);
+
+    assert_eq!((dialog_geom.x, dialog_geom.y, dialog_geom.width, dialog_geom.height), (2358, 248, 400, 300));
+}
+
+#[test]
+fn should_keep_top_edge_of_tall_dialog_on_screen() {
+    let bounds = Rectangle { x: 0, y: 20, width: 1366, height: 748 };
+    let geom = Rectangle { x: 0, y: 0, width: 800, height: 1000 };
+
+    let dialog_geom = calc_centered_dialog(&bounds, &geom, 0, (1, 1), 50, false);
+
+    assert_eq!((dialog_geom.x, dialog_geom.y), (283, 20));
+    assert_eq!(dialog_geom.height, 1000);
+
+    // Shrink just the oversized dimension
+    let dialog_geom = calc_centered_dialog(&bounds, &geom, 0, (1, 1), 50, true);
+
+    assert_eq!((dialog_geom.y, dialog_geom.width, dialog_geom.height), (20, 800, 748));
+}
+
+#[test]
+fn should_shrink_dialog_bigger_than_screen() {
+    let bounds = Rectangle { x: 0, y: 0, width: 1024, height: 600 };
+    let geom = Rectangle { x: 0, y: 0, width: 1600, height: 1200 };
+
+    let dialog_geom = calc_centered_dialog(&bounds, &geom, 1, (1, 1), 50, true);
+
+    assert_eq!((dialog_geom.x, dialog_geom.y, dialog_geom.width, dialog_geom.height), (0, 0, 1022, 598));
+
+    // Min size wins over the screen size
+    let dialog_geom = calc_centered_dialog(&bounds, &geom, 1, (1200, 700), 50, true);
+
+    assert_eq!((dialog_geom.width, dialog_geom.height, dialog_geom.y), (1200, 700, 0));
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_keep_oversized_dialog_reachable(width in 1u16..4000, height in 1u16..4000, min_visible in 0u16..200) {
+        let bounds = Rectangle { x: 100, y: 50, width: 1280, height: 720 };
+        let geom = Rectangle { x: 0, y: 0, width, height };
+
+        let dialog_geom = calc_centered_dialog(&bounds, &geom, 0, (1, 1), min_visible, false);
+        let visible = min_visible.min(width).min(bounds.width) as i32;
+
+        prop_assert!(bounds.y <= dialog_geom.y && dialog_geom.y < bounds.y + bounds.height as i16);
+        prop_assert!(dialog_geom.x as i32 + width as i32 >= bounds.x as i32 + visible);
+        prop_assert!(dialog_geom.x as i32 + visible <= bounds.x as i32 + bounds.width as i32);
+    }
+}
+
+fn create_aspect_client(ratio: f32, width_inc: u16, height_inc: u16) -> Client {
+    Client {
+        min_width: 1,
+        min_height: 1,
+        min_ratio: ratio,
+        max_ratio: ratio,
+        width_inc,
+        height_inc,
+        // Stale geometry must not influence the result
+        geom: Rectangle { x: 0, y: 0, width: 100, height: 1000 },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn should_apply_aspect_ratio_to_candidate_geom() {
+    let client = create_aspect_client(16.0 / 9.0, 1, 1);
+    let mut geom = Rectangle { x: 0, y: 0, width: 1000, height: 450 };
+
+    client.calc_hinted_geom(2000, 2000, false, false, false, &mut geom);
+
+    assert_eq!((geom.width, geom.height), (800, 450));
+
+    let client = create_aspect_client(4.0 / 3.0, 1, 1);
+    let mut geom = Rectangle { x: 0, y: 0, width: 300, height: 300 };
+
+    client.calc_hinted_geom(2000, 2000, false, false, false, &mut geom);
+
+    assert_eq!((geom.width, geom.height), (400, 300));
+}
+
+#[test]
+fn should_apply_aspect_ratio_after_increments() {
+    let client = create_aspect_client(4.0 / 3.0, 10, 10);
+    let mut geom = Rectangle { x: 0, y: 0, width: 405, height: 305 };
+
+    client.calc_hinted_geom(2000, 2000, false, false, false, &mut geom);
+
+    assert_eq!((geom.width, geom.height), (400, 300));
+}
+
+#[test]
+fn should_keep_aspect_ratio_within_max_size() {
+    let client = create_aspect_client(16.0 / 9.0, 1, 1);
+    let mut geom = Rectangle { x: 0, y: 0, width: 640, height: 900 };
+
+    client.calc_hinted_geom(1280, 1000, false, false, false, &mut geom);
+
+    assert_eq!((geom.width, geom.height), (1280, 720));
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_stay_within_limits_with_aspect_ratio(width in 1u16..3000, height in 1u16..3000,
+                                                   ratio in prop::sample::select(vec![16.0f32 / 9.0, 4.0 / 3.0]))
+    {
+        let client = create_aspect_client(ratio, 1, 1);
+        let mut geom = Rectangle { x: 0, y: 0, width, height };
+
+        client.calc_hinted_geom(1920, 1080, false, false, false, &mut geom);
+
+        prop_assert!(geom.width <= 1920 && geom.height <= 1080);
+    }
+}
+
+#[test]
+fn should_fall_back_to_default_tag_without_match() {
+    let subtle = Subtle {
+        executor: Executor::new(true),
+        ..Subtle::default()
+    };
+
+    for (name, regex) in [("default", None), ("editor", Some(regex::Regex::new("^vim$").unwrap()))] {
+        subtle.tags.borrow_mut().push(TagBuilder::default()
+            .name(name.to_string())
+            .regex(regex)
+            .build()
+            .unwrap());
+    }
+
+    let mut client = Client { instance: "xterm".to_string(), ..Default::default() };
+    let mut mode_flags = ClientFlags::empty();
+
+    client.retag(&subtle, &mut mode_flags).unwrap();
+
+    assert_eq!(client.tags.bits(), Tagging::TAG1.bits());
+
+    let mut client = Client { instance: "vim".to_string(), ..Default::default() };
+
+    client.retag(&subtle, &mut mode_flags).unwrap();
+
+    assert_eq!(client.tags.bits(), Tagging::TAG2.bits());
+}
+
+#[test]
+fn should_refuse_restacking_of_unmanaged_window() {
+    assert_eq!(calc_restacking(RestackOrder::Up, None, false, STACK_FOCUS), (Restacking::Refuse, false));
+}
+
+#[test]
+fn should_restack_and_focus_on_raise() {
+    let flags = Some(ClientFlags::TYPE_NORMAL);
+
+    assert_eq!(calc_restacking(RestackOrder::Up, flags, true, STACK_FOCUS),
+               (Restacking::Restack(RestackOrder::Up), true));
+    assert_eq!(calc_restacking(RestackOrder::Up, flags, false, 0),
+               (Restacking::Restack(RestackOrder::Up), false));
+    assert_eq!(calc_restacking(RestackOrder::Down, flags, false, STACK_FOCUS),
+               (Restacking::Restack(RestackOrder::Down), false));
+}
+
+#[test]
+fn should_refuse_lowering_desktop_unless_forced() {
+    let flags = Some(ClientFlags::TYPE_DESKTOP);
+
+    assert_eq!(calc_restacking(RestackOrder::Down, flags, false, 0), (Restacking::Refuse, false));
+    assert_eq!(calc_restacking(RestackOrder::Down, flags, false, STACK_FORCE),
+               (Restacking::Restack(RestackOrder::Down), false));
+}
+
+#[test]
+fn should_refuse_raising_dock_above_fullscreen_unless_forced() {
+    let flags = Some(ClientFlags::TYPE_DOCK);
+
+    assert_eq!(calc_restacking(RestackOrder::Up, flags, true, STACK_FOCUS), (Restacking::Refuse, false));
+    assert_eq!(calc_restacking(RestackOrder::Up, flags, true, STACK_FOCUS | STACK_FORCE),
+               (Restacking::RaiseAbove, true));
+    assert_eq!(calc_restacking(RestackOrder::Up, flags, false, 0),
+               (Restacking::Restack(RestackOrder::Up), false));
+}
+
+#[test]
+fn should_report_border_as_frame_extents() {
+    assert_eq!(calc_frame_extents(ClientFlags::TYPE_NORMAL, 2), vec![2, 2, 2, 2]);
+    assert_eq!(calc_frame_extents(ClientFlags::empty(), -1), vec![0, 0, 0, 0]);
+}
+
+#[test]
+fn should_report_no_frame_extents_without_border() {
+    for flags in [ClientFlags::MODE_BORDERLESS, ClientFlags::MODE_FULL, ClientFlags::TYPE_DESKTOP,
+        ClientFlags::TYPE_DOCK]
+    {
+        assert_eq!(calc_frame_extents(flags, 2), vec![0, 0, 0, 0]);
+    }
+}
+
+#[test]
+fn should_allow_move_and_resize_of_floating_clients_only() {
+    let base = AllowedActions::FULLSCREEN | AllowedActions::STICK | AllowedActions::CLOSE;
+
+    assert_eq!(calc_allowed_actions(ClientFlags::TYPE_NORMAL), base);
+    assert_eq!(calc_allowed_actions(ClientFlags::MODE_FLOAT),
+               base | AllowedActions::MOVE | AllowedActions::RESIZE);
+    assert_eq!(calc_allowed_actions(ClientFlags::MODE_FLOAT | ClientFlags::MODE_FULL), base);
+}
+
+#[test]
+fn should_not_allow_resize_of_fixed_clients() {
+    assert_eq!(calc_allowed_actions(ClientFlags::MODE_FLOAT | ClientFlags::MODE_FIXED),
+               AllowedActions::FULLSCREEN | AllowedActions::STICK | AllowedActions::CLOSE
+                   | AllowedActions::MOVE);
+}
+
+#[test]
+fn should_not_allow_fullscreen_of_desktop_and_dock() {
+    for flags in [ClientFlags::TYPE_DESKTOP, ClientFlags::TYPE_DOCK | ClientFlags::MODE_FLOAT] {
+        assert_eq!(calc_allowed_actions(flags), AllowedActions::STICK | AllowedActions::CLOSE);
+    }
+}
+
+#[test]
+fn should_pick_border_pixel_by_focus() {
+    assert_eq!(calc_border_pixel(ClientFlags::TYPE_NORMAL, true, 1, 2), Some(1));
+    assert_eq!(calc_border_pixel(ClientFlags::TYPE_NORMAL, false, 1, 2), Some(2));
+
+    // Keep color of hidden border in sync
+    assert_eq!(calc_border_pixel(ClientFlags::MODE_BORDERLESS, false, 1, 2), Some(2));
+}
+
+#[test]
+fn should_skip_border_of_desktop_and_dock() {
+    for flags in [ClientFlags::TYPE_DESKTOP, ClientFlags::TYPE_DOCK] {
+        assert_eq!(calc_border_pixel(flags, true, 1, 2), None);
+        assert_eq!(calc_border_width(flags, 2), 0);
+    }
+
+    assert_eq!(calc_border_width(ClientFlags::MODE_BORDERLESS, 2), 0);
+    assert_eq!(calc_border_width(ClientFlags::TYPE_NORMAL, 2), 2);
+}
+
+#[test]
+fn should_erase_previous_mask_before_drawing() {
+    let mut state = MaskState::default();
+    let first = Rectangle { x: 0, y: 0, width: 10, height: 10 };
+    let second = Rectangle { x: 5, y: 5, width: 10, height: 10 };
+
+    let geoms = state.show(&first);
+
+    assert_eq!(geoms.len(), 1);
+    assert_eq!(geoms[0].x, 0);
+
+    let geoms = state.show(&second);
+
+    assert_eq!(geoms.iter().map(|geom| geom.x).collect::<Vec<i16>>(), vec![0, 5]);
+    assert!(state.is_drawn());
+}
+
+#[test]
+fn should_erase_mask_only_once() {
+    let mut state = MaskState::default();
+
+    assert!(state.hide().is_none());
+
+    state.show(&Rectangle { x: 1, y: 2, width: 3, height: 4 });
+
+    assert_eq!(state.hide().map(|geom| geom.x), Some(1));
+    assert!(state.hide().is_none());
+    assert!(!state.is_drawn());
+}
+
+#[test]
+fn should_draw_mask_again_after_repaint() {
+    let mut state = MaskState::default();
+
+    assert!(state.invalidate().is_none());
+
+    state.show(&Rectangle { x: 7, y: 0, width: 3, height: 4 });
+
+    let geom = state.invalidate().unwrap();
+
+    // Repainted mask is gone, so drawing mustn't erase it first
+    assert_eq!(state.show(&geom).len(), 1);
+}
+
+#[test]
+fn should_pin_view_tags_without_removing_others() {
+    let tags = calc_pinned_tags(Tagging::TAG1, Tagging::TAG2 | Tagging::TAG3, true);
+
+    assert_eq!(tags.map(|tags| tags.bits()), Some((Tagging::TAG1 | Tagging::TAG2 | Tagging::TAG3).bits()));
+}
+
+#[test]
+fn should_unpin_view_tags_unless_orphaned() {
+    let tags = calc_pinned_tags(Tagging::TAG1 | Tagging::TAG2, Tagging::TAG2, false);
+
+    assert_eq!(tags.map(|tags| tags.bits()), Some(Tagging::TAG1.bits()));
+    assert!(calc_pinned_tags(Tagging::TAG2, Tagging::TAG2 | Tagging::TAG3, false).is_none());
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_never_orphan_unpinned_clients(bits in 1u32..u32::MAX, view_bits in 0u32..u32::MAX) {
+        let tags = calc_pinned_tags(Tagging::from_bits_retain(bits), Tagging::from_bits_retain(view_bits), false);
+
+        prop_assert!(tags.is_none_or(|tags| !tags.is_empty()));
+    }
+}
+
+#[test]
+fn should_select_lowest_view_as_desktop() {
+    let views = [Tagging::TAG1, Tagging::TAG2, Tagging::TAG3].iter()
+        .enumerate()
+        .map(|(view_idx, tags)| ViewBuilder::default()
+            .name(format!("view{}", view_idx))
+            .tags(*tags)
+            .build().unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(calc_wm_desktop(&views, Tagging::TAG2 | Tagging::TAG3), Some(1));
+    assert_eq!(calc_wm_desktop(&views, Tagging::TAG3), Some(2));
+    assert_eq!(calc_wm_desktop(&views, Tagging::TAG4), None);
+}
+
+#[test]
+fn should_refuse_resize_of_fixed_clients() {
+    assert!(!is_draggable(ClientFlags::MODE_FIXED, DragMode::RESIZE));
+    assert!(is_draggable(ClientFlags::MODE_FIXED, DragMode::MOVE));
+    assert!(is_draggable(ClientFlags::MODE_FLOAT, DragMode::RESIZE));
+}
+
+#[test]
+fn should_never_drag_desktop_or_dock() {
+    for flags in [ClientFlags::TYPE_DESKTOP, ClientFlags::TYPE_DOCK] {
+        assert!(!is_draggable(flags, DragMode::MOVE));
+        assert!(!is_draggable(flags, DragMode::RESIZE));
+    }
+}
+
+#[test]
+fn should_tally_indices_per_key() {
+    let mut tiles: Vec<((usize, usize, usize), Vec<usize>)> = Vec::new();
+
+    tally(&mut tiles, (0, 0, 0), 0);
+    tally(&mut tiles, (1, 0, 0), 1);
+    tally(&mut tiles, (0, 0, 0), 2);
+    tally(&mut tiles, (0, 0, 1), 3);
+
+    assert_eq!(tiles, vec![((0, 0, 0), vec![0, 2]), ((1, 0, 0), vec![1]), ((0, 0, 1), vec![3])]);
+}
+
+#[test]
+fn should_derive_handled_icons_from_docks() {
+    let mut clients = vec![
+        create_client(Tagging::empty(), ClientFlags::TYPE_NORMAL | ClientFlags::HANDLED_ICONS),
+        create_client(Tagging::empty(), ClientFlags::TYPE_DOCK),
+    ];
+
+    // Only docks can take over icon handling
+    assert!(!calc_handled_icons(&clients));
+
+    clients.push(create_client(Tagging::empty(), ClientFlags::TYPE_DOCK | ClientFlags::HANDLED_ICONS));
+
+    assert!(calc_handled_icons(&clients));
+
+    // Resume own handling once the taskbar is gone
+    clients[2].flags.insert(ClientFlags::DEAD);
+
+    assert!(!calc_handled_icons(&clients));
+
+    clients.pop();
+
+    assert!(!calc_handled_icons(&clients));
+}
+
+#[test]
+fn should_adjust_pending_geom_of_keyboard_drag() {
+    let geom = Rectangle { x: 100, y: 100, width: 200, height: 100 };
+    let mut keyboard_drag = KeyboardDrag::new(1, DragMode::MOVE, geom, Vec::new(), true);
+
+    for drag_dir in [DirectionOrder::Right, DirectionOrder::Right, DirectionOrder::Down, DirectionOrder::Left] {
+        keyboard_drag.step(drag_dir, 10, 1, 1);
+    }
+
+    assert_eq!((keyboard_drag.pending.x, keyboard_drag.pending.y), (110, 110));
+    assert_eq!((keyboard_drag.origin.x, keyboard_drag.origin.y), (100, 100));
+
+    // Resize grows by the size increments
+    let mut keyboard_drag = KeyboardDrag::new(1, DragMode::RESIZE, geom, Vec::new(), true);
+
+    for drag_dir in [DirectionOrder::Right, DirectionOrder::Down, DirectionOrder::Down, DirectionOrder::Up] {
+        keyboard_drag.step(drag_dir, 10, 8, 16);
+    }
+
+    let pending = keyboard_drag.pending;
+
+    assert_eq!((pending.x, pending.y, pending.width, pending.height), (100, 84, 208, 148));
+}
+
+#[test]
+fn should_apply_keyboard_drag_once() {
+    let geom = Rectangle { x: 100, y: 100, width: 200, height: 100 };
+
+    for outline in [true, false] {
+        let mut keyboard_drag = KeyboardDrag::new(1, DragMode::MOVE, geom, Vec::new(), outline);
+
+        keyboard_drag.step(DirectionOrder::Up, 5, 1, 1);
+
+        let commit_geom = keyboard_drag.calc_finish_geom(true);
+        let cancel_geom = keyboard_drag.calc_finish_geom(false);
+
+        // Outlines are applied on commit, live steps restored on cancel
+        if outline {
+            assert_eq!(commit_geom.map(|geom| geom.y), Some(95));
+            assert!(cancel_geom.is_none());
+        } else {
+            assert!(commit_geom.is_none());
+            assert_eq!(cancel_geom.map(|geom| geom.y), Some(100));
+        }
+    }
+}
+
+#[test]
+fn should_move_transients_by_keyboard_drag_delta() {
+    let geom = Rectangle { x: 100, y: 100, width: 200, height: 100 };
+    let mut keyboard_drag = KeyboardDrag::new(1, DragMode::MOVE, geom, Vec::new(), false);
+
+    for drag_dir in [DirectionOrder::Left, DirectionOrder::Left, DirectionOrder::Down] {
+        keyboard_drag.step(drag_dir, 10, 1, 1);
+    }
+
+    assert_eq!(keyboard_drag.calc_delta(&keyboard_drag.pending), (-20, 10));
+    assert_eq!(keyboard_drag.calc_delta(&keyboard_drag.origin), (0, 0));
+}
+
+#[test]
+fn should_map_keyboard_drag_keys() {
+    let mut keymap: KeyMap = HashMap::new();
+
+    keymap.insert(0xFF0D, (36, ModMask::default()));
+    keymap.insert(0xFF1B, (9, ModMask::default()));
+    keymap.insert(0xFF51, (113, ModMask::default()));
+
+    let grabs = vec![
+        Grab { flags: GrabFlags::IS_KEY | GrabFlags::WINDOW_SELECT, keycode: 43,
+            modifiers: ModMask::M4, action: GrabAction::Index(DirectionOrder::Left as u32) },
+        Grab { flags: GrabFlags::IS_KEY | GrabFlags::WINDOW_SELECT, keycode: 113,
+            modifiers: ModMask::M4, action: GrabAction::Index(DirectionOrder::Right as u32) },
+        Grab { flags: GrabFlags::IS_MOUSE | GrabFlags::WINDOW_SELECT, keycode: 1,
+            modifiers: ModMask::M4, action: GrabAction::Index(DirectionOrder::Up as u32) },
+    ];
+
+    let keys = calc_drag_keys(&keymap, &grabs);
+
+    assert_eq!(keys, vec![
+        (36, DragKey::Commit),
+        (9, DragKey::Cancel),
+        (113, DragKey::Step(DirectionOrder::Left)),
+        (43, DragKey::Step(DirectionOrder::Left)),
+    ]);
+
+    let keyboard_drag = KeyboardDrag::new(1, DragMode::MOVE, Rectangle::default(), keys, true);
+
+    assert_eq!(keyboard_drag.find_key(43), Some(DragKey::Step(DirectionOrder::Left)));
+    assert_eq!(keyboard_drag.find_key(44), None);
+}
+
+#[test]
+fn should_restore_saved_geom_when_it_fits() {
+    let bounds = Rectangle { x: 100, y: 50, width: 800, height: 600 };
+    let saved_geom = Rectangle { x: 300, y: 200, width: 200, height: 100 };
+
+    let geom = calc_restored_geom(&bounds, &saved_geom, 2, (0, 0), 50, false);
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (300, 200, 200, 100));
+
+    // Outer border edges exactly on the screen edges
+    let saved_geom = Rectangle { x: 100, y: 50, width: 796, height: 596 };
+
+    let geom = calc_restored_geom(&bounds, &saved_geom, 2, (0, 0), 50, false);
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (100, 50, 796, 596));
+}
+
+#[test]
+fn should_center_saved_geom_when_it_left_the_screen() {
+    let bounds = Rectangle { x: 100, y: 50, width: 800, height: 600 };
+    let saved_geom = Rectangle { x: 1500, y: 200, width: 200, height: 100 };
+
+    let geom = calc_restored_geom(&bounds, &saved_geom, 0, (0, 0), 50, false);
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (400, 300, 200, 100));
+
+    // Screen shrunk below the saved size
+    let saved_geom = Rectangle { x: 100, y: 50, width: 1000, height: 700 };
+
+    let geom = calc_restored_geom(&bounds, &saved_geom, 0, (0, 0), 50, true);
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (100, 50, 800, 600));
+}
+
+#[test]
+fn should_set_normal_type_for_explicit_and_untyped_windows() {
+    let atoms = Atoms {
+        _NET_WM_WINDOW_TYPE_DIALOG: 20,
+        _NET_WM_WINDOW_TYPE_NORMAL: 21,
+        ..Atoms::default()
+    };
+
+    for wm_types in [vec![], vec![21], vec![99]] {
+        let mut client = create_client(Tagging::empty(), ClientFlags::empty());
+        let mut mode_flags = ClientFlags::empty();
+
+        client.update_wm_type(&atoms, &wm_types, &mut mode_flags);
+
+        assert!(client.flags.contains(ClientFlags::TYPE_NORMAL));
+        assert!(tag::matches_types(client.flags, ClientFlags::empty(), ClientFlags::TYPE_NORMAL));
+    }
+
+    let mut client = create_client(Tagging::empty(), ClientFlags::empty());
+    let mut mode_flags = ClientFlags::empty();
+
+    client.update_wm_type(&atoms, &[20], &mut mode_flags);
+
+    assert!(!client.flags.contains(ClientFlags::TYPE_NORMAL));
+}
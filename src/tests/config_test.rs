@@ -0,0 +1,121 @@
+///
+/// @package subtle-rs
+///
+/// @file Config tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use std::collections::HashMap;
+use proptest::prelude::*;
+use crate::config::{calc_feature_warnings, check_duplicate_names, check_empty_names, has_tray_item, Command, FeatureFlags, MixedConfigVal};
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_keep_unique_names(names in proptest::collection::hash_set("[a-z]{1,8}", 1..10)) {
+        let mut names: Vec<String> = names.into_iter().collect();
+        let expected = names.clone();
+
+        prop_assert!(check_empty_names("view", &names).is_ok());
+        prop_assert!(check_duplicate_names("view", &mut names, false).is_ok());
+        prop_assert_eq!(names, expected);
+    }
+}
+
+#[test]
+fn should_reject_empty_names() {
+    let names = vec!["www".to_string(), "".to_string(), " ".to_string()];
+
+    let err = check_empty_names("tag", &names).unwrap_err();
+
+    assert_eq!(err.to_string(), "Empty tag names at index 1, 2");
+}
+
+#[test]
+fn should_reject_duplicate_names() {
+    let mut names = vec!["www".to_string(), "dev".to_string(), "www".to_string(), "www".to_string()];
+
+    let err = check_duplicate_names("view", &mut names, false).unwrap_err();
+
+    assert_eq!(err.to_string(), "Duplicate view names: www");
+}
+
+#[test]
+fn should_suffix_duplicate_names_when_lenient() {
+    let mut names = vec!["www".to_string(), "www".to_string(), "www_2".to_string(), "www".to_string()];
+
+    check_duplicate_names("view", &mut names, true).unwrap();
+
+    assert_eq!(names, vec!["www", "www_3", "www_2", "www_4"]);
+}
+
+fn create_screen(top_panel: &[&str], tray: bool) -> HashMap<String, MixedConfigVal> {
+    let mut values = HashMap::new();
+
+    values.insert("top_panel".to_string(),
+                  MixedConfigVal::VS(top_panel.iter().map(|name| name.to_string()).collect()));
+    values.insert("tray".to_string(), MixedConfigVal::B(tray));
+
+    values
+}
+
+#[test]
+fn should_not_warn_with_all_features() {
+    let screens = vec![create_screen(&["views", ">tray", "$clock"], true)];
+    let plugins = vec![HashMap::new()];
+
+    assert!(calc_feature_warnings(&screens, &plugins, FeatureFlags::all()).is_empty());
+}
+
+#[test]
+fn should_warn_about_disabled_features() {
+    let screens = vec![create_screen(&["views"], false), create_screen(&["<tray"], false)];
+    let plugins = vec![HashMap::new(), HashMap::new()];
+
+    assert_eq!(calc_feature_warnings(&screens, &plugins, FeatureFlags::PANEL), vec![
+        "Ignoring tray of screen `1`: built without feature `tray`",
+        "Ignoring 2 plugin(s): built without feature `plugins`",
+    ]);
+
+    assert_eq!(calc_feature_warnings(&screens, &[], FeatureFlags::empty()), vec![
+        "Ignoring panels of screen `0`: built without feature `panel`",
+        "Ignoring panels of screen `1`: built without feature `panel`",
+        "Ignoring tray of screen `1`: built without feature `tray`",
+    ]);
+}
+
+#[test]
+fn should_warn_about_tray_screen_without_tray_item() {
+    let screens = vec![create_screen(&[], true)];
+
+    assert_eq!(calc_feature_warnings(&screens, &[], FeatureFlags::PANEL), vec![
+        "Ignoring tray of screen `0`: built without feature `tray`",
+    ]);
+}
+
+#[test]
+fn should_find_tray_item_with_position_marker() {
+    assert!(has_tray_item(&create_screen(&["views", ">tray"], false)));
+    assert!(has_tray_item(&create_screen(&["tray"], false)));
+}
+
+#[test]
+fn should_not_find_tray_without_tray_item() {
+    assert!(!has_tray_item(&create_screen(&["views", "$tray_plugin"], true)));
+    assert!(!has_tray_item(&HashMap::new()));
+}
+
+#[test]
+fn should_parse_plugin_test_command() {
+    let args = ["plugin-test", "clock"].map(String::from);
+
+    assert!(matches!(Command::parse_args(&args),
+        Ok(Some(Command::PluginTest { name_or_url })) if "clock" == name_or_url));
+    assert!(matches!(Command::parse_args(&[]), Ok(None)));
+    assert!(Command::parse_args(&["plugin-test".to_string()]).is_err());
+    assert!(Command::parse_args(&["unknown".to_string()]).is_err());
+}
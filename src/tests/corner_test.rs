@@ -0,0 +1,94 @@
+///
+/// @package subtle-rs
+///
+/// @file Hot corner tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use proptest::prelude::*;
+use x11rb::protocol::xproto::Rectangle;
+use crate::config::MixedConfigVal;
+use crate::corner::{calc_corner_geom, parse_actions, should_trigger, CornerPosition};
+use crate::grab::GrabFlags;
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_place_corner_inside_of_screen(x in 0i16..2000, y in 0i16..2000,
+                                            width in 1u16..2000, height in 1u16..2000, size in 0u16..100)
+    {
+        let screen_geom = Rectangle { x, y, width, height };
+
+        for position in [CornerPosition::TopLeft, CornerPosition::TopRight,
+            CornerPosition::BottomLeft, CornerPosition::BottomRight]
+        {
+            let geom = calc_corner_geom(screen_geom, position, size);
+
+            prop_assert!(geom.x >= x && geom.y >= y);
+            prop_assert!(geom.x as i32 + geom.width as i32 <= x as i32 + width as i32);
+            prop_assert!(geom.y as i32 + geom.height as i32 <= y as i32 + height as i32);
+        }
+    }
+}
+
+#[test]
+fn should_place_corners_at_screen_corners() {
+    let screen_geom = Rectangle { x: 1920, y: 0, width: 1280, height: 1024 };
+
+    let geom = calc_corner_geom(screen_geom, CornerPosition::TopLeft, 2);
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (1920, 0, 2, 2));
+
+    let geom = calc_corner_geom(screen_geom, CornerPosition::TopRight, 2);
+
+    assert_eq!((geom.x, geom.y), (3198, 0));
+
+    let geom = calc_corner_geom(screen_geom, CornerPosition::BottomLeft, 2);
+
+    assert_eq!((geom.x, geom.y), (1920, 1022));
+
+    let geom = calc_corner_geom(screen_geom, CornerPosition::BottomRight, 0);
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (3199, 1023, 1, 1));
+}
+
+#[test]
+fn should_parse_corner_positions() {
+    assert_eq!(CornerPosition::try_from("bottom_right").unwrap(), CornerPosition::BottomRight);
+    assert!(CornerPosition::try_from("center").is_err());
+}
+
+#[test]
+fn should_suppress_repeated_triggers() {
+    let triggered_at = Cell::new(None);
+    let delay = Duration::from_millis(500);
+    let now = Instant::now();
+
+    assert!(should_trigger(&triggered_at, now, delay));
+    assert!(!should_trigger(&triggered_at, now + Duration::from_millis(100), delay));
+    assert!(!should_trigger(&triggered_at, now + Duration::from_millis(499), delay));
+    assert!(should_trigger(&triggered_at, now + Duration::from_millis(500), delay));
+    assert_eq!(triggered_at.get(), Some(now + Duration::from_millis(500)));
+}
+
+#[test]
+fn should_skip_unknown_corner_actions() {
+    let mut corners = HashMap::new();
+
+    corners.insert("top_left".to_string(), MixedConfigVal::S("view_next".to_string()));
+    corners.insert("top_right".to_string(), MixedConfigVal::S("view_nxet".to_string()));
+    corners.insert("bottom_left".to_string(), MixedConfigVal::I(1));
+
+    let actions = parse_actions(&corners);
+
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[0].position, CornerPosition::TopLeft);
+    assert_eq!(actions[0].flags, GrabFlags::VIEW_CYCLE);
+}
@@ -0,0 +1,129 @@
+///
+/// @package subtle-rs
+///
+/// @file Cursor tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use std::path::PathBuf;
+use proptest::prelude::*;
+use crate::cursor::{find_cursor_file, get_search_paths, parse_inherits, parse_xcursor};
+
+fn create_xcursor(images: &[(u32, u32, u32)]) -> Vec<u8> {
+    let mut data = Vec::new();
+
+    // Header
+    data.extend_from_slice(b"Xcur");
+    data.extend_from_slice(&16u32.to_le_bytes());
+    data.extend_from_slice(&0x10000u32.to_le_bytes());
+    data.extend_from_slice(&(images.len() as u32).to_le_bytes());
+
+    // Table of contents
+    let mut position = 16 + images.len() as u32 * 12;
+
+    for (nominal_size, width, height) in images.iter() {
+        data.extend_from_slice(&0xfffd0002u32.to_le_bytes());
+        data.extend_from_slice(&nominal_size.to_le_bytes());
+        data.extend_from_slice(&position.to_le_bytes());
+
+        position += 36 + width * height * 4;
+    }
+
+    // Image chunks with hotspot in the center and pixels set to the nominal size
+    for (nominal_size, width, height) in images.iter() {
+        for value in [36, 0xfffd0002, *nominal_size, 1, *width, *height, width / 2, height / 2, 0] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+
+        for _ in 0..(width * height) {
+            data.extend_from_slice(&nominal_size.to_le_bytes());
+        }
+    }
+
+    data
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_parse_xcursor_image(width in 1u32..64, height in 1u32..64) {
+        let data = create_xcursor(&[(24, width, height)]);
+
+        let image = parse_xcursor(&data, 24).unwrap();
+
+        prop_assert_eq!((image.width, image.height), (width as u16, height as u16));
+        prop_assert_eq!((image.xhot, image.yhot), ((width / 2) as u16, (height / 2) as u16));
+        prop_assert_eq!(image.pixels.len(), (width * height) as usize);
+    }
+}
+
+#[test]
+fn should_pick_closest_nominal_size() {
+    let data = create_xcursor(&[(16, 16, 16), (24, 24, 24), (48, 48, 48)]);
+
+    assert_eq!(parse_xcursor(&data, 24).unwrap().pixels[0], 24);
+    assert_eq!(parse_xcursor(&data, 32).unwrap().pixels[0], 24);
+    assert_eq!(parse_xcursor(&data, 40).unwrap().pixels[0], 48);
+    assert_eq!(parse_xcursor(&data, 8).unwrap().pixels[0], 16);
+}
+
+#[test]
+fn should_reject_invalid_xcursor() {
+    let data = create_xcursor(&[(24, 24, 24)]);
+
+    assert!(parse_xcursor(b"Xcu", 24).is_err());
+    assert!(parse_xcursor(b"NoXcursorFile", 24).is_err());
+    assert!(parse_xcursor(&data[..data.len() - 1], 24).is_err());
+    assert!(parse_xcursor(&create_xcursor(&[]), 24).is_err());
+}
+
+#[test]
+fn should_parse_inherits() {
+    let index_theme = "[Icon Theme]\nName=Test\nInherits = Adwaita, hicolor;core\n";
+
+    assert_eq!(parse_inherits(index_theme), vec!["Adwaita", "hicolor", "core"]);
+    assert!(parse_inherits("[Icon Theme]\nName=Test\n").is_empty());
+}
+
+#[test]
+fn should_get_search_paths() {
+    let paths = get_search_paths(None, Some("/home/user"), Some("/data"));
+
+    assert_eq!(paths[0], PathBuf::from("/data/icons"));
+    assert!(paths.contains(&PathBuf::from("/home/user/.icons")));
+    assert!(paths.contains(&PathBuf::from("/usr/share/icons")));
+
+    // XCURSOR_PATH replaces defaults
+    let paths = get_search_paths(Some("~/cursors::/opt/cursors"), Some("/home/user"), Some("/data"));
+
+    assert_eq!(paths, vec![PathBuf::from("/home/user/cursors"), PathBuf::from("/opt/cursors")]);
+
+    // Skip home-relative paths without home
+    let paths = get_search_paths(Some("~/cursors:/opt/cursors"), None, None);
+
+    assert_eq!(paths, vec![PathBuf::from("/opt/cursors")]);
+}
+
+#[test]
+fn should_find_cursor_in_inherited_theme() {
+    let base = std::env::temp_dir().join(format!("subtle-cursor-test-{}", std::process::id()));
+
+    // Theme child inherits from parent, which inherits back from child
+    std::fs::create_dir_all(base.join("child")).unwrap();
+    std::fs::create_dir_all(base.join("parent/cursors")).unwrap();
+    std::fs::write(base.join("child/index.theme"), "[Icon Theme]\nInherits=parent\n").unwrap();
+    std::fs::write(base.join("parent/index.theme"), "[Icon Theme]\nInherits=child\n").unwrap();
+    std::fs::write(base.join("parent/cursors/fleur"), create_xcursor(&[(24, 1, 1)])).unwrap();
+
+    let search_paths = vec![base.clone()];
+
+    assert_eq!(find_cursor_file(&search_paths, "child", "fleur"), Some(base.join("parent/cursors/fleur")));
+    assert_eq!(find_cursor_file(&search_paths, "child", "sizing"), None);
+    assert_eq!(find_cursor_file(&search_paths, "missing", "fleur"), None);
+
+    std::fs::remove_dir_all(&base).unwrap();
+}
@@ -0,0 +1,30 @@
+///
+/// @package subtle-rs
+///
+/// @file Display tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use proptest::prelude::*;
+use crate::display::calc_tray_selection_name;
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_end_tray_selection_with_screen_num(screen_num in 0usize..16) {
+        let name = calc_tray_selection_name(screen_num);
+
+        prop_assert!(name.starts_with("_NET_SYSTEM_TRAY_S"));
+        prop_assert_eq!(name.trim_start_matches("_NET_SYSTEM_TRAY_S"), screen_num.to_string());
+    }
+}
+
+#[test]
+fn should_name_tray_selection_after_screen() {
+    assert_eq!(calc_tray_selection_name(0), "_NET_SYSTEM_TRAY_S0");
+    assert_eq!(calc_tray_selection_name(1), "_NET_SYSTEM_TRAY_S1");
+}
@@ -0,0 +1,75 @@
+///
+/// @package subtle-rs
+///
+/// @file Dump tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use proptest::prelude::*;
+use x11rb::protocol::xproto::Rectangle;
+use crate::client::{Client, ClientFlags};
+use crate::dump::{to_json, ClientDump, Dump, GeomDump, ScreenDump, StateDump};
+use crate::tagging::Tagging;
+
+fn create_dump(win: u32, tags: u32) -> Dump {
+    let client = Client {
+        win,
+        name: "term".into(),
+        klass: "XTerm".into(),
+        flags: ClientFlags::INPUT | ClientFlags::MODE_FLOAT,
+        tags: Tagging::from_bits_retain(tags),
+        geom: Rectangle { x: 10, y: 20, width: 300, height: 200 },
+        gravities: vec![0, 3],
+        ..Client::default()
+    };
+
+    Dump {
+        client: ClientDump::from(&client),
+        state: StateDump {
+            visible_tags: tags,
+            screens: vec![ScreenDump { geom: GeomDump { x: 0, y: 0, width: 1024, height: 768 },
+                ..ScreenDump::default() }],
+            ..StateDump::default()
+        },
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+
+    #[test]
+    fn should_roundtrip_dump(win in 1u32..u32::MAX, tags in 0u32..u32::MAX) {
+        let dump = create_dump(win, tags);
+
+        let decoded: Dump = serde_json::from_str(&to_json(&dump).unwrap()).unwrap();
+
+        prop_assert_eq!(decoded, dump);
+    }
+}
+
+#[test]
+fn should_contain_client_keys() {
+    let json: serde_json::Value = serde_json::from_str(&to_json(&create_dump(42, 1)).unwrap()).unwrap();
+
+    for key in ["win", "leader", "transient_for", "name", "klass", "flags", "tags", "geom",
+        "size_hints", "screen_idx", "gravity_idx", "gravities"]
+    {
+        assert!(json["client"].get(key).is_some(), "missing key `{}`", key);
+    }
+
+    for key in ["visible_tags", "visible_views", "screens"] {
+        assert!(json["state"].get(key).is_some(), "missing key `{}`", key);
+    }
+
+    assert_eq!(json["client"]["flags"], serde_json::json!(["INPUT", "MODE_FLOAT"]));
+    assert_eq!(json["client"]["gravities"], serde_json::json!([0, 3]));
+}
+
+#[test]
+fn should_serialize_stable() {
+    assert_eq!(to_json(&create_dump(42, 1)).unwrap(), to_json(&create_dump(42, 1)).unwrap());
+}
@@ -0,0 +1,390 @@
+///
+/// @package subtle-rs
+///
+/// @file Effect tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use regex::Regex;
+use x11rb::NONE;
+use x11rb::protocol::xproto::Rectangle;
+use crate::client;
+use crate::client::{Client, ClientFlags};
+use crate::effect;
+use crate::effect::{Effect, Executor, Prop, MAX_RECORDED};
+use crate::ewmh::{AllowedActions, EWMHStateFlags, WMState};
+use crate::screen::Screen;
+use crate::subtle::Subtle;
+use crate::tag::{TagBuilder, TagFlags};
+use crate::tagging::Tagging;
+use crate::view::ViewBuilder;
+
+fn create_subtle() -> Subtle {
+    let mut subtle = Subtle {
+        executor: Executor::new(true),
+        ..Subtle::default()
+    };
+
+    let geom = Rectangle { x: 0, y: 0, width: 800, height: 600 };
+
+    subtle.screens.push(Screen { geom, base: geom, ..Screen::default() });
+    subtle.screens[0].view_idx.set(0);
+
+    for (name, tags) in [("www", Tagging::from_bits_retain(1 << 0)), ("dev", Tagging::from_bits_retain(1 << 1))] {
+        subtle.views.borrow_mut().push(ViewBuilder::default().name(name.to_string()).tags(tags).build().unwrap());
+    }
+
+    subtle
+}
+
+fn create_client(win: u32, tags: Tagging) -> Client {
+    Client {
+        flags: ClientFlags::INPUT | ClientFlags::MODE_FLOAT,
+        win,
+        tags,
+        gravity_idx: 0,
+        gravities: vec![0, 0],
+        geom: Rectangle { x: 100, y: 100, width: 200, height: 100 },
+        ..Client::default()
+    }
+}
+
+#[test]
+fn should_record_instead_of_execute() {
+    let subtle = create_subtle();
+
+    effect::apply(&subtle, Effect::Map(1)).unwrap();
+    effect::apply(&subtle, Effect::Unmap(1)).unwrap();
+    effect::flush(&subtle).unwrap();
+
+    assert_eq!(subtle.executor.take_recorded(), vec![Effect::Map(1), Effect::Unmap(1)]);
+    assert!(subtle.executor.take_recorded().is_empty());
+}
+
+#[test]
+fn should_keep_newest_recorded_effects() {
+    let subtle = create_subtle();
+
+    for win in 0..=MAX_RECORDED as u32 {
+        effect::apply(&subtle, Effect::Map(win)).unwrap();
+    }
+
+    let recorded = subtle.executor.take_recorded();
+
+    assert_eq!(recorded.len(), MAX_RECORDED);
+    assert_eq!(recorded.first(), Some(&Effect::Map(1)));
+}
+
+#[test]
+fn should_record_client_manage() {
+    let subtle = create_subtle();
+
+    subtle.tags.borrow_mut().push(TagBuilder::default()
+        .name("float".to_string())
+        .regex(Some(Regex::new("xterm").unwrap()))
+        .flags(TagFlags::GEOMETRY)
+        .geom(Some(Rectangle { x: 10, y: 10, width: 300, height: 200 }))
+        .build().unwrap());
+
+    let mut client = Client {
+        klass: "xterm".to_string(),
+        ..create_client(1, Tagging::empty())
+    };
+
+    client.flags.remove(ClientFlags::MODE_FLOAT);
+
+    let mut mode_flags = ClientFlags::empty();
+
+    client.manage(&subtle, &mut mode_flags).unwrap();
+
+    assert!(client.flags.contains(ClientFlags::MODE_FLOAT));
+    assert_eq!((client.geom.x, client.geom.y, client.geom.width, client.geom.height), (10, 10, 300, 200));
+
+    assert_eq!(subtle.executor.take_recorded(), vec![
+        Effect::SetProperty { win: 1, prop: Prop::ClientTags, data: vec![1] },
+        Effect::SetNetWmState { win: 1, state: EWMHStateFlags::FLOAT },
+        Effect::SetAllowedActions { win: 1, actions: AllowedActions::all() },
+        Effect::SetProperty { win: 1, prop: Prop::ClientFlags, data: vec![EWMHStateFlags::FLOAT.bits()] },
+        Effect::SetProperty { win: 1, prop: Prop::FrameExtents, data: vec![0, 0, 0, 0] },
+        Effect::SetProperty { win: 1, prop: Prop::ClientGravity, data: vec![0] },
+        Effect::SetProperty { win: 1, prop: Prop::ClientScreen, data: vec![0] },
+        Effect::SetProperty { win: 1, prop: Prop::WmDesktop, data: vec![0] },
+    ]);
+}
+
+#[test]
+fn should_record_view_switch() {
+    let subtle = create_subtle();
+
+    subtle.clients.borrow_mut().push(create_client(1, Tagging::from_bits_retain(1 << 0)));
+    subtle.clients.borrow_mut().push(create_client(2, Tagging::from_bits_retain(1 << 1)));
+
+    subtle.views.borrow()[1].focus(&subtle, 0, false, true).unwrap();
+
+    assert_eq!(subtle.screens[0].view_idx.get(), 1);
+
+    assert_eq!(subtle.executor.take_recorded(), vec![
+        Effect::SetRootProperty { prop: Prop::CurrentDesktop, data: vec![1] },
+        Effect::SetRootProperty { prop: Prop::ScreenViews, data: vec![1] },
+        Effect::SetWmState { win: 1, state: WMState::Withdrawn },
+        Effect::Unmap(1),
+        Effect::SetProperty { win: 2, prop: Prop::ClientGravity, data: vec![0] },
+        Effect::SetBorderColor { win: 2, pixel: subtle.clients_style.bg as u32 },
+        Effect::SetWmState { win: 2, state: WMState::Normal },
+        Effect::Map(2),
+        Effect::SetProperty { win: 2, prop: Prop::WmDesktop, data: vec![1] },
+        Effect::SetProperty { win: 2, prop: Prop::ClientScreen, data: vec![0] },
+        Effect::SetProperty { win: 2, prop: Prop::ClientGravity, data: vec![0] },
+        Effect::SetBorderColor { win: 2, pixel: subtle.clients_style.bg as u32 },
+        Effect::SetRootProperty { prop: Prop::VisibleTags, data: vec![1 << 1] },
+        Effect::SetRootProperty { prop: Prop::VisibleViews, data: vec![1 << 2] },
+        Effect::Focus(2),
+        Effect::GrabMouse(2),
+        Effect::SetBorderColor { win: 2, pixel: subtle.clients_style.fg as u32 },
+        Effect::SetRootProperty { prop: Prop::ActiveWindow, data: vec![2, NONE, NONE, NONE, NONE] },
+        Effect::WarpPointer { x: 200, y: 150 },
+    ]);
+}
+
+#[test]
+fn should_publish_urgent_tags_only_on_change() {
+    let subtle = create_subtle();
+
+    let mut client = create_client(1, Tagging::from_bits_retain(1 << 0));
+
+    client.flags.insert(ClientFlags::MODE_URGENT);
+    subtle.clients.borrow_mut().push(client);
+
+    client::update_urgent_tags(&subtle).unwrap();
+    client::update_urgent_tags(&subtle).unwrap();
+
+    assert_eq!(subtle.executor.take_recorded(), vec![
+        Effect::SetRootProperty { prop: Prop::UrgentTags, data: vec![1 << 0] },
+    ]);
+}
+
+#[test]
+fn should_not_focus_client_closed_during_view_switch() {
+    let subtle = create_subtle();
+
+    subtle.clients.borrow_mut().push(create_client(1, Tagging::from_bits_retain(1 << 0)));
+    subtle.clients.borrow_mut().push(create_client(2, Tagging::from_bits_retain(1 << 1)));
+    subtle.clients.borrow_mut().push(create_client(3, Tagging::from_bits_retain(1 << 1)));
+
+    // Remembered focus of the view is closed before the switch is handled
+    subtle.views.borrow()[1].focus_win.set(2);
+    subtle.clients.borrow_mut()[1].flags.insert(ClientFlags::DEAD);
+
+    subtle.views.borrow()[1].focus(&subtle, 0, false, true).unwrap();
+
+    let recorded = subtle.executor.take_recorded();
+
+    assert!(!recorded.contains(&Effect::Focus(2)));
+    assert!(recorded.contains(&Effect::Focus(3)));
+
+    let focus_client = subtle.find_focus_client().unwrap();
+
+    assert_eq!(focus_client.win, 3);
+    assert!(focus_client.is_visible(&subtle));
+}
+
+#[test]
+fn should_restore_unfocused_border_when_leaving_borderless() {
+    let mut subtle = create_subtle();
+
+    subtle.clients_style.fg = 1;
+    subtle.clients_style.bg = 2;
+    subtle.clients_style.border.top = 3;
+
+    let mut client = create_client(1, Tagging::from_bits_retain(1 << 0));
+
+    client.flags.insert(ClientFlags::MODE_BORDERLESS);
+
+    let mut mode_flags = ClientFlags::MODE_BORDERLESS;
+
+    client.toggle(&subtle, &mut mode_flags, false, None).unwrap();
+
+    let recorded = subtle.executor.take_recorded();
+
+    assert!(!client.flags.contains(ClientFlags::MODE_BORDERLESS));
+    assert!(recorded.contains(&Effect::SetBorderWidth { win: 1, width: 3 }));
+    assert!(recorded.contains(&Effect::SetBorderColor { win: 1, pixel: 2 }));
+}
+
+#[test]
+fn should_not_color_border_of_dock() {
+    let subtle = create_subtle();
+
+    let mut client = create_client(1, Tagging::from_bits_retain(1 << 0));
+
+    client.flags.insert(ClientFlags::TYPE_DOCK);
+
+    let mut mode_flags = ClientFlags::MODE_FULL;
+
+    client.toggle(&subtle, &mut mode_flags, false, None).unwrap();
+
+    assert!(!subtle.executor.take_recorded().iter()
+        .any(|effect| matches!(effect, Effect::SetBorderColor { .. } | Effect::SetBorderWidth { .. })));
+}
+
+#[test]
+fn should_restore_floating_geom_after_fullscreen() {
+    let subtle = create_subtle();
+
+    let mut client = create_client(1, Tagging::from_bits_retain(1 << 0));
+
+    let mut mode_flags = ClientFlags::MODE_FULL;
+
+    client.toggle(&subtle, &mut mode_flags, false, None).unwrap();
+
+    // Arrange overwrites geometry with the screen size
+    client.geom = subtle.screens[0].base;
+
+    let mut mode_flags = ClientFlags::MODE_FULL;
+
+    client.toggle(&subtle, &mut mode_flags, false, None).unwrap();
+
+    assert!(client.flags.contains(ClientFlags::MODE_FLOAT));
+    assert_eq!((client.geom.x, client.geom.y, client.geom.width, client.geom.height), (100, 100, 200, 100));
+    assert!(client.saved_geom.is_none());
+}
+
+#[test]
+fn should_restore_floating_geom_after_tiling() {
+    let subtle = create_subtle();
+
+    let mut client = create_client(1, Tagging::from_bits_retain(1 << 0));
+
+    let mut mode_flags = ClientFlags::MODE_FLOAT;
+
+    client.toggle(&subtle, &mut mode_flags, false, None).unwrap();
+
+    // Tiling overwrites geometry
+    client.geom = Rectangle { x: 0, y: 0, width: 400, height: 600 };
+
+    let mut mode_flags = ClientFlags::MODE_FLOAT;
+
+    client.toggle(&subtle, &mut mode_flags, false, None).unwrap();
+
+    assert_eq!((client.geom.x, client.geom.y, client.geom.width, client.geom.height), (100, 100, 200, 100));
+}
+
+#[test]
+fn should_restore_geom_after_center() {
+    let subtle = create_subtle();
+
+    let mut client = create_client(1, Tagging::from_bits_retain(1 << 0));
+
+    client.flags.remove(ClientFlags::MODE_FLOAT);
+
+    let mut mode_flags = ClientFlags::MODE_CENTER;
+
+    client.toggle(&subtle, &mut mode_flags, false, None).unwrap();
+
+    assert_eq!((client.geom.x, client.geom.y), (300, 250));
+
+    let mut mode_flags = ClientFlags::MODE_CENTER;
+
+    client.toggle(&subtle, &mut mode_flags, false, None).unwrap();
+
+    assert_eq!((client.geom.x, client.geom.y, client.geom.width, client.geom.height), (100, 100, 200, 100));
+}
+
+#[test]
+fn should_center_saved_geom_on_smaller_screen() {
+    let mut subtle = create_subtle();
+
+    let mut client = create_client(1, Tagging::from_bits_retain(1 << 0));
+
+    client.geom = Rectangle { x: 500, y: 400, width: 200, height: 100 };
+
+    let mut mode_flags = ClientFlags::MODE_FULL;
+
+    client.toggle(&subtle, &mut mode_flags, false, None).unwrap();
+
+    // Screen shrinks while in fullscreen
+    let geom = Rectangle { x: 0, y: 0, width: 640, height: 480 };
+
+    subtle.screens[0].geom = geom;
+    subtle.screens[0].base = geom;
+
+    let mut mode_flags = ClientFlags::MODE_FULL;
+
+    client.toggle(&subtle, &mut mode_flags, false, None).unwrap();
+
+    assert_eq!((client.geom.x, client.geom.y, client.geom.width, client.geom.height), (220, 190, 200, 100));
+}
+
+#[test]
+fn should_translate_saved_geom_to_other_screen() {
+    let mut subtle = create_subtle();
+
+    let geom = Rectangle { x: 800, y: 0, width: 800, height: 600 };
+
+    subtle.screens.push(Screen { geom, base: geom, ..Screen::default() });
+
+    let mut client = create_client(1, Tagging::from_bits_retain(1 << 0));
+
+    client.saved_geom = Some(Rectangle { x: 100, y: 100, width: 200, height: 100 });
+    client.translate_saved_geom(&subtle, 1);
+
+    let saved_geom = client.saved_geom.unwrap();
+
+    assert_eq!((saved_geom.x, saved_geom.y, saved_geom.width, saved_geom.height), (900, 100, 200, 100));
+}
+
+#[test]
+fn should_skip_focus_of_invisible_client() {
+    let subtle = create_subtle();
+
+    subtle.clients.borrow_mut().push(create_client(1, Tagging::from_bits_retain(1 << 1)));
+
+    subtle.clients.borrow()[0].focus(&subtle, false).unwrap();
+
+    assert!(subtle.executor.take_recorded().is_empty());
+    assert!(subtle.find_focus_client().is_none());
+}
+
+#[test]
+fn should_detect_last_focus_gone() {
+    let subtle = create_subtle();
+
+    subtle.visible_tags.set(Tagging::from_bits_retain(1 << 0));
+
+    // No clients at all
+    assert!(client::is_last_focus_gone(&subtle, true, 0));
+    assert!(!client::is_last_focus_gone(&subtle, false, 0));
+
+    // Invisible, dead or other screen clients can't take over
+    subtle.clients.borrow_mut().push(create_client(1, Tagging::from_bits_retain(1 << 1)));
+    subtle.clients.borrow_mut().push(Client { screen_idx: 1, ..create_client(2, Tagging::from_bits_retain(1 << 0)) });
+    subtle.clients.borrow_mut().push(create_client(3, Tagging::from_bits_retain(1 << 0)));
+    subtle.clients.borrow_mut()[2].flags.insert(ClientFlags::DEAD);
+
+    assert!(client::is_last_focus_gone(&subtle, true, 0));
+
+    // Visible client on the same screen
+    subtle.clients.borrow_mut().push(create_client(4, Tagging::from_bits_retain(1 << 0)));
+
+    assert!(!client::is_last_focus_gone(&subtle, true, 0));
+    assert!(!client::is_last_focus_gone(&subtle, true, 1));
+}
+
+#[test]
+fn should_record_focus_fallback() {
+    let subtle = create_subtle();
+
+    subtle.focus_history.push(1);
+
+    client::focus_fallback(&subtle).unwrap();
+
+    assert_eq!(subtle.executor.take_recorded(), vec![
+        Effect::FocusRoot,
+        Effect::SetRootProperty { prop: Prop::ActiveWindow, data: vec![NONE] },
+    ]);
+    assert_eq!(subtle.find_focus_win(), NONE);
+}
@@ -0,0 +1,120 @@
+///
+/// @package subtle-rs
+///
+/// @file Error tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+#[cfg(feature = "plugins")]
+use std::collections::HashMap;
+use anyhow::anyhow;
+use proptest::prelude::*;
+use x11rb::NONE;
+use x11rb::errors::{ConnectionError, ReplyError};
+use x11rb::protocol::ErrorKind;
+use x11rb::x11_utils::X11Error;
+#[cfg(feature = "plugins")]
+use crate::config::MixedConfigVal;
+use crate::display::check_wm_owner;
+use crate::error;
+use crate::error::{Classify, InitError};
+use crate::icon::Icon;
+use crate::subtle::Subtle;
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_classify_claim_failure_as_fatal(owner in 1u32..u32::MAX) {
+        prop_assert!(check_wm_owner(owner, false).is_err_and(|err| err.is_fatal()));
+        prop_assert!(check_wm_owner(owner, true).is_ok_and(|is_replace| is_replace));
+        prop_assert!(check_wm_owner(NONE, false).is_ok_and(|is_replace| !is_replace));
+    }
+}
+
+#[test]
+fn should_classify_bad_icon_as_recoverable() {
+    let subtle = Subtle::default();
+
+    assert!(Icon::load(&subtle, "/nonexistent/icon.xbm", &subtle.views_style).is_err_and(|err| !err.is_fatal()));
+}
+
+#[cfg(feature = "plugins")]
+#[test]
+fn should_classify_bad_plugin_as_recoverable() {
+    let values: HashMap<String, MixedConfigVal> = HashMap::from([
+        (String::from("name"), MixedConfigVal::S(String::from("broken"))),
+        (String::from("url"), MixedConfigVal::S(String::from("/nonexistent/plugin.wasm"))),
+        (String::from("interval"), MixedConfigVal::I(60)),
+    ]);
+
+    assert!(crate::plugin::parse_plugin(&values, &crate::plugin::HostRegistry::default()).is_err_and(|err| !err.is_fatal()));
+}
+
+#[test]
+fn should_collect_recoverable_errors() {
+    let subtle = Subtle::default();
+
+    let result: Result<(), InitError> = Err(anyhow!("Missing icon")).recoverable();
+
+    assert!(error::collect(&subtle, result).is_ok_and(|value| value.is_none()));
+    assert_eq!(subtle.init_errors.borrow().len(), 1);
+}
+
+#[test]
+fn should_pass_on_fatal_errors() {
+    let subtle = Subtle::default();
+
+    let result: Result<(), InitError> = Err(anyhow!("No gravities found")).fatal();
+    let err = error::collect(&subtle, result).unwrap_err();
+
+    assert!(error::is_fatal(&err));
+    assert!(error::is_fatal(&anyhow!("Unclassified")));
+    assert!(subtle.init_errors.borrow().is_empty());
+}
+
+fn create_x11_error(error_kind: ErrorKind) -> anyhow::Error {
+    ReplyError::X11Error(X11Error {
+        error_kind,
+        error_code: 0,
+        sequence: 0,
+        bad_value: 42,
+        minor_opcode: 0,
+        major_opcode: 0,
+        extension_name: None,
+        request_name: None,
+    }).into()
+}
+
+#[test]
+fn should_skip_transient_x_errors() {
+    for error_kind in [ErrorKind::Window, ErrorKind::Drawable, ErrorKind::Match] {
+        let err = create_x11_error(error_kind).context("Failed to configure");
+
+        assert!(error::is_transient(&err));
+        assert!(error::skip_transient::<()>(Err(err), "configure").is_ok_and(|value| value.is_none()));
+    }
+
+    assert_eq!(error::skip_transient(Ok(1), "configure").unwrap(), Some(1));
+}
+
+#[test]
+fn should_pass_on_protocol_misuse() {
+    for err in [create_x11_error(ErrorKind::Value), create_x11_error(ErrorKind::Access), anyhow!("Unrelated")] {
+        assert!(!error::is_transient(&err));
+        assert!(!error::is_connection_lost(&err));
+        assert!(error::skip_transient::<()>(Err(err), "configure").is_err());
+    }
+}
+
+#[test]
+fn should_detect_lost_connection() {
+    let err: anyhow::Error = ReplyError::ConnectionError(ConnectionError::UnknownError).into();
+
+    assert!(error::is_connection_lost(&err));
+    assert!(error::is_connection_lost(&anyhow::Error::from(ConnectionError::UnknownError)));
+    assert!(!error::is_transient(&err));
+}
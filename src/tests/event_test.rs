@@ -0,0 +1,107 @@
+///
+/// @package subtle-rs
+///
+/// @file Event tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::AsFd;
+use std::os::unix::net::UnixStream;
+use std::time::{Duration, Instant};
+use proptest::prelude::*;
+use x11rb::protocol::Event;
+use x11rb::protocol::xproto::{EnterNotifyEvent, ExposeEvent, KeyPressEvent, PropertyNotifyEvent};
+use crate::client::{Client, ClientFlags};
+use crate::effect::Executor;
+use crate::event::{get_timestamp, handle_grab_action, wait_for_input};
+use crate::grab::{GrabAction, GrabFlags, GrabOutcome};
+use crate::subtle::Subtle;
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+
+    #[test]
+    fn should_get_timestamp_of_user_events(time in 1u32..u32::MAX) {
+        prop_assert_eq!(get_timestamp(&Event::KeyPress(KeyPressEvent { time, ..Default::default() })),
+            Some(time));
+        prop_assert_eq!(get_timestamp(&Event::EnterNotify(EnterNotifyEvent { time, ..Default::default() })),
+            Some(time));
+        prop_assert_eq!(get_timestamp(&Event::PropertyNotify(PropertyNotifyEvent { time, ..Default::default() })),
+            Some(time));
+    }
+}
+
+#[test]
+fn should_skip_events_without_timestamp() {
+    assert_eq!(get_timestamp(&Event::Expose(ExposeEvent::default())), None);
+}
+
+#[test]
+fn should_wait_for_timeout_without_input() {
+    let (conn_stream, _server_stream) = UnixStream::pair().unwrap();
+    let (wakeup_read, _wakeup_write) = UnixStream::pair().unwrap();
+
+    wakeup_read.set_nonblocking(true).unwrap();
+
+    let start = Instant::now();
+
+    wait_for_input(conn_stream.as_fd(), Some(&wakeup_read), Some(Duration::from_millis(20))).unwrap();
+
+    assert!(Duration::from_millis(20) <= start.elapsed());
+}
+
+#[test]
+fn should_wake_up_and_drain_pipe() {
+    let (conn_stream, _server_stream) = UnixStream::pair().unwrap();
+    let (mut wakeup_read, mut wakeup_write) = UnixStream::pair().unwrap();
+
+    wakeup_read.set_nonblocking(true).unwrap();
+    wakeup_write.write_all(&[1, 1]).unwrap();
+
+    let start = Instant::now();
+
+    wait_for_input(conn_stream.as_fd(), Some(&wakeup_read), Some(Duration::from_secs(10))).unwrap();
+
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert_eq!(wakeup_read.read(&mut [0u8; 1]).unwrap_err().kind(), ErrorKind::WouldBlock);
+}
+
+#[test]
+fn should_wake_up_on_connection_input() {
+    let (conn_stream, mut server_stream) = UnixStream::pair().unwrap();
+
+    server_stream.write_all(&[0]).unwrap();
+
+    let start = Instant::now();
+
+    wait_for_input(conn_stream.as_fd(), None, Some(Duration::from_secs(10))).unwrap();
+
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn should_skip_gravity_of_fullscreen_client() {
+    let subtle = Subtle {
+        executor: Executor::new(true),
+        ..Subtle::default()
+    };
+
+    subtle.clients.borrow_mut().push(Client {
+        flags: ClientFlags::MODE_FULL,
+        win: 1,
+        ..Client::default()
+    });
+    subtle.focus_history.push(1);
+
+    let outcome = handle_grab_action(&subtle, GrabFlags::WINDOW_GRAVITY,
+                                     &GrabAction::List(vec![0]), 0, 0, 1).unwrap();
+
+    assert_eq!(outcome, GrabOutcome::NoOp);
+    assert!(subtle.clients.borrow()[0].flags.contains(ClientFlags::MODE_FULL));
+    assert!(subtle.executor.take_recorded().is_empty());
+}
@@ -0,0 +1,92 @@
+///
+/// @package subtle-rs
+///
+/// @file Flash tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+use proptest::prelude::*;
+use crate::client::{Client, ClientFlags};
+use crate::flash::{calc_timeout, find_target, schedule, should_flash, take_expired, FlashTarget, FLASH_DURATION};
+use crate::grab::GrabOutcome;
+use crate::screen::{Screen, ScreenFlags};
+use crate::subtle::Subtle;
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_revert_flash_after_duration(win in 1u32..1000, elapsed_ms in 0u64..300) {
+        let pending = Cell::new(None);
+        let now = Instant::now();
+
+        prop_assert!(schedule(&pending, FlashTarget::Client(win), now));
+
+        let later = now + Duration::from_millis(elapsed_ms);
+        let expired = take_expired(&pending, later);
+
+        if Duration::from_millis(elapsed_ms) >= FLASH_DURATION {
+            prop_assert_eq!(expired.map(|flash| flash.target), Some(FlashTarget::Client(win)));
+            prop_assert!(pending.get().is_none());
+        } else {
+            prop_assert!(expired.is_none());
+            prop_assert_eq!(calc_timeout(&pending, later),
+                Some(FLASH_DURATION - Duration::from_millis(elapsed_ms)));
+        }
+    }
+}
+
+#[test]
+fn should_ignore_overlapping_flashes() {
+    let pending = Cell::new(None);
+    let now = Instant::now();
+
+    assert!(schedule(&pending, FlashTarget::Client(1), now));
+    assert!(!schedule(&pending, FlashTarget::Panel(2), now + Duration::from_millis(50)));
+
+    let flash = pending.get().unwrap();
+
+    assert_eq!(flash.target, FlashTarget::Client(1));
+    assert_eq!(flash.deadline, now + FLASH_DURATION);
+
+    // Schedule again once reverted
+    assert!(take_expired(&pending, now + FLASH_DURATION).is_some());
+    assert!(schedule(&pending, FlashTarget::Panel(2), now + FLASH_DURATION));
+}
+
+#[test]
+fn should_flash_only_noop_outcomes() {
+    let mut subtle = Subtle::default();
+
+    assert!(should_flash(&subtle, GrabOutcome::NoOp));
+    assert!(!should_flash(&subtle, GrabOutcome::Done));
+
+    subtle.flash_on_noop = false;
+
+    assert!(!should_flash(&subtle, GrabOutcome::NoOp));
+    assert_eq!(calc_timeout(&subtle.flash, Instant::now()), None);
+}
+
+#[test]
+fn should_flash_panel_instead_of_desktop_and_dock() {
+    let subtle = Subtle {
+        screens: vec![Screen { flags: ScreenFlags::TOP_PANEL, top_panel_win: 5, ..Screen::default() }],
+        ..Subtle::default()
+    };
+
+    subtle.clients.borrow_mut().push(Client { win: 1, ..Default::default() });
+    subtle.focus_history.push(1);
+
+    assert_eq!(find_target(&subtle), Some(FlashTarget::Client(1)));
+
+    for flags in [ClientFlags::TYPE_DESKTOP, ClientFlags::TYPE_DOCK] {
+        subtle.clients.borrow_mut()[0].flags = flags;
+
+        assert_eq!(find_target(&subtle), Some(FlashTarget::Panel(5)));
+    }
+}
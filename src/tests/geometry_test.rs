@@ -0,0 +1,82 @@
+///
+/// @package subtle-rs
+///
+/// @file Geometry tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use proptest::prelude::*;
+use crate::geometry::{calc_centered, calc_percent, calc_shrunk, checked_to_i16, checked_to_u16};
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_keep_values_in_range(n in i16::MIN..=i16::MAX) {
+        prop_assert_eq!(checked_to_i16(n as i32), n);
+        prop_assert_eq!(checked_to_u16(n as i32), n.max(0) as u16);
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_clamp_values_out_of_range(n in 1i32..100000) {
+        prop_assert_eq!(checked_to_i16(i16::MAX as i32 + n), i16::MAX);
+        prop_assert_eq!(checked_to_i16(i16::MIN as i32 - n), i16::MIN);
+        prop_assert_eq!(checked_to_u16(u16::MAX as i32 + n), u16::MAX);
+        prop_assert_eq!(checked_to_u16(-n), 0);
+    }
+}
+
+#[test]
+fn should_clamp_around_boundaries() {
+    assert_eq!(checked_to_i16(32767), 32767);
+    assert_eq!(checked_to_i16(32768), 32767);
+    assert_eq!(checked_to_i16(-32768), -32768);
+    assert_eq!(checked_to_i16(-32769), -32768);
+    assert_eq!(checked_to_u16(65535), 65535);
+    assert_eq!(checked_to_u16(65536), 65535);
+    assert_eq!(checked_to_u16(-1), 0);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_center_on_wide_setups(screen_idx in 0i32..3, inner in 0i32..2560) {
+        // Three side-by-side screens of 2560 pixels each
+        let origin = screen_idx * 2560;
+        let x = calc_centered(origin, 2560, inner);
+
+        prop_assert!(x >= 0);
+        prop_assert_eq!(x as i32, origin + (2560 - inner) / 2);
+    }
+}
+
+#[test]
+fn should_center_near_boundaries() {
+    assert_eq!(calc_centered(32000, 2560, 100), i16::MAX);
+    assert_eq!(calc_centered(0, 65535, 0), 32767);
+    assert_eq!(calc_centered(0, 100, 200), -50);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_calculate_percent_without_overflow(percent in 0i32..=100) {
+        // The intermediate product exceeds i16 and u16 for wide screens
+        prop_assert_eq!(calc_percent(5120, 2560, percent), 5120 + 2560 * percent / 100);
+        prop_assert_eq!(calc_percent(0, 65535, percent), 65535 * percent / 100);
+    }
+}
+
+#[test]
+fn should_shrink_without_wrapping() {
+    assert_eq!(calc_shrunk(100, 20, 1), 80);
+    assert_eq!(calc_shrunk(10, 20, 1), 1);
+    assert_eq!(calc_shrunk(10, -20, 1), 30);
+    assert_eq!(calc_shrunk(u16::MAX, -1, 1), u16::MAX);
+}
@@ -11,17 +11,23 @@
 
 use proptest::prelude::*;
 use std::collections::HashMap;
-use x11rb::protocol::xproto::{Keycode, Keysym, ModMask};
+use std::time::{Duration, Instant};
+use x11rb::protocol::xproto::{Keysym, ModMask};
 use crate::grab;
+use crate::screen::ScreenFlags;
+use crate::config::MixedConfigVal;
+use crate::grab::{calc_cycle_idx, calc_shadowed_grabs, find_modifier_keycodes, find_similar_name, lookup_keysym, parse_grabs, calc_reverse_keymap, KeyMap, parse_gravity_list, resolve_gravity_names, CountPrefix, CycleOrder, GrabAction, GrabFlags, COUNT_TIMEOUT};
+use crate::gravity::Gravity;
 
 proptest! {
     #![proptest_config(ProptestConfig::with_cases(10))]
     #[test]
     fn should_parse_key_combinations(key in "([WCS]-){1,3}[a-z]") {
-        let mut mapping: HashMap<Keysym, Keycode> = HashMap::new();
+        let mut mapping: KeyMap = HashMap::new();
 
         mapping.insert(x11_keysymdef::lookup_by_name(
-            &key.chars().last().unwrap().to_string()).unwrap().keysym, key.chars().last().unwrap() as u8);
+            &key.chars().last().unwrap().to_string()).unwrap().keysym,
+            (key.chars().last().unwrap() as u8, ModMask::default()));
 
         if let Ok((_keycode, state, _is_mouse)) = grab::parse_keys(&*key, &mapping) {
             prop_assert!(ModMask::ANY != state);
@@ -35,10 +41,11 @@ proptest! {
     #![proptest_config(ProptestConfig::with_cases(5))]
     #[test]
     fn should_parse_mouse(key in "([WCS]-){1,3}B[1-9]") {
-        let mut mapping: HashMap<Keysym, Keycode> = HashMap::new();
+        let mut mapping: KeyMap = HashMap::new();
 
         mapping.insert(x11_keysymdef::lookup_by_name(
-            &key.chars().last().unwrap().to_string()).unwrap().keysym, key.chars().last().unwrap() as u8);
+            &key.chars().last().unwrap().to_string()).unwrap().keysym,
+            (key.chars().last().unwrap() as u8, ModMask::default()));
 
         if let Ok((keycode, state, is_mouse)) = grab::parse_keys(&*key, &mapping) {
             prop_assert!(0 < keycode);
@@ -49,3 +56,314 @@ proptest! {
         }
     }
 }
+
+#[test]
+fn should_parse_modifiers() {
+    assert_eq!(grab::parse_modifier("S"), Some(ModMask::SHIFT));
+    assert_eq!(grab::parse_modifier("C"), Some(ModMask::CONTROL));
+    assert_eq!(grab::parse_modifier("A"), Some(ModMask::M1));
+    assert_eq!(grab::parse_modifier("W"), Some(ModMask::M4));
+    assert_eq!(grab::parse_modifier("B1"), None);
+    assert_eq!(grab::parse_modifier("s"), None);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_cycle_with_wrap_around(len in 1usize..10, idx in 0usize..10, count in 1u32..30) {
+        let idx = idx % len;
+
+        prop_assert_eq!(calc_cycle_idx(len, Some(idx), count, CycleOrder::Next),
+            Some((idx + count as usize) % len));
+        prop_assert_eq!(calc_cycle_idx(len, Some(idx), count, CycleOrder::Prev),
+            Some((idx + len * 30 - count as usize) % len));
+    }
+}
+
+#[test]
+fn should_cycle_from_list_ends() {
+    assert_eq!(calc_cycle_idx(3, None, 1, CycleOrder::Next), Some(0));
+    assert_eq!(calc_cycle_idx(3, None, 2, CycleOrder::Next), Some(1));
+    assert_eq!(calc_cycle_idx(3, None, 1, CycleOrder::Prev), Some(2));
+    assert_eq!(calc_cycle_idx(3, None, 4, CycleOrder::Prev), Some(2));
+    assert_eq!(calc_cycle_idx(0, None, 1, CycleOrder::Next), None);
+}
+
+#[test]
+fn should_accumulate_count_prefix() {
+    let now = Instant::now();
+    let mut count_prefix = CountPrefix::default();
+
+    assert_eq!(count_prefix.get(now), None);
+    assert_eq!(count_prefix.calc_timeout(now), None);
+
+    count_prefix.push_digit(1, now);
+    count_prefix.push_digit(2, now + Duration::from_millis(100));
+
+    assert_eq!(count_prefix.get(now + Duration::from_millis(100)), Some(12));
+    assert_eq!(count_prefix.take(now + Duration::from_millis(200)), 12);
+
+    // Count resets after use
+    assert_eq!(count_prefix.take(now + Duration::from_millis(300)), 1);
+}
+
+#[test]
+fn should_drop_stale_count_prefix() {
+    let now = Instant::now();
+    let mut count_prefix = CountPrefix::default();
+
+    count_prefix.push_digit(3, now);
+
+    assert!(count_prefix.is_stale(now + COUNT_TIMEOUT));
+    assert_eq!(count_prefix.get(now + COUNT_TIMEOUT), None);
+
+    // Start over with new digit
+    count_prefix.push_digit(4, now + COUNT_TIMEOUT);
+
+    assert_eq!(count_prefix.take(now + COUNT_TIMEOUT), 4);
+}
+
+#[test]
+fn should_clamp_count_prefix() {
+    let now = Instant::now();
+    let mut count_prefix = CountPrefix::default();
+
+    for _ in 0..5 {
+        count_prefix.push_digit(9, now);
+    }
+
+    assert_eq!(count_prefix.get(now), Some(999));
+}
+
+#[test]
+fn should_parse_gravity_list() {
+    assert_eq!(parse_gravity_list("window_gravity top, top66,,top33"),
+               Some(vec!["top".to_string(), "top66".to_string(), "top33".to_string()]));
+    assert_eq!(parse_gravity_list("window_gravity"), None);
+    assert_eq!(parse_gravity_list("window_kill"), None);
+}
+
+#[test]
+fn should_resolve_gravity_names_in_order() {
+    let gravities = vec![Gravity::new("top", 0, 0, 100, 50),
+        Gravity::new("top66", 0, 0, 100, 66), Gravity::new("top33", 0, 0, 100, 33)];
+
+    let grav_names = vec!["top33".to_string(), "unknown".to_string(), "top".to_string()];
+
+    assert_eq!(resolve_gravity_names(&grav_names, &gravities), vec![2, 0]);
+}
+
+/// Synthetic mapping with four keysyms per keycode (plain, shift, AltGr, AltGr+shift)
+fn create_keyboard_mapping() -> Vec<Keysym> {
+    let sym = |name: &str| x11_keysymdef::lookup_by_name(name).unwrap().keysym;
+
+    vec![
+        // Keycode 10
+        sym("q"), sym("Q"), sym("at"), 0,
+        // Keycode 11
+        sym("2"), sym("quotedbl"), sym("twosuperior"), 0,
+        // Keycode 12
+        sym("apostrophe"), sym("at"), 0, 0,
+    ]
+}
+
+#[test]
+fn should_map_shift_levels() {
+    let keymap = calc_reverse_keymap(10, 4, &create_keyboard_mapping());
+    let sym = |name: &str| x11_keysymdef::lookup_by_name(name).unwrap().keysym;
+
+    assert_eq!(keymap.get(&sym("q")), Some(&(10, ModMask::default())));
+    assert_eq!(keymap.get(&sym("quotedbl")), Some(&(11, ModMask::SHIFT)));
+    assert_eq!(keymap.get(&sym("twosuperior")), Some(&(11, ModMask::M5)));
+
+    // Prefer the lowest level of symbols on multiple keycodes
+    assert_eq!(keymap.get(&sym("at")), Some(&(12, ModMask::SHIFT)));
+    assert!(!keymap.contains_key(&0));
+}
+
+#[test]
+fn should_add_level_modifiers_to_grab() {
+    let keymap = calc_reverse_keymap(10, 4, &create_keyboard_mapping());
+
+    let (keycode, modifiers, _) = grab::parse_keys("W-quotedbl", &keymap).unwrap();
+
+    assert_eq!(keycode, 11);
+    assert_eq!(modifiers, ModMask::M4 | ModMask::SHIFT);
+    assert!(grab::parse_keys("W-eacute", &keymap).unwrap_err().to_string().contains("eacute"));
+}
+
+#[test]
+fn should_find_keycodes_of_modifiers() {
+    // Two keycodes per modifier: Shift, Lock, Control, Mod1, Mod2, Mod3, Mod4, Mod5
+    let keycodes = [50, 62, 66, 0, 37, 105, 64, 108, 77, 0, 0, 0, 133, 134, 92, 0];
+
+    assert_eq!(find_modifier_keycodes(&keycodes, 2, ModMask::M1), vec![64, 108]);
+    assert_eq!(find_modifier_keycodes(&keycodes, 2, ModMask::M4 | ModMask::SHIFT), vec![50, 62, 133, 134]);
+    assert!(find_modifier_keycodes(&keycodes, 2, ModMask::default()).is_empty());
+    assert!(find_modifier_keycodes(&keycodes, 0, ModMask::M1).is_empty());
+}
+
+#[test]
+fn should_parse_window_cycle_names() {
+    let (flags, action) = grab::parse_name("window_cycle").unwrap();
+
+    assert_eq!(flags, GrabFlags::WINDOW_SWITCH);
+    assert!(matches!(action, GrabAction::Index(order) if CycleOrder::Next as u32 == order));
+
+    let (flags, action) = grab::parse_name("window_cycle_reverse").unwrap();
+
+    assert_eq!(flags, GrabFlags::WINDOW_SWITCH);
+    assert!(matches!(action, GrabAction::Index(order) if CycleOrder::Prev as u32 == order));
+}
+
+#[test]
+fn should_parse_relative_view_jumps() {
+    let (flags, action) = grab::parse_name("view_jump+2").unwrap();
+
+    assert_eq!(flags, GrabFlags::VIEW_CYCLE);
+    assert!(matches!(action, GrabAction::List(ref list) if *list == vec![CycleOrder::Next as usize, 2]));
+
+    let (flags, action) = grab::parse_name("view_jump-1").unwrap();
+
+    assert_eq!(flags, GrabFlags::VIEW_CYCLE);
+    assert!(matches!(action, GrabAction::List(ref list) if *list == vec![CycleOrder::Prev as usize, 1]));
+
+    let (flags, action) = grab::parse_name("view_jump3").unwrap();
+
+    assert_eq!(flags, GrabFlags::VIEW_JUMP);
+    assert!(matches!(action, GrabAction::Index(3)));
+
+    assert!(grab::parse_name("view_jump+0").is_err());
+    assert!(grab::parse_name("view_jump-x").is_err());
+}
+
+#[test]
+fn should_consume_count_only_in_counted_grabs() {
+    for flag in [GrabFlags::WINDOW_GRAVITY, GrabFlags::WINDOW_CYCLE, GrabFlags::VIEW_CYCLE,
+        GrabFlags::GAP_ADJUST, GrabFlags::WINDOW_SWITCH]
+    {
+        assert!(flag.intersects(GrabFlags::COUNTED));
+    }
+
+    for flag in [GrabFlags::COUNT_PREFIX, GrabFlags::VIEW_JUMP, GrabFlags::WINDOW_KILL,
+        GrabFlags::WINDOW_MODE, GrabFlags::COMMAND]
+    {
+        assert!(!flag.intersects(GrabFlags::COUNTED));
+    }
+}
+
+#[test]
+fn should_parse_window_pin_names() {
+    let (flags, action) = grab::parse_name("window_pin").unwrap();
+
+    assert_eq!(flags, GrabFlags::WINDOW_PIN);
+    assert!(matches!(action, GrabAction::Index(1)));
+
+    let (flags, action) = grab::parse_name("window_unpin").unwrap();
+
+    assert_eq!(flags, GrabFlags::WINDOW_PIN);
+    assert!(matches!(action, GrabAction::Index(0)));
+}
+
+#[test]
+fn should_parse_panel_toggle_names() {
+    let (flags, action) = grab::parse_name("panel_toggle_top").unwrap();
+
+    assert_eq!(flags, GrabFlags::PANEL_TOGGLE);
+    assert!(matches!(action, GrabAction::Index(panels) if ScreenFlags::TOP_PANEL.bits() == panels));
+
+    let (_, action) = grab::parse_name("panel_toggle").unwrap();
+
+    assert!(matches!(action, GrabAction::Index(panels)
+        if (ScreenFlags::TOP_PANEL | ScreenFlags::BOTTOM_PANEL).bits() == panels));
+}
+
+#[test]
+fn should_collect_grab_errors() {
+    let keymap = calc_reverse_keymap(10, 4, &create_keyboard_mapping());
+    let mut grabs = HashMap::new();
+
+    grabs.insert("window_kill".to_string(), MixedConfigVal::S("W-q".to_string()));
+    grabs.insert("windwo_kill".to_string(), MixedConfigVal::S("W-2".to_string()));
+    grabs.insert("window_float".to_string(), MixedConfigVal::S("W-eacute".to_string()));
+    grabs.insert("window_gravity top".to_string(), MixedConfigVal::S("W-at".to_string()));
+
+    let (parsed, errors) = parse_grabs(&grabs, &[], &keymap);
+
+    assert_eq!(parsed.iter().map(|(name, keys, _)| (name.as_str(), keys.as_str())).collect::<Vec<_>>(),
+        vec![("window_kill", "W-q")]);
+
+    // Errors are in name order and name the offending grab
+    let messages = errors.iter().map(|err| err.to_string()).collect::<Vec<_>>();
+
+    assert_eq!(messages, vec![
+        "Invalid grab `window_float` (W-eacute)",
+        "Invalid grab `window_gravity top` (W-at)",
+        "Invalid grab `windwo_kill` (W-2)",
+    ]);
+}
+
+#[test]
+fn should_find_shadowed_grabs() {
+    let keymap = calc_reverse_keymap(10, 4, &create_keyboard_mapping());
+    let mut grabs = HashMap::new();
+
+    grabs.insert("window_kill".to_string(), MixedConfigVal::S("W-q".to_string()));
+    grabs.insert("window_float".to_string(), MixedConfigVal::S("W-q".to_string()));
+    grabs.insert("window_dump".to_string(), MixedConfigVal::S("W-2".to_string()));
+    grabs.insert("window_stack".to_string(), MixedConfigVal::S("W-B2".to_string()));
+    grabs.insert("window_select".to_string(), MixedConfigVal::S("W-q".to_string()));
+
+    let (parsed, errors) = parse_grabs(&grabs, &[], &keymap);
+
+    assert!(errors.is_empty());
+
+    let names = parsed.iter().map(|(name, _, _)| name.as_str()).collect::<Vec<_>>();
+
+    assert_eq!(names, vec!["window_dump", "window_float", "window_kill", "window_select", "window_stack"]);
+    assert_eq!(calc_shadowed_grabs(&parsed.iter().map(|(_, _, grab)| grab).collect::<Vec<_>>()),
+        vec![(2, 1), (3, 1)]);
+}
+
+#[test]
+fn should_find_similar_grab_names() {
+    assert_eq!(find_similar_name("windwo_kill"), Some("window_kill"));
+    assert_eq!(find_similar_name("view_jmp"), Some("view_jump"));
+    assert_eq!(find_similar_name("window_kill"), None);
+    assert_eq!(find_similar_name("xterm"), None);
+    assert_eq!(find_similar_name("window_kil -9"), None);
+}
+
+#[test]
+fn should_look_up_key_aliases() {
+    assert_eq!(lookup_keysym("volume_up"), Some(0x1008_FF13));
+    assert_eq!(lookup_keysym("XF86AudioRaiseVolume"), Some(0x1008_FF13));
+    assert_eq!(lookup_keysym("brightness_down"), Some(0x1008_FF03));
+    assert_eq!(lookup_keysym("kp_7"), Some(0xFFB7));
+    assert_eq!(lookup_keysym("kp_plus"), lookup_keysym("KP_Add"));
+    assert_eq!(lookup_keysym("kp_enter"), Some(0xFF8D));
+    assert_eq!(lookup_keysym("q"), Some(0x71));
+    assert_eq!(lookup_keysym("volume_upp"), None);
+}
+
+#[test]
+fn should_bind_multimedia_keys() {
+    let mut keymap: KeyMap = HashMap::new();
+
+    keymap.insert(0x1008_FF13, (123, ModMask::default()));
+
+    assert_eq!(grab::parse_keys("volume_up", &keymap).unwrap().0, 123);
+
+    // Unresolved keys name the binding instead of vanishing
+    let mut grabs = HashMap::new();
+
+    grabs.insert("amixer set Master 5%+".to_string(), MixedConfigVal::S("W-volume_up".to_string()));
+    grabs.insert("amixer set Master 5%-".to_string(), MixedConfigVal::S("W-volume_down".to_string()));
+
+    let (parsed, errors) = parse_grabs(&grabs, &[], &keymap);
+
+    assert_eq!(parsed.len(), 1);
+    assert_eq!(errors.iter().map(|err| format!("{:#}", err)).collect::<Vec<_>>(), vec![
+        "Invalid grab `amixer set Master 5%-` (W-volume_down): Keysym not found in keyboard mapping: volume_down",
+    ]);
+}
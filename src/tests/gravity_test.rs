@@ -11,7 +11,9 @@
 
 use proptest::prelude::*;
 use x11rb::protocol::xproto::Rectangle;
-use crate::gravity::Gravity;
+use std::collections::HashMap;
+use crate::config::MixedConfigVal;
+use crate::gravity::{calc_gravity_list, calc_overflow_idx, calc_tiling_flags, parse_gravity, resolve_overflow, Gravity, GravityFlags};
 
 proptest! {
     #![proptest_config(ProptestConfig::with_cases(5))]
@@ -52,4 +54,160 @@ proptest! {
         prop_assert_eq!(geom.width, width * 50 / 100);
         prop_assert_eq!(geom.height, height * 50 / 100);
     }
-}
\ No newline at end of file
+}
+fn create_limited(max_clients: usize, overflow_idx: Option<usize>) -> Gravity {
+    let mut gravity = Gravity::new("test", 0, 0, 100, 100);
+
+    gravity.max_clients = max_clients;
+    gravity.overflow_idx = overflow_idx;
+
+    gravity
+}
+
+#[test]
+fn should_keep_gravity_without_limit() {
+    let gravities = vec![create_limited(0, Some(1)), create_limited(0, None)];
+
+    assert_eq!(calc_overflow_idx(&gravities, &[10, 0], 0), 0);
+}
+
+#[test]
+fn should_keep_gravity_below_limit() {
+    let gravities = vec![create_limited(3, Some(1)), create_limited(0, None)];
+
+    assert_eq!(calc_overflow_idx(&gravities, &[2, 0], 0), 0);
+}
+
+#[test]
+fn should_overflow_full_gravity() {
+    let gravities = vec![create_limited(3, Some(1)), create_limited(0, None)];
+
+    assert_eq!(calc_overflow_idx(&gravities, &[3, 0], 0), 1);
+}
+
+#[test]
+fn should_follow_overflow_chain() {
+    let gravities = vec![create_limited(1, Some(1)), create_limited(1, Some(2)),
+        create_limited(1, None)];
+
+    assert_eq!(calc_overflow_idx(&gravities, &[1, 1, 0], 0), 2);
+}
+
+#[test]
+fn should_fall_back_to_overflow_on_cycle() {
+    let gravities = vec![create_limited(1, Some(1)), create_limited(1, Some(2)),
+        create_limited(1, Some(0))];
+
+    assert_eq!(calc_overflow_idx(&gravities, &[1, 1, 1], 0), 1);
+}
+
+#[test]
+fn should_fall_back_to_overflow_at_chain_end() {
+    let gravities = vec![create_limited(1, Some(1)), create_limited(1, None)];
+
+    assert_eq!(calc_overflow_idx(&gravities, &[1, 1], 0), 1);
+}
+
+#[test]
+fn should_keep_full_gravity_without_overflow() {
+    let gravities = vec![create_limited(1, None)];
+
+    assert_eq!(calc_overflow_idx(&gravities, &[5], 0), 0);
+}
+
+#[test]
+fn should_ignore_invalid_overflow() {
+    let gravities = vec![create_limited(1, Some(7))];
+
+    assert_eq!(calc_overflow_idx(&gravities, &[1], 0), 0);
+}
+
+#[test]
+fn should_assign_overflow_stable() {
+    let gravities = vec![create_limited(2, Some(1)), create_limited(0, None)];
+
+    let assign = || {
+        let mut counts = vec![0usize; 2];
+
+        (0..4).map(|_| {
+            let idx = calc_overflow_idx(&gravities, &counts, 0);
+
+            counts[idx] += 1;
+
+            idx
+        }).collect::<Vec<usize>>()
+    };
+
+    assert_eq!(assign(), vec![0, 0, 1, 1]);
+    assert_eq!(assign(), assign());
+}
+
+fn create_values(horz: Option<bool>, vert: Option<bool>) -> HashMap<String, MixedConfigVal> {
+    let mut values = HashMap::from([
+        ("name".to_string(), MixedConfigVal::S("left".to_string())),
+        ("x".to_string(), MixedConfigVal::I(0)),
+        ("y".to_string(), MixedConfigVal::I(0)),
+        ("width".to_string(), MixedConfigVal::I(50)),
+        ("height".to_string(), MixedConfigVal::I(100)),
+    ]);
+
+    if let Some(horz) = horz {
+        values.insert("horz".to_string(), MixedConfigVal::B(horz));
+    }
+
+    if let Some(vert) = vert {
+        values.insert("vert".to_string(), MixedConfigVal::B(vert));
+    }
+
+    values
+}
+
+#[test]
+fn should_parse_tiling_flags() {
+    assert!(parse_gravity(&create_values(None, None)).unwrap().flags.is_empty());
+    assert!(parse_gravity(&create_values(Some(false), Some(false))).unwrap().flags.is_empty());
+    assert_eq!(parse_gravity(&create_values(Some(true), None)).unwrap().flags.bits(), GravityFlags::HORZ.bits());
+    assert_eq!(parse_gravity(&create_values(None, Some(true))).unwrap().flags.bits(), GravityFlags::VERT.bits());
+}
+
+#[test]
+fn should_fall_back_to_horz_when_both_are_set() {
+    assert_eq!(parse_gravity(&create_values(Some(true), Some(true))).unwrap().flags.bits(),
+        GravityFlags::HORZ.bits());
+    assert_eq!(calc_tiling_flags("test", true, true).bits(), GravityFlags::HORZ.bits());
+}
+
+#[test]
+fn should_skip_incomplete_gravity() {
+    let mut values = create_values(None, None);
+
+    values.remove("width");
+
+    assert!(parse_gravity(&values).is_none());
+}
+
+#[test]
+fn should_resolve_overflow_by_name() {
+    let mut gravities = vec![
+        Gravity::new("top", 0, 0, 100, 50),
+        Gravity::new("bottom", 0, 50, 100, 50),
+    ];
+
+    // Names of parsed gravities only, invalid entries are skipped
+    resolve_overflow(&mut gravities, &[Some("bottom".to_string()), Some("unknown".to_string())]);
+
+    assert_eq!(gravities[0].overflow_idx, Some(1));
+    assert_eq!(gravities[1].overflow_idx, None);
+}
+
+#[test]
+fn should_publish_tiling_suffix() {
+    let gravities = vec![
+        parse_gravity(&create_values(None, None)).unwrap(),
+        parse_gravity(&create_values(Some(true), None)).unwrap(),
+        parse_gravity(&create_values(None, Some(true))).unwrap(),
+    ];
+
+    assert_eq!(calc_gravity_list(&gravities), vec!["0x0+50+100#left", "0x0+50+100#left#horz",
+        "0x0+50+100#left#vert"]);
+}
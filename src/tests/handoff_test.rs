@@ -0,0 +1,90 @@
+///
+/// @package subtle-rs
+///
+/// @file Handoff tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use proptest::prelude::*;
+use crate::client::ClientFlags;
+use crate::handoff::{build_args, HandoffState, HANDOFF_ARG, SUBTLE_STATE_VERSION};
+use crate::tagging::Tagging;
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+
+    #[test]
+    fn should_roundtrip_state(tags in 0u32..u32::MAX, gravity_idx in 0isize..32, screen_idx in 0isize..4) {
+        let state = HandoffState {
+            tags: Tagging::from_bits_retain(tags),
+            gravity_idx,
+            screen_idx,
+            mode_flags: ClientFlags::MODE_FLOAT | ClientFlags::MODE_STICK,
+        };
+
+        let decoded = HandoffState::decode(&state.encode()).unwrap();
+
+        prop_assert_eq!(decoded.tags.bits(), tags);
+        prop_assert_eq!(decoded.gravity_idx, gravity_idx);
+        prop_assert_eq!(decoded.screen_idx, screen_idx);
+        prop_assert_eq!(decoded.mode_flags, state.mode_flags);
+    }
+}
+
+#[test]
+fn should_only_encode_mode_flags() {
+    let state = HandoffState {
+        tags: Tagging::empty(),
+        gravity_idx: 0,
+        screen_idx: 0,
+        mode_flags: ClientFlags::MODE_FULL | ClientFlags::ARRANGE | ClientFlags::TYPE_DIALOG,
+    };
+
+    let decoded = HandoffState::decode(&state.encode()).unwrap();
+
+    assert_eq!(decoded.mode_flags, ClientFlags::MODE_FULL);
+}
+
+#[test]
+fn should_reject_other_versions() {
+    let mut data = HandoffState {
+        tags: Tagging::from_bits_retain(1),
+        gravity_idx: 1,
+        screen_idx: 0,
+        mode_flags: ClientFlags::empty(),
+    }.encode();
+
+    data[0] = SUBTLE_STATE_VERSION + 1;
+
+    assert!(HandoffState::decode(&data).is_none());
+    assert!(HandoffState::decode(&[]).is_none());
+    assert!(HandoffState::decode(&[SUBTLE_STATE_VERSION, 1, 1]).is_none());
+}
+
+#[test]
+fn should_validate_indices() {
+    let state = HandoffState {
+        tags: Tagging::from_bits_retain(0b101),
+        gravity_idx: 2,
+        screen_idx: 1,
+        mode_flags: ClientFlags::empty(),
+    };
+
+    assert!(state.is_valid(3, 3, 2));
+    assert!(!state.is_valid(2, 3, 2));
+    assert!(!state.is_valid(3, 2, 2));
+    assert!(!state.is_valid(3, 3, 1));
+    assert!(!HandoffState { gravity_idx: -1, ..state }.is_valid(3, 3, 2));
+}
+
+#[test]
+fn should_add_handoff_arg_once() {
+    let args = build_args(vec!["subtle-rs".to_string(), "-d".to_string(), ":1".to_string()]);
+
+    assert_eq!(args, vec!["subtle-rs", "-d", ":1", HANDOFF_ARG]);
+    assert_eq!(build_args(args.clone()), args);
+}
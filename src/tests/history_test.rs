@@ -0,0 +1,139 @@
+///
+/// @package subtle-rs
+///
+/// @file History tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use proptest::prelude::*;
+use x11rb::NONE;
+use crate::history::{calc_mru_order, FocusHistory};
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_keep_size_and_unique_entries(wins in prop::collection::vec(1u32..8, 0..20)) {
+        let history = FocusHistory::new(5);
+
+        for win in wins.iter() {
+            history.push(*win);
+        }
+
+        let entries = history.to_vec();
+        let mut live: Vec<u32> = entries.iter().copied().filter(|win| NONE != *win).collect();
+
+        prop_assert_eq!(entries.len(), 5);
+        prop_assert_eq!(history.first(), wins.last().copied());
+
+        live.sort();
+        live.dedup();
+
+        prop_assert_eq!(live.len(), entries.iter().filter(|win| NONE != **win).count());
+    }
+}
+
+#[test]
+fn should_move_existing_window_to_front() {
+    let history = FocusHistory::new(5);
+
+    history.push(1);
+    history.push(2);
+    history.push(3);
+    history.push(1);
+
+    assert_eq!(history.to_vec(), vec![1, 3, 2, NONE, NONE]);
+}
+
+#[test]
+fn should_drop_oldest_window() {
+    let history = FocusHistory::new(3);
+
+    for win in 1..=4 {
+        history.push(win);
+    }
+
+    assert_eq!(history.to_vec(), vec![4, 3, 2]);
+}
+
+#[test]
+fn should_remove_window() {
+    let history = FocusHistory::new(4);
+
+    history.push(1);
+    history.push(2);
+    history.push(3);
+    history.remove(3);
+
+    assert_eq!(history.first(), Some(2));
+    assert_eq!(history.to_vec(), vec![2, 1, NONE, NONE]);
+
+    history.remove(2);
+    history.remove(1);
+
+    assert_eq!(history.first(), None);
+}
+
+#[test]
+fn should_ignore_none() {
+    let history = FocusHistory::new(2);
+
+    history.push(NONE);
+
+    assert_eq!(history.first(), None);
+}
+
+#[test]
+fn should_order_by_most_recent_use() {
+    let history = [3, 1, NONE, NONE];
+    let stacking = [1, 2, 3, 4];
+
+    // History first, then the rest from top to bottom
+    assert_eq!(calc_mru_order(&history, &stacking), vec![3, 1, 4, 2]);
+}
+
+#[test]
+fn should_skip_history_entries_of_other_screens() {
+    let history = [7, 2, 5, 1];
+    let stacking = [1, 2];
+
+    assert_eq!(calc_mru_order(&history, &stacking), vec![2, 1]);
+    assert!(calc_mru_order(&history, &[]).is_empty());
+}
+
+#[test]
+fn should_replace_history_and_keep_size() {
+    let history = FocusHistory::new(3);
+
+    history.replace(vec![4, NONE, 2, 9, 7]);
+
+    assert_eq!(history.to_vec(), vec![4, 2, 9]);
+
+    history.replace(vec![1]);
+
+    assert_eq!(history.to_vec(), vec![1, NONE, NONE]);
+}
+
+#[test]
+fn should_unset_first_and_close_gap_on_push() {
+    let history = FocusHistory::new(4);
+
+    history.push(1);
+    history.push(2);
+    history.unset_first();
+
+    assert_eq!(history.first(), None);
+    assert_eq!(history.to_vec(), vec![NONE, 2, 1, NONE]);
+
+    // Unset twice keeps the entries
+    history.unset_first();
+
+    assert_eq!(history.to_vec(), vec![NONE, 2, 1, NONE]);
+
+    history.push(3);
+
+    assert_eq!(history.to_vec(), vec![3, 2, 1, NONE]);
+}
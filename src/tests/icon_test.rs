@@ -0,0 +1,120 @@
+///
+/// @package subtle-rs
+///
+/// @file Icon tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use proptest::prelude::*;
+use crate::icon::{calc_blended, calc_max_height, calc_scaled, is_png, pack_pixels, parse_png, parse_xbm, Icon, BITMAP_DEPTH};
+use crate::subtle::Subtle;
+
+const XBM: &str = "#define test_width 9
+#define test_height 2
+static unsigned char test_bits[] = {
+  0x01, 0x01, 0x10, 0x00 };";
+
+fn encode_png(color_type: png::ColorType, width: u32, height: u32, data: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::new();
+
+    let mut encoder = png::Encoder::new(&mut buf, width, height);
+
+    encoder.set_color(color_type);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().unwrap();
+
+    writer.write_image_data(data).unwrap();
+    writer.finish().unwrap();
+
+    buf
+}
+
+#[test]
+fn should_parse_xbm() {
+    let (pixels, width, height) = parse_xbm(XBM).unwrap();
+
+    assert_eq!((width, height), (9, 2));
+    assert_eq!(pixels[0], u32::MAX);
+    assert_eq!(pixels[8], u32::MAX);
+    assert_eq!(pixels[9 + 4], u32::MAX);
+    assert_eq!(pixels.iter().filter(|pixel| 0 != **pixel).count(), 3);
+}
+
+#[test]
+fn should_reject_truncated_xbm() {
+    assert!(parse_xbm("#define test_width 16\n#define test_height 16\n{ 0x01 };").is_err());
+}
+
+#[test]
+fn should_detect_png_by_magic() {
+    let data = encode_png(png::ColorType::Rgb, 1, 1, &[1, 2, 3]);
+
+    assert!(is_png(&data));
+    assert!(!is_png(XBM.as_bytes()));
+}
+
+#[test]
+fn should_parse_png_as_argb() {
+    let rgba = encode_png(png::ColorType::Rgba, 2, 1, &[0x11, 0x22, 0x33, 0x80, 0xFF, 0x00, 0x00, 0x00]);
+
+    assert_eq!(parse_png(&rgba).unwrap(), (vec![0x80112233, 0x00FF0000], 2, 1));
+
+    let gray = encode_png(png::ColorType::Grayscale, 1, 1, &[0x42]);
+
+    assert_eq!(parse_png(&gray).unwrap(), (vec![0xFF424242], 1, 1));
+}
+
+#[test]
+fn should_composite_against_background() {
+    assert_eq!(calc_blended(0xFF123456, 0xABCDEF), 0x123456);
+    assert_eq!(calc_blended(0x00123456, 0xABCDEF), 0xABCDEF);
+    assert_eq!(calc_blended(0x80FF0000, 0x000000), 0x800000);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_scale_into_max_height(width in 1u16..64, height in 1u16..64, max_height in 1u16..32) {
+        let pixels = vec![0u32; width as usize * height as usize];
+
+        let (scaled, new_width, new_height) = calc_scaled(pixels, width, height, max_height);
+
+        prop_assert!(new_height <= max_height);
+        prop_assert!(new_height <= height);
+        prop_assert!(0 < new_width && new_width <= width);
+        prop_assert_eq!(scaled.len(), new_width as usize * new_height as usize);
+    }
+}
+
+#[test]
+fn should_pack_pixels_with_padding() {
+    let img_data = pack_pixels(&[0x112233], 1, 1, 24);
+
+    assert_eq!(img_data, vec![0x33, 0x22, 0x11, 0x00]);
+    assert_eq!(pack_pixels(&[0x112233; 2], 2, 1, 32).len(), 8);
+}
+
+#[test]
+fn should_rescale_icon_loaded_before_panel_height() {
+    let mut subtle = Subtle::default();
+
+    let (pixels, width, height) = parse_xbm(XBM).unwrap();
+
+    // Panel height is unknown while the config is read
+    let icon = Icon::from_pixels(pixels, width, height, BITMAP_DEPTH,
+                                 calc_max_height(subtle.panel_height, &subtle.views_style));
+
+    assert_eq!((icon.width, icon.height), (4, 1));
+
+    subtle.panel_height = 20;
+
+    let max_height = calc_max_height(subtle.panel_height, &subtle.views_style);
+
+    assert_eq!(icon.calc_rescaled(max_height), Some((width, height)));
+    assert_eq!(icon.calc_rescaled(1), None);
+}
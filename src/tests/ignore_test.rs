@@ -0,0 +1,55 @@
+///
+/// @package subtle-rs
+///
+/// @file Ignore tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use proptest::prelude::*;
+use regex::{Regex, RegexBuilder};
+use crate::ignore;
+use crate::subtle::Subtle;
+
+fn create_rules(values: &[&str]) -> Vec<Regex> {
+    values.iter()
+        .map(|value| RegexBuilder::new(value).case_insensitive(true).build().unwrap())
+        .collect()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_match_any_window_name(other in "[a-z]{1,8}") {
+        let rules = create_rules(&["screenkey", "^conky$"]);
+
+        prop_assert!(ignore::is_match(&rules, "screenkey", &other, &other));
+        prop_assert!(ignore::is_match(&rules, &other, "Screenkey", &other));
+        prop_assert!(ignore::is_match(&rules, &other, &other, "Conky"));
+        prop_assert!(!ignore::is_match(&rules, &other, &other, "Conkyrc"));
+        prop_assert!(!ignore::is_match(&[], "screenkey", "screenkey", "screenkey"));
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_remember_ignored_windows(win in 1u32..u32::MAX) {
+        let subtle = Subtle::default();
+
+        prop_assert!(!subtle.is_ignored_win(win));
+
+        subtle.add_ignored_win(win);
+        subtle.add_ignored_win(win);
+
+        prop_assert!(subtle.is_ignored_win(win));
+        prop_assert_eq!(subtle.ignored_wins.borrow().len(), 1);
+
+        subtle.remove_ignored_win(win);
+
+        prop_assert!(!subtle.is_ignored_win(win));
+    }
+}
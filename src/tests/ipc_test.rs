@@ -0,0 +1,56 @@
+///
+/// @package subtle-rs
+///
+/// @file IPC tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use proptest::prelude::*;
+use x11rb::protocol::xproto::Rectangle;
+use crate::client::{calc_client_geometries, Client};
+use crate::ipc::{split_names, ClientGeometry, CLIENT_GEOMETRY_LEN};
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_roundtrip_client_geometry(x in i16::MIN..i16::MAX, y in i16::MIN..i16::MAX,
+                                       width in 1u16..u16::MAX, height in 1u16..u16::MAX,
+                                       gravity_idx in -1isize..32, screen_idx in -1isize..4)
+    {
+        let geom = ClientGeometry { x, y, width, height, gravity_idx, screen_idx };
+
+        prop_assert_eq!(ClientGeometry::decode_all(&geom.encode()), vec![geom]);
+    }
+}
+
+#[test]
+fn should_publish_geometries_in_client_list_order() {
+    let clients = vec![
+        Client { win: 1, geom: Rectangle { x: -5, y: 10, width: 100, height: 200 }, gravity_idx: 2, ..Default::default() },
+        Client { win: 2, geom: Rectangle { x: 50, y: 0, width: 30, height: 40 }, screen_idx: 1, ..Default::default() },
+    ];
+
+    let data = calc_client_geometries(&clients);
+
+    assert_eq!(data.len(), 2 * CLIENT_GEOMETRY_LEN);
+    assert_eq!(ClientGeometry::decode_all(&data), vec![
+        ClientGeometry { x: -5, y: 10, width: 100, height: 200, gravity_idx: 2, screen_idx: 0 },
+        ClientGeometry { x: 50, y: 0, width: 30, height: 40, gravity_idx: 0, screen_idx: 1 },
+    ]);
+}
+
+#[test]
+fn should_ignore_incomplete_geometries() {
+    assert!(ClientGeometry::decode_all(&[1, 2, 3]).is_empty());
+}
+
+#[test]
+fn should_split_names() {
+    assert_eq!(split_names(b"www\0dev\0"), vec!["www", "dev"]);
+    assert_eq!(split_names(b"www\0\0dev"), vec!["www", "", "dev"]);
+    assert!(split_names(b"").is_empty());
+}
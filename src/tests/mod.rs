@@ -15,4 +15,30 @@ mod tag_test;
 mod view_test;
 mod tagging;
 mod style_test;
-mod spacing_test;
\ No newline at end of file
+mod spacing_test;
+mod subtle_test;
+mod client_test;
+mod screen_test;
+mod ignore_test;
+mod panel_test;
+mod geometry_test;
+mod cursor_test;
+mod flash_test;
+mod history_test;
+mod session_test;
+#[cfg(feature = "tray")]
+mod tray_test;
+mod effect_test;
+mod config_test;
+mod corner_test;
+mod report_test;
+mod handoff_test;
+mod event_test;
+mod error_test;
+mod icon_test;
+#[cfg(feature = "plugins")]
+mod plugin_test;
+mod ipc_test;
+mod dump_test;
+mod validate_test;
+mod display_test;
\ No newline at end of file
@@ -0,0 +1,302 @@
+///
+/// @package subtle-rs
+///
+/// @file Panel tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use proptest::prelude::*;
+use x11rb::protocol::xproto::Rectangle;
+use crate::client::{Client, ClientFlags};
+use crate::effect::Executor;
+use crate::panel::{calc_fitting_len, calc_start_offsets, calc_urgent_counts, format_view_name, parse_plugin_item, sanitize_text, serialize_layout, truncate_text, Panel, PanelAction, PanelFlags, PLACEHOLDER_TEXT};
+use crate::screen::Screen;
+use crate::style::StyleFlags;
+use crate::subtle::{Subtle, SubtleFlags};
+use crate::tagging::Tagging;
+use crate::view::ViewBuilder;
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_create_icon_panel(path in "/[a-z]{1,10}/[a-z]{1,10}\\.xbm") {
+        let panel = Panel::new(&format!("!{}", path)).unwrap();
+
+        prop_assert!(panel.flags.intersects(PanelFlags::ICON));
+        prop_assert_eq!(panel.text, Some(path));
+        prop_assert!(panel.icon.is_none());
+    }
+}
+
+#[test]
+fn should_create_positioned_icon_panel() {
+    let panel = Panel::new(">!/tmp/icon.xbm").unwrap();
+
+    assert!(panel.flags.contains(PanelFlags::ICON | PanelFlags::RIGHT_POS));
+    assert_eq!(panel.text.as_deref(), Some("/tmp/icon.xbm"));
+}
+
+#[test]
+fn should_create_separator_panel() {
+    let panel = Panel::new("|").unwrap();
+
+    assert!(panel.flags.intersects(PanelFlags::SEPARATOR));
+    assert_eq!(panel.text.as_deref(), Some("|"));
+}
+
+#[test]
+fn should_create_clickable_title_panel() {
+    let panel = Panel::new("=title").unwrap();
+
+    assert!(panel.flags.contains(PanelFlags::TITLE | PanelFlags::MOUSE_DOWN | PanelFlags::CENTER_POS));
+    assert_eq!(panel.text_widths.len(), 2);
+}
+
+#[test]
+fn should_parse_click_override_of_plugin_item() {
+    assert_eq!(parse_plugin_item("$cpu"), ("$cpu", None));
+    assert_eq!(parse_plugin_item("$cpu:click=htop"), ("$cpu", Some("htop")));
+    assert_eq!(parse_plugin_item("$cpu:click= "), ("$cpu", None));
+}
+
+#[test]
+fn should_create_clickable_plugin_panel_with_override() {
+    let plain = Panel::new(">$cpu").unwrap();
+    let clickable = Panel::new(">$cpu:click=htop").unwrap();
+
+    assert!(!plain.flags.intersects(PanelFlags::MOUSE_DOWN));
+    assert!(plain.click_command.is_none());
+    assert!(clickable.flags.contains(PanelFlags::PLUGIN | PanelFlags::MOUSE_DOWN | PanelFlags::RIGHT_POS));
+    assert_eq!(clickable.click_command.as_deref(), Some("htop"));
+}
+
+fn create_views_panel(subtle: &mut Subtle) -> Panel {
+    for name in ["www", "dev"] {
+        subtle.views.borrow_mut().push(ViewBuilder::default().name(name.to_string()).build().unwrap());
+    }
+
+    let mut panel = Panel::new("views").unwrap();
+
+    panel.text_widths = vec![10, 20];
+    panel.width = 30;
+
+    panel
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_hover_view_below_pointer(x in 0i16..=30) {
+        let mut subtle = Subtle::default();
+        let panel = create_views_panel(&mut subtle);
+
+        panel.handle_action(&subtle, &PanelAction::MouseOver(x, 0), false).unwrap();
+
+        prop_assert_eq!(panel.hovered_view_idx.get(), Some(if 10 >= x { 0 } else { 1 }));
+    }
+}
+
+#[test]
+fn should_clear_hover_outside_and_on_mouse_out() {
+    let mut subtle = Subtle::default();
+    let panel = create_views_panel(&mut subtle);
+
+    panel.handle_action(&subtle, &PanelAction::MouseOver(15, 0), false).unwrap();
+    assert_eq!(panel.hovered_view_idx.get(), Some(1));
+
+    panel.handle_action(&subtle, &PanelAction::MouseOver(100, 0), false).unwrap();
+    assert_eq!(panel.hovered_view_idx.get(), None);
+
+    panel.handle_action(&subtle, &PanelAction::MouseOver(5, 0), false).unwrap();
+    panel.handle_action(&subtle, &PanelAction::MouseOut, false).unwrap();
+    assert_eq!(panel.hovered_view_idx.get(), None);
+}
+
+#[test]
+fn should_hover_only_panel_that_received_event() {
+    let mut subtle = Subtle::default();
+    let mut screen = Screen::default();
+
+    screen.panels.push(create_views_panel(&mut subtle));
+
+    let mut bottom_panel = Panel::new("views").unwrap();
+
+    bottom_panel.text_widths = vec![10, 20];
+    bottom_panel.width = 30;
+    bottom_panel.flags.insert(PanelFlags::BOTTOM_START_MARKER);
+
+    screen.panels.push(bottom_panel);
+
+    screen.handle_action(&subtle, &PanelAction::MouseOver(15, 0), true).unwrap();
+
+    assert_eq!(screen.find_hovered_views(), vec![None, Some(1)]);
+
+    screen.handle_action(&subtle, &PanelAction::MouseOver(5, 0), false).unwrap();
+
+    assert_eq!(screen.find_hovered_views(), vec![Some(0), Some(1)]);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_skip_view_separator_on_hit_test(x in 15i16..=34) {
+        let mut subtle = Subtle::default();
+        let mut panel = create_views_panel(&mut subtle);
+
+        subtle.views_style.sep_string = Some("|".to_string());
+        subtle.views_style.sep_width = 4;
+        subtle.views_style.flags.insert(StyleFlags::SEPARATOR);
+
+        panel.width += subtle.views_style.sep_width;
+
+        panel.handle_action(&subtle, &PanelAction::MouseOver(x, 0), false).unwrap();
+
+        prop_assert_eq!(panel.hovered_view_idx.get(), Some(1));
+    }
+}
+
+#[test]
+fn should_ignore_clicks_on_view_separator() {
+    let mut subtle = Subtle::default();
+    let panel = create_views_panel(&mut subtle);
+
+    subtle.views_style.sep_string = Some("|".to_string());
+    subtle.views_style.sep_width = 4;
+    subtle.views_style.flags.insert(StyleFlags::SEPARATOR);
+
+    panel.handle_action(&subtle, &PanelAction::MouseOver(12, 0), false).unwrap();
+    assert_eq!(panel.hovered_view_idx.get(), None);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_serialize_panel_layout(x in 0i16..100, width in 1u16..100) {
+        let layout = vec![(String::from("views"), 0, 50), (String::from("clock"), x, width)];
+
+        prop_assert_eq!(serialize_layout(&layout, 200),
+            format!("0+50#views\0{}+{}#clock", x, width));
+    }
+}
+
+#[test]
+fn should_clip_overflowing_panel_layout() {
+    let layout = vec![
+        (String::from("title"), -10, 30),
+        (String::from("tray"), 180, 40),
+        (String::from("clock"), 200, 20),
+    ];
+
+    assert_eq!(serialize_layout(&layout, 200), "0+20#title\u{0}180+20#tray");
+    assert_eq!(serialize_layout(&[], 200), "");
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_sanitize_arbitrary_bytes(data in prop::collection::vec(any::<u8>(), 0..512), max_len in 4usize..64) {
+        let text = sanitize_text(&data, max_len);
+
+        prop_assert!(text.chars().count() <= max_len);
+        prop_assert!(!text.chars().any(|ch| ch.is_control()));
+        prop_assert!(!text.contains("  "));
+        prop_assert_eq!(text.trim(), text.as_str());
+    }
+}
+
+#[test]
+fn should_strip_control_characters_and_collapse_whitespace() {
+    assert_eq!(sanitize_text(b"  cpu:\t12%\n\n\x1b[1mmem\x07:  3G \r\n", 128), "cpu: 12% [1mmem: 3G");
+}
+
+#[test]
+fn should_replace_invalid_utf8() {
+    assert_eq!(sanitize_text(b"bat\xff\xfe 80%", 128), "bat?? 80%");
+}
+
+#[test]
+fn should_show_placeholder_for_unprintable_text() {
+    assert_eq!(sanitize_text(b"\n\t\x00\x1b", 128), PLACEHOLDER_TEXT);
+    assert_eq!(sanitize_text(b"", 128), "");
+}
+
+#[test]
+fn should_cap_long_text_with_ellipsis() {
+    let text = "x".repeat(10_000);
+
+    assert_eq!(sanitize_text(text.as_bytes(), 8), "xxxxx...");
+    assert_eq!(truncate_text("abcdef", 2), "ab");
+    assert_eq!(truncate_text("abc", 3), "abc");
+}
+
+#[test]
+fn should_calc_fitting_len() {
+    assert_eq!(calc_fitting_len(10, 100, 1000), None);
+    assert_eq!(calc_fitting_len(100, 1000, 1000), Some(50));
+    assert_eq!(calc_fitting_len(0, 0, 0), None);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_place_right_items_at_panel_end(panel_width in 100u16..2000, right_width in 0u16..100) {
+        let (_, _, right_x) = calc_start_offsets(panel_width, 0, 0, right_width);
+
+        prop_assert_eq!(right_x as i32 + right_width as i32, panel_width as i32);
+    }
+}
+
+#[test]
+fn should_calculate_start_offsets_on_reduced_panel() {
+    assert_eq!(calc_start_offsets(1200, 300, 200, 100), (300, 500, 1100));
+    assert_eq!(calc_start_offsets(1920, 300, 200, 100), (300, 860, 1820));
+}
+
+#[test]
+fn should_count_urgent_clients_per_view() {
+    let view_tags = [Tagging::from_bits_retain(0b001), Tagging::from_bits_retain(0b110),
+        Tagging::from_bits_retain(0b1000)];
+    let urgent_client_tags = [Tagging::from_bits_retain(0b010), Tagging::from_bits_retain(0b100),
+        Tagging::from_bits_retain(0b011)];
+
+    assert_eq!(calc_urgent_counts(&view_tags, &urgent_client_tags), vec![1, 3, 0]);
+}
+
+#[test]
+fn should_format_view_name_with_urgent_marker() {
+    assert_eq!(format_view_name("web", 0, "*%d"), "web");
+    assert_eq!(format_view_name("web", 2, "*%d"), "web*2");
+    assert_eq!(format_view_name("web", 2, "*"), "web*");
+}
+
+#[test]
+fn should_toggle_stick_of_focus_client_without_pointer_warp() {
+    let geom = Rectangle { x: 0, y: 0, width: 800, height: 600 };
+    let subtle = Subtle {
+        executor: Executor::new(true),
+        flags: SubtleFlags::SKIP_POINTER_WARP,
+        screens: vec![Screen { geom, base: geom, ..Screen::default() },
+            Screen { geom, base: geom, ..Screen::default() }],
+        ..Subtle::default()
+    };
+
+    subtle.visible_tags.set(Tagging::TAG1);
+    subtle.clients.borrow_mut().push(Client { win: 1, tags: Tagging::TAG1, screen_idx: 1, ..Default::default() });
+    subtle.focus_history.push(1);
+
+    let mut panel = Panel::new("=title").unwrap();
+
+    panel.width = 100;
+
+    // Right click toggles stick and must not borrow the clients twice
+    panel.handle_action(&subtle, &PanelAction::MouseDown(5, 0, 3), false).unwrap();
+
+    let client = subtle.find_client(1).unwrap();
+
+    assert!(client.flags.contains(ClientFlags::MODE_STICK));
+    assert_eq!(client.screen_idx, 1);
+}
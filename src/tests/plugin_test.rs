@@ -0,0 +1,126 @@
+///
+/// @package subtle-rs
+///
+/// @file Plugin tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use std::collections::HashMap;
+use anyhow::Result;
+use crate::config::MixedConfigVal;
+use crate::plugin::{find_plugin_values, parse_plugin, HostProvider, HostRegistry, PluginCache};
+
+/// Plugin that prints the time from the host and exports a click method
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "extism:host/env" "alloc" (func $alloc (param i64) (result i64)))
+  (import "extism:host/env" "length" (func $length (param i64) (result i64)))
+  (import "extism:host/env" "store_u8" (func $store_u8 (param i64 i32)))
+  (import "extism:host/env" "output_set" (func $output_set (param i64 i64)))
+  (import "extism:host/user" "get_formatted_time" (func $get_formatted_time (param i64) (result i64)))
+  (import "extism:host/user" "get_memory" (func $get_memory (param i64) (result i64)))
+
+  (func (export "run") (result i32)
+    (local $format i64)
+    (local $res i64)
+
+    (local.set $format (call $alloc (i64.const 2)))
+    (call $store_u8 (local.get $format) (i32.const 37))
+    (call $store_u8 (i64.add (local.get $format) (i64.const 1)) (i32.const 89))
+
+    (local.set $res (call $get_formatted_time (local.get $format)))
+    (call $output_set (local.get $res) (call $length (local.get $res)))
+    (i32.const 0))
+
+  (func (export "click") (result i32)
+    (local $res i64)
+
+    (local.set $res (call $get_memory (i64.const 0)))
+    (call $output_set (local.get $res) (call $length (local.get $res)))
+    (i32.const 0)))
+"#;
+
+/// Provider with fixed values
+struct MockProvider;
+
+impl HostProvider for MockProvider {
+    fn get_formatted_time(&mut self, format: &str) -> Result<String> {
+        Ok(format!("time:{}", format))
+    }
+
+    fn get_memory(&mut self) -> Result<String> {
+        Ok(String::from("100 50 25"))
+    }
+
+    fn get_battery(&mut self, battery_slot: &str) -> Result<String> {
+        Ok(format!("battery:{}", battery_slot))
+    }
+
+    fn get_cpu(&mut self) -> Result<bool> {
+        Ok(true)
+    }
+}
+
+fn create_fixture(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("subtle-plugin-test-{}-{}.wat", name, std::process::id()));
+
+    std::fs::write(&path, FIXTURE_WAT).unwrap();
+
+    path.to_string_lossy().to_string()
+}
+
+#[test]
+fn should_call_host_functions_of_provider() {
+    let url = create_fixture("host");
+    let values = find_plugin_values(&[], &url);
+
+    let plugin = parse_plugin(&values, &HostRegistry::new(MockProvider)).unwrap();
+
+    assert_eq!(plugin.update().unwrap(), b"time:%Y");
+    assert_eq!(plugin.call_optional("click").unwrap(), Some(b"100 50 25".to_vec()));
+    assert_eq!(plugin.call_optional("over").unwrap(), None);
+
+    std::fs::remove_file(url).unwrap();
+}
+
+#[test]
+fn should_find_plugin_by_name_or_url() {
+    let plugins = vec![HashMap::from([
+        (String::from("name"), MixedConfigVal::S(String::from("clock"))),
+        (String::from("url"), MixedConfigVal::S(String::from("/usr/lib/clock.wasm"))),
+        (String::from("interval"), MixedConfigVal::I(60)),
+    ])];
+
+    let values = find_plugin_values(&plugins, "clock");
+
+    assert!(matches!(values.get("url"), Some(MixedConfigVal::S(url)) if "/usr/lib/clock.wasm" == url));
+    assert!(matches!(values.get("interval"), Some(MixedConfigVal::I(60))));
+
+    let values = find_plugin_values(&plugins, "/tmp/other.wasm");
+
+    assert!(matches!(values.get("url"), Some(MixedConfigVal::S(url)) if "/tmp/other.wasm" == url));
+    assert!(matches!(values.get("interval"), Some(MixedConfigVal::I(0))));
+}
+
+#[test]
+fn should_run_plugin_once_per_generation() {
+    let mut cache = PluginCache::default();
+    let mut runs = 0;
+
+    for _ in 0..2 {
+        let text = cache.get_or_update(0, || { runs += 1; String::from("cpu") });
+
+        assert_eq!(text, "cpu");
+    }
+
+    assert_eq!(runs, 1);
+
+    cache.next_generation();
+
+    assert_eq!(cache.get_or_update(0, || String::from("cpu2")), "cpu2");
+    assert_eq!(cache.get_or_update(1, || String::from("mem")), "mem");
+}
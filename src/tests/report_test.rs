@@ -0,0 +1,73 @@
+///
+/// @package subtle-rs
+///
+/// @file Error report tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use std::path::PathBuf;
+use proptest::prelude::*;
+use x11rb::protocol::xproto::Rectangle;
+use crate::report::{calc_report_geom, get_path, wrap_lines};
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_wrap_within_limits(text in "[a-z \n]{0,500}", max_chars in 1usize..50, max_lines in 1usize..20) {
+        let lines = wrap_lines(&text, max_chars, max_lines);
+
+        prop_assert!(lines.len() <= max_lines.max(1));
+
+        for line in lines.iter() {
+            prop_assert!(line.chars().count() <= max_chars.max(3));
+        }
+    }
+}
+
+#[test]
+fn should_wrap_words_and_paragraphs() {
+    assert_eq!(wrap_lines("Failed to configure\n\nCaused by: No gravities found", 12, 10),
+               vec!["Failed to", "configure", "", "Caused by:", "No gravities", "found"]);
+}
+
+#[test]
+fn should_break_long_words() {
+    assert_eq!(wrap_lines("a abcdefghij", 4, 10), vec!["a", "abcd", "efgh", "ij"]);
+}
+
+#[test]
+fn should_truncate_too_many_lines() {
+    assert_eq!(wrap_lines("a\nb\nc\nd", 10, 3), vec!["a", "b", "..."]);
+}
+
+#[test]
+fn should_center_report_window() {
+    let screen_geom = Rectangle { x: 1920, y: 0, width: 1920, height: 1080 };
+    let lines = vec!["0123456789".to_string(), "01234".to_string()];
+
+    let geom = calc_report_geom(screen_geom, &lines, 6, 13);
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (2840, 517, 80, 46));
+}
+
+#[test]
+fn should_clamp_report_window_to_screen() {
+    let screen_geom = Rectangle { x: 0, y: 0, width: 100, height: 50 };
+    let lines = vec!["x".repeat(100); 10];
+
+    let geom = calc_report_geom(screen_geom, &lines, 6, 13);
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (0, 0, 100, 50));
+}
+
+#[test]
+fn should_get_error_path() {
+    assert_eq!(get_path(Some("/run/user/1000")),
+               Some(PathBuf::from("/run/user/1000/subtle-rs/last-error.txt")));
+    assert_eq!(get_path(Some("")), None);
+    assert_eq!(get_path(None), None);
+}
@@ -0,0 +1,394 @@
+///
+/// @package subtle-rs
+///
+/// @file Screen tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use proptest::prelude::*;
+use x11rb::protocol::xproto::Rectangle;
+use crate::client::ClientFlags;
+use crate::panel::{Panel, PanelFlags};
+use crate::screen;
+use crate::screen::{OwnWin, Screen, ScreenFlags};
+use crate::subtle::Subtle;
+
+fn create_screens() -> Vec<Rectangle> {
+    vec![
+        Rectangle { x: 0, y: 0, width: 1000, height: 800 },
+        Rectangle { x: 1000, y: 0, width: 1000, height: 800 },
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_find_containing_screen(x in 0i16..500, y in 0i16..400, width in 1u16..500, height in 1u16..400) {
+        let screens = create_screens();
+
+        prop_assert_eq!(screen::find_by_overlap(&Rectangle { x, y, width, height }, &screens), Some(0));
+        prop_assert_eq!(screen::find_by_overlap(&Rectangle { x: x + 1000, y, width, height }, &screens), Some(1));
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_find_screen_with_majority(overhang in 1u16..200) {
+        let screens = create_screens();
+
+        // Straddle both screens with the larger part on the second one
+        let geom = Rectangle { x: 1000 - overhang as i16, y: 100, width: 2 * overhang + 1, height: 100 };
+
+        prop_assert_eq!(screen::find_by_overlap(&geom, &screens), Some(1));
+
+        // And mirrored on the first one
+        let geom = Rectangle { x: 999 - overhang as i16, y: 100, width: 2 * overhang + 1, height: 100 };
+
+        prop_assert_eq!(screen::find_by_overlap(&geom, &screens), Some(0));
+    }
+}
+
+#[test]
+fn should_ignore_geometry_outside_of_screens() {
+    let screens = create_screens();
+
+    assert_eq!(screen::find_by_overlap(&Rectangle { x: 2000, y: 0, width: 100, height: 100 }, &screens), None);
+    assert_eq!(screen::find_by_overlap(&Rectangle { x: -200, y: -200, width: 100, height: 100 }, &screens), None);
+}
+
+fn create_l_shaped_screens() -> Vec<Rectangle> {
+    // Tall screen on the left, shorter one top-aligned on the right
+    vec![
+        Rectangle { x: 0, y: 0, width: 1000, height: 1000 },
+        Rectangle { x: 1000, y: 0, width: 1000, height: 600 },
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_cover_area_across_adjacent_screens(x in 500i16..1400, y in 0i16..500) {
+        let screens = create_l_shaped_screens();
+
+        let geom = Rectangle { x, y, width: 100, height: 100 };
+
+        prop_assert!(screen::is_covered(&geom, &screens));
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_not_cover_area_in_dead_region(x in 901i16..1900, y in 501i16..900) {
+        let screens = create_l_shaped_screens();
+
+        let geom = Rectangle { x, y, width: 100, height: 100 };
+
+        prop_assert!(!screen::is_covered(&geom, &screens));
+    }
+}
+
+#[test]
+fn should_not_cover_area_outside_of_screens() {
+    let screens = create_l_shaped_screens();
+
+    assert!(!screen::is_covered(&Rectangle { x: -10, y: 0, width: 100, height: 100 }, &screens));
+    assert!(!screen::is_covered(&Rectangle { x: 1950, y: 0, width: 100, height: 100 }, &screens));
+    assert!(!screen::is_covered(&Rectangle { x: 0, y: 950, width: 100, height: 100 }, &screens));
+    assert!(!screen::is_covered(&Rectangle { x: 0, y: 0, width: 100, height: 100 }, &[]));
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_keep_covered_geometry(x in 0i16..1900, y in 0i16..500, resistance in 0u16..100) {
+        let screens = create_l_shaped_screens();
+        let geom = Rectangle { x, y, width: 100, height: 100 };
+
+        let resisted = screen::calc_edge_resistance(&geom, &screens, resistance);
+
+        prop_assert_eq!((resisted.x, resisted.y), (x, y));
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_resist_moving_into_dead_region(overhang in 1i16..50) {
+        let screens = create_l_shaped_screens();
+
+        // Push down from the short screen into the dead region
+        let geom = Rectangle { x: 1500, y: 500 + overhang, width: 100, height: 100 };
+        let resisted = screen::calc_edge_resistance(&geom, &screens, 50);
+
+        prop_assert_eq!((resisted.x, resisted.y), (1500, 500));
+
+        // Push right from the tall screen into the dead region
+        let geom = Rectangle { x: 900 + overhang, y: 800, width: 100, height: 100 };
+        let resisted = screen::calc_edge_resistance(&geom, &screens, 50);
+
+        prop_assert_eq!((resisted.x, resisted.y), (900, 800));
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_release_after_resistance(overhang in 51i16..99) {
+        let screens = create_l_shaped_screens();
+        let geom = Rectangle { x: 1500, y: 500 + overhang, width: 100, height: 100 };
+
+        let resisted = screen::calc_edge_resistance(&geom, &screens, 50);
+
+        prop_assert_eq!((resisted.x, resisted.y), (geom.x, geom.y));
+    }
+}
+
+#[test]
+fn should_pick_nearest_position_in_corner() {
+    let screens = create_l_shaped_screens();
+
+    // Corner of the L: moving left is shorter than moving up
+    let geom = Rectangle { x: 920, y: 560, width: 100, height: 100 };
+    let resisted = screen::calc_edge_resistance(&geom, &screens, 100);
+
+    assert_eq!((resisted.x, resisted.y), (900, 560));
+
+    // And moving up is shorter than moving left
+    let geom = Rectangle { x: 960, y: 510, width: 100, height: 100 };
+    let resisted = screen::calc_edge_resistance(&geom, &screens, 100);
+
+    assert_eq!((resisted.x, resisted.y), (960, 500));
+}
+
+#[test]
+fn should_disable_resistance_with_zero_distance() {
+    let screens = create_l_shaped_screens();
+    let geom = Rectangle { x: 1500, y: 510, width: 100, height: 100 };
+
+    let resisted = screen::calc_edge_resistance(&geom, &screens, 0);
+
+    assert_eq!((resisted.x, resisted.y), (1500, 510));
+}
+
+#[test]
+fn should_calculate_virtual_geometry_on_wide_screen() {
+    let geom = Rectangle { x: 5120, y: 0, width: 2560, height: 1440 };
+
+    let left = screen::calc_virtual_geom(&geom, &[0, 0, 50, 100]);
+    let right = screen::calc_virtual_geom(&geom, &[50, 0, 50, 100]);
+
+    assert_eq!((left.x, left.y, left.width, left.height), (5120, 0, 1280, 1440));
+    assert_eq!((right.x, right.y, right.width, right.height), (6400, 0, 1280, 1440));
+}
+
+#[test]
+fn should_prefer_virtual_screen_over_parent() {
+    let geoms = [
+        Rectangle { x: 0, y: 0, width: 2560, height: 1440 },
+        Rectangle { x: 2560, y: 0, width: 1920, height: 1080 },
+        Rectangle { x: 1280, y: 0, width: 1280, height: 1440 },
+    ];
+
+    assert_eq!(screen::calc_screen_at(&geoms, 100, 100), Some(0));
+    assert_eq!(screen::calc_screen_at(&geoms, 2000, 100), Some(2));
+    assert_eq!(screen::calc_screen_at(&geoms, 3000, 100), Some(1));
+    assert_eq!(screen::calc_screen_at(&geoms, 3000, 1200), None);
+}
+
+#[test]
+fn should_map_config_entries_to_virtual_screens() {
+    // One physical screen, the first two entries each add a virtual screen
+    assert_eq!(screen::calc_config_screen_idxs(&[Some(1), Some(2), None, None]),
+               vec![Some(1), Some(2), None, Some(3)]);
+    assert_eq!(screen::calc_config_screen_idxs(&[None, None]), vec![Some(0), Some(1)]);
+}
+
+#[test]
+fn should_only_allow_pinned_screen() {
+    assert!(screen::is_screen_allowed(ClientFlags::empty(), 0, 1));
+    assert!(screen::is_screen_allowed(ClientFlags::MODE_STICK_SCREEN, 1, 1));
+    assert!(!screen::is_screen_allowed(ClientFlags::MODE_STICK_SCREEN, 1, 0));
+    assert!(screen::is_screen_allowed(ClientFlags::MODE_STICK_SCREEN | ClientFlags::MODE_STICK, 1, 0));
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_clamp_panel_geometry_to_screen(offset_x in -100i32..3000, width in -100i32..3000) {
+        let (calc_x, calc_width) = screen::calc_panel_geom(1920, Some((offset_x, width)));
+
+        prop_assert!(calc_x < 1920);
+        prop_assert!(0 < calc_width);
+        prop_assert!(calc_x as i32 + calc_width as i32 <= 1920);
+    }
+}
+
+#[test]
+fn should_calculate_panel_geometry() {
+    assert_eq!(screen::calc_panel_geom(1920, None), (0, 1920));
+    assert_eq!(screen::calc_panel_geom(1920, Some((0, 1200))), (0, 1200));
+    assert_eq!(screen::calc_panel_geom(1920, Some((720, 0))), (720, 1200));
+    assert_eq!(screen::calc_panel_geom(1920, Some((1800, 500))), (1800, 120));
+    assert_eq!(screen::calc_panel_geom(0, Some((10, 10))), (0, 0));
+}
+
+#[test]
+fn should_find_free_view() {
+    assert_eq!(screen::find_free_view(&[], 3), 0);
+    assert_eq!(screen::find_free_view(&[0, 2], 3), 1);
+    assert_eq!(screen::find_free_view(&[0, 1, 2], 3), -1);
+    assert_eq!(screen::find_free_view(&[-1], 0), -1);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_keep_migrated_geom_visible(x in 1000i16..3000, y in -500i16..1500, width in 1u16..3000, height in 1u16..3000) {
+        let screens = create_screens();
+
+        let geom = screen::calc_migrated_geom(Rectangle { x, y, width, height }, screens[1], screens[0]);
+
+        prop_assert!(0 <= geom.x && 0 <= geom.y);
+        prop_assert!(geom.x as i32 + geom.width as i32 <= 1000);
+        prop_assert!(geom.y as i32 + geom.height as i32 <= 800);
+    }
+}
+
+#[test]
+fn should_translate_migrated_geom() {
+    let screens = create_screens();
+
+    let geom = screen::calc_migrated_geom(Rectangle { x: 1100, y: 50, width: 200, height: 100 },
+                                          screens[1], screens[0]);
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (100, 50, 200, 100));
+}
+
+#[test]
+fn should_find_destroyed_own_windows() {
+    let panel_wins = [(10, 11), (20, 21)];
+
+    assert_eq!(screen::find_own_win(11, 1, &panel_wins, 30), Some(OwnWin::BottomPanel(0)));
+    assert_eq!(screen::find_own_win(20, 1, &panel_wins, 30), Some(OwnWin::TopPanel(1)));
+    assert_eq!(screen::find_own_win(42, 1, &panel_wins, 30), None);
+}
+
+#[test]
+fn should_recreate_tray_only_once() {
+    let panel_wins = [(10, 11)];
+
+    // Reported on itself and on root while it isn't embedded
+    assert_eq!(screen::find_own_win(30, 30, &panel_wins, 30), Some(OwnWin::Tray));
+    assert_eq!(screen::find_own_win(30, 1, &panel_wins, 30), None);
+}
+
+#[test]
+fn should_span_desktop_over_screens_off_origin() {
+    let screens = [
+        Rectangle { x: 1920, y: 0, width: 1280, height: 1024 },
+        Rectangle { x: 0, y: 200, width: 1920, height: 1080 },
+    ];
+
+    assert_eq!(screen::calc_desktop_size(3200, 1280, &screens), (3200, 1280));
+    assert_eq!(screen::calc_desktop_size(800, 600, &[]), (800, 600));
+}
+
+#[test]
+fn should_shrink_desktop_with_screens() {
+    let mut subtle = Subtle::default();
+
+    for base in [Rectangle { x: 0, y: 0, width: 1920, height: 1080 },
+                 Rectangle { x: 1920, y: 0, width: 1920, height: 1080 }]
+    {
+        subtle.screens.push(Screen { base, geom: base, ..Default::default() });
+    }
+
+    screen::update_desktop_size(&mut subtle, 3840, 1080);
+
+    assert_eq!((subtle.width, subtle.height), (3840, 1080));
+
+    // Unplug second screen
+    subtle.screens.pop();
+
+    screen::update_desktop_size(&mut subtle, 1920, 1080);
+
+    assert_eq!((subtle.width, subtle.height), (1920, 1080));
+}
+
+#[test]
+fn should_hide_and_show_configured_panels() {
+    let flags = ScreenFlags::TOP_PANEL | ScreenFlags::BOTTOM_PANEL;
+
+    let hidden = screen::calc_toggled_panels(flags, ScreenFlags::TOP_PANEL);
+
+    assert_eq!(hidden.bits(), (ScreenFlags::HIDDEN_TOP_PANEL | ScreenFlags::BOTTOM_PANEL).bits());
+
+    let shown = screen::calc_toggled_panels(hidden, ScreenFlags::TOP_PANEL);
+
+    assert_eq!(shown.bits(), flags.bits());
+}
+
+#[test]
+fn should_keep_screens_without_configured_panel() {
+    let flags = screen::calc_toggled_panels(ScreenFlags::TOP_PANEL,
+                                            ScreenFlags::TOP_PANEL | ScreenFlags::BOTTOM_PANEL);
+
+    assert_eq!(flags.bits(), ScreenFlags::HIDDEN_TOP_PANEL.bits());
+    assert_eq!(screen::calc_toggled_panels(ScreenFlags::VIRTUAL, ScreenFlags::BOTTOM_PANEL).bits(),
+               ScreenFlags::VIRTUAL.bits());
+}
+
+#[test]
+fn should_host_tray_on_first_tray_item() {
+    let tray_panels = [(0, 0), (0, 4), (1, 0)];
+
+    assert_eq!(screen::calc_tray_host(&tray_panels, None), Some((0, 0)));
+}
+
+#[test]
+fn should_host_tray_on_designated_screen() {
+    let tray_panels = [(0, 0), (1, 2), (1, 5)];
+
+    assert_eq!(screen::calc_tray_host(&tray_panels, Some(1)), Some((1, 2)));
+}
+
+#[test]
+fn should_fall_back_when_designated_screen_has_no_tray() {
+    let tray_panels = [(0, 3), (1, 0)];
+
+    assert_eq!(screen::calc_tray_host(&tray_panels, Some(2)), Some((0, 3)));
+}
+
+#[test]
+fn should_host_no_tray_without_tray_items() {
+    assert_eq!(screen::calc_tray_host(&[], Some(0)), None);
+}
+
+#[test]
+fn should_move_tray_host_to_surviving_screen() {
+    let mut subtle = Subtle::default();
+
+    for _ in 0..2 {
+        let mut screen = Screen::default();
+
+        screen.panels.push(Panel::new("tray").unwrap());
+        subtle.screens.push(screen);
+    }
+
+    screen::select_tray_host(&subtle, Some(1));
+
+    assert!(subtle.screens[1].panels.iter().any(|panel| panel.flags.contains(PanelFlags::TRAY_HOST)));
+
+    // Unplug screen with the tray
+    subtle.screens.pop();
+
+    screen::select_tray_host(&subtle, None);
+
+    assert!(subtle.screens[0].panels.iter().any(|panel| panel.flags.contains(PanelFlags::TRAY_HOST)));
+}
@@ -0,0 +1,188 @@
+///
+/// @package subtle-rs
+///
+/// @file Session tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use std::path::PathBuf;
+use proptest::prelude::*;
+use x11rb::protocol::xproto::Rectangle;
+use crate::client::Client;
+use crate::session::{get_path, record_gravities, select_geom, select_gravities, SessionKey, SessionStore};
+use crate::subtle::Subtle;
+
+fn create_key(klass: &str) -> SessionKey {
+    SessionKey {
+        klass: klass.to_string(),
+        instance: klass.to_lowercase(),
+        role: String::new(),
+    }
+}
+
+fn create_geom(x: i16) -> Rectangle {
+    Rectangle { x, y: 20, width: 300, height: 200 }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_roundtrip_store(klass in "[a-zA-Z\t ]{1,10}", role in "[a-z-]{0,10}", x in any::<i16>(),
+                              y in any::<i16>(), width in any::<u16>(), height in any::<u16>())
+    {
+        let mut store = SessionStore::default();
+
+        let key = SessionKey {
+            klass: klass.replace('\t', " "),
+            instance: String::from("instance"),
+            role,
+        };
+
+        store.remember_geom(key.clone(), Rectangle { x, y, width, height }, 42);
+
+        let parsed = SessionStore::parse(&store.serialize());
+        let geom = parsed.lookup_geom(&key).unwrap();
+
+        prop_assert_eq!((geom.x, geom.y, geom.width, geom.height), (x, y, width, height));
+    }
+}
+
+#[test]
+fn should_key_by_class_instance_and_role() {
+    let mut store = SessionStore::default();
+
+    store.remember_geom(create_key("Gcolor"), create_geom(10), 0);
+
+    let mut other_role = create_key("Gcolor");
+
+    other_role.role = String::from("picker");
+
+    assert_eq!(store.lookup_geom(&create_key("Gcolor")).map(|geom| geom.x), Some(10));
+    assert!(store.lookup_geom(&other_role).is_none());
+    assert!(store.lookup_geom(&create_key("Xcalc")).is_none());
+}
+
+#[test]
+fn should_select_geom_by_precedence() {
+    let hints = create_geom(1);
+
+    assert_eq!(select_geom(Some(create_geom(3)), Some(create_geom(2)), hints).x, 3);
+    assert_eq!(select_geom(None, Some(create_geom(2)), hints).x, 2);
+    assert_eq!(select_geom(None, None, hints).x, 1);
+}
+
+#[test]
+fn should_evict_stale_entries() {
+    let mut store = SessionStore::default();
+
+    store.remember_geom(create_key("Old"), create_geom(0), 100);
+    store.remember_geom(create_key("New"), create_geom(0), 900);
+
+    store.evict(1000, 500, 10);
+
+    assert!(store.lookup_geom(&create_key("Old")).is_none());
+    assert!(store.lookup_geom(&create_key("New")).is_some());
+}
+
+#[test]
+fn should_evict_oldest_entries_beyond_limit() {
+    let mut store = SessionStore::default();
+
+    for idx in 0..5 {
+        store.remember_geom(create_key(&format!("App{}", idx)), create_geom(idx), idx as u64);
+    }
+
+    store.evict(10, 100, 2);
+
+    assert_eq!(store.geometries.len(), 2);
+    assert!(store.lookup_geom(&create_key("App4")).is_some());
+    assert!(store.lookup_geom(&create_key("App3")).is_some());
+}
+
+#[test]
+fn should_skip_malformed_lines() {
+    let store = SessionStore::parse("geometry\tx\tA\ta\t\t0\t0\t1\t1\n\
+        geometry\t1\tA\ta\t\t0\t0\n\
+        gravity\t1\tA\ta\t\t0\t0\t1\t1\n\
+        geometry\t1\tB\tb\t\t-5\t0\t10\t10");
+
+    assert_eq!(store.geometries.len(), 1);
+    assert_eq!(store.lookup_geom(&create_key("B")).map(|geom| geom.x), Some(-5));
+}
+
+#[test]
+fn should_get_session_path() {
+    assert_eq!(get_path(Some("/state"), Some("/home/user")),
+               Some(PathBuf::from("/state/subtle-rs/session")));
+    assert_eq!(get_path(None, Some("/home/user")),
+               Some(PathBuf::from("/home/user/.local/state/subtle-rs/session")));
+    assert_eq!(get_path(Some(""), None), None);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_roundtrip_gravities(klass in "[a-zA-Z]{1,10}", gravities in prop::collection::vec(0usize..20, 1..8)) {
+        let mut store = SessionStore::default();
+
+        store.remember_gravities(create_key(&klass), &gravities, 42);
+
+        let mut parsed = SessionStore::parse(&store.serialize());
+
+        prop_assert_eq!(parsed.lookup_gravities(&create_key(&klass), 42), Some(gravities));
+    }
+}
+
+#[test]
+fn should_mark_looked_up_gravities_as_used() {
+    let mut store = SessionStore::default();
+
+    store.remember_gravities(create_key("Old"), &[1], 1);
+    store.remember_gravities(create_key("New"), &[2], 2);
+
+    assert_eq!(store.lookup_gravities(&create_key("Old"), 3), Some(vec![1]));
+
+    store.evict(10, 100, 1);
+
+    assert!(store.lookup_gravities(&create_key("Old"), 10).is_some());
+    assert!(store.lookup_gravities(&create_key("New"), 10).is_none());
+}
+
+#[test]
+fn should_select_gravities_by_precedence() {
+    let current = [0, 3, 0, 0];
+    let tagged = [false, true, false, false];
+
+    // Tag gravity wins, then valid remembered ones, then current
+    assert_eq!(select_gravities(&[5, 5, 9, 5], &tagged, &current, 6), vec![5, 3, 0, 5]);
+    assert_eq!(select_gravities(&[1], &tagged, &current, 6), vec![1, 3, 0, 0]);
+    assert_eq!(select_gravities(&[], &[], &current, 6), vec![0, 3, 0, 0]);
+}
+
+#[test]
+fn should_skip_malformed_gravity_lines() {
+    let mut store = SessionStore::parse("gravity\t1\tA\ta\t\t1,x\n\
+        gravity\t1\tB\tb\t\t2,4");
+
+    assert_eq!(store.gravities.len(), 1);
+    assert_eq!(store.lookup_gravities(&create_key("B"), 1), Some(vec![2, 4]));
+}
+
+#[test]
+fn should_skip_unnamed_clients() {
+    let subtle = Subtle::default();
+    let unnamed = Client { gravities: vec![1], ..Default::default() };
+    let named = Client { instance: "xterm".to_string(), gravities: vec![2], ..Default::default() };
+
+    assert!(SessionKey::from_client(&unnamed).is_none());
+
+    record_gravities(&subtle, &unnamed);
+    assert!(subtle.session.borrow().gravities.is_empty());
+
+    record_gravities(&subtle, &named);
+    assert_eq!(subtle.session.borrow().gravities.len(), 1);
+}
@@ -9,9 +9,12 @@
 /// See the file LICENSE for details.
 ///
 
+use std::collections::HashMap;
 use proptest::prelude::*;
+use crate::config::MixedConfigVal;
 use crate::spacing::Spacing;
-use crate::style::{CalcSpacing, Style};
+use crate::grab::GapOrder;
+use crate::style::{calc_gap_margin, resolve_inheritance, CalcSpacing, Style, MAX_GAP};
 
 proptest! {
     #![proptest_config(ProptestConfig::with_cases(5))]
@@ -37,3 +40,123 @@ proptest! {
         prop_assert_eq!(style.calc_spacing(CalcSpacing::Left), n * 2 * 3);
     }
 }
+
+fn create_style(values: &[(&str, &str)]) -> HashMap<String, MixedConfigVal> {
+    values.iter()
+        .map(|(key, value)| (key.to_string(), MixedConfigVal::S(value.to_string())))
+        .collect()
+}
+
+fn get_value(values: &HashMap<String, MixedConfigVal>, key: &str) -> Option<String> {
+    values.get(key).map(String::from)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_inherit_from_kind(fg in "#[0-9a-f]{6}", bg in "#[0-9a-f]{6}") {
+        let styles = vec![
+            create_style(&[("kind", "top_panel"), ("foreground", &fg), ("background", "#000000")]),
+            create_style(&[("kind", "bottom_panel"), ("inherits", "top_panel"), ("background", &bg)]),
+        ];
+
+        let resolved = resolve_inheritance(&styles).unwrap();
+
+        prop_assert_eq!(resolved.len(), 2);
+        prop_assert_eq!(get_value(&resolved[1], "kind"), Some("bottom_panel".to_string()));
+        prop_assert_eq!(get_value(&resolved[1], "foreground"), Some(fg));
+        prop_assert_eq!(get_value(&resolved[1], "background"), Some(bg));
+        prop_assert!(!resolved[1].contains_key("inherits"));
+    }
+}
+
+#[test]
+fn should_inherit_from_later_named_style() {
+    let styles = vec![
+        create_style(&[("kind", "title"), ("inherits", "accent")]),
+        create_style(&[("name", "accent"), ("inherits", "base"), ("foreground", "#fecf35")]),
+        create_style(&[("name", "base"), ("foreground", "#757575"), ("background", "#202020")]),
+    ];
+
+    let resolved = resolve_inheritance(&styles).unwrap();
+
+    assert_eq!(get_value(&resolved[0], "foreground"), Some("#fecf35".to_string()));
+    assert_eq!(get_value(&resolved[0], "background"), Some("#202020".to_string()));
+    assert!(!resolved[0].contains_key("name"));
+}
+
+#[test]
+fn should_inherit_by_kind_of_named_style() {
+    let styles = vec![
+        create_style(&[("name", "accent"), ("kind", "title"), ("foreground", "#fecf35")]),
+        create_style(&[("kind", "urgent"), ("inherits", "title")]),
+    ];
+
+    let resolved = resolve_inheritance(&styles).unwrap();
+
+    assert_eq!(get_value(&resolved[1], "foreground"), Some("#fecf35".to_string()));
+    assert_eq!(get_value(&resolved[1], "kind"), Some("urgent".to_string()));
+}
+
+#[test]
+fn should_keep_styles_without_inheritance() {
+    let styles = vec![
+        create_style(&[("kind", "views"), ("foreground", "#757575")]),
+        create_style(&[("kind", "urgent")]),
+    ];
+
+    let resolved = resolve_inheritance(&styles).unwrap();
+
+    assert_eq!(resolved.len(), 2);
+    assert_eq!(resolved[0].len(), 2);
+    assert_eq!(resolved[1].len(), 1);
+}
+
+#[test]
+fn should_report_inheritance_cycle() {
+    let styles = vec![
+        create_style(&[("kind", "views"), ("inherits", "a")]),
+        create_style(&[("name", "a"), ("inherits", "b")]),
+        create_style(&[("name", "b"), ("inherits", "a")]),
+    ];
+
+    let err = resolve_inheritance(&styles).unwrap_err().to_string();
+
+    assert!(err.contains("`a` -> `b` -> `a`"), "{}", err);
+}
+
+#[test]
+fn should_report_unknown_parent() {
+    let styles = vec![
+        create_style(&[("kind", "separator"), ("inherits", "missing")]),
+    ];
+
+    let err = resolve_inheritance(&styles).unwrap_err().to_string();
+
+    assert!(err.contains("`separator`") && err.contains("`missing`"), "{}", err);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+
+    #[test]
+    fn should_clamp_gaps(n in 0i16..MAX_GAP, step in 1i16..50) {
+        let margin = Spacing { top: n, right: n, bottom: n, left: n };
+
+        let increased = calc_gap_margin(margin, margin, GapOrder::Increase, step);
+        let decreased = calc_gap_margin(margin, margin, GapOrder::Decrease, step);
+
+        prop_assert_eq!(increased.top, (n + step).min(MAX_GAP));
+        prop_assert_eq!(increased.left, increased.right);
+        prop_assert_eq!(decreased.bottom, (n - step).max(0));
+    }
+}
+
+#[test]
+fn should_reset_gaps_to_config() {
+    let base = Spacing { top: 1, right: 2, bottom: 3, left: 4 };
+    let margin = Spacing { top: 10, right: 10, bottom: 10, left: 10 };
+
+    assert_eq!(calc_gap_margin(margin, base, GapOrder::Reset, 5), base);
+    assert_eq!(calc_gap_margin(margin, base, GapOrder::Decrease, i16::MAX), Spacing::default());
+}
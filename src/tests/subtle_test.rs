@@ -0,0 +1,147 @@
+///
+/// @package subtle-rs
+///
+/// @file Subtle tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use proptest::prelude::*;
+use std::collections::HashMap;
+use x11rb::protocol::xproto::Rectangle;
+use crate::client::Client;
+use crate::config::MixedConfigVal;
+use crate::subtle::{check_dry_run, parse_focus_model, FocusModel, Subtle, SubtleFlags};
+use crate::tagging::Tagging;
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_parse_focus_model(name in "(follow_mouse|click|keyboard)") {
+        let focus_model = FocusModel::try_from(&MixedConfigVal::S(name.clone()));
+
+        prop_assert!(focus_model.is_ok());
+        prop_assert_eq!(focus_model.unwrap(), match name.as_str() {
+            "click" => FocusModel::Click,
+            "keyboard" => FocusModel::Keyboard,
+            _ => FocusModel::FollowMouse,
+        });
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_reject_unknown_focus_model(name in "[a-z]{1,4}") {
+        prop_assert!(FocusModel::try_from(&MixedConfigVal::S(name)).is_err());
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_map_click_to_focus(click_to_focus in any::<bool>()) {
+        let mut values = HashMap::new();
+
+        values.insert("click_to_focus".to_string(), MixedConfigVal::B(click_to_focus));
+
+        prop_assert_eq!(parse_focus_model(&values), if click_to_focus {
+            FocusModel::Click
+        } else {
+            FocusModel::FollowMouse
+        });
+
+        // Explicit model wins over legacy flag
+        values.insert("focus_model".to_string(), MixedConfigVal::S("keyboard".to_string()));
+
+        prop_assert_eq!(parse_focus_model(&values), FocusModel::Keyboard);
+    }
+}
+
+#[test]
+fn should_decide_focus_on_enter() {
+    assert!(FocusModel::FollowMouse.focus_on_enter());
+    assert!(!FocusModel::Click.focus_on_enter());
+    assert!(!FocusModel::Keyboard.focus_on_enter());
+
+    assert!(!FocusModel::FollowMouse.focus_on_click());
+    assert!(FocusModel::Click.focus_on_click());
+    assert!(!FocusModel::Keyboard.focus_on_click());
+}
+
+fn create_client(win: u32, leader: u32, transient_for: u32) -> Client {
+    Client {
+        win,
+        leader,
+        transient_for,
+        geom: Rectangle { x: win as i16, y: 0, width: 100, height: 100 },
+        ..Default::default()
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_find_transients_of_window(parent in 1u32..100) {
+        let subtle = Subtle::default();
+
+        subtle.clients.borrow_mut().extend([
+            create_client(parent, parent, 0),
+            create_client(parent + 100, 0, parent),
+            create_client(parent + 200, parent, 0),
+            create_client(parent + 300, 0, 0),
+            create_client(parent + 400, parent + 300, parent + 300),
+        ]);
+
+        // Other windows of the group aren't transients
+        let transients: Vec<u32> = subtle.find_transients(parent).iter()
+            .map(|(win, _)| *win)
+            .collect();
+
+        prop_assert_eq!(transients, vec![parent + 100]);
+    }
+}
+
+#[test]
+fn should_find_no_transients_without_window() {
+    let subtle = Subtle::default();
+
+    subtle.clients.borrow_mut().push(create_client(1, 0, 0));
+
+    assert!(subtle.find_transients(0).is_empty());
+    assert!(subtle.find_transients(1).is_empty());
+}
+
+#[test]
+fn should_focus_on_press_only_in_click_model() {
+    assert!(FocusModel::Click.focus_on_press(1, 2));
+    assert!(!FocusModel::Click.focus_on_press(2, 2));
+    assert!(!FocusModel::Click.focus_on_press(1, 0));
+    assert!(!FocusModel::FollowMouse.focus_on_press(1, 2));
+    assert!(!FocusModel::Keyboard.focus_on_press(1, 2));
+}
+
+#[test]
+fn should_refuse_dry_run_on_live_display() {
+    assert!(check_dry_run(&SubtleFlags::DRY_RUN).is_err());
+    assert!(check_dry_run(&(SubtleFlags::DRY_RUN | SubtleFlags::CHECK)).is_ok());
+    assert!(check_dry_run(&(SubtleFlags::DRY_RUN | SubtleFlags::NESTED)).is_ok());
+    assert!(check_dry_run(&SubtleFlags::empty()).is_ok());
+}
+
+#[test]
+fn should_skip_excluded_client_when_finding_next() {
+    let subtle = Subtle::default();
+
+    subtle.visible_tags.set(Tagging::TAG1);
+
+    for win in [1, 2] {
+        subtle.clients.borrow_mut().push(Client { win, tags: Tagging::TAG1, ..Client::default() });
+    }
+
+    assert_eq!(subtle.find_next_client(0, false).map(|client| client.win), Some(1));
+    assert_eq!(subtle.find_next_client_except(0, false, 1).map(|client| client.win), Some(2));
+}
@@ -10,7 +10,11 @@
 ///
 
 use proptest::prelude::*;
-use crate::tag::TagBuilder;
+use crate::tag;
+use x11rb::protocol::xproto::Rectangle;
+use crate::client::{Client, ClientFlags};
+use crate::tag::{matches_types, parse_window_types, MAX_TAGS, Tag, TagBuilder, TagFlags};
+use crate::tagging::Tagging;
 
 proptest! {
     #![proptest_config(ProptestConfig::with_cases(5))]
@@ -22,4 +26,213 @@ proptest! {
 
         let _ = builder.build().unwrap();
     }
-}
\ No newline at end of file
+}
+
+fn create_tags(names: &[&str]) -> Vec<Tag> {
+    names.iter()
+        .map(|name| {
+            let mut builder = TagBuilder::default();
+
+            if name.is_empty() {
+                builder.flags(TagFlags::DEAD);
+            } else {
+                builder.name(name.to_string());
+            }
+
+            builder.build().unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn should_append_new_tag() {
+    let tags = create_tags(&["terms", "www"]);
+
+    assert_eq!(tag::find_free_idx(&tags, "dev").unwrap(), 2);
+}
+
+#[test]
+fn should_reuse_tombstone() {
+    let tags = create_tags(&["terms", "", "www", ""]);
+
+    assert_eq!(tag::find_free_idx(&tags, "dev").unwrap(), 1);
+}
+
+#[test]
+fn should_reject_duplicate_or_empty_name() {
+    let tags = create_tags(&["terms", "www"]);
+
+    assert!(tag::find_free_idx(&tags, "www").is_err());
+    assert!(tag::find_free_idx(&tags, "").is_err());
+}
+
+#[test]
+fn should_respect_tag_limit() {
+    let names: Vec<String> = (0..MAX_TAGS).map(|idx| format!("tag{}", idx)).collect();
+    let tags = create_tags(&names.iter().map(String::as_str).collect::<Vec<_>>());
+
+    assert!(tag::find_free_idx(&tags, "dev").is_err());
+}
+
+#[test]
+fn should_remove_unused_tag() {
+    let tags = create_tags(&["terms", "www", "dev"]);
+
+    assert_eq!(tag::find_removable_idx(&tags, "www", Tagging::TAG1 | Tagging::TAG3, false).unwrap(), 1);
+}
+
+#[test]
+fn should_refuse_removing_used_tag_unless_forced() {
+    let tags = create_tags(&["terms", "www"]);
+
+    assert!(tag::find_removable_idx(&tags, "www", Tagging::TAG2, false).is_err());
+    assert_eq!(tag::find_removable_idx(&tags, "www", Tagging::TAG2, true).unwrap(), 1);
+}
+
+#[test]
+fn should_refuse_removing_unknown_or_last_tag() {
+    let tags = create_tags(&["terms", ""]);
+
+    assert!(tag::find_removable_idx(&tags, "www", Tagging::empty(), true).is_err());
+    assert!(tag::find_removable_idx(&tags, "terms", Tagging::empty(), true).is_err());
+}
+
+#[test]
+fn should_parse_message() {
+    assert_eq!(tag::parse_message(b"dev\0^xterm$\0\0\0"),
+               Some(("dev".to_string(), Some("^xterm$".to_string()))));
+    assert_eq!(tag::parse_message(b"dev\0\0\0"), Some(("dev".to_string(), None)));
+    assert_eq!(tag::parse_message(b"\0force\0"), None);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_apply_gravity_to_views_with_tag(tag_idx in 0usize..4, gravity_id in 1usize..10) {
+        let view_tags = [Tagging::TAG1, Tagging::TAG2 | Tagging::TAG3, Tagging::TAG4, Tagging::empty()];
+        let mut gravities = vec![0; view_tags.len()];
+
+        tag::apply_gravity(&mut gravities, &view_tags, tag_idx, gravity_id);
+
+        for (gravity, tags) in gravities.iter().zip(view_tags.iter()) {
+            prop_assert_eq!(*gravity, if tags.intersects(Tagging::from_bits_retain(1 << tag_idx)) {
+                gravity_id } else { 0 });
+        }
+    }
+}
+
+#[test]
+fn should_calc_tag_geom() {
+    let client_geom = Rectangle { x: 1, y: 2, width: 300, height: 200 };
+    let tag_geom = Rectangle { x: 10, y: 20, width: 30, height: 40 };
+
+    let mut builder = TagBuilder::default();
+
+    builder.flags(TagFlags::GEOMETRY).geom(Some(tag_geom));
+
+    let geom = builder.build().unwrap().calc_geom(&client_geom).unwrap();
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (10, 20, 30, 40));
+
+    builder.flags(TagFlags::POSITION);
+
+    let geom = builder.build().unwrap().calc_geom(&client_geom).unwrap();
+
+    assert_eq!((geom.x, geom.y, geom.width, geom.height), (10, 20, 300, 200));
+
+    builder.flags(TagFlags::GRAVITY);
+
+    assert!(builder.build().unwrap().calc_geom(&client_geom).is_none());
+}
+
+#[test]
+fn should_fall_back_to_first_screen() {
+    assert_eq!(tag::calc_screen_id(1, 2), 1);
+    assert_eq!(tag::calc_screen_id(2, 2), 0);
+    assert_eq!(tag::calc_screen_id(-1, 2), 0);
+}
+
+#[test]
+fn should_prefer_excluded_over_only_types() {
+    let dialog = ClientFlags::TYPE_DIALOG;
+    let splash = ClientFlags::TYPE_SPLASH;
+
+    assert!(matches_types(ClientFlags::TYPE_NORMAL, dialog | splash, ClientFlags::empty()));
+    assert!(!matches_types(dialog, dialog | splash, ClientFlags::empty()));
+    assert!(matches_types(dialog, ClientFlags::empty(), dialog));
+    assert!(!matches_types(ClientFlags::TYPE_NORMAL, ClientFlags::empty(), dialog));
+    assert!(!matches_types(dialog, dialog, dialog));
+}
+
+#[test]
+fn should_parse_window_types() {
+    let names = vec!["dialog".to_string(), "unknown".to_string(), "splash".to_string()];
+
+    assert_eq!(parse_window_types(&names), ClientFlags::TYPE_DIALOG | ClientFlags::TYPE_SPLASH);
+}
+
+#[test]
+fn should_match_clients_by_type() {
+    let tag = TagBuilder::default()
+        .name("media".to_string())
+        .regex(Some(regex::Regex::new("mpv").unwrap()))
+        .exclude_types(ClientFlags::TYPE_DIALOG | ClientFlags::TYPE_SPLASH)
+        .build()
+        .unwrap();
+
+    let dialog_tag = TagBuilder::default()
+        .name("dialogs".to_string())
+        .regex(Some(regex::Regex::new("mpv").unwrap()))
+        .only_types(ClientFlags::TYPE_DIALOG)
+        .build()
+        .unwrap();
+
+    for (flags, is_media, is_dialog) in [
+        (ClientFlags::TYPE_NORMAL, true, false),
+        (ClientFlags::empty(), true, false),
+        (ClientFlags::TYPE_DIALOG, false, true),
+        (ClientFlags::TYPE_SPLASH, false, false),
+    ] {
+        let client = Client {
+            flags,
+            klass: "mpv".to_string(),
+            ..Client::default()
+        };
+
+        assert_eq!(tag.matches(&client), is_media);
+        assert_eq!(dialog_tag.matches(&client), is_dialog);
+    }
+}
+
+#[test]
+fn should_keep_default_tag_first() {
+    let mut tags = create_tags(&["terms", "www"]);
+
+    tag::ensure_default_tag(&mut tags).unwrap();
+
+    assert_eq!(tags.iter().map(|tag| tag.name.as_str()).collect::<Vec<&str>>(), vec!["default", "terms", "www"]);
+
+    // Move configured default tag to the front
+    let mut tags = create_tags(&["terms", "default"]);
+
+    tag::ensure_default_tag(&mut tags).unwrap();
+
+    assert_eq!(tags.iter().map(|tag| tag.name.as_str()).collect::<Vec<&str>>(), vec!["default", "terms"]);
+}
+
+#[test]
+fn should_respect_tag_limit_for_default_tag() {
+    let names: Vec<String> = (0..MAX_TAGS).map(|idx| format!("tag{}", idx)).collect();
+    let mut tags = create_tags(&names.iter().map(String::as_str).collect::<Vec<_>>());
+
+    assert!(tag::ensure_default_tag(&mut tags).is_err());
+    assert_eq!(tags.len(), MAX_TAGS);
+}
+
+#[test]
+fn should_refuse_removing_default_tag() {
+    let tags = create_tags(&["default", "www"]);
+
+    assert!(tag::find_removable_idx(&tags, "default", Tagging::empty(), false).is_err());
+    assert!(tag::find_removable_idx(&tags, "default", Tagging::empty(), true).is_err());
+}
@@ -10,6 +10,7 @@
 ///
 
 use proptest::prelude::*;
+use crate::tagging;
 use crate::tagging::Tagging;
 
 proptest! {
@@ -45,4 +46,14 @@ proptest! {
         prop_assert!(!remaining.contains(tag2));
         prop_assert!(!remaining.contains(tag3));
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn should_map_indices_to_bits() {
+    assert_eq!(tagging::default_tag().bits(), Tagging::TAG1.bits());
+    assert_eq!(tagging::tag_bit(1).bits(), Tagging::TAG2.bits());
+    assert_eq!(tagging::view_bit(0).bits(), 1 << 1);
+    assert_eq!(tagging::view_bit(1).bits(), 1 << 2);
+    assert!(tagging::tag_bit(32).is_empty());
+    assert!(tagging::view_bit(31).is_empty());
+}
@@ -0,0 +1,79 @@
+///
+/// @package subtle-rs
+///
+/// @file Tray tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use proptest::prelude::*;
+use x11rb::properties::{WmSizeHints, WmSizeHintsSpecification};
+use x11rb::protocol::xproto::NotifyDetail;
+use crate::grab::CycleOrder;
+use crate::tray::{calc_focus_detail, calc_icon_size, calc_width, is_panel_crossing, is_xembed_mapped, SystemTrayOpcode, XEmbed, XEmbedFocus};
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_calc_at_least_twice_panel_height(width in 0i32..100, panel_height in 1u16..50) {
+        let mut size_hints = WmSizeHints::new();
+
+        size_hints.size = Some((WmSizeHintsSpecification::ProgramSpecified, width, 0));
+
+        prop_assert_eq!(calc_width(Some(&size_hints), panel_height), (width as u16).max(panel_height * 2));
+    }
+}
+
+#[test]
+fn should_calc_zero_width_without_hints() {
+    assert_eq!(calc_width(None, 20), 0);
+    assert_eq!(calc_width(Some(&WmSizeHints::new()), 20), 0);
+}
+
+#[test]
+fn should_calc_square_icon_size() {
+    assert_eq!(calc_icon_size(20, 4), 16);
+    assert_eq!(calc_icon_size(20, 0), 20);
+    assert_eq!(calc_icon_size(2, 10), 1);
+}
+
+#[test]
+fn should_check_xembed_mapped_bit() {
+    assert!(is_xembed_mapped(&[0, 1]));
+    assert!(is_xembed_mapped(&[0, 3]));
+    assert!(!is_xembed_mapped(&[0, 0]));
+    assert!(!is_xembed_mapped(&[0, 2]));
+    assert!(!is_xembed_mapped(&[1]));
+    assert!(!is_xembed_mapped(&[]));
+}
+
+#[test]
+fn should_parse_system_tray_opcodes() {
+    assert_eq!(SystemTrayOpcode::from_repr(0), Some(SystemTrayOpcode::RequestDock));
+    assert_eq!(SystemTrayOpcode::from_repr(1), Some(SystemTrayOpcode::BeginMessage));
+    assert_eq!(SystemTrayOpcode::from_repr(2), Some(SystemTrayOpcode::CancelMessage));
+    assert_eq!(SystemTrayOpcode::from_repr(3), None);
+}
+
+#[test]
+fn should_parse_xembed_focus_requests() {
+    assert_eq!(XEmbed::from_repr(3), Some(XEmbed::RequestFocus));
+    assert_eq!(XEmbed::from_repr(6), Some(XEmbed::FocusNext));
+    assert_eq!(XEmbed::from_repr(7), Some(XEmbed::FocusPrev));
+}
+
+#[test]
+fn should_focus_first_or_last_widget_when_cycling() {
+    assert_eq!(calc_focus_detail(CycleOrder::Next), XEmbedFocus::First);
+    assert_eq!(calc_focus_detail(CycleOrder::Prev), XEmbedFocus::Last);
+}
+
+#[test]
+fn should_ignore_crossing_into_tray_win() {
+    assert!(is_panel_crossing(NotifyDetail::ANCESTOR));
+    assert!(is_panel_crossing(NotifyDetail::NONLINEAR));
+    assert!(!is_panel_crossing(NotifyDetail::INFERIOR));
+}
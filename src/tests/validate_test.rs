@@ -0,0 +1,73 @@
+///
+/// @package subtle-rs
+///
+/// @file Validation tests
+/// @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+/// @version $Id$
+///
+/// This program can be distributed under the terms of the GNU GPLv3.
+/// See the file LICENSE for details.
+///
+
+use proptest::prelude::*;
+use crate::validate::{ValidGravityIdx, ValidScreenIdx, ValidTagIdx, ValidTagging, ValidViewIdx};
+
+#[test]
+fn should_accept_indices_in_range() {
+    for len in 0usize..6 {
+        for raw in 0u32..8 {
+            let is_valid = (raw as usize) < len;
+
+            assert_eq!(ValidGravityIdx::new(raw, len).ok().map(ValidGravityIdx::get),
+                       is_valid.then_some(raw as usize));
+            assert_eq!(ValidScreenIdx::new(raw, len).ok().map(ValidScreenIdx::get),
+                       is_valid.then_some(raw as usize));
+            assert_eq!(ValidViewIdx::new(raw, len).ok().map(ValidViewIdx::get),
+                       is_valid.then_some(raw as usize));
+            assert_eq!(ValidTagIdx::new(raw, len).ok().map(ValidTagIdx::get),
+                       is_valid.then_some(raw as usize));
+        }
+    }
+}
+
+#[test]
+fn should_reject_stale_negative_index() {
+    // Former -1 values end up as max value in properties
+    assert!(ValidGravityIdx::new(u32::MAX, 3).is_err());
+    assert!(ValidScreenIdx::new(-1i32 as u32, 2).is_err());
+    assert!(ValidTagIdx::new(32, 40).is_err());
+}
+
+#[test]
+fn should_describe_invalid_index() {
+    let err = ValidScreenIdx::new(5, 2).unwrap_err();
+
+    assert_eq!(err.to_string(), "Invalid screen index `5`, expected less than `2`");
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+
+    #[test]
+    fn should_accept_only_defined_tags(raw in any::<u32>(), ntags in 0usize..=32) {
+        let tag_mask = if 32 == ntags { u32::MAX } else { (1u32 << ntags) - 1 };
+
+        let res = ValidTagging::new(raw, ntags);
+
+        prop_assert_eq!(res.is_ok(), 0 == raw & !tag_mask);
+
+        if let Ok(tags) = res {
+            prop_assert_eq!(tags.get().bits(), raw);
+        }
+    }
+}
+
+#[test]
+fn should_check_tags_at_boundaries() {
+    assert!(ValidTagging::new(0, 0).is_ok());
+    assert!(ValidTagging::new(1, 0).is_err());
+    assert!(ValidTagging::new(0b111, 3).is_ok());
+    assert!(ValidTagging::new(0b1000, 3).is_err());
+    assert!(ValidTagging::new(u32::MAX, 32).is_ok());
+    assert!(ValidTagging::new(u32::MAX, 40).is_ok());
+}
@@ -10,7 +10,15 @@
 ///
 
 use proptest::prelude::*;
-use crate::view::ViewBuilder;
+use x11rb::protocol::xproto::Rectangle;
+use crate::config::check_duplicate_names;
+use crate::effect::Executor;
+use crate::screen::Screen;
+use crate::subtle::Subtle;
+use crate::tagging::Tagging;
+use crate::icon::Icon;
+use crate::tagging;
+use crate::view::{ensure_default_view, calc_fallback_view, calc_current_desktop, calc_desktop_viewports, calc_icon_data, calc_removed_screen_views, calc_screen_views, sync_gravities, View, ViewBuilder};
 
 proptest! {
     #![proptest_config(ProptestConfig::with_cases(5))]
@@ -22,4 +30,193 @@ proptest! {
 
         let _ = builder.build().unwrap();
     }
-}
\ No newline at end of file
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_swap_or_jump_to_visible_view(view_a in 0usize..10, view_b in 10usize..20) {
+        // Switch swaps views of both screens
+        let mut screen_views = vec![view_a as isize, view_b as isize];
+
+        prop_assert_eq!(calc_screen_views(&mut screen_views, 0, view_b, true), 0);
+        prop_assert_eq!(screen_views, vec![view_b as isize, view_a as isize]);
+
+        // Jump keeps views and moves to other screen
+        let mut screen_views = vec![view_a as isize, view_b as isize];
+
+        prop_assert_eq!(calc_screen_views(&mut screen_views, 0, view_b, false), 1);
+        prop_assert_eq!(screen_views, vec![view_a as isize, view_b as isize]);
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_show_hidden_view_on_screen(view_a in 0usize..10, view_b in 10usize..20, swap_views in any::<bool>()) {
+        let mut screen_views = vec![view_a as isize, -1];
+
+        prop_assert_eq!(calc_screen_views(&mut screen_views, 0, view_b, swap_views), 0);
+        prop_assert_eq!(screen_views, vec![view_b as isize, -1]);
+    }
+}
+
+#[test]
+fn should_move_screens_of_removed_view_to_first_view() {
+    let mut screen_views = vec![0, 2, 3, 1];
+
+    calc_removed_screen_views(&mut screen_views, 2);
+
+    assert_eq!(screen_views, vec![0, 0, 2, 1]);
+}
+
+#[test]
+fn should_sync_gravities_with_views() {
+    let mut gravities = vec![1, 2, 3];
+
+    sync_gravities(&mut gravities, 4, None, 5);
+
+    assert_eq!(gravities, vec![1, 2, 3, 5]);
+
+    sync_gravities(&mut gravities, 3, Some(1), 5);
+
+    assert_eq!(gravities, vec![1, 3, 5]);
+}
+
+#[test]
+fn should_focus_view_by_identity_with_duplicate_names() {
+    let mut subtle = Subtle {
+        executor: Executor::new(true),
+        ..Subtle::default()
+    };
+
+    let geom = Rectangle { x: 0, y: 0, width: 800, height: 600 };
+
+    subtle.screens.push(Screen { geom, base: geom, ..Screen::default() });
+    subtle.screens[0].view_idx.set(0);
+
+    let mut names = vec!["www".to_string(), "www".to_string()];
+
+    check_duplicate_names("view", &mut names, true).unwrap();
+
+    for name in names {
+        subtle.views.borrow_mut().push(ViewBuilder::default().name(name).build().unwrap());
+    }
+
+    subtle.views.borrow()[1].focus(&subtle, 0, false, false).unwrap();
+
+    assert_eq!(subtle.screens[0].view_idx.get(), 1);
+    assert_eq!(subtle.views.borrow()[1].name, "www_2");
+}
+
+#[test]
+fn should_fall_back_from_emptied_dynamic_view() {
+    let view_tags: Vec<Tagging> = (0..4).map(|idx| Tagging::from_bits_retain(1 << idx)).collect();
+    let old_client_tags = Tagging::from_bits_retain(0b0110);
+
+    // Next occupied view wins over the first view
+    assert_eq!(calc_fallback_view(1, &view_tags, old_client_tags, Tagging::from_bits_retain(0b0100), &[1]), Some(2));
+
+    // Skip occupied views shown elsewhere and fall back to the first view
+    assert_eq!(calc_fallback_view(1, &view_tags, old_client_tags, Tagging::from_bits_retain(0b0100), &[1, 2]), Some(0));
+    assert_eq!(calc_fallback_view(1, &view_tags, old_client_tags, Tagging::empty(), &[1]), Some(0));
+}
+
+#[test]
+fn should_stay_on_dynamic_view_unless_emptied() {
+    let view_tags: Vec<Tagging> = (0..3).map(|idx| Tagging::from_bits_retain(1 << idx)).collect();
+
+    // Still occupied
+    assert_eq!(calc_fallback_view(1, &view_tags, Tagging::from_bits_retain(0b010),
+                                  Tagging::from_bits_retain(0b010), &[1]), None);
+
+    // Already empty before, e.g. when the view was selected on purpose
+    assert_eq!(calc_fallback_view(1, &view_tags, Tagging::empty(), Tagging::empty(), &[1]), None);
+
+    // No free view left
+    assert_eq!(calc_fallback_view(0, &view_tags[..1], Tagging::from_bits_retain(0b001), Tagging::empty(), &[0]), None);
+}
+
+#[test]
+fn should_publish_icon_pixmaps_and_geometries() {
+    let icon = Icon { pixmap: 42, width: 9, height: 8, depth: 1, ..Default::default() };
+
+    let (pixmaps, geoms) = calc_icon_data(&[None, Some(&icon)]);
+
+    assert_eq!(pixmaps, vec![0, 42]);
+    assert_eq!(geoms, vec![0, 0, 9, 8]);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5))]
+    #[test]
+    fn should_publish_origin_viewport_per_view(nviews in 0usize..32) {
+        let viewports = calc_desktop_viewports(nviews);
+
+        // EWMH: One x and y pair per desktop, regardless of the screens
+        prop_assert_eq!(viewports.len(), 2 * nviews);
+        prop_assert!(viewports.iter().all(|coord| 0 == *coord));
+    }
+}
+
+#[test]
+fn should_publish_viewport_pairs() {
+    assert!(calc_desktop_viewports(0).is_empty());
+    assert_eq!(calc_desktop_viewports(3), vec![0, 0, 0, 0, 0, 0]);
+}
+
+fn create_views(tags: &[Tagging]) -> Vec<View> {
+    tags.iter()
+        .map(|tags| ViewBuilder::default().tags(*tags).build().unwrap())
+        .collect()
+}
+
+#[test]
+fn should_show_untagged_clients_on_some_view() {
+    let shapes = [
+        // No views at all
+        vec![],
+        // Views without the default tag
+        vec![Tagging::TAG2, Tagging::TAG3],
+        // View with the default tag in the middle
+        vec![Tagging::TAG2, Tagging::TAG1 | Tagging::TAG3],
+        // Empty views
+        vec![Tagging::empty(), Tagging::empty()],
+    ];
+
+    for view_tags in shapes.iter() {
+        let mut views = create_views(view_tags);
+
+        ensure_default_view(&mut views).unwrap();
+
+        assert_eq!(1, views.iter().filter(|view| view.tags.get().intersects(tagging::default_tag())).count());
+    }
+}
+
+#[test]
+fn should_add_default_tag_to_first_view_only_when_missing() {
+    let mut views = create_views(&[Tagging::TAG2, Tagging::TAG1]);
+
+    ensure_default_view(&mut views).unwrap();
+
+    assert_eq!(views[0].tags.get().bits(), Tagging::TAG2.bits());
+
+    let mut views = create_views(&[Tagging::TAG2, Tagging::TAG3]);
+
+    ensure_default_view(&mut views).unwrap();
+
+    assert_eq!(views[0].tags.get().bits(), (Tagging::TAG1 | Tagging::TAG2).bits());
+}
+
+#[test]
+fn should_select_desktop_of_current_screen() {
+    assert_eq!(calc_current_desktop(&[0, 3], 1), 3);
+    assert_eq!(calc_current_desktop(&[2, 3], 0), 2);
+}
+
+#[test]
+fn should_fall_back_to_desktop_of_first_screen() {
+    assert_eq!(calc_current_desktop(&[2, 3], 5), 2);
+    assert_eq!(calc_current_desktop(&[-1], 0), 0);
+    assert_eq!(calc_current_desktop(&[], 0), 0);
+}
@@ -19,9 +19,14 @@ use strum_macros::FromRepr;
 use x11rb::{CURRENT_TIME, NONE};
 use x11rb::connection::Connection;
 use x11rb::properties::{WmSizeHints, WmSizeHintsSpecification};
-use x11rb::protocol::xproto::{AtomEnum, ChangeWindowAttributesAux, ConfigureWindowAux, ConnectionExt, EventMask, PropMode, SetMode, StackMode, Window};
+use x11rb::protocol::xproto::{AtomEnum, ChangeWindowAttributesAux, ConfigureWindowAux, ConnectionExt, EventMask, NotifyDetail, PropMode, SetMode, StackMode, Window};
 use x11rb::wrapper::ConnectionExt as ConnectionExtWrapper;
+use crate::client::reply_unless_gone;
+use crate::effect;
+use crate::effect::Effect;
 use crate::ewmh;
+use crate::grab;
+use crate::grab::CycleOrder;
 use crate::ewmh::WMState;
 use crate::style::CalcSpacing;
 use crate::subtle::Subtle;
@@ -36,6 +41,8 @@ bitflags! {
         const CLOSE = 1 << 1;
         /// Ignore unmaps
         const UNMAP = 1 << 2;
+        /// Icon asked to stay hidden
+        const HIDDEN = 1 << 3;
     }
 }
 
@@ -52,7 +59,18 @@ pub(crate) struct Tray {
 }
 
 #[repr(u8)]
-#[derive(Copy, Clone, FromRepr)]
+#[derive(Debug, Copy, Clone, PartialEq, FromRepr)]
+pub(crate) enum SystemTrayOpcode {
+    /// Dock icon into the tray
+    RequestDock = 0,
+    /// Start of a balloon message
+    BeginMessage = 1,
+    /// Cancel a balloon message
+    CancelMessage = 2,
+}
+
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, FromRepr)]
 pub(crate) enum XEmbed {
     /// Start embedding
     EmbeddedNotify = 0,
@@ -60,32 +78,43 @@ pub(crate) enum XEmbed {
     WindowActivate = 1,
     /// Tray has no focus
     WindowDeactivate = 2,
-    _RequestFocus = 3,
+    /// Icon asks for the input focus
+    RequestFocus = 3,
     /// Focus model
     FocusIn = 4,
-    _FocusOut = 5,
-    _FocusNext = 6,
-    _FocusPrev = 7,
+    /// Icon lost the focus
+    FocusOut = 5,
+    /// Icon passes the focus to the next one
+    FocusNext = 6,
+    /// Icon passes the focus to the previous one
+    FocusPrev = 7,
     _GrabKey = 8,
     _UngrabKey = 9,
-    _ModalityOn = 10,
-    _ModalityOff = 11,
+    /// Embedder is blocked by a modal dialog
+    ModalityOn = 10,
+    /// Embedder is no longer blocked
+    ModalityOff = 11,
     _RegisterAccelerator = 12,
     _UnregisterAccelerator = 13,
     _ActivateAccelerator = 14,
 }
 
 #[repr(u8)]
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub(crate) enum XEmbedFocus {
     /// Focus default
     Current = 0,
-    _First = 1,
-    _Last = 2,
+    /// Focus first widget of the icon
+    First = 1,
+    /// Focus last widget of the icon
+    Last = 2,
 }
 
 /// Tray mapped
-const _XEMBED_MAPPED: u8 = 1 << 0;
+const XEMBED_MAPPED: u32 = 1 << 0;
+
+/// Implemented version of the XEmbed protocol
+const XEMBED_VERSION: u32 = 0;
 
 impl Tray {
     /// Create a new instance
@@ -103,23 +132,14 @@ impl Tray {
         let atoms = subtle.atoms.get().unwrap();
 
         conn.grab_server()?;
-        conn.change_save_set(SetMode::INSERT, win)?;
 
-        // X Properties
-        let _geom_reply = conn.get_geometry(win)?.reply()?;
-
-        let aux = ChangeWindowAttributesAux::default()
-            .event_mask(EventMask::STRUCTURE_NOTIFY
-                | EventMask::SUBSTRUCTURE_NOTIFY
-                | EventMask::PROPERTY_CHANGE
-                | EventMask::FOCUS_CHANGE
-                | EventMask::ENTER_WINDOW);
-
-        conn.change_window_attributes(win, &aux)?.check()?;
-        conn.reparent_window(win, subtle.tray_win, 0, 0)?.check()?;
+        // Always ungrab again, the window might be gone already
+        let embedded = Self::embed_win(subtle, win);
 
         conn.ungrab_server()?;
 
+        embedded?;
+
         let mut tray = Self {
             win,
             ..Self::default()
@@ -132,52 +152,64 @@ impl Tray {
         tray.set_wm_state(subtle, WMState::Withdrawn)?;
 
         // Start embedding life cycle
-        conn.change_property32(PropMode::REPLACE, tray.win, atoms._XEMBED,
-                               AtomEnum::CARDINAL, &[0xFFFFFF, CURRENT_TIME,
-                XEmbed::EmbeddedNotify as u32, subtle.tray_win, 0])?.check()?;
+        tray.send_xembed(subtle, XEmbed::EmbeddedNotify, 0, subtle.tray_win, XEMBED_VERSION)?;
 
         debug!("{}: tray={}", function_name!(), tray);
 
         Ok(tray)
     }
 
-    /// Set size hints for the underlying win
+    /// Select events and reparent a new tray window
     ///
     /// # Arguments
     ///
     /// * `subtle` - Global state object
+    /// * `win` - Tray window
     ///
     /// # Returns
     ///
     /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-    pub(crate) fn set_size_hints(&mut self, subtle: &Subtle) -> Result<()> {
+    fn embed_win(subtle: &Subtle, win: Window) -> Result<()> {
         let conn = subtle.conn.get().unwrap();
 
-        // Set default values
-        self.width = 0;
+        conn.change_save_set(SetMode::INSERT, win)?;
 
-        // Size hints - no idea why it's called normal hints
-        if let Some(size_hints) = WmSizeHints::get_normal_hints(conn, self.win)?.reply()? {
+        // X Properties
+        let _geom_reply = conn.get_geometry(win)?.reply()?;
 
-            // Program min size - limit min size to screen size if larger
-            if let Some((min_width, _)) = size_hints.min_size {
-                self.width = max!(min_width as u16, subtle.panel_height * 2);
-            }
+        let aux = ChangeWindowAttributesAux::default()
+            .event_mask(EventMask::STRUCTURE_NOTIFY
+                | EventMask::SUBSTRUCTURE_NOTIFY
+                | EventMask::PROPERTY_CHANGE
+                | EventMask::FOCUS_CHANGE
+                | EventMask::ENTER_WINDOW);
 
-            // Base sizes
-            if let Some((base_width, _)) = size_hints.base_size {
-                self.width = max!(base_width as u16, subtle.panel_height * 2);
-            }
+        conn.change_window_attributes(win, &aux)?.check()?;
+        conn.reparent_window(win, subtle.tray_win, 0, 0)?.check()?;
 
-            // User/program size
-            if let Some((hint_spec, x, _y)) = size_hints.size {
-                match hint_spec {
-                    WmSizeHintsSpecification::UserSpecified | WmSizeHintsSpecification::ProgramSpecified => {
-                        self.width = max!(x as u16, subtle.panel_height * 2);
-                    }
-                }
-            }
-        }
+        debug!("{}: win={}", function_name!(), win);
+
+        Ok(())
+    }
+
+    /// Set size hints for the underlying win
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn set_size_hints(&mut self, subtle: &Subtle) -> Result<()> {
+        let conn = subtle.conn.get().unwrap();
+
+        // Size hints - no idea why it's called normal hints
+        let Some(size_hints) = reply_unless_gone(WmSizeHints::get_normal_hints(conn, self.win)?.reply())? else {
+            return Ok(());
+        };
+
+        self.width = calc_width(size_hints.as_ref(), subtle.panel_height);
 
         debug!("{}: tray={}", function_name!(), self);
 
@@ -197,10 +229,13 @@ impl Tray {
         let conn = subtle.conn.get().unwrap();
         let atoms = subtle.atoms.get().unwrap();
 
-        let wm_name = conn.get_property(false, self.win,
-                                        atoms.WM_NAME, AtomEnum::STRING,
-                                        0, u32::MAX)?.reply()?.value;
-        self.name = String::from_utf8(wm_name)?;
+        let Some(wm_name) = reply_unless_gone(conn.get_property(false, self.win,
+                                                                atoms.WM_NAME, AtomEnum::STRING,
+                                                                0, u32::MAX)?.reply())? else {
+            return Ok(());
+        };
+
+        self.name = String::from_utf8(wm_name.value)?;
 
         debug!("{}: tray={}", function_name!(), self);
 
@@ -258,36 +293,63 @@ impl Tray {
         Ok(())
     }
 
-    /// Resize underlying win
+    /// Move and resize underlying win to a square icon inside of the tray win
     ///
     /// # Arguments
     ///
     /// * `subtle` - Global state object
-    /// * `width` - New width
+    /// * `offset_x` - X offset inside of the tray win
     ///
     /// # Returns
     ///
     /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
-    pub(crate) fn resize(&self, subtle: &Subtle, width: i32) -> Result<()> {
+    pub(crate) fn resize(&mut self, subtle: &Subtle, offset_x: i32) -> Result<()> {
         let conn = subtle.conn.get().unwrap();
 
-        conn.map_window(self.win)?.check()?;
+        self.width = calc_icon_size(subtle.panel_height,
+                                    subtle.tray_style.calc_spacing(CalcSpacing::Height));
 
         let aux = &ConfigureWindowAux::default()
-            .x(width)
+            .x(offset_x)
             .y(0i32)
-            .width(max!(1, width) as u32)
-            .height(max!(1, subtle.panel_height as i16
-                            - subtle.tray_style.calc_spacing(CalcSpacing::Height)) as u32)
+            .width(self.width as u32)
+            .height(self.width as u32)
             .stack_mode(StackMode::ABOVE);
 
         conn.configure_window(self.win, aux)?.check()?;
+        conn.map_window(self.win)?.check()?;
 
         debug!("{}: tray={}", function_name!(), self);
 
         Ok(())
     }
 
+    /// Send XEmbed message to the underlying win
+    ///
+    /// # Arguments
+    ///
+    /// * `subtle` - Global state object
+    /// * `opcode` - XEmbed message
+    /// * `detail` - Detail of the message
+    /// * `data1` - First message specific value
+    /// * `data2` - Second message specific value
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn send_xembed(&self, subtle: &Subtle, opcode: XEmbed, detail: u32,
+                              data1: u32, data2: u32) -> Result<()>
+    {
+        let atoms = subtle.atoms.get().unwrap();
+
+        ewmh::send_message(subtle, self.win, atoms._XEMBED,
+                           &[CURRENT_TIME, opcode as u32, detail, data1, data2])?;
+
+        debug!("{}: tray={}, opcode={:?}", function_name!(), self, opcode);
+
+        Ok(())
+    }
+
     /// Set XEmbed state for the underlying win
     ///
     /// # Arguments
@@ -303,14 +365,17 @@ impl Tray {
         let mut opcode: XEmbed = XEmbed::WindowActivate;
 
         let xembed_info = conn.get_property(false, self.win, atoms._XEMBED_INFO,
-            atoms._XEMBED_INFO, 0, 2)?.reply()?.value;
+            atoms._XEMBED_INFO, 0, 2)?.reply()?
+            .value32().map(|values| values.collect::<Vec<u32>>()).unwrap_or_default();
+
+        if is_xembed_mapped(&xembed_info) {
+            self.flags.remove(TrayFlags::HIDDEN);
 
-        if let Some(_xembed_flags) = xembed_info.first() {
             conn.map_window(self.win)?.check()?;
 
             self.set_wm_state(subtle, WMState::Normal)?;
         } else {
-            self.flags.insert(TrayFlags::UNMAP);
+            self.flags.insert(TrayFlags::UNMAP | TrayFlags::HIDDEN);
 
             opcode = XEmbed::WindowDeactivate;
 
@@ -319,8 +384,7 @@ impl Tray {
             self.set_wm_state(subtle, WMState::Withdrawn)?;
         }
 
-        ewmh::send_message(subtle, self.win, atoms._XEMBED, &[CURRENT_TIME,
-            opcode as u32, 0, 0, 0])?;
+        self.send_xembed(subtle, opcode, 0, 0, 0)?;
 
         debug!("{}: tray={}", function_name!(), self);
 
@@ -404,6 +468,186 @@ impl PartialEq for Tray {
     }
 }
 
+/// Calculate tray width from size hints
+///
+/// # Arguments
+///
+/// * `size_hints` - Fetched size hints if any
+/// * `panel_height` - Height of the panel
+///
+/// # Returns
+///
+/// The width of the tray or `0` without any usable hint
+pub(crate) fn calc_width(size_hints: Option<&WmSizeHints>, panel_height: u16) -> u16 {
+    let mut width = 0;
+
+    if let Some(size_hints) = size_hints {
+        // Program min size - limit min size to screen size if larger
+        if let Some((min_width, _)) = size_hints.min_size {
+            width = max!(min_width as u16, panel_height * 2);
+        }
+
+        // Base sizes
+        if let Some((base_width, _)) = size_hints.base_size {
+            width = max!(base_width as u16, panel_height * 2);
+        }
+
+        // User/program size
+        if let Some((hint_spec, x, _y)) = size_hints.size {
+            match hint_spec {
+                WmSizeHintsSpecification::UserSpecified | WmSizeHintsSpecification::ProgramSpecified => {
+                    width = max!(x as u16, panel_height * 2);
+                }
+            }
+        }
+    }
+
+    width
+}
+
+/// Calculate size of square tray icons
+///
+/// # Arguments
+///
+/// * `panel_height` - Height of the panel
+/// * `spacing_height` - Vertical spacing of the tray style
+///
+/// # Returns
+///
+/// The width and height of an icon, at least `1`
+pub(crate) fn calc_icon_size(panel_height: u16, spacing_height: i16) -> u16 {
+    max!(1, panel_height as i32 - spacing_height as i32) as u16
+}
+
+/// Check whether the XEmbed info asks for the icon to be mapped
+///
+/// # Arguments
+///
+/// * `xembed_info` - Values of the _XEMBED_INFO property (version and flags)
+///
+/// # Returns
+///
+/// Either [`true`] if the mapped bit is set or otherwise [`false`]
+pub(crate) fn is_xembed_mapped(xembed_info: &[u32]) -> bool {
+    xembed_info.get(1).is_some_and(|flags| 0 != flags & XEMBED_MAPPED)
+}
+
+/// Check whether a crossing event enters or leaves the panel itself
+///
+/// # Arguments
+///
+/// * `detail` - Detail of the crossing event
+///
+/// # Returns
+///
+/// Either [`true`] unless the pointer just moved between panel and tray win or otherwise [`false`]
+pub(crate) fn is_panel_crossing(detail: NotifyDetail) -> bool {
+    NotifyDetail::INFERIOR != detail
+}
+
+/// Map cycle direction to the focus detail for the icon receiving the focus
+///
+/// # Arguments
+///
+/// * `order` - Direction of the focus cycle
+///
+/// # Returns
+///
+/// The [`XEmbedFocus`] to send along with the focus in message
+pub(crate) fn calc_focus_detail(order: CycleOrder) -> XEmbedFocus {
+    match order {
+        CycleOrder::Next => XEmbedFocus::First,
+        CycleOrder::Prev => XEmbedFocus::Last,
+    }
+}
+
+/// Tell all icons whether the tray is active
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `is_active` - Whether the panel of the tray has the pointer
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn activate(subtle: &Subtle, is_active: bool) -> Result<()> {
+    let opcode = if is_active { XEmbed::WindowActivate } else { XEmbed::WindowDeactivate };
+
+    for tray in subtle.trays.borrow().iter().filter(|tray| !tray.flags.intersects(TrayFlags::DEAD)) {
+        tray.send_xembed(subtle, opcode, 0, 0, 0)?;
+    }
+
+    debug!("{}: is_active={}", function_name!(), is_active);
+
+    Ok(())
+}
+
+/// Give the input focus to the icon below the pointer
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn request_focus(subtle: &Subtle) -> Result<()> {
+    let conn = subtle.conn.get().unwrap();
+
+    // All icons share the tray win as embedder, so the pointer tells which one asked
+    let child = conn.query_pointer(subtle.tray_win)?.reply()?.child;
+
+    if let Some(tray) = subtle.find_tray(child) {
+        effect::apply(subtle, Effect::Focus(tray.win))?;
+
+        tray.send_xembed(subtle, XEmbed::FocusIn, XEmbedFocus::Current as u32, 0, 0)?;
+    }
+
+    debug!("{}: child={}", function_name!(), child);
+
+    Ok(())
+}
+
+/// Pass the input focus from the focused icon to the next or previous one
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `order` - Direction of the focus cycle
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn cycle_focus(subtle: &Subtle, order: CycleOrder) -> Result<()> {
+    let conn = subtle.conn.get().unwrap();
+
+    let focus_win = conn.get_input_focus()?.reply()?.focus;
+
+    let trays = subtle.trays.borrow();
+    let icons = trays.iter()
+        .filter(|tray| !tray.flags.intersects(TrayFlags::DEAD | TrayFlags::HIDDEN))
+        .collect::<Vec<&Tray>>();
+
+    let current_idx = icons.iter().position(|tray| tray.win == focus_win);
+
+    if let Some(next_idx) = grab::calc_cycle_idx(icons.len(), current_idx, 1, order)
+        && current_idx != Some(next_idx)
+    {
+        if let Some(current_idx) = current_idx {
+            icons[current_idx].send_xembed(subtle, XEmbed::FocusOut, 0, 0, 0)?;
+        }
+
+        effect::apply(subtle, Effect::Focus(icons[next_idx].win))?;
+
+        icons[next_idx].send_xembed(subtle, XEmbed::FocusIn, calc_focus_detail(order) as u32, 0, 0)?;
+    }
+
+    debug!("{}: focus_win={}, order={:?}", function_name!(), focus_win, order);
+
+    Ok(())
+}
+
 /// Publish and export all relevant atoms to allow IPC
 ///
 /// # Arguments
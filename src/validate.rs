@@ -0,0 +1,170 @@
+//!
+//! @package subtle-rs
+//!
+//! @file Validation functions
+//! @copyright (c) 2025-present Christoph Kappel <christoph@unexist.dev>
+//! @version $Id$
+//!
+//! This program can be distributed under the terms of the GNU GPLv3.
+//! See the file LICENSE for details.
+//!
+//! Raw numbers from properties or client messages are never trusted: Every ingestion
+//! point converts them with one of the `Valid*` wrappers before they are used as index
+//! or tags.
+//!
+
+use anyhow::{anyhow, Result};
+use crate::tag::MAX_TAGS;
+use crate::tagging::Tagging;
+
+/// Gravity index checked against the gravity list
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct ValidGravityIdx(usize);
+
+/// Screen index checked against the screen list
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct ValidScreenIdx(usize);
+
+/// View index checked against the view list
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct ValidViewIdx(usize);
+
+/// Tag index checked against the tag list
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct ValidTagIdx(usize);
+
+/// Tags checked against the tag list
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct ValidTagging(Tagging);
+
+/// Check raw index against the length of a list
+///
+/// # Arguments
+///
+/// * `kind` - Kind of the index for the error
+/// * `raw` - Raw value
+/// * `len` - Length of the list
+///
+/// # Returns
+///
+/// A [`Result`] with either the index on success or otherwise [`anyhow::Error`]
+fn check_idx(kind: &str, raw: u32, len: usize) -> Result<usize> {
+    let idx = raw as usize;
+
+    if idx < len {
+        Ok(idx)
+    } else {
+        Err(anyhow!("Invalid {} index `{}`, expected less than `{}`", kind, raw, len))
+    }
+}
+
+impl ValidGravityIdx {
+    /// Create a new instance
+    ///
+    /// # Arguments
+    ///
+    /// * `raw` - Raw value
+    /// * `ngravities` - Number of gravities
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`ValidGravityIdx`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn new(raw: u32, ngravities: usize) -> Result<Self> {
+        check_idx("gravity", raw, ngravities).map(Self)
+    }
+
+    /// Get checked index
+    pub(crate) fn get(self) -> usize {
+        self.0
+    }
+}
+
+impl ValidScreenIdx {
+    /// Create a new instance
+    ///
+    /// # Arguments
+    ///
+    /// * `raw` - Raw value
+    /// * `nscreens` - Number of screens
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`ValidScreenIdx`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn new(raw: u32, nscreens: usize) -> Result<Self> {
+        check_idx("screen", raw, nscreens).map(Self)
+    }
+
+    /// Get checked index
+    pub(crate) fn get(self) -> usize {
+        self.0
+    }
+}
+
+impl ValidViewIdx {
+    /// Create a new instance
+    ///
+    /// # Arguments
+    ///
+    /// * `raw` - Raw value
+    /// * `nviews` - Number of views
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`ValidViewIdx`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn new(raw: u32, nviews: usize) -> Result<Self> {
+        check_idx("view", raw, nviews).map(Self)
+    }
+
+    /// Get checked index
+    pub(crate) fn get(self) -> usize {
+        self.0
+    }
+}
+
+impl ValidTagIdx {
+    /// Create a new instance
+    ///
+    /// # Arguments
+    ///
+    /// * `raw` - Raw value
+    /// * `ntags` - Number of tags
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`ValidTagIdx`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn new(raw: u32, ntags: usize) -> Result<Self> {
+        check_idx("tag", raw, ntags.min(MAX_TAGS)).map(Self)
+    }
+
+    /// Get checked index
+    pub(crate) fn get(self) -> usize {
+        self.0
+    }
+}
+
+impl ValidTagging {
+    /// Create a new instance
+    ///
+    /// # Arguments
+    ///
+    /// * `raw` - Raw tag bits
+    /// * `ntags` - Number of tags
+    ///
+    /// # Returns
+    ///
+    /// A [`Result`] with either [`ValidTagging`] on success or otherwise [`anyhow::Error`]
+    pub(crate) fn new(raw: u32, ntags: usize) -> Result<Self> {
+        let tag_mask = if MAX_TAGS <= ntags { u32::MAX } else { (1u32 << ntags) - 1 };
+
+        if 0 == raw & !tag_mask {
+            Ok(Self(Tagging::from_bits_retain(raw)))
+        } else {
+            Err(anyhow!("Invalid tags `{:#x}`, expected just the `{}` defined tags", raw, ntags))
+        }
+    }
+
+    /// Get checked tags
+    pub(crate) fn get(self) -> Tagging {
+        self.0
+    }
+}
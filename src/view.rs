@@ -13,18 +13,25 @@ use std::fmt;
 use std::cell::Cell;
 use bitflags::bitflags;
 use regex::{Regex, RegexBuilder};
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use derive_builder::Builder;
-use log::debug;
+use log::{debug, info};
 use stdext::function_name;
 use x11rb::connection::Connection;
 use x11rb::NONE;
 use x11rb::protocol::xproto::{AtomEnum, PropMode, Window};
 use x11rb::wrapper::ConnectionExt as ConnectionExtWrapper;
 use crate::config::{Config, MixedConfigVal};
-use crate::subtle::Subtle;
-use crate::tagging::Tagging;
+use crate::subtle::{Subtle, SubtleFlags};
+use crate::tagging;
+use crate::tagging::{Tagging, DEFAULT_TAG_NAME};
 use crate::icon::Icon;
+use crate::{config, effect, error, screen};
+use crate::error::Classify;
+use crate::effect::{Effect, Prop};
+
+/// Max number of views, visible views are stored as bits shifted by one
+pub(crate) const MAX_VIEWS: usize = 31;
 
 bitflags! {
     /// Config and state-flags for [`View`]
@@ -48,7 +55,8 @@ pub(crate) struct View {
     /// Config and state-flags
     pub(crate) flags: ViewFlags,
     /// Current tagging
-    pub(crate) tags: Tagging,
+    #[builder(setter(into))]
+    pub(crate) tags: Cell<Tagging>,
     /// Name of this view
     pub(crate) name: String,
     /// Regex to match tags
@@ -66,18 +74,18 @@ impl View {
     ///
     /// * `subtle` - Global state object
     fn retag(&mut self, subtle: &Subtle) {
-        for (tag_idx, tag) in subtle.tags.iter().enumerate() {
+        for (tag_idx, tag) in subtle.tags.borrow().iter().enumerate() {
             if let Some(regex) = self.regex.as_ref()
                 && regex.is_match(&tag.name)
             {
-                self.tags = Tagging::from_bits_retain(1 << tag_idx);
+                self.tags.set(self.tags.get() | tagging::tag_bit(tag_idx));
             }
         }
 
         debug!("{}: {}", function_name!(), self);
     }
 
-    /// Focus view and swap views if more than one screen is active
+    /// Focus view and either swap views or jump to the screen already showing it
     ///
     /// # Arguments
     ///
@@ -90,47 +98,47 @@ impl View {
     ///
     /// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
     pub(crate) fn focus(&self, subtle: &Subtle, screen_idx: usize, swap_views: bool, focus_next: bool) -> Result<()> {
-        if let Some(screen) = subtle.screens.get(screen_idx) {
-            if let Some(view_idx) = subtle.views.iter().position(|v| v == self) {
-
-                // Check if view is visible on any screen
-                if subtle.visible_views.get().intersects(Tagging::from_bits_retain(1 << (view_idx + 1))) {
-
-                    // This only makes sense with more than one screen - ignore otherwise
-                    if 1 < subtle.screens.len() {
-
-                        // Find screen with view and swap
-                        for other_screen in subtle.screens.iter() {
-                            if other_screen.view_idx.get() == view_idx as isize {
-                                if swap_views {
-                                    other_screen.view_idx.set(screen.view_idx.get());
-                                    screen.view_idx.set(view_idx as isize);
-                                } else {
-                                    //screen.warp();
-                                }
-
-                                break;
-                            }
-                        }
-                    }
-                } else {
-                    screen.view_idx.set(view_idx as isize);
-                }
+        let mut focus_screen_idx = screen_idx;
+
+        if screen_idx < subtle.screens.len()
+            && let Some(view_idx) = subtle.views.borrow().iter().position(|v| std::ptr::eq(v, self))
+        {
+            let mut screen_views: Vec<isize> = subtle.screens.iter()
+                .map(|screen| screen.view_idx.get())
+                .collect();
+
+            focus_screen_idx = calc_screen_views(&mut screen_views, screen_idx, view_idx, swap_views);
+
+            for (screen, new_view_idx) in subtle.screens.iter().zip(screen_views) {
+                screen.view_idx.set(new_view_idx);
+            }
+
+            // Jump to screen with view
+            if focus_screen_idx != screen_idx && !subtle.flags.intersects(SubtleFlags::SKIP_POINTER_WARP)
+                && let Some(focus_screen) = subtle.screens.get(focus_screen_idx)
+            {
+                focus_screen.warp(subtle)?;
             }
+
         }
 
+        publish_current(subtle, focus_screen_idx)?;
+
         if focus_next {
-            // Restore focus on view
-            if let Some(focus_client) = subtle.find_client(self.focus_win.get()) {
-                if focus_client.is_visible(subtle) {
-                    focus_client.focus(subtle, true)?;
-                } else {
-                    self.focus_win.set(NONE);
-                }
-            } else if let Some(focus_client) = subtle.find_next_client(
-                screen_idx as isize, false)
+            // Update visibility first, focus must not depend on a later enter event
+            screen::configure(subtle)?;
+
+            // Restore focus on view, the client might have been closed or moved meanwhile
+            if let Some(focus_client) = subtle.find_client(self.focus_win.get())
+                .filter(|focus_client| focus_client.is_focusable(subtle))
             {
                 focus_client.focus(subtle, true)?;
+            } else {
+                self.focus_win.set(NONE);
+
+                if let Some(focus_client) = subtle.find_next_client(focus_screen_idx as isize, false) {
+                    focus_client.focus(subtle, true)?;
+                }
             }
         }
 
@@ -142,16 +150,272 @@ impl View {
 
 impl fmt::Display for View {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "(name={}, regex={:?}, tags={:?})", self.name, self.regex, self.tags)
+        write!(f, "(name={}, regex={:?}, tags={:?})", self.name, self.regex, self.tags.get())
+    }
+}
+
+/// Find the current screen, either the one of the focus client or the one with the pointer
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+///
+/// # Returns
+///
+/// Index of the current screen
+pub(crate) fn find_current_screen_idx(subtle: &Subtle) -> usize {
+    subtle.find_focus_client()
+        .filter(|focus_client| focus_client.is_visible(subtle) && 0 <= focus_client.screen_idx)
+        .map(|focus_client| focus_client.screen_idx as usize)
+        .or_else(|| subtle.find_screen_by_pointer().map(|(screen_idx, _)| screen_idx))
+        .unwrap_or(0)
+}
+
+/// Select desktop of the current screen
+///
+/// # Arguments
+///
+/// * `screen_views` - View index of every screen
+/// * `screen_idx` - Index of the current screen
+///
+/// # Returns
+///
+/// The EWMH desktop index
+pub(crate) fn calc_current_desktop(screen_views: &[isize], screen_idx: usize) -> u32 {
+    screen_views.get(screen_idx)
+        .or(screen_views.first())
+        .map_or(0, |view_idx| (*view_idx).max(0) as u32)
+}
+
+/// Publish current desktop and views of all screens after views or screens changed
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `screen_idx` - Index of the current screen
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn publish_current(subtle: &Subtle, screen_idx: usize) -> Result<()> {
+    let screen_views: Vec<isize> = subtle.screens.iter()
+        .map(|screen| screen.view_idx.get())
+        .collect();
+
+    // EWMH: Current desktop
+    effect::apply(subtle, Effect::SetRootProperty { prop: Prop::CurrentDesktop,
+        data: vec![calc_current_desktop(&screen_views, screen_idx)] })?;
+
+    // subtle: Views per screen
+    effect::apply(subtle, Effect::SetRootProperty { prop: Prop::ScreenViews,
+        data: screen_views.iter().map(|view_idx| *view_idx as u32).collect() })?;
+
+    debug!("{}: screen_idx={}, screen_views={:?}", function_name!(), screen_idx, screen_views);
+
+    Ok(())
+}
+
+/// Assign view to screen and either swap or jump when the view is visible elsewhere
+///
+/// # Arguments
+///
+/// * `screen_views` - View index of every screen
+/// * `screen_idx` - Index of the screen to show the view
+/// * `view_idx` - Index of the view
+/// * `swap_views` - Whether views shall be swapped
+///
+/// # Returns
+///
+/// Index of the screen that shows the view afterward
+pub(crate) fn calc_screen_views(screen_views: &mut [isize], screen_idx: usize, view_idx: usize, swap_views: bool) -> usize {
+    match screen_views.iter().position(|idx| *idx == view_idx as isize) {
+        Some(other_screen_idx) if other_screen_idx != screen_idx => {
+            if swap_views {
+                screen_views[other_screen_idx] = screen_views[screen_idx];
+                screen_views[screen_idx] = view_idx as isize;
+
+                screen_idx
+            } else {
+                other_screen_idx
+            }
+        },
+        Some(_) => screen_idx,
+        None => {
+            screen_views[screen_idx] = view_idx as isize;
+
+            screen_idx
+        }
+    }
+}
+
+/// Find view to fall back to when the last client of a dynamic view went away
+///
+/// # Arguments
+///
+/// * `view_idx` - Index of the dynamic view
+/// * `view_tags` - Tags per view
+/// * `old_client_tags` - Tags of all clients before the change
+/// * `client_tags` - Tags of all clients after the change
+/// * `screen_views` - View index of every screen
+///
+/// # Returns
+///
+/// A [`Option`] with either [`Some`] next occupied or first view not shown elsewhere or otherwise [`None`]
+pub(crate) fn calc_fallback_view(view_idx: usize, view_tags: &[Tagging], old_client_tags: Tagging,
+                                 client_tags: Tagging, screen_views: &[isize]) -> Option<usize>
+{
+    let tags = view_tags.get(view_idx)?;
+
+    // Only leave views that just lost their last client
+    if !tags.intersects(old_client_tags) || tags.intersects(client_tags) {
+        return None;
     }
+
+    let nviews = view_tags.len();
+    let is_free = |idx: &usize| *idx != view_idx && !screen_views.contains(&(*idx as isize));
+
+    (1..nviews).map(|offset| (view_idx + offset) % nviews)
+        .find(|idx| is_free(idx) && view_tags[*idx].intersects(client_tags))
+        .or_else(|| (0..nviews).find(is_free))
 }
 
-impl PartialEq for View {
-    fn eq(&self, other: &Self) -> bool {
-        self.name == other.name
+/// Update view index of screens after a view has been removed
+///
+/// # Arguments
+///
+/// * `screen_views` - View index of every screen
+/// * `view_idx` - Index of the removed view
+pub(crate) fn calc_removed_screen_views(screen_views: &mut [isize], view_idx: usize) {
+    for screen_view_idx in screen_views.iter_mut() {
+        if *screen_view_idx == view_idx as isize {
+            *screen_view_idx = 0;
+        } else if *screen_view_idx > view_idx as isize {
+            *screen_view_idx -= 1;
+        }
     }
 }
 
+/// Keep per-view gravities of a client in sync with the views
+///
+/// # Arguments
+///
+/// * `gravities` - Gravities of the client per view
+/// * `nviews` - Number of views
+/// * `removed_view_idx` - Index of a removed view if any
+/// * `default_gravity` - Gravity for new views
+pub(crate) fn sync_gravities(gravities: &mut Vec<usize>, nviews: usize,
+                             removed_view_idx: Option<usize>, default_gravity: usize)
+{
+    if let Some(view_idx) = removed_view_idx && view_idx < gravities.len() {
+        gravities.remove(view_idx);
+    }
+
+    gravities.resize(nviews, default_gravity);
+}
+
+/// Add view at runtime
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `name` - Name of the view
+/// * `pattern` - Regex to match tags if any
+///
+/// # Returns
+///
+/// A [`Result`] with either index of the view on success or otherwise [`anyhow::Error`]
+pub(crate) fn add(subtle: &Subtle, name: &str, pattern: Option<&str>) -> Result<usize> {
+    if name.is_empty() {
+        return Err(anyhow!("Empty view name"));
+    }
+
+    if subtle.views.borrow().iter().any(|view| view.name == name) {
+        return Err(anyhow!("View `{}` already exists", name));
+    }
+
+    if MAX_VIEWS <= subtle.views.borrow().len() {
+        return Err(anyhow!("Too many views"));
+    }
+
+    let mut builder = ViewBuilder::default();
+
+    builder.name(name.into());
+    builder.regex(pattern.map(|pattern| RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build())
+        .transpose()?);
+
+    let mut view = builder.build()?;
+
+    view.retag(subtle);
+
+    subtle.views.borrow_mut().push(view);
+
+    let nviews = subtle.views.borrow().len();
+    let default_gravity = subtle.get_default_gravity().max(0) as usize;
+
+    for client in subtle.clients.borrow_mut().iter_mut() {
+        sync_gravities(&mut client.gravities, nviews, None, default_gravity);
+    }
+
+    publish(subtle)?;
+
+    debug!("{}: name={}, pattern={:?}, nviews={}", function_name!(), name, pattern, nviews);
+
+    Ok(nviews - 1)
+}
+
+/// Remove view at runtime and move screens showing it to the first view
+///
+/// # Arguments
+///
+/// * `subtle` - Global state object
+/// * `view_idx` - Index of the view
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn remove(subtle: &Subtle, view_idx: usize) -> Result<()> {
+    let nviews = subtle.views.borrow().len();
+
+    if view_idx >= nviews {
+        return Err(anyhow!("View {} not found", view_idx));
+    }
+
+    if 1 == nviews {
+        return Err(anyhow!("Cannot remove last view"));
+    }
+
+    let view = subtle.views.borrow_mut().remove(view_idx);
+
+    // Free icon of the removed view
+    if let (Some(icon), Some(conn)) = (view.icon.as_ref(), subtle.conn.get()) {
+        icon.kill(conn)?;
+    }
+
+    let mut screen_views: Vec<isize> = subtle.screens.iter()
+        .map(|screen| screen.view_idx.get())
+        .collect();
+
+    calc_removed_screen_views(&mut screen_views, view_idx);
+
+    for (screen, new_view_idx) in subtle.screens.iter().zip(screen_views) {
+        screen.view_idx.set(new_view_idx);
+    }
+
+    let default_gravity = subtle.get_default_gravity().max(0) as usize;
+
+    for client in subtle.clients.borrow_mut().iter_mut() {
+        sync_gravities(&mut client.gravities, nviews - 1, Some(view_idx), default_gravity);
+    }
+
+    publish(subtle)?;
+
+    debug!("{}: view_idx={}, nviews={}", function_name!(), view_idx, nviews - 1);
+
+    Ok(())
+}
+
 /// Check config and init all view related options
 ///
 /// # Arguments
@@ -171,21 +435,28 @@ pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
             builder.name(name.into());
         }
 
+        // Keep view without match on invalid patterns
         if let Some(MixedConfigVal::S(value)) = values.get("match") {
-            builder.regex(Some(RegexBuilder::new(value)
+            builder.regex(error::collect(subtle, RegexBuilder::new(value)
                 .case_insensitive(true)
-                .build()?));
+                .build()
+                .with_context(|| format!("Invalid view match `{}`", value))
+                .recoverable())?);
         }
 
         if let Some(MixedConfigVal::B(icon_only)) = values.get("icon_only") && *icon_only {
             flags.insert(ViewFlags::MODE_ICON_ONLY);
         }
 
-        if let Some(MixedConfigVal::S(icon_file)) = values.get("icon") {
-            if let Ok(icon) = Icon::new(subtle, icon_file) {
-                flags.insert(ViewFlags::MODE_ICON);
-                builder.icon(Some(icon));
-            }
+        if let Some(MixedConfigVal::B(dynamic)) = values.get("dynamic") && *dynamic {
+            flags.insert(ViewFlags::MODE_DYNAMIC);
+        }
+
+        if let Some(MixedConfigVal::S(icon_file)) = values.get("icon")
+            && let Some(icon) = error::collect(subtle, Icon::load(subtle, icon_file, &subtle.views_style))?
+        {
+            flags.insert(ViewFlags::MODE_ICON);
+            builder.icon(Some(icon));
         }
 
         // Finally create view and apply tagging
@@ -195,18 +466,25 @@ pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
 
         view.retag(subtle);
 
-        subtle.views.push(view)
+        subtle.views.borrow_mut().push(view)
     }
 
-    // Sanity check
-    if subtle.views.is_empty() {
-        let mut builder = ViewBuilder::default();
+    // Check names
+    {
+        let mut views = subtle.views.borrow_mut();
+        let mut names: Vec<String> = views.iter().map(|view| view.name.clone()).collect();
 
-        builder.name("default".into());
+        config::check_empty_names("view", &names)?;
+        config::check_duplicate_names("view", &mut names,
+            subtle.flags.contains(SubtleFlags::LENIENT_CONFIG))?;
 
-        subtle.views.push(builder.build()?);
+        for (view, name) in views.iter_mut().zip(names) {
+            view.name = name;
+        }
     }
 
+    ensure_default_view(&mut subtle.views.borrow_mut())?;
+
     publish(subtle)?;
 
     debug!("{}", function_name!());
@@ -214,6 +492,74 @@ pub(crate) fn init(config: &Config, subtle: &mut Subtle) -> Result<()> {
     Ok(())
 }
 
+/// Make sure a view shows clients with the default tag
+///
+/// Without views a default view is created, otherwise the first view catches the default tag
+/// unless any view already matches it.
+///
+/// # Arguments
+///
+/// * `views` - Configured views
+///
+/// # Returns
+///
+/// A [`Result`] with either [`unit`] on success or otherwise [`anyhow::Error`]
+pub(crate) fn ensure_default_view(views: &mut Vec<View>) -> Result<()> {
+    if views.is_empty() {
+        let mut builder = ViewBuilder::default();
+
+        builder.name(DEFAULT_TAG_NAME.into());
+        builder.tags(tagging::default_tag());
+
+        views.push(builder.build()?);
+    } else if !views.iter().any(|view| view.tags.get().intersects(tagging::default_tag())) {
+        let view = &views[0];
+
+        view.tags.set(view.tags.get() | tagging::default_tag());
+
+        info!("Showing clients without tags on view `{}`", view.name);
+    }
+
+    debug!("{}: nviews={}", function_name!(), views.len());
+
+    Ok(())
+}
+
+/// Calculate viewport of every view for publishing
+///
+/// Views never span a large desktop, so every viewport is just the origin.
+///
+/// # Arguments
+///
+/// * `nviews` - Number of views
+///
+/// # Returns
+///
+/// A [`Vec`] with one x and y pair per view
+pub(crate) fn calc_desktop_viewports(nviews: usize) -> Vec<u32> {
+    vec![0; 2 * nviews]
+}
+
+/// Collect pixmaps and geometries of view icons for publishing
+///
+/// # Arguments
+///
+/// * `icons` - Icon per view if any
+///
+/// # Returns
+///
+/// A tuple with the pixmap per view and width and height pairs per view, zero for views without icon
+pub(crate) fn calc_icon_data(icons: &[Option<&Icon>]) -> (Vec<u32>, Vec<u32>) {
+    let pixmaps = icons.iter()
+        .map(|icon| icon.map_or(0, |icon| icon.pixmap))
+        .collect();
+    let geoms = icons.iter()
+        .flat_map(|icon| icon.map_or([0, 0], |icon| icon.get_geometry()))
+        .collect();
+
+    (pixmaps, geoms)
+}
+
 /// Publish and export all relevant atoms to allow IPC
 ///
 /// # Arguments
@@ -229,26 +575,32 @@ pub(crate) fn publish(subtle: &Subtle) -> Result<()> {
 
     let default_screen = &conn.setup().roots[subtle.screen_num];
 
-    let mut names: Vec<&str> = Vec::with_capacity(subtle.views.len());
-    let mut tags: Vec<u32> = Vec::with_capacity(subtle.views.len());
-    let mut icons: Vec<u32> = Vec::with_capacity(subtle.views.len());
+    let views = subtle.views.borrow();
+
+    let mut names: Vec<&str> = Vec::with_capacity(views.len());
+    let mut tags: Vec<u32> = Vec::with_capacity(views.len());
 
-    for view in subtle.views.iter() {
+    for view in views.iter() {
         names.push(&*view.name);
-        tags.push(view.tags.bits());
-        icons.push(0);
+        tags.push(view.tags.get().bits());
     }
 
+    let (icons, icon_geoms) = calc_icon_data(&views.iter()
+        .map(|view| view.icon.as_ref())
+        .collect::<Vec<Option<&Icon>>>());
+
     // EWMH: Tags
     conn.change_property32(PropMode::REPLACE, default_screen.root, atoms.SUBTLE_VIEW_TAGS,
                            AtomEnum::CARDINAL, &tags)?.check()?;
 
-    // EWMH: Icons
+    // EWMH: Icons and their width and height
     conn.change_property32(PropMode::REPLACE, default_screen.root, atoms.SUBTLE_VIEW_ICONS,
                            AtomEnum::CARDINAL, &icons)?.check()?;
+    conn.change_property32(PropMode::REPLACE, default_screen.root, atoms.SUBTLE_VIEW_ICON_GEOMETRY,
+                           AtomEnum::CARDINAL, &icon_geoms)?.check()?;
 
     // EWMH: Desktops
-    let data: [u32; 1] = [subtle.views.len() as u32];
+    let data: [u32; 1] = [views.len() as u32];
 
     conn.change_property32(PropMode::REPLACE, default_screen.root, atoms._NET_NUMBER_OF_DESKTOPS,
                            AtomEnum::CARDINAL, &data)?.check()?;
@@ -256,15 +608,22 @@ pub(crate) fn publish(subtle: &Subtle) -> Result<()> {
     conn.change_property8(PropMode::REPLACE, default_screen.root, atoms._NET_DESKTOP_NAMES,
                           AtomEnum::STRING, names.join("\0").as_bytes())?.check()?;
 
+    // EWMH: Desktop viewport
+    conn.change_property32(PropMode::REPLACE, default_screen.root, atoms._NET_DESKTOP_VIEWPORT,
+                           AtomEnum::CARDINAL, &calc_desktop_viewports(views.len()))?.check()?;
+
     // EWMH: Current desktop
-    let data: [u32; 1] = [0];
+    let screen_views: Vec<isize> = subtle.screens.iter()
+        .map(|screen| screen.view_idx.get())
+        .collect();
+    let data: [u32; 1] = [calc_current_desktop(&screen_views, find_current_screen_idx(subtle))];
 
     conn.change_property32(PropMode::REPLACE, default_screen.root, atoms._NET_CURRENT_DESKTOP,
                            AtomEnum::CARDINAL, &data)?.check()?;
 
     conn.flush()?;
 
-    debug!("{}: nviews={}", function_name!(), subtle.views.len());
+    debug!("{}: nviews={}", function_name!(), views.len());
 
     Ok(())
 }